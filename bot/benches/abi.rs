@@ -0,0 +1,47 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ethers::{abi::AbiEncode, prelude::*};
+use std::time::Duration;
+use subway_rs::abi::*;
+
+mod common;
+
+/// Encodes a realistic `swapExactETHForTokens` call
+fn swap_calldata() -> Bytes {
+    let call = SwapExactETHForTokensCall {
+        amount_out_min: U256::from(1_000_000u64),
+        path: vec![Address::random(), Address::random()],
+        to: Address::random(),
+        deadline: U256::from(u64::MAX),
+    };
+    Bytes::from(call.encode())
+}
+
+fn bench_decode_uniswap_router_calldata(c: &mut Criterion) {
+    let calldata = swap_calldata();
+    c.bench_function("decode uniswap router calldata", |b| {
+        b.iter(|| decode_uniswap_router_calldata(&calldata))
+    });
+}
+
+fn bench_parse_swap_view(c: &mut Criterion) {
+    let calldata = swap_calldata();
+    c.bench_function("parse swap calldata view", |b| {
+        b.iter(|| SwapExactEthForTokensView::parse(calldata.as_ref()).map(|v| v.path_at(1)))
+    });
+}
+
+fn bench_decode_invalid_calldata(c: &mut Criterion) {
+    // Most mempool transactions aren't router swaps, so rejection must be cheap too
+    let calldata = Bytes::from(vec![0u8; 4 + 32 * 4]);
+    c.bench_function("decode invalid calldata", |b| {
+        b.iter(|| decode_uniswap_router_calldata(&calldata))
+    });
+}
+
+criterion_group! {
+    name = abi_benches;
+    config = common::regression_config().measurement_time(Duration::from_secs(5));
+    targets = bench_decode_uniswap_router_calldata, bench_parse_swap_view, bench_decode_invalid_calldata
+}
+
+criterion_main!(abi_benches);
@@ -0,0 +1,13 @@
+//! Settings shared by the benches
+
+use criterion::Criterion;
+
+/// Changes over 2% are reported as regressions
+pub const NOISE_THRESHOLD: f64 = 0.02;
+
+/// The criterion config regression-tracked benches run with
+pub fn regression_config() -> Criterion {
+    Criterion::default()
+        .noise_threshold(NOISE_THRESHOLD)
+        .significance_level(0.01)
+}
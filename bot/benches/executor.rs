@@ -0,0 +1,38 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ethers::prelude::*;
+use std::time::Duration;
+use subway_rs::executor::*;
+
+mod common;
+
+fn bench_encode_frontrun(c: &mut Criterion) {
+    let mut group = c.benchmark_group("FrontrunPayloadEncoding");
+    let leg = SwapLeg {
+        token_in: Address::random(),
+        token_out: Address::random(),
+        pair: Address::random(),
+        amount_in: U256::from(10u64).pow(U256::from(18u64)),
+        amount_out: U256::from(10u64).pow(U256::from(20u64)),
+    };
+    for kind in [
+        ExecutorKind::Subway,
+        ExecutorKind::V3,
+        ExecutorKind::Multicall,
+    ] {
+        let executor = kind.build(Address::random());
+        group.bench_with_input(
+            BenchmarkId::new("Executor", executor.name()),
+            &leg,
+            |b, leg| b.iter(|| executor.encode_frontrun(leg, ExecutionMode::Inventory)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = executor_benches;
+    config = common::regression_config().measurement_time(Duration::from_secs(5));
+    targets = bench_encode_frontrun
+}
+
+criterion_main!(executor_benches);
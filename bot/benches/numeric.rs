@@ -0,0 +1,109 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ethers::{prelude::*, utils::parse_ether};
+use std::time::Duration;
+use subway_rs::{numeric::*, uniswap::get_univ2_data_given_in};
+
+mod common;
+
+fn bench_get_univ2_data_given_in(c: &mut Criterion) {
+    let amount_in = parse_ether("1").unwrap();
+    let weth_reserves = parse_ether("1000").unwrap();
+    let token_reserves = parse_ether("2000000").unwrap();
+    c.bench_function("univ2 data given in", |b| {
+        b.iter(|| get_univ2_data_given_in(&amount_in, &weth_reserves, &token_reserves))
+    });
+}
+
+fn bench_calculate_sandwich_optimal_in(c: &mut Criterion) {
+    let mut group = c.benchmark_group("SandwichOptimalIn");
+    let weth_reserves = parse_ether("1000").unwrap();
+    let token_reserves = parse_ether("2000000").unwrap();
+
+    // Larger victims with looser slippage take more search steps
+    for victim in ["1", "10", "50"] {
+        let user_amount_in = parse_ether(victim).unwrap();
+        let (expected_out, _, _) =
+            get_univ2_data_given_in(&user_amount_in, &weth_reserves, &token_reserves);
+        let user_min_recv = expected_out * 95 / 100;
+        group.bench_with_input(
+            BenchmarkId::new("Victim", victim),
+            &(user_amount_in, user_min_recv),
+            |b, (amount_in, min_recv)| {
+                b.iter(|| {
+                    calculate_sandwich_optimal_in(
+                        amount_in,
+                        min_recv,
+                        &weth_reserves,
+                        &token_reserves,
+                    )
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_optimal_in_backends(c: &mut Criterion) {
+    let mut group = c.benchmark_group("OptimalInBackend");
+    let weth_reserves = parse_ether("1000").unwrap();
+    let token_reserves = parse_ether("2000000").unwrap();
+    let user_amount_in = parse_ether("10").unwrap();
+    let (expected_out, _, _) =
+        get_univ2_data_given_in(&user_amount_in, &weth_reserves, &token_reserves);
+    let user_min_recv = expected_out * 95 / 100;
+
+    group.bench_function("ethers", |b| {
+        b.iter(|| {
+            reference_sandwich_optimal_in(
+                &user_amount_in,
+                &user_min_recv,
+                &weth_reserves,
+                &token_reserves,
+            )
+        })
+    });
+    // Run with `--features fast-math` to compare against the ruint search
+    #[cfg(feature = "fast-math")]
+    group.bench_function("ruint", |b| {
+        b.iter(|| {
+            subway_rs::fast_math::calculate_sandwich_optimal_in(
+                &user_amount_in,
+                &user_min_recv,
+                &weth_reserves,
+                &token_reserves,
+            )
+        })
+    });
+    group.finish();
+}
+
+fn bench_calculate_sandwich_context(c: &mut Criterion) {
+    let weth_reserves = parse_ether("1000").unwrap();
+    let token_reserves = parse_ether("2000000").unwrap();
+    let user_amount_in = parse_ether("10").unwrap();
+    let user_min_recv = U256::zero();
+    let optimal_weth_in = parse_ether("5").unwrap();
+    c.bench_function("sandwich context", |b| {
+        b.iter(|| {
+            calculate_sandwich_context(
+                &optimal_weth_in,
+                &user_amount_in,
+                &user_min_recv,
+                &weth_reserves,
+                &token_reserves,
+            )
+        })
+    });
+}
+
+criterion_group! {
+    name = numeric_benches;
+    config = common::regression_config().measurement_time(Duration::from_secs(5));
+    targets =
+        bench_get_univ2_data_given_in,
+        bench_calculate_sandwich_optimal_in,
+        bench_optimal_in_backends,
+        bench_calculate_sandwich_context
+}
+
+criterion_main!(numeric_benches);
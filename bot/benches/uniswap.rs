@@ -4,6 +4,8 @@ use subway_rs::uniswap::*;
 
 use tokio::runtime;
 
+mod common;
+
 fn bench_univ2_router_address(c: &mut Criterion) {
     c.bench_function("uniswap v2 router", |b| {
         b.iter(|| get_univ2_router_address())
@@ -63,7 +65,7 @@ fn bench_pair_addresses(c: &mut Criterion) {
 
 criterion_group! {
     name = uniswap_benches;
-    config = Criterion::default();
+    config = common::regression_config();
     targets =
         bench_univ2_router_address,
         bench_univ2_factory_address,
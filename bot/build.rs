@@ -0,0 +1,6 @@
+fn main() {
+    // The gRPC service is only generated with the `grpc` feature, which needs `protoc`
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/pipeline.proto").expect("Failed to compile pipeline.proto");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,146 @@
+//! Zero-allocation Router Calldata Decoding
+//!
+//! Every pending transaction passes through the decoder, so the arguments are read straight out of
+//! the calldata slice instead of building intermediate ethabi tokens.
+
+use ethers::prelude::*;
+use thiserror::Error;
+
+use super::SwapExactETHForTokensCall;
+
+/// The `swapExactETHForTokens(uint256,address[],address,uint256)` selector
+pub const SWAP_EXACT_ETH_FOR_TOKENS_SELECTOR: [u8; 4] = [0x7f, 0xf3, 0x6a, 0xb5];
+
+/// The size of an abi word
+const WORD: usize = 32;
+
+/// A calldata decoding error
+///
+/// Errors carry no heap data so rejecting the (many) non-matching transactions stays allocation free.
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The calldata is shorter than the selector
+    #[error("calldata is missing a selector")]
+    MissingSelector,
+    /// The selector doesn't match the expected call
+    #[error("unexpected selector {0:02x?}")]
+    InvalidSelector([u8; 4]),
+    /// The calldata ends before the expected arguments
+    #[error("calldata is too short")]
+    TooShort,
+    /// A dynamic offset or length doesn't fit in the calldata
+    #[error("invalid offset or length")]
+    InvalidOffset,
+}
+
+/// Reads the word at `offset`
+fn word(data: &[u8], offset: usize) -> Result<&[u8], DecodeError> {
+    let end = offset.checked_add(WORD).ok_or(DecodeError::InvalidOffset)?;
+    data.get(offset..end).ok_or(DecodeError::TooShort)
+}
+
+/// Reads the word at `offset` as a usize offset or length
+fn word_usize(data: &[u8], offset: usize) -> Result<usize, DecodeError> {
+    let word = word(data, offset)?;
+    if word[..WORD - 8].iter().any(|b| *b != 0) {
+        return Err(DecodeError::InvalidOffset);
+    }
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&word[WORD - 8..]);
+    usize::try_from(u64::from_be_bytes(bytes)).map_err(|_| DecodeError::InvalidOffset)
+}
+
+/// A borrowed view over `swapExactETHForTokens` calldata
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapExactEthForTokensView<'a> {
+    /// The abi-encoded arguments, after the selector
+    args: &'a [u8],
+    /// Where the path elements start in `args`
+    path_start: usize,
+    /// The number of path elements
+    path_len: usize,
+}
+
+impl<'a> SwapExactEthForTokensView<'a> {
+    /// Validates the selector and argument layout without copying anything
+    pub fn parse(data: &'a [u8]) -> Result<Self, DecodeError> {
+        let selector: [u8; 4] = data
+            .get(0..4)
+            .and_then(|s| s.try_into().ok())
+            .ok_or(DecodeError::MissingSelector)?;
+        if selector != SWAP_EXACT_ETH_FOR_TOKENS_SELECTOR {
+            return Err(DecodeError::InvalidSelector(selector));
+        }
+
+        // Static head: amountOutMin, path offset, to, deadline
+        let args = &data[4..];
+        if args.len() < 4 * WORD {
+            return Err(DecodeError::TooShort);
+        }
+
+        // Dynamic path: length followed by the elements
+        let path_offset = word_usize(args, WORD)?;
+        let path_len = word_usize(args, path_offset)?;
+        let path_start = path_offset + WORD;
+        let path_end = path_len
+            .checked_mul(WORD)
+            .and_then(|len| len.checked_add(path_start))
+            .ok_or(DecodeError::InvalidOffset)?;
+        if path_end > args.len() {
+            return Err(DecodeError::TooShort);
+        }
+
+        Ok(Self {
+            args,
+            path_start,
+            path_len,
+        })
+    }
+
+    /// The minimum amount of output tokens
+    pub fn amount_out_min(&self) -> U256 {
+        U256::from_big_endian(&self.args[0..WORD])
+    }
+
+    /// The recipient of the output tokens
+    pub fn to(&self) -> Address {
+        Address::from_slice(&self.args[2 * WORD + 12..3 * WORD])
+    }
+
+    /// The unix timestamp after which the swap reverts
+    pub fn deadline(&self) -> U256 {
+        U256::from_big_endian(&self.args[3 * WORD..4 * WORD])
+    }
+
+    /// The number of tokens in the swap path
+    pub fn path_len(&self) -> usize {
+        self.path_len
+    }
+
+    /// The token at `index` in the swap path
+    pub fn path_at(&self, index: usize) -> Option<Address> {
+        if index >= self.path_len {
+            return None;
+        }
+        let start = self.path_start + index * WORD + 12;
+        Some(Address::from_slice(&self.args[start..start + 20]))
+    }
+
+    /// Iterates over the swap path
+    pub fn path(&self) -> impl Iterator<Item = Address> + 'a {
+        let view = *self;
+        (0..self.path_len).filter_map(move |i| view.path_at(i))
+    }
+
+    /// Materializes the view into the abigen call type
+    ///
+    /// This is the only allocation, for the owned path.
+    pub fn to_call(&self) -> SwapExactETHForTokensCall {
+        SwapExactETHForTokensCall {
+            amount_out_min: self.amount_out_min(),
+            path: self.path().collect(),
+            to: self.to(),
+            deadline: self.deadline(),
+        }
+    }
+}
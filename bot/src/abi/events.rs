@@ -0,0 +1,283 @@
+//! Event Log Decoding
+//!
+//! Typed decoders for the logs the bot reads back: UniswapV2 `Swap`, `Sync` and `PairCreated`, and
+//! ERC-20 `Transfer`. Like the calldata decoder, fields are read straight out of the log instead of going
+//! through ethabi tokens.
+//!
+//! The bundled executor contracts don't emit events of their own. Their effects are recovered from
+//! the token `Transfer` logs they cause, see [token_flows].
+
+use ethers::prelude::*;
+use thiserror::Error;
+
+/// The `Transfer(address,address,uint256)` topic
+pub const TRANSFER_TOPIC: [u8; 32] = [
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+];
+
+/// The `Sync(uint112,uint112)` topic
+pub const SYNC_TOPIC: [u8; 32] = [
+    0x1c, 0x41, 0x1e, 0x9a, 0x96, 0xe0, 0x71, 0x24, 0x1c, 0x2f, 0x21, 0xf7, 0x72, 0x6b, 0x17, 0xae,
+    0x89, 0xe3, 0xca, 0xb4, 0xc7, 0x8b, 0xe5, 0x0e, 0x06, 0x2b, 0x03, 0xa9, 0xff, 0xfb, 0xba, 0xd1,
+];
+
+/// The `Swap(address,uint256,uint256,uint256,uint256,address)` topic
+pub const SWAP_TOPIC: [u8; 32] = [
+    0xd7, 0x8a, 0xd9, 0x5f, 0xa4, 0x6c, 0x99, 0x4b, 0x65, 0x51, 0xd0, 0xda, 0x85, 0xfc, 0x27, 0x5f,
+    0xe6, 0x13, 0xce, 0x37, 0x65, 0x7f, 0xb8, 0xd5, 0xe3, 0xd1, 0x30, 0x84, 0x01, 0x59, 0xd8, 0x22,
+];
+
+/// The `PairCreated(address,address,address,uint256)` topic
+pub const PAIR_CREATED_TOPIC: [u8; 32] = [
+    0x0d, 0x36, 0x48, 0xbd, 0x0f, 0x6b, 0xa8, 0x01, 0x34, 0xa3, 0x3b, 0xa9, 0x27, 0x5a, 0xc5, 0x85,
+    0xd9, 0xd3, 0x15, 0xf0, 0xad, 0x83, 0x55, 0xcd, 0xde, 0xfd, 0xe3, 0x1a, 0xfa, 0x28, 0xd0, 0xe9,
+];
+
+/// The size of an abi word
+const WORD: usize = 32;
+
+/// An event log decoding error
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum EventDecodeError {
+    /// The log has no topics, like an anonymous event
+    #[error("log has no topics")]
+    MissingTopic,
+    /// The event signature doesn't match the expected event
+    #[error("unexpected event topic {0:?}")]
+    UnknownTopic(H256),
+    /// The log has the wrong number of indexed topics
+    #[error("expected {expected} topics, got {actual}")]
+    InvalidTopics {
+        /// The expected number of topics
+        expected: usize,
+        /// The number of topics in the log
+        actual: usize,
+    },
+    /// The log data has the wrong length
+    #[error("expected {expected} bytes of data, got {actual}")]
+    InvalidData {
+        /// The expected data length
+        expected: usize,
+        /// The log data length
+        actual: usize,
+    },
+}
+
+/// Checks the log's signature, topic count and data length
+fn check(
+    log: &Log,
+    topic: [u8; 32],
+    topics: usize,
+    data_words: usize,
+) -> Result<(), EventDecodeError> {
+    let signature = log.topics.first().ok_or(EventDecodeError::MissingTopic)?;
+    if signature.as_bytes() != topic {
+        return Err(EventDecodeError::UnknownTopic(*signature));
+    }
+    if log.topics.len() != topics {
+        return Err(EventDecodeError::InvalidTopics {
+            expected: topics,
+            actual: log.topics.len(),
+        });
+    }
+    if log.data.len() != data_words * WORD {
+        return Err(EventDecodeError::InvalidData {
+            expected: data_words * WORD,
+            actual: log.data.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Reads the data word at `index`
+fn word(log: &Log, index: usize) -> U256 {
+    U256::from_big_endian(&log.data[index * WORD..(index + 1) * WORD])
+}
+
+/// An ERC-20 `Transfer`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Erc20Transfer {
+    /// The token
+    pub token: Address,
+    /// The sender
+    pub from: Address,
+    /// The recipient
+    pub to: Address,
+    /// The amount transferred
+    pub value: U256,
+}
+
+impl Erc20Transfer {
+    /// Decodes a `Transfer` log
+    pub fn decode(log: &Log) -> Result<Self, EventDecodeError> {
+        check(log, TRANSFER_TOPIC, 3, 1)?;
+        Ok(Self {
+            token: log.address,
+            from: Address::from(log.topics[1]),
+            to: Address::from(log.topics[2]),
+            value: word(log, 0),
+        })
+    }
+}
+
+/// A UniswapV2 pair `Sync`, emitted with the reserves after every change
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct V2Sync {
+    /// The pair
+    pub pair: Address,
+    /// The token0 reserves
+    pub reserve0: U256,
+    /// The token1 reserves
+    pub reserve1: U256,
+}
+
+impl V2Sync {
+    /// Decodes a `Sync` log
+    pub fn decode(log: &Log) -> Result<Self, EventDecodeError> {
+        check(log, SYNC_TOPIC, 1, 2)?;
+        Ok(Self {
+            pair: log.address,
+            reserve0: word(log, 0),
+            reserve1: word(log, 1),
+        })
+    }
+}
+
+/// A UniswapV2 pair `Swap`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct V2Swap {
+    /// The pair
+    pub pair: Address,
+    /// The caller, usually the router
+    pub sender: Address,
+    /// The token0 paid in
+    pub amount0_in: U256,
+    /// The token1 paid in
+    pub amount1_in: U256,
+    /// The token0 paid out
+    pub amount0_out: U256,
+    /// The token1 paid out
+    pub amount1_out: U256,
+    /// The recipient of the output
+    pub to: Address,
+}
+
+impl V2Swap {
+    /// Decodes a `Swap` log
+    pub fn decode(log: &Log) -> Result<Self, EventDecodeError> {
+        check(log, SWAP_TOPIC, 3, 4)?;
+        Ok(Self {
+            pair: log.address,
+            sender: Address::from(log.topics[1]),
+            amount0_in: word(log, 0),
+            amount1_in: word(log, 1),
+            amount0_out: word(log, 2),
+            amount1_out: word(log, 3),
+            to: Address::from(log.topics[2]),
+        })
+    }
+
+    /// Whether the swap sold token0 for token1
+    pub fn zero_for_one(&self) -> bool {
+        !self.amount0_in.is_zero() && !self.amount1_out.is_zero()
+    }
+}
+
+/// A UniswapV2 factory `PairCreated`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct V2PairCreated {
+    /// The factory
+    pub factory: Address,
+    /// The lower sorted token
+    pub token0: Address,
+    /// The higher sorted token
+    pub token1: Address,
+    /// The new pair
+    pub pair: Address,
+    /// The number of pairs the factory has created, including this one
+    pub index: U256,
+}
+
+impl V2PairCreated {
+    /// Decodes a `PairCreated` log
+    pub fn decode(log: &Log) -> Result<Self, EventDecodeError> {
+        check(log, PAIR_CREATED_TOPIC, 3, 2)?;
+        Ok(Self {
+            factory: log.address,
+            token0: Address::from(log.topics[1]),
+            token1: Address::from(log.topics[2]),
+            pair: Address::from(H256::from_slice(&log.data[..WORD])),
+            index: word(log, 1),
+        })
+    }
+
+    /// The token paired with `token`, if the pair has it
+    pub fn other(&self, token: Address) -> Option<Address> {
+        if self.token0 == token {
+            Some(self.token1)
+        } else if self.token1 == token {
+            Some(self.token0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Any decoded log the bot understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodedLog {
+    /// An ERC-20 `Transfer`
+    Transfer(Erc20Transfer),
+    /// A UniswapV2 `Sync`
+    Sync(V2Sync),
+    /// A UniswapV2 `Swap`
+    Swap(V2Swap),
+    /// A UniswapV2 `PairCreated`
+    PairCreated(V2PairCreated),
+}
+
+impl DecodedLog {
+    /// Decodes a log by its signature, returning `None` for unknown or malformed events
+    pub fn decode(log: &Log) -> Option<Self> {
+        let signature = log.topics.first()?.to_fixed_bytes();
+        match signature {
+            TRANSFER_TOPIC => Erc20Transfer::decode(log).ok().map(Self::Transfer),
+            SYNC_TOPIC => V2Sync::decode(log).ok().map(Self::Sync),
+            SWAP_TOPIC => V2Swap::decode(log).ok().map(Self::Swap),
+            PAIR_CREATED_TOPIC => V2PairCreated::decode(log).ok().map(Self::PairCreated),
+            _ => None,
+        }
+    }
+}
+
+/// Sums the amount of `token` an account sent and received in a set of logs
+pub fn token_flows(logs: &[Log], token: Address, account: Address) -> (U256, U256) {
+    let mut sent = U256::zero();
+    let mut received = U256::zero();
+    for transfer in logs
+        .iter()
+        .filter(|log| log.address == token)
+        .filter_map(|log| Erc20Transfer::decode(log).ok())
+    {
+        if transfer.from == account {
+            sent = sent.saturating_add(transfer.value);
+        }
+        if transfer.to == account {
+            received = received.saturating_add(transfer.value);
+        }
+    }
+    (sent, received)
+}
+
+/// Returns the last reserves each pair synced to in a set of logs
+pub fn latest_reserves(logs: &[Log]) -> Vec<V2Sync> {
+    let mut latest: Vec<V2Sync> = Vec::new();
+    for sync in logs.iter().filter_map(|log| V2Sync::decode(log).ok()) {
+        match latest.iter_mut().find(|s| s.pair == sync.pair) {
+            Some(existing) => *existing = sync,
+            None => latest.push(sync),
+        }
+    }
+    latest
+}
@@ -3,14 +3,92 @@
 //! Contract ABIs are refactored into their own module to gracefully deal with allowing missing docs on the abigen macro.
 #![allow(missing_docs)]
 
-use ethers::{abi::AbiDecode, prelude::*};
-use eyre::Result;
+use ethers::prelude::*;
+
+/// Allocation-free calldata decoding
+pub mod calldata;
+pub use calldata::*;
+
+/// Event log decoding
+pub mod events;
+pub use events::*;
 
 abigen!(UniswapV2Pair, "src/abi/IUniswapV2Pair.json");
 abigen!(UniswapV2Router02, "src/abi/IUniswapV2Router02.json");
 abigen!(UniswapV2Factory, "src/abi/IUniswapV2Factory.json");
 
+/// A minimal ERC20 interface
+///
+/// Generated in its own module so its call types don't collide with the pair's ERC20 methods.
+pub mod erc20 {
+    use ethers::prelude::*;
+
+    abigen!(
+        ERC20,
+        r#"[
+            function balanceOf(address) external view returns (uint256)
+            function allowance(address,address) external view returns (uint256)
+            function approve(address,uint256) external returns (bool)
+            function transfer(address,uint256) external returns (bool)
+            function decimals() external view returns (uint8)
+            function symbol() external view returns (string)
+        ]"#
+    );
+}
+pub use erc20::ERC20;
+
+/// The Uniswap V3 pool state getters
+pub mod v3_pool {
+    use ethers::prelude::*;
+
+    abigen!(
+        UniswapV3Pool,
+        r#"[
+            function slot0() external view returns (uint160 sqrtPriceX96, int24 tick, uint16 observationIndex, uint16 observationCardinality, uint16 observationCardinalityNext, uint8 feeProtocol, bool unlocked)
+            function liquidity() external view returns (uint128)
+            function tickSpacing() external view returns (int24)
+            function token0() external view returns (address)
+        ]"#
+    );
+}
+pub use v3_pool::UniswapV3Pool;
+
+/// The Aave V3 and Compound V2 position getters
+pub mod lending {
+    use ethers::prelude::*;
+
+    abigen!(
+        AavePool,
+        r#"[
+            function getUserAccountData(address) external view returns (uint256 totalCollateralBase, uint256 totalDebtBase, uint256 availableBorrowsBase, uint256 currentLiquidationThreshold, uint256 ltv, uint256 healthFactor)
+        ]"#
+    );
+
+    abigen!(
+        Comptroller,
+        r#"[
+            function getAccountLiquidity(address) external view returns (uint256, uint256, uint256)
+            function closeFactorMantissa() external view returns (uint256)
+            function getAllMarkets() external view returns (address[])
+        ]"#
+    );
+
+    abigen!(
+        CToken,
+        r#"[
+            function borrowBalanceStored(address) external view returns (uint256)
+            function underlying() external view returns (address)
+        ]"#
+    );
+}
+pub use lending::{AavePool, CToken, Comptroller};
+
 /// Decodes the raw tx data into a UniswapV2Router02 Call
-pub fn decode_uniswap_router_calldata(data: &Bytes) -> Result<SwapExactETHForTokensCall> {
-    SwapExactETHForTokensCall::decode(data).map_err(|e| eyre::eyre!(e))
+///
+/// The selector and arguments are read directly from the slice, so non-matching transactions are
+/// rejected without allocating.
+pub fn decode_uniswap_router_calldata(
+    data: &Bytes,
+) -> Result<SwapExactETHForTokensCall, DecodeError> {
+    SwapExactEthForTokensView::parse(data.as_ref()).map(|view| view.to_call())
 }
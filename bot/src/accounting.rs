@@ -0,0 +1,658 @@
+//! Profit Accounting
+//!
+//! Tracks realized profit in wei. Unlike [crate::metrics] counters, amounts are kept as `U256` so
+//! large sums don't overflow. Totals of previous sessions can be restored from a
+//! [crate::metrics::MetricsSnapshot] for a lifetime view.
+//!
+//! Landed sandwiches are settled from their receipts: both legs must have succeeded, and the profit
+//! is the executor's net WETH flow in the legs' `Transfer` logs. Settlements are reconciled against
+//! the pre-trade estimate so mispriced sandwiches stand out.
+//!
+//! Gas is accounted at the effective gas price each leg paid, read from its receipt, rather than
+//! the max fees it was signed with. The priority fee the backrun actually paid is kept next to the
+//! bribe it was priced with, so the bribe model can be calibrated against realized costs.
+//!
+//! MEV-Share backruns refund part of their bribe to the user. The refunded part is kept as a
+//! rebate, apart from the gas, so the ledger shows what orderflow costs.
+//!
+//! A settled sandwich stays pending until its block has enough confirmations, and is only then
+//! added to the realized totals. Pending sandwiches whose block is reorged out are rolled back
+//! instead, so the realized profit never counts a sandwich that didn't stay on chain. Pending
+//! sandwiches are saved with the [crate::metrics::MetricsSnapshot], so those still waiting at
+//! shutdown are confirmed or rolled back after a restart.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{abi::token_flows, metrics, mev_share::split_rebate};
+
+/// The default deviation between estimated and realized profit that gets flagged, in bips
+pub const DEFAULT_DEVIATION_BPS: u64 = 1_000;
+
+/// The default confirmations before a settled sandwich's profit is realized
+pub const DEFAULT_CONFIRMATIONS: u64 = 12;
+
+/// Profit totals, in wei
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Ledger {
+    /// Revenue before rebates and gas
+    pub gross_profit: U256,
+    /// Rebates refunded to users through MEV-Share, out of the bribes
+    #[serde(default)]
+    pub rebates: U256,
+    /// Gas paid, less the rebates
+    pub gas: U256,
+    /// Revenue estimated before trading, for the sandwiches that settled
+    pub estimated_profit: U256,
+    /// Gas estimated before trading at the predicted base fee and bribe, for the sandwiches that
+    /// settled
+    #[serde(default)]
+    pub estimated_gas: U256,
+    /// Priority fees the backruns paid, the realized bribes
+    #[serde(default)]
+    pub bribes: U256,
+    /// Bribes the backruns were priced with, for the sandwiches that settled
+    #[serde(default)]
+    pub estimated_bribes: U256,
+}
+
+impl Ledger {
+    /// Profit after rebates and gas
+    pub fn net_profit(&self) -> U256 {
+        self.gross_profit
+            .saturating_sub(self.rebates)
+            .saturating_sub(self.gas)
+    }
+}
+
+impl Ledger {
+    /// Adds a landed bundle's gross profit, rebate and gas cost
+    fn add(&mut self, gross_profit: U256, rebate: U256, gas: U256) {
+        self.gross_profit = self.gross_profit.saturating_add(gross_profit);
+        self.rebates = self.rebates.saturating_add(rebate);
+        self.gas = self.gas.saturating_add(gas);
+    }
+
+    /// Adds a settled sandwich's estimates and realized bribe
+    fn add_estimates(&mut self, estimated_profit: U256, fees: &FeeEstimate, bribe: U256) {
+        self.estimated_profit = self.estimated_profit.saturating_add(estimated_profit);
+        self.estimated_gas = self.estimated_gas.saturating_add(fees.cost());
+        self.bribes = self.bribes.saturating_add(bribe);
+        self.estimated_bribes = self.estimated_bribes.saturating_add(fees.bribe());
+    }
+
+    /// Sums two ledgers
+    pub fn combined(&self, other: &Ledger) -> Ledger {
+        let mut combined = *self;
+        combined.add(other.gross_profit, other.rebates, other.gas);
+        combined.estimated_profit = combined
+            .estimated_profit
+            .saturating_add(other.estimated_profit);
+        combined.estimated_gas = combined.estimated_gas.saturating_add(other.estimated_gas);
+        combined.bribes = combined.bribes.saturating_add(other.bribes);
+        combined.estimated_bribes = combined
+            .estimated_bribes
+            .saturating_add(other.estimated_bribes);
+        combined
+    }
+}
+
+/// A settled sandwich waiting for confirmations
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PendingSettlement {
+    /// The wallet that sent the sandwich
+    pub wallet: Address,
+    /// The block the sandwich landed in
+    pub block_number: U64,
+    /// The hash of that block when the sandwich was settled
+    pub block_hash: H256,
+    /// The sandwich's profit, gas and estimates
+    pub ledger: Ledger,
+}
+
+/// What a round of confirmation checks did
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Confirmations {
+    /// Pending sandwiches realized
+    pub realized: usize,
+    /// Pending sandwiches rolled back, their block reorged out
+    pub reorged: usize,
+}
+
+/// A thread-safe profit ledger, totalled across searcher wallets and kept per wallet
+#[derive(Debug, Default)]
+pub struct Accounting {
+    ledger: Mutex<Ledger>,
+    wallets: Mutex<BTreeMap<Address, Ledger>>,
+    restored: Mutex<Ledger>,
+    pending: Mutex<Vec<PendingSettlement>>,
+}
+
+impl Accounting {
+    /// Creates an empty ledger
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a landed bundle's gross profit, rebate and gas cost
+    pub fn record(&self, gross_profit: U256, rebate: U256, gas: U256) {
+        let mut ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        ledger.add(gross_profit, rebate, gas);
+    }
+
+    /// Records a landed bundle sent from `wallet`, in both the total and the wallet's ledger
+    pub fn record_for(&self, wallet: Address, gross_profit: U256, rebate: U256, gas: U256) {
+        self.record(gross_profit, rebate, gas);
+        let mut wallets = self.wallets.lock().unwrap_or_else(|e| e.into_inner());
+        wallets
+            .entry(wallet)
+            .or_default()
+            .add(gross_profit, rebate, gas);
+    }
+
+    /// Records a sandwich settled from `wallet` against its pre-trade revenue and fee estimates
+    ///
+    /// The sandwich is pending until [Accounting::confirm] sees enough confirmations of its block.
+    pub fn reconcile(
+        &self,
+        wallet: Address,
+        estimated_profit: U256,
+        estimated_fees: &FeeEstimate,
+        settlement: &Settlement,
+    ) -> Reconciliation {
+        let realized_profit = settlement.gross_profit();
+        let (rebate, _) = split_rebate(settlement.bribe(), estimated_fees.refund_percent);
+        let mut ledger = Ledger::default();
+        ledger.add(
+            realized_profit,
+            rebate,
+            settlement.gas.saturating_sub(rebate),
+        );
+        ledger.add_estimates(estimated_profit, estimated_fees, settlement.bribe());
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(PendingSettlement {
+                wallet,
+                block_number: settlement.block_number,
+                block_hash: settlement.block_hash,
+                ledger,
+            });
+        Reconciliation {
+            estimated_profit,
+            realized_profit,
+            deviation_bps: deviation_bps(estimated_profit, realized_profit),
+            estimated_gas: estimated_fees.cost(),
+            realized_gas: settlement.gas,
+            gas_deviation_bps: deviation_bps(estimated_fees.cost(), settlement.gas),
+        }
+    }
+
+    /// Realizes pending sandwiches with `confirmations` blocks on top of and including theirs at
+    /// `head`, and rolls back those whose block isn't canonical anymore
+    ///
+    /// `canonical` holds the current hash of the pending sandwiches' blocks, see
+    /// [Accounting::pending_blocks]. Sandwiches whose block is missing from it stay pending.
+    pub fn confirm(
+        &self,
+        head: U64,
+        confirmations: u64,
+        canonical: &BTreeMap<U64, H256>,
+    ) -> Confirmations {
+        let mut outcome = Confirmations::default();
+        let mut realized = Vec::new();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|pending| match canonical.get(&pending.block_number) {
+                Some(hash) if *hash != pending.block_hash => {
+                    outcome.reorged += 1;
+                    false
+                }
+                Some(_) if head + 1 >= pending.block_number + confirmations => {
+                    realized.push(*pending);
+                    false
+                }
+                _ => true,
+            });
+        outcome.realized = realized.len();
+
+        let mut ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        let mut wallets = self.wallets.lock().unwrap_or_else(|e| e.into_inner());
+        for pending in realized {
+            *ledger = ledger.combined(&pending.ledger);
+            let wallet = wallets.entry(pending.wallet).or_default();
+            *wallet = wallet.combined(&pending.ledger);
+        }
+        outcome
+    }
+
+    /// The blocks pending sandwiches landed in
+    pub fn pending_blocks(&self) -> Vec<U64> {
+        let mut blocks = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|pending| pending.block_number)
+            .collect::<Vec<_>>();
+        blocks.sort();
+        blocks.dedup();
+        blocks
+    }
+
+    /// Returns the totals of the sandwiches waiting for confirmations
+    pub fn pending_snapshot(&self) -> Ledger {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .fold(Ledger::default(), |total, pending| {
+                total.combined(&pending.ledger)
+            })
+    }
+
+    /// Returns a copy of the sandwiches waiting for confirmations
+    pub fn pending(&self) -> Vec<PendingSettlement> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Restores the sandwiches a previous session left waiting for confirmations
+    pub fn restore_pending(&self, pending: Vec<PendingSettlement>) {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(pending);
+    }
+
+    /// Returns a copy of the realized totals across every wallet
+    pub fn snapshot(&self) -> Ledger {
+        *self.ledger.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Restores the totals of previous sessions
+    pub fn restore(&self, ledger: Ledger) {
+        *self.restored.lock().unwrap_or_else(|e| e.into_inner()) = ledger;
+    }
+
+    /// Returns the totals across every wallet and session
+    pub fn lifetime(&self) -> Ledger {
+        let restored = *self.restored.lock().unwrap_or_else(|e| e.into_inner());
+        restored.combined(&self.snapshot())
+    }
+
+    /// Returns a copy of each wallet's totals
+    pub fn wallet_snapshots(&self) -> BTreeMap<Address, Ledger> {
+        self.wallets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// Returns the global profit ledger
+pub fn ledger() -> &'static Accounting {
+    static ACCOUNTING: OnceLock<Accounting> = OnceLock::new();
+    ACCOUNTING.get_or_init(Accounting::new)
+}
+
+/// The gas a leg was priced with before trading
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FeeEstimate {
+    /// Simulated gas used by the frontrun
+    pub frontrun_gas: U256,
+    /// Simulated gas used by the backrun
+    pub backrun_gas: U256,
+    /// The predicted base fee of the target block
+    pub base_fee: U256,
+    /// The priority fee per gas the backrun was signed with
+    pub bribe_per_gas: U256,
+    /// The share of the bribe refunded to the user, in percent, for MEV-Share backruns
+    #[serde(default)]
+    pub refund_percent: u8,
+}
+
+impl FeeEstimate {
+    /// The bribe the backrun is expected to pay
+    pub fn bribe(&self) -> U256 {
+        self.backrun_gas.saturating_mul(self.bribe_per_gas)
+    }
+
+    /// The gas both legs are expected to pay, including the bribe
+    pub fn cost(&self) -> U256 {
+        self.frontrun_gas
+            .saturating_add(self.backrun_gas)
+            .saturating_mul(self.base_fee)
+            .saturating_add(self.bribe())
+    }
+}
+
+/// The gas a landed leg paid, from its receipt
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PaidGas {
+    /// Gas used
+    pub gas_used: U256,
+    /// The effective gas price paid, the base fee plus the priority fee actually paid
+    pub effective_gas_price: U256,
+    /// The base fee of the block the leg landed in
+    pub base_fee: U256,
+}
+
+impl PaidGas {
+    /// The gas paid
+    pub fn cost(&self) -> U256 {
+        self.gas_used.saturating_mul(self.effective_gas_price)
+    }
+
+    /// The priority fee paid per gas
+    pub fn priority_fee_per_gas(&self) -> U256 {
+        self.effective_gas_price.saturating_sub(self.base_fee)
+    }
+
+    /// The priority fee paid
+    pub fn priority_fee(&self) -> U256 {
+        self.gas_used.saturating_mul(self.priority_fee_per_gas())
+    }
+}
+
+/// The on-chain outcome of a landed sandwich
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Settlement {
+    /// Whether both legs landed and succeeded
+    pub both_legs: bool,
+    /// WETH sent from the executor across both legs
+    pub weth_spent: U256,
+    /// WETH received by the executor across both legs
+    pub weth_received: U256,
+    /// Gas paid for both legs, including the priority fee bribe
+    pub gas: U256,
+    /// The frontrun's gas, if it landed
+    pub frontrun_gas: Option<PaidGas>,
+    /// The backrun's gas, if it landed
+    pub backrun_gas: Option<PaidGas>,
+    /// The block the frontrun landed in
+    #[serde(default)]
+    pub block_number: U64,
+    /// The hash of that block
+    #[serde(default)]
+    pub block_hash: H256,
+}
+
+impl Settlement {
+    /// The priority fee the backrun paid, the realized bribe
+    pub fn bribe(&self) -> U256 {
+        self.backrun_gas
+            .map(|leg| leg.priority_fee())
+            .unwrap_or_default()
+    }
+
+    /// Revenue before gas, zero if the sandwich lost WETH
+    pub fn gross_profit(&self) -> U256 {
+        self.weth_received.saturating_sub(self.weth_spent)
+    }
+
+    /// Whether the sandwich ended with less WETH than it started with
+    pub fn is_loss(&self) -> bool {
+        self.weth_received < self.weth_spent
+    }
+}
+
+/// A settled sandwich compared to its estimate
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// The pre-trade revenue estimate
+    pub estimated_profit: U256,
+    /// The revenue realized on-chain
+    pub realized_profit: U256,
+    /// How far the realized revenue is from the estimate, in bips of the estimate
+    pub deviation_bps: u64,
+    /// The pre-trade gas estimate, including the bribe
+    pub estimated_gas: U256,
+    /// The gas paid on-chain, including the bribe
+    pub realized_gas: U256,
+    /// How far the realized gas is from the estimate, in bips of the estimate
+    pub gas_deviation_bps: u64,
+}
+
+/// Returns how far `realized` is from `estimated`, in bips of `estimated`
+///
+/// Any realized profit against a zero estimate is a full deviation.
+pub fn deviation_bps(estimated: U256, realized: U256) -> u64 {
+    let difference = if realized > estimated {
+        realized - estimated
+    } else {
+        estimated - realized
+    };
+    if difference.is_zero() {
+        return 0;
+    }
+    if estimated.is_zero() {
+        return 10_000;
+    }
+    let bps = difference.saturating_mul(U256::from(10_000)) / estimated;
+    if bps > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        bps.as_u64()
+    }
+}
+
+/// Sums the WETH an account sent and received in a set of logs
+pub fn weth_flows(logs: &[Log], weth: Address, account: Address) -> (U256, U256) {
+    token_flows(logs, weth, account)
+}
+
+/// Settles a sandwich from its leg receipts
+///
+/// Returns `None` if the frontrun never landed.
+pub async fn settle<M: Middleware + 'static>(
+    client: Arc<M>,
+    frontrun: TxHash,
+    backrun: TxHash,
+    weth: Address,
+    executor: Address,
+) -> Result<Option<Settlement>> {
+    let mut receipts = Vec::new();
+    for hash in [frontrun, backrun] {
+        receipts.push(
+            client
+                .get_transaction_receipt(hash)
+                .await
+                .map_err(|e| eyre::eyre!("Failed to fetch receipt: {:?}", e))?,
+        );
+    }
+    if receipts[0].is_none() {
+        return Ok(None);
+    }
+
+    let mut settlement = Settlement {
+        both_legs: true,
+        ..Default::default()
+    };
+    let mut base_fees = BTreeMap::new();
+    for (i, receipt) in receipts.iter().enumerate() {
+        let receipt = match receipt {
+            Some(r) => r,
+            None => {
+                settlement.both_legs = false;
+                continue;
+            }
+        };
+        if receipt.status != Some(U64::one()) {
+            settlement.both_legs = false;
+        }
+        let (spent, received) = weth_flows(&receipt.logs, weth, executor);
+        settlement.weth_spent += spent;
+        settlement.weth_received += received;
+
+        // Both legs normally land in the same block, so its base fee is only fetched once
+        let block = receipt.block_number.unwrap_or_default();
+        let base_fee = match base_fees.get(&block) {
+            Some(base_fee) => *base_fee,
+            None => {
+                let base_fee = client
+                    .get_block(block)
+                    .await
+                    .map_err(|e| eyre::eyre!("Failed to fetch block: {:?}", e))?
+                    .and_then(|b| b.base_fee_per_gas)
+                    .unwrap_or_default();
+                base_fees.insert(block, base_fee);
+                base_fee
+            }
+        };
+        let leg = leg_gas(receipt, base_fee);
+        settlement.gas += leg.cost();
+        if i == 0 {
+            settlement.block_number = block;
+            settlement.block_hash = receipt.block_hash.unwrap_or_default();
+            settlement.frontrun_gas = Some(leg);
+        } else {
+            settlement.backrun_gas = Some(leg);
+        }
+    }
+    Ok(Some(settlement))
+}
+
+/// Reads the gas a leg paid from its receipt, given the base fee of its block
+pub fn leg_gas(receipt: &TransactionReceipt, base_fee: U256) -> PaidGas {
+    PaidGas {
+        gas_used: receipt.gas_used.unwrap_or_default(),
+        effective_gas_price: receipt.effective_gas_price.unwrap_or_default(),
+        base_fee,
+    }
+}
+
+/// Settles a landed sandwich sent from `wallet` and reconciles it in the global ledger, flagging
+/// large deviations
+#[allow(clippy::too_many_arguments)]
+pub async fn reconcile_sandwich<M: Middleware + 'static>(
+    client: Arc<M>,
+    wallet: Address,
+    frontrun: TxHash,
+    backrun: TxHash,
+    weth: Address,
+    executor: Address,
+    estimated_profit: U256,
+    estimated_fees: FeeEstimate,
+    max_deviation_bps: u64,
+) -> Result<Option<Reconciliation>> {
+    let settlement = match settle(client, frontrun, backrun, weth, executor).await? {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    if !settlement.both_legs {
+        metrics::increment("settlement_partial");
+        tracing::warn!(
+            "[SETTLE] Sandwich {:?} landed without both legs succeeding: {:?}",
+            frontrun,
+            settlement
+        );
+    }
+    if settlement.is_loss() {
+        metrics::increment("settlement_loss");
+    }
+
+    let reconciliation = ledger().reconcile(wallet, estimated_profit, &estimated_fees, &settlement);
+    metrics::increment("settlement_landed");
+    if reconciliation.deviation_bps > max_deviation_bps {
+        metrics::increment("settlement_deviation");
+        tracing::warn!(
+            "[SETTLE] Sandwich {:?} realized {} against an estimated {} ({} bips off)",
+            frontrun,
+            reconciliation.realized_profit,
+            reconciliation.estimated_profit,
+            reconciliation.deviation_bps
+        );
+    } else {
+        tracing::info!(
+            "[SETTLE] Sandwich {:?} realized {} against an estimated {}",
+            frontrun,
+            reconciliation.realized_profit,
+            reconciliation.estimated_profit
+        );
+    }
+    if reconciliation.gas_deviation_bps > max_deviation_bps {
+        metrics::increment("settlement_gas_deviation");
+    }
+    tracing::info!(
+        "[SETTLE] Sandwich {:?} paid {} gas against an estimated {}, a {} bribe against {}",
+        frontrun,
+        reconciliation.realized_gas,
+        reconciliation.estimated_gas,
+        settlement.bribe(),
+        estimated_fees.bribe()
+    );
+    Ok(Some(reconciliation))
+}
+
+/// Fetches the current hash of every block with pending sandwiches and confirms them at `head`
+pub async fn confirm_settlements<M: Middleware + 'static>(
+    client: Arc<M>,
+    head: U64,
+    confirmations: u64,
+) -> Result<Confirmations> {
+    let mut canonical = BTreeMap::new();
+    for number in ledger().pending_blocks() {
+        if number > head {
+            continue;
+        }
+        let block = client
+            .get_block(number)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch block {}: {:?}", number, e))?;
+        if let Some(hash) = block.and_then(|b| b.hash) {
+            canonical.insert(number, hash);
+        }
+    }
+    Ok(ledger().confirm(head, confirmations, &canonical))
+}
+
+/// Confirms pending sandwiches on every new block
+///
+/// Rolled back sandwiches are counted in the `settlement_reorged` metric.
+pub async fn watch_confirmations(client: Arc<Provider<Ws>>, confirmations: u64) -> Result<()> {
+    let mut blocks = client
+        .subscribe_blocks()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to subscribe to blocks: {:?}", e))?;
+    while let Some(block) = blocks.next().await {
+        let head = match block.number {
+            Some(n) => n,
+            None => continue,
+        };
+        let outcome = match confirm_settlements(client.clone(), head, confirmations).await {
+            Ok(o) => o,
+            Err(e) => {
+                tracing::debug!("[SETTLE] Failed to confirm settlements: {:?}", e);
+                continue;
+            }
+        };
+        for _ in 0..outcome.reorged {
+            metrics::increment("settlement_reorged");
+        }
+        if outcome.reorged > 0 {
+            tracing::warn!(
+                "[SETTLE] Rolled back {} sandwiches reorged out before block {}",
+                outcome.reorged,
+                head
+            );
+        }
+        if outcome.realized > 0 {
+            tracing::info!(
+                "[SETTLE] Realized {} sandwiches at block {}",
+                outcome.realized,
+                head
+            );
+        }
+    }
+    eyre::bail!("Block subscription ended")
+}
@@ -0,0 +1,429 @@
+//! Admin API
+//!
+//! A minimal HTTP endpoint for operating a running bot without restarting it, since a restart
+//! loses the mempool warm state. It should only be bound to a private interface, and routes that
+//! change the bot need an `authorization: Bearer <token>` header carrying `ADMIN_TOKEN`.
+//!
+//! - `GET /metrics` returns the session and lifetime counters, gauges and profit as json.
+//! - `GET /health` returns the status, restarts and last event of every supervised task as json.
+//! - `GET /edge` returns the mempool edge over mined swaps, per source, as json.
+//! - `GET /explain?tx=0x..` returns why a victim transaction was or wasn't sandwiched as json.
+//! - `GET /approvals` returns the sandwiches waiting for operator approval as json.
+//! - `GET /competition` returns how many victims each competitor contract took from us as json.
+//! - `POST /approve?id=N` and `POST /reject?id=N` decide on a waiting sandwich.
+//! - `POST /halt` stops submission and pauses the executor contract, `POST /resume` undoes it.
+//! - `GET /log-filter` returns the tracing filter directives.
+//! - `PUT /log-filter` replaces them with the request body, like `info,subway_rs::uniswap=trace`.
+//!   With `?secs=N`, the previous filter is restored after `N` seconds unless it was changed again.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use eyre::Result;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    control, explain, instances, manual_approval, mempool, metrics, postmortem, supervisor, utils,
+};
+
+/// The largest request accepted, in bytes
+pub const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Reads and replaces the tracing filter
+pub trait LogFilterControl: Send + Sync {
+    /// Returns the current filter directives
+    fn current(&self) -> String;
+
+    /// Replaces the filter with `directives`
+    fn set(&self, directives: &str) -> Result<()>;
+}
+
+/// A parsed admin request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Request {
+    /// The method, like `GET`
+    pub method: String,
+    /// The path without the query
+    pub path: String,
+    /// The query parameters
+    pub query: HashMap<String, String>,
+    /// The headers, keyed by lower-cased name
+    pub headers: HashMap<String, String>,
+    /// The body
+    pub body: String,
+}
+
+impl Request {
+    /// Parses a complete HTTP/1.1 request
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let raw = std::str::from_utf8(raw)?;
+        let (head, body) = raw
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| eyre::eyre!("Incomplete request head"))?;
+        let mut lines = head.lines();
+        let mut parts = lines.next().unwrap_or_default().split_whitespace();
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(m), Some(t)) => (m, t),
+            _ => eyre::bail!("Invalid request line"),
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (key.to_string(), value.to_string())
+            })
+            .collect();
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        Ok(Self {
+            method: method.to_uppercase(),
+            path: path.to_string(),
+            query,
+            headers,
+            body: body.trim().to_string(),
+        })
+    }
+
+    /// The bearer token in the `authorization` header, if any
+    pub fn bearer_token(&self) -> Option<&str> {
+        self.headers
+            .get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+    }
+}
+
+/// An admin response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// The status code
+    pub status: u16,
+    /// The body
+    pub body: String,
+}
+
+impl Response {
+    /// A response with `status` and `body`
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+
+    /// Serializes the response as HTTP/1.1
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.body.len(),
+            self.body
+        )
+        .into_bytes()
+    }
+}
+
+/// Returns the length of a complete request in `buf`, if it's been fully read
+fn request_len(buf: &[u8]) -> Option<usize> {
+    let head_end = buf.windows(4).position(|w| w == b"\r\n\r\n")? + 4;
+    let head = String::from_utf8_lossy(&buf[..head_end]);
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    let len = head_end + content_length;
+    (buf.len() >= len).then_some(len)
+}
+
+/// Reads the bearer token mutating routes need from `ADMIN_TOKEN`, which must be set
+pub fn token_from_env() -> Result<String> {
+    instances::var("ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| eyre::eyre!("\"ADMIN_ADDR\" requires \"ADMIN_TOKEN\" to be set"))
+}
+
+/// Whether a route changes the running bot, and so needs the bearer token
+pub fn is_mutating(method: &str, path: &str) -> bool {
+    matches!(
+        (method, path),
+        ("POST", "/approve")
+            | ("POST", "/reject")
+            | ("POST", "/halt")
+            | ("POST", "/resume")
+            | ("PUT", "/log-filter")
+    )
+}
+
+/// The admin API's handles into the running bot
+#[derive(Default)]
+pub struct Admin {
+    log_filter: Option<Arc<dyn LogFilterControl>>,
+    filter_generation: Arc<AtomicU64>,
+    token: Option<String>,
+}
+
+impl std::fmt::Debug for Admin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Admin")
+            .field("log_filter", &self.log_filter.as_ref().map(|l| l.current()))
+            .finish()
+    }
+}
+
+impl Admin {
+    /// Creates an admin API without a log filter, refusing mutating routes until given a token
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the bearer token mutating routes need
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Lets the admin API reconfigure the tracing filter
+    pub fn with_log_filter(mut self, log_filter: Arc<dyn LogFilterControl>) -> Self {
+        self.log_filter = Some(log_filter);
+        self
+    }
+
+    /// Whether a request carries the bearer token
+    fn authorized(&self, request: &Request) -> bool {
+        match (&self.token, request.bearer_token()) {
+            (Some(token), Some(given)) => {
+                utils::constant_time_eq(given.as_bytes(), token.as_bytes())
+            }
+            _ => false,
+        }
+    }
+
+    /// Handles a request
+    pub fn handle(&self, request: &Request) -> Response {
+        if is_mutating(&request.method, &request.path) && !self.authorized(request) {
+            metrics::increment("admin_unauthorized");
+            return Response::new(401, "Invalid or missing bearer token");
+        }
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/metrics") => {
+                match serde_json::to_string(&metrics::MetricsSnapshot::capture()) {
+                    Ok(json) => Response::new(200, json),
+                    Err(e) => Response::new(500, e.to_string()),
+                }
+            }
+            ("GET", "/health") => match serde_json::to_string(&supervisor::tasks().health()) {
+                Ok(json) => Response::new(200, json),
+                Err(e) => Response::new(500, e.to_string()),
+            },
+            ("GET", "/edge") => {
+                let report = mempool::inclusion()
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .report();
+                match serde_json::to_string(&report) {
+                    Ok(json) => Response::new(200, json),
+                    Err(e) => Response::new(500, e.to_string()),
+                }
+            }
+            ("GET", "/explain") => Self::explain(request),
+            ("GET", "/competition") => {
+                match serde_json::to_string(&postmortem::competition().losses_by_contract()) {
+                    Ok(json) => Response::new(200, json),
+                    Err(e) => Response::new(500, e.to_string()),
+                }
+            }
+            ("GET", "/approvals") => {
+                match serde_json::to_string(&manual_approval::approval_gate().pending()) {
+                    Ok(json) => Response::new(200, json),
+                    Err(e) => Response::new(500, e.to_string()),
+                }
+            }
+            ("POST", "/approve") => Self::decide(request, true),
+            ("POST", "/reject") => Self::decide(request, false),
+            ("POST", "/halt") => {
+                if control::kill_switch().halt() {
+                    tracing::warn!("[ADMIN] Halted by the kill switch");
+                    Response::new(200, "Halted")
+                } else {
+                    Response::new(200, "Already halted")
+                }
+            }
+            ("POST", "/resume") => {
+                if control::kill_switch().resume() {
+                    tracing::warn!("[ADMIN] Resumed by the kill switch");
+                    Response::new(200, "Resumed")
+                } else {
+                    Response::new(200, "Not halted")
+                }
+            }
+            ("GET", "/log-filter") => match &self.log_filter {
+                Some(filter) => Response::new(200, filter.current()),
+                None => Response::new(404, "Log filter reloading is not enabled"),
+            },
+            ("PUT", "/log-filter") => self.set_log_filter(request),
+            (_, "/metrics")
+            | (_, "/health")
+            | (_, "/edge")
+            | (_, "/explain")
+            | (_, "/competition")
+            | (_, "/approvals")
+            | (_, "/approve")
+            | (_, "/reject")
+            | (_, "/halt")
+            | (_, "/resume")
+            | (_, "/log-filter") => Response::new(405, "Method not allowed"),
+            _ => Response::new(404, "Not found"),
+        }
+    }
+
+    fn explain(request: &Request) -> Response {
+        let tx = match request
+            .query
+            .get("tx")
+            .map(|s| s.parse::<ethers::types::TxHash>())
+        {
+            Some(Ok(tx)) => tx,
+            _ => return Response::new(400, "Invalid tx"),
+        };
+        match explain::explanations().get(&tx) {
+            Some(explanation) => match serde_json::to_string(&explanation) {
+                Ok(json) => Response::new(200, json),
+                Err(e) => Response::new(500, e.to_string()),
+            },
+            None => Response::new(404, "No report for that transaction"),
+        }
+    }
+
+    fn decide(request: &Request, approve: bool) -> Response {
+        let id = match request.query.get("id").map(|s| s.parse::<u64>()) {
+            Some(Ok(id)) => id,
+            _ => return Response::new(400, "Invalid id"),
+        };
+        match manual_approval::approval_gate().decide(id, approve) {
+            Some(approval) => {
+                tracing::info!(
+                    "[ADMIN] Sandwich #{} {}",
+                    id,
+                    if approve { "approved" } else { "rejected" }
+                );
+                match serde_json::to_string(&approval) {
+                    Ok(json) => Response::new(200, json),
+                    Err(e) => Response::new(500, e.to_string()),
+                }
+            }
+            None => Response::new(404, "No pending sandwich with that id"),
+        }
+    }
+
+    fn set_log_filter(&self, request: &Request) -> Response {
+        let filter = match &self.log_filter {
+            Some(f) => f.clone(),
+            None => return Response::new(404, "Log filter reloading is not enabled"),
+        };
+        let secs = match request.query.get("secs").map(|s| s.parse::<u64>()) {
+            Some(Ok(secs)) => Some(secs),
+            Some(Err(_)) => return Response::new(400, "Invalid secs"),
+            None => None,
+        };
+        let previous = filter.current();
+        if let Err(e) = filter.set(&request.body) {
+            return Response::new(400, format!("{:?}", e));
+        }
+        let generation = self.filter_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        tracing::info!(
+            "[ADMIN] Log filter set to \"{}\" (was \"{}\")",
+            request.body,
+            previous
+        );
+
+        // Restore the previous filter later, unless it's been changed since
+        if let Some(secs) = secs {
+            let generations = self.filter_generation.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(secs)).await;
+                if generations.load(Ordering::SeqCst) == generation {
+                    match filter.set(&previous) {
+                        Ok(()) => tracing::info!("[ADMIN] Log filter restored to \"{}\"", previous),
+                        Err(e) => tracing::warn!("[ADMIN] Failed to restore log filter: {:?}", e),
+                    }
+                }
+            });
+        }
+        Response::new(200, filter.current())
+    }
+
+    async fn serve_connection(&self, mut stream: TcpStream) -> Result<()> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let len = loop {
+            if let Some(len) = request_len(&buf) {
+                break len;
+            }
+            if buf.len() > MAX_REQUEST_BYTES {
+                eyre::bail!("Request too large");
+            }
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                eyre::bail!("Connection closed mid-request");
+            }
+            buf.extend_from_slice(&chunk[..read]);
+        };
+        let response = match Request::parse(&buf[..len]) {
+            Ok(request) => self.handle(&request),
+            Err(e) => Response::new(400, e.to_string()),
+        };
+        stream.write_all(&response.to_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Serves the admin API on `addr` until it can't bind
+    pub async fn serve(self: Arc<Self>, addr: SocketAddr) -> Result<()> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to bind admin API to {}: {:?}", addr, e))?;
+        tracing::info!("[ADMIN] Listening on {}", addr);
+        loop {
+            // A failed accept, like running out of file descriptors, only loses that connection
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("[ADMIN] Failed to accept a connection: {:?}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let admin = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = admin.serve_connection(stream).await {
+                    tracing::debug!("[ADMIN] {:?}", e);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Inventory Allocation
+//!
+//! Sandwiches targeting the same block all fund their frontruns from the executor's WETH, but
+//! each one reads the same on-chain balance. Sized first-come-first-served, they overcommit the
+//! inventory and whichever lands last reverts. The [InventoryAllocator] reserves the WETH of each
+//! bundle shipped for a block, and sizes each new opportunity against what's left.
+//!
+//! A shipped bundle is already signed, so it can't be resized, only displaced. When a new
+//! opportunity would make more with the inventory held by lower return bundles than they do, those
+//! bundles are superseded and their WETH goes to the new one, maximizing the block's total
+//! expected revenue.
+
+use ethers::prelude::*;
+
+use crate::{conflicts::ClaimHandle, numeric};
+
+/// WETH reserved by a shipped bundle
+#[derive(Debug, Clone)]
+pub struct Reservation {
+    /// The frontrun input
+    pub weth_in: U256,
+    /// The expected revenue
+    pub revenue: U256,
+    /// The bundle's claim, superseded if it's displaced
+    pub handle: ClaimHandle,
+}
+
+/// How much WETH a new opportunity gets
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    /// The frontrun input
+    pub weth_in: U256,
+    /// The expected revenue at that input
+    pub revenue: U256,
+    /// The claims of the bundles displaced to fund it
+    pub displaced: Vec<ClaimHandle>,
+}
+
+/// Reserves the executor's WETH inventory across the bundles of a block
+#[derive(Debug, Default)]
+pub struct InventoryAllocator {
+    target_block: U64,
+    reservations: Vec<Reservation>,
+}
+
+impl InventoryAllocator {
+    /// Creates an allocator without reservations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops reservations for earlier blocks and of superseded bundles
+    fn prune(&mut self, target_block: U64) {
+        if target_block != self.target_block {
+            self.target_block = target_block;
+            self.reservations.clear();
+        }
+        self.reservations.retain(|r| !r.handle.is_superseded());
+    }
+
+    /// The WETH reserved for `target_block`
+    pub fn reserved(&mut self, target_block: U64) -> U256 {
+        self.prune(target_block);
+        self.reservations
+            .iter()
+            .fold(U256::zero(), |sum, r| sum.saturating_add(r.weth_in))
+    }
+
+    /// Sizes a new opportunity wanting up to `max_weth_in` out of `inventory`
+    ///
+    /// Reservations are considered for displacement from the lowest return on capital up, and the
+    /// plan making the most total revenue is returned. Returns `None` if no plan makes revenue.
+    pub fn allocate(
+        &mut self,
+        target_block: U64,
+        inventory: U256,
+        max_weth_in: U256,
+        revenue_at: impl Fn(U256) -> U256,
+    ) -> Option<Allocation> {
+        let reserved = self.reserved(target_block);
+        let mut order: Vec<usize> = (0..self.reservations.len()).collect();
+        order.sort_by_key(|&i| {
+            let r = &self.reservations[i];
+            numeric::return_on_capital(&r.revenue, &r.weth_in)
+        });
+
+        let mut free = inventory.saturating_sub(reserved);
+        let mut displaced_revenue = U256::zero();
+        let mut best: Option<(U256, Allocation)> = None;
+        for displace in 0..=order.len() {
+            if displace > 0 {
+                let r = &self.reservations[order[displace - 1]];
+                free = free.saturating_add(r.weth_in);
+                displaced_revenue = displaced_revenue.saturating_add(r.revenue);
+            }
+            let weth_in = free.min(max_weth_in);
+            let revenue = revenue_at(weth_in);
+            if revenue.is_zero() || revenue <= displaced_revenue {
+                continue;
+            }
+
+            // Gain over keeping every reservation, only strictly better plans displace more
+            let gain = revenue - displaced_revenue;
+            if best.as_ref().map_or(true, |(g, _)| gain > *g) {
+                best = Some((
+                    gain,
+                    Allocation {
+                        weth_in,
+                        revenue,
+                        displaced: order[..displace]
+                            .iter()
+                            .map(|&i| self.reservations[i].handle.clone())
+                            .collect(),
+                    },
+                ));
+            }
+            if weth_in == max_weth_in {
+                break;
+            }
+        }
+        best.map(|(_, allocation)| allocation)
+    }
+
+    /// Reserves an allocation for a shipped bundle, superseding the bundles it displaced
+    ///
+    /// Returns how many bundles were displaced.
+    pub fn commit(
+        &mut self,
+        target_block: U64,
+        allocation: &Allocation,
+        handle: ClaimHandle,
+    ) -> usize {
+        for handle in &allocation.displaced {
+            handle.supersede();
+        }
+        self.prune(target_block);
+        self.reservations.push(Reservation {
+            weth_in: allocation.weth_in,
+            revenue: allocation.revenue,
+            handle,
+        });
+        allocation.displaced.len()
+    }
+}
@@ -0,0 +1,210 @@
+//! Pending Victim Approvals
+//!
+//! A victim spending a token through a router needs an allowance, and a first-time trader often
+//! sends the `approve` and the swap back to back. While the approval is pending, the swap can't be
+//! mined before it, and a bundle carrying the swap alone either has a nonce gap or reverts in the
+//! router's `transferFrom`, even if it simulated fine against state where the approval had landed.
+//!
+//! Pending `approve` calls are tracked in [PendingApprovals]. A victim depending on one gets the
+//! approval included right before it in the bundle when the approval can pay the target block's
+//! base fee, and is skipped otherwise. ETH-in V2 swaps never need an allowance, so this only
+//! guards strategies whose victims spend tokens, like JIT liquidity on WETH-in V3 swaps.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The `approve(address,uint256)` selector
+pub const APPROVE_SELECTOR: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+
+/// How long a pending approval is remembered, in seconds
+pub const PENDING_APPROVAL_TTL_SECS: u64 = 300;
+
+/// The most pending approvals remembered
+pub const PENDING_APPROVAL_CAPACITY: usize = 4_096;
+
+/// A pending `approve` call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingApproval {
+    /// The token approved
+    pub token: Address,
+    /// The spender approved
+    pub spender: Address,
+    /// The allowance granted
+    pub amount: U256,
+    /// The approval transaction
+    pub tx: Transaction,
+    /// When the approval was seen
+    pub seen_at: Instant,
+}
+
+impl PendingApproval {
+    /// Decodes a pending `approve` call, `None` if the transaction isn't one
+    pub fn decode(tx: &Transaction, seen_at: Instant) -> Option<Self> {
+        let data = tx.input.as_ref();
+        if data.len() != 4 + 2 * 32 || data[..4] != APPROVE_SELECTOR {
+            return None;
+        }
+        Some(Self {
+            token: tx.to?,
+            spender: Address::from_slice(&data[16..36]),
+            amount: U256::from_big_endian(&data[36..68]),
+            tx: tx.clone(),
+            seen_at,
+        })
+    }
+
+    /// The most the approval pays per gas
+    pub fn max_fee_per_gas(&self) -> U256 {
+        self.tx
+            .max_fee_per_gas
+            .or(self.tx.gas_price)
+            .unwrap_or_default()
+    }
+}
+
+/// Recently seen pending approvals, by owner, token and spender
+#[derive(Debug, Default)]
+pub struct PendingApprovals {
+    approvals: HashMap<(Address, Address, Address), PendingApproval>,
+    order: VecDeque<(Address, Address, Address)>,
+}
+
+impl PendingApprovals {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a transaction if it's an `approve` call, returning whether it was
+    ///
+    /// A later approval from the same owner for the same token and spender replaces the earlier
+    /// one, like a fee bump would.
+    pub fn observe(&mut self, tx: &Transaction, now: Instant) -> bool {
+        let approval = match PendingApproval::decode(tx, now) {
+            Some(a) => a,
+            None => return false,
+        };
+        self.expire(now);
+        let key = (tx.from, approval.token, approval.spender);
+        if self.approvals.insert(key, approval).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > PENDING_APPROVAL_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.approvals.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Forgets approvals older than [PENDING_APPROVAL_TTL_SECS]
+    fn expire(&mut self, now: Instant) {
+        let ttl = Duration::from_secs(PENDING_APPROVAL_TTL_SECS);
+        let approvals = &mut self.approvals;
+        self.order.retain(|key| {
+            let live = matches!(
+                approvals.get(key),
+                Some(a) if now.saturating_duration_since(a.seen_at) < ttl
+            );
+            if !live {
+                approvals.remove(key);
+            }
+            live
+        });
+    }
+
+    /// The pending approval `owner` sent for `spender` to pull `token`, if any
+    pub fn pending(
+        &self,
+        owner: Address,
+        token: Address,
+        spender: Address,
+    ) -> Option<&PendingApproval> {
+        self.approvals.get(&(owner, token, spender))
+    }
+
+    /// The number of pending approvals remembered
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Whether no approval is pending
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+/// Returns the process-wide pending approvals
+pub fn pending_approvals() -> &'static Mutex<PendingApprovals> {
+    static APPROVALS: OnceLock<Mutex<PendingApprovals>> = OnceLock::new();
+    APPROVALS.get_or_init(|| Mutex::new(PendingApprovals::new()))
+}
+
+/// Records a pending transaction into [pending_approvals] if it's an `approve` call
+///
+/// Only the selector is compared for other transactions, so every candidate can be observed.
+pub fn observe_approval(tx: &Transaction) -> bool {
+    if !tx.input.as_ref().starts_with(&APPROVE_SELECTOR) {
+        return false;
+    }
+    pending_approvals()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .observe(tx, Instant::now())
+}
+
+/// What to do about a victim's pending approval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApprovalDependency {
+    /// The victim doesn't depend on a pending approval
+    None,
+    /// The approval goes in the bundle right before the victim
+    Include(Transaction),
+    /// The victim can't be bundled
+    Skip(String),
+}
+
+/// Decides how to bundle a victim spending `amount` of `token` through `spender`
+///
+/// A pending approval from the victim at a lower nonce must land first. It's included if it grants
+/// enough, comes right before the victim and pays at least `base_fee`, the target block's base fee.
+pub fn check_approval(
+    approvals: &PendingApprovals,
+    victim: &Transaction,
+    token: Address,
+    spender: Address,
+    amount: U256,
+    base_fee: U256,
+) -> ApprovalDependency {
+    let approval = match approvals.pending(victim.from, token, spender) {
+        Some(a) if a.tx.nonce < victim.nonce => a,
+        _ => return ApprovalDependency::None,
+    };
+    if approval.amount < amount {
+        return ApprovalDependency::Skip(format!(
+            "Pending approval {:?} grants {}, less than the {} swapped",
+            approval.tx.hash, approval.amount, amount
+        ));
+    }
+    if approval.tx.nonce + 1 != victim.nonce {
+        return ApprovalDependency::Skip(format!(
+            "Pending approval {:?} isn't the victim's previous nonce",
+            approval.tx.hash
+        ));
+    }
+    if approval.max_fee_per_gas() < base_fee {
+        return ApprovalDependency::Skip(format!(
+            "Pending approval {:?} pays {} per gas, under the {} base fee",
+            approval.tx.hash,
+            approval.max_fee_per_gas(),
+            base_fee
+        ));
+    }
+    ApprovalDependency::Include(approval.tx.clone())
+}
@@ -0,0 +1,116 @@
+//! Executor Token Approvals
+//!
+//! Some spenders pull tokens from the executor contract, like the Aave pool collecting a
+//! liquidation's debt repayment. Missing approvals only surface as simulation reverts, so they're checked at startup
+//! and set in a single batched executor call.
+
+use std::{str::FromStr, sync::Arc};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{abi::ERC20, control::send_executor_call, transactions::TxFactory};
+
+/// The Aave V3 mainnet pool
+pub const AAVE_V3_POOL: &str = "0x87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2";
+
+/// The gas limit for each approval in a batch
+pub const APPROVAL_GAS_LIMIT: u64 = 60_000;
+
+/// An ERC20 allowance the executor contract grants a spender
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Approval {
+    /// The approved token
+    pub token: Address,
+    /// The spender
+    pub spender: Address,
+}
+
+impl FromStr for Approval {
+    type Err = eyre::Report;
+
+    /// Parses `token:spender`
+    fn from_str(s: &str) -> Result<Self> {
+        let (token, spender) = s
+            .split_once(':')
+            .ok_or_else(|| eyre::eyre!("Expected token:spender, got \"{}\"", s))?;
+        let parse = |value: &str| {
+            Address::from_str(value.trim())
+                .map_err(|_| eyre::eyre!("Invalid address \"{}\"", value))
+        };
+        Ok(Self {
+            token: parse(token)?,
+            spender: parse(spender)?,
+        })
+    }
+}
+
+/// A comma-separated list of approvals
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Approvals(pub Vec<Approval>);
+
+impl FromStr for Approvals {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(Approval::from_str)
+            .collect::<Result<Vec<_>>>()
+            .map(Approvals)
+    }
+}
+
+/// Returns every approval the executor needs for the configuration, deduplicated
+pub fn required_approvals(extra: &Approvals) -> Vec<Approval> {
+    let mut approvals = extra.0.clone();
+    approvals.sort();
+    approvals.dedup();
+    approvals
+}
+
+/// Returns the approvals `owner` hasn't granted
+///
+/// An allowance below half of the max counts as missing, since max approvals are only decremented
+/// by tokens that don't special-case them.
+pub async fn missing_approvals<M: Middleware + 'static>(
+    client: Arc<M>,
+    owner: Address,
+    approvals: &[Approval],
+) -> Result<Vec<Approval>> {
+    let mut missing = Vec::new();
+    for approval in approvals {
+        let allowance = ERC20::new(approval.token, client.clone())
+            .allowance(owner, approval.spender)
+            .call()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch allowance: {:?}", e))?;
+        if allowance < U256::MAX / 2 {
+            missing.push(*approval);
+        }
+    }
+    Ok(missing)
+}
+
+/// Sets any missing approvals through the executor, returning the approval transaction hash
+///
+/// Returns `None` if every approval is already in place.
+pub async fn ensure_approvals<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: &TxFactory,
+    approvals: &[Approval],
+) -> Result<Option<TxHash>> {
+    let missing =
+        missing_approvals(client.clone(), tx_factory.executor.address(), approvals).await?;
+    if missing.is_empty() {
+        return Ok(None);
+    }
+
+    let data = tx_factory.executor.encode_approvals(&missing)?;
+    let gas_limit = APPROVAL_GAS_LIMIT * missing.len() as u64 + 50_000;
+    send_executor_call(client, tx_factory, data, gas_limit)
+        .await
+        .map(Some)
+}
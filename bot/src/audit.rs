@@ -0,0 +1,139 @@
+//! Submission Audit
+//!
+//! Every bundle submission is appended to an [AuditLog] as a json line: the relay, the target
+//! block, the full signed transactions and the relay's response or error. Records can be signed
+//! with the searcher key, so the log can be shown to a relay in a dispute and checked with
+//! [AuditEntry::verify].
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::opportunity::unix_millis;
+
+/// A bundle submitted to a relay
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubmissionRecord {
+    /// When the relay responded, in milliseconds since the unix epoch
+    pub timestamp: u64,
+    /// The relay the bundle was sent to
+    pub relay: String,
+    /// The block the bundle targets
+    pub target_block: U64,
+    /// The bundle's signed transactions, in order
+    pub transactions: Vec<Bytes>,
+    /// Whether the relay accepted the bundle
+    pub accepted: bool,
+    /// The relay's response, or the error if it was refused
+    pub response: String,
+}
+
+impl SubmissionRecord {
+    /// Records a submission the relay answered with `response`
+    pub fn new<T: std::fmt::Debug, E: std::fmt::Debug>(
+        relay: &str,
+        target_block: U64,
+        transactions: &[Bytes],
+        response: &std::result::Result<T, E>,
+    ) -> Self {
+        let (accepted, response) = match response {
+            Ok(r) => (true, format!("{:?}", r)),
+            Err(e) => (false, format!("{:?}", e)),
+        };
+        Self {
+            timestamp: unix_millis(),
+            relay: relay.to_string(),
+            target_block,
+            transactions: transactions.to_vec(),
+            accepted,
+            response,
+        }
+    }
+}
+
+/// A line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The submission
+    pub record: SubmissionRecord,
+    /// The signer's EIP-191 signature of the record's json, if the log is signed
+    pub signature: Option<Signature>,
+}
+
+impl AuditEntry {
+    /// Whether the entry is signed by `signer`
+    pub fn verify(&self, signer: Address) -> bool {
+        let (signature, message) = match (&self.signature, serde_json::to_vec(&self.record)) {
+            (Some(s), Ok(m)) => (s, m),
+            _ => return false,
+        };
+        signature.verify(message, signer).is_ok()
+    }
+}
+
+/// An append-only json lines file of bundle submissions
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    file: Mutex<File>,
+    signer: Option<LocalWallet>,
+}
+
+impl AuditLog {
+    /// Opens a log, creating the file if it doesn't exist, signing entries with `signer` if set
+    pub fn open(path: impl AsRef<Path>, signer: Option<LocalWallet>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| eyre::eyre!("Failed to open audit log {:?}: {:?}", path, e))?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            signer,
+        })
+    }
+
+    /// The path of the log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a submission, signing it if the log has a signer
+    pub async fn append(&self, record: SubmissionRecord) -> Result<()> {
+        let signature = match &self.signer {
+            Some(signer) => Some(
+                signer
+                    .sign_message(serde_json::to_vec(&record)?)
+                    .await
+                    .map_err(|e| eyre::eyre!("Failed to sign audit record: {:?}", e))?,
+            ),
+            None => None,
+        };
+        let mut line = serde_json::to_string(&AuditEntry { record, signature })?;
+        line.push('\n');
+        self.file
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Loads every entry in a log
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<AuditEntry>> {
+        let file = File::open(path.as_ref())?;
+        BufReader::new(file)
+            .lines()
+            .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+}
@@ -0,0 +1,103 @@
+//! Gas Reserve Monitoring
+//!
+//! Every submitted bundle pays gas from the searcher wallet. Once its ETH runs low, bundles start
+//! failing simulation or landing underfunded, so a background watcher pauses submission below a
+//! threshold and resumes it once the wallet is topped up.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+
+use crate::{metrics, notify::Notifier};
+
+/// The default searcher balance below which submission pauses, 0.05 ether
+pub const DEFAULT_GAS_RESERVE: u128 = 50_000_000_000_000_000;
+
+/// The default balance check interval, one slot
+pub const DEFAULT_BALANCE_CHECK_SECS: u64 = 12;
+
+/// A change in whether submission is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseTransition {
+    /// The balance fell below the reserve
+    Paused,
+    /// The balance was topped back up to the reserve
+    Resumed,
+}
+
+/// Returns the transition, if any, for a balance check
+pub fn transition(balance: U256, reserve: U256, paused: bool) -> Option<PauseTransition> {
+    match (paused, balance < reserve) {
+        (false, true) => Some(PauseTransition::Paused),
+        (true, false) => Some(PauseTransition::Resumed),
+        _ => None,
+    }
+}
+
+/// A shared switch pausing submission
+#[derive(Debug, Clone, Default)]
+pub struct PauseSwitch(Arc<AtomicBool>);
+
+impl PauseSwitch {
+    /// Whether submission is paused
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes submission
+    pub fn set(&self, paused: bool) {
+        self.0.store(paused, Ordering::Relaxed);
+    }
+}
+
+/// Checks `wallet`'s balance every `interval`, flipping `switch` and alerting when it crosses `reserve`
+pub async fn watch_gas_reserve<M: Middleware + 'static>(
+    client: Arc<M>,
+    wallet: Address,
+    reserve: U256,
+    interval: Duration,
+    switch: PauseSwitch,
+    notifier: Notifier,
+) -> Result<()> {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let balance = match client.get_balance(wallet, None).await {
+            Ok(b) => b,
+            Err(e) => {
+                tracing::debug!("[BALANCE] Failed to fetch searcher balance: {:?}", e);
+                continue;
+            }
+        };
+        match transition(balance, reserve, switch.is_paused()) {
+            Some(PauseTransition::Paused) => {
+                switch.set(true);
+                metrics::increment("gas_reserve_paused");
+                notifier
+                    .notify(&format!(
+                        "Searcher {:?} balance {} is below the gas reserve {}, pausing submission",
+                        wallet, balance, reserve
+                    ))
+                    .await;
+            }
+            Some(PauseTransition::Resumed) => {
+                switch.set(false);
+                metrics::increment("gas_reserve_resumed");
+                notifier
+                    .notify(&format!(
+                        "Searcher {:?} topped up to {}, resuming submission",
+                        wallet, balance
+                    ))
+                    .await;
+            }
+            None => {}
+        }
+    }
+}
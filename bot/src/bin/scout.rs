@@ -0,0 +1,120 @@
+use eyre::Result;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::Semaphore;
+
+use ethers::prelude::*;
+use subway_rs::{
+    clock, config, evaluate, mempool, scout, session, supervisor, telemetry, uniswap, utils,
+};
+
+/// Streams the mempool and reports its swap flow, without a wallet, relay or executor
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Configure Telemetry
+    let (subscriber, _) = telemetry::get_reloadable_subscriber("info".into());
+    telemetry::init_subscriber(subscriber);
+
+    // Only the node and the mempool sources are configured
+    let client = utils::create_websocket_client().await?;
+    let tx_sources = config::parse_env_var::<mempool::TxSources>("TX_SOURCES")?.unwrap_or_default();
+    tracing::info!("[SCOUT] Mempool sources: {:?}", tx_sources.0);
+    let sources = tx_sources
+        .0
+        .iter()
+        .map(|kind| kind.build(client.clone()))
+        .collect::<Result<Vec<_>>>()?;
+    let report_every = Duration::from_secs(
+        config::parse_env_var("SCOUT_REPORT_SECS")?
+            .unwrap_or(scout::DEFAULT_SCOUT_REPORT_SECS)
+            .max(1),
+    );
+    let min_revenue = config::parse_env_var::<u128>("SCOUT_MIN_REVENUE")?
+        .map(U256::from)
+        .unwrap_or_default();
+    let max_inflight = config::parse_env_var("SCOUT_MAX_INFLIGHT")?
+        .unwrap_or(scout::DEFAULT_SCOUT_MAX_INFLIGHT)
+        .max(1);
+
+    // Other chains name their own router, wrapped native token and V2 deployment
+    let default = scout::ScoutChain::default();
+    let chain = Arc::new(scout::ScoutChain {
+        router: config::parse_env_var("SCOUT_ROUTER")?.unwrap_or(default.router),
+        weth: config::parse_env_var("SCOUT_WETH")?.unwrap_or(default.weth),
+        v2: uniswap::V2Deployment {
+            factory: config::parse_env_var("SCOUT_FACTORY")?.unwrap_or(default.v2.factory),
+            init_code_hash: config::parse_env_var("SCOUT_INIT_CODE_HASH")?
+                .unwrap_or(default.v2.init_code_hash),
+        },
+    });
+    tracing::info!("[SCOUT] Chain: {:?}", chain);
+
+    // Drop transactions delivered after they were mined, like the bot
+    {
+        let client = client.clone();
+        supervisor::supervise_task("mined_txs", None, move |_| {
+            mempool::watch_mined(client.clone())
+        });
+    }
+
+    let mut candidates = mempool::merge_sources(
+        sources,
+        mempool::DEDUP_CAPACITY,
+        Duration::from_secs(mempool::DEFAULT_SOURCE_STALL_SECS),
+    );
+    let router = chain.router;
+    let state = Arc::new(evaluate::ProviderState::new(client.clone()));
+    let inflight = Arc::new(Semaphore::new(max_inflight));
+    let stats = Arc::new(Mutex::new(scout::FlowStats::new()));
+
+    let mut report = tokio::time::interval(report_every);
+    report.tick().await;
+    let shutdown = session::shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            candidate = candidates.recv() => {
+                let tx = match candidate {
+                    Some(c) => c.tx,
+                    None => eyre::bail!("Every mempool source stopped"),
+                };
+                stats
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .record_seen(&tx, router);
+                if tx.to != Some(router) {
+                    continue;
+                }
+
+                // Price swaps concurrently, reserve reads shouldn't hold up the stream, but only so
+                // many at once
+                let permit = inflight.clone().acquire_owned().await?;
+                let chain = chain.clone();
+                let state = state.clone();
+                let stats = stats.clone();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    let now = clock::unix_secs();
+                    if let Some(flow) =
+                        scout::analyze_swap(&tx, &chain, now, &*state, min_revenue).await
+                    {
+                        tracing::debug!("[SCOUT] {:?}", flow);
+                        stats.lock().unwrap_or_else(|e| e.into_inner()).record(&flow);
+                    }
+                });
+            }
+            _ = report.tick() => {
+                stats.lock().unwrap_or_else(|e| e.into_inner()).log();
+            }
+            signal = &mut shutdown => {
+                // Print the final stats for offline analysis
+                let stats = stats.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                stats.log();
+                println!("{}", serde_json::to_string(&stats)?);
+                return signal;
+            }
+        }
+    }
+}
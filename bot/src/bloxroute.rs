@@ -0,0 +1,242 @@
+//! bloXroute BDN
+//!
+//! Streams pending transactions from the bloXroute Blockchain Distribution Network and submits
+//! transactions and bundles through the bloXroute cloud API. BDN customers see transactions well
+//! before they reach a regular node's mempool.
+
+use ethers::prelude::*;
+use eyre::Result;
+use futures::{future::BoxFuture, SinkExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, http::HeaderValue, Message};
+
+use crate::{envelope, instances, mempool::TxSource, timing::BundleRelay};
+
+/// The bloXroute cloud websocket gateway
+pub const BLOXROUTE_WS_URL: &str = "wss://api.blxrbdn.com/ws";
+
+/// The bloXroute cloud API
+pub const BLOXROUTE_API_URL: &str = "https://api.blxrbdn.com";
+
+/// Returns the bloXroute account authorization header
+pub fn get_bloxroute_auth_header() -> Result<String> {
+    instances::var("BLOXROUTE_AUTH_HEADER")
+        .map_err(|_| eyre::eyre!("Required environment variable \"BLOXROUTE_AUTH_HEADER\" not set"))
+}
+
+/// Returns the `newTxs` subscription request
+pub fn subscribe_request() -> String {
+    serde_json::json!({
+        "id": 1,
+        "method": "subscribe",
+        "params": ["newTxs", { "include": ["tx_hash", "tx_contents"] }],
+    })
+    .to_string()
+}
+
+/// A `newTxs` subscription result
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewTx {
+    tx_contents: serde_json::Value,
+}
+
+/// The params of a subscription notification
+#[derive(Debug, Clone, Deserialize)]
+struct NotificationParams {
+    result: NewTx,
+}
+
+/// A subscription notification
+#[derive(Debug, Clone, Deserialize)]
+struct Notification {
+    params: Option<NotificationParams>,
+}
+
+/// Parses a gateway message, returning the transaction if it's a `newTxs` notification
+///
+/// Other messages, like the subscription acknowledgement, return `None`.
+pub fn parse_notification(message: &str) -> Result<Option<Transaction>> {
+    let notification: Notification = serde_json::from_str(message)?;
+    notification
+        .params
+        .map(|p| envelope::from_json(p.result.tx_contents))
+        .transpose()
+}
+
+/// Pending transactions streamed from the bloXroute gateway
+#[derive(Debug, Clone)]
+pub struct BloxrouteSource {
+    /// The gateway websocket url
+    pub url: String,
+    /// The account authorization header
+    pub auth_header: String,
+}
+
+impl BloxrouteSource {
+    /// Creates a source for the cloud gateway, reading the auth header from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            url: BLOXROUTE_WS_URL.to_string(),
+            auth_header: get_bloxroute_auth_header()?,
+        })
+    }
+}
+
+impl TxSource for BloxrouteSource {
+    fn name(&self) -> &'static str {
+        "bloxroute"
+    }
+
+    fn run(&self, sender: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>> {
+        let source = self.clone();
+        Box::pin(async move {
+            let mut request = source.url.as_str().into_client_request()?;
+            request
+                .headers_mut()
+                .insert("Authorization", HeaderValue::from_str(&source.auth_header)?);
+            let (mut socket, _) = tokio_tungstenite::connect_async(request)
+                .await
+                .map_err(|e| eyre::eyre!("bloXroute connection error: {:?}", e))?;
+            socket.send(Message::Text(subscribe_request())).await?;
+
+            while let Some(message) = socket.next().await {
+                let text = match message? {
+                    Message::Text(text) => text,
+                    Message::Close(_) => break,
+                    _ => continue,
+                };
+                match parse_notification(&text) {
+                    Ok(Some(tx)) => {
+                        if sender.send(tx).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::debug!("Failed to parse bloXroute message: {:?}", e),
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A bloXroute API request
+#[derive(Debug, Clone, Serialize)]
+struct ApiRequest<P> {
+    id: u64,
+    method: &'static str,
+    params: P,
+}
+
+/// `blxr_tx` params
+#[derive(Debug, Clone, Serialize)]
+struct TxParams {
+    transaction: String,
+}
+
+/// `blxr_submit_bundle` params
+#[derive(Debug, Clone, Serialize)]
+struct BundleParams {
+    transaction: Vec<String>,
+    block_number: String,
+    mev_builders: serde_json::Value,
+}
+
+/// Encodes a signed transaction the way the API expects, as hex without the `0x` prefix
+fn raw_hex(tx: &Bytes) -> String {
+    hex::encode(tx.as_ref())
+}
+
+/// Submits transactions and bundles through the bloXroute cloud API
+#[derive(Debug, Clone)]
+pub struct BloxrouteClient {
+    /// The http client
+    pub http: reqwest::Client,
+    /// The API url
+    pub url: String,
+    /// The account authorization header
+    pub auth_header: String,
+}
+
+impl BloxrouteClient {
+    /// Creates a client for the cloud API, reading the auth header from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            http: reqwest::Client::new(),
+            url: BLOXROUTE_API_URL.to_string(),
+            auth_header: get_bloxroute_auth_header()?,
+        })
+    }
+
+    /// Posts an API request, returning the result
+    async fn request<P: Serialize>(
+        &self,
+        method: &'static str,
+        params: P,
+    ) -> Result<serde_json::Value> {
+        let response: serde_json::Value = self
+            .http
+            .post(&self.url)
+            .header("Authorization", &self.auth_header)
+            .json(&ApiRequest {
+                id: 1,
+                method,
+                params,
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            eyre::bail!("bloXroute {} error: {}", method, error);
+        }
+        Ok(response
+            .get("result")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null))
+    }
+
+    /// Propagates a signed transaction over the BDN with `blxr_tx`
+    pub async fn send_transaction(&self, signed_tx: &Bytes) -> Result<serde_json::Value> {
+        self.request(
+            "blxr_tx",
+            TxParams {
+                transaction: raw_hex(signed_tx),
+            },
+        )
+        .await
+    }
+
+    /// Submits a bundle of signed transactions to every builder bloXroute forwards to
+    pub async fn send_bundle(
+        &self,
+        signed_txs: &[Bytes],
+        block_number: U64,
+    ) -> Result<serde_json::Value> {
+        self.request(
+            "blxr_submit_bundle",
+            BundleParams {
+                transaction: signed_txs.iter().map(raw_hex).collect(),
+                block_number: format!("{:#x}", block_number),
+                mev_builders: serde_json::json!({ "all": "" }),
+            },
+        )
+        .await
+    }
+}
+
+impl BundleRelay for BloxrouteClient {
+    fn name(&self) -> &'static str {
+        "bloxroute"
+    }
+
+    fn submit_bundle<'a>(
+        &'a self,
+        signed_txs: &'a [Bytes],
+        block_number: U64,
+    ) -> BoxFuture<'a, Result<serde_json::Value>> {
+        Box::pin(self.send_bundle(signed_txs, block_number))
+    }
+}
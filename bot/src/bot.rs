@@ -0,0 +1,3677 @@
+//! Assembles the whole sandwich pipeline from its sources, strategies, relays and risk limits
+//!
+//! [BotBuilder] starts from a [Config], usually read from the environment, and each `with_`
+//! method swaps in part of the pipeline, so custom bots can be composed without forking the binary:
+//!
+//! ```no_run
+//! # async fn run() -> eyre::Result<()> {
+//! use subway_rs::{bot::*, jit::JitConfig};
+//!
+//! BotBuilder::from_env()?
+//!     .with_strategy(Strategy::Jit(JitConfig::default()))
+//!     .build()
+//!     .await?
+//!     .run()
+//!     .await
+//! # }
+//! ```
+//!
+//! Keys and node endpoints not given with [BotBuilder::with_wallets] and
+//! [BotBuilder::with_endpoints] are read from the environment when the bot is built, see
+//! [crate::utils].
+
+use ethers::prelude::*;
+use eyre::Result;
+use reqwest::Url;
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    abi, accounting, allocation, allowances, approvals, audit, balance,
+    bloxroute::BloxrouteClient,
+    chain,
+    chainbound::EchoClient,
+    clock,
+    config::Config,
+    conflicts, control, envelope, ev, evaluate, executor, explain, gas, honeypot, instances, jit,
+    launches, liquidations, manual_approval, mempool, metrics, nonces, notify, numeric, observer,
+    opportunity, orders, pipeline, postmortem, profiles, relayer, reserves, risk, safety, session,
+    simulation, skim, snapshot, spam, state, strategy, summary, supervisor,
+    timing::{self, BundleRelay},
+    transactions, transport, uniswap, uniswap_v3, utils,
+};
+
+#[cfg(feature = "scripting")]
+use crate::scripting;
+#[cfg(feature = "grpc")]
+use crate::{grpc, plans};
+
+/// The relay bundles are simulated and sent through unless another is configured
+pub const FLASHBOTS_RELAY_URL: &str = "https://relay.flashbots.net";
+
+/// The relays bundles are simulated and submitted through
+#[derive(Debug, Clone)]
+pub struct Relays {
+    /// The Flashbots-compatible relay every bundle is simulated and sent through
+    pub flashbots: Url,
+    /// Relays the simulated bundle is raced through after Flashbots, in order
+    pub others: Vec<Arc<dyn BundleRelay>>,
+    /// The pooled http client every relay is reached through
+    pub http: reqwest::Client,
+}
+
+impl Relays {
+    /// Submits through Flashbots only
+    pub fn flashbots() -> Result<Self> {
+        Ok(Self {
+            flashbots: Url::parse(FLASHBOTS_RELAY_URL)?,
+            others: Vec::new(),
+            http: transport::relay_http_client(&Default::default())?,
+        })
+    }
+
+    /// Also races bundles through `relay`
+    pub fn with_relay(mut self, relay: Arc<dyn BundleRelay>) -> Self {
+        self.others.push(relay);
+        self
+    }
+
+    /// Submits through Flashbots and whichever other relays are enabled in `config`
+    ///
+    /// Every relay shares one pooled http client, tuned by `config.relay_http`.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let http = transport::relay_http_client(&config.relay_http)?;
+        let mut relays = Self {
+            http: http.clone(),
+            ..Self::flashbots()?
+        };
+        if config.submit_bloxroute {
+            tracing::info!("[CONFIG] Submitting bundles through bloXroute");
+            relays = relays.with_relay(Arc::new(BloxrouteClient {
+                http: http.clone(),
+                ..BloxrouteClient::from_env()?
+            }));
+        }
+        if config.submit_echo {
+            tracing::info!("[CONFIG] Propagating bundles through Echo");
+            relays = relays.with_relay(Arc::new(EchoClient {
+                http,
+                ..EchoClient::from_env()?
+            }));
+        }
+        Ok(relays)
+    }
+}
+
+/// A strategy the bot runs, or how it sizes the sandwiches it always runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Strategy {
+    /// Size sandwiches of V2 router swaps with `optimizer`, holding each class to its profit floor
+    Sandwich {
+        /// What the frontrun size is optimized for
+        optimizer: numeric::Optimizer,
+        /// The least revenue, in wei, a return on capital optimized sandwich must make
+        min_roc_revenue: U256,
+        /// Per-class profit floors
+        profit_rules: strategy::ProfitRules,
+    },
+    /// Provide JIT liquidity to large V3 swaps
+    Jit(jit::JitConfig),
+    /// Backrun oracle updates with liquidations
+    Liquidations(liquidations::LiquidationConfig),
+}
+
+/// The limits every sandwich is checked against before it's shipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RiskLimits {
+    /// The bribe share, or refusal, for revenue large enough to invite a reorg
+    pub reorg_guard: risk::ReorgGuard,
+    /// Caps on the WETH committed to a single token
+    pub exposure: risk::ExposureLimits,
+    /// Base fee spike limits
+    pub base_fee: gas::BaseFeeLimits,
+    /// Checks that the simulated bundle pays the builder what it's priced to
+    pub builder_payment: simulation::BuilderPaymentLimits,
+    /// The largest share of the block gas limit a bundle may use, in bips
+    pub max_bundle_gas_bps: u64,
+    /// Whether frontruns larger than the inventory are downsized instead of skipped
+    pub downsize_to_inventory: bool,
+}
+
+impl RiskLimits {
+    /// Reads the limits from `config`
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            reorg_guard: config.reorg_guard,
+            exposure: config.exposure_limits,
+            base_fee: config.base_fee_limits,
+            builder_payment: config.builder_payment,
+            max_bundle_gas_bps: config.max_bundle_gas_bps,
+            downsize_to_inventory: config.downsize_to_inventory,
+        }
+    }
+}
+
+/// Composes a [Bot] from a [Config], overriding its sources, strategies, relays and risk limits
+#[derive(Debug, Default)]
+pub struct BotBuilder {
+    config: Config,
+    wallets: Option<utils::Wallets>,
+    endpoints: Option<utils::Endpoints>,
+    sources: Vec<Box<dyn mempool::TxSource>>,
+    relays: Option<Relays>,
+}
+
+impl BotBuilder {
+    /// Starts from the default [Config]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from the [Config] read from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self::new().with_config(Config::from_env()?))
+    }
+
+    /// Starts from an instance's configuration, wallets and endpoints
+    pub fn from_instance(instance: instances::Instance) -> Self {
+        Self::new()
+            .with_config(instance.config)
+            .with_wallets(instance.wallets)
+            .with_endpoints(instance.endpoints)
+    }
+
+    /// Replaces the whole configuration
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Signs with `wallets` instead of the keys in the environment
+    pub fn with_wallets(mut self, wallets: utils::Wallets) -> Self {
+        self.wallets = Some(wallets);
+        self
+    }
+
+    /// Connects to `endpoints` instead of the nodes in the environment
+    pub fn with_endpoints(mut self, endpoints: utils::Endpoints) -> Self {
+        self.endpoints = Some(endpoints);
+        self
+    }
+
+    /// Streams pending transactions from `source`
+    ///
+    /// Once any source is added the configured `tx_sources` are ignored.
+    pub fn with_source(mut self, source: Box<dyn mempool::TxSource>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Enables a strategy, or resizes the sandwich strategy
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        match strategy {
+            Strategy::Sandwich {
+                optimizer,
+                min_roc_revenue,
+                profit_rules,
+            } => {
+                self.config.optimizer = optimizer;
+                self.config.min_roc_revenue = min_roc_revenue;
+                self.config.profit_rules = profit_rules;
+            }
+            Strategy::Jit(jit) => {
+                self.config.jit = jit::JitConfig {
+                    enabled: true,
+                    ..jit
+                }
+            }
+            Strategy::Liquidations(liquidations) => {
+                self.config.liquidations = liquidations::LiquidationConfig {
+                    enabled: true,
+                    ..liquidations
+                }
+            }
+        }
+        self
+    }
+
+    /// Submits through `relays` instead of the configured ones
+    pub fn with_relays(mut self, relays: Relays) -> Self {
+        self.relays = Some(relays);
+        self
+    }
+
+    /// Checks sandwiches against `limits` instead of the configured ones
+    pub fn with_risk(mut self, limits: RiskLimits) -> Self {
+        self.config.reorg_guard = limits.reorg_guard;
+        self.config.exposure_limits = limits.exposure;
+        self.config.base_fee_limits = limits.base_fee;
+        self.config.builder_payment = limits.builder_payment;
+        self.config.max_bundle_gas_bps = limits.max_bundle_gas_bps;
+        self.config.downsize_to_inventory = limits.downsize_to_inventory;
+        self
+    }
+
+    /// The configuration the bot will be built with
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Connects to the node and sets up the executor and searcher wallets
+    pub async fn build(self) -> Result<Bot> {
+        let config = self.config;
+        let wallets = match self.wallets {
+            Some(wallets) => wallets,
+            None => utils::Wallets::from_env()?,
+        };
+        let endpoints = match self.endpoints {
+            Some(endpoints) => endpoints,
+            None => utils::Endpoints::from_env()?,
+        };
+
+        // Create the websocket clieant
+        let client = Arc::new(utils::connect_ws_provider(&endpoints.rpc_url_wss).await?);
+
+        // Read chain state over IPC from a co-located node, when one is configured
+        let node_reader: Arc<dyn state::StateReader> = match &endpoints.ipc_path {
+            Some(path) => {
+                tracing::info!("[CONFIG] Reading chain state over IPC");
+                let ipc = Arc::new(utils::get_ipc_provider(path).await?);
+                Arc::new(state::ProviderReader::new(ipc))
+            }
+            None => Arc::new(state::ProviderReader::new(client.clone())),
+        };
+
+        // Cross-check reserves against a second provider, so one bad node can't skew the math
+        let node_reader: Arc<dyn state::StateReader> = if config.reserve_consensus
+            != state::ReserveConsensus::Off
+        {
+            tracing::info!(
+                "[CONFIG] Cross-checking reserves against a second provider ({:?})",
+                config.reserve_consensus
+            );
+            let url = endpoints.consensus_rpc_url.as_deref().ok_or_else(|| {
+                eyre::eyre!("Required environment variable \"RESERVE_CONSENSUS_RPC_URL\" not set")
+            })?;
+            let consensus = Provider::<Http>::try_from(url)
+                .map_err(|_| eyre::eyre!("Invalid reserve consensus RPC URL"))?;
+            Arc::new(state::ConsensusReader::new(
+                node_reader,
+                state::ProviderReader::new(Arc::new(consensus)),
+                config.reserve_consensus,
+            ))
+        } else {
+            node_reader
+        };
+
+        // Cache immutable chain data for the session, and reserves for the chain tip
+        let chain_cache = Arc::new(state::CachedReader::new(node_reader));
+        let chain_state: Arc<dyn state::StateReader> = chain_cache.clone();
+
+        // Warm the cache up from a previous instance's snapshot
+        if let Some(path) = &config.chain_snapshot {
+            if let Some(snapshot) = snapshot::ChainSnapshot::load(path)? {
+                let primed = snapshot.prime(&chain_cache, config.chain_id)?;
+                tracing::info!(
+                    "[CONFIG] Primed {} chain data entries from {:?}",
+                    primed,
+                    path
+                );
+            }
+        }
+
+        // Get the latest block
+        let last_block = client
+            .get_block(BlockNumber::Latest)
+            .await?
+            .unwrap()
+            .number
+            .unwrap();
+        tracing::info!("[START] Sandwich bot initializing on block {}", last_block);
+
+        // Get the Flashbots Bundle Signer
+        let bundle_signer = wallets.bundle_signer;
+        tracing::info!(
+            "[START] Flashbots bundle signer (! your searcher identity !): {:?}",
+            bundle_signer.address()
+        );
+
+        let sandwich_contract = config.sandwich_contract.ok_or_else(|| {
+            eyre::eyre!("Required environment variable \"SANDWICH_CONTRACT\" not set")
+        })?;
+        let tx_factory = Arc::new(
+            transactions::TxFactory::new(
+                config.executor.build(sandwich_contract),
+                config.execution_mode,
+                wallets.searcher,
+                config.chain_id,
+            )?
+            .with_wallets(wallets.extra)
+            .with_replay_guard(config.replay_guard)?
+            .with_abort_guard(config.abort_deviation_bps)?,
+        );
+        tracing::info!(
+            "[CONFIG] Sandwich executor: {} at {:?} ({:?} mode)",
+            tx_factory.executor.name(),
+            tx_factory.executor.address(),
+            tx_factory.mode
+        );
+        tracing::info!(
+            "[CONFIG] Searcher wallet address: {:?}",
+            tx_factory.searcher()
+        );
+        if tx_factory.wallets.len() > 1 {
+            control::require_operators(
+                client.clone(),
+                tx_factory.executor.address(),
+                &tx_factory.searchers()[1..],
+            )
+            .await?;
+            tracing::info!(
+                "[CONFIG] Spreading bundles across searcher wallets: {:?}",
+                tx_factory.searchers()
+            );
+        }
+
+        // Stream pending transactions from every configured source, unless sources were given
+        let sources = if self.sources.is_empty() {
+            tracing::info!("[CONFIG] Mempool sources: {:?}", config.tx_sources.0);
+            config
+                .tx_sources
+                .0
+                .iter()
+                .map(|kind| kind.build(client.clone()))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            self.sources
+        };
+        let relays = match self.relays {
+            Some(relays) => relays,
+            None => Relays::from_config(&config)?,
+        };
+
+        Ok(Bot {
+            config,
+            client,
+            chain_state,
+            chain_cache,
+            bundle_signer,
+            plan_signer: wallets.plan_signer,
+            tx_factory,
+            last_block,
+            sources,
+            relays,
+        })
+    }
+}
+
+/// A connected bot, ready to run
+pub struct Bot {
+    config: Config,
+    client: Arc<Provider<Ws>>,
+    chain_state: Arc<dyn state::StateReader>,
+    chain_cache: Arc<state::CachedReader<Arc<dyn state::StateReader>>>,
+    bundle_signer: LocalWallet,
+    plan_signer: Option<LocalWallet>,
+    tx_factory: Arc<transactions::TxFactory>,
+    last_block: U64,
+    sources: Vec<Box<dyn mempool::TxSource>>,
+    relays: Relays,
+}
+
+impl Bot {
+    /// The configuration the bot was built with
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The websocket client
+    pub fn client(&self) -> Arc<Provider<Ws>> {
+        self.client.clone()
+    }
+
+    /// The executor and searcher wallets
+    pub fn tx_factory(&self) -> &transactions::TxFactory {
+        &self.tx_factory
+    }
+
+    /// The token approvals the executor contract needs
+    pub fn required_approvals(&self) -> Vec<approvals::Approval> {
+        approvals::required_approvals(&self.config.approvals)
+    }
+
+    /// Sets any missing executor approvals, returning the approval transaction if one was sent
+    pub async fn approve(&self) -> Result<Option<TxHash>> {
+        approvals::ensure_approvals(
+            self.client.clone(),
+            &self.tx_factory,
+            &self.required_approvals(),
+        )
+        .await
+    }
+
+    /// Signs and sends the plans streamed by the detector at `endpoint`, instead of detecting
+    ///
+    /// Plans must be signed by one of `PLAN_DETECTORS`, and stay under `PLAN_MAX_WETH_IN`,
+    /// `PLAN_MAX_FEE_PER_GAS` and `PLAN_MAX_BRIBE` when they're set.
+    #[cfg(feature = "grpc")]
+    pub async fn execute_plans(self, endpoint: String) -> Result<()> {
+        use crate::config::parse_env_var;
+
+        let detectors = parse_env_var::<safety::AddressList>("PLAN_DETECTORS")?
+            .filter(|detectors| !detectors.0.is_empty())
+            .ok_or_else(|| eyre::eyre!("Executing plans needs the trusted \"PLAN_DETECTORS\""))?;
+        let limit = |key| Ok::<_, eyre::Report>(parse_env_var::<u128>(key)?.map(U256::from));
+        let verifier = plans::PlanVerifier::new(
+            detectors.0,
+            self.config.chain_id,
+            self.tx_factory.executor.address(),
+        )
+        .with_max_weth_in(limit("PLAN_MAX_WETH_IN")?)
+        .with_max_fee_per_gas(limit("PLAN_MAX_FEE_PER_GAS")?)
+        .with_max_bribe(limit("PLAN_MAX_BRIBE")?);
+        let token = instances::var("GRPC_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| eyre::eyre!("Executing plans needs the detector's \"GRPC_TOKEN\""))?;
+        let relay = relayer::FlashbotsRelay::new(
+            self.relays.http,
+            self.relays.flashbots,
+            self.bundle_signer,
+        );
+        grpc::execute_plans(
+            endpoint,
+            token,
+            verifier,
+            self.client,
+            self.tx_factory,
+            relay,
+        )
+        .await
+    }
+
+    /// Runs every strategy and the sandwich pipeline until it fails
+    pub async fn run(self) -> Result<()> {
+        // Deadlines and slot timing read the local clock, so check it before sandwiching
+        match clock::check_ntp(&self.config.clock).await {
+            Ok(Some(offset)) => tracing::info!("[CLOCK] System clock is {}ms off NTP", offset),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("[CLOCK] Failed to check the clock: {:?}", e),
+        }
+
+        // Refuse to send bundles the deployed executor contract can't execute
+        control::check_executor_version(self.client.clone(), &self.tx_factory).await?;
+
+        if self.tx_factory.executor.supports_pause() {
+            match control::executor_status(self.client.clone(), self.tx_factory.executor.address())
+                .await
+            {
+                Ok(status) if status.paused => {
+                    tracing::warn!("[CONTROL] Executor is paused, run `subway unpause` to sandwich")
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("[CONTROL] Failed to read executor status: {:?}", e),
+            }
+        }
+
+        // Check the executor's token approvals, setting them with auto-approve
+        if self.config.auto_approve {
+            match self.approve().await? {
+                Some(hash) => tracing::info!("[APPROVE] Sent approval transaction {:?}", hash),
+                None => tracing::info!("[APPROVE] Executor approvals are already set"),
+            }
+        } else {
+            let missing = approvals::missing_approvals(
+                self.client.clone(),
+                self.tx_factory.executor.address(),
+                &self.required_approvals(),
+            )
+            .await?;
+            if !missing.is_empty() {
+                tracing::warn!(
+                    "[APPROVE] Executor is missing approvals, run `subway approve`: {:?}",
+                    missing
+                );
+            }
+        }
+
+        run_sandwiches(self).await
+    }
+}
+
+/// Runs the strategies and the sandwich pipeline of a connected bot
+async fn run_sandwiches(bot: Bot) -> Result<()> {
+    let Bot {
+        config,
+        client,
+        chain_state,
+        chain_cache,
+        bundle_signer,
+        plan_signer,
+        tx_factory,
+        last_block,
+        sources,
+        relays,
+    } = bot;
+    let usdc_addr = config.addresses.usdc;
+    let uni_v2_addr = config.addresses.v2_router;
+    let weth_addr = config.addresses.weth;
+    let v2 = config.addresses.v2;
+    let Relays {
+        flashbots: flashbots_url,
+        others: bundle_relays,
+        http: relay_http,
+    } = relays;
+    // NOTE: This is for ethereum mainnet
+    // Built once on the shared client, so every bundle reuses the relay's pooled connection
+    let flashbots_relay = relayer::FlashbotsRelay::new(relay_http, flashbots_url, bundle_signer);
+
+    // Watch off-chain order flow alongside the mempool
+    if !config.order_sources.0.is_empty() {
+        if !tx_factory.executor.supports_order_fills() {
+            eyre::bail!(
+                "Order flow needs an executor that can fill orders, \"{}\" can't",
+                tx_factory.executor.name()
+            );
+        }
+        tracing::info!("[CONFIG] Watching order flow: {:?}", config.order_sources.0);
+        let order_flow = run_order_flow(
+            config.order_sources.0.clone(),
+            client.clone(),
+            chain_state.clone(),
+            v2,
+            flashbots_relay.clone(),
+            tx_factory.clone(),
+        );
+        instances::spawn(async move {
+            if let Err(e) = order_flow.await {
+                tracing::error!("[ORDER] Order flow stopped: {:?}", e);
+            }
+        });
+    }
+
+    // Track how builders include blobs, which can crowd bundles out of full blocks
+    let blob_client = client.clone();
+    instances::spawn(async move {
+        if let Err(e) = gas::monitor_blob_inclusion(blob_client).await {
+            tracing::error!("[GAS] Blob monitor stopped: {:?}", e);
+        }
+    });
+
+    // Optionally check tokens against third-party honeypot APIs
+    let honeypot_checker = if config.honeypot.apis.0.is_empty() {
+        None
+    } else {
+        tracing::info!("[CONFIG] Honeypot checks: {:?}", config.honeypot.apis.0);
+        Some(honeypot::HoneypotChecker::new(
+            config.honeypot.clone(),
+            config.chain_id,
+        ))
+    };
+
+    // Compile the operator's go/no-go script, refusing to run without it if one is configured
+    #[cfg(feature = "scripting")]
+    let script_filter = match &config.script_filter {
+        Some(path) => {
+            tracing::info!(
+                "[CONFIG] Filtering candidates through {:?}, up to {} operations each",
+                path,
+                config.script_max_operations
+            );
+            Some(scripting::ScriptFilter::from_file(
+                path,
+                config.script_max_operations,
+            )?)
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "scripting"))]
+    if let Some(path) = &config.script_filter {
+        eyre::bail!(
+            "The filter script {:?} needs the bot built with the `scripting` feature",
+            path
+        );
+    }
+
+    // Prioritize swaps into freshly launched tokens
+    if config.launches.enabled {
+        tracing::info!("[CONFIG] Watching for new pair launches");
+        let launch_watcher = launches::watch_pair_launches(
+            client.clone(),
+            chain_state.clone(),
+            weth_addr,
+            config.addresses.v2.factory,
+            config.launches.clone(),
+            config.safety.max_risk_score,
+        );
+        instances::spawn(async move {
+            if let Err(e) = launch_watcher.await {
+                tracing::error!("[LAUNCH] Launch watcher stopped: {:?}", e);
+            }
+        });
+    }
+
+    // Pause each searcher wallet while it can't cover gas, resuming after a top-up
+    let notifier = notify::Notifier::from_env();
+    let pause_switches = tx_factory
+        .searchers()
+        .into_iter()
+        .map(|wallet| {
+            let switch = balance::PauseSwitch::default();
+            let balance_watcher = balance::watch_gas_reserve(
+                client.clone(),
+                wallet,
+                config.gas_reserve,
+                Duration::from_secs(config.balance_check_secs),
+                switch.clone(),
+                notifier.clone(),
+            );
+            instances::spawn(async move {
+                if let Err(e) = balance_watcher.await {
+                    tracing::error!("[BALANCE] Gas reserve watcher stopped: {:?}", e);
+                }
+            });
+            switch
+        })
+        .collect::<Vec<_>>();
+
+    // Hold large sandwiches for operator approval, over the admin API or Telegram
+    let approval_window = Duration::from_secs(config.manual_approval_secs);
+    let telegram_approver = match config.manual_approval_min_weth {
+        Some(min_weth) => {
+            tracing::info!(
+                "[CONFIG] Sandwiches from {} wei need approval within {:?}",
+                min_weth,
+                approval_window
+            );
+            manual_approval::TelegramApprover::from_env()?.map(Arc::new)
+        }
+        None => None,
+    };
+    if let Some(telegram) = &telegram_approver {
+        let telegram = telegram.clone();
+        supervisor::supervise_task("telegram_approvals", None, move |_| {
+            let telegram = telegram.clone();
+            async move { telegram.run().await }
+        });
+    }
+
+    // Hold shipped opportunities until a decider answers over the gRPC API
+    #[cfg(feature = "grpc")]
+    let remote_window = Duration::from_millis(config.remote_decision_ms);
+    if config.remote_decisions {
+        #[cfg(not(feature = "grpc"))]
+        eyre::bail!("Remote decisions need the bot built with the `grpc` feature");
+        #[cfg(feature = "grpc")]
+        tracing::info!(
+            "[CONFIG] Opportunities need a remote decision within {:?}",
+            remote_window
+        );
+    }
+
+    // Hand remote executors signed plans instead of signing and sending the legs here
+    #[cfg(not(feature = "grpc"))]
+    let _ = plan_signer;
+    #[cfg(feature = "grpc")]
+    if let Some(signer) = &plan_signer {
+        if config.remote_decisions {
+            eyre::bail!("\"PLAN_SIGNER_KEY\" and \"REMOTE_DECISIONS\" can't be combined");
+        }
+        tracing::info!(
+            "[CONFIG] Signing sandwich plans as {:?}, remote executors sign and send the legs",
+            signer.address()
+        );
+    }
+
+    // Periodically move profit above the working capital to the cold address
+    if let Some(to) = config.skim.to {
+        tracing::info!(
+            "[CONFIG] Skimming profit to {:?} every {}s{}",
+            to,
+            config.skim.interval_secs,
+            if config.skim.manual { ", manually" } else { "" }
+        );
+        let client = client.clone();
+        let tx_factory = tx_factory.clone();
+        let skim_config = config.skim.clone();
+        let notifier = notifier.clone();
+        supervisor::supervise_task("profit_skim", None, move |_| {
+            skim::watch_profit_skim(
+                client.clone(),
+                tx_factory.clone(),
+                weth_addr,
+                skim_config.clone(),
+                notifier.clone(),
+            )
+        });
+    }
+
+    // Realize settled sandwiches once confirmed, and roll back those reorged out
+    {
+        let client = client.clone();
+        let confirmations = config.settlement_confirmations;
+        supervisor::supervise_task("settlement_confirmations", None, move |_| {
+            accounting::watch_confirmations(client.clone(), confirmations)
+        });
+    }
+
+    // Snapshot the chain data learned so far, for the next instance to start from
+    if let Some(path) = config.chain_snapshot.clone() {
+        let chain_id = config.chain_id;
+        let interval = Duration::from_secs(config.chain_snapshot_secs.max(1));
+        supervisor::supervise_task("chain_snapshot", None, move |_| {
+            snapshot::persist_chain_snapshots(chain_cache.clone(), chain_id, path.clone(), interval)
+        });
+    }
+
+    // Keep checking the clock against NTP and block timestamps
+    {
+        let client = client.clone();
+        let clock_config = config.clock.clone();
+        supervisor::supervise_task("clock_sync", None, move |_| {
+            clock::watch_clock(client.clone(), clock_config.clone())
+        });
+    }
+
+    // An emergency halt stops submission and pauses the executor contract on-chain
+    if tx_factory.executor.supports_pause() && !config.simulate_only {
+        let client = client.clone();
+        let tx_factory = tx_factory.clone();
+        let notifier = notifier.clone();
+        supervisor::supervise_task("kill_switch", None, move |_| {
+            control::watch_kill_switch(
+                client.clone(),
+                tx_factory.clone(),
+                control::kill_switch().clone(),
+                notifier.clone(),
+            )
+        });
+    }
+
+    // Keep evaluating and recording opportunities if the relay or signer fails
+    let observer = observer::ObserverMode::new(
+        config.relay_failure_threshold,
+        Duration::from_secs(config.observer_probe_secs),
+    );
+
+    // Schedule submissions against each relay's builder cutoff
+    let scheduler = Arc::new(std::sync::Mutex::new(timing::SubmissionScheduler::new(
+        config.submission_timing.clone(),
+    )));
+
+    // Stream pending transactions from every source
+    let tx_stream = mempool::merge_sources(
+        sources,
+        config.ingest_queue_size,
+        Duration::from_secs(config.source_stall_secs.max(1)),
+    );
+
+    // Route V2 router swaps to the sandwich queue, dropping candidates to any unrouted target
+    let mut routes = pipeline::RoutingTable::new();
+    routes.sandwich(uni_v2_addr, pipeline::score_v2_swap);
+
+    // Divert V3 router swaps to the JIT liquidity strategy
+    if config.jit.enabled {
+        if !tx_factory.executor.supports_jit() {
+            eyre::bail!(
+                "JIT liquidity needs an executor that can mint liquidity, \"{}\" can't",
+                tx_factory.executor.name()
+            );
+        }
+        // No executor in contracts/ mints liquidity yet, so check the deployed one does
+        control::require_functions(
+            client.clone(),
+            tx_factory.executor.address(),
+            "JIT liquidity",
+            &tx_factory.executor.jit_functions(),
+        )
+        .await?;
+        tracing::info!(
+            "[CONFIG] Providing JIT liquidity to V3 swaps from {} wei",
+            config.jit.min_swap_weth
+        );
+        let v3_swaps = routes.divert(&[config.addresses.v3_router], config.ingest_queue_size);
+        let jit_strategy = run_jit(
+            v3_swaps,
+            client.clone(),
+            flashbots_relay.clone(),
+            tx_factory.clone(),
+            config.addresses,
+            config.jit.clone(),
+        );
+        instances::spawn(async move {
+            if let Err(e) = jit_strategy.await {
+                tracing::error!("[JIT] JIT liquidity stopped: {:?}", e);
+            }
+        });
+    }
+
+    // Every strategy records its submissions in the same audit log
+    let audit_log = match &config.audit_log {
+        Some(path) => {
+            tracing::info!("[CONFIG] Auditing submissions in {:?}", path);
+            let signer = if config.sign_audit_log {
+                Some(tx_factory.wallet.clone())
+            } else {
+                None
+            };
+            Some(Arc::new(audit::AuditLog::open(path, signer)?))
+        }
+        None => None,
+    };
+
+    // Run the liquidation strategy, backrunning pending oracle updates
+    if config.liquidations.enabled {
+        if !tx_factory.executor.supports_liquidations() {
+            eyre::bail!(
+                "Liquidations need an executor that can repay debt, \"{}\" can't",
+                tx_factory.executor.name()
+            );
+        }
+        tracing::info!(
+            "[CONFIG] Liquidating {:?} positions behind {} oracles",
+            config.liquidations.protocols.0,
+            config.liquidations.oracles.0.len()
+        );
+        let oracle_updates =
+            routes.divert(&config.liquidations.oracles.0, config.ingest_queue_size);
+        let liquidation_strategy = run_liquidations(
+            oracle_updates,
+            client.clone(),
+            flashbots_relay.clone(),
+            bundle_relays.clone(),
+            audit_log.clone(),
+            tx_factory.clone(),
+            pause_switches.clone(),
+            config.liquidations.clone(),
+        );
+        instances::spawn(async move {
+            if let Err(e) = liquidation_strategy.await {
+                tracing::error!("[LIQUIDATE] Liquidations stopped: {:?}", e);
+            }
+        });
+    }
+
+    // Backrun swaps shared through MEV-Share, refunding part of the bribe to their senders
+    if config.mev_share.enabled {
+        tracing::info!(
+            "[CONFIG] Backrunning MEV-Share swaps, refunding {}% of the bribe",
+            config.mev_share.refund_percent
+        );
+        let mev_share_strategy = run_mev_share(
+            client.clone(),
+            chain_state.clone(),
+            flashbots_relay.clone(),
+            audit_log.clone(),
+            tx_factory.clone(),
+            scheduler.clone(),
+            config.addresses,
+            config.reconcile_deviation_bps,
+            config.mev_share.clone(),
+        );
+        instances::spawn(async move {
+            if let Err(e) = mev_share_strategy.await {
+                tracing::error!("[MEV-SHARE] MEV-Share backruns stopped: {:?}", e);
+            }
+        });
+    }
+
+    // Remember recently mined transactions, so stale candidates are dropped without a receipt
+    {
+        let client = client.clone();
+        supervisor::supervise_task("mined_txs", None, move |_| {
+            mempool::watch_mined(client.clone())
+        });
+    }
+
+    // Measure how far ahead of inclusion the sources deliver the swaps that get mined
+    let inclusion_watcher = mempool::watch_inclusion(client.clone(), uni_v2_addr);
+    instances::spawn(async move {
+        if let Err(e) = inclusion_watcher.await {
+            tracing::error!("[EDGE] Inclusion watcher stopped: {:?}", e);
+        }
+    });
+
+    // Shed the lowest value candidates when evaluation falls behind, instead of blocking ingestion
+    let eval_queue = pipeline::shed_into_queue(tx_stream, routes, config.eval_queue_size);
+    let head = pipeline::watch_blocks(
+        client.clone(),
+        last_block,
+        Duration::from_secs(config.block_stall_secs.max(1)),
+    );
+    {
+        let head = head.clone();
+        supervisor::supervise_task("block_summaries", None, move |_| {
+            summary::emit_summaries(head.clone())
+        });
+    }
+    let nonce_gaps = Arc::new(std::sync::Mutex::new(nonces::NonceGaps::new(
+        nonces::NONCE_GAP_CAPACITY,
+    )));
+    {
+        let client = client.clone();
+        let head = head.clone();
+        let eval_queue = eval_queue.clone();
+        let nonce_gaps = nonce_gaps.clone();
+        supervisor::supervise_task("nonce_gaps", None, move |_| {
+            nonces::release_nonce_gaps(
+                client.clone(),
+                head.clone(),
+                eval_queue.clone(),
+                nonce_gaps.clone(),
+            )
+        });
+    }
+    let staleness_guard = pipeline::StalenessGuard::new(config.candidate_budget_ms);
+    let mut spam_guard = spam::SpamGuard::new(config.spam_limits);
+    let mut bot_registry = spam::BotRegistry::new(&config.known_bots, config.bot_strike_limit);
+    let mut allocator = allocation::InventoryAllocator::new();
+    let mut exposure = risk::ExposureTracker::new(config.exposure_limits);
+    let mut base_fee_guard = gas::BaseFeeGuard::new(config.base_fee_limits);
+    let mut competing_swaps = simulation::CompetingSwaps::new();
+    let opportunity_store = match &config.opportunity_log {
+        Some(path) => {
+            tracing::info!("[CONFIG] Storing opportunities in {:?}", path);
+            Some(opportunity::OpportunityStore::open(path)?)
+        }
+        None => None,
+    };
+    if let Some(path) = &config.victim_profiles {
+        let loaded = profiles::profiles().load(path)?;
+        tracing::info!("[CONFIG] Loaded {} victim profiles from {:?}", loaded, path);
+    }
+    let mut simulation_cache =
+        simulation::SimulationCache::new(simulation::DEFAULT_SIMULATION_CACHE_SIZE);
+
+    let mut gas_model = gas::GasModel::default();
+
+    // Listening to pending transactions
+    while let Some(candidate) = eval_queue.next().await {
+        let tx = &candidate.tx;
+        let seen_block = *head.borrow();
+        summary::block_summaries().seen(seen_block);
+
+        // Record why each candidate was or wasn't sandwiched, whichever stage it ends in
+        let mut report = explain::DecisionReport::new(tx.hash, candidate.received_at);
+
+        // Don't spend requests on candidates we can't submit
+        if control::kill_switch().is_halted() {
+            metrics::increment("halted_candidates");
+            report.skip("Halted by the kill switch");
+            continue;
+        }
+        if pause_switches.iter().all(|s| s.is_paused()) {
+            metrics::increment("paused_candidates");
+            report.skip("Every searcher wallet is paused");
+            continue;
+        }
+        // println!("[IN] Received pending transaction: {:?}", tx);
+
+        // Match on tx.to
+        if tx.to != Some(uni_v2_addr) {
+            tracing::debug!("Transaction is not to uniswap v2, skipping...");
+            report.discard();
+            continue;
+        }
+
+        // Relays refuse blob victims without their sidecar, which pending transactions don't carry
+        if !envelope::bundleable(tx) {
+            metrics::increment("unbundleable_victims");
+            report.skip("Victim's transaction type can't be bundled");
+            continue;
+        }
+
+        // Decode the transaction data
+        report.stage("decode");
+        let decoded = if let Ok(d) = abi::decode_uniswap_router_calldata(&tx.input) {
+            d
+        } else {
+            tracing::debug!("Failed to decode transaction data, skipping...");
+            report.skip("Calldata isn't a supported router swap");
+            continue;
+        };
+
+        // Crafted calldata can carry a path too short to swap through
+        if decoded.path.len() < 2 {
+            tracing::debug!("Transaction path is too short, skipping...");
+            report.skip(format!("Path has {} token(s)", decoded.path.len()));
+            continue;
+        }
+
+        // Other searchers set their slippage too tight to sandwich
+        report.stage("spam");
+        report.value("weth_in", tx.value);
+        if let Err(rejection) = bot_registry.check(&tx.from, &decoded.to) {
+            metrics::increment(rejection.metric());
+            tracing::debug!("Dropped {:?} from a known bot, skipping...", tx.hash);
+            report.skip(format!("Sent by a known bot: {:?}", rejection));
+            continue;
+        }
+
+        // Drop dust and rate-limited swaps before spending any requests on them
+        if let Err(rejection) = spam_guard.check(
+            tx.from,
+            decoded.path[1],
+            tx.value,
+            std::time::Instant::now(),
+        ) {
+            metrics::increment(rejection.metric());
+            tracing::debug!(
+                "Dropped {:?} as spam: {:?}, skipping...",
+                tx.hash,
+                rejection
+            );
+            report.skip(format!("Spam: {:?}", rejection));
+            continue;
+        }
+
+        // Skip victims mined in a recent block
+        report.stage("pending");
+        if let Some(block) = mempool::mined()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(&tx.hash)
+        {
+            tracing::debug!("Transaction was mined in block {:?}, skipping...", block);
+            metrics::increment("stale_mined_candidates");
+            report.skip(format!("Already mined in block {:?}", block));
+            continue;
+        }
+
+        // A victim behind a nonce gap can't execute until the sender's earlier transactions land
+        let sender_nonce = match client.get_transaction_count(tx.from, None).await {
+            Ok(n) => n,
+            Err(e) => {
+                tracing::debug!("{:?}", e);
+                report.skip(format!("Failed to get the sender's nonce: {:?}", e));
+                continue;
+            }
+        };
+        match nonces::check_nonce(tx.nonce, sender_nonce) {
+            nonces::NonceCheck::Ready => {}
+            nonces::NonceCheck::Used => {
+                report.skip("Nonce was already used");
+                continue;
+            }
+            nonces::NonceCheck::Gap { missing } => {
+                report.value("missing_nonces", missing);
+                let overflow = nonce_gaps
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .hold(candidate.clone(), seen_block);
+                if overflow.is_some() {
+                    metrics::increment("nonce_gap_overflow");
+                    report.skip("Behind a nonce gap, and too many victims are held");
+                } else {
+                    metrics::increment("nonce_gap_held");
+                    report.skip("Held until the sender's nonce gap closes");
+                }
+                continue;
+            }
+        }
+
+        // We don't want to sandwich uniswap calls with expired deadlines
+        let deadline = decoded.deadline;
+        if U256::from(clock::unix_secs()) > deadline {
+            tracing::debug!("Transaction deadline has expired, skipping...");
+            report.value("deadline", deadline);
+            report.skip("Deadline has expired");
+            continue;
+        }
+
+        // Get the min recv for token directly after WETH
+        let path_block = *head.borrow();
+        let user_min_recv = if let Ok(m) = uniswap::get_univ2_exact_weth_token_min_recv_in(
+            &*chain_state,
+            &v2,
+            &decoded.amount_out_min,
+            &decoded.path,
+            Some(path_block),
+        )
+        .await
+        {
+            m
+        } else {
+            tracing::debug!("Failed to get min recv for token, skipping...");
+            report.skip("Failed to get the minimum received after WETH");
+            continue;
+        };
+        report.value("user_min_recv", user_min_recv);
+
+        tracing::info!(
+            "[DETECTED] Potential sandwichable transaction: {:#?}",
+            decoded
+        );
+
+        // Calculate sandwichability
+        // NOTE: Token A will always be WETH here since the call is decoded as a SwapExactETHForTokensCall
+        let token_a = decoded.path[0];
+        let token_b = decoded.path[1];
+
+        // Get the pair to sandwich
+        report.stage("reserves");
+        let sorted_pair = uniswap::SortedPair::new(token_a, token_b);
+        let pair_to_sandwich = sorted_pair.address_in(&v2);
+        tracing::info!("Found pair to swandwich: {:?}", pair_to_sandwich);
+        competing_swaps.observe(simulation::PendingSwap {
+            hash: tx.hash,
+            pair: pair_to_sandwich,
+            weth_in: tx.value,
+            priority_fee: simulation::PendingSwap::priority_fee_of(tx),
+            seen_at: candidate.received_at,
+        });
+
+        // Get the token reserves, remembering the block they were read at
+        let snapshot_block = *head.borrow();
+        metrics::global().set("head_block", snapshot_block.as_u64());
+        let (token_a_reserves, token_b_reserves) = if let Ok(r) = sorted_pair
+            .reserves_in(&*chain_state, &v2, token_a, Some(snapshot_block))
+            .await
+        {
+            r
+        } else {
+            tracing::debug!(
+                "Failed to get uniswap v2 reserves for pair {:?}, skipping...",
+                pair_to_sandwich
+            );
+            report.skip("Failed to get the pair's reserves");
+            continue;
+        };
+        report.value("reserves_block", snapshot_block);
+        report.value("weth_reserves", token_a_reserves);
+        report.value("token_reserves", token_b_reserves);
+
+        // Distrust reserves that moved implausibly, likely manipulated to bait sandwich bots
+        if config.reserve_sanity.enabled() {
+            match reserves::sane_reserves(
+                &*chain_state,
+                &config.reserve_sanity,
+                &v2,
+                &sorted_pair,
+                token_a,
+                snapshot_block,
+                (token_a_reserves, token_b_reserves),
+            )
+            .await
+            {
+                Ok(Ok(())) => {}
+                Ok(Err(anomaly)) => {
+                    metrics::increment(anomaly.metric());
+                    tracing::warn!(
+                        "[RESERVES] Pair {:?} {}, skipping...",
+                        pair_to_sandwich,
+                        anomaly
+                    );
+                    report.skip(format!("Reserves look manipulated: {}", anomaly));
+                    continue;
+                }
+                Err(e) => {
+                    tracing::debug!("[RESERVES] Failed to check reserves: {:?}", e);
+                    report.skip(format!("Failed to check the reserves: {:?}", e));
+                    continue;
+                }
+            }
+        }
+
+        // Run the operator's go/no-go script before spending a simulation on the candidate
+        #[cfg(feature = "scripting")]
+        if let Some(filter) = &script_filter {
+            report.stage("script");
+            let symbol = if filter.reads_symbol() {
+                scripting::token_symbol(client.clone(), token_b).await
+            } else {
+                String::new()
+            };
+            let scripted = scripting::ScriptCandidate {
+                token: token_b,
+                symbol,
+                pair: pair_to_sandwich,
+                sender: tx.from,
+                weth_in: tx.value,
+                liquidity: token_a_reserves,
+                token_reserve: token_b_reserves,
+                block: snapshot_block,
+            };
+            match filter.evaluate(&scripted) {
+                Ok(true) => {}
+                Ok(false) => {
+                    metrics::increment("script_rejected");
+                    report.skip("Rejected by the filter script");
+                    continue;
+                }
+                Err(e) => {
+                    metrics::increment(e.metric());
+                    tracing::warn!("[SCRIPT] {}, skipping...", e);
+                    report.skip(format!("Filter script couldn't decide: {}", e));
+                    continue;
+                }
+            }
+        }
+
+        // Collect what's known about the opportunity as it moves through evaluation
+        let mut opportunity = opportunity::Opportunity::new(
+            tx.clone(),
+            opportunity::DecodedSwap::from(&decoded),
+            pair_to_sandwich,
+            simulation::StateSnapshot {
+                block: snapshot_block,
+                reserves: (token_a_reserves, token_b_reserves),
+            },
+            user_min_recv,
+            candidate.received_at,
+        );
+
+        // Make sure the victim succeeds on its own and moves the pool as decoded
+        report.stage("victim_simulation");
+        let amounts = match simulation::simulate_victim(client.clone(), tx).await {
+            Ok(a) => a,
+            Err(e) => {
+                metrics::increment("victim_sim_reverted");
+                tracing::warn!("[SIM] {:?}, skipping...", e);
+                report.skip(format!("Victim reverts: {:?}", e));
+                continue;
+            }
+        };
+        let (expected_out, _, _) = uniswap::get_univ2_data_given_in(
+            &opportunity.user_amount_in(),
+            &opportunity.weth_reserves(),
+            &opportunity.token_reserves(),
+        );
+        if let Err(e) = simulation::verify_victim_amounts(
+            &amounts,
+            opportunity.user_amount_in(),
+            opportunity.swap.amount_out_min,
+            expected_out,
+            config.victim_tolerance_bps,
+        ) {
+            metrics::increment(e.metric());
+            tracing::warn!("[SIM] Victim {:?} mismatch: {}, skipping...", tx.hash, e);
+            report.value("expected_out", expected_out);
+            report.value("victim_tolerance_bps", config.victim_tolerance_bps);
+            report.skip(e);
+            continue;
+        }
+
+        // Catch math regressions by quoting the victim's path through the router too
+        if config.quote_check != uniswap::QuoteCheck::Off {
+            match uniswap::check_amounts_out(
+                client.clone(),
+                &*chain_state,
+                &v2,
+                uni_v2_addr,
+                opportunity.user_amount_in(),
+                &opportunity.swap.path,
+                snapshot_block,
+            )
+            .await
+            {
+                Ok(Ok(())) => {}
+                Ok(Err(mismatch)) => {
+                    metrics::increment("quote_mismatch");
+                    tracing::error!("[QUOTE] {:?} path quote mismatch: {}", tx.hash, mismatch);
+                    assert!(
+                        config.quote_check != uniswap::QuoteCheck::Assert,
+                        "Quote mismatch for {:?}: {}",
+                        tx.hash,
+                        mismatch
+                    );
+                }
+                Err(e) => tracing::debug!("[QUOTE] Failed to check quote: {:?}", e),
+            }
+        }
+
+        // Other pending swaps through the pair paying as much as the victim may land first
+        if config.simulate_competing_swaps {
+            let competitors = competing_swaps.competitors(
+                opportunity.pair,
+                tx.hash,
+                simulation::PendingSwap::priority_fee_of(tx),
+            );
+            if !competitors.is_empty() {
+                metrics::increment("competing_swaps_applied");
+                opportunity.reserves.reserves = simulation::apply_competing_swaps(
+                    opportunity.weth_reserves(),
+                    opportunity.token_reserves(),
+                    &competitors,
+                );
+                tracing::info!(
+                    "[SIM] Pricing {:?} after {} competing swaps",
+                    tx.hash,
+                    competitors.len()
+                );
+            }
+        }
+
+        // Refuse victims that look like bait
+        report.stage("safety");
+        let current_block = match client.get_block_number().await {
+            Ok(b) => b,
+            Err(e) => {
+                tracing::debug!("{:?}", e);
+                report.skip(format!("Failed to get the block number: {:?}", e));
+                continue;
+            }
+        };
+        match launches::pair_is_mature(
+            &*chain_state,
+            &config.launches,
+            opportunity.pair,
+            opportunity.token,
+            current_block,
+        )
+        .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                metrics::increment("young_pair_refused");
+                tracing::warn!(
+                    "[LAUNCH] Pair {:?} is younger than {} blocks, skipping...",
+                    opportunity.pair,
+                    config.launches.min_pair_age_blocks
+                );
+                report.value("min_pair_age_blocks", config.launches.min_pair_age_blocks);
+                report.skip("Pair is too young");
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("[LAUNCH] Failed to check pair age: {:?}, skipping...", e);
+                report.skip(format!("Failed to check the pair's age: {:?}", e));
+                continue;
+            }
+        }
+        match safety::assess_victim(
+            client.clone(),
+            &*chain_state,
+            &config.safety,
+            tx.from,
+            opportunity.token,
+            opportunity.pair,
+            current_block,
+        )
+        .await
+        {
+            Ok(mut safety_report) => {
+                if let Some(checker) = &honeypot_checker {
+                    match checker.check(opportunity.token).await {
+                        Ok(r) => safety_report.merge(&r),
+                        Err(e) => {
+                            tracing::warn!("[SAFETY] {:?}, skipping...", e);
+                            report.skip(format!("Honeypot check failed: {:?}", e));
+                            continue;
+                        }
+                    }
+                }
+                for flag in &safety_report.flags {
+                    metrics::increment(flag.metric());
+                }
+                report.value("risk_score", safety_report.score());
+                report.value("max_risk_score", config.safety.max_risk_score);
+                if !safety_report.is_safe(config.safety.max_risk_score) {
+                    metrics::increment("safety_refused");
+                    tracing::warn!(
+                        "[SAFETY] Victim {:?} scored {} {:?}, skipping...",
+                        tx.hash,
+                        safety_report.score(),
+                        safety_report.flags
+                    );
+                    report.skip(format!("Unsafe: {:?}", safety_report.flags));
+                    continue;
+                }
+            }
+            Err(e) => {
+                tracing::warn!("[SAFETY] Failed to assess victim: {:?}, skipping...", e);
+                report.skip(format!("Failed to assess the victim: {:?}", e));
+                continue;
+            }
+        }
+
+        // Caclulate the optimal swap amount
+        report.stage("sizing");
+        summary::block_summaries().evaluated(seen_block);
+        tracing::info!("Calculating optimal swap amount...");
+        // Priced like the library's evaluate::evaluate_sandwich
+        let plan = match evaluate::price_sandwich(
+            tx.hash,
+            weth_addr,
+            opportunity.pair,
+            opportunity.token,
+            opportunity.user_amount_in(),
+            opportunity.user_min_recv,
+            (opportunity.weth_reserves(), opportunity.token_reserves()),
+        ) {
+            Ok(Some(plan)) => plan,
+            // Lmeow, nothing to sandwich!
+            Ok(None) => {
+                tracing::warn!("[LOSS] Nothing to sandwich! Skipping...");
+                if bot_registry.strike(tx.from) {
+                    metrics::increment("bots_learned");
+                    tracing::info!("[SPAM] Treating {:?} as a searcher bot", tx.from);
+                }
+                report.skip("The victim's slippage leaves nothing to sandwich");
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("[ABORT] Failed to calculate sandwich context: {:?}", e);
+                report.skip("Failed to calculate the sandwich context");
+                continue;
+            }
+        };
+        // Contains full parameters and pool states for sandwich construction
+        let mut sandwich_context = plan.context;
+        let mut optimal_weth_in = sandwich_context.optimal_weth_in;
+        tracing::info!(
+            "[CALC] Optimal swap amount: {} ether",
+            ethers::utils::format_units(optimal_weth_in, "ether")
+                .unwrap_or_else(|_| optimal_weth_in.to_string())
+        );
+        report.value("optimal_weth_in", optimal_weth_in);
+        bot_registry.clear(&tx.from);
+
+        if config.optimizer == numeric::Optimizer::ReturnOnCapital {
+            // Shared inventory goes further in smaller sandwiches that earn more per WETH
+            sandwich_context = match numeric::calculate_capital_efficient_sandwich(
+                &config.min_roc_revenue,
+                &opportunity.user_amount_in(),
+                &opportunity.user_min_recv,
+                &opportunity.weth_reserves(),
+                &opportunity.token_reserves(),
+            ) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    metrics::increment("roc_below_min_revenue");
+                    tracing::warn!("[ABORT] {:?}, skipping...", e);
+                    report.value("min_roc_revenue", config.min_roc_revenue);
+                    report.skip(format!("{:?}", e));
+                    continue;
+                }
+            };
+            optimal_weth_in = sandwich_context.optimal_weth_in;
+        }
+        report.value("optimal_weth_in", optimal_weth_in);
+        report.value("revenue", sandwich_context.revenue);
+
+        tracing::info!("Found Sandwich Context {:#?}", sandwich_context);
+
+        // Hold each class of opportunity to its own profit floor, first on the gross revenue
+        // and again once gas and the bribe are known
+        report.stage("profit_floor");
+        let mut profit_floor = None;
+        if !config.profit_rules.is_empty() {
+            let class = config.profit_rules.classify(opportunity.token);
+            let (weth_reserves, usdc_reserves) = match strategy::weth_usdc_reserves(
+                &*chain_state,
+                &v2,
+                weth_addr,
+                usdc_addr,
+                Some(opportunity.reserves.block),
+            )
+            .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    tracing::warn!("[ABORT] Failed to price profit floor: {:?}", e);
+                    report.skip(format!("Failed to price the profit floor: {:?}", e));
+                    continue;
+                }
+            };
+            if let Some(floor) = config
+                .profit_rules
+                .floor_weth(class, weth_reserves, usdc_reserves)
+            {
+                report.value("profit_floor", floor);
+                if sandwich_context.revenue < floor {
+                    metrics::increment("profit_floor_refused");
+                    tracing::warn!(
+                        "[ABORT] Revenue {} is under the {:?} floor of {}, skipping...",
+                        sandwich_context.revenue,
+                        class,
+                        floor
+                    );
+                    report.skip(format!("Revenue is under the {:?} profit floor", class));
+                    continue;
+                }
+                profit_floor = Some((class, floor));
+            }
+        }
+
+        // Pay more, or walk away, when the revenue is large enough to invite a reorg
+        report.stage("reorg_guard");
+        let bribe_bps = match config
+            .reorg_guard
+            .bribe_bps(sandwich_context.revenue, risk::DEFAULT_BRIBE_BPS)
+        {
+            Some(bps) => bps,
+            None => {
+                tracing::warn!(
+                    "[ABORT] Revenue {} is large enough to invite a reorg, skipping...",
+                    sandwich_context.revenue
+                );
+                metrics::increment("reorg_guard_refused");
+                report.skip("Revenue is large enough to invite a reorg");
+                continue;
+            }
+        };
+        report.value("bribe_bps", bribe_bps);
+
+        // Get block data to compute bribes etc
+        // as bribes calculation has correlation with gasUsed
+        report.stage("base_fee");
+        let block = match client.get_block(BlockId::Number(BlockNumber::Latest)).await {
+            Ok(Some(b)) => b,
+            Ok(None) => {
+                tracing::warn!("[ABORT] Failed to get latest block, skipping...");
+                report.skip("Failed to get the latest block");
+                continue;
+            }
+            Err(e) => {
+                tracing::debug!("{:?}", e);
+                report.skip(format!("Failed to get the latest block: {:?}", e));
+                continue;
+            }
+        };
+        let slot_start = block.timestamp.as_u64();
+        let block_gas_limit = block.gas_limit;
+        let target = if let Some(b) = block.number {
+            b + 1
+        } else {
+            tracing::warn!("[ABORT] Failed to get latest block number, skipping...");
+            report.skip("Failed to get the latest block number");
+            continue;
+        };
+        let next_base_fee = if let Ok(nbf) = utils::calculate_next_block_base_fee(block) {
+            nbf
+        } else {
+            tracing::warn!("[ABORT] Failed to calculate next block base fee, skipping...");
+            report.skip("Failed to calculate the next base fee");
+            continue;
+        };
+        report.value("target_block", target);
+        report.value("next_base_fee", next_base_fee);
+        // Fee spikes make the gas estimates and bribe math unreliable
+        base_fee_guard.observe(target, next_base_fee);
+        if let Err(e) = base_fee_guard.check(next_base_fee) {
+            metrics::increment(e.metric());
+            tracing::warn!("[GAS] {}, skipping...", e);
+            report.skip(e);
+            continue;
+        }
+
+        // Allocate a searcher wallet to the bundle, each has its own nonce sequence
+        report.stage("wallet");
+        let wallet_index = match tx_factory.allocate_wallet(|i| !pause_switches[i].is_paused()) {
+            Some(i) => i,
+            None => {
+                metrics::increment("paused_candidates");
+                report.skip("Every searcher wallet is paused");
+                continue;
+            }
+        };
+        let searcher = tx_factory.searcher_at(wallet_index);
+        let nonce = if let Ok(n) = tx_factory.nonces.next(&*client, searcher).await {
+            n
+        } else {
+            tracing::warn!("[ABORT] Failed to get searcher wallet nonce, skipping...");
+            report.skip("Failed to get the searcher wallet's nonce");
+            continue;
+        };
+
+        tracing::info!(
+            "Sandwich Parameters: [block: {}, nonce: {}, base fee: {}]",
+            target,
+            nonce,
+            ethers::utils::format_units(next_base_fee, "ether")
+                .unwrap_or_else(|_| next_base_fee.to_string())
+        );
+
+        // Make sure we can fund the frontrun and gas for both legs before signing
+        report.stage("preflight");
+        let leg_fees = transactions::LegFees::base_fee_only(next_base_fee);
+        let inventory = match risk::Inventory::fetch(
+            client.clone(),
+            weth_addr,
+            tx_factory.executor.address(),
+            searcher,
+        )
+        .await
+        {
+            Ok(i) => i,
+            Err(e) => {
+                tracing::warn!("[ABORT] {:?}", e);
+                report.skip(format!("{:?}", e));
+                continue;
+            }
+        };
+        let gas_cost = risk::worst_case_gas_cost(leg_fees.gas_limit, leg_fees.max_fee_per_gas);
+        report.value("gas_cost", gas_cost);
+        report.value("executor_weth", inventory.executor_weth);
+        let preflight = risk::preflight(
+            optimal_weth_in,
+            gas_cost,
+            &inventory,
+            tx_factory.mode,
+            config.downsize_to_inventory,
+        );
+        match preflight {
+            risk::Preflight::Proceed => {}
+            risk::Preflight::Downsize(available) => {
+                metrics::increment("preflight_downsized");
+                tracing::info!(
+                    "[PREFLIGHT] Downsizing frontrun from {} to available inventory {}",
+                    optimal_weth_in,
+                    available
+                );
+                sandwich_context = match numeric::calculate_constrained_sandwich(
+                    &available,
+                    &opportunity.user_amount_in(),
+                    &opportunity.user_min_recv,
+                    &opportunity.weth_reserves(),
+                    &opportunity.token_reserves(),
+                ) {
+                    Ok(sc) if !sc.revenue.is_zero() => sc,
+                    Ok(_) => {
+                        tracing::warn!("[ABORT] Downsized sandwich has no revenue, skipping...");
+                        report.skip("Downsized to the inventory, the sandwich has no revenue");
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::warn!("[ABORT] Failed to downsize sandwich: {:?}", e);
+                        report.skip(format!("Failed to downsize: {:?}", e));
+                        continue;
+                    }
+                };
+                optimal_weth_in = sandwich_context.optimal_weth_in;
+            }
+            risk::Preflight::Skip(failure) => {
+                metrics::increment(failure.metric());
+                tracing::warn!(
+                    "[PREFLIGHT] {:?}: [weth in: {}, gas: {}, inventory: {:?}], skipping...",
+                    failure,
+                    optimal_weth_in,
+                    gas_cost,
+                    inventory
+                );
+                report.skip(format!("{:?}", failure));
+                continue;
+            }
+        }
+
+        // Cap the WETH committed to any one token in case it rugs
+        report.stage("exposure");
+        if !exposure.allows(
+            opportunity.token,
+            optimal_weth_in,
+            std::time::Instant::now(),
+        ) {
+            metrics::increment("exposure_limited");
+            tracing::warn!(
+                "[RISK] Exposure to {:?} would exceed the cap, skipping...",
+                opportunity.token
+            );
+            report.skip("Exposure to the token would exceed the cap");
+            continue;
+        }
+
+        // Share the executor's WETH with the other bundles of the block, displacing lower
+        // return ones when this sandwich makes more with their inventory
+        report.stage("allocation");
+        let allocation = if tx_factory.mode == executor::ExecutionMode::Inventory {
+            let revenue_at = |weth_in: U256| -> U256 {
+                numeric::calculate_constrained_sandwich(
+                    &weth_in,
+                    &opportunity.user_amount_in(),
+                    &opportunity.user_min_recv,
+                    &opportunity.weth_reserves(),
+                    &opportunity.token_reserves(),
+                )
+                .map(|ctx| ctx.revenue)
+                .unwrap_or_default()
+            };
+            let allocation = match allocator.allocate(
+                target,
+                inventory.executor_weth,
+                optimal_weth_in,
+                revenue_at,
+            ) {
+                Some(a) => a,
+                None => {
+                    metrics::increment("allocation_exhausted");
+                    tracing::warn!(
+                        "[ALLOCATE] No inventory left for block {} is worth taking, skipping...",
+                        target
+                    );
+                    report.skip("No inventory left for the block is worth taking");
+                    continue;
+                }
+            };
+            if allocation.weth_in < optimal_weth_in {
+                metrics::increment("allocation_downsized");
+                tracing::info!(
+                    "[ALLOCATE] Sizing frontrun at {} of {} wanted, displacing {} bundle(s)",
+                    allocation.weth_in,
+                    optimal_weth_in,
+                    allocation.displaced.len()
+                );
+                sandwich_context = match numeric::calculate_constrained_sandwich(
+                    &allocation.weth_in,
+                    &opportunity.user_amount_in(),
+                    &opportunity.user_min_recv,
+                    &opportunity.weth_reserves(),
+                    &opportunity.token_reserves(),
+                ) {
+                    Ok(sc) => sc,
+                    Err(e) => {
+                        tracing::warn!("[ABORT] Failed to size allocated sandwich: {:?}", e);
+                        report.skip(format!("Failed to size the allocation: {:?}", e));
+                        continue;
+                    }
+                };
+                optimal_weth_in = sandwich_context.optimal_weth_in;
+                report.value("allocated_weth_in", optimal_weth_in);
+                report.value("revenue", sandwich_context.revenue);
+            }
+            Some(allocation)
+        } else {
+            None
+        };
+
+        opportunity.price(sandwich_context);
+
+        // Drop bundles that can't pay for gas and the bribe at the gas we've seen on this pair
+        report.stage("gas_estimate");
+        if let Some(leg_gas) = gas_model.estimate(&opportunity.pair, &opportunity.token) {
+            if gas::estimate_bribe(sandwich_context.revenue, &leg_gas, next_base_fee, bribe_bps)
+                .is_none()
+            {
+                metrics::increment("presim_unprofitable");
+                tracing::warn!(
+                    "[ABORT] Revenue {} can't cover the estimated gas and bribe, skipping...",
+                    sandwich_context.revenue
+                );
+                report.skip("Revenue can't cover the estimated gas and bribe");
+                continue;
+            }
+        }
+
+        // A rate limited relay would reject the simulation too
+        if relayer::relay_paused("flashbots") {
+            metrics::increment("relay_backoff_skips");
+            report.skip("Flashbots is rate limiting");
+            continue;
+        }
+
+        // While observing, record the opportunity instead of submitting, probing now and then
+        if !observer.should_submit(std::time::Instant::now()) {
+            metrics::increment("observed_opportunities");
+            report.conclude(explain::Verdict::Observed);
+            if let Some(store) = &opportunity_store {
+                if let Err(e) = store.append(&opportunity) {
+                    tracing::warn!("[STORE] Failed to store opportunity: {:?}", e);
+                }
+            }
+            continue;
+        }
+
+        // Construct the frontrun transaction
+        report.stage("bundle");
+        let frontrun_leg = executor::SwapLeg {
+            token_in: weth_addr,
+            token_out: opportunity.token,
+            pair: opportunity.pair,
+            amount_in: optimal_weth_in,
+            amount_out: sandwich_context.frontrun.amount_out,
+        };
+        let backrun_leg = executor::SwapLeg {
+            token_in: opportunity.token,
+            token_out: weth_addr,
+            pair: opportunity.pair,
+            amount_in: sandwich_context.frontrun.amount_out,
+            amount_out: sandwich_context.backrun.amount_out,
+        };
+        // With an abort guard, the backrun reverts unless the frontrun adds to what's already held
+        let held = if tx_factory.abort_deviation_bps.is_some() {
+            match abi::ERC20::new(opportunity.token, client.clone())
+                .balance_of(tx_factory.executor.address())
+                .call()
+                .await
+            {
+                Ok(held) => held,
+                Err(e) => {
+                    tracing::warn!("[ABORT] Failed to fetch executor token balance: {:?}", e);
+                    report.skip("Failed to fetch the executor token balance");
+                    continue;
+                }
+            }
+        } else {
+            U256::zero()
+        };
+
+        // Get the raw transaction from the tx
+        let signed_victim = match envelope::encode_raw(&tx) {
+            Ok(signed) => signed,
+            Err(e) => {
+                tracing::warn!("[ABORT] Failed to re-encode victim transaction: {:?}", e);
+                report.skip("Failed to re-encode the victim");
+                continue;
+            }
+        };
+
+        // A detector with a plan signer leaves signing, simulating and sending to the remote
+        // executor, so its wallets never sign and no signed legs are streamed
+        #[cfg(feature = "grpc")]
+        if let Some(signer) = &plan_signer {
+            let leg_gas = gas_model
+                .estimate(&opportunity.pair, &opportunity.token)
+                .unwrap_or(gas::LegGas {
+                    frontrun: leg_fees.gas_limit,
+                    backrun: leg_fees.gas_limit,
+                    samples: 0,
+                });
+            let bribe = match gas::estimate_bribe(
+                sandwich_context.revenue,
+                &leg_gas,
+                next_base_fee,
+                bribe_bps,
+            ) {
+                Some(b) if profit_floor.map_or(true, |(_, floor)| b.profit >= floor) => b,
+                _ => {
+                    metrics::increment("presim_unprofitable");
+                    report.skip("Revenue can't cover the estimated gas, bribe and profit floor");
+                    continue;
+                }
+            };
+            let bribed_fees = transactions::LegFees {
+                max_fee_per_gas: next_base_fee + bribe.max_priority_fee_per_gas,
+                max_priority_fee_per_gas: bribe.max_priority_fee_per_gas,
+                gas_limit: leg_fees.gas_limit,
+            };
+            let plan = plans::RemotePlan::new(
+                config.chain_id,
+                tx_factory.executor.address(),
+                target,
+                evaluate::SandwichPlan {
+                    victim: tx.hash,
+                    pair: opportunity.pair,
+                    token: opportunity.token,
+                    user_min_recv: opportunity.user_min_recv,
+                    context: sandwich_context,
+                    frontrun: frontrun_leg,
+                    backrun: backrun_leg,
+                },
+                signed_victim,
+                (leg_fees, bribed_fees),
+            )
+            .with_held(held);
+            let signed_plan = match plan.sign(signer).await {
+                Ok(signed) => signed,
+                Err(e) => {
+                    tracing::warn!("[ABORT] {:?}", e);
+                    report.skip("Failed to sign the plan");
+                    continue;
+                }
+            };
+            if let Some(store) = &opportunity_store {
+                if let Err(e) = store.append(&opportunity) {
+                    tracing::warn!("[STORE] Failed to store opportunity: {:?}", e);
+                }
+            }
+            grpc::publish(&opportunity, optimal_weth_in, Some(&signed_plan), None);
+            metrics::increment("plans_published");
+            report.conclude(explain::Verdict::Planned);
+            continue;
+        }
+
+        let frontrun_nonce = transactions::NonceSlot {
+            wallet: wallet_index,
+            nonce,
+        };
+        // The bundle is submitted for the block after the target, which guarded legs are built for
+        let bundle_block = target + 1;
+        let frontrun_tx =
+            match tx_factory.frontrun(&frontrun_leg, frontrun_nonce, &leg_fees, bundle_block) {
+                Ok(t) => t,
+                Err(e) => {
+                    tracing::warn!("[ABORT] Failed to build frontrun transaction: {:?}", e);
+                    report.skip(format!("Failed to build the frontrun: {:?}", e));
+                    continue;
+                }
+            };
+
+        // Sign the frontrun transaction
+        let signed_frontrun_tx = if let Ok(s) = tx_factory.sign(&frontrun_tx).await {
+            s
+        } else {
+            tracing::warn!("[ABORT] Failed to sign frontrun transaction, skipping...");
+            report.skip("Failed to sign the frontrun");
+            observer::announce(&notifier, observer.signer_failed()).await;
+            continue;
+        };
+
+        // Construct the backrun transaction
+        let backrun_tx = match tx_factory.backrun_checked(
+            &backrun_leg,
+            held,
+            transactions::NonceSlot {
+                wallet: wallet_index,
+                nonce: nonce + 1,
+            },
+            &leg_fees,
+            bundle_block,
+        ) {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!("[ABORT] Failed to build backrun transaction: {:?}", e);
+                report.skip(format!("Failed to build the backrun: {:?}", e));
+                continue;
+            }
+        };
+
+        // Sign the backrun transaction
+        let signed_backrun_tx = if let Ok(s) = tx_factory.sign(&backrun_tx).await {
+            s
+        } else {
+            tracing::warn!("[ABORT] Failed to sign backrun transaction, skipping...");
+            report.skip("Failed to sign the backrun");
+            observer::announce(&notifier, observer.signer_failed()).await;
+            continue;
+        };
+
+        // Simulate tx to get the gas used
+        // let txs = vec![frontrun_transaction_request, middle_transaction, backrun_transaction_request];
+        let signed_transactions = vec![signed_frontrun_tx, signed_victim, signed_backrun_tx];
+
+        tracing::info!("Signed Transaction!");
+        opportunity.plan(opportunity::BundlePlan {
+            target_block: target,
+            wallet: searcher,
+            nonce,
+            base_fee: next_base_fee,
+            bribe_bps,
+            transactions: signed_transactions.clone(),
+        });
+
+        // Construct the bundle
+        let bundle = match relayer::construct_bundle(signed_transactions.clone(), target) {
+            Ok(b) => b,
+            Err(e) => {
+                tracing::warn!(
+                    "[ABORT] Failed to construct flashbots bundle request: {:?}",
+                    e
+                );
+                report.skip(format!("Failed to construct the bundle: {:?}", e));
+                continue;
+            }
+        };
+
+        tracing::info!("[FLASHBOTS] Constructed Flashbots Bundle Request!");
+
+        // Simulate the flashbots bundle, unless the same sandwich was already simulated this block
+        report.stage("bundle_simulation");
+        let simulation_key = simulation::SimulationKey {
+            victim: tx.hash,
+            pair: opportunity.pair,
+            frontrun_in: frontrun_leg.amount_in,
+            backrun_in: backrun_leg.amount_in,
+        };
+        let state = opportunity.reserves;
+        let simulated_bundle = match simulation_cache.get(&simulation_key, &state) {
+            Some(sb) => {
+                metrics::increment("simulation_cache_hit");
+                sb
+            }
+            None => match flashbots_relay.simulate_bundle(&bundle).await {
+                Ok(sb) => {
+                    simulation_cache.insert(simulation_key, state, sb.clone());
+                    sb
+                }
+                Err(e) => {
+                    tracing::warn!("[ABORT] Failed to simulate flashbots bundle: {:?}", e);
+                    report.skip(format!("Failed to simulate the bundle: {:?}", e));
+                    relay_failed(&observer, &notifier, &relayer::RelayError::from_error(&e)).await;
+                    continue;
+                }
+            },
+        };
+
+        // Validate the simulation response
+        if let Err(e) = relayer::validate_simulation_response(&simulated_bundle) {
+            tracing::warn!("[SIM] Simulation Validation Failed: {:?}", e);
+            report.skip(format!("Bundle simulation failed: {:?}", e));
+            continue;
+        }
+
+        // Get the gas used from the simulated bundle, charging calldata heavy legs the floor
+        let frontrun_gas = gas::charged_gas(
+            &frontrun_tx.data().cloned().unwrap_or_default(),
+            simulated_bundle.transactions[0].gas_used,
+        );
+        let backrun_gas = gas::charged_gas(
+            &backrun_tx.data().cloned().unwrap_or_default(),
+            simulated_bundle.transactions[2].gas_used,
+        );
+        gas_model.observe(
+            opportunity.pair,
+            opportunity.token,
+            frontrun_gas,
+            backrun_gas,
+        );
+        let formatted_frontrun_gas = ethers::utils::format_units(frontrun_gas, "ether")
+            .unwrap_or_else(|_| frontrun_gas.to_string());
+        let formatted_backrun_gas = ethers::utils::format_units(backrun_gas, "ether")
+            .unwrap_or_else(|_| backrun_gas.to_string());
+        tracing::info!(
+            "Simulated Bundle Gas Costs: [frontrun: {} ether, backrun: {} ether]",
+            formatted_frontrun_gas,
+            formatted_backrun_gas
+        );
+
+        // Bribe amount - 13.37% by default, more for revenue large enough to invite a reorg
+        report.stage("bribe");
+        report.value("frontrun_gas", frontrun_gas);
+        report.value("backrun_gas", backrun_gas);
+        let bribe_amount = match frontrun_gas
+            .checked_mul(next_base_fee)
+            .and_then(|frontrun_cost| sandwich_context.revenue.checked_sub(frontrun_cost))
+        {
+            Some(amount) => amount,
+            None => {
+                tracing::warn!(
+                    "[LOSS] Frontrun gas costs more than the revenue {}, skipping...",
+                    sandwich_context.revenue
+                );
+                report.value("base_fee", next_base_fee);
+                report.skip("The frontrun's gas costs more than the revenue");
+                continue;
+            }
+        };
+        let proportional_fee = ((bribe_amount * bribe_bps) / 10_000) / backrun_gas;
+
+        // Bid at least what competitors beat us with, while leaving part of the revenue
+        let calibration_cap =
+            bribe_amount * postmortem::MAX_CALIBRATED_BRIBE_BPS / 10_000 / backrun_gas;
+        let max_priority_fee_per_gas =
+            postmortem::competition().calibrate(proportional_fee, calibration_cap);
+        if max_priority_fee_per_gas > proportional_fee {
+            metrics::increment("bribe_calibrated");
+            report.value("uncalibrated_priority_fee", proportional_fee);
+        }
+
+        // Note: you probably want circuit breakers here so you don't lose money if you fucked shit up
+
+        // If 99.99% bribe isn't enough to cover base fee, its not worth it
+        if max_priority_fee_per_gas < next_base_fee {
+            tracing::warn!(
+                "Bribe amount too low: {} < {}, skipping...",
+                max_priority_fee_per_gas,
+                next_base_fee
+            );
+            report.value("max_priority_fee_per_gas", max_priority_fee_per_gas);
+            report.skip("The bribe can't cover the base fee");
+            continue;
+        }
+
+        // Hold the floor again against what's left after gas and the bribe
+        if let Some((class, floor)) = profit_floor {
+            let fees = accounting::FeeEstimate {
+                frontrun_gas,
+                backrun_gas,
+                base_fee: next_base_fee,
+                bribe_per_gas: max_priority_fee_per_gas,
+                refund_percent: 0,
+            };
+            let net_profit = sandwich_context.revenue.saturating_sub(fees.cost());
+            report.value("net_profit", net_profit);
+            if net_profit < floor {
+                metrics::increment("profit_floor_refused");
+                tracing::warn!(
+                    "[ABORT] Net profit {} is under the {:?} floor of {}, skipping...",
+                    net_profit,
+                    class,
+                    floor
+                );
+                report.skip(format!("Net profit is under the {:?} profit floor", class));
+                continue;
+            }
+        }
+
+        // Rebuild the backrun paying the bribe as its priority fee
+        let bribed_fees = transactions::LegFees {
+            max_fee_per_gas: next_base_fee + max_priority_fee_per_gas,
+            max_priority_fee_per_gas,
+            gas_limit: leg_fees.gas_limit,
+        };
+        let bribed_backrun_tx = match tx_factory.backrun_checked(
+            &backrun_leg,
+            held,
+            transactions::NonceSlot {
+                wallet: wallet_index,
+                nonce: nonce + 1,
+            },
+            &bribed_fees,
+            bundle_block,
+        ) {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!(
+                    "[ABORT] Failed to build bribed backrun transaction: {:?}",
+                    e
+                );
+                report.skip(format!("Failed to build the bribed backrun: {:?}", e));
+                continue;
+            }
+        };
+        let signed_bribed_backrun_tx = if let Ok(s) = tx_factory.sign(&bribed_backrun_tx).await {
+            s
+        } else {
+            tracing::warn!("[ABORT] Failed to sign bribed backrun transaction, skipping...");
+            report.skip("Failed to sign the bribed backrun");
+            observer::announce(&notifier, observer.signer_failed()).await;
+            continue;
+        };
+        let signed_transactions = vec![
+            signed_transactions[0].clone(),
+            signed_transactions[1].clone(),
+            signed_bribed_backrun_tx,
+        ];
+        let bundle = match relayer::construct_bundle(signed_transactions.clone(), target) {
+            Ok(b) => b,
+            Err(e) => {
+                tracing::warn!("[ABORT] Failed to construct bribed bundle request: {:?}", e);
+                report.skip(format!("Failed to construct the bribed bundle: {:?}", e));
+                continue;
+            }
+        };
+        if let Some(plan) = &mut opportunity.plan {
+            plan.transactions = signed_transactions.clone();
+        }
+
+        // Make sure the bribe actually reaches the builder before submitting
+        let simulated_bundle = match flashbots_relay.simulate_bundle(&bundle).await {
+            Ok(sb) => sb,
+            Err(e) => {
+                tracing::warn!("[ABORT] Failed to simulate bribed bundle: {:?}", e);
+                report.skip(format!("Failed to simulate the bribed bundle: {:?}", e));
+                relay_failed(&observer, &notifier, &relayer::RelayError::from_error(&e)).await;
+                continue;
+            }
+        };
+        observer::announce(&notifier, observer.succeeded()).await;
+        if let Err(e) = relayer::validate_simulation_response(&simulated_bundle) {
+            tracing::warn!("[SIM] Bribed Bundle Validation Failed: {:?}", e);
+            report.skip(format!("Bribed bundle simulation failed: {:?}", e));
+            continue;
+        }
+        let legs_payment = simulated_bundle.transactions[0].coinbase_diff
+            + simulated_bundle.transactions[2].coinbase_diff;
+        let expected_payment = simulated_bundle.transactions[2].gas_used * max_priority_fee_per_gas;
+        if let Err(e) = config.builder_payment.check(
+            simulated_bundle.coinbase_diff,
+            legs_payment,
+            expected_payment,
+        ) {
+            metrics::increment(e.metric());
+            tracing::warn!("[SIM] {}, skipping...", e);
+            report.skip(e);
+            continue;
+        }
+
+        // Builders won't merge a bundle that crowds out the rest of the block. The legs and their
+        // victim are one group, so an oversized sandwich is dropped even when splitting
+        let gas_budget = gas::BundleGasBudget::new(block_gas_limit, config.max_bundle_gas_bps);
+        let groups = [(signed_transactions.clone(), simulated_bundle.gas_used)];
+        if let Err(e) = gas_budget.fit(&groups, config.split_oversized_bundles) {
+            metrics::increment(e.metric());
+            tracing::warn!("[ABORT] {}, skipping...", e);
+            report.skip(e);
+            continue;
+        }
+
+        // Don't ship a bundle priced against an outdated block or after the evaluation budget
+        report.stage("staleness");
+        if let Err(staleness) = staleness_guard.check(
+            &candidate,
+            opportunity.reserves.block,
+            *head.borrow(),
+            std::time::Instant::now(),
+        ) {
+            metrics::increment(staleness.metric());
+            tracing::warn!(
+                "[STALE] {:?} went stale: {:?}, skipping...",
+                tx.hash,
+                staleness
+            );
+            report.skip(format!("{:?}", staleness));
+            continue;
+        }
+
+        // In simulate-only mode, record the simulated bundle instead of sending it
+        if config.simulate_only {
+            report.conclude(explain::Verdict::Simulated);
+            if let Some(store) = &opportunity_store {
+                if let Err(e) = store.append(&opportunity) {
+                    tracing::warn!("[STORE] Failed to store opportunity: {:?}", e);
+                }
+            }
+            continue;
+        }
+
+        // Weigh the estimated profit by how often bundles like this one land
+        report.stage("expected_value");
+        let estimated_fees = accounting::FeeEstimate {
+            frontrun_gas,
+            backrun_gas,
+            base_fee: next_base_fee,
+            bribe_per_gas: max_priority_fee_per_gas,
+            refund_percent: 0,
+        };
+        let victim_value = tx.value;
+        let inclusion_bps = ev::inclusion_model().probability_bps(bribe_bps, victim_value);
+        let expected_value = ev::inclusion_model().expected_value(
+            bribe_bps,
+            victim_value,
+            sandwich_context.revenue,
+            estimated_fees.cost(),
+        );
+        report.value("inclusion_bps", inclusion_bps);
+        report.value("expected_value", expected_value);
+
+        // Don't race our own bundles for the same pair or nonces, keep the higher value one
+        report.stage("conflicts");
+        let claim = match tx_factory.nonces.claim(conflicts::BundleClaim {
+            target_block: bundle_block,
+            pair: opportunity.pair,
+            wallet: searcher,
+            first_nonce: nonce,
+            last_nonce: nonce + 1,
+            value: expected_value,
+        }) {
+            conflicts::ClaimOutcome::Accepted { handle, superseded } => {
+                if superseded > 0 {
+                    metrics::increment("conflict_superseded");
+                    tracing::info!(
+                        "[CONFLICT] {:?} superseded {} lower value bundle(s)",
+                        tx.hash,
+                        superseded
+                    );
+                }
+                handle
+            }
+            conflicts::ClaimOutcome::Rejected { existing_value } => {
+                metrics::increment("conflict_rejected");
+                tracing::warn!(
+                    "[CONFLICT] {:?} conflicts with a bundle worth {}, skipping...",
+                    tx.hash,
+                    existing_value
+                );
+                report.value("conflicting_value", existing_value);
+                report.skip("Conflicts with a higher value bundle");
+                continue;
+            }
+        };
+
+        // Keep shipped opportunities for replay and backtesting
+        if let Some(store) = &opportunity_store {
+            if let Err(e) = store.append(&opportunity) {
+                tracing::warn!("[STORE] Failed to store opportunity: {:?}", e);
+            }
+        }
+
+        // Stream the opportunity to remote deciders, holding it for their decision if enabled
+        #[cfg(feature = "grpc")]
+        let remote_decision = grpc::publish(
+            &opportunity,
+            optimal_weth_in,
+            None,
+            config.remote_decisions.then_some(remote_window),
+        );
+
+        if let Some(allocation) = &allocation {
+            let displaced = allocator.commit(target, allocation, claim.clone());
+            if displaced > 0 {
+                metrics::increment("allocation_displaced");
+                tracing::info!(
+                    "[ALLOCATE] {:?} displaced {} lower return bundle(s)",
+                    tx.hash,
+                    displaced
+                );
+            }
+        }
+
+        profiles::profiles().record_shipped(tx.from);
+        session::session_tokens().record_shipped(opportunity.token, expected_value);
+        exposure.commit(
+            opportunity.token,
+            optimal_weth_in,
+            std::time::Instant::now(),
+        );
+
+        // Record the report before shipping, so the submission can note what became of it
+        report.conclude(explain::Verdict::Shipped);
+        drop(report);
+
+        // Ship the bundle in the background, holding it until just before each relay's cutoff
+        let victim_hash = tx.hash;
+        let client = client.clone();
+        let flashbots_relay = flashbots_relay.clone();
+        let chain_state = chain_state.clone();
+        let scheduler = scheduler.clone();
+        let bundle_relays = bundle_relays.clone();
+        let executor_addr = tx_factory.executor.address();
+        let estimated_profit = sandwich_context.revenue;
+        let reconcile_deviation_bps = config.reconcile_deviation_bps;
+        let victim_sender = tx.from;
+        let victim_profiles = config.victim_profiles.clone();
+        let audit_log = audit_log.clone();
+        let observer = observer.clone();
+        let rebuild = (candidate.rebuilds < relayer::MAX_REBUILDS)
+            .then(|| (candidate.rebuild(), eval_queue.clone()));
+        let notifier = notifier.clone();
+        let telegram_approver = telegram_approver.clone();
+        let approval = config
+            .manual_approval_min_weth
+            .filter(|min_weth| optimal_weth_in >= *min_weth)
+            .map(|_| {
+                manual_approval::approval_gate().open(
+                    tx.hash,
+                    opportunity.token,
+                    optimal_weth_in,
+                    sandwich_context.revenue,
+                    target,
+                    approval_window,
+                )
+            });
+        let victim_recheck = simulation::VictimRecheck {
+            pair: opportunity.pair,
+            weth: weth_addr,
+            token: opportunity.token,
+            frontrun_weth_in: optimal_weth_in,
+            user_amount_in: opportunity.user_amount_in(),
+            user_min_recv: opportunity.user_min_recv,
+        };
+        instances::spawn(async move {
+            let mut submissions = Vec::new();
+
+            // Large sandwiches are only sent once an operator approves them
+            if let Some((request, decision)) = approval {
+                manual_approval::announce_approval(
+                    &notifier,
+                    telegram_approver.as_deref(),
+                    &request,
+                )
+                .await;
+                let outcome = manual_approval::approval_gate()
+                    .wait(request.id, decision, approval_window)
+                    .await;
+                metrics::increment(outcome.metric());
+                if outcome != manual_approval::ApprovalOutcome::Approved {
+                    tracing::info!(
+                        "[APPROVAL] Sandwich #{} of {:?} {:?}, skipping...",
+                        request.id,
+                        request.victim,
+                        outcome
+                    );
+                    explain::explanations()
+                        .note(victim_hash, format!("Manual approval {:?}", outcome));
+                    claim.supersede();
+                    return;
+                }
+                tracing::info!("[APPROVAL] Sandwich #{} approved", request.id);
+            }
+
+            // Held opportunities are only sent once a remote decider executes them
+            #[cfg(feature = "grpc")]
+            if let Some((request, decision)) = remote_decision {
+                let outcome = grpc::remote_decisions()
+                    .wait(request.id, decision, remote_window)
+                    .await;
+                metrics::increment(grpc::decision_metric(outcome));
+                if outcome != manual_approval::ApprovalOutcome::Approved {
+                    tracing::info!(
+                        "[GRPC] Opportunity #{} of {:?} {:?} remotely, skipping...",
+                        request.id,
+                        request.victim,
+                        outcome
+                    );
+                    explain::explanations()
+                        .note(victim_hash, format!("Remote decision {:?}", outcome));
+                    claim.supersede();
+                    return;
+                }
+                tracing::info!("[GRPC] Opportunity #{} executed remotely", request.id);
+            }
+
+            let offset = timing::wait_for_slot(&scheduler, "flashbots", slot_start).await;
+            if control::kill_switch().is_halted() {
+                explain::explanations().note(victim_hash, "Halted by the kill switch");
+                claim.supersede();
+                return;
+            }
+            if claim.is_superseded() {
+                tracing::info!("[CONFLICT] Bundle for block {} was superseded", target);
+                explain::explanations().note(victim_hash, "Superseded by a higher value bundle");
+                return;
+            }
+            if !victim_still_clears(&*chain_state, &victim_recheck).await {
+                explain::explanations().note(victim_hash, "Victim no longer clears at submission");
+                return;
+            }
+            // Retries and other workers may have sent the same bundle already
+            let bundle_hash = simulation::bundle_hash(&signed_transactions);
+            if !relayer::claim_submission("flashbots", target + 1, bundle_hash) {
+                explain::explanations().note(victim_hash, "Already sent to flashbots");
+                return;
+            }
+            let sent_at = std::time::Instant::now();
+            let response = flashbots_relay.send_bundle(&bundle).await;
+            transport::record_round_trip("flashbots", sent_at.elapsed());
+            audit_submission(
+                audit_log.as_deref(),
+                audit::SubmissionRecord::new(
+                    "flashbots",
+                    target + 1,
+                    &signed_transactions,
+                    &response.as_ref().map(|pb| pb.bundle_hash),
+                ),
+            )
+            .await;
+            let pending_bundle = match response {
+                Ok(pb) => pb,
+                Err(e) => {
+                    let error = relayer::RelayError::from_error(&e);
+                    tracing::warn!(
+                        "[RELAY] Flashbots rejected the bundle ({}): {:?}",
+                        error.describe(),
+                        e
+                    );
+                    explain::explanations().note(
+                        victim_hash,
+                        format!("Flashbots rejected the bundle: {}", error.describe()),
+                    );
+                    let reaction = relay_failed(&observer, &notifier, &error).await;
+                    if let (relayer::RelayReaction::Rebuild, Some((candidate, queue))) =
+                        (reaction, rebuild)
+                    {
+                        // The legs' nonces went stale, so evaluate the victim again on fresh ones
+                        let score = pipeline::score_v2_swap(&candidate.tx).unwrap_or_default();
+                        if queue.requeue(candidate, score) {
+                            metrics::increment("relay_rebuilds");
+                        }
+                    }
+                    return;
+                }
+            };
+            submissions.push(("flashbots".to_string(), offset));
+            summary::block_summaries().submitted(target + 1, expected_value);
+            explain::explanations().note(
+                victim_hash,
+                format!("Sent to flashbots as {:?}", pending_bundle.bundle_hash),
+            );
+
+            // Bundle was sent :rocket:
+            tracing::info!(
+                "Bundle sent:[hash: {:?}, block: {:?}]",
+                pending_bundle.bundle_hash,
+                pending_bundle.block
+            );
+
+            // Race the same bundle through the other configured relays
+            for relay in &bundle_relays {
+                if relayer::relay_paused(relay.name()) {
+                    metrics::increment("relay_backoff_skips");
+                    continue;
+                }
+                let offset = timing::wait_for_slot(&scheduler, relay.name(), slot_start).await;
+                if claim.is_superseded()
+                    || !victim_still_clears(&*chain_state, &victim_recheck).await
+                {
+                    return;
+                }
+                if !relayer::claim_submission(relay.name(), target + 1, bundle_hash) {
+                    continue;
+                }
+                let sent_at = std::time::Instant::now();
+                let response = relay.submit_bundle(&signed_transactions, target + 1).await;
+                transport::record_round_trip(relay.name(), sent_at.elapsed());
+                audit_submission(
+                    audit_log.as_deref(),
+                    audit::SubmissionRecord::new(
+                        relay.name(),
+                        target + 1,
+                        &signed_transactions,
+                        &response,
+                    ),
+                )
+                .await;
+                match response {
+                    Ok(r) => {
+                        tracing::info!("[RELAY] Bundle sent to {}: {}", relay.name(), r);
+                        submissions.push((relay.name().to_string(), offset));
+                    }
+                    Err(e) => {
+                        let error = relayer::RelayError::from_error(&e);
+                        relayer::react(relay.name(), &error);
+                        tracing::warn!(
+                            "[RELAY] {} rejected the bundle ({}): {:?}",
+                            relay.name(),
+                            error.describe(),
+                            e
+                        )
+                    }
+                }
+            }
+
+            // Learn the relays' cutoffs from whether the frontrun landed
+            let frontrun_hash = TxHash::from(ethers::utils::keccak256(&signed_transactions[0]));
+            let backrun_hash = TxHash::from(ethers::utils::keccak256(&signed_transactions[2]));
+            match timing::track_inclusion(
+                client.clone(),
+                scheduler,
+                submissions,
+                frontrun_hash,
+                target + 1,
+            )
+            .await
+            {
+                Ok(Some(included)) => {
+                    ev::inclusion_model().record(bribe_bps, victim_value, included);
+                    if included {
+                        summary::block_summaries().included(target + 1);
+                    } else {
+                        report_competitor(&*client, victim_hash, max_priority_fee_per_gas).await;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!("[TIMING] Failed to track bundle inclusion: {:?}", e),
+            }
+
+            // Verify both legs executed and reconcile the realized profit with the estimate
+            let reconciliation = accounting::reconcile_sandwich(
+                client,
+                searcher,
+                frontrun_hash,
+                backrun_hash,
+                weth_addr,
+                executor_addr,
+                estimated_profit,
+                estimated_fees,
+                reconcile_deviation_bps,
+            )
+            .await;
+            if let Ok(Some(r)) = &reconciliation {
+                summary::block_summaries().realized(target + 1, r.realized_profit);
+            }
+            match reconciliation {
+                // A landed sandwich that made nothing was likely baited, once it happens again
+                Ok(Some(r)) if r.realized_profit.is_zero() => {
+                    if safety::baiters().strike(victim_sender, std::time::Instant::now()) {
+                        tracing::warn!("[SAFETY] Marking {:?} as a baiter", victim_sender);
+                    }
+                }
+                // Remember senders worth prioritizing
+                Ok(Some(r)) => {
+                    safety::baiters().forgive(&victim_sender);
+                    profiles::profiles().record_profit(victim_sender, r.realized_profit);
+                    if let Some(path) = &victim_profiles {
+                        if let Err(e) = profiles::profiles().save(path) {
+                            tracing::warn!("[PROFILES] Failed to save victim profiles: {:?}", e);
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!("[SETTLE] Failed to settle sandwich: {:?}", e),
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Logs who sandwiched a victim our bundle lost, if anyone did
+async fn report_competitor<M: Middleware>(
+    client: &M,
+    victim: H256,
+    our_priority_fee_per_gas: U256,
+) {
+    match postmortem::investigate_loss(client, victim, our_priority_fee_per_gas).await {
+        Ok(Some(sighting)) => {
+            tracing::info!(
+                "[POSTMORTEM] Lost {:?} to {:?} in block {}, bribing {} per gas to our {}",
+                victim,
+                sighting.contract,
+                sighting.block,
+                sighting.priority_fee_per_gas,
+                our_priority_fee_per_gas
+            );
+            explain::explanations().note(victim, format!("Outbid by {:?}", sighting.contract));
+        }
+        Ok(None) => {}
+        Err(e) => tracing::debug!("[POSTMORTEM] Failed to investigate {:?}: {:?}", victim, e),
+    }
+}
+
+/// Reacts to a Flashbots error, counting only outages towards observer mode
+async fn relay_failed(
+    observer: &observer::ObserverMode,
+    notifier: &notify::Notifier,
+    error: &relayer::RelayError,
+) -> relayer::RelayReaction {
+    let reaction = relayer::react("flashbots", error);
+    if reaction == relayer::RelayReaction::Degrade {
+        observer::announce(notifier, observer.relay_failed()).await;
+    }
+    reaction
+}
+
+/// Rechecks that the victim still clears its minimum behind our frontrun before a submission
+///
+/// Submits anyway if the reserves can't be read, since the victim cleared at evaluation.
+async fn victim_still_clears(
+    chain_state: &dyn state::StateReader,
+    recheck: &simulation::VictimRecheck,
+) -> bool {
+    match recheck.check_current(chain_state).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(violation)) => {
+            metrics::increment("victim_min_recheck_failed");
+            tracing::warn!("[ABORT] {}, dropping bundle...", violation);
+            false
+        }
+        Err(e) => {
+            tracing::debug!("[RECHECK] Failed to read reserves: {:?}", e);
+            true
+        }
+    }
+}
+
+/// Appends a submission to the audit log, if one is configured
+async fn audit_submission(log: Option<&audit::AuditLog>, record: audit::SubmissionRecord) {
+    if let Some(log) = log {
+        if let Err(e) = log.append(record).await {
+            tracing::warn!("[AUDIT] Failed to record submission: {:?}", e);
+        }
+    }
+}
+
+/// Sends a simulated bundle through Flashbots, then races it through the other configured relays,
+/// auditing every submission
+///
+/// Returns whether any relay accepted the bundle.
+async fn submit_bundle(
+    flashbots: &relayer::FlashbotsRelay,
+    bundle_relays: &[Arc<dyn BundleRelay>],
+    audit_log: Option<&audit::AuditLog>,
+    signed_transactions: &[Bytes],
+    block: U64,
+) -> Result<bool> {
+    let bundle = relayer::construct_bundle(signed_transactions.to_vec(), block)?;
+    let bundle_hash = simulation::bundle_hash(signed_transactions);
+    let mut accepted = false;
+    if relayer::claim_submission("flashbots", block, bundle_hash) {
+        let sent_at = std::time::Instant::now();
+        let response = flashbots.send_bundle(&bundle).await;
+        transport::record_round_trip("flashbots", sent_at.elapsed());
+        audit_submission(
+            audit_log,
+            audit::SubmissionRecord::new(
+                "flashbots",
+                block,
+                signed_transactions,
+                &response.as_ref().map(|pb| pb.bundle_hash),
+            ),
+        )
+        .await;
+        match response {
+            Ok(pb) => {
+                tracing::info!("[RELAY] Bundle sent to flashbots: {:?}", pb.bundle_hash);
+                accepted = true;
+            }
+            Err(e) => {
+                let error = relayer::RelayError::from_error(&e);
+                relayer::react("flashbots", &error);
+                tracing::warn!(
+                    "[RELAY] Flashbots rejected the bundle ({}): {:?}",
+                    error.describe(),
+                    e
+                );
+            }
+        }
+    }
+    for relay in bundle_relays {
+        if relayer::relay_paused(relay.name()) {
+            metrics::increment("relay_backoff_skips");
+            continue;
+        }
+        if !relayer::claim_submission(relay.name(), block, bundle_hash) {
+            continue;
+        }
+        let sent_at = std::time::Instant::now();
+        let response = relay.submit_bundle(signed_transactions, block).await;
+        transport::record_round_trip(relay.name(), sent_at.elapsed());
+        audit_submission(
+            audit_log,
+            audit::SubmissionRecord::new(relay.name(), block, signed_transactions, &response),
+        )
+        .await;
+        match response {
+            Ok(r) => {
+                tracing::info!("[RELAY] Bundle sent to {}: {}", relay.name(), r);
+                accepted = true;
+            }
+            Err(e) => {
+                let error = relayer::RelayError::from_error(&e);
+                relayer::react(relay.name(), &error);
+                tracing::warn!(
+                    "[RELAY] {} rejected the bundle ({}): {:?}",
+                    relay.name(),
+                    error.describe(),
+                    e
+                )
+            }
+        }
+    }
+    Ok(accepted)
+}
+
+/// Watches off-chain order flow, filling profitable UniswapX orders through the executor with
+/// single-transaction bundles
+async fn run_order_flow(
+    sources: Vec<orders::OrderSource>,
+    client: Arc<Provider<Ws>>,
+    chain_state: Arc<dyn state::StateReader>,
+    v2: uniswap::V2Deployment,
+    flashbots: relayer::FlashbotsRelay,
+    tx_factory: Arc<transactions::TxFactory>,
+) -> Result<()> {
+    // Fan in every source onto one channel
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(1024);
+    for source in sources {
+        instances::spawn(orders::watch_orders(
+            source,
+            Duration::from_secs(2),
+            sender.clone(),
+        ));
+    }
+    drop(sender);
+
+    let executor = tx_factory.executor.address();
+    while let Some(order) = receiver.recv().await {
+        // Price the order at the amounts it resolves to in the next block, against its V2 pair
+        let block = match client.get_block(BlockNumber::Latest).await {
+            Ok(Some(b)) => b,
+            _ => continue,
+        };
+        let target = match block.number {
+            Some(n) => n + 1,
+            None => continue,
+        };
+        let target_timestamp = block.timestamp.as_u64() + timing::SLOT_DURATION_MS / 1_000;
+        let base_fee = match utils::calculate_next_block_base_fee(block) {
+            Ok(f) => f,
+            Err(_) => continue,
+        };
+        let (sell_reserves, buy_reserves) =
+            match uniswap::SortedPair::new(order.sell_token, order.buy_token)
+                .reserves_in(&*chain_state, &v2, order.sell_token, None)
+                .await
+            {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+        let plan = match orders::evaluate_order_in(
+            &order,
+            &v2,
+            &sell_reserves,
+            &buy_reserves,
+            target_timestamp,
+        ) {
+            Ok(Some(p)) => p,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::debug!("Failed to evaluate order {}: {:?}", order.id, e);
+                continue;
+            }
+        };
+        metrics::increment("orders_profitable");
+        tracing::info!(
+            "[ORDER] {:?} order {} has {} surplus through pair {:?}",
+            plan.order.source,
+            plan.order.id,
+            plan.surplus,
+            plan.pair
+        );
+
+        // The executor pays the swapper before swapping the input back, so it must hold the output
+        let inventory = match abi::ERC20::new(plan.fill.output_token, client.clone())
+            .balance_of(executor)
+            .call()
+            .await
+        {
+            Ok(b) => b,
+            Err(_) => continue,
+        };
+        if inventory < plan.buy_amount {
+            metrics::increment("orders_short_inventory");
+            tracing::debug!(
+                "[ORDER] Executor holds {} of the {} order {} pays out",
+                inventory,
+                plan.buy_amount,
+                plan.order.id
+            );
+            continue;
+        }
+
+        // Take the nonce from the shared manager, yielding to any bundle on the same pair or nonce
+        let searcher = tx_factory.searcher();
+        let nonce = match tx_factory.nonces.next(&*client, searcher).await {
+            Ok(n) => n,
+            Err(e) => {
+                tracing::debug!("[ORDER] {:?}", e);
+                continue;
+            }
+        };
+        let claim = tx_factory.nonces.claim(conflicts::BundleClaim {
+            target_block: target,
+            pair: plan.pair,
+            wallet: searcher,
+            first_nonce: nonce,
+            last_nonce: nonce,
+            value: U256::zero(),
+        });
+        let claim = match claim {
+            conflicts::ClaimOutcome::Accepted { handle, .. } => handle,
+            conflicts::ClaimOutcome::Rejected { .. } => {
+                metrics::increment("conflict_rejected");
+                continue;
+            }
+        };
+
+        // Build and sign the fill
+        let fees = transactions::LegFees::base_fee_only(base_fee);
+        let fill = match tx_factory.fill_order(&plan.fill, nonce, &fees, target) {
+            Ok(f) => f,
+            Err(e) => {
+                tracing::warn!("[ORDER] Failed to build fill: {:?}", e);
+                continue;
+            }
+        };
+        let signed_fill = match tx_factory.sign(&fill).await {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!("[ORDER] Failed to sign fill: {:?}", e);
+                continue;
+            }
+        };
+
+        // Simulate and ship the fill bundle
+        let bundle = match relayer::construct_bundle(vec![signed_fill], target) {
+            Ok(b) => b,
+            Err(_) => continue,
+        };
+        match flashbots.simulate_bundle(&bundle).await {
+            Ok(sim) => {
+                if let Err(e) = relayer::validate_simulation_response(&sim) {
+                    tracing::warn!("[ORDER] Fill simulation failed: {:?}", e);
+                    continue;
+                }
+            }
+            Err(e) => {
+                tracing::warn!("[ORDER] Failed to simulate fill bundle: {:?}", e);
+                continue;
+            }
+        }
+        if claim.is_superseded() || !relayer::claim_request("flashbots", &bundle) {
+            continue;
+        }
+        let sent_at = std::time::Instant::now();
+        let response = flashbots.send_bundle(&bundle).await;
+        transport::record_round_trip("flashbots", sent_at.elapsed());
+        match response {
+            Ok(pb) => tracing::info!(
+                "[ORDER] Fill bundle sent: [hash: {:?}, block: {:?}]",
+                pb.bundle_hash,
+                pb.block
+            ),
+            Err(e) => {
+                let error = relayer::RelayError::from_error(&e);
+                relayer::react("flashbots", &error);
+                tracing::warn!(
+                    "[ORDER] Failed to send fill bundle ({}): {:?}",
+                    error.describe(),
+                    e
+                )
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Provides JIT liquidity to large V3 swaps, shipping `[mint, victim, burn]` bundles
+async fn run_jit(
+    mut swaps: tokio::sync::mpsc::Receiver<pipeline::Candidate>,
+    client: Arc<Provider<Ws>>,
+    flashbots: relayer::FlashbotsRelay,
+    tx_factory: Arc<transactions::TxFactory>,
+    addresses: chain::ChainAddresses,
+    config: jit::JitConfig,
+) -> Result<()> {
+    let weth = addresses.weth;
+    while let Some(candidate) = swaps.recv().await {
+        let victim = candidate.tx;
+        if !envelope::bundleable(&victim) {
+            metrics::increment("unbundleable_victims");
+            continue;
+        }
+        let swap = match jit::V3Swap::decode(victim.input.as_ref()) {
+            Some(s) if s.token_in == weth && s.amount_in >= config.min_swap_weth => s,
+            _ => continue,
+        };
+        if control::kill_switch().is_halted() {
+            metrics::increment("halted_candidates");
+            continue;
+        }
+        metrics::increment("jit_candidates");
+
+        // Plan a position against the pool the victim names and the executor's inventory
+        let target_pool = uniswap_v3::get_pool_in(
+            addresses.v3_factory,
+            swap.token_in,
+            swap.token_out,
+            swap.fee,
+        );
+        let pool = match jit::read_pool_state(client.clone(), target_pool, swap.fee).await {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::debug!("[JIT] {:?}", e);
+                continue;
+            }
+        };
+        let token1 = if pool.token0 == swap.token_in {
+            swap.token_out
+        } else {
+            swap.token_in
+        };
+        let executor = tx_factory.executor.address();
+        let (balance0, balance1) = match futures::try_join!(
+            abi::ERC20::new(pool.token0, client.clone())
+                .balance_of(executor)
+                .call(),
+            abi::ERC20::new(token1, client.clone())
+                .balance_of(executor)
+                .call(),
+        ) {
+            Ok(b) => b,
+            Err(_) => continue,
+        };
+        let plan = match jit::plan_jit(&swap, &pool, weth, balance0, balance1, &config) {
+            Some(p) => p,
+            None => continue,
+        };
+
+        // Price the bribe the same way as a sandwich's
+        let block = match client.get_block(BlockNumber::Latest).await {
+            Ok(Some(b)) => b,
+            _ => continue,
+        };
+        let target = match block.number {
+            Some(n) => n + 1,
+            None => continue,
+        };
+        let base_fee = match utils::calculate_next_block_base_fee(block) {
+            Ok(f) => f,
+            Err(_) => continue,
+        };
+
+        // A swap paid in WETH rather than ETH may still be waiting on its router approval
+        let approval = if victim.value < swap.amount_in {
+            let dependency = allowances::check_approval(
+                &allowances::pending_approvals()
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner()),
+                &victim,
+                weth,
+                addresses.v3_router,
+                swap.amount_in,
+                base_fee,
+            );
+            match dependency {
+                allowances::ApprovalDependency::None => None,
+                allowances::ApprovalDependency::Include(approval) => {
+                    match client.get_transaction_receipt(approval.hash).await {
+                        Ok(None) => {
+                            metrics::increment("approval_dependencies_included");
+                            Some(approval)
+                        }
+                        Ok(Some(_)) => None,
+                        Err(_) => continue,
+                    }
+                }
+                allowances::ApprovalDependency::Skip(reason) => {
+                    metrics::increment("approval_dependency_skips");
+                    tracing::debug!("[JIT] Skipping {:?}: {}", victim.hash, reason);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+        // The fees only count net of what the position loses to the swap moving the price
+        let priority_fee = match jit::jit_bribe(plan.net_fees(), base_fee, risk::DEFAULT_BRIBE_BPS)
+        {
+            Some(f) => f,
+            None => {
+                tracing::debug!(
+                    "[JIT] Fees {} less inventory loss {} on {:?} can't cover gas and the bribe",
+                    plan.expected_fees,
+                    plan.inventory_loss,
+                    victim.hash
+                );
+                continue;
+            }
+        };
+        tracing::info!(
+            "[JIT] {:?} pays {} wei of fees, less {} of inventory loss, to a {} bps share of {:?}",
+            victim.hash,
+            plan.expected_fees,
+            plan.inventory_loss,
+            plan.share_bps,
+            pool.address
+        );
+
+        // Take the nonces from the shared manager, and don't race our own bundles for them
+        let searcher = tx_factory.searcher();
+        let nonce = match tx_factory.nonces.next(&*client, searcher).await {
+            Ok(n) => n,
+            Err(e) => {
+                tracing::debug!("[JIT] {:?}", e);
+                continue;
+            }
+        };
+        let claim = tx_factory.nonces.claim(conflicts::BundleClaim {
+            target_block: target,
+            pair: pool.address,
+            wallet: searcher,
+            first_nonce: nonce,
+            last_nonce: nonce + 1,
+            value: plan.net_fees(),
+        });
+        let claim = match claim {
+            conflicts::ClaimOutcome::Accepted { handle, .. } => handle,
+            conflicts::ClaimOutcome::Rejected { existing_value } => {
+                metrics::increment("conflict_rejected");
+                tracing::debug!(
+                    "[JIT] {:?} conflicts with a bundle worth {}, skipping...",
+                    victim.hash,
+                    existing_value
+                );
+                continue;
+            }
+        };
+
+        // Build and sign the mint and burn around the victim
+        let mint_fees = transactions::LegFees {
+            gas_limit: U256::from(jit::JIT_MINT_GAS_LIMIT),
+            ..transactions::LegFees::base_fee_only(base_fee)
+        };
+        let burn_fees = transactions::LegFees {
+            max_fee_per_gas: base_fee + priority_fee,
+            max_priority_fee_per_gas: priority_fee,
+            gas_limit: U256::from(jit::JIT_BURN_GAS_LIMIT),
+        };
+        let legs = tx_factory
+            .jit_mint(&plan.position, nonce, &mint_fees, target)
+            .and_then(|mint| {
+                let burn = tx_factory.jit_burn(&plan.position, nonce + 1, &burn_fees, target)?;
+                Ok((mint, burn))
+            });
+        let (mint, burn) = match legs {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!("[JIT] Failed to build the position legs: {:?}", e);
+                continue;
+            }
+        };
+        let (signed_mint, signed_burn) =
+            match futures::try_join!(tx_factory.sign(&mint), tx_factory.sign(&burn)) {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::warn!("[JIT] Failed to sign the position legs: {:?}", e);
+                    continue;
+                }
+            };
+
+        // Simulate and ship the bundle
+        let signed_victims = match approval
+            .iter()
+            .chain([&victim])
+            .map(envelope::encode_raw)
+            .collect::<Result<Vec<_>>>()
+        {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!("[JIT] Failed to re-encode the victim: {:?}", e);
+                continue;
+            }
+        };
+        let mut signed_transactions = vec![signed_mint];
+        signed_transactions.extend(signed_victims);
+        signed_transactions.push(signed_burn);
+        let bundle = match relayer::construct_bundle(signed_transactions, target) {
+            Ok(b) => b,
+            Err(_) => continue,
+        };
+        match flashbots.simulate_bundle(&bundle).await {
+            Ok(sim) => {
+                if let Err(e) = relayer::validate_simulation_response(&sim) {
+                    tracing::warn!("[JIT] Bundle simulation failed: {:?}", e);
+                    continue;
+                }
+            }
+            Err(e) => {
+                tracing::warn!("[JIT] Failed to simulate bundle: {:?}", e);
+                continue;
+            }
+        }
+        if claim.is_superseded() || !relayer::claim_request("flashbots", &bundle) {
+            continue;
+        }
+        let sent_at = std::time::Instant::now();
+        let response = flashbots.send_bundle(&bundle).await;
+        transport::record_round_trip("flashbots", sent_at.elapsed());
+        match response {
+            Ok(pb) => {
+                metrics::increment("jit_bundles_sent");
+                tracing::info!(
+                    "[JIT] Bundle sent: [hash: {:?}, block: {:?}]",
+                    pb.bundle_hash,
+                    pb.block
+                )
+            }
+            Err(e) => {
+                let error = relayer::RelayError::from_error(&e);
+                relayer::react("flashbots", &error);
+                tracing::warn!(
+                    "[JIT] Failed to send bundle ({}): {:?}",
+                    error.describe(),
+                    e
+                )
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Backruns Uniswap V2 swaps hinted through MEV-Share with an arbitrage against the twin deployment
+///
+/// The shared transaction can't be simulated, so legs are priced at [mev_share::BACKRUN_LEG_GAS]
+/// and the bundle only lands if neither leg reverts.
+#[allow(clippy::too_many_arguments)]
+async fn run_mev_share(
+    client: Arc<Provider<Ws>>,
+    chain_state: Arc<dyn state::StateReader>,
+    flashbots: relayer::FlashbotsRelay,
+    audit_log: Option<Arc<audit::AuditLog>>,
+    tx_factory: Arc<transactions::TxFactory>,
+    scheduler: Arc<std::sync::Mutex<timing::SubmissionScheduler>>,
+    addresses: chain::ChainAddresses,
+    reconcile_deviation_bps: u64,
+    config: mev_share::MevShareConfig,
+) -> Result<()> {
+    let (weth, v2) = (addresses.weth, addresses.v2);
+    let twin = config
+        .twin
+        .ok_or_else(|| eyre::eyre!("MEV-Share backruns need a twin deployment"))?;
+    let (sender, mut events) = tokio::sync::mpsc::channel(1024);
+    {
+        let http = flashbots.http.clone();
+        let url = config.stream_url.clone();
+        supervisor::supervise_task("mev_share_events", None, move |_| {
+            mev_share::stream_events(http.clone(), url.clone(), sender.clone())
+        });
+    }
+
+    let executor = tx_factory.executor.address();
+    let leg_gas = U256::from(mev_share::BACKRUN_LEG_GAS);
+    while let Some(event) = events.recv().await {
+        for swap in event.swaps() {
+            // Any contract can emit a `Swap` log, so only trust Uniswap V2 pairs against WETH
+            let pair = match chain_state.pair_tokens(swap.pair).await {
+                Ok((token0, token1)) => uniswap::SortedPair::new(token0, token1),
+                Err(_) => continue,
+            };
+            let token = match pair.other(weth) {
+                Some(t) if pair.address_in(&v2) == swap.pair => t,
+                _ => continue,
+            };
+            if control::kill_switch().is_halted() {
+                metrics::increment("halted_candidates");
+                continue;
+            }
+            metrics::increment("mev_share_candidates");
+
+            // Price the hinted pair as the shared swap leaves it against its twin
+            let twin_pair = pair.address_in(&twin);
+            let (hinted_reserves, twin_reserves) = match futures::try_join!(
+                chain_state.reserves(swap.pair, None),
+                chain_state.reserves(twin_pair, None),
+            ) {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            let hinted_reserves = match mev_share::reserves_after(&swap, hinted_reserves) {
+                Some(r) => r,
+                None => continue,
+            };
+            let arb_pair = |address, reserves| {
+                let (weth_reserve, token_reserve) = pair.orient(weth, reserves);
+                mev_share::ArbPair {
+                    address,
+                    weth_reserve,
+                    token_reserve,
+                }
+            };
+            let inventory = match abi::ERC20::new(weth, client.clone())
+                .balance_of(executor)
+                .call()
+                .await
+            {
+                Ok(b) => b,
+                Err(_) => continue,
+            };
+            let pairs = [
+                arb_pair(swap.pair, hinted_reserves),
+                arb_pair(twin_pair, twin_reserves),
+            ];
+            let arb = match mev_share::plan_arb(weth, token, pairs, inventory) {
+                Some(a) => a,
+                None => continue,
+            };
+
+            let block = match client.get_block(BlockNumber::Latest).await {
+                Ok(Some(b)) => b,
+                _ => continue,
+            };
+            let target = match block.number {
+                Some(n) => n + 1,
+                None => continue,
+            };
+            let base_fee = match utils::calculate_next_block_base_fee(block) {
+                Ok(f) => f,
+                Err(_) => continue,
+            };
+            let priority_fee =
+                match mev_share::backrun_bribe(arb.revenue, base_fee, risk::DEFAULT_BRIBE_BPS) {
+                    Some(f) => f,
+                    None => {
+                        tracing::debug!(
+                            "[MEV-SHARE] Revenue {} backrunning {:?} can't cover gas and the bribe",
+                            arb.revenue,
+                            event.hash
+                        );
+                        continue;
+                    }
+                };
+            tracing::info!(
+                "[MEV-SHARE] Backrunning {:?} for {} wei, buying from {:?} and selling to {:?}",
+                event.hash,
+                arb.revenue,
+                arb.buy.pair,
+                arb.sell.pair
+            );
+
+            // Take the nonces from the shared manager, and don't race our own bundles for them
+            let searcher = tx_factory.searcher();
+            let nonce = match tx_factory.nonces.next(&*client, searcher).await {
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::debug!("[MEV-SHARE] {:?}", e);
+                    continue;
+                }
+            };
+            let claim = tx_factory.nonces.claim(conflicts::BundleClaim {
+                target_block: target,
+                pair: swap.pair,
+                wallet: searcher,
+                first_nonce: nonce,
+                last_nonce: nonce + 1,
+                value: arb.revenue,
+            });
+            let claim = match claim {
+                conflicts::ClaimOutcome::Accepted { handle, .. } => handle,
+                conflicts::ClaimOutcome::Rejected { .. } => {
+                    metrics::increment("conflict_rejected");
+                    continue;
+                }
+            };
+
+            // Build and sign both legs, only the selling leg bids
+            let buy_fees = transactions::LegFees::base_fee_only(base_fee);
+            let sell_fees = transactions::LegFees {
+                max_fee_per_gas: base_fee + priority_fee,
+                max_priority_fee_per_gas: priority_fee,
+                ..transactions::LegFees::base_fee_only(base_fee)
+            };
+            let legs = tx_factory
+                .frontrun(&arb.buy, nonce, &buy_fees, target)
+                .and_then(|buy| {
+                    let sell = tx_factory.backrun(&arb.sell, nonce + 1, &sell_fees, target)?;
+                    Ok((buy, sell))
+                });
+            let (buy, sell) = match legs {
+                Ok(l) => l,
+                Err(e) => {
+                    tracing::warn!("[MEV-SHARE] Failed to build the legs: {:?}", e);
+                    continue;
+                }
+            };
+            let signed_transactions =
+                match futures::try_join!(tx_factory.sign(&buy), tx_factory.sign(&sell)) {
+                    Ok((buy, sell)) => vec![buy, sell],
+                    Err(e) => {
+                        tracing::warn!("[MEV-SHARE] Failed to sign the legs: {:?}", e);
+                        continue;
+                    }
+                };
+            let bundle = match mev_share::MevShareBundle::backrun(
+                event.hash,
+                signed_transactions.clone(),
+                target,
+                config.refund_percent,
+            ) {
+                Ok(b) => b,
+                Err(e) => {
+                    tracing::warn!("[MEV-SHARE] Failed to build the bundle: {:?}", e);
+                    continue;
+                }
+            };
+            if claim.is_superseded() {
+                continue;
+            }
+            let sent_at = std::time::Instant::now();
+            let response = flashbots.send_mev_share_bundle(&bundle).await;
+            transport::record_round_trip("flashbots", sent_at.elapsed());
+            audit_submission(
+                audit_log.as_deref(),
+                audit::SubmissionRecord::new(
+                    "mev-share",
+                    target,
+                    &signed_transactions,
+                    &response.as_ref().map(|pb| pb.bundle_hash),
+                ),
+            )
+            .await;
+            match response {
+                Ok(pb) => {
+                    metrics::increment("mev_share_bundles_sent");
+                    tracing::info!(
+                        "[MEV-SHARE] Bundle sent: [hash: {:?}, block: {:?}]",
+                        pb.bundle_hash,
+                        pb.block
+                    )
+                }
+                Err(e) => {
+                    let error = relayer::RelayError::from_error(&e);
+                    relayer::react("flashbots", &error);
+                    tracing::warn!(
+                        "[MEV-SHARE] Failed to send bundle ({}): {:?}",
+                        error.describe(),
+                        e
+                    );
+                    continue;
+                }
+            }
+
+            // Settle once the target block is in, the refunded part of the bribe is a rebate
+            let estimated_fees = accounting::FeeEstimate {
+                frontrun_gas: leg_gas,
+                backrun_gas: leg_gas,
+                base_fee,
+                bribe_per_gas: priority_fee,
+                refund_percent: config.refund_percent,
+            };
+            let buy_hash = TxHash::from(ethers::utils::keccak256(&signed_transactions[0]));
+            let sell_hash = TxHash::from(ethers::utils::keccak256(&signed_transactions[1]));
+            let (client, scheduler) = (client.clone(), scheduler.clone());
+            instances::spawn(async move {
+                if let Err(e) =
+                    timing::track_inclusion(client.clone(), scheduler, Vec::new(), buy_hash, target)
+                        .await
+                {
+                    tracing::debug!("[TIMING] Failed to track bundle inclusion: {:?}", e);
+                }
+                if let Err(e) = accounting::reconcile_sandwich(
+                    client,
+                    searcher,
+                    buy_hash,
+                    sell_hash,
+                    weth,
+                    executor,
+                    arb.revenue,
+                    estimated_fees,
+                    reconcile_deviation_bps,
+                )
+                .await
+                {
+                    tracing::debug!("[SETTLE] Failed to settle MEV-Share backrun: {:?}", e);
+                }
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Liquidates Aave and Compound positions, alone when underwater or behind a pending oracle update
+async fn run_liquidations(
+    mut oracle_updates: tokio::sync::mpsc::Receiver<pipeline::Candidate>,
+    client: Arc<Provider<Ws>>,
+    flashbots: relayer::FlashbotsRelay,
+    bundle_relays: Vec<Arc<dyn BundleRelay>>,
+    audit_log: Option<Arc<audit::AuditLog>>,
+    tx_factory: Arc<transactions::TxFactory>,
+    pause_switches: Vec<balance::PauseSwitch>,
+    config: liquidations::LiquidationConfig,
+) -> Result<()> {
+    // Discover borrowers from past events, then follow new ones
+    let mut book = liquidations::PositionBook::new();
+    if config
+        .protocols
+        .0
+        .contains(&liquidations::LendingProtocol::CompoundV2)
+    {
+        liquidations::load_compound_markets(client.clone(), &config, &mut book).await?;
+    }
+    let head = client
+        .get_block_number()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch block number: {:?}", e))?;
+    let events = liquidations::discover_borrowers(client.clone(), &mut book, &config, head).await?;
+    tracing::info!(
+        "[LIQUIDATE] Watching {} positions from {} events",
+        book.len(),
+        events
+    );
+    let mut borrower_logs = Vec::new();
+    for filter in liquidations::borrower_filters(&config, &book.compound_markets()) {
+        borrower_logs.push(Box::pin(client.subscribe_logs(&filter).await.map_err(
+            |e| eyre::eyre!("Failed to subscribe to borrower events: {:?}", e),
+        )?));
+    }
+    let mut borrower_logs = futures::stream::select_all(borrower_logs);
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.refresh_secs.max(1)));
+
+    loop {
+        // Liquidate underwater positions on their own, or near ones behind an oracle update
+        let update = tokio::select! {
+            _ = ticker.tick() => {
+                let refreshed =
+                    liquidations::refresh_health(client.clone(), &config, &mut book).await;
+                tracing::debug!("[LIQUIDATE] Refreshed {} positions", refreshed);
+                None
+            }
+            log = borrower_logs.next() => {
+                match log {
+                    Some(log) => {
+                        book.apply_log(&log);
+                        continue;
+                    }
+                    None => eyre::bail!("Borrower event subscription ended"),
+                }
+            }
+            update = oracle_updates.recv() => match update {
+                Some(candidate) => Some(candidate.tx),
+                None => eyre::bail!("Oracle update channel closed"),
+            },
+        };
+        if control::kill_switch().is_halted() {
+            continue;
+        }
+        let positions = book
+            .near_liquidation(&config)
+            .into_iter()
+            .filter(|p| update.is_some() || matches!(p.health, Some(h) if h.is_liquidatable()))
+            .take(config.max_per_update)
+            .cloned()
+            .collect::<Vec<_>>();
+        if positions.is_empty() {
+            continue;
+        }
+
+        let block = match client.get_block(BlockNumber::Latest).await {
+            Ok(Some(b)) => b,
+            _ => continue,
+        };
+        let target = match block.number {
+            Some(n) => n + 1,
+            None => continue,
+        };
+        let base_fee = match utils::calculate_next_block_base_fee(block) {
+            Ok(f) => f,
+            Err(_) => continue,
+        };
+        let fees = transactions::LegFees {
+            max_fee_per_gas: base_fee + config.priority_fee,
+            max_priority_fee_per_gas: config.priority_fee,
+            gas_limit: U256::from(liquidations::LIQUIDATION_GAS_LIMIT),
+        };
+
+        // Each liquidation is its own bundle, so one reverting doesn't sink the others
+        for position in positions {
+            let liquidation = match liquidations::plan_liquidation(
+                client.clone(),
+                &config,
+                &position,
+                tx_factory.executor.address(),
+            )
+            .await
+            {
+                Ok(Some(l)) => l,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::debug!("[LIQUIDATE] {:?}", e);
+                    continue;
+                }
+            };
+            // Take the nonce like a sandwich, on a wallet that isn't paused
+            let wallet_index = match tx_factory.allocate_wallet(|i| !pause_switches[i].is_paused())
+            {
+                Some(i) => i,
+                None => continue,
+            };
+            let searcher = tx_factory.searcher_at(wallet_index);
+            let nonce = match tx_factory.nonces.next(&*client, searcher).await {
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::debug!("[LIQUIDATE] {:?}", e);
+                    continue;
+                }
+            };
+            let nonce = transactions::NonceSlot {
+                wallet: wallet_index,
+                nonce,
+            };
+            let tx = match tx_factory.liquidate(&liquidation, nonce, &fees, target) {
+                Ok(tx) => tx,
+                Err(e) => {
+                    tracing::warn!("[LIQUIDATE] Failed to build liquidation: {:?}", e);
+                    continue;
+                }
+            };
+            let signed = match tx_factory.sign(&tx).await {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::warn!("[LIQUIDATE] Failed to sign liquidation: {:?}", e);
+                    continue;
+                }
+            };
+            let signed_transactions = match update.as_ref().map(envelope::encode_raw) {
+                Some(Ok(update)) => vec![update, signed],
+                Some(Err(e)) => {
+                    tracing::warn!("[LIQUIDATE] Failed to re-encode oracle update: {:?}", e);
+                    continue;
+                }
+                None => vec![signed],
+            };
+            let bundle = match relayer::construct_bundle(signed_transactions.clone(), target) {
+                Ok(b) => b,
+                Err(_) => continue,
+            };
+            match flashbots.simulate_bundle(&bundle).await {
+                Ok(sim) => {
+                    if let Err(e) = relayer::validate_simulation_response(&sim) {
+                        tracing::debug!(
+                            "[LIQUIDATE] Liquidation of {:?} failed simulation: {:?}",
+                            liquidation.borrower,
+                            e
+                        );
+                        continue;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("[LIQUIDATE] Failed to simulate bundle: {:?}", e);
+                    continue;
+                }
+            }
+
+            // One bundle per borrower and block, and none reusing another bundle's nonce. The bonus
+            // isn't priced in WETH, so liquidations yield to any conflicting sandwich
+            let claim = tx_factory.nonces.claim(conflicts::BundleClaim {
+                target_block: target,
+                pair: liquidation.borrower,
+                wallet: searcher,
+                first_nonce: nonce.nonce,
+                last_nonce: nonce.nonce,
+                value: U256::zero(),
+            });
+            if let conflicts::ClaimOutcome::Rejected { existing_value } = claim {
+                metrics::increment("conflict_rejected");
+                tracing::debug!(
+                    "[LIQUIDATE] Liquidation of {:?} conflicts with a bundle worth {}",
+                    liquidation.borrower,
+                    existing_value
+                );
+                continue;
+            }
+            match submit_bundle(
+                &flashbots,
+                &bundle_relays,
+                audit_log.as_deref(),
+                &signed_transactions,
+                target,
+            )
+            .await
+            {
+                Ok(true) => {
+                    metrics::increment("liquidation_bundles_sent");
+                    tracing::info!(
+                        "[LIQUIDATE] Liquidating {:?} on {:?} in block {}",
+                        liquidation.borrower,
+                        liquidation.protocol,
+                        target
+                    )
+                }
+                Ok(false) => {}
+                Err(e) => tracing::warn!("[LIQUIDATE] Failed to send bundle: {:?}", e),
+            }
+        }
+    }
+}
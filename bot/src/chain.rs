@@ -0,0 +1,48 @@
+//! Chain Addresses
+//!
+//! The tokens and DEX contracts the bot trades through differ between chains. [ChainAddresses]
+//! holds them for one instance, mainnet's by default, and is part of its [crate::config::Config],
+//! so instances on different chains each price, route and watch through their own contracts.
+
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::{jit, uniswap, uniswap_v3, utils};
+
+/// The contracts an instance trades through
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChainAddresses {
+    /// The wrapped native token
+    pub weth: Address,
+    /// The USD stablecoin profit floors are priced in
+    pub usdc: Address,
+    /// The Uniswap V2 router victims swap through
+    pub v2_router: Address,
+    /// The Uniswap V2 deployment pairs are derived from
+    pub v2: uniswap::V2Deployment,
+    /// The Uniswap V3 router JIT liquidity watches
+    pub v3_router: Address,
+    /// The Uniswap V3 factory pools are derived from
+    pub v3_factory: Address,
+}
+
+impl Default for ChainAddresses {
+    /// The mainnet contracts
+    fn default() -> Self {
+        Self {
+            weth: utils::get_weth_address(),
+            usdc: utils::get_usdc_address(),
+            v2_router: uniswap::get_univ2_router_address(),
+            v2: uniswap::V2Deployment::default(),
+            v3_router: jit::get_univ3_router_address(),
+            v3_factory: uniswap_v3::get_factory_address(),
+        }
+    }
+}
+
+impl ChainAddresses {
+    /// Whether these are the mainnet contracts
+    pub fn is_mainnet(&self) -> bool {
+        *self == Self::default()
+    }
+}
@@ -0,0 +1,167 @@
+//! Chainbound Fiber and Echo
+//!
+//! Fiber streams pending transactions over gRPC from a globally distributed network of nodes,
+//! and Echo propagates bundles to block builders. Fiber ingestion needs the `fiber` feature, Echo
+//! is plain JSON-RPC and always available.
+
+use ethers::prelude::*;
+use eyre::Result;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::{instances, mempool::TxSource, timing::BundleRelay};
+
+/// The Fiber gRPC endpoint
+pub const FIBER_URL: &str = "beta.fiberapi.io:8080";
+
+/// The Echo bundle propagation RPC
+pub const ECHO_RPC_URL: &str = "https://echo-rpc.chainbound.io";
+
+/// Returns the Fiber api key
+pub fn get_fiber_api_key() -> Result<String> {
+    instances::var("FIBER_API_KEY")
+        .map_err(|_| eyre::eyre!("Required environment variable \"FIBER_API_KEY\" not set"))
+}
+
+/// Returns the Echo api key
+pub fn get_echo_api_key() -> Result<String> {
+    instances::var("ECHO_API_KEY")
+        .map_err(|_| eyre::eyre!("Required environment variable \"ECHO_API_KEY\" not set"))
+}
+
+/// Pending transactions streamed from Fiber
+#[derive(Debug, Clone)]
+pub struct FiberSource {
+    /// The gRPC endpoint
+    pub url: String,
+    /// The api key
+    pub api_key: String,
+}
+
+impl FiberSource {
+    /// Creates a source for the public endpoint, reading the api key from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            url: FIBER_URL.to_string(),
+            api_key: get_fiber_api_key()?,
+        })
+    }
+}
+
+impl TxSource for FiberSource {
+    fn name(&self) -> &'static str {
+        "fiber"
+    }
+
+    #[cfg(feature = "fiber")]
+    fn run(&self, sender: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>> {
+        let source = self.clone();
+        Box::pin(async move {
+            let client = fiber::Client::connect(source.url, source.api_key)
+                .await
+                .map_err(|e| eyre::eyre!("Fiber connection error: {:?}", e))?;
+            let mut txs = client.subscribe_new_txs(None).await;
+            while let Some(tx) = txs.next().await {
+                // Fiber pins its own ethers version, so convert through the shared json encoding
+                let tx = match serde_json::to_value(tx)
+                    .map_err(eyre::Report::from)
+                    .and_then(crate::envelope::from_json)
+                {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        tracing::debug!("Failed to convert Fiber transaction: {:?}", e);
+                        continue;
+                    }
+                };
+                if sender.send(tx).await.is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    #[cfg(not(feature = "fiber"))]
+    fn run(&self, _: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>> {
+        Box::pin(async { eyre::bail!("Fiber ingestion requires the \"fiber\" feature") })
+    }
+}
+
+/// An Echo `eth_sendBundle` request
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EchoBundle {
+    txs: Vec<Bytes>,
+    block_number: U64,
+    mev_builders: Vec<&'static str>,
+}
+
+/// Propagates bundles through Echo
+#[derive(Debug, Clone)]
+pub struct EchoClient {
+    /// The http client
+    pub http: reqwest::Client,
+    /// The RPC url
+    pub url: String,
+    /// The api key
+    pub api_key: String,
+}
+
+impl EchoClient {
+    /// Creates a client for the public RPC, reading the api key from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            http: reqwest::Client::new(),
+            url: ECHO_RPC_URL.to_string(),
+            api_key: get_echo_api_key()?,
+        })
+    }
+
+    /// Sends a bundle of signed transactions to every builder Echo forwards to
+    pub async fn send_bundle(
+        &self,
+        signed_txs: &[Bytes],
+        block_number: U64,
+    ) -> Result<serde_json::Value> {
+        let response: serde_json::Value = self
+            .http
+            .post(&self.url)
+            .header("X-Api-Key", &self.api_key)
+            .json(&serde_json::json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "method": "eth_sendBundle",
+                "params": [EchoBundle {
+                    txs: signed_txs.to_vec(),
+                    block_number,
+                    mev_builders: vec!["all"],
+                }],
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            eyre::bail!("Echo eth_sendBundle error: {}", error);
+        }
+        Ok(response
+            .get("result")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null))
+    }
+}
+
+impl BundleRelay for EchoClient {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn submit_bundle<'a>(
+        &'a self,
+        signed_txs: &'a [Bytes],
+        block_number: U64,
+    ) -> BoxFuture<'a, Result<serde_json::Value>> {
+        Box::pin(self.send_bundle(signed_txs, block_number))
+    }
+}
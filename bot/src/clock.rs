@@ -0,0 +1,274 @@
+//! Clock Synchronization
+//!
+//! Victim deadlines, order expiries and the submission schedule are all compared against the local
+//! clock, so a host that drifts a few seconds silently sandwiches expired swaps, or skips live
+//! ones, and submits at the wrong point in the slot.
+//!
+//! The clock is checked two ways. An SNTP query against [ClockConfig::ntp_server] measures the
+//! offset directly, at startup and every [ClockConfig::check_secs]. Each new block's timestamp is
+//! also compared with when it arrived: a block can't arrive before its timestamp, or long after it,
+//! so the earliest arrival over recent blocks bounds the drift without any outside service.
+//!
+//! Drift beyond [ClockConfig::max_drift_ms] is warned about and counted in the
+//! `clock_drift_warnings` metric. With [ClockConfig::correct], the measured NTP offset is applied to
+//! [now], which the deadline and slot timing checks read instead of the system clock.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+
+use crate::metrics;
+
+/// The default NTP server
+pub const DEFAULT_NTP_SERVER: &str = "pool.ntp.org:123";
+
+/// The default largest drift tolerated before warning, in milliseconds
+pub const DEFAULT_MAX_CLOCK_DRIFT_MS: u64 = 500;
+
+/// The default interval between NTP checks, in seconds
+pub const DEFAULT_CLOCK_CHECK_SECS: u64 = 300;
+
+/// How long to wait for an NTP response, in milliseconds
+pub const NTP_TIMEOUT_MS: u64 = 2_000;
+
+/// How many recent block arrivals bound the drift
+pub const BLOCK_LAG_WINDOW: usize = 32;
+
+/// How long after its timestamp a block can take to arrive, in milliseconds
+pub const BLOCK_ARRIVAL_ALLOWANCE_MS: i64 = 4_000;
+
+/// Seconds between the NTP epoch, 1900, and the unix epoch
+const NTP_UNIX_OFFSET_SECS: i64 = 2_208_988_800;
+
+/// The clock check settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClockConfig {
+    /// The NTP server as `host:port`, or `None` to only check against block timestamps
+    pub ntp_server: Option<String>,
+    /// The largest drift tolerated before warning, in milliseconds
+    pub max_drift_ms: u64,
+    /// How often the NTP server is queried, in seconds
+    pub check_secs: u64,
+    /// Whether to apply the measured NTP offset to [now]
+    pub correct: bool,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            ntp_server: Some(DEFAULT_NTP_SERVER.to_string()),
+            max_drift_ms: DEFAULT_MAX_CLOCK_DRIFT_MS,
+            check_secs: DEFAULT_CLOCK_CHECK_SECS,
+            correct: false,
+        }
+    }
+}
+
+/// The correction applied to the system clock, in milliseconds
+static OFFSET_MS: AtomicI64 = AtomicI64::new(0);
+
+/// The correction applied to the system clock by [now], in milliseconds
+pub fn offset_ms() -> i64 {
+    OFFSET_MS.load(Ordering::Relaxed)
+}
+
+/// Sets the correction applied to the system clock by [now], in milliseconds
+pub fn set_offset_ms(offset: i64) {
+    OFFSET_MS.store(offset, Ordering::Relaxed);
+}
+
+/// The current time, corrected by [offset_ms]
+pub fn now() -> SystemTime {
+    let offset = offset_ms();
+    let magnitude = Duration::from_millis(offset.unsigned_abs());
+    if offset >= 0 {
+        SystemTime::now() + magnitude
+    } else {
+        SystemTime::now() - magnitude
+    }
+}
+
+/// The current unix time in seconds, corrected by [offset_ms]
+pub fn unix_secs() -> u64 {
+    now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn unix_millis_at(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Converts a 64-bit NTP timestamp to unix milliseconds
+pub fn ntp_timestamp_millis(bytes: [u8; 8]) -> i64 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as i64;
+    (secs - NTP_UNIX_OFFSET_SECS) * 1_000 + ((fraction * 1_000) >> 32)
+}
+
+/// The offset of the local clock from the server's, in milliseconds, from an SNTP exchange
+///
+/// `sent` and `received` are the local times the request left and the response arrived,
+/// `server_received` and `server_sent` the server's. Positive when the local clock is behind.
+pub fn ntp_offset_ms(sent: i64, server_received: i64, server_sent: i64, received: i64) -> i64 {
+    ((server_received - sent) + (server_sent - received)) / 2
+}
+
+/// Queries an NTP server, returning how far the system clock is behind it in milliseconds
+pub async fn query_ntp_offset(server: &str, timeout: Duration) -> Result<i64> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket
+        .connect(server)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to reach NTP server {}: {:?}", server, e))?;
+
+    // Version 3, client mode
+    let mut request = [0u8; 48];
+    request[0] = 0x1b;
+    let sent = unix_millis_at(SystemTime::now());
+    socket.send(&request).await?;
+
+    let mut response = [0u8; 48];
+    let len = tokio::time::timeout(timeout, socket.recv(&mut response))
+        .await
+        .map_err(|_| eyre::eyre!("NTP server {} didn't respond in {:?}", server, timeout))??;
+    let received = unix_millis_at(SystemTime::now());
+    if len < response.len() {
+        eyre::bail!("NTP server {} sent a {} byte response", server, len);
+    }
+    if response[0] & 0x07 != 4 || response[1] == 0 {
+        eyre::bail!("NTP server {} refused the request", server);
+    }
+
+    let timestamp = |at: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&response[at..at + 8]);
+        ntp_timestamp_millis(bytes)
+    };
+    Ok(ntp_offset_ms(sent, timestamp(32), timestamp(40), received))
+}
+
+/// Queries the configured NTP server, warning about drift and applying the correction if enabled
+///
+/// Returns the measured offset, or `None` without an NTP server.
+pub async fn check_ntp(config: &ClockConfig) -> Result<Option<i64>> {
+    let server = match &config.ntp_server {
+        Some(server) => server,
+        None => return Ok(None),
+    };
+    let offset = match query_ntp_offset(server, Duration::from_millis(NTP_TIMEOUT_MS)).await {
+        Ok(offset) => offset,
+        Err(e) => {
+            metrics::increment("ntp_failures");
+            return Err(e);
+        }
+    };
+    metrics::global().set("clock_drift_ms", offset.unsigned_abs());
+    if offset.unsigned_abs() > config.max_drift_ms {
+        metrics::increment("clock_drift_warnings");
+        tracing::warn!(
+            "[CLOCK] System clock is {}ms {} {}",
+            offset.unsigned_abs(),
+            if offset > 0 { "behind" } else { "ahead of" },
+            server
+        );
+    }
+    if config.correct {
+        set_offset_ms(offset);
+    }
+    Ok(Some(offset))
+}
+
+/// The arrival lag of recent blocks behind their timestamps
+#[derive(Debug, Clone)]
+pub struct BlockLags {
+    lags: VecDeque<i64>,
+    capacity: usize,
+}
+
+impl BlockLags {
+    /// Remembers the last `capacity` arrivals
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lags: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Records a block with timestamp `timestamp` (unix seconds) arriving at `arrived_ms`
+    pub fn record(&mut self, timestamp: u64, arrived_ms: i64) {
+        self.lags.push_back(arrived_ms - timestamp as i64 * 1_000);
+        while self.lags.len() > self.capacity {
+            self.lags.pop_front();
+        }
+    }
+
+    /// How far the clock has drifted at least, in milliseconds, positive when it's ahead
+    ///
+    /// A block arriving before its timestamp means the clock is behind, and none arriving within
+    /// [BLOCK_ARRIVAL_ALLOWANCE_MS] means it's ahead. `None` before any blocks are recorded.
+    pub fn drift_ms(&self) -> Option<i64> {
+        let earliest = *self.lags.iter().min()?;
+        Some(if earliest < 0 {
+            earliest
+        } else {
+            (earliest - BLOCK_ARRIVAL_ALLOWANCE_MS).max(0)
+        })
+    }
+}
+
+/// Checks the clock against NTP every [ClockConfig::check_secs], and against each new block
+pub async fn watch_clock(client: Arc<Provider<Ws>>, config: ClockConfig) -> Result<()> {
+    let mut blocks = client
+        .subscribe_blocks()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to subscribe to blocks: {:?}", e))?;
+    let period = Duration::from_secs(config.check_secs.max(1));
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut lags = BlockLags::new(BLOCK_LAG_WINDOW);
+    let mut drifting = false;
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                if let Err(e) = check_ntp(&config).await {
+                    tracing::debug!("[CLOCK] {:?}", e);
+                }
+            }
+            block = blocks.next() => {
+                let block = match block {
+                    Some(block) => block,
+                    None => eyre::bail!("Block subscription ended"),
+                };
+                lags.record(block.timestamp.as_u64(), unix_millis_at(now()));
+                let drift = lags.drift_ms().unwrap_or_default();
+                metrics::global().set("clock_block_drift_ms", drift.unsigned_abs());
+
+                // Warn once when drift appears, not on every block
+                let over = drift.unsigned_abs() > config.max_drift_ms;
+                if over && !drifting {
+                    metrics::increment("clock_drift_warnings");
+                    tracing::warn!(
+                        "[CLOCK] Block timestamps put the clock at least {}ms {}",
+                        drift.unsigned_abs(),
+                        if drift > 0 { "ahead" } else { "behind" }
+                    );
+                }
+                drifting = over;
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Alloy Primitive Conversions
+//!
+//! Conversions between the ethers primitives used throughout the bot and their
+//! [alloy-primitives](https://crates.io/crates/alloy-primitives) equivalents. Only primitives are
+//! covered: providers, signers and transaction types are still ethers.
+
+use alloy_primitives as alloy;
+use ethers::types as ethers_types;
+
+/// Converts an ethers type into its alloy equivalent
+pub trait ToAlloy {
+    /// The alloy type
+    type Alloy;
+
+    /// Converts into the alloy type
+    fn to_alloy(&self) -> Self::Alloy;
+}
+
+/// Converts an alloy type into its ethers equivalent
+pub trait ToEthers {
+    /// The ethers type
+    type Ethers;
+
+    /// Converts into the ethers type
+    fn to_ethers(&self) -> Self::Ethers;
+}
+
+impl ToAlloy for ethers_types::Address {
+    type Alloy = alloy::Address;
+
+    fn to_alloy(&self) -> Self::Alloy {
+        alloy::Address::from_slice(self.as_bytes())
+    }
+}
+
+impl ToEthers for alloy::Address {
+    type Ethers = ethers_types::Address;
+
+    fn to_ethers(&self) -> Self::Ethers {
+        ethers_types::Address::from_slice(self.as_slice())
+    }
+}
+
+impl ToAlloy for ethers_types::H256 {
+    type Alloy = alloy::B256;
+
+    fn to_alloy(&self) -> Self::Alloy {
+        alloy::B256::from_slice(self.as_bytes())
+    }
+}
+
+impl ToEthers for alloy::B256 {
+    type Ethers = ethers_types::H256;
+
+    fn to_ethers(&self) -> Self::Ethers {
+        ethers_types::H256::from_slice(self.as_slice())
+    }
+}
+
+impl ToAlloy for ethers_types::U256 {
+    type Alloy = alloy::U256;
+
+    fn to_alloy(&self) -> Self::Alloy {
+        // Both store little-endian u64 limbs
+        alloy::U256::from_limbs(self.0)
+    }
+}
+
+impl ToEthers for alloy::U256 {
+    type Ethers = ethers_types::U256;
+
+    fn to_ethers(&self) -> Self::Ethers {
+        ethers_types::U256(self.into_limbs())
+    }
+}
+
+impl ToAlloy for ethers_types::Bytes {
+    type Alloy = alloy::Bytes;
+
+    fn to_alloy(&self) -> Self::Alloy {
+        alloy::Bytes::copy_from_slice(self.as_ref())
+    }
+}
+
+impl ToEthers for alloy::Bytes {
+    type Ethers = ethers_types::Bytes;
+
+    fn to_ethers(&self) -> Self::Ethers {
+        ethers_types::Bytes::from(self.to_vec())
+    }
+}
@@ -0,0 +1,602 @@
+//! Bot Configuration
+//!
+//! Optional settings are read from environment variables alongside the required ones in [crate::utils::read_env_vars].
+
+use std::{net::SocketAddr, path::PathBuf, str::FromStr};
+
+use ethers::types::{Address, H256, U256};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounting::{DEFAULT_CONFIRMATIONS, DEFAULT_DEVIATION_BPS},
+    approvals::Approvals,
+    balance::{DEFAULT_BALANCE_CHECK_SECS, DEFAULT_GAS_RESERVE},
+    chain::ChainAddresses,
+    clock::ClockConfig,
+    executor::{ExecutionMode, ExecutorKind},
+    gas::{BaseFeeLimits, DEFAULT_MAX_BUNDLE_GAS_BPS},
+    honeypot::HoneypotConfig,
+    instances,
+    jit::JitConfig,
+    launches::LaunchConfig,
+    liquidations::LiquidationConfig,
+    manual_approval::DEFAULT_APPROVAL_SECS,
+    mempool::{TxSources, DEFAULT_SOURCE_STALL_SECS},
+    metrics::DEFAULT_METRICS_PERSIST_SECS,
+    mev_share::MevShareConfig,
+    numeric::Optimizer,
+    observer::{DEFAULT_OBSERVER_PROBE_SECS, DEFAULT_RELAY_FAILURE_THRESHOLD},
+    orders::OrderSources,
+    pipeline::{
+        DEFAULT_BLOCK_STALL_SECS, DEFAULT_CANDIDATE_BUDGET_MS, DEFAULT_EVAL_QUEUE_SIZE,
+        DEFAULT_INGEST_QUEUE_SIZE,
+    },
+    reserves::ReserveSanity,
+    risk::{ExposureLimits, ReorgGuard},
+    safety::{AddressList, SafetyConfig},
+    simulation::{BuilderPaymentLimits, DEFAULT_VICTIM_TOLERANCE_BPS},
+    skim::SkimConfig,
+    snapshot::DEFAULT_CHAIN_SNAPSHOT_SECS,
+    spam::{SpamLimits, DEFAULT_BOT_STRIKES},
+    state::ReserveConsensus,
+    strategy::ProfitRules,
+    timing::RelayTimings,
+    transport::RelayHttpConfig,
+    uniswap::{QuoteCheck, V2Deployment},
+};
+
+/// The default operations a filter script may take per candidate
+///
+/// Kept here rather than in the scripting module, which is only built with the `scripting` feature.
+pub const DEFAULT_SCRIPT_MAX_OPERATIONS: u64 = 10_000;
+
+/// The default time a remote decider has to execute an opportunity, in milliseconds
+///
+/// Kept here as the gRPC module is only built with the `grpc` feature.
+pub const DEFAULT_REMOTE_DECISION_MS: u64 = 1_000;
+
+/// Reads an optional environment variable, parsing it if present
+///
+/// Inside a named instance, the instance's prefixed variable is preferred.
+pub fn parse_env_var<T: FromStr>(key: &str) -> Result<Option<T>>
+where
+    T::Err: std::fmt::Debug,
+{
+    match instances::var(key) {
+        Ok(value) => value
+            .parse::<T>()
+            .map(Some)
+            .map_err(|e| eyre::eyre!("Invalid value \"{}\" for \"{}\": {:?}", value, key, e)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// The Bot Configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Config {
+    /// The chain id transactions are signed for
+    pub chain_id: u64,
+    /// The tokens and DEX contracts traded through
+    pub addresses: ChainAddresses,
+    /// The deployed executor contract
+    pub sandwich_contract: Option<Address>,
+    /// The executor contract interface
+    pub executor: ExecutorKind,
+    /// How the frontrun leg is funded
+    pub execution_mode: ExecutionMode,
+    /// Whether to shrink sandwiches to the available inventory instead of skipping them
+    pub downsize_to_inventory: bool,
+    /// The off-chain order flow sources to watch
+    pub order_sources: OrderSources,
+    /// The mempool sources to stream pending transactions from
+    pub tx_sources: TxSources,
+    /// Whether to also submit bundles through the bloXroute API
+    pub submit_bloxroute: bool,
+    /// Whether to also propagate bundles through Chainbound Echo
+    pub submit_echo: bool,
+    /// Per-relay submission timing, bundles for other relays are sent immediately
+    pub submission_timing: RelayTimings,
+    /// Connection pooling and socket settings for relay submissions
+    pub relay_http: RelayHttpConfig,
+    /// Clock drift checks against NTP and block timestamps
+    pub clock: ClockConfig,
+    /// Profit thresholds guarding against uncle-bandit and reorg attacks
+    pub reorg_guard: ReorgGuard,
+    /// Caps on the WETH committed to a single token
+    pub exposure_limits: ExposureLimits,
+    /// Checks on the builder payment of the simulated bribed bundle
+    pub builder_payment: BuilderPaymentLimits,
+    /// Profit floors per opportunity class
+    pub profit_rules: ProfitRules,
+    /// Whether computed amounts are cross-checked against the router
+    pub quote_check: QuoteCheck,
+    /// What the frontrun size is optimized for
+    pub optimizer: Optimizer,
+    /// The least revenue, in wei, a return on capital optimized sandwich must make
+    pub min_roc_revenue: U256,
+    /// Extra token approvals the executor contract needs
+    pub approvals: Approvals,
+    /// Whether to set missing executor approvals at startup instead of only warning
+    pub auto_approve: bool,
+    /// Whether legs only execute in the block their bundle targets
+    pub replay_guard: bool,
+    /// How far, in bips, the frontrun output may fall short before the backrun reverts, if checked
+    pub abort_deviation_bps: Option<u64>,
+    /// How far the simulated victim output may drift from the decoded swap, in bips
+    pub victim_tolerance_bps: u64,
+    /// A Rhai script candidates must pass before they're simulated, with the `scripting` feature
+    pub script_filter: Option<PathBuf>,
+    /// How many operations the filter script may take per candidate
+    pub script_max_operations: u64,
+    /// Whether to price sandwiches after competing pending swaps through the same pair
+    pub simulate_competing_swaps: bool,
+    /// How far a pair's reserves may move before they're distrusted as manipulated
+    pub reserve_sanity: ReserveSanity,
+    /// Whether reserve reads are cross-checked against a second provider
+    pub reserve_consensus: ReserveConsensus,
+    /// How far realized profit and gas may drift from the estimates before they're flagged, in bips
+    pub reconcile_deviation_bps: u64,
+    /// Confirmations before a settled sandwich's profit counts as realized
+    pub settlement_confirmations: u64,
+    /// Dust and rate limits applied to decoded swaps before they're evaluated
+    pub spam_limits: SpamLimits,
+    /// Searcher senders and recipient contracts whose swaps are skipped
+    pub known_bots: AddressList,
+    /// Swaps without slippage room after which a sender is skipped as a bot, 0 to never learn
+    pub bot_strike_limit: u32,
+    /// Bait detection settings
+    pub safety: SafetyConfig,
+    /// Third-party honeypot check settings
+    pub honeypot: HoneypotConfig,
+    /// New pair launch watcher settings
+    pub launches: LaunchConfig,
+    /// How long a candidate may take from ingestion to submission, in milliseconds
+    pub candidate_budget_ms: u64,
+    /// How many ingested transactions are buffered before pre-filtering
+    pub ingest_queue_size: usize,
+    /// How many candidates wait for evaluation before the lowest scored are shed
+    pub eval_queue_size: usize,
+    /// How long a mempool source may deliver nothing before it's reconnected, in seconds
+    pub source_stall_secs: u64,
+    /// How long without a new block before the block subscription is restarted, in seconds
+    pub block_stall_secs: u64,
+    /// The searcher balance below which submission pauses, in wei
+    pub gas_reserve: U256,
+    /// How often the searcher balance is checked, in seconds
+    pub balance_check_secs: u64,
+    /// The largest share of the block gas limit a bundle may use, in bips
+    pub max_bundle_gas_bps: u64,
+    /// Whether bundles over the gas budget are split by victim instead of dropped
+    pub split_oversized_bundles: bool,
+    /// Base fee and volatility limits above which submission is skipped
+    pub base_fee_limits: BaseFeeLimits,
+    /// A json lines file shipped opportunities are appended to
+    pub opportunity_log: Option<PathBuf>,
+    /// A json file victim profiles are loaded from and saved to
+    pub victim_profiles: Option<PathBuf>,
+    /// A json file metrics and profit totals are saved to and restored from
+    pub metrics_file: Option<PathBuf>,
+    /// How often metrics are saved, in seconds
+    pub metrics_persist_secs: u64,
+    /// Whether the session report logged at shutdown is also posted to the notification webhook
+    pub notify_shutdown_report: bool,
+    /// A json file the pair tokens, decimals and baiters are primed from and saved to
+    pub chain_snapshot: Option<PathBuf>,
+    /// How often the chain data snapshot is saved, in seconds
+    pub chain_snapshot_secs: u64,
+    /// How many relay requests may fail in a row before switching to observer mode
+    pub relay_failure_threshold: u32,
+    /// How often a candidate is submitted in observer mode to detect recovery, in seconds
+    pub observer_probe_secs: u64,
+    /// Whether bundles are simulated but never sent, always set with the `sim-only` feature
+    pub simulate_only: bool,
+    /// The address to serve the admin API on
+    pub admin_addr: Option<SocketAddr>,
+    /// A json lines file bundle submissions are appended to
+    pub audit_log: Option<PathBuf>,
+    /// Whether to sign audit log records with the searcher key
+    pub sign_audit_log: bool,
+    /// The frontrun size, in wei of WETH, from which sandwiches wait for operator approval
+    pub manual_approval_min_weth: Option<U256>,
+    /// How long an operator has to approve a sandwich before it's skipped, in seconds
+    pub manual_approval_secs: u64,
+    /// The address to serve the gRPC pipeline API on, with the `grpc` feature
+    pub grpc_addr: Option<SocketAddr>,
+    /// Whether shipped opportunities wait for a decision over the gRPC API before submission
+    pub remote_decisions: bool,
+    /// How long a remote decider has to execute an opportunity, in milliseconds
+    pub remote_decision_ms: u64,
+    /// Profit skimming settings
+    pub skim: SkimConfig,
+    /// JIT liquidity settings
+    pub jit: JitConfig,
+    /// Liquidation strategy settings
+    pub liquidations: LiquidationConfig,
+    /// MEV-Share backrun settings
+    pub mev_share: MevShareConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            chain_id: 1,
+            addresses: ChainAddresses::default(),
+            sandwich_contract: None,
+            executor: ExecutorKind::default(),
+            execution_mode: ExecutionMode::default(),
+            downsize_to_inventory: true,
+            order_sources: OrderSources::default(),
+            tx_sources: TxSources::default(),
+            submit_bloxroute: false,
+            submit_echo: false,
+            submission_timing: RelayTimings::default(),
+            relay_http: RelayHttpConfig::default(),
+            clock: ClockConfig::default(),
+            reorg_guard: ReorgGuard::default(),
+            exposure_limits: ExposureLimits::default(),
+            builder_payment: BuilderPaymentLimits::default(),
+            profit_rules: ProfitRules::default(),
+            quote_check: QuoteCheck::default(),
+            optimizer: Optimizer::default(),
+            min_roc_revenue: U256::zero(),
+            approvals: Approvals::default(),
+            auto_approve: false,
+            replay_guard: false,
+            abort_deviation_bps: None,
+            victim_tolerance_bps: DEFAULT_VICTIM_TOLERANCE_BPS,
+            script_filter: None,
+            script_max_operations: DEFAULT_SCRIPT_MAX_OPERATIONS,
+            simulate_competing_swaps: false,
+            reserve_sanity: ReserveSanity::default(),
+            reserve_consensus: ReserveConsensus::default(),
+            reconcile_deviation_bps: DEFAULT_DEVIATION_BPS,
+            settlement_confirmations: DEFAULT_CONFIRMATIONS,
+            spam_limits: SpamLimits::default(),
+            known_bots: AddressList::default(),
+            bot_strike_limit: DEFAULT_BOT_STRIKES,
+            safety: SafetyConfig::default(),
+            honeypot: HoneypotConfig::default(),
+            launches: LaunchConfig::default(),
+            candidate_budget_ms: DEFAULT_CANDIDATE_BUDGET_MS,
+            ingest_queue_size: DEFAULT_INGEST_QUEUE_SIZE,
+            eval_queue_size: DEFAULT_EVAL_QUEUE_SIZE,
+            source_stall_secs: DEFAULT_SOURCE_STALL_SECS,
+            block_stall_secs: DEFAULT_BLOCK_STALL_SECS,
+            gas_reserve: U256::from(DEFAULT_GAS_RESERVE),
+            balance_check_secs: DEFAULT_BALANCE_CHECK_SECS,
+            max_bundle_gas_bps: DEFAULT_MAX_BUNDLE_GAS_BPS,
+            split_oversized_bundles: false,
+            base_fee_limits: BaseFeeLimits::default(),
+            opportunity_log: None,
+            victim_profiles: None,
+            metrics_file: None,
+            metrics_persist_secs: DEFAULT_METRICS_PERSIST_SECS,
+            notify_shutdown_report: false,
+            chain_snapshot: None,
+            chain_snapshot_secs: DEFAULT_CHAIN_SNAPSHOT_SECS,
+            relay_failure_threshold: DEFAULT_RELAY_FAILURE_THRESHOLD,
+            observer_probe_secs: DEFAULT_OBSERVER_PROBE_SECS,
+            simulate_only: cfg!(feature = "sim-only"),
+            admin_addr: None,
+            audit_log: None,
+            sign_audit_log: false,
+            manual_approval_min_weth: None,
+            manual_approval_secs: DEFAULT_APPROVAL_SECS,
+            grpc_addr: None,
+            remote_decisions: false,
+            remote_decision_ms: DEFAULT_REMOTE_DECISION_MS,
+            skim: SkimConfig::default(),
+            jit: JitConfig::default(),
+            liquidations: LiquidationConfig::default(),
+            mev_share: MevShareConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the environment
+    ///
+    /// Every variable is optional, see the README for the full list and defaults.
+    pub fn from_env() -> Result<Self> {
+        let default = Self::default();
+        let config = Self {
+            chain_id: parse_env_var("CHAIN_ID")?.unwrap_or(default.chain_id),
+            addresses: ChainAddresses {
+                weth: parse_env_var("WETH_ADDRESS")?.unwrap_or(default.addresses.weth),
+                usdc: parse_env_var("USDC_ADDRESS")?.unwrap_or(default.addresses.usdc),
+                v2_router: parse_env_var("UNIV2_ROUTER")?.unwrap_or(default.addresses.v2_router),
+                v2: V2Deployment {
+                    factory: parse_env_var("UNIV2_FACTORY")?
+                        .unwrap_or(default.addresses.v2.factory),
+                    init_code_hash: parse_env_var("UNIV2_INIT_CODE_HASH")?
+                        .unwrap_or(default.addresses.v2.init_code_hash),
+                },
+                v3_router: parse_env_var("UNIV3_ROUTER")?.unwrap_or(default.addresses.v3_router),
+                v3_factory: parse_env_var("UNIV3_FACTORY")?.unwrap_or(default.addresses.v3_factory),
+            },
+            sandwich_contract: parse_env_var("SANDWICH_CONTRACT")?,
+            executor: parse_env_var("SANDWICH_EXECUTOR")?.unwrap_or(default.executor),
+            execution_mode: parse_env_var("EXECUTION_MODE")?.unwrap_or(default.execution_mode),
+            downsize_to_inventory: parse_env_var("DOWNSIZE_TO_INVENTORY")?
+                .unwrap_or(default.downsize_to_inventory),
+            order_sources: parse_env_var("ORDER_SOURCES")?.unwrap_or_default(),
+            tx_sources: parse_env_var("TX_SOURCES")?.unwrap_or_default(),
+            submit_bloxroute: parse_env_var("SUBMIT_BLOXROUTE")?
+                .unwrap_or(default.submit_bloxroute),
+            submit_echo: parse_env_var("SUBMIT_ECHO")?.unwrap_or(default.submit_echo),
+            submission_timing: parse_env_var("SUBMISSION_TIMING")?.unwrap_or_default(),
+            relay_http: RelayHttpConfig {
+                http2: parse_env_var("RELAY_HTTP2")?.unwrap_or(default.relay_http.http2),
+                pool_idle_secs: parse_env_var("RELAY_POOL_IDLE_SECS")?
+                    .unwrap_or(default.relay_http.pool_idle_secs),
+                keepalive_secs: parse_env_var("RELAY_KEEPALIVE_SECS")?
+                    .unwrap_or(default.relay_http.keepalive_secs),
+                timeout_ms: parse_env_var("RELAY_TIMEOUT_MS")?
+                    .unwrap_or(default.relay_http.timeout_ms),
+            },
+            clock: ClockConfig {
+                // An empty server disables NTP, leaving only the block timestamp check
+                ntp_server: match parse_env_var::<String>("NTP_SERVER")? {
+                    Some(server) if server.is_empty() => None,
+                    Some(server) => Some(server),
+                    None => default.clock.ntp_server,
+                },
+                max_drift_ms: parse_env_var("MAX_CLOCK_DRIFT_MS")?
+                    .unwrap_or(default.clock.max_drift_ms),
+                check_secs: parse_env_var("CLOCK_CHECK_SECS")?.unwrap_or(default.clock.check_secs),
+                correct: parse_env_var("CORRECT_CLOCK")?.unwrap_or(default.clock.correct),
+            },
+            reorg_guard: ReorgGuard {
+                bribe_threshold: parse_env_var::<u128>("REORG_BRIBE_THRESHOLD")?.map(U256::from),
+                bribe_step_bps: parse_env_var("REORG_BRIBE_STEP_BPS")?
+                    .unwrap_or(default.reorg_guard.bribe_step_bps),
+                max_revenue: parse_env_var::<u128>("REORG_MAX_REVENUE")?.map(U256::from),
+            },
+            exposure_limits: ExposureLimits {
+                max_token_exposure: parse_env_var::<u128>("MAX_TOKEN_EXPOSURE")?.map(U256::from),
+                window_secs: parse_env_var("EXPOSURE_WINDOW_SECS")?
+                    .unwrap_or(default.exposure_limits.window_secs),
+            },
+            builder_payment: BuilderPaymentLimits {
+                min_payment: parse_env_var::<u128>("MIN_BUILDER_PAYMENT")?
+                    .map(U256::from)
+                    .unwrap_or(default.builder_payment.min_payment),
+                tolerance_bps: parse_env_var("BUILDER_PAYMENT_TOLERANCE_BPS")?
+                    .unwrap_or(default.builder_payment.tolerance_bps),
+            },
+            profit_rules: ProfitRules {
+                floors: parse_env_var("PROFIT_FLOORS")?.unwrap_or_default(),
+                blue_chips: parse_env_var("BLUE_CHIP_TOKENS")?.unwrap_or_default(),
+            },
+            quote_check: parse_env_var("QUOTE_CHECK")?.unwrap_or(default.quote_check),
+            optimizer: parse_env_var("OPTIMIZER")?.unwrap_or(default.optimizer),
+            min_roc_revenue: parse_env_var::<u128>("MIN_ROC_REVENUE")?
+                .map(U256::from)
+                .unwrap_or(default.min_roc_revenue),
+            approvals: parse_env_var("EXECUTOR_APPROVALS")?.unwrap_or_default(),
+            auto_approve: parse_env_var("AUTO_APPROVE")?.unwrap_or(default.auto_approve),
+            replay_guard: parse_env_var("REPLAY_GUARD")?.unwrap_or(default.replay_guard),
+            abort_deviation_bps: parse_env_var("ABORT_DEVIATION_BPS")?,
+            victim_tolerance_bps: parse_env_var("VICTIM_TOLERANCE_BPS")?
+                .unwrap_or(default.victim_tolerance_bps),
+            script_filter: parse_env_var("SCRIPT_FILTER")?,
+            script_max_operations: parse_env_var("SCRIPT_MAX_OPERATIONS")?
+                .unwrap_or(default.script_max_operations),
+            simulate_competing_swaps: parse_env_var("SIMULATE_COMPETING_SWAPS")?
+                .unwrap_or(default.simulate_competing_swaps),
+            reserve_sanity: ReserveSanity {
+                max_move_bps: parse_env_var("MAX_RESERVE_MOVE_BPS")?
+                    .unwrap_or(default.reserve_sanity.max_move_bps),
+                max_average_deviation_bps: parse_env_var("MAX_RESERVE_AVERAGE_DEVIATION_BPS")?
+                    .unwrap_or(default.reserve_sanity.max_average_deviation_bps),
+            },
+            reserve_consensus: parse_env_var("RESERVE_CONSENSUS")?
+                .unwrap_or(default.reserve_consensus),
+            reconcile_deviation_bps: parse_env_var("RECONCILE_DEVIATION_BPS")?
+                .unwrap_or(default.reconcile_deviation_bps),
+            settlement_confirmations: parse_env_var("SETTLEMENT_CONFIRMATIONS")?
+                .unwrap_or(default.settlement_confirmations),
+            spam_limits: SpamLimits {
+                sender_limit: parse_env_var("SPAM_SENDER_LIMIT")?
+                    .unwrap_or(default.spam_limits.sender_limit),
+                token_limit: parse_env_var("SPAM_TOKEN_LIMIT")?
+                    .unwrap_or(default.spam_limits.token_limit),
+                window_secs: parse_env_var("SPAM_WINDOW_SECS")?
+                    .unwrap_or(default.spam_limits.window_secs),
+                min_value: parse_env_var::<u128>("MIN_VICTIM_VALUE")?
+                    .map(U256::from)
+                    .unwrap_or(default.spam_limits.min_value),
+            },
+            known_bots: parse_env_var("KNOWN_BOTS")?.unwrap_or_default(),
+            bot_strike_limit: parse_env_var("BOT_STRIKE_LIMIT")?
+                .unwrap_or(default.bot_strike_limit),
+            safety: SafetyConfig {
+                lookback_blocks: parse_env_var("SAFETY_LOOKBACK_BLOCKS")?
+                    .unwrap_or(default.safety.lookback_blocks),
+                max_reserve_swing_bps: parse_env_var("MAX_RESERVE_SWING_BPS")?
+                    .unwrap_or(default.safety.max_reserve_swing_bps),
+                max_risk_score: parse_env_var("MAX_RISK_SCORE")?
+                    .unwrap_or(default.safety.max_risk_score),
+                known_baiters: parse_env_var("KNOWN_BAITERS")?.unwrap_or_default(),
+            },
+            honeypot: HoneypotConfig {
+                apis: parse_env_var("HONEYPOT_APIS")?.unwrap_or_default(),
+                timeout_ms: parse_env_var("HONEYPOT_TIMEOUT_MS")?
+                    .unwrap_or(default.honeypot.timeout_ms),
+                cache_secs: parse_env_var("HONEYPOT_CACHE_SECS")?
+                    .unwrap_or(default.honeypot.cache_secs),
+                max_tax_bps: parse_env_var("MAX_TOKEN_TAX_BPS")?
+                    .unwrap_or(default.honeypot.max_tax_bps),
+                fail_open: parse_env_var("HONEYPOT_FAIL_OPEN")?
+                    .unwrap_or(default.honeypot.fail_open),
+            },
+            launches: LaunchConfig {
+                enabled: parse_env_var("WATCH_LAUNCHES")?.unwrap_or(default.launches.enabled),
+                min_liquidity: parse_env_var::<u128>("LAUNCH_MIN_LIQUIDITY")?
+                    .map(U256::from)
+                    .unwrap_or(default.launches.min_liquidity),
+                track_secs: parse_env_var("LAUNCH_TRACK_SECS")?
+                    .unwrap_or(default.launches.track_secs),
+                min_pair_age_blocks: parse_env_var("MIN_PAIR_AGE_BLOCKS")?
+                    .unwrap_or(default.launches.min_pair_age_blocks),
+                trusted_launches: parse_env_var("TRUSTED_LAUNCHES")?.unwrap_or_default(),
+            },
+            candidate_budget_ms: parse_env_var("CANDIDATE_BUDGET_MS")?
+                .unwrap_or(default.candidate_budget_ms),
+            ingest_queue_size: parse_env_var("INGEST_QUEUE_SIZE")?
+                .unwrap_or(default.ingest_queue_size),
+            eval_queue_size: parse_env_var("EVAL_QUEUE_SIZE")?.unwrap_or(default.eval_queue_size),
+            source_stall_secs: parse_env_var("SOURCE_STALL_SECS")?
+                .unwrap_or(default.source_stall_secs),
+            block_stall_secs: parse_env_var("BLOCK_STALL_SECS")?
+                .unwrap_or(default.block_stall_secs),
+            gas_reserve: parse_env_var::<u128>("GAS_RESERVE")?
+                .map(U256::from)
+                .unwrap_or(default.gas_reserve),
+            balance_check_secs: parse_env_var("BALANCE_CHECK_SECS")?
+                .unwrap_or(default.balance_check_secs),
+            max_bundle_gas_bps: parse_env_var("MAX_BUNDLE_GAS_BPS")?
+                .unwrap_or(default.max_bundle_gas_bps),
+            split_oversized_bundles: parse_env_var("SPLIT_OVERSIZED_BUNDLES")?
+                .unwrap_or(default.split_oversized_bundles),
+            base_fee_limits: BaseFeeLimits {
+                max_base_fee: parse_env_var::<u128>("MAX_BASE_FEE")?.map(U256::from),
+                max_volatility_bps: parse_env_var("MAX_BASE_FEE_VOLATILITY_BPS")?,
+                volatility_blocks: parse_env_var("BASE_FEE_VOLATILITY_BLOCKS")?
+                    .unwrap_or(default.base_fee_limits.volatility_blocks),
+            },
+            opportunity_log: parse_env_var("OPPORTUNITY_LOG")?,
+            victim_profiles: parse_env_var("VICTIM_PROFILES")?,
+            metrics_file: parse_env_var("METRICS_FILE")?,
+            metrics_persist_secs: parse_env_var("METRICS_PERSIST_SECS")?
+                .unwrap_or(default.metrics_persist_secs),
+            notify_shutdown_report: parse_env_var("NOTIFY_SHUTDOWN_REPORT")?
+                .unwrap_or(default.notify_shutdown_report),
+            chain_snapshot: parse_env_var("CHAIN_SNAPSHOT")?,
+            chain_snapshot_secs: parse_env_var("CHAIN_SNAPSHOT_SECS")?
+                .unwrap_or(default.chain_snapshot_secs),
+            relay_failure_threshold: parse_env_var("RELAY_FAILURE_THRESHOLD")?
+                .unwrap_or(default.relay_failure_threshold),
+            observer_probe_secs: parse_env_var("OBSERVER_PROBE_SECS")?
+                .unwrap_or(default.observer_probe_secs),
+            simulate_only: parse_env_var("SIMULATE_ONLY")?.unwrap_or(default.simulate_only)
+                || cfg!(feature = "sim-only"),
+            admin_addr: parse_env_var("ADMIN_ADDR")?,
+            audit_log: parse_env_var("AUDIT_LOG")?,
+            sign_audit_log: parse_env_var("SIGN_AUDIT_LOG")?.unwrap_or(default.sign_audit_log),
+            manual_approval_min_weth: parse_env_var::<u128>("MANUAL_APPROVAL_MIN_WETH")?
+                .map(U256::from),
+            manual_approval_secs: parse_env_var("MANUAL_APPROVAL_SECS")?
+                .unwrap_or(default.manual_approval_secs),
+            grpc_addr: parse_env_var("GRPC_ADDR")?,
+            remote_decisions: parse_env_var("REMOTE_DECISIONS")?
+                .unwrap_or(default.remote_decisions),
+            remote_decision_ms: parse_env_var("REMOTE_DECISION_MS")?
+                .unwrap_or(default.remote_decision_ms),
+            skim: SkimConfig {
+                to: parse_env_var("SKIM_TO")?,
+                working_capital: parse_env_var::<u128>("SKIM_WORKING_CAPITAL")?
+                    .map(U256::from)
+                    .unwrap_or(default.skim.working_capital),
+                wallet_float: parse_env_var::<u128>("SKIM_WALLET_FLOAT")?.map(U256::from),
+                min_skim: parse_env_var::<u128>("SKIM_MIN")?
+                    .map(U256::from)
+                    .unwrap_or(default.skim.min_skim),
+                interval_secs: parse_env_var("SKIM_SECS")?.unwrap_or(default.skim.interval_secs),
+                manual: parse_env_var("SKIM_MANUAL")?.unwrap_or(default.skim.manual),
+            },
+            jit: JitConfig {
+                enabled: parse_env_var("JIT_LIQUIDITY")?.unwrap_or(default.jit.enabled),
+                min_swap_weth: parse_env_var::<u128>("JIT_MIN_SWAP_WETH")?
+                    .map(U256::from)
+                    .unwrap_or(default.jit.min_swap_weth),
+                range_spacings: parse_env_var("JIT_RANGE_SPACINGS")?
+                    .unwrap_or(default.jit.range_spacings),
+            },
+            liquidations: LiquidationConfig {
+                enabled: parse_env_var("LIQUIDATIONS")?.unwrap_or(default.liquidations.enabled),
+                protocols: parse_env_var("LIQUIDATION_PROTOCOLS")?
+                    .unwrap_or(default.liquidations.protocols),
+                oracles: parse_env_var("LIQUIDATION_ORACLES")?.unwrap_or_default(),
+                watch_health_bps: parse_env_var("LIQUIDATION_WATCH_HEALTH_BPS")?
+                    .unwrap_or(default.liquidations.watch_health_bps),
+                watch_liquidity_usd: parse_env_var::<u128>("LIQUIDATION_WATCH_LIQUIDITY_USD")?
+                    .map(U256::from)
+                    .unwrap_or(default.liquidations.watch_liquidity_usd),
+                lookback_blocks: parse_env_var("LIQUIDATION_LOOKBACK_BLOCKS")?
+                    .unwrap_or(default.liquidations.lookback_blocks),
+                refresh_secs: parse_env_var("LIQUIDATION_REFRESH_SECS")?
+                    .unwrap_or(default.liquidations.refresh_secs),
+                priority_fee: parse_env_var::<u128>("LIQUIDATION_PRIORITY_FEE")?
+                    .map(U256::from)
+                    .unwrap_or(default.liquidations.priority_fee),
+                max_per_update: parse_env_var("MAX_LIQUIDATIONS_PER_UPDATE")?
+                    .unwrap_or(default.liquidations.max_per_update),
+                aave_pool: parse_env_var("AAVE_POOL")?.unwrap_or(default.liquidations.aave_pool),
+                comptroller: parse_env_var("COMPOUND_COMPTROLLER")?
+                    .unwrap_or(default.liquidations.comptroller),
+            },
+            mev_share: MevShareConfig {
+                enabled: parse_env_var("MEV_SHARE")?.unwrap_or(default.mev_share.enabled),
+                stream_url: parse_env_var("MEV_SHARE_STREAM_URL")?
+                    .unwrap_or_else(|| default.mev_share.stream_url.clone()),
+                refund_percent: parse_env_var("MEV_SHARE_REFUND_PERCENT")?
+                    .unwrap_or(default.mev_share.refund_percent),
+                twin: match (
+                    parse_env_var::<Address>("MEV_SHARE_TWIN_FACTORY")?,
+                    parse_env_var::<H256>("MEV_SHARE_TWIN_INIT_CODE_HASH")?,
+                ) {
+                    (Some(factory), Some(init_code_hash)) => Some(V2Deployment {
+                        factory,
+                        init_code_hash,
+                    }),
+                    (None, None) => None,
+                    _ => eyre::bail!("Set both or neither \"MEV_SHARE_TWIN_*\" variables"),
+                },
+            },
+        };
+        if config.chain_id != 1 && config.addresses.is_mainnet() {
+            eyre::bail!(
+                "Chain {} needs its own \"WETH_ADDRESS\" and Uniswap addresses",
+                config.chain_id
+            );
+        }
+        if config.ingest_queue_size == 0 || config.eval_queue_size == 0 {
+            eyre::bail!("Queue sizes must be at least 1");
+        }
+        if config.max_bundle_gas_bps > 10_000 {
+            eyre::bail!("\"MAX_BUNDLE_GAS_BPS\" must be at most 10000");
+        }
+        if matches!(config.skim.wallet_float, Some(float) if float < config.gas_reserve) {
+            eyre::bail!("\"SKIM_WALLET_FLOAT\" must be at least the gas reserve");
+        }
+        if config.jit.range_spacings == 0 {
+            eyre::bail!("\"JIT_RANGE_SPACINGS\" must be at least 1");
+        }
+        if config.liquidations.enabled && config.liquidations.protocols.0.is_empty() {
+            eyre::bail!("\"LIQUIDATION_PROTOCOLS\" must name at least one protocol");
+        }
+        if config.mev_share.refund_percent > 100 {
+            eyre::bail!("\"MEV_SHARE_REFUND_PERCENT\" must be at most 100");
+        }
+        if config.mev_share.enabled && config.mev_share.twin.is_none() {
+            eyre::bail!("\"MEV_SHARE\" requires \"MEV_SHARE_TWIN_FACTORY\" to be set");
+        }
+        if config.remote_decisions && config.grpc_addr.is_none() {
+            eyre::bail!("\"REMOTE_DECISIONS\" requires \"GRPC_ADDR\" to be set");
+        }
+        if config.simulate_only
+            && (!config.order_sources.0.is_empty()
+                || config.jit.enabled
+                || config.liquidations.enabled
+                || config.mev_share.enabled
+                || config.skim.to.is_some())
+        {
+            eyre::bail!(
+                "\"SIMULATE_ONLY\" can't run order flow, JIT, liquidations, MEV-Share or skimming"
+            );
+        }
+        Ok(config)
+    }
+}
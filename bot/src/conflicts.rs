@@ -0,0 +1,159 @@
+//! Bundle Conflicts
+//!
+//! Two sandwiches of the same pair in the same block are priced against the same reserves, and two
+//! bundles from one wallet in the same block reuse the same nonces. At most one of each can land,
+//! and submitting both only competes with ourselves. Each bundle claims its block, pair and nonces
+//! with the [ConflictTracker] before it's shipped, and the higher expected value wins.
+//!
+//! Conflicting bundles aren't merged, since each was priced as if it were alone in the block.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use ethers::prelude::*;
+
+/// What a bundle occupies in its target block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleClaim {
+    /// The block the bundle targets
+    pub target_block: U64,
+    /// The sandwiched pair, or whatever else only one bundle per block can take, like the pool a
+    /// JIT position is minted in
+    pub pair: Address,
+    /// The searcher wallet sending the legs
+    pub wallet: Address,
+    /// The first nonce used
+    pub first_nonce: U256,
+    /// The last nonce used
+    pub last_nonce: U256,
+    /// The expected value of the bundle
+    pub value: U256,
+}
+
+impl BundleClaim {
+    /// Whether both bundles can't land in the same block
+    pub fn conflicts_with(&self, other: &BundleClaim) -> bool {
+        if self.target_block != other.target_block {
+            return false;
+        }
+        let nonces_overlap = self.wallet == other.wallet
+            && self.first_nonce <= other.last_nonce
+            && other.first_nonce <= self.last_nonce;
+        self.pair == other.pair || nonces_overlap
+    }
+}
+
+/// A claim held by a bundle waiting to be shipped
+#[derive(Debug, Clone, Default)]
+pub struct ClaimHandle(Arc<AtomicBool>);
+
+impl ClaimHandle {
+    /// Whether a higher value bundle superseded this one
+    pub fn is_superseded(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Supersedes the bundle, so it isn't shipped
+    pub fn supersede(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+}
+
+/// The result of claiming a block
+#[derive(Debug, Clone)]
+pub enum ClaimOutcome {
+    /// The bundle may be shipped, any lower value conflicts were superseded
+    Accepted {
+        /// The claim, checked before each submission
+        handle: ClaimHandle,
+        /// How many conflicting bundles were superseded
+        superseded: usize,
+    },
+    /// A conflicting bundle of at least the same value already holds the block
+    Rejected {
+        /// The value of the conflicting bundle
+        existing_value: U256,
+    },
+}
+
+/// Tracks the claims of in-flight bundles
+#[derive(Debug, Default)]
+pub struct ConflictTracker {
+    claims: Vec<(BundleClaim, ClaimHandle)>,
+}
+
+impl ConflictTracker {
+    /// Creates an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claims a block for a bundle, keeping whichever conflicting bundle has the higher value
+    ///
+    /// Claims for blocks before the new claim's target are forgotten.
+    pub fn claim(&mut self, claim: BundleClaim) -> ClaimOutcome {
+        self.claims
+            .retain(|(c, h)| c.target_block >= claim.target_block && !h.is_superseded());
+
+        if let Some((existing, _)) = self
+            .claims
+            .iter()
+            .filter(|(c, _)| c.conflicts_with(&claim))
+            .max_by_key(|(c, _)| c.value)
+        {
+            if existing.value >= claim.value {
+                return ClaimOutcome::Rejected {
+                    existing_value: existing.value,
+                };
+            }
+        }
+
+        let mut superseded = 0;
+        self.claims.retain(|(c, h)| {
+            if c.conflicts_with(&claim) {
+                h.0.store(true, Ordering::Relaxed);
+                superseded += 1;
+                false
+            } else {
+                true
+            }
+        });
+        let handle = ClaimHandle::default();
+        self.claims.push((claim, handle.clone()));
+        ClaimOutcome::Accepted { handle, superseded }
+    }
+
+    /// Supersedes the bundles of `wallet` using `nonce`, now that it was taken outside a bundle,
+    /// returning how many were superseded
+    pub fn release_nonce(&mut self, wallet: Address, nonce: U256) -> usize {
+        let mut superseded = 0;
+        self.claims.retain(|(c, h)| {
+            let uses = c.wallet == wallet && c.first_nonce <= nonce && nonce <= c.last_nonce;
+            if uses {
+                h.supersede();
+                superseded += 1;
+            }
+            !uses
+        });
+        superseded
+    }
+
+    /// Whether `wallet` has bundles in flight targeting `block` or later
+    pub fn is_pending(&self, wallet: Address, block: U64) -> bool {
+        self.claims
+            .iter()
+            .any(|(c, h)| c.wallet == wallet && c.target_block >= block && !h.is_superseded())
+    }
+
+    /// The number of live claims
+    pub fn len(&self) -> usize {
+        self.claims.len()
+    }
+
+    /// Whether there are no live claims
+    pub fn is_empty(&self) -> bool {
+        self.claims.is_empty()
+    }
+}
@@ -0,0 +1,418 @@
+//! Executor Control
+//!
+//! The owner of the executor contract can pause sandwiches and hand the contract to another
+//! wallet. `subway pause`, `subway unpause`, `subway transfer-ownership <address>` and
+//! `subway executor-status` send or read these from the searcher wallet without running the bot.
+//!
+//! The [KillSwitch] halts the running bot: candidates are skipped and nothing is submitted. A halt
+//! also pauses the executor contract on-chain, so legs that were already signed and handed to
+//! builders revert instead of executing. Resuming unpauses it again.
+
+use std::sync::{Arc, OnceLock};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    executor::{self, SubwayExecutor},
+    metrics,
+    notify::Notifier,
+    transactions::{LegFees, TxFactory},
+};
+
+/// The gas limit for pause, unpause and ownership transfer calls
+pub const CONTROL_GAS_LIMIT: u64 = 60_000;
+
+/// An admin action on the executor contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Pause sandwiches
+    Pause,
+    /// Unpause sandwiches
+    Unpause,
+    /// Hand the contract to a new owner
+    TransferOwnership(Address),
+    /// Print the owner and pause flag
+    Status,
+}
+
+impl ControlCommand {
+    /// Parses the command line arguments after the binary name
+    ///
+    /// Returns `None` if the first argument isn't a control command.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>> {
+        let command = match args.first().map(String::as_str) {
+            Some("pause") => Self::Pause,
+            Some("unpause") => Self::Unpause,
+            Some("executor-status") => Self::Status,
+            Some("transfer-ownership") => {
+                let owner = args
+                    .get(1)
+                    .ok_or_else(|| eyre::eyre!("Usage: subway transfer-ownership <address>"))?;
+                let owner = owner
+                    .parse::<Address>()
+                    .map_err(|_| eyre::eyre!("Invalid address \"{}\"", owner))?;
+                Self::TransferOwnership(owner)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(command))
+    }
+
+    /// Encodes the executor call, `None` for read-only commands
+    pub fn calldata(&self, tx_factory: &TxFactory) -> Result<Option<Bytes>> {
+        let executor = &tx_factory.executor;
+        match self {
+            Self::Pause => executor.encode_pause(true).map(Some),
+            Self::Unpause => executor.encode_pause(false).map(Some),
+            Self::TransferOwnership(owner) => executor.encode_transfer_ownership(*owner).map(Some),
+            Self::Status => Ok(None),
+        }
+    }
+}
+
+/// The owner and pause flag of the executor contract
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExecutorStatus {
+    /// The wallet allowed to call the executor
+    pub owner: Address,
+    /// Whether sandwiches revert
+    pub paused: bool,
+}
+
+/// Decodes the 32 byte return values of `owner()` and `paused()`
+pub fn decode_status(owner: &[u8], paused: &[u8]) -> Result<ExecutorStatus> {
+    if owner.len() != 32 || paused.len() != 32 {
+        eyre::bail!("Executor returned malformed status");
+    }
+    Ok(ExecutorStatus {
+        owner: Address::from_slice(&owner[12..]),
+        paused: paused[31] != 0,
+    })
+}
+
+/// Calls a getter of the executor contract at `executor`, returning the raw return data
+async fn read_executor<M: Middleware + 'static>(
+    client: &M,
+    executor: Address,
+    signature: &str,
+) -> Result<Bytes> {
+    let tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+        to: Some(NameOrAddress::Address(executor)),
+        data: Some(Bytes::from(ethers::utils::id(signature).to_vec())),
+        ..Default::default()
+    });
+    client
+        .call(&tx, None)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to read executor {}: {:?}", signature, e))
+}
+
+/// Reads the owner and pause flag of the executor contract at `executor`
+pub async fn executor_status<M: Middleware + 'static>(
+    client: Arc<M>,
+    executor: Address,
+) -> Result<ExecutorStatus> {
+    let owner = read_executor(&*client, executor, SubwayExecutor::OWNER_SIGNATURE).await?;
+    let paused = read_executor(&*client, executor, SubwayExecutor::PAUSED_SIGNATURE).await?;
+    decode_status(&owner, &paused)
+}
+
+/// Decodes the 32 byte return value of `payloadVersion()`
+pub fn decode_payload_version(version: &[u8]) -> Result<u64> {
+    if version.len() != 32 || version[..24].iter().any(|b| *b != 0) {
+        eyre::bail!("Executor returned a malformed payload version");
+    }
+    Ok(u64::from_be_bytes(version[24..].try_into()?))
+}
+
+/// Whether an `eth_call` error is the call reverting, rather than the node failing to run it
+pub fn is_revert(message: &str) -> bool {
+    message.to_lowercase().contains("revert")
+}
+
+/// Reads the payload version of the executor contract at `executor`
+///
+/// Contracts deployed before they reported a version revert the getter, and are assumed to be
+/// [SubwayExecutor::UNVERSIONED_PAYLOAD_VERSION]. That includes deployments that already checked
+/// the replay guard, so those must be redeployed to use it. Other failures are returned.
+pub async fn payload_version<M: Middleware + 'static>(
+    client: Arc<M>,
+    executor: Address,
+) -> Result<u64> {
+    let code = client
+        .get_code(executor, None)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to read executor code: {:?}", e))?;
+    if code.as_ref().is_empty() {
+        eyre::bail!("No executor contract is deployed at {:?}", executor);
+    }
+    match read_executor(
+        &*client,
+        executor,
+        SubwayExecutor::PAYLOAD_VERSION_SIGNATURE,
+    )
+    .await
+    {
+        Ok(version) => decode_payload_version(&version),
+        Err(e) if is_revert(&e.to_string()) => {
+            tracing::debug!("[CONTROL] {:?}", e);
+            Ok(SubwayExecutor::UNVERSIONED_PAYLOAD_VERSION)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Errors unless the deployed executor contract understands every payload the bot will send
+pub async fn check_executor_version<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: &TxFactory,
+) -> Result<()> {
+    let executor = &tx_factory.executor;
+    if executor.payload_version().is_none() {
+        return Ok(());
+    }
+    let deployed = payload_version(client, executor.address()).await?;
+    executor::check_payload_version(executor.as_ref(), deployed, &tx_factory.capabilities())?;
+    tracing::info!(
+        "[CONTROL] Executor {:?} has payload version {}",
+        executor.address(),
+        deployed
+    );
+    Ok(())
+}
+
+/// Whether deployed bytecode dispatches `selector`, which function dispatchers push with `PUSH4`
+pub fn dispatches(code: &[u8], selector: [u8; 4]) -> bool {
+    code.windows(5).any(|w| w[0] == 0x63 && w[1..] == selector)
+}
+
+/// Errors unless the contract at `address` dispatches every function in `signatures`, which
+/// `feature` calls
+pub async fn require_functions<M: Middleware + 'static>(
+    client: Arc<M>,
+    address: Address,
+    feature: &str,
+    signatures: &[&str],
+) -> Result<()> {
+    let code = client
+        .get_code(address, None)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch executor code: {:?}", e))?;
+    let missing = signatures
+        .iter()
+        .filter(|signature| !dispatches(&code, ethers::utils::id(signature)))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        eyre::bail!(
+            "{} needs an executor implementing {:?}, the one at {:?} doesn't",
+            feature,
+            missing,
+            address
+        );
+    }
+    Ok(())
+}
+
+/// The getter of an executor that lets wallets other than its owner call it
+pub const IS_OPERATOR_SIGNATURE: &str = "isOperator(address)";
+
+/// Errors unless the executor at `executor` lets every wallet in `wallets` call it
+///
+/// The Huff contract only answers to its owner, so extra searcher wallets need an executor with an
+/// operator allow-list.
+pub async fn require_operators<M: Middleware + 'static>(
+    client: Arc<M>,
+    executor: Address,
+    wallets: &[Address],
+) -> Result<()> {
+    require_functions(
+        client.clone(),
+        executor,
+        "Extra searcher wallets",
+        &[IS_OPERATOR_SIGNATURE],
+    )
+    .await?;
+    for wallet in wallets {
+        let mut data = ethers::utils::id(IS_OPERATOR_SIGNATURE).to_vec();
+        data.extend(ethers::abi::encode(&[ethers::abi::Token::Address(*wallet)]));
+        let tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+            to: Some(NameOrAddress::Address(executor)),
+            data: Some(Bytes::from(data)),
+            ..Default::default()
+        });
+        let operator = client
+            .call(&tx, None)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to read executor operators: {:?}", e))?;
+        if operator.len() != 32 || operator[31] != 1 {
+            eyre::bail!(
+                "Searcher wallet {:?} isn't an operator of the executor at {:?}",
+                wallet,
+                executor
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Sends a call to the executor contract from the searcher wallet, returning the transaction hash
+///
+/// The call pays twice the latest base fee with a 1 gwei tip, admin calls aren't time sensitive.
+/// Its nonce comes from the factory's [crate::transactions::NonceManager], superseding any
+/// in-flight bundle that reused it.
+pub async fn send_executor_call<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: &TxFactory,
+    data: Bytes,
+    gas_limit: u64,
+) -> Result<TxHash> {
+    // Holding the nonce until the call is sent keeps other direct transactions off it
+    let (nonce, _nonce_guard) = tx_factory
+        .nonces
+        .direct(&*client, tx_factory.searcher())
+        .await?;
+    let base_fee = client
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch latest block: {:?}", e))?
+        .and_then(|b| b.base_fee_per_gas)
+        .ok_or_else(|| eyre::eyre!("Latest block is missing a base fee"))?;
+    let fees = LegFees {
+        max_fee_per_gas: base_fee * 2,
+        max_priority_fee_per_gas: U256::from(1_000_000_000u64),
+        gas_limit: U256::from(gas_limit),
+    };
+    let tx = tx_factory.call(
+        tx_factory.executor.address(),
+        data,
+        U256::zero(),
+        nonce,
+        &fees,
+    );
+    let signed = tx_factory.sign(&tx).await?;
+    let pending = client
+        .send_raw_transaction(signed)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to send executor transaction: {:?}", e))?;
+    Ok(*pending)
+}
+
+/// Runs a control command from the command line
+pub async fn run_command<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: &TxFactory,
+    command: ControlCommand,
+) -> Result<()> {
+    if !tx_factory.executor.supports_pause() {
+        eyre::bail!(
+            "Executor \"{}\" has no owner controls",
+            tx_factory.executor.name()
+        );
+    }
+    let executor = tx_factory.executor.address();
+    if let ControlCommand::TransferOwnership(owner) = command {
+        if !tx_factory.searchers().contains(&owner) {
+            tracing::warn!(
+                "[CONTROL] {:?} isn't a searcher wallet, the bot can't execute through the executor after the transfer",
+                owner
+            );
+        }
+    }
+    match command.calldata(tx_factory)? {
+        Some(data) => {
+            let hash = send_executor_call(client, tx_factory, data, CONTROL_GAS_LIMIT).await?;
+            tracing::info!("[CONTROL] Sent {:?} to {:?}: {:?}", command, executor, hash);
+        }
+        None => {
+            let status = executor_status(client, executor).await?;
+            tracing::info!(
+                "[CONTROL] Executor {:?} is owned by {:?}{}",
+                executor,
+                status.owner,
+                if status.paused { " and paused" } else { "" }
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Halts sandwiching in an emergency
+///
+/// Cloning shares the switch. Halting is idempotent, and only changes wake up subscribers.
+#[derive(Debug, Clone)]
+pub struct KillSwitch(Arc<watch::Sender<bool>>);
+
+impl Default for KillSwitch {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(false).0))
+    }
+}
+
+impl KillSwitch {
+    /// Halts the bot, returning whether it was running
+    pub fn halt(&self) -> bool {
+        self.0
+            .send_if_modified(|halted| !std::mem::replace(halted, true))
+    }
+
+    /// Resumes the bot, returning whether it was halted
+    pub fn resume(&self) -> bool {
+        self.0
+            .send_if_modified(|halted| std::mem::replace(halted, false))
+    }
+
+    /// Whether the bot is halted
+    pub fn is_halted(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Subscribes to halts and resumes
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.0.subscribe()
+    }
+}
+
+/// Returns the global kill switch
+pub fn kill_switch() -> &'static KillSwitch {
+    static KILL_SWITCH: OnceLock<KillSwitch> = OnceLock::new();
+    KILL_SWITCH.get_or_init(KillSwitch::default)
+}
+
+/// Pauses the executor contract whenever `switch` halts, and unpauses it on resume
+///
+/// Only changes of the switch are applied, so an executor paused from the command line stays paused
+/// while the bot runs. A halt is applied again when the task restarts, so a failed pause is retried.
+pub async fn watch_kill_switch<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: Arc<TxFactory>,
+    switch: KillSwitch,
+    notifier: Notifier,
+) -> Result<()> {
+    let mut halts = switch.subscribe();
+    let executor = tx_factory.executor.address();
+    let mut paused = false;
+    loop {
+        let halted = *halts.borrow_and_update();
+        if halted != paused {
+            let data = tx_factory.executor.encode_pause(halted)?;
+            let hash =
+                send_executor_call(client.clone(), &tx_factory, data, CONTROL_GAS_LIMIT).await?;
+            paused = halted;
+            let action = if halted { "Pausing" } else { "Unpausing" };
+            metrics::increment(if halted {
+                "executor_paused"
+            } else {
+                "executor_unpaused"
+            });
+            notifier
+                .notify(&format!("{} executor {:?}: {:?}", action, executor, hash))
+                .await;
+        }
+        if halts.changed().await.is_err() {
+            return Ok(());
+        }
+    }
+}
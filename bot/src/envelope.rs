@@ -0,0 +1,290 @@
+//! Transaction Envelopes
+//!
+//! ethers only knows the legacy, access list and dynamic fee transaction types. Blob transactions
+//! ([EIP-4844](https://eips.ethereum.org/EIPS/eip-4844), type 3) and set code transactions
+//! ([EIP-7702](https://eips.ethereum.org/EIPS/eip-7702), type 4) still deserialize into a
+//! [Transaction], with the fields ethers doesn't know left in [Transaction::other], but
+//! [Transaction::rlp] silently encodes them as legacy transactions.
+//!
+//! This module fills that gap: [from_json] normalizes a pending transaction from any source,
+//! [decode_raw] reads raw type 3 and type 4 envelopes, and [encode_raw] re-encodes any supported
+//! type, checking the result against the transaction hash before it can end up in a bundle.
+//!
+//! Pending blob transactions arrive without their blob sidecar, and relays refuse blob transactions
+//! in bundles without one, so [bundleable] keeps them out of bundles.
+
+use ethers::{prelude::*, utils::keccak256};
+use eyre::Result;
+use rlp::{Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+
+/// The blob transaction type
+pub const BLOB_TX_TYPE: u64 = 3;
+
+/// The set code transaction type
+pub const SET_CODE_TX_TYPE: u64 = 4;
+
+/// A transaction's envelope type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TxType {
+    /// Type 0
+    Legacy,
+    /// Type 1, [EIP-2930](https://eips.ethereum.org/EIPS/eip-2930)
+    AccessList,
+    /// Type 2, [EIP-1559](https://eips.ethereum.org/EIPS/eip-1559)
+    DynamicFee,
+    /// Type 3, [EIP-4844](https://eips.ethereum.org/EIPS/eip-4844)
+    Blob,
+    /// Type 4, [EIP-7702](https://eips.ethereum.org/EIPS/eip-7702)
+    SetCode,
+    /// A type the bot can't encode
+    Unknown(u64),
+}
+
+impl TxType {
+    /// The envelope type of a transaction, legacy if it has none
+    pub fn of(tx: &Transaction) -> Self {
+        match tx.transaction_type.map(|t| t.as_u64()).unwrap_or_default() {
+            0 => Self::Legacy,
+            1 => Self::AccessList,
+            2 => Self::DynamicFee,
+            BLOB_TX_TYPE => Self::Blob,
+            SET_CODE_TX_TYPE => Self::SetCode,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// An EIP-7702 authorization, delegating an account's code to `address`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Authorization {
+    /// The chain the authorization is valid on, zero for any
+    pub chain_id: U256,
+    /// The delegate
+    pub address: Address,
+    /// The authority's nonce
+    pub nonce: U64,
+    /// The signature's y parity
+    pub y_parity: U64,
+    /// The signature's r
+    pub r: U256,
+    /// The signature's s
+    pub s: U256,
+}
+
+/// Normalizes a pending transaction's json into a [Transaction]
+///
+/// Some sources only send `yParity` for typed transactions, which ethers requires as `v`.
+pub fn from_json(mut value: serde_json::Value) -> Result<Transaction> {
+    if let Some(fields) = value.as_object_mut() {
+        if !fields.contains_key("v") {
+            if let Some(y_parity) = fields.get("yParity").cloned() {
+                fields.insert("v".to_string(), y_parity);
+            }
+        }
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// The versioned hashes of a blob transaction's blobs
+pub fn blob_versioned_hashes(tx: &Transaction) -> Result<Vec<H256>> {
+    other_field(tx, "blobVersionedHashes")
+}
+
+/// The maximum fee per blob gas of a blob transaction
+pub fn max_fee_per_blob_gas(tx: &Transaction) -> Result<U256> {
+    other_field(tx, "maxFeePerBlobGas")
+}
+
+/// The authorizations of a set code transaction
+pub fn authorization_list(tx: &Transaction) -> Result<Vec<Authorization>> {
+    other_field(tx, "authorizationList")
+}
+
+fn other_field<T: serde::de::DeserializeOwned>(tx: &Transaction, key: &str) -> Result<T> {
+    let value = tx
+        .other
+        .get(key)
+        .ok_or_else(|| eyre::eyre!("Transaction {:?} has no {}", tx.hash, key))?;
+    Ok(serde_json::from_value(value.clone())?)
+}
+
+/// Whether a pending transaction can be bundled, which blob transactions without their sidecar and
+/// unknown types can't
+pub fn bundleable(tx: &Transaction) -> bool {
+    !matches!(TxType::of(tx), TxType::Blob | TxType::Unknown(_))
+}
+
+/// Encodes a signed transaction as it was broadcast, for bundling it
+///
+/// Fails if the transaction's type isn't supported, a field its type requires is missing, or the
+/// encoding doesn't hash to the transaction's hash. Blob transactions are encoded without their
+/// blob sidecar, which the pending transaction doesn't carry, so check [bundleable] first.
+pub fn encode_raw(tx: &Transaction) -> Result<Bytes> {
+    let encoded = match TxType::of(tx) {
+        TxType::Legacy | TxType::AccessList | TxType::DynamicFee => tx.rlp(),
+        TxType::Blob => encode_typed(BLOB_TX_TYPE, tx)?,
+        TxType::SetCode => encode_typed(SET_CODE_TX_TYPE, tx)?,
+        TxType::Unknown(t) => eyre::bail!("Unsupported transaction type {}", t),
+    };
+    let hash = H256::from(keccak256(&encoded));
+    if hash != tx.hash {
+        eyre::bail!(
+            "Re-encoded transaction {:?} hashes to {:?} instead",
+            tx.hash,
+            hash
+        );
+    }
+    Ok(encoded)
+}
+
+/// Decodes a raw signed type 3 or type 4 envelope, recovering its sender
+///
+/// Blob transactions are accepted with or without their blob sidecar.
+pub fn decode_raw(raw: &[u8]) -> Result<Transaction> {
+    let (&tx_type, payload) = raw
+        .split_first()
+        .ok_or_else(|| eyre::eyre!("Empty transaction"))?;
+    let tx_type = u64::from(tx_type);
+    if tx_type != BLOB_TX_TYPE && tx_type != SET_CODE_TX_TYPE {
+        eyre::bail!("Unsupported transaction type {}", tx_type);
+    }
+
+    // The network form of a blob transaction wraps the transaction with its blobs
+    let outer = Rlp::new(payload);
+    let (rlp, signed) = if tx_type == BLOB_TX_TYPE && outer.at(0)?.is_list() {
+        let inner = outer.at(0)?;
+        let mut signed = vec![tx_type as u8];
+        signed.extend_from_slice(inner.as_raw());
+        (inner, signed)
+    } else {
+        (outer, raw.to_vec())
+    };
+    let expected = if tx_type == BLOB_TX_TYPE { 14 } else { 13 };
+    if rlp.item_count()? != expected {
+        eyre::bail!(
+            "Type {} transaction has {} fields, expected {}",
+            tx_type,
+            rlp.item_count()?,
+            expected
+        );
+    }
+
+    let mut other = OtherFields::default();
+    let signature_at = if tx_type == BLOB_TX_TYPE {
+        other.insert(
+            "maxFeePerBlobGas".to_string(),
+            serde_json::to_value(rlp.val_at::<U256>(9)?)?,
+        );
+        other.insert(
+            "blobVersionedHashes".to_string(),
+            serde_json::to_value(rlp.list_at::<H256>(10)?)?,
+        );
+        11
+    } else {
+        let authorizations = rlp
+            .at(9)?
+            .iter()
+            .map(|a| {
+                Ok(Authorization {
+                    chain_id: a.val_at(0)?,
+                    address: a.val_at(1)?,
+                    nonce: a.val_at(2)?,
+                    y_parity: a.val_at(3)?,
+                    r: a.val_at(4)?,
+                    s: a.val_at(5)?,
+                })
+            })
+            .collect::<Result<Vec<_>, rlp::DecoderError>>()?;
+        other.insert(
+            "authorizationList".to_string(),
+            serde_json::to_value(authorizations)?,
+        );
+        10
+    };
+
+    // The signature covers every field before it
+    let mut unsigned = RlpStream::new_list(signature_at);
+    for i in 0..signature_at {
+        unsigned.append_raw(rlp.at(i)?.as_raw(), 1);
+    }
+    let mut sighash = vec![tx_type as u8];
+    sighash.extend_from_slice(&unsigned.out());
+    let v: U64 = rlp.val_at(signature_at)?;
+    let r: U256 = rlp.val_at(signature_at + 1)?;
+    let s: U256 = rlp.val_at(signature_at + 2)?;
+    let from = Signature {
+        r,
+        s,
+        v: v.as_u64(),
+    }
+    .recover(H256::from(keccak256(&sighash)))?;
+
+    let max_fee_per_gas: U256 = rlp.val_at(3)?;
+    Ok(Transaction {
+        hash: H256::from(keccak256(&signed)),
+        nonce: rlp.val_at(1)?,
+        from,
+        to: Some(rlp.val_at(5)?),
+        value: rlp.val_at(6)?,
+        gas_price: Some(max_fee_per_gas),
+        gas: rlp.val_at(4)?,
+        input: Bytes::from(rlp.val_at::<Vec<u8>>(7)?),
+        v,
+        r,
+        s,
+        transaction_type: Some(U64::from(tx_type)),
+        access_list: Some(rlp.val_at(8)?),
+        max_priority_fee_per_gas: Some(rlp.val_at(2)?),
+        max_fee_per_gas: Some(max_fee_per_gas),
+        chain_id: Some(rlp.val_at(0)?),
+        other,
+        ..Default::default()
+    })
+}
+
+/// Encodes a type 3 or type 4 transaction from its fields
+fn encode_typed(tx_type: u64, tx: &Transaction) -> Result<Bytes> {
+    let missing = |field: &str| eyre::eyre!("Type {} transaction has no {}", tx_type, field);
+    let to = tx.to.ok_or_else(|| missing("recipient"))?;
+    let chain_id = tx.chain_id.ok_or_else(|| missing("chain id"))?;
+    let max_priority_fee_per_gas = tx
+        .max_priority_fee_per_gas
+        .ok_or_else(|| missing("max priority fee"))?;
+    let max_fee_per_gas = tx.max_fee_per_gas.ok_or_else(|| missing("max fee"))?;
+
+    let mut rlp = RlpStream::new_list(if tx_type == BLOB_TX_TYPE { 14 } else { 13 });
+    rlp.append(&chain_id);
+    rlp.append(&tx.nonce);
+    rlp.append(&max_priority_fee_per_gas);
+    rlp.append(&max_fee_per_gas);
+    rlp.append(&tx.gas);
+    rlp.append(&to);
+    rlp.append(&tx.value);
+    rlp.append(&tx.input.to_vec());
+    rlp.append(&tx.access_list.clone().unwrap_or_default());
+    if tx_type == BLOB_TX_TYPE {
+        rlp.append(&max_fee_per_blob_gas(tx)?);
+        rlp.append_list::<H256, H256>(&blob_versioned_hashes(tx)?);
+    } else {
+        let authorizations = authorization_list(tx)?;
+        rlp.begin_list(authorizations.len());
+        for a in &authorizations {
+            rlp.begin_list(6);
+            rlp.append(&a.chain_id);
+            rlp.append(&a.address);
+            rlp.append(&a.nonce);
+            rlp.append(&a.y_parity);
+            rlp.append(&a.r);
+            rlp.append(&a.s);
+        }
+    }
+    rlp.append(&tx.v);
+    rlp.append(&tx.r);
+    rlp.append(&tx.s);
+
+    let mut encoded = vec![tx_type as u8];
+    encoded.extend_from_slice(&rlp.out());
+    Ok(Bytes::from(encoded))
+}
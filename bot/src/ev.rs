@@ -0,0 +1,172 @@
+//! Expected Value
+//!
+//! A bundle only pays off if it lands, and how often it lands depends on how much of the revenue
+//! is bribed away and how contested the opportunity is. The [InclusionModel] tracks the inclusion
+//! rate of shipped bundles by bribe decile and victim swap size, and candidates are ranked by
+//! their expected value, the estimated profit weighted by that rate, rather than the raw estimate.
+//!
+//! Buckets without much history lean on the rate for the same swap size at any bribe, which leans
+//! on the prior, so the model starts out ranking exactly like the raw estimate.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, OnceLock},
+};
+
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The inclusion rate assumed before any outcomes are recorded, in bips
+pub const DEFAULT_INCLUSION_PRIOR_BPS: u64 = 5_000;
+
+/// How many outcomes the prior is worth
+pub const PRIOR_WEIGHT: u64 = 4;
+
+/// The width of a bribe bucket, in bips of revenue
+pub const BRIBE_BUCKET_BPS: u64 = 1_000;
+
+/// The smallest swap size bucket, swaps of up to 0.01 ETH
+pub const SIZE_BUCKET_UNIT: u64 = 10_000_000_000_000_000;
+
+/// The largest swap size bucket, swaps of a few hundred ETH and above
+pub const MAX_SIZE_BUCKET: u8 = 16;
+
+/// Shipped bundles and how many of them landed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionStats {
+    /// Bundles whose target block was seen
+    pub submitted: u64,
+    /// Bundles whose frontrun landed in the target block
+    pub included: u64,
+}
+
+impl InclusionStats {
+    /// The inclusion rate in bips, smoothed towards `prior_bps`
+    pub fn probability_bps(&self, prior_bps: u64) -> u64 {
+        let included = self
+            .included
+            .saturating_mul(10_000)
+            .saturating_add(prior_bps.saturating_mul(PRIOR_WEIGHT));
+        included / self.submitted.saturating_add(PRIOR_WEIGHT)
+    }
+
+    fn record(&mut self, included: bool) {
+        self.submitted = self.submitted.saturating_add(1);
+        if included {
+            self.included = self.included.saturating_add(1);
+        }
+    }
+}
+
+/// The bribe bucket of a bribe, in bips of revenue
+pub fn bribe_bucket(bribe_bps: u64) -> u8 {
+    (bribe_bps.min(9_999) / BRIBE_BUCKET_BPS) as u8
+}
+
+/// The size bucket of a victim swap, doubling with each bucket
+pub fn size_bucket(swap_value: U256) -> u8 {
+    let units = swap_value / U256::from(SIZE_BUCKET_UNIT);
+    (units.bits() as u8).min(MAX_SIZE_BUCKET)
+}
+
+/// Inclusion rates by bribe and swap size bucket
+#[derive(Debug)]
+pub struct InclusionModel {
+    prior_bps: u64,
+    buckets: Mutex<BTreeMap<(u8, u8), InclusionStats>>,
+}
+
+impl Default for InclusionModel {
+    fn default() -> Self {
+        Self::new(DEFAULT_INCLUSION_PRIOR_BPS)
+    }
+}
+
+impl InclusionModel {
+    /// Creates a model assuming `prior_bps` before any outcomes are recorded
+    pub fn new(prior_bps: u64) -> Self {
+        Self {
+            prior_bps: prior_bps.min(10_000),
+            buckets: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Records whether a bundle bribing `bribe_bps` of its revenue, around a victim swapping
+    /// `swap_value`, landed
+    pub fn record(&self, bribe_bps: u64, swap_value: U256, included: bool) {
+        self.buckets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((bribe_bucket(bribe_bps), size_bucket(swap_value)))
+            .or_default()
+            .record(included);
+    }
+
+    /// The inclusion rate of swaps of `swap_value` at any bribe, in bips
+    pub fn size_probability_bps(&self, swap_value: U256) -> u64 {
+        let size = size_bucket(swap_value);
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        Self::size_stats(&buckets, size).probability_bps(self.prior_bps)
+    }
+
+    /// The inclusion rate of a bundle bribing `bribe_bps` around a swap of `swap_value`, in bips
+    pub fn probability_bps(&self, bribe_bps: u64, swap_value: U256) -> u64 {
+        let size = size_bucket(swap_value);
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let size_prior = Self::size_stats(&buckets, size).probability_bps(self.prior_bps);
+        buckets
+            .get(&(bribe_bucket(bribe_bps), size))
+            .copied()
+            .unwrap_or_default()
+            .probability_bps(size_prior)
+    }
+
+    /// The expected value of a bundle: its estimated profit weighted by its inclusion rate
+    ///
+    /// Bundles pay nothing unless they land, so the costs are weighted along with the revenue.
+    pub fn expected_value(
+        &self,
+        bribe_bps: u64,
+        swap_value: U256,
+        revenue: U256,
+        costs: U256,
+    ) -> U256 {
+        weigh(
+            revenue.saturating_sub(costs),
+            self.probability_bps(bribe_bps, swap_value),
+        )
+    }
+
+    /// Weighs a queue score by the inclusion rate of the swap's size
+    pub fn weigh_score(&self, swap_value: U256, score: U256) -> U256 {
+        weigh(score, self.size_probability_bps(swap_value))
+    }
+
+    /// The recorded outcomes by `(bribe bucket, size bucket)`
+    pub fn snapshot(&self) -> BTreeMap<(u8, u8), InclusionStats> {
+        self.buckets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn size_stats(buckets: &BTreeMap<(u8, u8), InclusionStats>, size: u8) -> InclusionStats {
+        buckets.iter().filter(|((_, s), _)| *s == size).fold(
+            InclusionStats::default(),
+            |total, (_, stats)| InclusionStats {
+                submitted: total.submitted.saturating_add(stats.submitted),
+                included: total.included.saturating_add(stats.included),
+            },
+        )
+    }
+}
+
+fn weigh(value: U256, probability_bps: u64) -> U256 {
+    value.saturating_mul(U256::from(probability_bps)) / 10_000
+}
+
+/// Returns the process-wide inclusion model
+pub fn inclusion_model() -> &'static InclusionModel {
+    static MODEL: OnceLock<InclusionModel> = OnceLock::new();
+    MODEL.get_or_init(InclusionModel::default)
+}
@@ -0,0 +1,240 @@
+//! Sandwich Evaluation
+//!
+//! [evaluate_sandwich] runs the detection and pricing steps of the bot on a single pending
+//! transaction: it decodes the swap, reads the pairs along its path from a [StateProvider] and
+//! returns a priced [SandwichPlan] with both legs, or `None` if the swap can't be sandwiched. It
+//! doesn't simulate, check safety or sign anything, so it can be embedded in other runtimes.
+//!
+//! The pricing itself is [price_sandwich], which the bot's own loop sizes its sandwiches with too.
+
+use std::{collections::HashMap, sync::Arc};
+
+use ethers::prelude::*;
+use eyre::Result;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::{self, UniswapV2Pair},
+    executor::SwapLeg,
+    numeric::{self, SandwichContext},
+    uniswap,
+};
+
+/// Reads Uniswap V2 pair reserves
+pub trait StateProvider {
+    /// Returns a pair's reserves, ordered as `(reserve0, reserve1)` like `getReserves`
+    fn reserves(&self, pair: Address) -> BoxFuture<'_, Result<(U256, U256)>>;
+}
+
+/// Reserves held in memory, like a snapshot or a cache kept up to date by `Sync` events
+impl StateProvider for HashMap<Address, (U256, U256)> {
+    fn reserves(&self, pair: Address) -> BoxFuture<'_, Result<(U256, U256)>> {
+        let reserves = self
+            .get(&pair)
+            .copied()
+            .ok_or_else(|| eyre::eyre!("No reserves for pair {:?}", pair));
+        Box::pin(async move { reserves })
+    }
+}
+
+/// Reads reserves from a node
+#[derive(Debug, Clone)]
+pub struct ProviderState<M> {
+    client: Arc<M>,
+    block: Option<BlockId>,
+}
+
+impl<M: Middleware + 'static> ProviderState<M> {
+    /// Reads the latest reserves through `client`
+    pub fn new(client: Arc<M>) -> Self {
+        Self {
+            client,
+            block: None,
+        }
+    }
+
+    /// Reads reserves at `block` instead of the latest block
+    pub fn at(mut self, block: impl Into<BlockId>) -> Self {
+        self.block = Some(block.into());
+        self
+    }
+}
+
+impl<M: Middleware + 'static> StateProvider for ProviderState<M> {
+    fn reserves(&self, pair: Address) -> BoxFuture<'_, Result<(U256, U256)>> {
+        Box::pin(async move {
+            let contract = UniswapV2Pair::new(pair, self.client.clone());
+            let mut call = contract.get_reserves();
+            if let Some(block) = self.block {
+                call = call.block(block);
+            }
+            let (reserve0, reserve1, _) = call
+                .call()
+                .await
+                .map_err(|e| eyre::eyre!("Failed to get reserves of {:?}: {:?}", pair, e))?;
+            Ok((U256::from(reserve0), U256::from(reserve1)))
+        })
+    }
+}
+
+/// A priced sandwich of a pending swap
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SandwichPlan {
+    /// The victim transaction
+    pub victim: TxHash,
+    /// The sandwiched pair
+    pub pair: Address,
+    /// The token bought from the pair
+    pub token: Address,
+    /// The minimum amount of `token` the victim accepts from the pair
+    pub user_min_recv: U256,
+    /// The priced sandwich
+    pub context: SandwichContext,
+    /// The frontrun, buying `token` with WETH
+    pub frontrun: SwapLeg,
+    /// The backrun, selling `token` back to WETH
+    pub backrun: SwapLeg,
+}
+
+/// Reads the pair's reserves in `deployment` ordered as `(a, b)`
+async fn ordered_reserves<S: StateProvider + ?Sized>(
+    state: &S,
+    deployment: &uniswap::V2Deployment,
+    a: Address,
+    b: Address,
+) -> Result<(Address, U256, U256)> {
+    let sorted = uniswap::SortedPair::new(a, b);
+    let pair = sorted.address_in(deployment);
+    let (reserve_a, reserve_b) = sorted.orient(a, state.reserves(pair).await?);
+    Ok((pair, reserve_a, reserve_b))
+}
+
+/// The least of `path[1]` the victim accepts for its swap to still meet `final_min_recv`
+///
+/// Walks the path backwards from the final output, like
+/// [uniswap::get_univ2_exact_weth_token_min_recv], with reserves read from `state`.
+pub async fn min_recv_through_path<S: StateProvider + ?Sized>(
+    state: &S,
+    final_min_recv: U256,
+    path: &[Address],
+) -> Result<U256> {
+    min_recv_through_path_in(
+        state,
+        &uniswap::V2Deployment::default(),
+        final_min_recv,
+        path,
+    )
+    .await
+}
+
+/// [min_recv_through_path] through the pairs of `deployment`
+pub async fn min_recv_through_path_in<S: StateProvider + ?Sized>(
+    state: &S,
+    deployment: &uniswap::V2Deployment,
+    final_min_recv: U256,
+    path: &[Address],
+) -> Result<U256> {
+    let mut min_recv = final_min_recv;
+    for hop in path.windows(2).skip(1).rev() {
+        let (_, from_reserves, to_reserves) =
+            ordered_reserves(state, deployment, hop[0], hop[1]).await?;
+        (min_recv, _, _) =
+            uniswap::get_univ2_data_given_out(&min_recv, &from_reserves, &to_reserves);
+    }
+    Ok(min_recv)
+}
+
+/// Prices the optimal sandwich of a victim swapping `user_amount_in` WETH for at least
+/// `user_min_recv` of `token` through `pair`, with its reserves ordered as `(weth, token)`
+///
+/// Returns `None` when the victim's slippage leaves nothing to sandwich.
+pub fn price_sandwich(
+    victim: TxHash,
+    weth: Address,
+    pair: Address,
+    token: Address,
+    user_amount_in: U256,
+    user_min_recv: U256,
+    (weth_reserves, token_reserves): (U256, U256),
+) -> Result<Option<SandwichPlan>> {
+    let optimal_weth_in = numeric::calculate_sandwich_optimal_in(
+        &user_amount_in,
+        &user_min_recv,
+        &weth_reserves,
+        &token_reserves,
+    );
+    if optimal_weth_in.is_zero() {
+        return Ok(None);
+    }
+    let context = numeric::calculate_sandwich_context(
+        &optimal_weth_in,
+        &user_amount_in,
+        &user_min_recv,
+        &weth_reserves,
+        &token_reserves,
+    )?;
+    if context.revenue.is_zero() {
+        return Ok(None);
+    }
+
+    Ok(Some(SandwichPlan {
+        victim,
+        pair,
+        token,
+        user_min_recv,
+        context,
+        frontrun: SwapLeg {
+            token_in: weth,
+            token_out: token,
+            pair,
+            amount_in: context.optimal_weth_in,
+            amount_out: context.frontrun.amount_out,
+        },
+        backrun: SwapLeg {
+            token_in: token,
+            token_out: weth,
+            pair,
+            amount_in: context.frontrun.amount_out,
+            amount_out: context.backrun.amount_out,
+        },
+    }))
+}
+
+/// Prices a sandwich of a pending `swapExactETHForTokens` through `router`
+///
+/// Returns `None` when the transaction isn't a WETH swap through the router, its deadline passed
+/// by `now`, or the optimal sandwich makes no revenue. Errors if `state` can't provide a pair on
+/// the path, or the sandwich can't be priced.
+pub async fn evaluate_sandwich<S: StateProvider + ?Sized>(
+    tx: &Transaction,
+    router: Address,
+    weth: Address,
+    now: u64,
+    state: &S,
+) -> Result<Option<SandwichPlan>> {
+    if tx.to != Some(router) {
+        return Ok(None);
+    }
+    let decoded = match abi::decode_uniswap_router_calldata(&tx.input) {
+        Ok(d) => d,
+        Err(_) => return Ok(None),
+    };
+    if decoded.path.len() < 2 || decoded.path[0] != weth || U256::from(now) > decoded.deadline {
+        return Ok(None);
+    }
+    let token = decoded.path[1];
+
+    let user_min_recv = min_recv_through_path(state, decoded.amount_out_min, &decoded.path).await?;
+    let (pair, weth_reserves, token_reserves) =
+        ordered_reserves(state, &uniswap::V2Deployment::default(), weth, token).await?;
+    price_sandwich(
+        tx.hash,
+        weth,
+        pair,
+        token,
+        tx.value,
+        user_min_recv,
+        (weth_reserves, token_reserves),
+    )
+}
@@ -0,0 +1,771 @@
+//! Sandwich Executor Contract Adapters
+//!
+//! The bot never talks to a sandwich contract directly, it goes through an [Executor].
+//! Each executor knows how to encode the calldata for its own contract ABI, so a custom
+//! contract can be swapped in without touching the payload-encoding code in the bot loop.
+
+use std::{fmt, str::FromStr};
+
+use ethers::{
+    abi::{self, Token},
+    prelude::*,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{approvals::Approval, liquidations::Liquidation, orders::OrderFill};
+
+/// A single swap executed through the sandwich contract
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SwapLeg {
+    /// The token transferred into the pair
+    pub token_in: Address,
+    /// The token received from the pair
+    pub token_out: Address,
+    /// The pair to swap through
+    pub pair: Address,
+    /// The amount of `token_in` sent to the pair
+    pub amount_in: U256,
+    /// The amount of `token_out` to receive from the pair
+    pub amount_out: U256,
+}
+
+impl SwapLeg {
+    /// The index of the output token in the pair
+    ///
+    /// Pairs order their tokens by address, so the output token is `token0` if it sorts before the input.
+    pub fn token_out_no(&self) -> u8 {
+        if self.token_out < self.token_in {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Returns the `(amount0Out, amount1Out)` arguments for `pair.swap`
+    pub fn amounts_out(&self) -> (U256, U256) {
+        match self.token_out_no() {
+            0 => (self.amount_out, U256::zero()),
+            _ => (U256::zero(), self.amount_out),
+        }
+    }
+}
+
+/// How the frontrun leg is funded
+///
+/// There's no flash loan mode: a loan must be repaid within the call that takes it, but the
+/// frontrun only buys the token, and the victim and backrun trade in later transactions. The
+/// repayment could only come from WETH the executor already holds, making the loan pointless.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum ExecutionMode {
+    /// Swap WETH already held by the executor contract
+    #[default]
+    Inventory,
+    /// Send ETH as the frontrun transaction value, wrapped by the executor before swapping
+    Value,
+}
+
+impl FromStr for ExecutionMode {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "inventory" | "contract" => Ok(ExecutionMode::Inventory),
+            "value" => Ok(ExecutionMode::Value),
+            _ => Err(eyre::eyre!("Unknown execution mode \"{}\"", s)),
+        }
+    }
+}
+
+/// A Sandwich Executor Contract
+///
+/// Implementors encode the frontrun and backrun legs of a sandwich into calldata for their contract.
+pub trait Executor: fmt::Debug + Send + Sync {
+    /// The name of the executor
+    fn name(&self) -> &'static str;
+
+    /// The deployed executor contract address
+    fn address(&self) -> Address;
+
+    /// Whether the executor contract can fund the frontrun in the given mode
+    fn supports(&self, mode: ExecutionMode) -> bool {
+        mode == ExecutionMode::Inventory
+    }
+
+    /// The transaction value to send with the frontrun leg
+    fn frontrun_value(&self, leg: &SwapLeg, mode: ExecutionMode) -> U256 {
+        match mode {
+            ExecutionMode::Inventory => U256::zero(),
+            ExecutionMode::Value => leg.amount_in,
+        }
+    }
+
+    /// Encodes the calldata for the frontrun leg
+    fn encode_frontrun(&self, leg: &SwapLeg, mode: ExecutionMode) -> Result<Bytes>;
+
+    /// Encodes the calldata for the backrun leg
+    ///
+    /// Backrun proceeds are always left as WETH on the executor, regardless of the frontrun funding mode.
+    fn encode_backrun(&self, leg: &SwapLeg) -> Result<Bytes>;
+
+    /// Whether the executor contract can restrict a leg to a single block
+    fn supports_replay_guard(&self) -> bool {
+        false
+    }
+
+    /// Restricts encoded leg calldata to execute only in `block`
+    ///
+    /// A signed leg pulled out of its bundle then reverts in any other block, so it can't be
+    /// replayed on its own, like after its bundle landed in an uncled block.
+    fn guard_block(&self, _: Bytes, _: U64) -> Result<Bytes> {
+        eyre::bail!(
+            "Executor \"{}\" does not support replay guards",
+            self.name()
+        )
+    }
+
+    /// Whether the executor contract can check its token balance before a leg
+    fn supports_balance_guard(&self) -> bool {
+        false
+    }
+
+    /// Makes block guarded leg calldata revert unless the executor holds `min_balance` of the
+    /// leg's input token
+    ///
+    /// Checked on a backrun, this aborts the bundle when the frontrun delivered less than expected,
+    /// like when state drifted between simulation and inclusion.
+    fn guard_balance(&self, _: Bytes, _: U256) -> Result<Bytes> {
+        eyre::bail!(
+            "Executor \"{}\" does not support balance guards",
+            self.name()
+        )
+    }
+
+    /// Encodes calldata making the executor contract grant each approval for the max amount
+    fn encode_approvals(&self, _: &[Approval]) -> Result<Bytes> {
+        eyre::bail!("Executor \"{}\" cannot set token approvals", self.name())
+    }
+
+    /// Encodes calldata making the executor contract send `amount` of `token` to `to`
+    fn encode_withdrawal(&self, _: Address, _: Address, _: U256) -> Result<Bytes> {
+        eyre::bail!("Executor \"{}\" cannot withdraw tokens", self.name())
+    }
+
+    /// Whether the executor contract can mint and burn V3 liquidity
+    fn supports_jit(&self) -> bool {
+        false
+    }
+
+    /// The functions the deployed executor contract must implement to mint and burn V3 liquidity,
+    /// including the pool's mint callback
+    fn jit_functions(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Encodes calldata minting a V3 position owned by the executor contract
+    fn encode_mint(&self, _: &LiquidityPosition) -> Result<Bytes> {
+        eyre::bail!("Executor \"{}\" cannot mint liquidity", self.name())
+    }
+
+    /// Encodes calldata burning a V3 position and collecting its tokens and fees
+    fn encode_burn(&self, _: &LiquidityPosition) -> Result<Bytes> {
+        eyre::bail!("Executor \"{}\" cannot burn liquidity", self.name())
+    }
+
+    /// Whether the executor contract can repay a borrower's debt from its inventory
+    fn supports_liquidations(&self) -> bool {
+        false
+    }
+
+    /// Encodes calldata approving and calling a lending protocol liquidation
+    fn encode_liquidation(&self, _: &Liquidation) -> Result<Bytes> {
+        eyre::bail!("Executor \"{}\" cannot liquidate positions", self.name())
+    }
+
+    /// Whether the executor contract can fill UniswapX orders from its inventory
+    fn supports_order_fills(&self) -> bool {
+        false
+    }
+
+    /// Encodes calldata filling an order and swapping its input back into the output token
+    fn encode_order_fill(&self, _: &OrderFill) -> Result<Bytes> {
+        eyre::bail!("Executor \"{}\" cannot fill orders", self.name())
+    }
+
+    /// Whether the executor contract can be paused and its ownership transferred
+    fn supports_pause(&self) -> bool {
+        false
+    }
+
+    /// Encodes calldata pausing or unpausing sandwiches on the executor contract
+    fn encode_pause(&self, _: bool) -> Result<Bytes> {
+        eyre::bail!("Executor \"{}\" cannot be paused", self.name())
+    }
+
+    /// Encodes calldata transferring ownership of the executor contract
+    fn encode_transfer_ownership(&self, _: Address) -> Result<Bytes> {
+        eyre::bail!(
+            "Executor \"{}\" does not support ownership transfers",
+            self.name()
+        )
+    }
+
+    /// The payload version the adapter encodes, for executor contracts reporting theirs
+    fn payload_version(&self) -> Option<u64> {
+        None
+    }
+
+    /// The first payload version of the executor contract with `capability`
+    fn capability_version(&self, _: PayloadCapability) -> u64 {
+        1
+    }
+}
+
+/// A payload feature the deployed executor contract must understand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadCapability {
+    /// Swap legs
+    Legs,
+    /// A target block, see [Executor::guard_block]
+    ReplayGuard,
+    /// A minimum token balance, see [Executor::guard_balance]
+    BalanceGuard,
+}
+
+/// Errors unless an executor contract reporting payload version `deployed` can execute legs with
+/// every capability
+///
+/// A contract newer than the adapter is refused too, since its payload format may have changed.
+pub fn check_payload_version(
+    executor: &dyn Executor,
+    deployed: u64,
+    capabilities: &[PayloadCapability],
+) -> Result<()> {
+    let encoded = match executor.payload_version() {
+        Some(version) => version,
+        None => return Ok(()),
+    };
+    if deployed > encoded {
+        eyre::bail!(
+            "Executor \"{}\" at {:?} has payload version {}, newer than the {} this bot encodes",
+            executor.name(),
+            executor.address(),
+            deployed,
+            encoded
+        );
+    }
+    for capability in capabilities {
+        let required = executor.capability_version(*capability);
+        if deployed < required {
+            eyre::bail!(
+                "Executor \"{}\" at {:?} has payload version {}, {:?} needs version {}, redeploy it",
+                executor.name(),
+                executor.address(),
+                deployed,
+                capability,
+                required
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Errors if the executor does not support the execution mode
+pub fn ensure_supported(executor: &dyn Executor, mode: ExecutionMode) -> Result<()> {
+    if !executor.supports(mode) {
+        eyre::bail!(
+            "Executor \"{}\" does not support {:?} execution mode",
+            executor.name(),
+            mode
+        );
+    }
+    Ok(())
+}
+
+/// A concentrated liquidity position the executor contract owns in a V3 pool
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LiquidityPosition {
+    /// The pool
+    pub pool: Address,
+    /// The lower tick of the range
+    pub tick_lower: i32,
+    /// The upper tick of the range
+    pub tick_upper: i32,
+    /// The liquidity minted or burned
+    pub liquidity: u128,
+}
+
+/// The supported executor contract kinds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum ExecutorKind {
+    /// The classic subway huff contract
+    #[default]
+    Subway,
+    /// A V3-capable executor with an abi-encoded swap entrypoint
+    V3,
+    /// A generic multicall executor
+    Multicall,
+}
+
+impl FromStr for ExecutorKind {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "subway" | "classic" => Ok(ExecutorKind::Subway),
+            "v3" => Ok(ExecutorKind::V3),
+            "multicall" => Ok(ExecutorKind::Multicall),
+            _ => Err(eyre::eyre!("Unknown executor kind \"{}\"", s)),
+        }
+    }
+}
+
+impl ExecutorKind {
+    /// Constructs the executor for a deployed contract address
+    pub fn build(self, address: Address) -> Box<dyn Executor> {
+        match self {
+            ExecutorKind::Subway => Box::new(SubwayExecutor::new(address)),
+            ExecutorKind::V3 => Box::new(V3Executor::new(address)),
+            ExecutorKind::Multicall => Box::new(MulticallExecutor::new(address)),
+        }
+    }
+}
+
+/// Builds an executor of the given kind for the `SANDWICH_CONTRACT` address
+pub fn get_executor(kind: ExecutorKind) -> Result<Box<dyn Executor>> {
+    let address = crate::utils::get_sandwich_contract_address()?;
+    Ok(kind.build(address))
+}
+
+/// Converts a U256 into a u128, erroring if it would truncate
+fn checked_u128(value: U256) -> Result<u128> {
+    if value > U256::from(u128::MAX) {
+        eyre::bail!("Amount {} overflows uint128", value);
+    }
+    Ok(value.as_u128())
+}
+
+/// The classic subway huff contract
+///
+/// Calldata is tightly packed as
+/// `[tag (uint8), token, pair, amountIn (uint128), amountOut (uint128), tokenOutNo (uint8)]`,
+/// optionally followed by the `targetBlock (uint64)` the leg must execute in, and then by the
+/// `minBalance (uint128)` of the token the contract must hold before the leg. The owner can pause
+/// sandwiches, withdraw tokens and transfer ownership through abi-encoded admin functions. The
+/// leading [SubwayExecutor::SANDWICH_TAG] keeps a token address from being read as their selector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubwayExecutor {
+    /// The contract address
+    pub address: Address,
+}
+
+impl SubwayExecutor {
+    /// The first byte of every packed payload, no admin function selector starts with it
+    pub const SANDWICH_TAG: u8 = 0x00;
+
+    /// The length of the packed payload
+    pub const PAYLOAD_LENGTH: usize = 74;
+
+    /// The length of the packed payload with a target block
+    pub const GUARDED_PAYLOAD_LENGTH: usize = 82;
+
+    /// The length of the packed payload with a target block and a minimum balance
+    pub const CHECKED_PAYLOAD_LENGTH: usize = 98;
+
+    /// The payload version encoded, matching `PAYLOAD_VERSION` in the contract
+    pub const PAYLOAD_VERSION: u64 = 4;
+
+    /// The version assumed for contracts deployed before they reported one
+    pub const UNVERSIONED_PAYLOAD_VERSION: u64 = 1;
+
+    /// The payload version getter signature
+    pub const PAYLOAD_VERSION_SIGNATURE: &'static str = "payloadVersion()";
+
+    /// The withdrawal function signature
+    pub const WITHDRAW_SIGNATURE: &'static str = "withdrawERC20(address,address,uint256)";
+
+    /// The pause function signature
+    pub const PAUSE_SIGNATURE: &'static str = "pause()";
+
+    /// The unpause function signature
+    pub const UNPAUSE_SIGNATURE: &'static str = "unpause()";
+
+    /// The ownership transfer function signature
+    pub const TRANSFER_OWNERSHIP_SIGNATURE: &'static str = "transferOwnership(address)";
+
+    /// The owner getter signature
+    pub const OWNER_SIGNATURE: &'static str = "owner()";
+
+    /// The pause flag getter signature
+    pub const PAUSED_SIGNATURE: &'static str = "paused()";
+
+    /// Creates a new subway executor
+    pub fn new(address: Address) -> Self {
+        Self { address }
+    }
+
+    /// Packs a swap leg
+    pub fn encode_leg(leg: &SwapLeg) -> Result<Bytes> {
+        let mut payload = [0u8; Self::PAYLOAD_LENGTH];
+        payload[0] = Self::SANDWICH_TAG;
+        payload[1..21].copy_from_slice(leg.token_in.as_bytes());
+        payload[21..41].copy_from_slice(leg.pair.as_bytes());
+        payload[41..57].copy_from_slice(&checked_u128(leg.amount_in)?.to_be_bytes());
+        payload[57..73].copy_from_slice(&checked_u128(leg.amount_out)?.to_be_bytes());
+        payload[73] = leg.token_out_no();
+        Ok(Bytes::from(payload.to_vec()))
+    }
+}
+
+impl Executor for SubwayExecutor {
+    fn name(&self) -> &'static str {
+        "subway"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn encode_frontrun(&self, leg: &SwapLeg, mode: ExecutionMode) -> Result<Bytes> {
+        ensure_supported(self, mode)?;
+        Self::encode_leg(leg)
+    }
+
+    fn encode_backrun(&self, leg: &SwapLeg) -> Result<Bytes> {
+        Self::encode_leg(leg)
+    }
+
+    fn supports_replay_guard(&self) -> bool {
+        true
+    }
+
+    fn guard_block(&self, payload: Bytes, block: U64) -> Result<Bytes> {
+        if payload.len() != Self::PAYLOAD_LENGTH {
+            eyre::bail!("Can't guard a {} byte payload", payload.len());
+        }
+        let mut guarded = payload.to_vec();
+        guarded.extend(block.as_u64().to_be_bytes());
+        Ok(Bytes::from(guarded))
+    }
+
+    fn supports_balance_guard(&self) -> bool {
+        true
+    }
+
+    fn guard_balance(&self, payload: Bytes, min_balance: U256) -> Result<Bytes> {
+        if payload.len() != Self::GUARDED_PAYLOAD_LENGTH {
+            eyre::bail!(
+                "Can't check the balance of a {} byte payload",
+                payload.len()
+            );
+        }
+        let mut checked = payload.to_vec();
+        checked.extend(checked_u128(min_balance)?.to_be_bytes());
+        Ok(Bytes::from(checked))
+    }
+
+    fn payload_version(&self) -> Option<u64> {
+        Some(Self::PAYLOAD_VERSION)
+    }
+
+    fn capability_version(&self, capability: PayloadCapability) -> u64 {
+        // Untagged payloads can't be executed since version 4, whatever they carry
+        match capability {
+            PayloadCapability::Legs
+            | PayloadCapability::ReplayGuard
+            | PayloadCapability::BalanceGuard => 4,
+        }
+    }
+
+    fn encode_withdrawal(&self, token: Address, to: Address, amount: U256) -> Result<Bytes> {
+        let mut calldata = ethers::utils::id(Self::WITHDRAW_SIGNATURE).to_vec();
+        calldata.extend(abi::encode(&[
+            Token::Address(token),
+            Token::Address(to),
+            Token::Uint(amount),
+        ]));
+        Ok(Bytes::from(calldata))
+    }
+
+    fn supports_pause(&self) -> bool {
+        true
+    }
+
+    fn encode_pause(&self, paused: bool) -> Result<Bytes> {
+        let signature = if paused {
+            Self::PAUSE_SIGNATURE
+        } else {
+            Self::UNPAUSE_SIGNATURE
+        };
+        Ok(Bytes::from(ethers::utils::id(signature).to_vec()))
+    }
+
+    fn encode_transfer_ownership(&self, owner: Address) -> Result<Bytes> {
+        if owner.is_zero() {
+            eyre::bail!("Can't transfer ownership to the zero address");
+        }
+        let mut calldata = ethers::utils::id(Self::TRANSFER_OWNERSHIP_SIGNATURE).to_vec();
+        calldata.extend(abi::encode(&[Token::Address(owner)]));
+        Ok(Bytes::from(calldata))
+    }
+}
+
+/// A V3-capable executor
+///
+/// Calls `swap(address tokenIn, address pool, uint256 amountIn, uint256 amountOut, bool zeroForOne)`.
+/// The swap entrypoint is payable and wraps any `msg.value` into WETH before swapping.
+///
+/// Liquidity is provided with `mintLiquidity(address pool, int24 tickLower, int24 tickUpper, uint128 liquidity)`,
+/// paying the pool from the contract's inventory in the mint callback, and removed with
+/// `burnLiquidity(..)`, which burns the position and collects its tokens and fees back to the contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct V3Executor {
+    /// The contract address
+    pub address: Address,
+}
+
+impl V3Executor {
+    /// The swap function signature
+    pub const SWAP_SIGNATURE: &'static str = "swap(address,address,uint256,uint256,bool)";
+
+    /// The liquidity mint function signature
+    pub const MINT_SIGNATURE: &'static str = "mintLiquidity(address,int24,int24,uint128)";
+
+    /// The liquidity burn function signature
+    pub const BURN_SIGNATURE: &'static str = "burnLiquidity(address,int24,int24,uint128)";
+
+    /// The callback V3 pools pay a mint's tokens through
+    pub const MINT_CALLBACK_SIGNATURE: &'static str =
+        "uniswapV3MintCallback(uint256,uint256,bytes)";
+
+    /// Creates a new V3 executor
+    pub fn new(address: Address) -> Self {
+        Self { address }
+    }
+
+    /// Abi-encodes a swap leg
+    pub fn encode_leg(leg: &SwapLeg) -> Result<Bytes> {
+        let args = abi::encode(&[
+            Token::Address(leg.token_in),
+            Token::Address(leg.pair),
+            Token::Uint(leg.amount_in),
+            Token::Uint(leg.amount_out),
+            Token::Bool(leg.token_out_no() == 1),
+        ]);
+        let mut calldata = ethers::utils::id(Self::SWAP_SIGNATURE).to_vec();
+        calldata.extend(args);
+        Ok(Bytes::from(calldata))
+    }
+
+    /// Abi-encodes a liquidity call, sign-extending the ticks to 256 bits
+    pub fn encode_position(signature: &str, position: &LiquidityPosition) -> Bytes {
+        let tick = |tick: i32| Token::Int(I256::from(tick).into_raw());
+        let mut calldata = ethers::utils::id(signature).to_vec();
+        calldata.extend(abi::encode(&[
+            Token::Address(position.pool),
+            tick(position.tick_lower),
+            tick(position.tick_upper),
+            Token::Uint(U256::from(position.liquidity)),
+        ]));
+        Bytes::from(calldata)
+    }
+}
+
+impl Executor for V3Executor {
+    fn name(&self) -> &'static str {
+        "v3"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn supports(&self, _: ExecutionMode) -> bool {
+        true
+    }
+
+    fn encode_frontrun(&self, leg: &SwapLeg, _: ExecutionMode) -> Result<Bytes> {
+        Self::encode_leg(leg)
+    }
+
+    fn encode_backrun(&self, leg: &SwapLeg) -> Result<Bytes> {
+        Self::encode_leg(leg)
+    }
+
+    fn supports_jit(&self) -> bool {
+        true
+    }
+
+    fn jit_functions(&self) -> Vec<&'static str> {
+        vec![
+            Self::MINT_SIGNATURE,
+            Self::BURN_SIGNATURE,
+            Self::MINT_CALLBACK_SIGNATURE,
+        ]
+    }
+
+    fn encode_mint(&self, position: &LiquidityPosition) -> Result<Bytes> {
+        Ok(Self::encode_position(Self::MINT_SIGNATURE, position))
+    }
+
+    fn encode_burn(&self, position: &LiquidityPosition) -> Result<Bytes> {
+        Ok(Self::encode_position(Self::BURN_SIGNATURE, position))
+    }
+}
+
+/// A generic multicall executor
+///
+/// Calls `multicall((address target, uint256 value, bytes data)[])` with a token transfer into the pair
+/// followed by the pair swap, so any contract exposing an owner-gated multicall can execute sandwiches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulticallExecutor {
+    /// The contract address
+    pub address: Address,
+}
+
+impl MulticallExecutor {
+    /// The multicall function signature
+    pub const MULTICALL_SIGNATURE: &'static str = "multicall((address,uint256,bytes)[])";
+
+    /// Creates a new multicall executor
+    pub fn new(address: Address) -> Self {
+        Self { address }
+    }
+
+    /// Encodes a single call tuple
+    pub fn encode_call(target: Address, value: U256, data: Vec<u8>) -> Token {
+        Token::Tuple(vec![
+            Token::Address(target),
+            Token::Uint(value),
+            Token::Bytes(data),
+        ])
+    }
+
+    /// Encodes a list of calls into multicall calldata
+    pub fn encode_calls(calls: Vec<Token>) -> Bytes {
+        let mut calldata = ethers::utils::id(Self::MULTICALL_SIGNATURE).to_vec();
+        calldata.extend(abi::encode(&[Token::Array(calls)]));
+        Bytes::from(calldata)
+    }
+
+    /// Encodes the `token.transfer(pair, amountIn)` and `pair.swap(..)` calls for a leg
+    pub fn leg_calls(&self, leg: &SwapLeg) -> Vec<Token> {
+        let mut transfer = ethers::utils::id("transfer(address,uint256)").to_vec();
+        transfer.extend(abi::encode(&[
+            Token::Address(leg.pair),
+            Token::Uint(leg.amount_in),
+        ]));
+
+        let (amount0_out, amount1_out) = leg.amounts_out();
+        let mut swap = ethers::utils::id("swap(uint256,uint256,address,bytes)").to_vec();
+        swap.extend(abi::encode(&[
+            Token::Uint(amount0_out),
+            Token::Uint(amount1_out),
+            Token::Address(self.address),
+            Token::Bytes(vec![]),
+        ]));
+
+        vec![
+            Self::encode_call(leg.token_in, U256::zero(), transfer),
+            Self::encode_call(leg.pair, U256::zero(), swap),
+        ]
+    }
+
+    /// Encodes the `weth.deposit{value: amountIn}()` call wrapping the frontrun value
+    pub fn deposit_call(leg: &SwapLeg) -> Token {
+        let deposit = ethers::utils::id("deposit()").to_vec();
+        Self::encode_call(leg.token_in, leg.amount_in, deposit)
+    }
+
+    /// Encodes a leg into multicall calldata
+    pub fn encode_leg(&self, leg: &SwapLeg) -> Result<Bytes> {
+        Ok(Self::encode_calls(self.leg_calls(leg)))
+    }
+}
+
+impl Executor for MulticallExecutor {
+    fn name(&self) -> &'static str {
+        "multicall"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn supports(&self, _: ExecutionMode) -> bool {
+        true
+    }
+
+    fn encode_frontrun(&self, leg: &SwapLeg, mode: ExecutionMode) -> Result<Bytes> {
+        let mut calls = self.leg_calls(leg);
+        match mode {
+            ExecutionMode::Inventory => {}
+            ExecutionMode::Value => calls.insert(0, Self::deposit_call(leg)),
+        }
+        Ok(Self::encode_calls(calls))
+    }
+
+    fn encode_backrun(&self, leg: &SwapLeg) -> Result<Bytes> {
+        self.encode_leg(leg)
+    }
+
+    fn encode_approvals(&self, approvals: &[Approval]) -> Result<Bytes> {
+        let calls = approvals
+            .iter()
+            .map(|approval| {
+                let mut approve = ethers::utils::id("approve(address,uint256)").to_vec();
+                approve.extend(abi::encode(&[
+                    Token::Address(approval.spender),
+                    Token::Uint(U256::MAX),
+                ]));
+                Self::encode_call(approval.token, U256::zero(), approve)
+            })
+            .collect();
+        Ok(Self::encode_calls(calls))
+    }
+
+    fn encode_withdrawal(&self, token: Address, to: Address, amount: U256) -> Result<Bytes> {
+        let mut transfer = ethers::utils::id("transfer(address,uint256)").to_vec();
+        transfer.extend(abi::encode(&[Token::Address(to), Token::Uint(amount)]));
+        Ok(Self::encode_calls(vec![Self::encode_call(
+            token,
+            U256::zero(),
+            transfer,
+        )]))
+    }
+
+    fn supports_liquidations(&self) -> bool {
+        true
+    }
+
+    fn encode_liquidation(&self, liquidation: &Liquidation) -> Result<Bytes> {
+        let mut approve = ethers::utils::id("approve(address,uint256)").to_vec();
+        approve.extend(abi::encode(&[
+            Token::Address(liquidation.market),
+            Token::Uint(liquidation.repay_amount),
+        ]));
+        let (target, call) = liquidation.call();
+        Ok(Self::encode_calls(vec![
+            Self::encode_call(liquidation.debt_asset, U256::zero(), approve),
+            Self::encode_call(target, U256::zero(), call),
+        ]))
+    }
+
+    fn supports_order_fills(&self) -> bool {
+        true
+    }
+
+    fn encode_order_fill(&self, fill: &OrderFill) -> Result<Bytes> {
+        let mut approve = ethers::utils::id("approve(address,uint256)").to_vec();
+        approve.extend(abi::encode(&[
+            Token::Address(fill.reactor),
+            Token::Uint(fill.output_amount),
+        ]));
+        let mut calls = vec![
+            Self::encode_call(fill.output_token, U256::zero(), approve),
+            Self::encode_call(fill.reactor, U256::zero(), fill.execute.to_vec()),
+        ];
+        calls.extend(self.leg_calls(&fill.rebalance));
+        Ok(Self::encode_calls(calls))
+    }
+}
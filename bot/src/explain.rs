@@ -0,0 +1,219 @@
+//! Decision Reports
+//!
+//! "Why didn't the bot sandwich my test transaction?" used to mean grepping the logs for its hash.
+//! Instead, every candidate evaluated carries a [DecisionReport] through the main loop: the stages
+//! it cleared, the values computed along the way and the thresholds they were held to, and the
+//! stage that dropped it and why. The report is recorded when the candidate's iteration ends, so
+//! every `continue` records the stage it happened in, and the admin API's `GET /explain?tx=0x..`
+//! returns it.
+//!
+//! Only the most recent [DEFAULT_EXPLANATION_CAPACITY] reports are kept, but every report counts
+//! towards the `candidates_skipped_<stage>`, `candidates_observed` and `candidates_shipped` metrics.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Display,
+    sync::{Mutex, OnceLock},
+    time::Instant,
+};
+
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{metrics, opportunity::unix_millis};
+
+/// How many reports are kept
+pub const DEFAULT_EXPLANATION_CAPACITY: usize = 10_000;
+
+/// What became of a candidate
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Dropped by a filter
+    Skipped,
+    /// Recorded instead of submitted, in observer mode
+    Observed,
+    /// Simulated but not submitted, in simulate-only mode
+    Simulated,
+    /// Handed to remote executors as a signed plan
+    Planned,
+    /// Handed to submission
+    Shipped,
+}
+
+/// Why a candidate was or wasn't sandwiched
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Explanation {
+    /// The victim transaction
+    pub tx: TxHash,
+    /// When the candidate was received, in milliseconds since the unix epoch
+    pub received_at: u64,
+    /// When evaluation ended, in milliseconds since the unix epoch
+    pub decided_at: u64,
+    /// What became of it
+    pub verdict: Verdict,
+    /// The stage evaluation ended in
+    pub stage: String,
+    /// Why it was skipped, if known
+    pub reason: Option<String>,
+    /// The stages it cleared, in order
+    pub passed: Vec<String>,
+    /// The values computed and the thresholds they were held to, by name
+    pub values: BTreeMap<String, String>,
+    /// What happened after evaluation, like the bundle being superseded
+    pub notes: Vec<String>,
+}
+
+/// Builds the [Explanation] of a candidate, recording it when dropped
+#[derive(Debug)]
+pub struct DecisionReport {
+    explanation: Explanation,
+    discarded: bool,
+}
+
+impl DecisionReport {
+    /// Starts the report of a candidate received at `received_at`
+    pub fn new(tx: TxHash, received_at: Instant) -> Self {
+        Self {
+            explanation: Explanation {
+                tx,
+                received_at: unix_millis().saturating_sub(received_at.elapsed().as_millis() as u64),
+                decided_at: 0,
+                verdict: Verdict::Skipped,
+                stage: "ingest".to_string(),
+                reason: None,
+                passed: Vec::new(),
+                values: BTreeMap::new(),
+                notes: Vec::new(),
+            },
+            discarded: false,
+        }
+    }
+
+    /// Enters the next stage, the current one having passed
+    pub fn stage(&mut self, name: &str) {
+        let previous = std::mem::replace(&mut self.explanation.stage, name.to_string());
+        self.explanation.passed.push(previous);
+    }
+
+    /// Records a computed value or threshold
+    pub fn value(&mut self, name: &str, value: impl Display) {
+        self.explanation
+            .values
+            .insert(name.to_string(), value.to_string());
+    }
+
+    /// Records why the candidate is skipped in the current stage
+    pub fn skip(&mut self, reason: impl Display) {
+        self.explanation.verdict = Verdict::Skipped;
+        self.explanation.reason = Some(reason.to_string());
+    }
+
+    /// Records that evaluation ended with `verdict` rather than a skip
+    pub fn conclude(&mut self, verdict: Verdict) {
+        self.explanation.verdict = verdict;
+        self.explanation.reason = None;
+    }
+
+    /// Drops the report without recording it, for transactions that aren't candidates at all
+    pub fn discard(&mut self) {
+        self.discarded = true;
+    }
+
+    /// The explanation so far
+    pub fn explanation(&self) -> &Explanation {
+        &self.explanation
+    }
+}
+
+impl Drop for DecisionReport {
+    fn drop(&mut self) {
+        if self.discarded {
+            return;
+        }
+        self.explanation.decided_at = unix_millis();
+        match self.explanation.verdict {
+            Verdict::Skipped => {
+                metrics::increment(&format!("candidates_skipped_{}", self.explanation.stage))
+            }
+            Verdict::Observed => metrics::increment("candidates_observed"),
+            Verdict::Simulated => metrics::increment("candidates_simulated"),
+            Verdict::Planned => metrics::increment("candidates_planned"),
+            Verdict::Shipped => metrics::increment("candidates_shipped"),
+        }
+        explanations().record(self.explanation.clone());
+    }
+}
+
+/// The most recent explanations, by victim transaction
+#[derive(Debug)]
+pub struct ExplanationLog {
+    capacity: usize,
+    inner: Mutex<(HashMap<TxHash, Explanation>, VecDeque<TxHash>)>,
+}
+
+impl ExplanationLog {
+    /// Keeps the latest `capacity` explanations
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new((HashMap::new(), VecDeque::new())),
+        }
+    }
+
+    /// Records an explanation, replacing an earlier one of the same transaction
+    pub fn record(&self, explanation: Explanation) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let (explanations, order) = &mut *inner;
+        let tx = explanation.tx;
+        if explanations.insert(tx, explanation).is_none() {
+            order.push_back(tx);
+        }
+        while explanations.len() > self.capacity {
+            match order.pop_front() {
+                Some(oldest) => {
+                    explanations.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Adds a note to a recorded explanation, returning whether it was found
+    pub fn note(&self, tx: TxHash, note: impl Display) -> bool {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match inner.0.get_mut(&tx) {
+            Some(explanation) => {
+                explanation.notes.push(note.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The explanation of a transaction, if it's still kept
+    pub fn get(&self, tx: &TxHash) -> Option<Explanation> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .0
+            .get(tx)
+            .cloned()
+    }
+
+    /// How many explanations are kept
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).0.len()
+    }
+
+    /// Whether no explanations are kept
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns the global explanation log
+pub fn explanations() -> &'static ExplanationLog {
+    static EXPLANATIONS: OnceLock<ExplanationLog> = OnceLock::new();
+    EXPLANATIONS.get_or_init(|| ExplanationLog::new(DEFAULT_EXPLANATION_CAPACITY))
+}
@@ -0,0 +1,306 @@
+//! Opportunity Exports
+//!
+//! Flattens the opportunities of an [OpportunityStore](crate::opportunity::OpportunityStore) into
+//! one row each, for analysis in pandas or duckdb, written as CSV with [write_csv] or as Parquet
+//! with [write_parquet], behind the `parquet` feature.
+//!
+//! Both formats share the [COLUMNS] schema. It's stable across versions: columns are only ever
+//! appended, never renamed, reordered or retyped, and [EXPORT_SCHEMA_VERSION], exported in every
+//! row, is bumped when they are. Amounts are exact decimal strings of wei, since they don't fit a
+//! 64 bit integer, and addresses and hashes are `0x` prefixed hex. Columns of stages an
+//! opportunity didn't reach are null, an empty field in CSV.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+
+use crate::opportunity::Opportunity;
+
+/// The version of the [COLUMNS] schema
+pub const EXPORT_SCHEMA_VERSION: u64 = 1;
+
+/// The type of an exported column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    /// A string, for addresses, hashes and wei amounts
+    Utf8,
+    /// An unsigned 64 bit integer, for blocks, bips and timestamps
+    UInt64,
+}
+
+/// An exported column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    /// The column name
+    pub name: &'static str,
+    /// The column type
+    pub kind: ColumnType,
+    /// Whether the column can be null
+    pub nullable: bool,
+}
+
+const fn column(name: &'static str, kind: ColumnType, nullable: bool) -> Column {
+    Column {
+        name,
+        kind,
+        nullable,
+    }
+}
+
+/// The exported columns, in order
+pub const COLUMNS: [Column; 23] = [
+    column("schema_version", ColumnType::UInt64, false),
+    column("victim", ColumnType::Utf8, false),
+    column("sender", ColumnType::Utf8, false),
+    column("pair", ColumnType::Utf8, false),
+    column("token", ColumnType::Utf8, false),
+    column("path", ColumnType::Utf8, false),
+    column("user_amount_in", ColumnType::Utf8, false),
+    column("amount_out_min", ColumnType::Utf8, false),
+    column("user_min_recv", ColumnType::Utf8, false),
+    column("deadline", ColumnType::Utf8, false),
+    column("reserves_block", ColumnType::UInt64, false),
+    column("weth_reserves", ColumnType::Utf8, false),
+    column("token_reserves", ColumnType::Utf8, false),
+    column("optimal_weth_in", ColumnType::Utf8, true),
+    column("revenue", ColumnType::Utf8, true),
+    column("target_block", ColumnType::UInt64, true),
+    column("wallet", ColumnType::Utf8, true),
+    column("base_fee", ColumnType::Utf8, true),
+    column("bribe_bps", ColumnType::UInt64, true),
+    column("flash_loan_amount", ColumnType::Utf8, true),
+    column("seen_at_ms", ColumnType::UInt64, false),
+    column("priced_at_ms", ColumnType::UInt64, true),
+    column("planned_at_ms", ColumnType::UInt64, true),
+];
+
+/// A non-null exported value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cell {
+    /// A [ColumnType::Utf8] value
+    Text(String),
+    /// A [ColumnType::UInt64] value
+    UInt(u64),
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Text(text) => f.write_str(text),
+            Cell::UInt(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+fn hex(value: impl std::fmt::Debug) -> Option<Cell> {
+    Some(Cell::Text(format!("{:?}", value)))
+}
+
+fn wei(value: U256) -> Option<Cell> {
+    Some(Cell::Text(value.to_string()))
+}
+
+/// Flattens an opportunity into a row of [COLUMNS], `None` being null
+pub fn export_row(opportunity: &Opportunity) -> Vec<Option<Cell>> {
+    let context = opportunity.context.as_ref();
+    let plan = opportunity.plan.as_ref();
+    let path = opportunity
+        .swap
+        .path
+        .iter()
+        .map(|token| format!("{:?}", token))
+        .collect::<Vec<_>>()
+        .join(",");
+    vec![
+        Some(Cell::UInt(EXPORT_SCHEMA_VERSION)),
+        hex(opportunity.victim.hash),
+        hex(opportunity.victim.from),
+        hex(opportunity.pair),
+        hex(opportunity.token),
+        Some(Cell::Text(path)),
+        wei(opportunity.user_amount_in()),
+        wei(opportunity.swap.amount_out_min),
+        wei(opportunity.user_min_recv),
+        wei(opportunity.swap.deadline),
+        Some(Cell::UInt(opportunity.reserves.block.as_u64())),
+        wei(opportunity.weth_reserves()),
+        wei(opportunity.token_reserves()),
+        context.and_then(|c| wei(c.optimal_weth_in)),
+        context.and_then(|c| wei(c.revenue)),
+        plan.map(|p| Cell::UInt(p.target_block.as_u64())),
+        plan.and_then(|p| hex(p.wallet)),
+        plan.and_then(|p| wei(p.base_fee)),
+        plan.map(|p| Cell::UInt(p.bribe_bps)),
+        // Frontruns are no longer borrowed, the column stays for the schema
+        None,
+        Some(Cell::UInt(opportunity.timestamps.seen)),
+        opportunity.timestamps.priced.map(Cell::UInt),
+        opportunity.timestamps.planned.map(Cell::UInt),
+    ]
+}
+
+/// Quotes a CSV field if it holds a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes opportunities as CSV with a header row, returning how many were written
+pub fn write_csv<W: Write>(opportunities: &[Opportunity], writer: W) -> Result<usize> {
+    let mut writer = BufWriter::new(writer);
+    let header = COLUMNS.iter().map(|c| c.name).collect::<Vec<_>>();
+    writeln!(writer, "{}", header.join(","))?;
+    for opportunity in opportunities {
+        let row = export_row(opportunity)
+            .iter()
+            .map(|cell| cell.as_ref().map(|c| csv_field(&c.to_string())))
+            .map(Option::unwrap_or_default)
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    writer.flush()?;
+    Ok(opportunities.len())
+}
+
+/// The Parquet message type of [COLUMNS]
+pub fn parquet_message_type() -> String {
+    let fields = COLUMNS
+        .iter()
+        .map(|column| {
+            let repetition = if column.nullable {
+                "OPTIONAL"
+            } else {
+                "REQUIRED"
+            };
+            let (physical, logical) = match column.kind {
+                ColumnType::Utf8 => ("BYTE_ARRAY", "UTF8"),
+                ColumnType::UInt64 => ("INT64", "INTEGER(64,false)"),
+            };
+            format!("  {repetition} {physical} {} ({logical});", column.name)
+        })
+        .collect::<Vec<_>>();
+    format!("message opportunity {{\n{}\n}}", fields.join("\n"))
+}
+
+/// Writes opportunities as a single row group Parquet file, returning how many were written
+#[cfg(feature = "parquet")]
+pub fn write_parquet<W: Write + Send>(opportunities: &[Opportunity], writer: W) -> Result<usize> {
+    use std::sync::Arc;
+
+    use parquet::{
+        basic::Compression,
+        data_type::{ByteArray, ByteArrayType, Int64Type},
+        file::{properties::WriterProperties, writer::SerializedFileWriter},
+        schema::parser::parse_message_type,
+    };
+
+    let schema = Arc::new(parse_message_type(&parquet_message_type())?);
+    let properties = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(writer, schema, properties)?;
+    let rows = opportunities.iter().map(export_row).collect::<Vec<_>>();
+
+    let mut row_group = writer.next_row_group()?;
+    for (index, column) in COLUMNS.iter().enumerate() {
+        let mut column_writer = row_group
+            .next_column()?
+            .ok_or_else(|| eyre::eyre!("Parquet schema is missing column {}", column.name))?;
+        let cells = rows.iter().map(|row| row[index].as_ref());
+        let definitions = cells
+            .clone()
+            .map(|cell| cell.is_some() as i16)
+            .collect::<Vec<_>>();
+        let definitions = column.nullable.then_some(&definitions[..]);
+        match column.kind {
+            ColumnType::Utf8 => {
+                let values = cells
+                    .filter_map(|cell| match cell {
+                        Some(Cell::Text(text)) => Some(ByteArray::from(text.as_str())),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                column_writer
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, definitions, None)?;
+            }
+            ColumnType::UInt64 => {
+                // Unsigned integers are stored in their signed physical type
+                let values = cells
+                    .filter_map(|cell| match cell {
+                        Some(Cell::UInt(value)) => Some(*value as i64),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                column_writer
+                    .typed::<Int64Type>()
+                    .write_batch(&values, definitions, None)?;
+            }
+        }
+        column_writer.close()?;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(opportunities.len())
+}
+
+/// An export file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// Apache Parquet, needing the `parquet` feature
+    Parquet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "parquet" | "pq" => Ok(ExportFormat::Parquet),
+            _ => Err(eyre::eyre!("Unknown export format \"{}\"", s)),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// The format of a file, from its extension
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .ok_or_else(|| eyre::eyre!("Export file {:?} has no extension", path))?
+            .parse()
+    }
+}
+
+/// Exports opportunities to `path`, in the format of its extension
+pub fn export_file(opportunities: &[Opportunity], path: impl AsRef<Path>) -> Result<usize> {
+    let path = path.as_ref();
+    let create = || {
+        File::create(path)
+            .map_err(|e| eyre::eyre!("Failed to create export file {:?}: {:?}", path, e))
+    };
+    match ExportFormat::from_path(path)? {
+        ExportFormat::Csv => write_csv(opportunities, create()?),
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => write_parquet(opportunities, create()?),
+        #[cfg(not(feature = "parquet"))]
+        ExportFormat::Parquet => {
+            eyre::bail!("Parquet exports need the bot built with the `parquet` feature")
+        }
+    }
+}
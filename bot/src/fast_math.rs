@@ -0,0 +1,87 @@
+//! Fast Math
+//!
+//! The optimal input search evaluates the Uniswap V2 output formula a few dozen times per candidate.
+//! With the `fast-math` feature the search runs on [ruint](https://crates.io/crates/ruint) integers,
+//! converting the inputs once up front instead of going through the ethers `U256` on every step.
+
+use ethers::types::U256 as EthersU256;
+use ruint::aliases::U256;
+
+/// The search upper bound, 100 ether
+const UPPER_BOUND: U256 = U256::from_limbs([0x6bc7_5e2d_6310_0000, 0x5, 0, 0]);
+
+/// The search tolerance, in bips
+const TOLERANCE_BIPS: u64 = 100;
+
+/// Converts an ethers `U256` into a ruint `U256`
+pub fn from_ethers(value: &EthersU256) -> U256 {
+    // Both store little-endian u64 limbs
+    U256::from_limbs(value.0)
+}
+
+/// Converts a ruint `U256` into an ethers `U256`
+pub fn to_ethers(value: &U256) -> EthersU256 {
+    EthersU256(value.into_limbs())
+}
+
+/// Returns the output and new reserves of a Uniswap V2 swap, mirroring [crate::uniswap::get_univ2_data_given_in]
+///
+/// Overflowing products saturate rather than panic.
+pub fn get_univ2_data_given_in(
+    a_in: U256,
+    a_reserves: U256,
+    b_reserves: U256,
+) -> (U256, U256, U256) {
+    let a_in_with_fee = a_in.saturating_mul(U256::from(997u64));
+    let numerator = a_in_with_fee.saturating_mul(b_reserves);
+    let denominator = a_reserves
+        .saturating_mul(U256::from(1000u64))
+        .saturating_add(a_in_with_fee);
+    let b_out = numerator.checked_div(denominator).unwrap_or(U256::ZERO);
+
+    let new_b_reserves = b_reserves.checked_sub(b_out).unwrap_or(U256::from(1u64));
+    let new_a_reserves = a_reserves.checked_add(a_in).unwrap_or(U256::MAX);
+
+    (b_out, new_a_reserves, new_b_reserves)
+}
+
+/// Calculate the max sandwich amount, mirroring [crate::numeric::reference_sandwich_optimal_in]
+pub fn calculate_sandwich_optimal_in(
+    user_amount_in: &EthersU256,
+    user_min_recv_token: &EthersU256,
+    weth_reserves: &EthersU256,
+    token_reserves: &EthersU256,
+) -> EthersU256 {
+    let user_amount_in = from_ethers(user_amount_in);
+    let user_min_recv_token = from_ethers(user_min_recv_token);
+    let weth_reserves = from_ethers(weth_reserves);
+    let token_reserves = from_ethers(token_reserves);
+
+    let base = U256::from(10_000u64);
+    let tolerance = U256::from(TOLERANCE_BIPS);
+    let (mut lower, mut upper) = (U256::ZERO, UPPER_BOUND);
+
+    // The same bisection as [crate::numeric::binary_search], unrolled into a loop
+    loop {
+        let mid = (upper + lower) / U256::from(2u64);
+        let delta = tolerance * mid / base;
+        let delta = if delta.is_zero() {
+            U256::from(1u64)
+        } else {
+            delta
+        };
+        if upper - lower <= delta {
+            return to_ethers(&mid);
+        }
+
+        let (_, new_a_reserves, new_b_reserves) =
+            get_univ2_data_given_in(mid, weth_reserves, token_reserves);
+        let (amount_out, _, _) =
+            get_univ2_data_given_in(user_amount_in, new_a_reserves, new_b_reserves);
+        if amount_out >= user_min_recv_token {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+}
@@ -0,0 +1,564 @@
+//! Gas Pricing
+//!
+//! Post-4844 blocks price blob gas on a separate fee market, and full blocks carrying blobs can
+//! crowd out normal bundles. This module tracks blob inclusion per builder and exports the next
+//! blob base fee as a gauge. Calldata heavy legs pay the EIP-7623 floor, which simulated leg gas
+//! is raised to before it prices the bribe.
+//!
+//! Builders merge many bundles into a block and won't take one that crowds out the rest. Bundles
+//! are checked against a [BundleGasBudget] after simulation and dropped before they reach the
+//! relay when they're too large, or with splitting enabled, packed into smaller bundles without
+//! separating any victim from its legs.
+//!
+//! Fee spikes make the fixed gas estimates and bribe math unreliable, so the [BaseFeeGuard] skips
+//! submission while the base fee is too high or swinging too much between blocks.
+//!
+//! Leg gas barely changes between sandwiches on the same pair, so a [GasModel] keeps a moving
+//! average of simulated gas per pair and token. It prices the bribe and profit of a bundle before
+//! it's sent to the relay, dropping bundles that can't pay without a simulation round trip.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::metrics;
+
+/// The minimum blob base fee, in wei
+pub const MIN_BLOB_BASE_FEE: u64 = 1;
+
+/// Controls how fast the blob base fee moves
+pub const BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 3_338_477;
+
+/// The blob gas consumed by each blob
+pub const GAS_PER_BLOB: u64 = 131_072;
+
+/// The blob gas targeted per block
+pub const TARGET_BLOB_GAS_PER_BLOCK: u64 = 393_216;
+
+/// The intrinsic gas of every transaction
+pub const TX_BASE_GAS: u64 = 21_000;
+
+/// The share of the gas limit above which a block counts as full, in bips
+pub const FULL_BLOCK_BPS: u64 = 9_500;
+
+/// The default largest share of the block gas limit a bundle may use, in bips
+pub const DEFAULT_MAX_BUNDLE_GAS_BPS: u64 = 2_500;
+
+/// The default number of blocks base fee volatility is measured over
+pub const DEFAULT_VOLATILITY_BLOCKS: usize = 5;
+
+/// The default weight of each new gas sample in the moving average, in bips
+pub const DEFAULT_GAS_EWMA_ALPHA_BPS: u64 = 2_000;
+
+/// Approximates `factor * e ** (numerator / denominator)` as specified in EIP-4844
+pub fn fake_exponential(factor: U256, numerator: U256, denominator: U256) -> U256 {
+    let mut i = U256::one();
+    let mut output = U256::zero();
+    let mut accum = factor * denominator;
+    while !accum.is_zero() {
+        output += accum;
+        accum = accum * numerator / (denominator * i);
+        i += U256::one();
+    }
+    output / denominator
+}
+
+/// Returns the blob base fee for a block with the given excess blob gas
+pub fn blob_base_fee(excess_blob_gas: u64) -> U256 {
+    fake_exponential(
+        U256::from(MIN_BLOB_BASE_FEE),
+        U256::from(excess_blob_gas),
+        U256::from(BLOB_BASE_FEE_UPDATE_FRACTION),
+    )
+}
+
+/// Returns the excess blob gas of the child of a block
+pub fn next_excess_blob_gas(excess_blob_gas: u64, blob_gas_used: u64) -> u64 {
+    (excess_blob_gas + blob_gas_used).saturating_sub(TARGET_BLOB_GAS_PER_BLOCK)
+}
+
+/// Returns the EIP-7623 floor gas of a transaction with the given calldata
+///
+/// Transactions pay the larger of their regular gas and `21000 + 10 * tokens`, where each zero
+/// byte is one token and each non-zero byte is four.
+pub fn calldata_floor_gas(data: &[u8]) -> u64 {
+    let tokens: u64 = data.iter().map(|b| if *b == 0 { 1 } else { 4 }).sum();
+    TX_BASE_GAS + 10 * tokens
+}
+
+/// Returns the gas a transaction is charged given the regular gas it uses
+///
+/// Relays simulating against state before the floor report the regular gas only.
+pub fn charged_gas(data: &[u8], gas_used: U256) -> U256 {
+    gas_used.max(U256::from(calldata_floor_gas(data)))
+}
+
+/// The fee fields of a block, read from the raw RPC response
+///
+/// Fetched as raw json because the pinned ethers `Block` type predates the blob fields.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockFees {
+    /// The block number
+    pub number: U64,
+    /// The execution gas used
+    pub gas_used: U256,
+    /// The execution gas limit
+    pub gas_limit: U256,
+    /// The execution base fee
+    pub base_fee_per_gas: Option<U256>,
+    /// The blob gas used, absent before 4844
+    #[serde(default)]
+    pub blob_gas_used: Option<U64>,
+    /// The excess blob gas, absent before 4844
+    #[serde(default)]
+    pub excess_blob_gas: Option<U64>,
+    /// The builder's extra data
+    #[serde(default)]
+    pub extra_data: Bytes,
+}
+
+impl BlockFees {
+    /// Fetches the fee fields of a block
+    pub async fn fetch<M: Middleware + 'static>(
+        client: Arc<M>,
+        block: BlockNumber,
+    ) -> Result<Self> {
+        client
+            .provider()
+            .request::<_, Option<Self>>("eth_getBlockByNumber", (block, false))
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch block: {:?}", e))?
+            .ok_or_else(|| eyre::eyre!("Block {:?} not found", block))
+    }
+
+    /// The number of blobs in the block
+    pub fn blob_count(&self) -> u64 {
+        self.blob_gas_used.unwrap_or_default().as_u64() / GAS_PER_BLOB
+    }
+
+    /// Whether the block used nearly all of its gas limit
+    pub fn is_full(&self) -> bool {
+        self.gas_used * 10_000 >= self.gas_limit * FULL_BLOCK_BPS
+    }
+
+    /// The blob base fee of the next block
+    pub fn next_blob_base_fee(&self) -> U256 {
+        blob_base_fee(next_excess_blob_gas(
+            self.excess_blob_gas.unwrap_or_default().as_u64(),
+            self.blob_gas_used.unwrap_or_default().as_u64(),
+        ))
+    }
+
+    /// The builder name from the extra data, falling back to `unknown`
+    pub fn builder(&self) -> String {
+        let name: String = String::from_utf8_lossy(self.extra_data.as_ref())
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        if name.is_empty() {
+            "unknown".to_string()
+        } else {
+            name
+        }
+    }
+}
+
+/// Records how a block's builder included blobs
+///
+/// `full_blocks_with_blobs` counts the blocks where blobs competed with bundles for space.
+/// The `next_blob_base_fee` gauge tracks the blob base fee of the next block.
+pub fn record_blob_inclusion(block: &BlockFees) {
+    let builder = block.builder();
+    let blobs = block.blob_count();
+    metrics::increment("blocks_seen");
+    metrics::increment(&format!("blocks_by_builder_{builder}"));
+    let blob_base_fee = block.next_blob_base_fee().min(U256::from(u64::MAX));
+    metrics::global().set("next_blob_base_fee", blob_base_fee.as_u64());
+    if blobs > 0 {
+        metrics::increment("blocks_with_blobs");
+        metrics::global().add("blobs_included", blobs);
+        metrics::global().add(&format!("blobs_by_builder_{builder}"), blobs);
+        if block.is_full() {
+            metrics::increment("full_blocks_with_blobs");
+            metrics::increment(&format!("full_blocks_with_blobs_by_builder_{builder}"));
+        }
+    }
+}
+
+/// Records blob inclusion for every new block until the subscription ends
+pub async fn monitor_blob_inclusion(client: Arc<Provider<Ws>>) -> Result<()> {
+    let mut blocks = client
+        .subscribe_blocks()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to subscribe to blocks: {:?}", e))?;
+    while let Some(block) = blocks.next().await {
+        let number = match block.number {
+            Some(n) => n,
+            None => continue,
+        };
+        match BlockFees::fetch(client.clone(), BlockNumber::Number(number)).await {
+            Ok(fees) => {
+                record_blob_inclusion(&fees);
+                tracing::debug!(
+                    "[GAS] Block {} by {}: {} blobs, next blob base fee {}",
+                    fees.number,
+                    fees.builder(),
+                    fees.blob_count(),
+                    fees.next_blob_base_fee()
+                );
+            }
+            Err(e) => tracing::debug!("[GAS] {:?}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Why a bundle doesn't fit in the block
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum BundleGasError {
+    /// The bundle uses more than the share of the block builders will merge
+    #[error("bundle gas {gas} exceeds the builder limit {limit}")]
+    ExceedsBuilderLimit {
+        /// The bundle gas
+        gas: U256,
+        /// The largest bundle builders will merge
+        limit: U256,
+    },
+    /// The bundle uses more gas than is left in the block
+    #[error("bundle gas {gas} exceeds the remaining block gas {remaining}")]
+    ExceedsRemaining {
+        /// The bundle gas
+        gas: U256,
+        /// The gas left in the block
+        remaining: U256,
+    },
+}
+
+impl BundleGasError {
+    /// The metric counting bundles dropped for this reason
+    pub fn metric(&self) -> &'static str {
+        match self {
+            BundleGasError::ExceedsBuilderLimit { .. } => "bundle_gas_builder_limit",
+            BundleGasError::ExceedsRemaining { .. } => "bundle_gas_remaining",
+        }
+    }
+}
+
+/// The gas a bundle may use in its target block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleGasBudget {
+    /// The block gas limit
+    pub gas_limit: U256,
+    /// The gas already used in the block, zero for a block that hasn't been built yet
+    pub gas_used: U256,
+    /// The largest share of the gas limit a bundle may use, in bips
+    pub max_bundle_gas_bps: u64,
+}
+
+impl BundleGasBudget {
+    /// Creates a budget for an empty block
+    pub fn new(gas_limit: U256, max_bundle_gas_bps: u64) -> Self {
+        Self {
+            gas_limit,
+            gas_used: U256::zero(),
+            max_bundle_gas_bps,
+        }
+    }
+
+    /// The gas left in the block
+    pub fn remaining(&self) -> U256 {
+        self.gas_limit.saturating_sub(self.gas_used)
+    }
+
+    /// The largest bundle builders will merge
+    pub fn builder_limit(&self) -> U256 {
+        self.gas_limit * self.max_bundle_gas_bps / 10_000
+    }
+
+    /// Checks a bundle using `gas`
+    pub fn check(&self, gas: U256) -> Result<(), BundleGasError> {
+        if gas > self.remaining() {
+            return Err(BundleGasError::ExceedsRemaining {
+                gas,
+                remaining: self.remaining(),
+            });
+        }
+        if gas > self.builder_limit() {
+            return Err(BundleGasError::ExceedsBuilderLimit {
+                gas,
+                limit: self.builder_limit(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Fits groups of transactions into bundles, each group with the gas it uses
+    ///
+    /// A group, like a sandwich and its victim, is never split. When the groups don't fit in one
+    /// bundle and `split` is set, they're packed in order into as few bundles as fit, otherwise
+    /// the bundle is rejected.
+    pub fn fit<T: Clone>(
+        &self,
+        groups: &[(Vec<T>, U256)],
+        split: bool,
+    ) -> Result<Vec<Vec<T>>, BundleGasError> {
+        let total = groups
+            .iter()
+            .fold(U256::zero(), |acc, (_, gas)| acc.saturating_add(*gas));
+        match self.check(total) {
+            Ok(()) => {
+                return Ok(vec![groups
+                    .iter()
+                    .flat_map(|(txs, _)| txs.clone())
+                    .collect()])
+            }
+            Err(e) if !split => return Err(e),
+            Err(_) => {}
+        }
+
+        let mut bundles = Vec::new();
+        let mut current: Vec<T> = Vec::new();
+        let mut current_gas = U256::zero();
+        for (txs, gas) in groups {
+            self.check(*gas)?;
+            if !current.is_empty() && self.check(current_gas + *gas).is_err() {
+                bundles.push(std::mem::take(&mut current));
+                current_gas = U256::zero();
+            }
+            current.extend(txs.iter().cloned());
+            current_gas += *gas;
+        }
+        if !current.is_empty() {
+            bundles.push(current);
+        }
+        Ok(bundles)
+    }
+}
+
+/// Why the base fee guard skipped a submission
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum BaseFeeRejection {
+    /// The base fee is above the cap
+    #[error("base fee {base_fee} exceeds the cap {max}")]
+    TooHigh {
+        /// The next block's base fee
+        base_fee: U256,
+        /// The cap
+        max: U256,
+    },
+    /// The base fee swung too much over recent blocks
+    #[error("base fee moved {volatility_bps} bips, above the limit {max_bps}")]
+    Volatile {
+        /// The range of recent base fees, in bips of the lowest
+        volatility_bps: u64,
+        /// The limit
+        max_bps: u64,
+    },
+}
+
+impl BaseFeeRejection {
+    /// The metric counting submissions skipped for this reason
+    pub fn metric(&self) -> &'static str {
+        match self {
+            BaseFeeRejection::TooHigh { .. } => "base_fee_too_high",
+            BaseFeeRejection::Volatile { .. } => "base_fee_volatile",
+        }
+    }
+}
+
+/// The base fee guard settings
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BaseFeeLimits {
+    /// The highest base fee submitted at, unlimited if unset
+    pub max_base_fee: Option<U256>,
+    /// The widest range of recent base fees submitted at, in bips of the lowest, unlimited if unset
+    pub max_volatility_bps: Option<u64>,
+    /// How many recent blocks volatility is measured over
+    pub volatility_blocks: usize,
+}
+
+impl Default for BaseFeeLimits {
+    fn default() -> Self {
+        Self {
+            max_base_fee: None,
+            max_volatility_bps: None,
+            volatility_blocks: DEFAULT_VOLATILITY_BLOCKS,
+        }
+    }
+}
+
+/// Skips submission while the base fee is too high or volatile
+#[derive(Debug, Clone)]
+pub struct BaseFeeGuard {
+    limits: BaseFeeLimits,
+    history: VecDeque<(U64, U256)>,
+}
+
+impl BaseFeeGuard {
+    /// Creates a guard enforcing `limits`
+    pub fn new(limits: BaseFeeLimits) -> Self {
+        Self {
+            limits,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Records the base fee of `block`, once per block
+    pub fn observe(&mut self, block: U64, base_fee: U256) {
+        if matches!(self.history.back(), Some((last, _)) if *last >= block) {
+            return;
+        }
+        self.history.push_back((block, base_fee));
+        while self.history.len() > self.limits.volatility_blocks.max(1) {
+            self.history.pop_front();
+        }
+    }
+
+    /// The range of the recorded base fees, in bips of the lowest
+    pub fn volatility_bps(&self) -> u64 {
+        let fees = self.history.iter().map(|(_, fee)| *fee);
+        let (low, high) = match (fees.clone().min(), fees.max()) {
+            (Some(low), Some(high)) => (low, high),
+            _ => return 0,
+        };
+        if low.is_zero() {
+            return if high.is_zero() { 0 } else { u64::MAX };
+        }
+        let bps = (high - low).saturating_mul(U256::from(10_000)) / low;
+        bps.min(U256::from(u64::MAX)).as_u64()
+    }
+
+    /// Checks whether to submit at `base_fee`
+    pub fn check(&self, base_fee: U256) -> Result<(), BaseFeeRejection> {
+        if let Some(max) = self.limits.max_base_fee {
+            if base_fee > max {
+                return Err(BaseFeeRejection::TooHigh { base_fee, max });
+            }
+        }
+        if let Some(max_bps) = self.limits.max_volatility_bps {
+            let volatility_bps = self.volatility_bps();
+            if volatility_bps > max_bps {
+                return Err(BaseFeeRejection::Volatile {
+                    volatility_bps,
+                    max_bps,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The moving average gas of both sandwich legs
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LegGas {
+    /// The frontrun's gas
+    pub frontrun: U256,
+    /// The backrun's gas
+    pub backrun: U256,
+    /// How many simulations were averaged
+    pub samples: u32,
+}
+
+impl LegGas {
+    fn update(&mut self, frontrun: U256, backrun: U256, alpha_bps: u64) {
+        let average = |old: U256, new: U256| {
+            if self.samples == 0 {
+                return new;
+            }
+            (new * alpha_bps + old * (10_000 - alpha_bps)) / 10_000
+        };
+        self.frontrun = average(self.frontrun, frontrun);
+        self.backrun = average(self.backrun, backrun);
+        self.samples = self.samples.saturating_add(1);
+    }
+}
+
+/// A bundle priced from estimated gas
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BribeEstimate {
+    /// The backrun's priority fee per gas
+    pub max_priority_fee_per_gas: U256,
+    /// The revenue left after gas and the bribe
+    pub profit: U256,
+}
+
+/// Prices the bribe and profit of a bundle like the post-simulation bribe math
+///
+/// Returns `None` when the bribe can't cover the base fee or gas eats the revenue.
+pub fn estimate_bribe(
+    revenue: U256,
+    gas: &LegGas,
+    base_fee: U256,
+    bribe_bps: u64,
+) -> Option<BribeEstimate> {
+    if gas.backrun.is_zero() {
+        return None;
+    }
+    let bribe_amount = revenue.checked_sub(gas.frontrun.saturating_mul(base_fee))?;
+    let max_priority_fee_per_gas = bribe_amount * bribe_bps / 10_000 / gas.backrun;
+    if max_priority_fee_per_gas < base_fee {
+        return None;
+    }
+    let backrun_cost = gas
+        .backrun
+        .saturating_mul(base_fee + max_priority_fee_per_gas);
+    let profit = bribe_amount.checked_sub(backrun_cost)?;
+    Some(BribeEstimate {
+        max_priority_fee_per_gas,
+        profit,
+    })
+}
+
+/// Moving averages of simulated leg gas by pair and token
+#[derive(Debug, Clone)]
+pub struct GasModel {
+    alpha_bps: u64,
+    pairs: HashMap<Address, LegGas>,
+    tokens: HashMap<Address, LegGas>,
+}
+
+impl Default for GasModel {
+    fn default() -> Self {
+        Self::new(DEFAULT_GAS_EWMA_ALPHA_BPS)
+    }
+}
+
+impl GasModel {
+    /// Creates a model weighting each new sample by `alpha_bps`
+    pub fn new(alpha_bps: u64) -> Self {
+        Self {
+            alpha_bps: alpha_bps.min(10_000),
+            pairs: HashMap::new(),
+            tokens: HashMap::new(),
+        }
+    }
+
+    /// Records the simulated gas of a sandwich on `pair` trading `token`
+    pub fn observe(&mut self, pair: Address, token: Address, frontrun: U256, backrun: U256) {
+        let alpha_bps = self.alpha_bps;
+        self.pairs
+            .entry(pair)
+            .or_default()
+            .update(frontrun, backrun, alpha_bps);
+        self.tokens
+            .entry(token)
+            .or_default()
+            .update(frontrun, backrun, alpha_bps);
+    }
+
+    /// Returns the estimated leg gas on `pair`, falling back to other pairs trading `token`
+    pub fn estimate(&self, pair: &Address, token: &Address) -> Option<LegGas> {
+        self.pairs
+            .get(pair)
+            .or_else(|| self.tokens.get(token))
+            .copied()
+    }
+}
@@ -0,0 +1,323 @@
+//! Pipeline gRPC API
+//!
+//! Splits detection from decision making: a bot running next to its node streams every shipped
+//! opportunity over gRPC, and a process elsewhere decides which of them are executed. The service
+//! is defined in `proto/pipeline.proto`:
+//!
+//! - `StreamOpportunities` streams each shipped [Opportunity] from the moment of the call. With a
+//!   plan signer it carries no signed legs, only the [SignedPlan] a remote executor signs the legs
+//!   from itself. A subscriber that falls more than [FEED_CAPACITY] opportunities behind skips the
+//!   ones it missed.
+//! - `Decide` executes or skips an opportunity held for a decision.
+//!
+//! With remote decisions enabled, each bundle is held in the [remote_decisions] gate, like a
+//! manual approval, and only submitted if a decider answers `execute` before its window passes.
+//! Without them, opportunities are only streamed.
+//!
+//! [execute_plans] is the other end of a split deployment: run as `subway execute-plans <url>` next
+//! to the hot wallet, it subscribes to a detector's stream and verifies, signs and sends each plan.
+//!
+//! Calls are authorized with the bearer token in `GRPC_TOKEN`, which serving requires. The service
+//! should still only be reachable over a private network, as the stream carries signed bundles.
+
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use tokio::sync::{broadcast, oneshot};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tonic::{
+    metadata::{Ascii, MetadataValue},
+    transport::{Endpoint, Server},
+    Request, Response, Status,
+};
+
+use crate::{
+    instances,
+    manual_approval::{ApprovalGate, ApprovalOutcome, ApprovalRequest},
+    metrics,
+    opportunity::{unix_millis, Opportunity},
+    plans::{self, PlanVerifier, SignedPlan},
+    relayer::{self, FlashbotsRelay},
+    transactions::{NonceSlot, TxFactory},
+    utils,
+};
+
+/// The messages and service generated from `proto/pipeline.proto`
+#[allow(missing_docs)]
+pub mod proto {
+    tonic::include_proto!("subway.pipeline");
+}
+
+use proto::{
+    pipeline_client::PipelineClient,
+    pipeline_server::{Pipeline, PipelineServer},
+};
+
+/// How many opportunities a subscriber may fall behind before it skips some
+pub const FEED_CAPACITY: usize = 1_024;
+
+/// Returns the global feed of shipped opportunities
+pub fn opportunity_feed() -> &'static broadcast::Sender<proto::Opportunity> {
+    static FEED: OnceLock<broadcast::Sender<proto::Opportunity>> = OnceLock::new();
+    FEED.get_or_init(|| broadcast::channel(FEED_CAPACITY).0)
+}
+
+/// Returns the global gate opportunities wait in for a remote decision
+pub fn remote_decisions() -> &'static ApprovalGate {
+    static GATE: OnceLock<ApprovalGate> = OnceLock::new();
+    GATE.get_or_init(ApprovalGate::new)
+}
+
+/// The metric counting remote decisions with this outcome
+pub fn decision_metric(outcome: ApprovalOutcome) -> &'static str {
+    match outcome {
+        ApprovalOutcome::Approved => "remote_executed",
+        ApprovalOutcome::Rejected => "remote_skipped",
+        ApprovalOutcome::Expired => "remote_expired",
+    }
+}
+
+/// The message streamed for an opportunity frontrunning with `weth_in`, held by `request` if any
+pub fn opportunity_message(
+    opportunity: &Opportunity,
+    weth_in: U256,
+    signed_plan: Option<&SignedPlan>,
+    request: Option<&ApprovalRequest>,
+) -> Result<proto::Opportunity> {
+    Ok(proto::Opportunity {
+        id: request.map(|r| r.id).unwrap_or_default(),
+        decision_required: request.is_some(),
+        expires_at: request.map(|r| r.expires_at).unwrap_or_default(),
+        victim: format!("{:?}", opportunity.victim.hash),
+        pair: format!("{:?}", opportunity.pair),
+        token: format!("{:?}", opportunity.token),
+        weth_in: weth_in.to_string(),
+        revenue: opportunity
+            .context
+            .as_ref()
+            .map(|c| c.revenue)
+            .unwrap_or_default()
+            .to_string(),
+        target_block: opportunity
+            .plan
+            .as_ref()
+            .map(|p| p.target_block.as_u64())
+            .unwrap_or_default(),
+        json: serde_json::to_string(opportunity)?,
+        signed_plan: signed_plan
+            .map(serde_json::to_string)
+            .transpose()?
+            .unwrap_or_default(),
+    })
+}
+
+/// Streams a shipped opportunity, holding it for a remote decision within `window` if given
+///
+/// Returns the request and the receiver of its decision when one is awaited.
+pub fn publish(
+    opportunity: &Opportunity,
+    weth_in: U256,
+    signed_plan: Option<&SignedPlan>,
+    window: Option<Duration>,
+) -> Option<(ApprovalRequest, oneshot::Receiver<bool>)> {
+    let held = window.map(|window| {
+        remote_decisions().open(
+            opportunity.victim.hash,
+            opportunity.token,
+            weth_in,
+            opportunity
+                .context
+                .as_ref()
+                .map(|c| c.revenue)
+                .unwrap_or_default(),
+            opportunity
+                .plan
+                .as_ref()
+                .map(|p| p.target_block)
+                .unwrap_or_default(),
+            window,
+        )
+    });
+    match opportunity_message(
+        opportunity,
+        weth_in,
+        signed_plan,
+        held.as_ref().map(|(r, _)| r),
+    ) {
+        // Sending only fails without subscribers, a held opportunity then waits out its window
+        Ok(message) => {
+            let _ = opportunity_feed().send(message);
+        }
+        Err(e) => tracing::warn!("[GRPC] Failed to encode opportunity: {:?}", e),
+    }
+    held
+}
+
+/// The pipeline service, backed by the global [opportunity_feed] and [remote_decisions]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PipelineService;
+
+type OpportunityStream = Pin<Box<dyn Stream<Item = Result<proto::Opportunity, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Pipeline for PipelineService {
+    type StreamOpportunitiesStream = OpportunityStream;
+
+    async fn stream_opportunities(
+        &self,
+        _request: Request<proto::StreamRequest>,
+    ) -> Result<Response<Self::StreamOpportunitiesStream>, Status> {
+        metrics::increment("grpc_subscriptions");
+        let stream =
+            BroadcastStream::new(opportunity_feed().subscribe()).filter_map(|item| match item {
+                Ok(opportunity) => Some(Ok(opportunity)),
+                Err(_) => {
+                    metrics::increment("grpc_lagged");
+                    None
+                }
+            });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn decide(
+        &self,
+        request: Request<proto::Decision>,
+    ) -> Result<Response<proto::DecisionAck>, Status> {
+        let decision = request.into_inner();
+        let accepted = remote_decisions()
+            .decide(decision.id, decision.execute)
+            .is_some();
+        tracing::info!(
+            "[GRPC] Opportunity #{} {}",
+            decision.id,
+            match (accepted, decision.execute) {
+                (false, _) => "was no longer waiting",
+                (true, true) => "executed remotely",
+                (true, false) => "skipped remotely",
+            }
+        );
+        Ok(Response::new(proto::DecisionAck { accepted }))
+    }
+}
+
+/// Reads the bearer token calls must carry from `GRPC_TOKEN`, which must be set
+pub fn token_from_env() -> Result<String> {
+    instances::var("GRPC_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| eyre::eyre!("\"GRPC_ADDR\" requires \"GRPC_TOKEN\" to be set"))
+}
+
+/// Checks a call carries `authorization: Bearer <token>`
+pub fn authorize<T>(request: &Request<T>, token: &str) -> Result<(), Status> {
+    let authorized = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map_or(false, |given| {
+            utils::constant_time_eq(given.as_bytes(), token.as_bytes())
+        });
+    if authorized {
+        Ok(())
+    } else {
+        metrics::increment("grpc_unauthorized");
+        Err(Status::unauthenticated("Invalid or missing bearer token"))
+    }
+}
+
+/// Serves the pipeline API on `addr` until the server fails
+pub async fn serve(addr: SocketAddr, token: String) -> Result<()> {
+    tracing::info!("[GRPC] Listening on {}", addr);
+    let service = PipelineServer::with_interceptor(PipelineService, move |request| {
+        authorize(&request, &token).map(|_| request)
+    });
+    Server::builder()
+        .add_service(service)
+        .serve(addr)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to serve the gRPC API on {}: {:?}", addr, e))
+}
+
+/// Executes the plans streamed by the detector at `endpoint` until the stream ends
+///
+/// Each plan is checked by `verifier` before the legs are signed with `tx_factory`'s primary
+/// wallet, then simulated and sent through `relay`. Plans that fail are logged and skipped.
+pub async fn execute_plans(
+    endpoint: String,
+    token: String,
+    verifier: PlanVerifier,
+    client: Arc<Provider<Ws>>,
+    tx_factory: Arc<TxFactory>,
+    relay: FlashbotsRelay,
+) -> Result<()> {
+    let channel = Endpoint::from_shared(endpoint.clone())?.connect().await?;
+    let bearer: MetadataValue<Ascii> = format!("Bearer {}", token).parse()?;
+    let mut pipeline = PipelineClient::with_interceptor(channel, move |mut request: Request<()>| {
+        request
+            .metadata_mut()
+            .insert("authorization", bearer.clone());
+        Ok::<_, Status>(request)
+    });
+    let mut stream = pipeline
+        .stream_opportunities(proto::StreamRequest {})
+        .await?
+        .into_inner();
+    tracing::info!("[PLANS] Executing plans streamed from {}", endpoint);
+    while let Some(opportunity) = stream.message().await? {
+        if opportunity.signed_plan.is_empty() {
+            continue;
+        }
+        if let Err(e) = execute_plan(
+            &opportunity.signed_plan,
+            &verifier,
+            &client,
+            &tx_factory,
+            &relay,
+        )
+        .await
+        {
+            tracing::warn!("[PLANS] {:?}", e);
+        }
+    }
+    eyre::bail!("The plan stream from {} ended", endpoint)
+}
+
+/// Verifies, signs, simulates and sends a single streamed plan
+async fn execute_plan(
+    json: &str,
+    verifier: &PlanVerifier,
+    client: &Provider<Ws>,
+    tx_factory: &TxFactory,
+    relay: &FlashbotsRelay,
+) -> Result<()> {
+    let signed: SignedPlan = serde_json::from_str(json)?;
+    let plan = verifier
+        .verify(&signed, unix_millis())
+        .map_err(|rejection| {
+            metrics::increment(rejection.metric());
+            eyre::eyre!("Refused plan: {}", rejection)
+        })?;
+    let nonce = client
+        .get_transaction_count(tx_factory.searcher(), None)
+        .await?;
+    let legs = plans::sign_plan(tx_factory, &plan, NonceSlot::from(nonce)).await?;
+    let bundle = relayer::construct_bundle(legs, plan.target_block)?;
+    let simulated = relay.simulate_bundle(&bundle).await?;
+    relayer::validate_simulation_response(&simulated)?;
+    let sent = relay.send_bundle(&bundle).await?;
+    metrics::increment("plans_executed");
+    tracing::info!(
+        "[PLANS] Sent plan {:?} sandwiching {:?}: {:?}",
+        plan.id,
+        plan.sandwich.victim,
+        sent.bundle_hash
+    );
+    Ok(())
+}
@@ -0,0 +1,280 @@
+//! Honeypot APIs
+//!
+//! GoPlus and honeypot.is simulate buying and selling a token and report whether it can be sold
+//! and what it taxes. Their verdicts are folded into the [crate::safety] risk score as another
+//! signal. Results are cached per token, and an API that times out or errors either lets the
+//! sandwich proceed or refuses it, depending on the configuration.
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    metrics,
+    safety::{RiskFlag, SafetyReport},
+};
+
+/// The GoPlus token security API
+pub const GOPLUS_API_URL: &str = "https://api.gopluslabs.io/api/v1/token_security";
+
+/// The honeypot.is API
+pub const HONEYPOT_IS_API_URL: &str = "https://api.honeypot.is/v2/IsHoneypot";
+
+/// The default request timeout, in milliseconds
+pub const DEFAULT_HONEYPOT_TIMEOUT_MS: u64 = 300;
+
+/// The default time a verdict is cached, in seconds
+pub const DEFAULT_HONEYPOT_CACHE_SECS: u64 = 600;
+
+/// The default highest buy or sell tax that isn't flagged, in bips
+pub const DEFAULT_MAX_TOKEN_TAX_BPS: u64 = 1_000;
+
+/// A honeypot check API
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HoneypotApi {
+    /// GoPlus token security
+    GoPlus,
+    /// honeypot.is
+    HoneypotIs,
+}
+
+impl FromStr for HoneypotApi {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "goplus" => Ok(HoneypotApi::GoPlus),
+            "honeypotis" | "honeypot.is" => Ok(HoneypotApi::HoneypotIs),
+            _ => Err(eyre::eyre!("Unknown honeypot api \"{}\"", s)),
+        }
+    }
+}
+
+impl HoneypotApi {
+    /// A short name for logs and metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            HoneypotApi::GoPlus => "goplus",
+            HoneypotApi::HoneypotIs => "honeypotis",
+        }
+    }
+}
+
+/// A comma-separated list of honeypot APIs
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HoneypotApis(pub Vec<HoneypotApi>);
+
+impl FromStr for HoneypotApis {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(HoneypotApi::from_str)
+            .collect::<Result<Vec<_>>>()
+            .map(HoneypotApis)
+    }
+}
+
+/// The honeypot check settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HoneypotConfig {
+    /// The APIs to query, none by default
+    pub apis: HoneypotApis,
+    /// The request timeout, in milliseconds
+    pub timeout_ms: u64,
+    /// How long verdicts are cached, in seconds
+    pub cache_secs: u64,
+    /// The highest buy or sell tax that isn't flagged, in bips
+    pub max_tax_bps: u64,
+    /// Whether to proceed when an API times out or errors
+    pub fail_open: bool,
+}
+
+impl Default for HoneypotConfig {
+    fn default() -> Self {
+        Self {
+            apis: HoneypotApis::default(),
+            timeout_ms: DEFAULT_HONEYPOT_TIMEOUT_MS,
+            cache_secs: DEFAULT_HONEYPOT_CACHE_SECS,
+            max_tax_bps: DEFAULT_MAX_TOKEN_TAX_BPS,
+            fail_open: true,
+        }
+    }
+}
+
+/// A token verdict from a honeypot API
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HoneypotVerdict {
+    /// Whether the token can't be sold
+    pub is_honeypot: bool,
+    /// The buy tax, in bips
+    pub buy_tax_bps: u64,
+    /// The sell tax, in bips
+    pub sell_tax_bps: u64,
+}
+
+impl HoneypotVerdict {
+    /// Converts the verdict into risk flags
+    pub fn report(&self, max_tax_bps: u64) -> SafetyReport {
+        let mut report = SafetyReport::default();
+        if self.is_honeypot {
+            report.flag(RiskFlag::Honeypot);
+        }
+        if self.buy_tax_bps.max(self.sell_tax_bps) > max_tax_bps {
+            report.flag(RiskFlag::HighTax);
+        }
+        report
+    }
+}
+
+/// Converts a tax fraction, like `0.05`, to bips
+fn fraction_to_bps(fraction: f64) -> u64 {
+    (fraction * 10_000.0).round().max(0.0) as u64
+}
+
+/// Parses a GoPlus `token_security` response for `token`
+///
+/// GoPlus encodes every field as a string, `"1"` for true and a fraction for taxes.
+pub fn parse_goplus(response: &serde_json::Value, token: Address) -> Result<HoneypotVerdict> {
+    let key = format!("{:?}", token).to_lowercase();
+    let result = response
+        .get("result")
+        .and_then(|r| r.get(&key))
+        .ok_or_else(|| eyre::eyre!("GoPlus has no result for {}", key))?;
+    let flag = |field: &str| result.get(field).and_then(|v| v.as_str()) == Some("1");
+    let tax = |field: &str| {
+        result
+            .get(field)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(fraction_to_bps)
+            .unwrap_or_default()
+    };
+    Ok(HoneypotVerdict {
+        is_honeypot: flag("is_honeypot") || flag("cannot_sell_all"),
+        buy_tax_bps: tax("buy_tax"),
+        sell_tax_bps: tax("sell_tax"),
+    })
+}
+
+/// Parses a honeypot.is `IsHoneypot` response
+///
+/// Taxes are reported as percentages.
+pub fn parse_honeypot_is(response: &serde_json::Value) -> Result<HoneypotVerdict> {
+    let is_honeypot = response
+        .get("honeypotResult")
+        .and_then(|r| r.get("isHoneypot"))
+        .and_then(|v| v.as_bool())
+        .ok_or_else(|| eyre::eyre!("honeypot.is response has no verdict"))?;
+    let tax = |field: &str| {
+        response
+            .get("simulationResult")
+            .and_then(|r| r.get(field))
+            .and_then(|v| v.as_f64())
+            .map(|percent| fraction_to_bps(percent / 100.0))
+            .unwrap_or_default()
+    };
+    Ok(HoneypotVerdict {
+        is_honeypot,
+        buy_tax_bps: tax("buyTax"),
+        sell_tax_bps: tax("sellTax"),
+    })
+}
+
+/// Queries the configured honeypot APIs, caching their combined verdicts per token
+#[derive(Debug)]
+pub struct HoneypotChecker {
+    http: reqwest::Client,
+    config: HoneypotConfig,
+    chain_id: u64,
+    cache: Mutex<HashMap<Address, (Instant, SafetyReport)>>,
+}
+
+impl HoneypotChecker {
+    /// Creates a checker for the configured APIs
+    pub fn new(config: HoneypotConfig, chain_id: u64) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+            chain_id,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns a cached report for `token` if it hasn't expired
+    pub fn cached(&self, token: &Address) -> Option<SafetyReport> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .get(token)
+            .filter(|(at, _)| at.elapsed() < Duration::from_secs(self.config.cache_secs))
+            .map(|(_, report)| report.clone())
+    }
+
+    /// Queries a single API
+    async fn query(&self, api: HoneypotApi, token: Address) -> Result<HoneypotVerdict> {
+        let request = match api {
+            HoneypotApi::GoPlus => self
+                .http
+                .get(format!("{}/{}", GOPLUS_API_URL, self.chain_id))
+                .query(&[("contract_addresses", format!("{:?}", token))]),
+            HoneypotApi::HoneypotIs => self.http.get(HONEYPOT_IS_API_URL).query(&[
+                ("address", format!("{:?}", token)),
+                ("chainID", self.chain_id.to_string()),
+            ]),
+        };
+        let response: serde_json::Value = request
+            .timeout(Duration::from_millis(self.config.timeout_ms))
+            .send()
+            .await?
+            .json()
+            .await?;
+        match api {
+            HoneypotApi::GoPlus => parse_goplus(&response, token),
+            HoneypotApi::HoneypotIs => parse_honeypot_is(&response),
+        }
+    }
+
+    /// Checks a token against every configured API
+    ///
+    /// An API that fails is skipped when failing open, and errors the check otherwise. Reports
+    /// are only cached once every API answered.
+    pub async fn check(&self, token: Address) -> Result<SafetyReport> {
+        if let Some(report) = self.cached(&token) {
+            metrics::increment("honeypot_cache_hit");
+            return Ok(report);
+        }
+
+        let mut report = SafetyReport::default();
+        let mut complete = true;
+        for api in &self.config.apis.0 {
+            match self.query(*api, token).await {
+                Ok(verdict) => report.merge(&verdict.report(self.config.max_tax_bps)),
+                Err(e) => {
+                    metrics::increment(&format!("honeypot_api_failed_{}", api.name()));
+                    if !self.config.fail_open {
+                        return Err(e.wrap_err(format!("{} check failed", api.name())));
+                    }
+                    tracing::debug!("[SAFETY] {} check failed, proceeding: {:?}", api.name(), e);
+                    complete = false;
+                }
+            }
+        }
+
+        if complete {
+            self.cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(token, (Instant::now(), report.clone()));
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,210 @@
+//! Strategy Instances
+//!
+//! One process can run several independent instances of the bot, each on its own chain or set of
+//! DEXes. `INSTANCES` names them, like `mainnet,base`, and every environment variable an instance
+//! reads is looked up with its upper-cased name as a prefix first: the `base` instance reads
+//! `BASE_RPC_URL_WSS` before `RPC_URL_WSS`, so instances only set the variables they don't share.
+//!
+//! [Instance::from_env] reads an instance's config, chain and DEX addresses, wallets and endpoints
+//! once, and its bot is built from them rather than from the environment. Each bot owns its nonce
+//! manager and held victims, and the metrics an instance records are namespaced as
+//! `<name>.<metric>`. The profit ledger, kill switch and admin API stay process-wide.
+//!
+//! Each instance runs under [supervise], which restarts it with a backoff when it fails or panics,
+//! without touching the others. Tasks an instance starts through [spawn] are aborted with it, so a
+//! restart doesn't leave the previous run's watchers behind.
+
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::{
+    config::Config,
+    metrics,
+    utils::{Endpoints, Wallets},
+};
+
+/// The delay before an instance's first restart, doubled on each quick failure
+pub const DEFAULT_RESTART_BACKOFF_SECS: u64 = 5;
+
+/// The longest delay between restarts
+pub const MAX_RESTART_BACKOFF_SECS: u64 = 300;
+
+/// A running instance and the tasks it spawned
+#[derive(Debug)]
+struct InstanceContext {
+    name: String,
+    tasks: Mutex<Vec<AbortHandle>>,
+}
+
+impl InstanceContext {
+    fn new(name: &str) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            tasks: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn abort_tasks(&self) {
+        for task in self
+            .tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .drain(..)
+        {
+            task.abort();
+        }
+    }
+}
+
+tokio::task_local! {
+    static INSTANCE: Arc<InstanceContext>;
+}
+
+/// The names of the instances to run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InstanceNames(pub Vec<String>);
+
+impl FromStr for InstanceNames {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut names: Vec<String> = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                eyre::bail!("Invalid instance name \"{}\"", name);
+            }
+            let name = name.to_lowercase();
+            if names.contains(&name) {
+                eyre::bail!("Duplicate instance name \"{}\"", name);
+            }
+            names.push(name);
+        }
+        if names.is_empty() {
+            eyre::bail!("At least one instance name is required");
+        }
+        Ok(InstanceNames(names))
+    }
+}
+
+/// What an instance's bot is built from, read once from its prefixed environment
+#[derive(Debug, Clone)]
+pub struct Instance {
+    /// The instance name
+    pub name: String,
+    /// The configuration, with the chain and DEX addresses
+    pub config: Config,
+    /// The keys the instance signs with
+    pub wallets: Wallets,
+    /// The nodes the instance connects to
+    pub endpoints: Endpoints,
+}
+
+impl Instance {
+    /// Reads the instance `name`, preferring its prefixed variables
+    pub fn from_env(name: &str) -> Result<Self> {
+        INSTANCE.sync_scope(InstanceContext::new(name), || {
+            Ok(Self {
+                name: name.to_string(),
+                config: Config::from_env()?,
+                wallets: Wallets::from_env()?,
+                endpoints: Endpoints::from_env()?,
+            })
+        })
+    }
+}
+
+/// The name of the instance running the current task, if any
+pub fn current() -> Option<String> {
+    INSTANCE.try_with(|i| i.name.clone()).ok()
+}
+
+/// The environment variable prefix of an instance, like `BASE_`
+pub fn env_prefix(name: &str) -> String {
+    format!("{}_", name.to_uppercase())
+}
+
+/// Reads an environment variable, preferring the current instance's prefixed variable
+pub fn var(key: &str) -> Result<String, std::env::VarError> {
+    let prefixed = INSTANCE
+        .try_with(|i| std::env::var(format!("{}{}", env_prefix(&i.name), key)).ok())
+        .ok()
+        .flatten();
+    match prefixed {
+        Some(value) => Ok(value),
+        None => std::env::var(key),
+    }
+}
+
+/// Namespaces a metric name with the current instance
+pub fn namespaced(metric: &str) -> String {
+    match current() {
+        Some(name) => format!("{name}.{metric}"),
+        None => metric.to_string(),
+    }
+}
+
+/// Spawns a task belonging to the current instance, if any
+///
+/// The task keeps the instance's environment and metrics namespace, and is aborted when the
+/// instance stops.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let instance = match INSTANCE.try_with(Arc::clone) {
+        Ok(i) => i,
+        Err(_) => return tokio::spawn(future),
+    };
+    let handle = tokio::spawn(INSTANCE.scope(instance.clone(), future));
+    let mut tasks = instance.tasks.lock().unwrap_or_else(|e| e.into_inner());
+    tasks.retain(|t| !t.is_finished());
+    tasks.push(handle.abort_handle());
+    handle
+}
+
+/// Runs an instance, restarting it whenever it fails or panics
+///
+/// Returns once a run ends cleanly. The instance runs on the current thread, so this must be
+/// awaited inside a [tokio::task::LocalSet]. Restarts are counted in `instance_restarts_<name>`.
+pub async fn supervise<F, Fut>(name: String, run: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<()>> + 'static,
+{
+    let mut backoff = Duration::from_secs(DEFAULT_RESTART_BACKOFF_SECS);
+    loop {
+        let instance = InstanceContext::new(&name);
+        let started = Instant::now();
+        tracing::info!("[INSTANCE] Starting {}", name);
+        let result = tokio::task::spawn_local(INSTANCE.scope(instance.clone(), run())).await;
+        instance.abort_tasks();
+        match result {
+            Ok(Ok(())) => {
+                tracing::info!("[INSTANCE] {} stopped", name);
+                return;
+            }
+            Ok(Err(e)) => tracing::error!("[INSTANCE] {} failed: {:?}", name, e),
+            Err(e) if e.is_panic() => tracing::error!("[INSTANCE] {} panicked: {:?}", name, e),
+            Err(_) => return,
+        }
+
+        // A run that lasted longer than the longest backoff starts the backoff over
+        let max_backoff = Duration::from_secs(MAX_RESTART_BACKOFF_SECS);
+        if started.elapsed() > max_backoff {
+            backoff = Duration::from_secs(DEFAULT_RESTART_BACKOFF_SECS);
+        }
+        metrics::increment(&format!("instance_restarts_{name}"));
+        tracing::warn!("[INSTANCE] Restarting {} in {:?}", name, backoff);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(max_backoff);
+    }
+}
@@ -0,0 +1,479 @@
+//! JIT Liquidity
+//!
+//! A large swap through a Uniswap V3 pool pays the pool fee to the liquidity in range. Instead of
+//! sandwiching it, the executor can mint a concentrated position around the current price right
+//! before the swap and burn it right after, earning most of the fee for one block of exposure. The
+//! bundle is `[mint, victim, burn]`, built by the [crate::transactions::TxFactory] and shipped
+//! through the same relays as a sandwich, with the burn paying the bribe.
+//!
+//! Only WETH-in `exactInputSingle` swaps through the V3 router are considered, so the fee is earned
+//! in WETH. The position is funded from the executor's inventory of both pool tokens, and pools the
+//! executor holds none of the other token for are skipped. The swap sells WETH into the position on
+//! its way through, so the fees only count net of what the position loses to the price moving.
+
+use std::{str::FromStr, sync::Arc};
+
+use ethers::{
+    abi::{self, ParamType, Token},
+    prelude::*,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{abi::UniswapV3Pool, executor::LiquidityPosition, uniswap_v3};
+
+/// The Uniswap V3 SwapRouter
+pub const UNISWAP_V3_ROUTER: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
+
+/// The router's single-pool exact input swap
+pub const EXACT_INPUT_SINGLE_SIGNATURE: &str =
+    "exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))";
+
+/// The lowest tick a V3 pool supports
+pub const MIN_TICK: i32 = -887_272;
+
+/// The highest tick a V3 pool supports
+pub const MAX_TICK: i32 = 887_272;
+
+/// The gas limit for minting a position
+pub const JIT_MINT_GAS_LIMIT: u64 = 400_000;
+
+/// The gas limit for burning and collecting a position
+pub const JIT_BURN_GAS_LIMIT: u64 = 250_000;
+
+/// The default smallest swap considered, 10 WETH
+pub const DEFAULT_JIT_MIN_SWAP_WETH: u128 = 10_000_000_000_000_000_000;
+
+/// Fees are quoted in hundredths of a bip
+const FEE_DENOMINATOR: u64 = 1_000_000;
+
+/// The JIT liquidity settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JitConfig {
+    /// Whether to provide JIT liquidity to large V3 swaps
+    pub enabled: bool,
+    /// The smallest swap considered, in wei of WETH
+    pub min_swap_weth: U256,
+    /// How many tick spacings the position reaches out from the current tick, `1` for only the
+    /// spacing containing it
+    pub range_spacings: u32,
+}
+
+impl Default for JitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_swap_weth: U256::from(DEFAULT_JIT_MIN_SWAP_WETH),
+            range_spacings: 1,
+        }
+    }
+}
+
+/// Returns the Uniswap V3 SwapRouter address
+pub fn get_univ3_router_address() -> Address {
+    Address::from_str(UNISWAP_V3_ROUTER).unwrap()
+}
+
+/// Computes the address of the V3 pool of two tokens at a fee tier
+pub fn calculate_uniswap_v3_pool_address(a: Address, b: Address, fee: u32) -> Result<Address> {
+    Ok(uniswap_v3::get_pool(a, b, fee))
+}
+
+/// A single-pool exact input swap through the V3 router
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct V3Swap {
+    /// The token sold
+    pub token_in: Address,
+    /// The token bought
+    pub token_out: Address,
+    /// The pool fee, in hundredths of a bip
+    pub fee: u32,
+    /// Who receives the output
+    pub recipient: Address,
+    /// The amount sold
+    pub amount_in: U256,
+    /// The least output accepted
+    pub amount_out_minimum: U256,
+    /// The price the swap stops at, zero for none
+    pub sqrt_price_limit_x96: U256,
+}
+
+impl V3Swap {
+    /// Decodes `exactInputSingle` router calldata
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let selector = ethers::utils::id(EXACT_INPUT_SINGLE_SIGNATURE);
+        if data.len() < 4 || data[..4] != selector {
+            return None;
+        }
+        let params = ParamType::Tuple(vec![
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Uint(24),
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(160),
+        ]);
+        let mut fields = match abi::decode(&[params], &data[4..]).ok()?.pop()? {
+            Token::Tuple(fields) => fields.into_iter(),
+            _ => return None,
+        };
+        let mut next = || fields.next();
+        Some(Self {
+            token_in: next()?.into_address()?,
+            token_out: next()?.into_address()?,
+            fee: next()?.into_uint()?.as_u32(),
+            recipient: next()?.into_address()?,
+            amount_in: {
+                next()?; // deadline
+                next()?.into_uint()?
+            },
+            amount_out_minimum: next()?.into_uint()?,
+            sqrt_price_limit_x96: next()?.into_uint()?,
+        })
+    }
+}
+
+/// The state of a V3 pool a position is planned against
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PoolState {
+    /// The pool
+    pub address: Address,
+    /// The lower sorted token
+    pub token0: Address,
+    /// The current price, as a Q64.96 square root
+    pub sqrt_price_x96: U256,
+    /// The current tick
+    pub tick: i32,
+    /// The liquidity in range
+    pub liquidity: u128,
+    /// The spacing of initializable ticks
+    pub tick_spacing: i32,
+    /// The pool fee, in hundredths of a bip
+    pub fee: u32,
+}
+
+/// Reads the state of a V3 pool
+pub async fn read_pool_state<M: Middleware + 'static>(
+    client: Arc<M>,
+    address: Address,
+    fee: u32,
+) -> Result<PoolState> {
+    let pool = UniswapV3Pool::new(address, client);
+    let map_err = |e| eyre::eyre!("Failed to read pool {:?}: {:?}", address, e);
+    let (sqrt_price_x96, tick, ..) = pool.slot_0().call().await.map_err(map_err)?;
+    Ok(PoolState {
+        address,
+        token0: pool.token_0().call().await.map_err(map_err)?,
+        sqrt_price_x96,
+        tick,
+        liquidity: pool.liquidity().call().await.map_err(map_err)?,
+        tick_spacing: pool.tick_spacing().call().await.map_err(map_err)?,
+        fee,
+    })
+}
+
+/// `2^96`, the fixed point scale of square root prices
+pub fn q96() -> U256 {
+    U256::one() << 96
+}
+
+/// Computes `a * b / denominator` without overflowing the product
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    if denominator.is_zero() {
+        return None;
+    }
+    U256::try_from(a.full_mul(b) / U512::from(denominator)).ok()
+}
+
+/// Returns the Q64.96 square root price at a tick, like the pools' `TickMath.getSqrtRatioAtTick`
+pub fn sqrt_ratio_at_tick(tick: i32) -> Result<U256> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        eyre::bail!("Tick {} is out of range", tick);
+    }
+    const FACTORS: [(u32, &str); 19] = [
+        (0x2, "fff97272373d413259a46990580e213a"),
+        (0x4, "fff2e50f5f656932ef12357cf3c7fdcc"),
+        (0x8, "ffe5caca7e10e4e61c3624eaa0941cd0"),
+        (0x10, "ffcb9843d60f6159c9db58835c926644"),
+        (0x20, "ff973b41fa98c081472e6896dfb254c0"),
+        (0x40, "ff2ea16466c96a3843ec78b326b52861"),
+        (0x80, "fe5dee046a99a2a811c461f1969c3053"),
+        (0x100, "fcbe86c7900a88aedcffc83b479aa3a4"),
+        (0x200, "f987a7253ac413176f2b074cf7815e54"),
+        (0x400, "f3392b0822b70005940c7a398e4b70f3"),
+        (0x800, "e7159475a2c29b7443b29c7fa6e889d9"),
+        (0x1000, "d097f3bdfd2022b8845ad8f792aa5825"),
+        (0x2000, "a9f746462d870fdf8a65dc1f90e061e5"),
+        (0x4000, "70d869a156d2a1b890bb3df62baf32f7"),
+        (0x8000, "31be135f97d08fd981231505542fcfa6"),
+        (0x10000, "9aa508b5b7a84e1c677de54f3e99bc9"),
+        (0x20000, "5d6af8dedb81196699c329225ee604"),
+        (0x40000, "2216e584f5fa1ea926041bedfe98"),
+        (0x80000, "48a170391f7dc42444e8fa2"),
+    ];
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        U256::from_str_radix("fffcb933bd6fad37aa2d162d1a594001", 16)?
+    } else {
+        U256::one() << 128
+    };
+    for (bit, factor) in FACTORS {
+        if abs_tick & bit != 0 {
+            ratio = (ratio * U256::from_str_radix(factor, 16)?) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+    // Round up, so the price at a tick is never below the tick
+    let rounding = if (ratio & U256::from(u32::MAX)).is_zero() {
+        U256::zero()
+    } else {
+        U256::one()
+    };
+    Ok((ratio >> 32) + rounding)
+}
+
+/// Orders two square root prices
+fn sorted(a: U256, b: U256) -> (U256, U256) {
+    if a > b {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+/// The liquidity `amount0` of token0 provides between two prices
+pub fn liquidity_for_amount0(sqrt_a: U256, sqrt_b: U256, amount0: U256) -> Option<U256> {
+    let (sqrt_a, sqrt_b) = sorted(sqrt_a, sqrt_b);
+    let intermediate = mul_div(sqrt_a, sqrt_b, q96())?;
+    mul_div(amount0, intermediate, sqrt_b - sqrt_a)
+}
+
+/// The liquidity `amount1` of token1 provides between two prices
+pub fn liquidity_for_amount1(sqrt_a: U256, sqrt_b: U256, amount1: U256) -> Option<U256> {
+    let (sqrt_a, sqrt_b) = sorted(sqrt_a, sqrt_b);
+    mul_div(amount1, q96(), sqrt_b - sqrt_a)
+}
+
+/// The most liquidity the amounts provide between two prices at the current price
+pub fn liquidity_for_amounts(
+    sqrt_price: U256,
+    sqrt_a: U256,
+    sqrt_b: U256,
+    amount0: U256,
+    amount1: U256,
+) -> Option<U256> {
+    let (sqrt_a, sqrt_b) = sorted(sqrt_a, sqrt_b);
+    if sqrt_price <= sqrt_a {
+        liquidity_for_amount0(sqrt_a, sqrt_b, amount0)
+    } else if sqrt_price < sqrt_b {
+        let liquidity0 = liquidity_for_amount0(sqrt_price, sqrt_b, amount0)?;
+        let liquidity1 = liquidity_for_amount1(sqrt_a, sqrt_price, amount1)?;
+        Some(liquidity0.min(liquidity1))
+    } else {
+        liquidity_for_amount1(sqrt_a, sqrt_b, amount1)
+    }
+}
+
+/// The token amounts backing `liquidity` between two prices at the current price
+pub fn amounts_for_liquidity(
+    sqrt_price: U256,
+    sqrt_a: U256,
+    sqrt_b: U256,
+    liquidity: U256,
+) -> Option<(U256, U256)> {
+    let (sqrt_a, sqrt_b) = sorted(sqrt_a, sqrt_b);
+    let amount0 = |lower: U256| mul_div(liquidity << 96, sqrt_b - lower, sqrt_b).map(|a| a / lower);
+    let amount1 = |upper: U256| mul_div(liquidity, upper - sqrt_a, q96());
+    if sqrt_price <= sqrt_a {
+        Some((amount0(sqrt_a)?, U256::zero()))
+    } else if sqrt_price < sqrt_b {
+        Some((amount0(sqrt_price)?, amount1(sqrt_price)?))
+    } else {
+        Some((U256::zero(), amount1(sqrt_b)?))
+    }
+}
+
+/// The range of the spacing containing `tick`, widened by `spacings - 1` spacings on each side
+pub fn position_range(tick: i32, tick_spacing: i32, spacings: u32) -> Option<(i32, i32)> {
+    if tick_spacing <= 0 || spacings == 0 {
+        return None;
+    }
+    let floor = tick.div_euclid(tick_spacing) * tick_spacing;
+    let width = tick_spacing.checked_mul(spacings as i32)?;
+    let lower = floor.checked_sub(width - tick_spacing)?;
+    let upper = floor.checked_add(width)?;
+    (lower >= MIN_TICK && upper <= MAX_TICK).then_some((lower, upper))
+}
+
+/// The square root price after `amount_in`, net of the fee, is swapped through `liquidity`
+///
+/// Selling token0 lowers the price and selling token1 raises it, like the pools' `SqrtPriceMath`.
+pub fn next_sqrt_price(
+    sqrt_price: U256,
+    liquidity: U256,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Option<U256> {
+    if liquidity.is_zero() {
+        return None;
+    }
+    if zero_for_one {
+        let numerator = liquidity.checked_mul(q96())?;
+        let denominator = numerator.checked_add(amount_in.checked_mul(sqrt_price)?)?;
+        mul_div(numerator, sqrt_price, denominator)
+    } else {
+        sqrt_price.checked_add(mul_div(amount_in, q96(), liquidity)?)
+    }
+}
+
+/// What a position loses to the price moving through it, in the token sold into the pool
+///
+/// The position sells the other token on the way at a worse price than the price the swap ends
+/// at, so it's worth less after the swap than its tokens held aside would be.
+pub fn inventory_loss(
+    sqrt_a: U256,
+    sqrt_b: U256,
+    liquidity: U256,
+    sqrt_price: U256,
+    next_sqrt_price: U256,
+    zero_for_one: bool,
+) -> Option<U256> {
+    let (before0, before1) = amounts_for_liquidity(sqrt_price, sqrt_a, sqrt_b, liquidity)?;
+    let (after0, after1) = amounts_for_liquidity(next_sqrt_price, sqrt_a, sqrt_b, liquidity)?;
+    let (received, sold) = if zero_for_one {
+        // token1 priced in token0 at the new price
+        let sold = before1.saturating_sub(after1);
+        let sold = mul_div(
+            mul_div(sold, q96(), next_sqrt_price)?,
+            q96(),
+            next_sqrt_price,
+        )?;
+        (after0.saturating_sub(before0), sold)
+    } else {
+        // token0 priced in token1 at the new price
+        let sold = before0.saturating_sub(after0);
+        let sold = mul_div(
+            mul_div(sold, next_sqrt_price, q96())?,
+            next_sqrt_price,
+            q96(),
+        )?;
+        (after1.saturating_sub(before1), sold)
+    };
+    Some(sold.saturating_sub(received))
+}
+
+/// A planned JIT position and what it's expected to earn
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JitPlan {
+    /// The position minted ahead of the swap
+    pub position: LiquidityPosition,
+    /// The token0 paid into the position
+    pub amount0: U256,
+    /// The token1 paid into the position
+    pub amount1: U256,
+    /// The share of the in-range liquidity the position holds, in bips
+    pub share_bps: u64,
+    /// The swap fee the position earns if the swap stays in range, in wei of WETH
+    pub expected_fees: U256,
+    /// What the position loses to the swap moving the price through it, in wei of WETH
+    pub inventory_loss: U256,
+}
+
+impl JitPlan {
+    /// The fees earned net of the inventory loss, in wei of WETH
+    pub fn net_fees(&self) -> U256 {
+        self.expected_fees.saturating_sub(self.inventory_loss)
+    }
+}
+
+/// Plans a position around the current price from the executor's token balances
+///
+/// Returns `None` if the swap is too small or doesn't sell WETH, or if the balances can't mint any
+/// liquidity in range.
+pub fn plan_jit(
+    swap: &V3Swap,
+    pool: &PoolState,
+    weth: Address,
+    balance0: U256,
+    balance1: U256,
+    config: &JitConfig,
+) -> Option<JitPlan> {
+    if swap.token_in != weth || swap.amount_in < config.min_swap_weth {
+        return None;
+    }
+    let (tick_lower, tick_upper) =
+        position_range(pool.tick, pool.tick_spacing, config.range_spacings)?;
+    let sqrt_lower = sqrt_ratio_at_tick(tick_lower).ok()?;
+    let sqrt_upper = sqrt_ratio_at_tick(tick_upper).ok()?;
+
+    // Mint amounts round up, so leave a wei of each balance spare
+    let liquidity = liquidity_for_amounts(
+        pool.sqrt_price_x96,
+        sqrt_lower,
+        sqrt_upper,
+        balance0.saturating_sub(U256::one()),
+        balance1.saturating_sub(U256::one()),
+    )?;
+    if liquidity.is_zero() || liquidity > U256::from(u128::MAX) {
+        return None;
+    }
+    let (amount0, amount1) =
+        amounts_for_liquidity(pool.sqrt_price_x96, sqrt_lower, sqrt_upper, liquidity)?;
+
+    let total = liquidity + U256::from(pool.liquidity);
+    let share_bps = (liquidity * U256::from(10_000) / total).as_u64();
+    let fee = mul_div(
+        swap.amount_in,
+        U256::from(pool.fee),
+        U256::from(FEE_DENOMINATOR),
+    )?;
+    let expected_fees = mul_div(fee, liquidity, total)?;
+
+    // The swap moves the price through the position, which sells the other token on the way
+    let zero_for_one = swap.token_in == pool.token0;
+    let next_sqrt_price = next_sqrt_price(
+        pool.sqrt_price_x96,
+        total,
+        swap.amount_in - fee,
+        zero_for_one,
+    )?
+    .clamp(sqrt_lower, sqrt_upper);
+    let inventory_loss = inventory_loss(
+        sqrt_lower,
+        sqrt_upper,
+        liquidity,
+        pool.sqrt_price_x96,
+        next_sqrt_price,
+        zero_for_one,
+    )?;
+    Some(JitPlan {
+        position: LiquidityPosition {
+            pool: pool.address,
+            tick_lower,
+            tick_upper,
+            liquidity: liquidity.as_u128(),
+        },
+        amount0,
+        amount1,
+        share_bps,
+        expected_fees,
+        inventory_loss,
+    })
+}
+
+/// Prices the burn's priority fee from a plan's net fees, like a sandwich's backrun bribe
+///
+/// The mint pays the base fee, and `bribe_bps` of the fees left over go to the builder through the
+/// burn. Returns `None` if nothing would be left after gas and the bribe.
+pub fn jit_bribe(expected_fees: U256, base_fee: U256, bribe_bps: u64) -> Option<U256> {
+    let mint_cost = U256::from(JIT_MINT_GAS_LIMIT) * base_fee;
+    let burn_gas = U256::from(JIT_BURN_GAS_LIMIT);
+    let bribe = expected_fees.checked_sub(mint_cost)? * U256::from(bribe_bps) / 10_000;
+    let priority_fee = bribe / burn_gas;
+    let cost = mint_cost + burn_gas * (base_fee + priority_fee);
+    (expected_fees > cost).then_some(priority_fee)
+}
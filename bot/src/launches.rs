@@ -0,0 +1,248 @@
+//! Pair Launches
+//!
+//! Launch-hour trading is where the loosest slippage settings show up. New WETH pairs created by
+//! the Uniswap V2 factory are watched, and once a pair holds enough liquidity and its token passes
+//! the bytecode scan, the token is tracked in [LaunchedTokens] for a while. Swaps into tracked
+//! tokens are boosted in the evaluation queue.
+//!
+//! Pairs are usually created and funded in the same transaction, but a pair created empty is
+//! rechecked for liquidity for [LAUNCH_PENDING_BLOCKS] blocks.
+//!
+//! Independently of the watcher, [pair_is_mature] refuses pairs younger than a set number of
+//! blocks, where a rug is most likely, unless the pair or its token is a trusted launch.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::{V2PairCreated, PAIR_CREATED_TOPIC},
+    metrics,
+    safety::{self, AddressList},
+    state::StateReader,
+};
+
+/// The default WETH a new pair must hold to be tracked, 1 ether
+pub const DEFAULT_LAUNCH_MIN_LIQUIDITY: u128 = 1_000_000_000_000_000_000;
+
+/// The default time a launched token is tracked, one hour
+pub const DEFAULT_LAUNCH_TRACK_SECS: u64 = 3_600;
+
+/// How many blocks a pair created without enough liquidity is rechecked
+pub const LAUNCH_PENDING_BLOCKS: u64 = 25;
+
+/// The score boost of swaps into a launched token, in bips
+pub const LAUNCH_BOOST_BPS: u64 = 10_000;
+
+/// The launch watcher settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LaunchConfig {
+    /// Whether to watch for new pairs
+    pub enabled: bool,
+    /// The WETH a new pair must hold to be tracked
+    pub min_liquidity: U256,
+    /// How long a launched token is tracked, in seconds
+    pub track_secs: u64,
+    /// The youngest a pair may be to be sandwiched, in blocks, or 0 to allow any age
+    pub min_pair_age_blocks: u64,
+    /// Pairs or tokens exempt from `min_pair_age_blocks`
+    pub trusted_launches: AddressList,
+}
+
+impl LaunchConfig {
+    /// Whether a pair or its token is a trusted launch
+    pub fn is_trusted(&self, pair: &Address, token: &Address) -> bool {
+        self.trusted_launches.0.contains(pair) || self.trusted_launches.0.contains(token)
+    }
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_liquidity: U256::from(DEFAULT_LAUNCH_MIN_LIQUIDITY),
+            track_secs: DEFAULT_LAUNCH_TRACK_SECS,
+            min_pair_age_blocks: 0,
+            trusted_launches: AddressList::default(),
+        }
+    }
+}
+
+/// Recently launched tokens and when they stop being tracked
+#[derive(Debug, Default)]
+pub struct LaunchedTokens {
+    tokens: Mutex<HashMap<Address, Instant>>,
+}
+
+impl LaunchedTokens {
+    /// Tracks a token until `until`
+    pub fn insert(&self, token: Address, until: Instant) {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        tokens.retain(|_, until| *until > now);
+        tokens.insert(token, until);
+    }
+
+    /// Whether a token is still tracked at `now`
+    pub fn contains(&self, token: &Address, now: Instant) -> bool {
+        self.tokens
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(token)
+            .map_or(false, |until| *until > now)
+    }
+
+    /// Boosts the score of a swap into `token` if it's tracked
+    pub fn boost(&self, token: Option<Address>, score: U256, now: Instant) -> U256 {
+        match token {
+            Some(t) if self.contains(&t, now) => {
+                score.saturating_mul(U256::from(10_000 + LAUNCH_BOOST_BPS)) / 10_000
+            }
+            _ => score,
+        }
+    }
+}
+
+/// Returns the global launched tokens
+pub fn launches() -> &'static LaunchedTokens {
+    static LAUNCHES: OnceLock<LaunchedTokens> = OnceLock::new();
+    LAUNCHES.get_or_init(LaunchedTokens::default)
+}
+
+/// Checks whether a new pair should be tracked, returning its token
+///
+/// Returns `None` for pairs without WETH or without enough liquidity yet, and errors for tokens
+/// that fail the bytecode scan.
+pub async fn admit_pair<S: StateReader + ?Sized>(
+    state: &S,
+    created: &V2PairCreated,
+    weth: Address,
+    config: &LaunchConfig,
+    max_risk_score: u32,
+) -> Result<Option<Address>> {
+    let token = match created.other(weth) {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    let (reserve0, reserve1) = state.reserves(created.pair, None).await?;
+    let weth_reserves = if created.token0 == weth {
+        reserve0
+    } else {
+        reserve1
+    };
+    if weth_reserves < config.min_liquidity {
+        return Ok(None);
+    }
+    let report = safety::scan_token(state, token).await?;
+    if !report.is_safe(max_risk_score) {
+        eyre::bail!(
+            "Launched token {:?} scored {} {:?}",
+            token,
+            report.score(),
+            report.flags
+        );
+    }
+    Ok(Some(token))
+}
+
+/// Checks whether a pair is at least `min_pair_age_blocks` old at `block`
+///
+/// A pair without code `min_pair_age_blocks` ago was created since. Trusted launches and a
+/// `min_pair_age_blocks` of 0 always pass.
+pub async fn pair_is_mature<S: StateReader + ?Sized>(
+    state: &S,
+    config: &LaunchConfig,
+    pair: Address,
+    token: Address,
+    block: U64,
+) -> Result<bool> {
+    if config.min_pair_age_blocks == 0 || config.is_trusted(&pair, &token) {
+        return Ok(true);
+    }
+    let past = block.saturating_sub(config.min_pair_age_blocks.into());
+    let code = state.code(pair, Some(past)).await?;
+    Ok(!code.as_ref().is_empty())
+}
+
+/// Watches the Uniswap V2 `factory` for new WETH pairs, tracking those that qualify
+pub async fn watch_pair_launches(
+    client: Arc<Provider<Ws>>,
+    state: Arc<dyn StateReader>,
+    weth: Address,
+    factory: Address,
+    config: LaunchConfig,
+    max_risk_score: u32,
+) -> Result<()> {
+    let filter = Filter::new()
+        .address(factory)
+        .topic0(H256::from(PAIR_CREATED_TOPIC));
+    let mut logs = client
+        .subscribe_logs(&filter)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to subscribe to pair creations: {:?}", e))?;
+    let mut blocks = client
+        .subscribe_blocks()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to subscribe to blocks: {:?}", e))?;
+    let track = Duration::from_secs(config.track_secs);
+
+    // Pairs waiting for liquidity, with the block they were created in
+    let mut pending: Vec<(V2PairCreated, U64)> = Vec::new();
+    let mut head = U64::zero();
+    loop {
+        let candidates = tokio::select! {
+            log = logs.next() => {
+                let log = match log {
+                    Some(l) => l,
+                    None => break,
+                };
+                match V2PairCreated::decode(&log) {
+                    Ok(created) => {
+                        metrics::increment("launches_seen");
+                        vec![(created, log.block_number.unwrap_or(head))]
+                    }
+                    Err(_) => continue,
+                }
+            }
+            block = blocks.next() => {
+                let block = match block {
+                    Some(b) => b,
+                    None => break,
+                };
+                match block.number {
+                    Some(number) => head = number,
+                    None => continue,
+                }
+                pending.retain(|(_, created_at)| head <= *created_at + LAUNCH_PENDING_BLOCKS);
+                std::mem::take(&mut pending)
+            }
+        };
+
+        for (created, created_at) in candidates {
+            match admit_pair(&*state, &created, weth, &config, max_risk_score).await {
+                Ok(Some(token)) => {
+                    metrics::increment("launches_tracked");
+                    tracing::info!(
+                        "[LAUNCH] Tracking {:?} launched in pair {:?}",
+                        token,
+                        created.pair
+                    );
+                    launches().insert(token, Instant::now() + track);
+                }
+                Ok(None) if created.other(weth).is_some() => pending.push((created, created_at)),
+                Ok(None) => {}
+                Err(e) => {
+                    metrics::increment("launches_refused");
+                    tracing::debug!("[LAUNCH] {:?}", e);
+                }
+            }
+        }
+    }
+    Err(eyre::eyre!("Pair creation subscription ended"))
+}
@@ -7,26 +7,242 @@
 /// A banner
 pub mod banner;
 
+/// Gas Reserve Monitoring
+pub mod balance;
+
 /// A Relayer
+#[cfg(feature = "relay")]
 pub mod relayer;
 
 /// Common Utilities
 pub mod utils;
 
+/// Profit Accounting
+pub mod accounting;
+
+/// Admin API
+pub mod admin;
+
+/// Inventory Allocation
+pub mod allocation;
+
+/// Pending Victim Approvals
+pub mod allowances;
+
+/// Executor Token Approvals
+pub mod approvals;
+
+/// Executor Control
+pub mod control;
+
+/// Submission Audit
+pub mod audit;
+
+/// Bot Assembly
+#[cfg(feature = "relay")]
+pub mod bot;
+
 /// ABIs
 pub mod abi;
 
 /// Uniswap Utilities
 pub mod uniswap;
 
+/// Uniswap V3 Pools
+pub mod uniswap_v3;
+
 /// Numeric Operations
 pub mod numeric;
 
+/// Fast 256-bit Math
+#[cfg(feature = "fast-math")]
+pub mod fast_math;
+
+/// Chain Addresses
+pub mod chain;
+
+/// Chainbound Fiber and Echo
+pub mod chainbound;
+
+/// Alloy Primitive Conversions
+#[cfg(feature = "alloy")]
+pub mod compat;
+
+/// Transaction Envelopes
+pub mod envelope;
+
+/// Sandwich Evaluation
+pub mod evaluate;
+
+/// Expected Value
+pub mod ev;
+
+/// Decision Reports
+pub mod explain;
+
+/// Sandwich Executor Contract Adapters
+pub mod executor;
+
+/// Opportunity Exports
+pub mod export;
+
+/// Sandwich Transaction Construction
+pub mod transactions;
+
+/// Clock Synchronization
+pub mod clock;
+
+/// Bot Configuration
+pub mod config;
+
+/// Bundle Conflicts
+pub mod conflicts;
+
+/// Reserve Sanity
+pub mod reserves;
+
+/// Risk Checks
+pub mod risk;
+
+/// Bot Metrics
+pub mod metrics;
+
+/// bloXroute BDN
+pub mod bloxroute;
+
+/// Gas Pricing
+pub mod gas;
+
+/// Pipeline gRPC API
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+/// Honeypot APIs
+pub mod honeypot;
+
+/// Strategy Instances
+pub mod instances;
+
+/// JIT Liquidity
+pub mod jit;
+
+/// Pair Launches
+pub mod launches;
+
+/// Liquidations
+pub mod liquidations;
+
+/// Manual Approval
+pub mod manual_approval;
+
+/// Mempool Sources
+pub mod mempool;
+
+/// MEV-Share Backruns
+pub mod mev_share;
+
+/// Victim Nonce Gaps
+pub mod nonces;
+
+/// Notifications
+pub mod notify;
+
+/// Observer Mode
+pub mod observer;
+
+/// Opportunities
+pub mod opportunity;
+
+/// Off-chain Order Flow
+pub mod orders;
+
+/// Candidate Pipeline
+pub mod pipeline;
+
+/// Signed Sandwich Plans
+pub mod plans;
+
+/// Competition Postmortems
+pub mod postmortem;
+
+/// Victim Profiles
+pub mod profiles;
+
+/// Victim and Token Safety
+pub mod safety;
+
+/// Mempool Flow Analytics
+pub mod scout;
+
+/// Scripted Filters
+#[cfg(feature = "scripting")]
+pub mod scripting;
+
+/// Session Reports
+pub mod session;
+
+/// Victim Simulation
+pub mod simulation;
+
+/// Profit Skimming
+pub mod skim;
+
+/// Chain Data Snapshots
+pub mod snapshot;
+
+/// Spam Protection
+pub mod spam;
+
+/// State Reader
+pub mod state;
+
+/// Strategy Rules
+pub mod strategy;
+
+/// Block Summaries
+pub mod summary;
+
+/// Task Supervision
+pub mod supervisor;
+
+/// Submission Timing
+pub mod timing;
+
+/// Relay Transport
+pub mod transport;
+
+/// Test Doubles
+#[cfg(feature = "testing")]
+pub mod testing;
+
 /// Telemetry
 /// Uses [tracing](https://crates.io/crates/tracing) and [tracing-subscriber](https://crates.io/crates/tracing-subscriber)
+#[cfg(feature = "telemetry")]
 pub mod telemetry;
 
 /// Re-export a prelude
 pub mod prelude {
-    pub use super::{abi::*, banner::*, numeric::*, relayer::*, uniswap::*, utils::*};
+    pub use super::{
+        abi::*, accounting::*, admin::*, allocation::*, allowances::*, approvals::*, audit::*,
+        balance::*, banner::*, bloxroute::*, chain::*, chainbound::*, clock::*, config::*,
+        conflicts::*, control::*, envelope::*, ev::*, evaluate::*, executor::*, explain::*,
+        export::*, gas::*, honeypot::*, instances::*, jit::*, launches::*, liquidations::*,
+        manual_approval::*, mempool::*, metrics::*, mev_share::*, nonces::*, notify::*, numeric::*,
+        observer::*, opportunity::*, orders::*, pipeline::*, plans::*, postmortem::*, profiles::*,
+        reserves::*, risk::*, safety::*, scout::*, session::*, simulation::*, skim::*, snapshot::*,
+        state::*, strategy::*, summary::*, supervisor::*, timing::*, transactions::*, transport::*,
+        uniswap::*, uniswap_v3::*, utils::*,
+    };
+
+    #[cfg(feature = "relay")]
+    pub use super::{bot::*, relayer::*};
+
+    #[cfg(feature = "grpc")]
+    pub use super::grpc::*;
+
+    #[cfg(feature = "scripting")]
+    pub use super::scripting::*;
+
+    #[cfg(feature = "testing")]
+    pub use super::testing::*;
 }
@@ -0,0 +1,619 @@
+//! Liquidations
+//!
+//! A second strategy alongside sandwiching. Aave V3 and Compound V2 borrowers are discovered from
+//! their `Borrow` and collateral events into a [PositionBook], and their health is refreshed
+//! periodically. Positions that are already underwater are liquidated right away, and positions
+//! close to it are liquidated in a backrun of a pending Chainlink price update, in a
+//! `[oracle update, liquidation]` bundle.
+//!
+//! The price an update will report isn't decoded. Instead the closest positions are tried and the
+//! relay simulation decides, so a liquidation is only sent if it doesn't revert after the update.
+//! Debt is repaid from the executor's inventory of the debt asset, and the seized collateral, as
+//! the underlying for Aave and as cTokens for Compound, is left on the executor.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    str::FromStr,
+    sync::Arc,
+};
+
+use ethers::{
+    abi::{self, Token},
+    prelude::*,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::{AavePool, CToken, Comptroller, ERC20},
+    approvals::AAVE_V3_POOL,
+    safety::AddressList,
+};
+
+/// The Compound V2 comptroller
+pub const COMPOUND_COMPTROLLER: &str = "0x3d9819210A31b4961b30EF54bE2aeD79B9c9Cd3B";
+
+/// The Aave liquidation entrypoint
+pub const AAVE_LIQUIDATION_SIGNATURE: &str =
+    "liquidationCall(address,address,address,uint256,bool)";
+
+/// The Compound liquidation entrypoint, called on the borrowed market
+pub const COMPOUND_LIQUIDATION_SIGNATURE: &str = "liquidateBorrow(address,uint256,address)";
+
+/// The Aave V3 `Borrow` event, with the reserve and borrower indexed
+pub const AAVE_BORROW_EVENT: &str = "Borrow(address,address,address,uint256,uint8,uint256,uint16)";
+
+/// The Aave V3 event enabling a reserve as a user's collateral
+pub const AAVE_COLLATERAL_EVENT: &str = "ReserveUsedAsCollateralEnabled(address,address)";
+
+/// The Compound V2 cToken `Borrow` event
+pub const COMPOUND_BORROW_EVENT: &str = "Borrow(address,uint256,uint256,uint256)";
+
+/// The Compound V2 comptroller event entering an account into a market
+pub const COMPOUND_MARKET_ENTERED_EVENT: &str = "MarketEntered(address,address)";
+
+/// The gas limit for a liquidation
+pub const LIQUIDATION_GAS_LIMIT: u64 = 800_000;
+
+/// The default Aave health factor, in bips, below which a position is near liquidation
+pub const DEFAULT_WATCH_HEALTH_BPS: u64 = 10_500;
+
+/// The default Compound account liquidity, in 18 decimal USD, below which a position is near
+/// liquidation
+pub const DEFAULT_WATCH_LIQUIDITY_USD: u128 = 1_000_000_000_000_000_000_000;
+
+/// The default blocks of events scanned for borrowers at startup
+pub const DEFAULT_LIQUIDATION_LOOKBACK_BLOCKS: u64 = 50_000;
+
+/// The default time between health refreshes, in seconds
+pub const DEFAULT_LIQUIDATION_REFRESH_SECS: u64 = 60;
+
+/// The default liquidation priority fee, 2 gwei
+pub const DEFAULT_LIQUIDATION_PRIORITY_FEE: u128 = 2_000_000_000;
+
+/// The default most liquidations tried behind a single oracle update
+pub const DEFAULT_MAX_LIQUIDATIONS_PER_UPDATE: usize = 3;
+
+/// A health factor of one, in 18 decimals
+const HEALTH_FACTOR_ONE: u128 = 1_000_000_000_000_000_000;
+
+/// A supported lending protocol
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LendingProtocol {
+    /// Aave V3, liquidated through the pool
+    AaveV3,
+    /// Compound V2, liquidated through the borrowed cToken
+    CompoundV2,
+}
+
+impl FromStr for LendingProtocol {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "aave" | "aavev3" => Ok(LendingProtocol::AaveV3),
+            "compound" | "compoundv2" => Ok(LendingProtocol::CompoundV2),
+            _ => Err(eyre::eyre!("Unknown lending protocol \"{}\"", s)),
+        }
+    }
+}
+
+/// A comma-separated list of lending protocols
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LendingProtocols(pub Vec<LendingProtocol>);
+
+impl Default for LendingProtocols {
+    fn default() -> Self {
+        Self(vec![LendingProtocol::AaveV3, LendingProtocol::CompoundV2])
+    }
+}
+
+impl FromStr for LendingProtocols {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(LendingProtocol::from_str)
+            .collect::<Result<Vec<_>>>()
+            .map(LendingProtocols)
+    }
+}
+
+/// The liquidation strategy settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LiquidationConfig {
+    /// Whether to run the liquidation strategy
+    pub enabled: bool,
+    /// The protocols watched
+    pub protocols: LendingProtocols,
+    /// The Chainlink aggregators whose pending updates are backrun
+    pub oracles: AddressList,
+    /// The Aave health factor, in bips, below which a position is near liquidation
+    pub watch_health_bps: u64,
+    /// The Compound account liquidity, in 18 decimal USD, below which a position is near liquidation
+    pub watch_liquidity_usd: U256,
+    /// Blocks of events scanned for borrowers at startup
+    pub lookback_blocks: u64,
+    /// How often position health is refreshed, in seconds
+    pub refresh_secs: u64,
+    /// The priority fee paid by liquidations, in wei per gas
+    pub priority_fee: U256,
+    /// The most liquidations tried behind a single oracle update
+    pub max_per_update: usize,
+    /// The Aave V3 pool
+    pub aave_pool: Address,
+    /// The Compound V2 comptroller
+    pub comptroller: Address,
+}
+
+impl Default for LiquidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            protocols: LendingProtocols::default(),
+            oracles: AddressList::default(),
+            watch_health_bps: DEFAULT_WATCH_HEALTH_BPS,
+            watch_liquidity_usd: U256::from(DEFAULT_WATCH_LIQUIDITY_USD),
+            lookback_blocks: DEFAULT_LIQUIDATION_LOOKBACK_BLOCKS,
+            refresh_secs: DEFAULT_LIQUIDATION_REFRESH_SECS,
+            priority_fee: U256::from(DEFAULT_LIQUIDATION_PRIORITY_FEE),
+            max_per_update: DEFAULT_MAX_LIQUIDATIONS_PER_UPDATE,
+            aave_pool: get_aave_pool_address(),
+            comptroller: get_comptroller_address(),
+        }
+    }
+}
+
+/// Returns the Aave V3 pool address
+pub fn get_aave_pool_address() -> Address {
+    Address::from_str(AAVE_V3_POOL).unwrap()
+}
+
+/// Returns the Compound V2 comptroller address
+pub fn get_comptroller_address() -> Address {
+    Address::from_str(COMPOUND_COMPTROLLER).unwrap()
+}
+
+/// How close a position is to liquidation
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Health {
+    /// An Aave health factor, in 18 decimals, liquidatable below one
+    Aave {
+        /// The health factor
+        health_factor: U256,
+    },
+    /// A Compound account's excess collateral or shortfall, in 18 decimal USD
+    Compound {
+        /// The collateral value above the borrow limit
+        liquidity: U256,
+        /// The borrows above the borrow limit, liquidatable when non-zero
+        shortfall: U256,
+    },
+}
+
+impl Health {
+    /// Whether the position can be liquidated now
+    pub fn is_liquidatable(&self) -> bool {
+        match self {
+            Health::Aave { health_factor } => *health_factor < U256::from(HEALTH_FACTOR_ONE),
+            Health::Compound { shortfall, .. } => !shortfall.is_zero(),
+        }
+    }
+
+    /// Whether the position is liquidatable or close enough for a price update to tip it over
+    pub fn is_near(&self, config: &LiquidationConfig) -> bool {
+        match self {
+            Health::Aave { health_factor } => {
+                *health_factor < U256::from(HEALTH_FACTOR_ONE) * config.watch_health_bps / 10_000
+            }
+            Health::Compound {
+                liquidity,
+                shortfall,
+            } => !shortfall.is_zero() || *liquidity < config.watch_liquidity_usd,
+        }
+    }
+
+    /// Orders positions from the most to the least liquidatable within a protocol
+    fn margin(&self) -> U256 {
+        match self {
+            Health::Aave { health_factor } => *health_factor,
+            Health::Compound { liquidity, .. } => *liquidity,
+        }
+    }
+}
+
+/// A borrower's position in one protocol
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Position {
+    /// The protocol
+    pub protocol: LendingProtocol,
+    /// The borrower
+    pub borrower: Address,
+    /// The borrowed markets, Aave reserves or Compound cTokens
+    pub debts: BTreeSet<Address>,
+    /// The collateral markets, Aave reserves or Compound cTokens
+    pub collaterals: BTreeSet<Address>,
+    /// The last health read, if any
+    pub health: Option<Health>,
+}
+
+impl Position {
+    /// Creates a position without any markets
+    pub fn new(protocol: LendingProtocol, borrower: Address) -> Self {
+        Self {
+            protocol,
+            borrower,
+            debts: BTreeSet::new(),
+            collaterals: BTreeSet::new(),
+            health: None,
+        }
+    }
+}
+
+/// Discovered borrower positions
+#[derive(Debug, Default)]
+pub struct PositionBook {
+    positions: HashMap<(LendingProtocol, Address), Position>,
+    /// The Compound markets, whose `Borrow` events are trusted
+    compound_markets: BTreeSet<Address>,
+}
+
+impl PositionBook {
+    /// Creates an empty book
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the Compound markets whose `Borrow` events are recorded
+    pub fn set_compound_markets(&mut self, markets: impl IntoIterator<Item = Address>) {
+        self.compound_markets = markets.into_iter().collect();
+    }
+
+    /// The Compound markets whose `Borrow` events are recorded
+    pub fn compound_markets(&self) -> Vec<Address> {
+        self.compound_markets.iter().copied().collect()
+    }
+
+    /// Returns the position of a borrower
+    pub fn get(&self, protocol: LendingProtocol, borrower: Address) -> Option<&Position> {
+        self.positions.get(&(protocol, borrower))
+    }
+
+    /// The number of positions
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Whether the book is empty
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The watched positions
+    pub fn positions(&self) -> impl Iterator<Item = &Position> {
+        self.positions.values()
+    }
+
+    fn entry(&mut self, protocol: LendingProtocol, borrower: Address) -> &mut Position {
+        self.positions
+            .entry((protocol, borrower))
+            .or_insert_with(|| Position::new(protocol, borrower))
+    }
+
+    /// Records a borrower event, returning whether the log was one
+    pub fn apply_log(&mut self, log: &Log) -> bool {
+        let signature = match log.topics.first() {
+            Some(s) => *s,
+            None => return false,
+        };
+        let word = |i: usize| {
+            log.data
+                .get(i * 32 + 12..(i + 1) * 32)
+                .map(Address::from_slice)
+        };
+        let topic = |i: usize| log.topics.get(i).map(|t| Address::from(*t));
+
+        if signature == event_topic(AAVE_BORROW_EVENT) {
+            if let (Some(reserve), Some(borrower)) = (topic(1), topic(2)) {
+                self.entry(LendingProtocol::AaveV3, borrower)
+                    .debts
+                    .insert(reserve);
+                return true;
+            }
+        } else if signature == event_topic(AAVE_COLLATERAL_EVENT) {
+            if let (Some(reserve), Some(user)) = (topic(1), topic(2)) {
+                self.entry(LendingProtocol::AaveV3, user)
+                    .collaterals
+                    .insert(reserve);
+                return true;
+            }
+        } else if signature == event_topic(COMPOUND_BORROW_EVENT) {
+            if !self.compound_markets.contains(&log.address) {
+                return false;
+            }
+            if let Some(borrower) = word(0) {
+                self.entry(LendingProtocol::CompoundV2, borrower)
+                    .debts
+                    .insert(log.address);
+                return true;
+            }
+        } else if signature == event_topic(COMPOUND_MARKET_ENTERED_EVENT) {
+            if let (Some(market), Some(account)) = (word(0), word(1)) {
+                self.entry(LendingProtocol::CompoundV2, account)
+                    .collaterals
+                    .insert(market);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Records a position's health
+    pub fn set_health(&mut self, protocol: LendingProtocol, borrower: Address, health: Health) {
+        if let Some(position) = self.positions.get_mut(&(protocol, borrower)) {
+            position.health = Some(health);
+        }
+    }
+
+    /// Positions near liquidation that can be liquidated, the most liquidatable first
+    ///
+    /// A position needs at least one debt and one collateral market to be liquidated.
+    pub fn near_liquidation(&self, config: &LiquidationConfig) -> Vec<&Position> {
+        let mut near = self
+            .positions
+            .values()
+            .filter(|p| !p.debts.is_empty() && !p.collaterals.is_empty())
+            .filter(|p| matches!(p.health, Some(health) if health.is_near(config)))
+            .collect::<Vec<_>>();
+        near.sort_by_key(|p| {
+            let health = p.health.unwrap();
+            (!health.is_liquidatable(), p.protocol, health.margin())
+        });
+        near
+    }
+}
+
+/// Returns the topic of an event signature
+pub fn event_topic(signature: &str) -> H256 {
+    H256::from(ethers::utils::keccak256(signature))
+}
+
+/// The event filters borrowers are discovered from, for the enabled protocols
+pub fn borrower_filters(config: &LiquidationConfig, compound_markets: &[Address]) -> Vec<Filter> {
+    let mut filters = Vec::new();
+    if config.protocols.0.contains(&LendingProtocol::AaveV3) {
+        filters.push(Filter::new().address(config.aave_pool).topic0(vec![
+            event_topic(AAVE_BORROW_EVENT),
+            event_topic(AAVE_COLLATERAL_EVENT),
+        ]));
+    }
+    if config.protocols.0.contains(&LendingProtocol::CompoundV2) {
+        filters.push(
+            Filter::new()
+                .address(config.comptroller)
+                .topic0(event_topic(COMPOUND_MARKET_ENTERED_EVENT)),
+        );
+        if !compound_markets.is_empty() {
+            filters.push(
+                Filter::new()
+                    .address(compound_markets.to_vec())
+                    .topic0(event_topic(COMPOUND_BORROW_EVENT)),
+            );
+        }
+    }
+    filters
+}
+
+/// Reads a borrower's health
+pub async fn read_health<M: Middleware + 'static>(
+    client: Arc<M>,
+    config: &LiquidationConfig,
+    protocol: LendingProtocol,
+    borrower: Address,
+) -> Result<Health> {
+    let map_err = |e| eyre::eyre!("Failed to read the health of {:?}: {:?}", borrower, e);
+    match protocol {
+        LendingProtocol::AaveV3 => {
+            let (.., health_factor) = AavePool::new(config.aave_pool, client)
+                .get_user_account_data(borrower)
+                .call()
+                .await
+                .map_err(map_err)?;
+            Ok(Health::Aave { health_factor })
+        }
+        LendingProtocol::CompoundV2 => {
+            let (error, liquidity, shortfall) = Comptroller::new(config.comptroller, client)
+                .get_account_liquidity(borrower)
+                .call()
+                .await
+                .map_err(map_err)?;
+            if !error.is_zero() {
+                eyre::bail!("Comptroller error {} reading {:?}", error, borrower);
+            }
+            Ok(Health::Compound {
+                liquidity,
+                shortfall,
+            })
+        }
+    }
+}
+
+/// A liquidation of one debt against one collateral market
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Liquidation {
+    /// The protocol
+    pub protocol: LendingProtocol,
+    /// The borrower
+    pub borrower: Address,
+    /// The contract repaid through, the Aave pool or the borrowed cToken
+    pub market: Address,
+    /// The token repaid
+    pub debt_asset: Address,
+    /// The collateral seized, an Aave reserve or a Compound cToken
+    pub collateral: Address,
+    /// The debt repaid
+    pub repay_amount: U256,
+}
+
+impl Liquidation {
+    /// The call liquidating the position, as `(target, calldata)`
+    ///
+    /// The target must be approved to pull `repay_amount` of the debt asset first.
+    pub fn call(&self) -> (Address, Vec<u8>) {
+        let data = match self.protocol {
+            LendingProtocol::AaveV3 => {
+                let mut data = ethers::utils::id(AAVE_LIQUIDATION_SIGNATURE).to_vec();
+                data.extend(abi::encode(&[
+                    Token::Address(self.collateral),
+                    Token::Address(self.debt_asset),
+                    Token::Address(self.borrower),
+                    Token::Uint(self.repay_amount),
+                    Token::Bool(false),
+                ]));
+                data
+            }
+            LendingProtocol::CompoundV2 => {
+                let mut data = ethers::utils::id(COMPOUND_LIQUIDATION_SIGNATURE).to_vec();
+                data.extend(abi::encode(&[
+                    Token::Address(self.borrower),
+                    Token::Uint(self.repay_amount),
+                    Token::Address(self.collateral),
+                ]));
+                data
+            }
+        };
+        (self.market, data)
+    }
+}
+
+/// Plans a liquidation of a position from the executor's inventory
+///
+/// The first debt the executor holds any of is repaid against the first collateral that isn't the
+/// same market. Aave caps the repayment at its close factor itself, while Compound repayments are
+/// capped at `borrowBalanceStored * closeFactor` here.
+pub async fn plan_liquidation<M: Middleware + 'static>(
+    client: Arc<M>,
+    config: &LiquidationConfig,
+    position: &Position,
+    executor: Address,
+) -> Result<Option<Liquidation>> {
+    for debt in &position.debts {
+        let collateral = match position.collaterals.iter().find(|c| *c != debt) {
+            Some(c) => *c,
+            None => continue,
+        };
+        let debt_asset = match position.protocol {
+            LendingProtocol::AaveV3 => *debt,
+            // cETH has no underlying and is repaid in ETH, which the executor doesn't do
+            LendingProtocol::CompoundV2 => {
+                match CToken::new(*debt, client.clone()).underlying().call().await {
+                    Ok(underlying) => underlying,
+                    Err(_) => continue,
+                }
+            }
+        };
+        let balance = ERC20::new(debt_asset, client.clone())
+            .balance_of(executor)
+            .call()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch executor balance: {:?}", e))?;
+        if balance.is_zero() {
+            continue;
+        }
+        let (market, repay_amount) = match position.protocol {
+            LendingProtocol::AaveV3 => (config.aave_pool, balance),
+            LendingProtocol::CompoundV2 => {
+                let borrowed = CToken::new(*debt, client.clone())
+                    .borrow_balance_stored(position.borrower)
+                    .call()
+                    .await
+                    .map_err(|e| eyre::eyre!("Failed to fetch borrow balance: {:?}", e))?;
+                let close_factor = Comptroller::new(config.comptroller, client.clone())
+                    .close_factor_mantissa()
+                    .call()
+                    .await
+                    .map_err(|e| eyre::eyre!("Failed to fetch close factor: {:?}", e))?;
+                let max_repay = borrowed * close_factor / U256::exp10(18);
+                (*debt, balance.min(max_repay))
+            }
+        };
+        if repay_amount.is_zero() {
+            continue;
+        }
+        return Ok(Some(Liquidation {
+            protocol: position.protocol,
+            borrower: position.borrower,
+            market,
+            debt_asset,
+            collateral,
+            repay_amount,
+        }));
+    }
+    Ok(None)
+}
+
+/// Loads the Compound markets, so only their `Borrow` events are recorded
+pub async fn load_compound_markets<M: Middleware + 'static>(
+    client: Arc<M>,
+    config: &LiquidationConfig,
+    book: &mut PositionBook,
+) -> Result<()> {
+    let markets = Comptroller::new(config.comptroller, client)
+        .get_all_markets()
+        .call()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch Compound markets: {:?}", e))?;
+    book.set_compound_markets(markets);
+    Ok(())
+}
+
+/// Records the borrowers from `lookback_blocks` of events up to `head`, returning the events read
+pub async fn discover_borrowers<M: Middleware + 'static>(
+    client: Arc<M>,
+    book: &mut PositionBook,
+    config: &LiquidationConfig,
+    head: U64,
+) -> Result<usize> {
+    let from = head.saturating_sub(U64::from(config.lookback_blocks));
+    let mut applied = 0;
+    for filter in borrower_filters(config, &book.compound_markets()) {
+        let logs = client
+            .get_logs(&filter.from_block(from).to_block(head))
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch borrower events: {:?}", e))?;
+        applied += logs.iter().filter(|log| book.apply_log(log)).count();
+    }
+    Ok(applied)
+}
+
+/// Refreshes the health of every position, returning how many were read
+///
+/// Positions are read a batch at a time, and a failed read keeps the last health.
+pub async fn refresh_health<M: Middleware + 'static>(
+    client: Arc<M>,
+    config: &LiquidationConfig,
+    book: &mut PositionBook,
+) -> usize {
+    const BATCH: usize = 32;
+    let keys = book
+        .positions()
+        .map(|p| (p.protocol, p.borrower))
+        .collect::<Vec<_>>();
+    let mut refreshed = 0;
+    for batch in keys.chunks(BATCH) {
+        let reads =
+            futures::future::join_all(batch.iter().map(|(protocol, borrower)| {
+                read_health(client.clone(), config, *protocol, *borrower)
+            }))
+            .await;
+        for ((protocol, borrower), health) in batch.iter().zip(reads) {
+            if let Ok(health) = health {
+                book.set_health(*protocol, *borrower, health);
+                refreshed += 1;
+            }
+        }
+    }
+    refreshed
+}
@@ -0,0 +1,375 @@
+//! Manual Approval
+//!
+//! While building trust in a new deployment, operators may want to see large sandwiches before
+//! they're sent. In manual approval mode, every sandwich frontrunning with at least a set amount of
+//! WETH is held as an [ApprovalRequest] and announced. It's only submitted if an operator approves
+//! it within the approval window, through the admin API (`POST /approve?id=N`) or the inline
+//! buttons of a Telegram message, and skipped otherwise.
+//!
+//! The request waits in the bundle's background task, so evaluation keeps running meanwhile.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::{instances, notify::Notifier, opportunity::unix_millis};
+
+/// The default time an operator has to approve a sandwich, in seconds
+pub const DEFAULT_APPROVAL_SECS: u64 = 5;
+
+/// The Telegram bot API
+pub const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+/// How long a Telegram update poll waits for updates, in seconds
+pub const TELEGRAM_POLL_SECS: u64 = 25;
+
+/// A sandwich waiting for approval
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ApprovalRequest {
+    /// The request id operators approve by
+    pub id: u64,
+    /// The victim transaction
+    pub victim: TxHash,
+    /// The token sandwiched
+    pub token: Address,
+    /// The frontrun's WETH input
+    pub weth_in: U256,
+    /// The expected revenue
+    pub revenue: U256,
+    /// The block the bundle targets
+    pub target_block: U64,
+    /// When the request expires, in milliseconds since the unix epoch
+    pub expires_at: u64,
+}
+
+impl ApprovalRequest {
+    /// A one-line summary for alerts
+    pub fn summary(&self) -> String {
+        format!(
+            "Sandwich #{} of {:?} on {:?}: {} WETH in for {} revenue, targeting block {}",
+            self.id, self.victim, self.token, self.weth_in, self.revenue, self.target_block
+        )
+    }
+}
+
+/// How an approval request was resolved
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ApprovalOutcome {
+    /// An operator approved it
+    Approved,
+    /// An operator rejected it
+    Rejected,
+    /// Nobody answered within the approval window
+    Expired,
+}
+
+impl ApprovalOutcome {
+    /// The metric counting this outcome
+    pub fn metric(&self) -> &'static str {
+        match self {
+            ApprovalOutcome::Approved => "manual_approved",
+            ApprovalOutcome::Rejected => "manual_rejected",
+            ApprovalOutcome::Expired => "manual_expired",
+        }
+    }
+}
+
+struct Pending {
+    request: ApprovalRequest,
+    responder: oneshot::Sender<bool>,
+}
+
+/// Holds sandwiches until an operator decides on them
+#[derive(Default)]
+pub struct ApprovalGate {
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, Pending>>,
+}
+
+impl std::fmt::Debug for ApprovalGate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApprovalGate")
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+impl ApprovalGate {
+    /// Creates a gate without pending requests
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a request for a sandwich, returning it with the receiver of its decision
+    pub fn open(
+        &self,
+        victim: TxHash,
+        token: Address,
+        weth_in: U256,
+        revenue: U256,
+        target_block: U64,
+        window: Duration,
+    ) -> (ApprovalRequest, oneshot::Receiver<bool>) {
+        let request = ApprovalRequest {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst) + 1,
+            victim,
+            token,
+            weth_in,
+            revenue,
+            target_block,
+            expires_at: unix_millis().saturating_add(window.as_millis() as u64),
+        };
+        let (responder, decision) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                request.id,
+                Pending {
+                    request: request.clone(),
+                    responder,
+                },
+            );
+        (request, decision)
+    }
+
+    /// Waits for the decision on a request until `window` passes
+    ///
+    /// The request is withdrawn once it expires.
+    pub async fn wait(
+        &self,
+        id: u64,
+        decision: oneshot::Receiver<bool>,
+        window: Duration,
+    ) -> ApprovalOutcome {
+        let outcome = match tokio::time::timeout(window, decision).await {
+            Ok(Ok(true)) => ApprovalOutcome::Approved,
+            Ok(Ok(false)) => ApprovalOutcome::Rejected,
+            Ok(Err(_)) | Err(_) => ApprovalOutcome::Expired,
+        };
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+        outcome
+    }
+
+    /// Approves or rejects a pending request, returning it if it was still pending
+    pub fn decide(&self, id: u64, approve: bool) -> Option<ApprovalRequest> {
+        let pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id)?;
+        // The waiter may have just expired, the decision is moot then
+        let _ = pending.responder.send(approve);
+        Some(pending.request)
+    }
+
+    /// The requests waiting for a decision, oldest first
+    pub fn pending(&self) -> Vec<ApprovalRequest> {
+        let mut pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|p| p.request.clone())
+            .collect::<Vec<_>>();
+        pending.sort_by_key(|r| r.id);
+        pending
+    }
+}
+
+/// Returns the global approval gate
+pub fn approval_gate() -> &'static ApprovalGate {
+    static GATE: OnceLock<ApprovalGate> = OnceLock::new();
+    GATE.get_or_init(ApprovalGate::new)
+}
+
+/// The inline keyboard approving or rejecting a request
+pub fn approval_keyboard(id: u64) -> serde_json::Value {
+    serde_json::json!({
+        "inline_keyboard": [[
+            { "text": "Approve", "callback_data": format!("approve:{id}") },
+            { "text": "Reject", "callback_data": format!("reject:{id}") },
+        ]]
+    })
+}
+
+/// Parses the callback data of an approval button into the request id and whether it approves
+pub fn parse_callback(data: &str) -> Option<(u64, bool)> {
+    let (action, id) = data.split_once(':')?;
+    let approve = match action {
+        "approve" => true,
+        "reject" => false,
+        _ => return None,
+    };
+    Some((id.parse().ok()?, approve))
+}
+
+/// An approval button pressed in Telegram
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelegramDecision {
+    /// The callback query to answer
+    pub callback_id: String,
+    /// The request id
+    pub id: u64,
+    /// Whether it approves the request
+    pub approve: bool,
+}
+
+/// Reads the approval buttons pressed in `chat_id` from a `getUpdates` response
+///
+/// Returns the decisions and the offset of the next poll. Presses from other chats are ignored.
+pub fn parse_updates(
+    updates: &serde_json::Value,
+    chat_id: i64,
+    offset: i64,
+) -> (Vec<TelegramDecision>, i64) {
+    let mut next_offset = offset;
+    let mut decisions = Vec::new();
+    for update in updates["result"].as_array().into_iter().flatten() {
+        if let Some(update_id) = update["update_id"].as_i64() {
+            next_offset = next_offset.max(update_id + 1);
+        }
+        let query = &update["callback_query"];
+        if query["message"]["chat"]["id"].as_i64() != Some(chat_id) {
+            continue;
+        }
+        let (callback_id, data) = match (query["id"].as_str(), query["data"].as_str()) {
+            (Some(callback_id), Some(data)) => (callback_id, data),
+            _ => continue,
+        };
+        if let Some((id, approve)) = parse_callback(data) {
+            decisions.push(TelegramDecision {
+                callback_id: callback_id.to_string(),
+                id,
+                approve,
+            });
+        }
+    }
+    (decisions, next_offset)
+}
+
+/// Sends approval requests to a Telegram chat and reads back the buttons pressed
+#[derive(Debug, Clone)]
+pub struct TelegramApprover {
+    /// The http client
+    pub http: reqwest::Client,
+    /// The bot token
+    pub token: String,
+    /// The chat requests are sent to
+    pub chat_id: i64,
+}
+
+impl TelegramApprover {
+    /// Reads the bot token and chat from `TELEGRAM_BOT_TOKEN` and `TELEGRAM_CHAT_ID`
+    ///
+    /// Returns `None` unless both are set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let token = match instances::var("TELEGRAM_BOT_TOKEN") {
+            Ok(token) if !token.is_empty() => token,
+            _ => return Ok(None),
+        };
+        let chat_id = match instances::var("TELEGRAM_CHAT_ID") {
+            Ok(chat_id) => chat_id
+                .parse()
+                .map_err(|_| eyre::eyre!("Invalid \"TELEGRAM_CHAT_ID\" \"{}\"", chat_id))?,
+            Err(_) => return Ok(None),
+        };
+        Ok(Some(Self {
+            http: reqwest::Client::new(),
+            token,
+            chat_id,
+        }))
+    }
+
+    fn method_url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", TELEGRAM_API_URL, self.token, method)
+    }
+
+    /// Sends a request with approve and reject buttons
+    pub async fn send(&self, request: &ApprovalRequest) -> Result<()> {
+        self.http
+            .post(self.method_url("sendMessage"))
+            .json(&serde_json::json!({
+                "chat_id": self.chat_id,
+                "text": request.summary(),
+                "reply_markup": approval_keyboard(request.id),
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Polls for pressed buttons and applies them to the global [approval_gate] until polling fails
+    pub async fn run(&self) -> Result<()> {
+        let mut offset = 0;
+        loop {
+            let updates: serde_json::Value = self
+                .http
+                .get(self.method_url("getUpdates"))
+                .query(&[
+                    ("offset", offset.to_string()),
+                    ("timeout", TELEGRAM_POLL_SECS.to_string()),
+                    ("allowed_updates", "[\"callback_query\"]".to_string()),
+                ])
+                .timeout(Duration::from_secs(TELEGRAM_POLL_SECS + 5))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let (decisions, next_offset) = parse_updates(&updates, self.chat_id, offset);
+            offset = next_offset;
+            for decision in decisions {
+                let answer = match approval_gate().decide(decision.id, decision.approve) {
+                    Some(_) if decision.approve => "Approved",
+                    Some(_) => "Rejected",
+                    None => "Already expired",
+                };
+                tracing::info!(
+                    "[APPROVAL] Sandwich #{} {} over Telegram",
+                    decision.id,
+                    answer
+                );
+                let _ = self
+                    .http
+                    .post(self.method_url("answerCallbackQuery"))
+                    .json(&serde_json::json!({
+                        "callback_query_id": decision.callback_id,
+                        "text": answer,
+                    }))
+                    .send()
+                    .await;
+            }
+        }
+    }
+}
+
+/// Announces a request through the notifier, and Telegram if configured
+pub async fn announce_approval(
+    notifier: &Notifier,
+    telegram: Option<&TelegramApprover>,
+    request: &ApprovalRequest,
+) {
+    notifier
+        .notify(&format!("Approval needed: {}", request.summary()))
+        .await;
+    if let Some(telegram) = telegram {
+        if let Err(e) = telegram.send(request).await {
+            tracing::error!("[APPROVAL] Failed to send Telegram request: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,807 @@
+//! Mempool Sources
+//!
+//! Pending transactions can come from more than one place. Each [TxSource] streams into a shared
+//! channel, and [merge_sources] drops the duplicates so every transaction is evaluated once.
+//!
+//! Which source delivers first only says how the sources compare to each other. The
+//! [InclusionTracker] measures the edge that matters, against the chain: for every router swap that
+//! gets mined, how long before its block's timestamp each source delivered it, and how many were
+//! never seen at all.
+//!
+//! The hashes of the last [MINED_BLOCKS_WINDOW] blocks are kept in [MinedTxs], so a transaction
+//! delivered or evaluated after it was mined is recognized without asking the node for a receipt.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::{
+    bloxroute::BloxrouteSource,
+    chainbound::FiberSource,
+    envelope, instances, metrics,
+    opportunity::unix_millis,
+    pipeline::{self, Candidate},
+    supervisor, utils,
+};
+
+/// How many transaction hashes are remembered for deduplication
+pub const DEDUP_CAPACITY: usize = 65_536;
+
+/// The default time a source may deliver nothing before it's reconnected, in seconds
+pub const DEFAULT_SOURCE_STALL_SECS: u64 = 30;
+
+/// How often `txpool_content` is polled, by the `txpool` source or when the node can't stream
+/// pending transactions, in milliseconds
+///
+/// Each poll returns the whole pool, often several megabytes, so it's polled sparingly.
+pub const TXPOOL_POLL_MS: u64 = 1_000;
+
+/// How many recent blocks' transaction hashes are kept to recognize mined transactions
+pub const MINED_BLOCKS_WINDOW: usize = 32;
+
+/// A stream of pending transactions
+pub trait TxSource: Debug + Send + Sync {
+    /// A short name for logs and metrics
+    fn name(&self) -> &'static str;
+
+    /// Streams pending transactions into `sender` until the stream ends or the receiver is dropped
+    ///
+    /// Called again to reconnect after the stream ends.
+    fn run(&self, sender: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>>;
+}
+
+/// Pending transactions seen by the bot's own node
+///
+/// The node only announces hashes, so each transaction is fetched before it's forwarded. Nodes and
+/// hosted providers that don't support pending transaction filters are polled with
+/// `txpool_content` instead, see [poll_txpool].
+#[derive(Debug, Clone)]
+pub struct NodeSource {
+    /// The websocket client
+    pub client: Arc<Provider<Ws>>,
+}
+
+impl TxSource for NodeSource {
+    fn name(&self) -> &'static str {
+        "node"
+    }
+
+    fn run(&self, sender: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>> {
+        let client = self.client.clone();
+        Box::pin(async move {
+            let stream = match client.watch_pending_transactions().await {
+                Ok(stream) => stream,
+                Err(e) if is_unsupported(&e.to_string()) => {
+                    tracing::warn!(
+                        "[MEMPOOL] Node has no pending transaction filters, polling txpool_content"
+                    );
+                    metrics::increment("mempool_txpool_fallbacks");
+                    return poll_txpool(
+                        client.clone(),
+                        sender,
+                        Duration::from_millis(TXPOOL_POLL_MS),
+                        false,
+                    )
+                    .await;
+                }
+                Err(e) => eyre::bail!("Failed to watch pending transactions: {:?}", e),
+            };
+            let mut txs = stream.transactions_unordered(usize::MAX);
+            while let Some(tx) = txs.next().await {
+                match tx {
+                    Ok(tx) => {
+                        if sender.send(tx).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => tracing::debug!("Transaction error: {:?}", e),
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Whether an RPC error says the method or subscription isn't supported by the node
+pub fn is_unsupported(error: &str) -> bool {
+    let error = error.to_lowercase();
+    [
+        "-32601",
+        "method not found",
+        "does not exist",
+        "not supported",
+        "unsupported",
+        "not available",
+    ]
+    .iter()
+    .any(|needle| error.contains(needle))
+}
+
+/// The transactions new to the pool since the previous poll
+///
+/// The first poll only seeds the seen hashes, since the pool a poller starts on is mostly stale
+/// and would flood evaluation. Only the hashes in the latest poll are remembered, so a transaction
+/// that leaves the pool and comes back is delivered again, and [merge_sources] drops it as a
+/// duplicate.
+#[derive(Debug, Clone, Default)]
+pub struct TxpoolDiff {
+    seen: HashSet<TxHash>,
+    seeded: bool,
+}
+
+impl TxpoolDiff {
+    /// Returns the transactions in `pool` that weren't in the previous one, or none on the first
+    pub fn diff(&mut self, pool: impl IntoIterator<Item = Transaction>) -> Vec<Transaction> {
+        let mut current = HashSet::with_capacity(self.seen.len());
+        let mut new = Vec::new();
+        for tx in pool {
+            if current.insert(tx.hash) && self.seeded && !self.seen.contains(&tx.hash) {
+                new.push(tx);
+            }
+        }
+        self.seen = current;
+        self.seeded = true;
+        new
+    }
+}
+
+/// Streams the node's pending transactions into `sender` by polling `txpool_content` every
+/// `interval` and forwarding the new ones
+///
+/// With `queued`, transactions the node holds back behind a nonce gap are forwarded too, counted in
+/// the `mempool_queued` metric. Runs until the receiver is dropped or a poll fails.
+pub async fn poll_txpool(
+    client: Arc<Provider<Ws>>,
+    sender: mpsc::Sender<Transaction>,
+    interval: Duration,
+    queued: bool,
+) -> Result<()> {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut pool = TxpoolDiff::default();
+    loop {
+        ticker.tick().await;
+        let content = client
+            .txpool_content()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to poll txpool_content: {:?}", e))?;
+        let pending = content
+            .pending
+            .into_values()
+            .flat_map(|txs| txs.into_values());
+        let queued: Vec<Transaction> = if queued {
+            content
+                .queued
+                .into_values()
+                .flat_map(|txs| txs.into_values())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let queued_hashes = queued.iter().map(|tx| tx.hash).collect::<HashSet<_>>();
+        for tx in pool.diff(pending.chain(queued)) {
+            if queued_hashes.contains(&tx.hash) {
+                metrics::increment("mempool_queued");
+            }
+            if sender.send(tx).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Pending and queued transactions polled from the bot's own node with `txpool_content`
+///
+/// Subscriptions only announce transactions the node considers executable, so a victim signed
+/// ahead of a nonce gap is never emitted until the gap fills, often in the same block. Polling the
+/// pool also finds these queued transactions. `txpool_inspect` only summarizes transactions,
+/// without hashes or calldata, so the full content is polled. Needs the `txpool` namespace, served
+/// by geth and erigon.
+#[derive(Debug, Clone)]
+pub struct TxpoolSource {
+    /// The websocket client
+    pub client: Arc<Provider<Ws>>,
+    /// How often the pool is polled
+    pub interval: Duration,
+}
+
+impl TxSource for TxpoolSource {
+    fn name(&self) -> &'static str {
+        "txpool"
+    }
+
+    fn run(&self, sender: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>> {
+        Box::pin(poll_txpool(
+            self.client.clone(),
+            sender,
+            self.interval,
+            true,
+        ))
+    }
+}
+
+/// Full pending transactions pushed by a remote websocket stream
+///
+/// Works with any endpoint supporting `eth_subscribe` to `newPendingTransactions` with full
+/// transaction bodies, such as the Eden mempool stream.
+#[derive(Debug, Clone)]
+pub struct StreamSource {
+    /// The source name
+    pub name: &'static str,
+    /// The websocket url
+    pub url: String,
+}
+
+impl TxSource for StreamSource {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn run(&self, sender: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>> {
+        let source = self.clone();
+        Box::pin(async move {
+            let provider = Provider::<Ws>::connect(&source.url)
+                .await
+                .map_err(|e| eyre::eyre!("{} connection error: {:?}", source.name, e))?;
+            let mut txs = provider
+                .subscribe::<_, serde_json::Value>(("newPendingTransactions", true))
+                .await
+                .map_err(|e| eyre::eyre!("{} subscription error: {:?}", source.name, e))?;
+            while let Some(tx) = txs.next().await {
+                let tx = match envelope::from_json(tx) {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        tracing::debug!("Failed to decode {} transaction: {:?}", source.name, e);
+                        continue;
+                    }
+                };
+                if sender.send(tx).await.is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Full pending transactions pushed by a co-located node over its IPC socket
+///
+/// The node must support `eth_subscribe` to `newPendingTransactions` with full transaction bodies.
+#[derive(Debug, Clone)]
+pub struct IpcSource {
+    /// The socket path
+    pub path: String,
+}
+
+impl TxSource for IpcSource {
+    fn name(&self) -> &'static str {
+        "ipc"
+    }
+
+    fn run(&self, sender: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>> {
+        let path = self.path.clone();
+        Box::pin(async move {
+            let provider = utils::get_ipc_provider(&path).await?;
+            let mut txs = provider
+                .subscribe::<_, serde_json::Value>(("newPendingTransactions", true))
+                .await
+                .map_err(|e| eyre::eyre!("IPC subscription error: {:?}", e))?;
+            while let Some(tx) = txs.next().await {
+                let tx = match envelope::from_json(tx) {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        tracing::debug!("Failed to decode IPC transaction: {:?}", e);
+                        continue;
+                    }
+                };
+                if sender.send(tx).await.is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A configurable mempool source
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum TxSourceKind {
+    /// The bot's own node, over `RPC_URL_WSS`
+    Node,
+    /// The bot's own node, over its IPC socket at `RPC_IPC_PATH`
+    Ipc,
+    /// The bot's own node's pending and queued transactions, polled with `txpool_content`
+    Txpool,
+    /// The Eden mempool stream, over `EDEN_WSS_URL`
+    Eden,
+    /// The bloXroute BDN gateway, authorized with `BLOXROUTE_AUTH_HEADER`
+    Bloxroute,
+    /// Chainbound Fiber, with `FIBER_API_KEY`, when built with the `fiber` feature
+    Fiber,
+}
+
+impl FromStr for TxSourceKind {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "node" | "local" => Ok(TxSourceKind::Node),
+            "ipc" => Ok(TxSourceKind::Ipc),
+            "txpool" => Ok(TxSourceKind::Txpool),
+            "eden" => Ok(TxSourceKind::Eden),
+            "bloxroute" | "bdn" => Ok(TxSourceKind::Bloxroute),
+            "fiber" => Ok(TxSourceKind::Fiber),
+            _ => Err(eyre::eyre!("Unknown transaction source \"{}\"", s)),
+        }
+    }
+}
+
+impl TxSourceKind {
+    /// Builds the source, reading any endpoint it needs from the environment
+    pub fn build(&self, client: Arc<Provider<Ws>>) -> Result<Box<dyn TxSource>> {
+        match self {
+            TxSourceKind::Node => Ok(Box::new(NodeSource { client })),
+            TxSourceKind::Ipc => Ok(Box::new(IpcSource {
+                path: utils::get_ipc_path().ok_or_else(|| {
+                    eyre::eyre!("Required environment variable \"RPC_IPC_PATH\" not set")
+                })?,
+            })),
+            TxSourceKind::Txpool => Ok(Box::new(TxpoolSource {
+                client,
+                interval: Duration::from_millis(TXPOOL_POLL_MS),
+            })),
+            TxSourceKind::Eden => Ok(Box::new(StreamSource {
+                name: "eden",
+                url: get_eden_wss_url()?,
+            })),
+            TxSourceKind::Bloxroute => Ok(Box::new(BloxrouteSource::from_env()?)),
+            TxSourceKind::Fiber => Ok(Box::new(FiberSource::from_env()?)),
+        }
+    }
+}
+
+/// A comma-separated list of mempool sources
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxSources(pub Vec<TxSourceKind>);
+
+impl Default for TxSources {
+    fn default() -> Self {
+        Self(vec![TxSourceKind::Node])
+    }
+}
+
+impl FromStr for TxSources {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let sources = s
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(TxSourceKind::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        if sources.is_empty() {
+            eyre::bail!("At least one transaction source is required");
+        }
+        Ok(TxSources(sources))
+    }
+}
+
+/// Returns the Eden mempool stream url
+pub fn get_eden_wss_url() -> Result<String> {
+    instances::var("EDEN_WSS_URL")
+        .map_err(|_| eyre::eyre!("Required environment variable \"EDEN_WSS_URL\" not set"))
+}
+
+/// Remembers when the most recent transaction hashes were first seen
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    seen: HashMap<TxHash, Instant>,
+    order: VecDeque<TxHash>,
+    capacity: usize,
+}
+
+impl Deduplicator {
+    /// Creates a deduplicator remembering up to `capacity` hashes
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            seen: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records a hash, returning whether it's the first time it's been seen
+    ///
+    /// The oldest hash is forgotten once the capacity is reached.
+    pub fn insert(&mut self, hash: TxHash) -> bool {
+        if self.seen.contains_key(&hash) {
+            return false;
+        }
+        self.seen.insert(hash, Instant::now());
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Returns when a remembered hash was first seen
+    pub fn first_seen(&self, hash: &TxHash) -> Option<Instant> {
+        self.seen.get(hash).copied()
+    }
+}
+
+/// Runs every source concurrently, returning a channel of deduplicated candidates
+///
+/// Each source is supervised as `mempool_<source>`: one that fails, or delivers nothing for
+/// `stall_after`, is reconnected while the others keep running. For every source, the
+/// `mempool_first_<source>` counter tracks how often it delivered a transaction first, and
+/// `mempool_lag_ms_<source>` over `mempool_late_<source>` gives its average lag behind the winner.
+/// Transactions a source delivers after they were mined are dropped, counted in
+/// `mempool_stale_<source>`, and `mempool_delivered_<source>` counts every delivery.
+pub fn merge_sources(
+    sources: Vec<Box<dyn TxSource>>,
+    buffer: usize,
+    stall_after: Duration,
+) -> mpsc::Receiver<Candidate> {
+    let (raw_sender, mut raw_receiver) = mpsc::channel::<(&'static str, Transaction)>(buffer);
+    let (sender, receiver) = mpsc::channel(buffer);
+
+    for source in sources {
+        let source: Arc<dyn TxSource> = Arc::from(source);
+        let name = source.name();
+        let raw_sender = raw_sender.clone();
+        supervisor::supervise_task(
+            &format!("mempool_{name}"),
+            Some(stall_after),
+            move |heartbeat| {
+                let (source_sender, mut source_receiver) = mpsc::channel(buffer);
+                let run = source.run(source_sender);
+
+                // Tag each transaction with its source
+                let raw_sender = raw_sender.clone();
+                let forward = async move {
+                    while let Some(tx) = source_receiver.recv().await {
+                        heartbeat.beat();
+                        if raw_sender.send((name, tx)).await.is_err() {
+                            return true;
+                        }
+                    }
+                    false
+                };
+                async move {
+                    let (result, closed) = futures::join!(run, forward);
+                    result?;
+                    if closed {
+                        return Ok(());
+                    }
+                    eyre::bail!("{} stream ended", name)
+                }
+            },
+        );
+    }
+    drop(raw_sender);
+
+    instances::spawn(async move {
+        let mut dedup = Deduplicator::new(DEDUP_CAPACITY);
+        while let Some((name, tx)) = raw_receiver.recv().await {
+            inclusion()
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .record_seen(name, tx.hash, unix_millis());
+            metrics::increment(&format!("mempool_delivered_{name}"));
+            if mined()
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .contains(&tx.hash)
+                .is_some()
+            {
+                metrics::increment(&format!("mempool_stale_{name}"));
+                continue;
+            }
+            if let Some(first_seen) = dedup.first_seen(&tx.hash) {
+                let lag = first_seen.elapsed().as_millis() as u64;
+                metrics::global().add(&format!("mempool_lag_ms_{name}"), lag);
+                metrics::increment(&format!("mempool_late_{name}"));
+                continue;
+            }
+            dedup.insert(tx.hash);
+            metrics::increment(&format!("mempool_first_{name}"));
+            if sender.send(Candidate::new(tx)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+/// How one source fared on the swaps that were mined
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SourceEdge {
+    /// Mined swaps the source delivered before their block's timestamp
+    pub seen: u64,
+    /// Mined swaps the source only delivered after their block's timestamp
+    pub late: u64,
+    /// Mined swaps the source delivered before any other source
+    pub first: u64,
+    /// The summed lead of the swaps seen, from delivery to the block's timestamp
+    pub lead_ms: u64,
+}
+
+impl SourceEdge {
+    /// The average lead of the swaps seen, `None` if none were
+    pub fn average_lead_ms(&self) -> Option<u64> {
+        self.lead_ms.checked_div(self.seen)
+    }
+}
+
+/// The mempool edge over every mined swap observed
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EdgeReport {
+    /// Mined swaps observed
+    pub mined: u64,
+    /// Mined swaps seen by any source before their block's timestamp
+    pub seen: u64,
+    /// The edge of each source, by name
+    pub sources: BTreeMap<String, SourceEdge>,
+}
+
+impl EdgeReport {
+    /// The fraction of mined swaps seen by any source
+    pub fn fraction_seen(&self) -> f64 {
+        if self.mined == 0 {
+            return 0.0;
+        }
+        self.seen as f64 / self.mined as f64
+    }
+
+    /// The fraction of mined swaps seen by `source`
+    pub fn source_fraction_seen(&self, source: &str) -> f64 {
+        match self.sources.get(source) {
+            Some(edge) if self.mined > 0 => edge.seen as f64 / self.mined as f64,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Remembers when each source first delivered the most recent transactions, and scores them
+/// against the blocks they're mined in
+#[derive(Debug, Clone)]
+pub struct InclusionTracker {
+    sightings: HashMap<TxHash, Vec<(&'static str, u64)>>,
+    order: VecDeque<TxHash>,
+    capacity: usize,
+    report: EdgeReport,
+}
+
+impl InclusionTracker {
+    /// Creates a tracker remembering the sightings of up to `capacity` transactions
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sightings: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+            report: EdgeReport::default(),
+        }
+    }
+
+    /// Records `source` delivering `hash` at `at_ms` unix millis, keeping only its first delivery
+    ///
+    /// The oldest transaction is forgotten once the capacity is reached.
+    pub fn record_seen(&mut self, source: &'static str, hash: TxHash, at_ms: u64) {
+        let sightings = match self.sightings.get_mut(&hash) {
+            Some(sightings) => sightings,
+            None => {
+                self.order.push_back(hash);
+                if self.order.len() > self.capacity {
+                    if let Some(oldest) = self.order.pop_front() {
+                        self.sightings.remove(&oldest);
+                    }
+                }
+                self.sightings.entry(hash).or_default()
+            }
+        };
+        if sightings.iter().all(|(s, _)| *s != source) {
+            sightings.push((source, at_ms));
+        }
+    }
+
+    /// Scores a swap mined in a block with `timestamp_ms`, returning the earliest lead if any
+    /// source saw it in time
+    ///
+    /// Block timestamps only have second precision, so leads are accurate to a second.
+    pub fn record_mined(&mut self, hash: TxHash, timestamp_ms: u64) -> Option<u64> {
+        self.report.mined += 1;
+        metrics::increment("edge_mined");
+        let sightings = self.sightings.remove(&hash).unwrap_or_default();
+
+        let mut earliest: Option<(&'static str, u64)> = None;
+        for (source, at_ms) in sightings {
+            let edge = self.report.sources.entry(source.to_string()).or_default();
+            if at_ms > timestamp_ms {
+                edge.late += 1;
+                metrics::increment(&format!("edge_late_{source}"));
+                continue;
+            }
+            let lead = timestamp_ms - at_ms;
+            edge.seen += 1;
+            edge.lead_ms = edge.lead_ms.saturating_add(lead);
+            metrics::increment(&format!("edge_seen_{source}"));
+            metrics::global().add(&format!("edge_lead_ms_{source}"), lead);
+            if earliest.map_or(true, |(_, e)| lead > e) {
+                earliest = Some((source, lead));
+            }
+        }
+
+        let (source, lead) = earliest?;
+        self.report.seen += 1;
+        metrics::increment("edge_seen");
+        if let Some(edge) = self.report.sources.get_mut(source) {
+            edge.first += 1;
+        }
+        metrics::increment(&format!("edge_first_{source}"));
+        Some(lead)
+    }
+
+    /// The edge measured so far
+    pub fn report(&self) -> EdgeReport {
+        self.report.clone()
+    }
+}
+
+/// Returns the global inclusion tracker, fed by [merge_sources]
+pub fn inclusion() -> &'static Mutex<InclusionTracker> {
+    static INCLUSION: OnceLock<Mutex<InclusionTracker>> = OnceLock::new();
+    INCLUSION.get_or_init(|| Mutex::new(InclusionTracker::new(DEDUP_CAPACITY)))
+}
+
+/// Scores every swap through `router` mined in new blocks against the global inclusion tracker
+///
+/// Runs until the block subscription ends.
+pub async fn watch_inclusion(client: Arc<Provider<Ws>>, router: Address) -> Result<()> {
+    let mut blocks = client
+        .subscribe_blocks()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to subscribe to blocks: {:?}", e))?;
+    while let Some(header) = blocks.next().await {
+        let hash = match header.hash {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let block = match client.get_block_with_txs(hash).await {
+            Ok(Some(block)) => block,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::debug!("[EDGE] Failed to fetch block {:?}: {:?}", hash, e);
+                continue;
+            }
+        };
+        let timestamp_ms = block.timestamp.as_u64().saturating_mul(1000);
+        let mut tracker = inclusion().lock().unwrap_or_else(|e| e.into_inner());
+        let (mut mined, mut seen) = (0, 0);
+        for tx in &block.transactions {
+            if pipeline::prefilter_score(tx, router).is_none() {
+                continue;
+            }
+            mined += 1;
+            if tracker.record_mined(tx.hash, timestamp_ms).is_some() {
+                seen += 1;
+            }
+        }
+        if mined > 0 {
+            tracing::debug!(
+                "[EDGE] Block {:?}: saw {}/{} mined swaps",
+                block.number,
+                seen,
+                mined
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The transaction hashes of the most recent blocks
+#[derive(Debug, Clone)]
+pub struct MinedTxs {
+    blocks: BTreeMap<U64, Vec<TxHash>>,
+    index: HashMap<TxHash, U64>,
+    capacity: usize,
+}
+
+impl MinedTxs {
+    /// Creates a set remembering the last `capacity` blocks
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            blocks: BTreeMap::new(),
+            index: HashMap::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Records the transactions mined in block `number`
+    ///
+    /// A block reorged out at the same height is replaced, and the oldest block is forgotten once
+    /// the capacity is reached.
+    pub fn record(&mut self, number: U64, hashes: Vec<TxHash>) {
+        if let Some(replaced) = self.blocks.remove(&number) {
+            self.forget(number, replaced);
+        }
+        for hash in &hashes {
+            self.index.insert(*hash, number);
+        }
+        self.blocks.insert(number, hashes);
+        while self.blocks.len() > self.capacity {
+            if let Some((oldest, hashes)) = self.blocks.pop_first() {
+                self.forget(oldest, hashes);
+            }
+        }
+    }
+
+    fn forget(&mut self, number: U64, hashes: Vec<TxHash>) {
+        for hash in hashes {
+            if self.index.get(&hash) == Some(&number) {
+                self.index.remove(&hash);
+            }
+        }
+    }
+
+    /// The block a remembered transaction was mined in
+    pub fn contains(&self, hash: &TxHash) -> Option<U64> {
+        self.index.get(hash).copied()
+    }
+
+    /// The number of blocks remembered
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Whether no blocks are remembered
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+/// Returns the global set of recently mined transactions, fed by [watch_mined]
+pub fn mined() -> &'static Mutex<MinedTxs> {
+    static MINED: OnceLock<Mutex<MinedTxs>> = OnceLock::new();
+    MINED.get_or_init(|| Mutex::new(MinedTxs::new(MINED_BLOCKS_WINDOW)))
+}
+
+/// Records the transaction hashes of every new block in the global [mined] set
+///
+/// Runs until the block subscription ends.
+pub async fn watch_mined(client: Arc<Provider<Ws>>) -> Result<()> {
+    let mut blocks = client
+        .subscribe_blocks()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to subscribe to blocks: {:?}", e))?;
+    while let Some(header) = blocks.next().await {
+        let (hash, number) = match (header.hash, header.number) {
+            (Some(hash), Some(number)) => (hash, number),
+            _ => continue,
+        };
+        match client.get_block(hash).await {
+            Ok(Some(block)) => mined()
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .record(number, block.transactions),
+            Ok(None) => {}
+            Err(e) => tracing::debug!("[MEMPOOL] Failed to fetch block {:?}: {:?}", hash, e),
+        }
+    }
+    Ok(())
+}
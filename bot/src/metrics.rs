@@ -0,0 +1,203 @@
+//! Bot Metrics
+//!
+//! A minimal process-wide counter registry. Counters are keyed by name so new call sites
+//! don't need to register anything up front. Gauges hold the latest value of a reading instead of
+//! a running total.
+//!
+//! Counters count the current session. With a metrics file configured, a [MetricsSnapshot] of the
+//! lifetime totals and profit is saved periodically and restored at startup, so totals survive
+//! restarts. Session and lifetime views are both kept.
+//!
+//! Metrics recorded by a named instance are namespaced as `<instance>.<metric>`, see
+//! [crate::instances].
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounting::{self, Ledger, PendingSettlement},
+    instances,
+    opportunity::unix_millis,
+};
+
+/// The default interval between metrics snapshots, in seconds
+pub const DEFAULT_METRICS_PERSIST_SECS: u64 = 60;
+
+/// A registry of named counters and gauges
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<String, u64>>,
+    gauges: Mutex<BTreeMap<String, u64>>,
+    restored: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Metrics {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value` to the named counter
+    pub fn add(&self, name: &str, value: u64) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        *counters.entry(instances::namespaced(name)).or_default() += value;
+    }
+
+    /// Increments the named counter by one
+    pub fn increment(&self, name: &str) {
+        self.add(name, 1);
+    }
+
+    /// Returns the current value of the named counter
+    pub fn get(&self, name: &str) -> u64 {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        counters
+            .get(&instances::namespaced(name))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns a copy of every counter
+    pub fn snapshot(&self) -> BTreeMap<String, u64> {
+        self.counters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Sets the named gauge
+    pub fn set(&self, name: &str, value: u64) {
+        let mut gauges = self.gauges.lock().unwrap_or_else(|e| e.into_inner());
+        gauges.insert(instances::namespaced(name), value);
+    }
+
+    /// Returns a copy of every gauge
+    pub fn gauges(&self) -> BTreeMap<String, u64> {
+        self.gauges
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Restores the totals of previous sessions and their last gauge readings
+    ///
+    /// Gauges already set this session are kept.
+    pub fn restore(&self, counters: BTreeMap<String, u64>, gauges: BTreeMap<String, u64>) {
+        *self.restored.lock().unwrap_or_else(|e| e.into_inner()) = counters;
+        let mut current = self.gauges.lock().unwrap_or_else(|e| e.into_inner());
+        for (name, value) in gauges {
+            current.entry(name).or_insert(value);
+        }
+    }
+
+    /// Returns every counter's total across sessions
+    pub fn lifetime(&self) -> BTreeMap<String, u64> {
+        let mut lifetime = self
+            .restored
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for (name, value) in self.snapshot() {
+            let total = lifetime.entry(name).or_default();
+            *total = total.saturating_add(value);
+        }
+        lifetime
+    }
+}
+
+/// Returns the global metrics registry
+pub fn global() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Increments a counter in the global registry
+pub fn increment(name: &str) {
+    global().increment(name);
+}
+
+/// Session and lifetime totals of the global metrics and profit ledger
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// When the snapshot was taken, in milliseconds since the unix epoch
+    pub timestamp: u64,
+    /// This session's counters
+    pub session: BTreeMap<String, u64>,
+    /// The counters across every session
+    pub lifetime: BTreeMap<String, u64>,
+    /// The latest gauge readings
+    pub gauges: BTreeMap<String, u64>,
+    /// This session's realized profit
+    pub session_profit: Ledger,
+    /// Profit from settled sandwiches still waiting for confirmations
+    #[serde(default)]
+    pub pending_profit: Ledger,
+    /// The settled sandwiches still waiting for confirmations
+    #[serde(default)]
+    pub pending: Vec<PendingSettlement>,
+    /// The profit across every session
+    pub lifetime_profit: Ledger,
+}
+
+impl MetricsSnapshot {
+    /// Captures the global metrics and profit ledger
+    pub fn capture() -> Self {
+        Self {
+            timestamp: unix_millis(),
+            session: global().snapshot(),
+            lifetime: global().lifetime(),
+            gauges: global().gauges(),
+            session_profit: accounting::ledger().snapshot(),
+            pending_profit: accounting::ledger().pending_snapshot(),
+            pending: accounting::ledger().pending(),
+            lifetime_profit: accounting::ledger().lifetime(),
+        }
+    }
+
+    /// Restores a previous session's lifetime totals and pending sandwiches into the global metrics
+    /// and profit ledger
+    pub fn restore(self) {
+        global().restore(self.lifetime, self.gauges);
+        accounting::ledger().restore(self.lifetime_profit);
+        accounting::ledger().restore_pending(self.pending);
+    }
+
+    /// Saves the snapshot, replacing the file atomically
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = PathBuf::from(path);
+        tmp.set_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Loads a snapshot, or `None` if the file doesn't exist yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        match fs::read(path.as_ref()) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Saves a snapshot of the global metrics to `path` every `interval`
+pub async fn persist_periodically(path: PathBuf, interval: Duration) -> Result<()> {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = MetricsSnapshot::capture().save(&path) {
+            tracing::warn!("[METRICS] Failed to save metrics to {:?}: {:?}", path, e);
+        }
+    }
+}
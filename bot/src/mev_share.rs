@@ -0,0 +1,350 @@
+//! MEV-Share Bundles
+//!
+//! Transactions sent through MEV-Share stay private, only hints about them are streamed, like the
+//! `Swap` logs they emit. They can't be sandwiched, but they can be backrun: a hinted Uniswap V2
+//! swap moves its pair's price away from the same pair on another V2 deployment, the twin, and
+//! [plan_arb] sizes the two legs buying the token from the cheaper pair and selling it into the
+//! other.
+//!
+//! Backruns are submitted with `mev_sendBundle`, referencing the shared transaction by hash.
+//! Orderflow providers require a share of the backrun's payment to the builder to be refunded to
+//! the user, which is set through the bundle's `validity` fields. The refund comes out of the
+//! bribe, and is tracked as a rebate in the [crate::accounting] ledger.
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::V2Swap,
+    executor::SwapLeg,
+    metrics,
+    numeric::ternary_search,
+    uniswap::{self, V2Deployment},
+};
+
+/// The Flashbots MEV-Share event stream
+pub const MEV_SHARE_STREAM_URL: &str = "https://mev-share.flashbots.net";
+
+/// The default share of the backrun's bribe refunded to the user, in percent
+pub const DEFAULT_REFUND_PERCENT: u8 = 90;
+
+/// The gas a packed leg is priced at, the shared transaction can't be simulated to measure it
+pub const BACKRUN_LEG_GAS: u64 = 120_000;
+
+/// The MEV-Share backrun settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MevShareConfig {
+    /// Whether to backrun transactions shared through MEV-Share
+    pub enabled: bool,
+    /// The event stream hints are read from
+    pub stream_url: String,
+    /// The share of the backrun's bribe refunded to the user, in percent
+    pub refund_percent: u8,
+    /// The V2 deployment hinted swaps are arbitraged against, charging the Uniswap V2 fee
+    pub twin: Option<V2Deployment>,
+}
+
+impl Default for MevShareConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stream_url: MEV_SHARE_STREAM_URL.to_string(),
+            refund_percent: DEFAULT_REFUND_PERCENT,
+            twin: None,
+        }
+    }
+}
+
+/// A log hinted by a shared transaction, its data may be left out
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HintLog {
+    /// The emitting contract
+    pub address: Address,
+    /// The topics
+    pub topics: Vec<H256>,
+    /// The data, if shared
+    #[serde(default)]
+    pub data: Bytes,
+}
+
+/// A transaction shared through MEV-Share
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MevShareEvent {
+    /// The transaction hash
+    pub hash: TxHash,
+    /// The logs it's hinted to emit
+    #[serde(default)]
+    pub logs: Vec<HintLog>,
+}
+
+impl MevShareEvent {
+    /// The Uniswap V2 swaps hinted with their amounts
+    pub fn swaps(&self) -> Vec<V2Swap> {
+        self.logs
+            .iter()
+            .filter_map(|hint| {
+                let log = Log {
+                    address: hint.address,
+                    topics: hint.topics.clone(),
+                    data: hint.data.clone(),
+                    ..Default::default()
+                };
+                V2Swap::decode(&log).ok()
+            })
+            .collect()
+    }
+}
+
+/// Splits the server-sent event stream into events
+#[derive(Debug, Default)]
+pub struct EventStream {
+    buffer: String,
+}
+
+impl EventStream {
+    /// Feeds a chunk of the stream, returning the events it completes
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<MevShareEvent> {
+        self.buffer.push_str(&String::from_utf8_lossy(chunk));
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.find('\n') {
+            let line = self.buffer.drain(..=end).collect::<String>();
+            let data = match line.trim_end().strip_prefix("data:") {
+                Some(d) => d.trim(),
+                None => continue,
+            };
+            match serde_json::from_str(data) {
+                Ok(event) => events.push(event),
+                Err(e) => {
+                    metrics::increment("mev_share_malformed_events");
+                    tracing::debug!("[MEV-SHARE] Malformed event {}: {:?}", data, e);
+                }
+            }
+        }
+        events
+    }
+}
+
+/// Streams shared transactions into `sender` until the stream ends
+pub async fn stream_events(
+    http: reqwest::Client,
+    url: String,
+    sender: tokio::sync::mpsc::Sender<MevShareEvent>,
+) -> Result<()> {
+    let mut response = http
+        .get(&url)
+        .header("Accept", "text/event-stream")
+        .send()
+        .await?
+        .error_for_status()?;
+    let mut stream = EventStream::default();
+    while let Some(chunk) = response.chunk().await? {
+        for event in stream.push(&chunk) {
+            if sender.send(event).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+    eyre::bail!("MEV-Share event stream ended")
+}
+
+/// A pair's reserves, ordered as `(reserve0, reserve1)`, after a swap through it
+pub fn reserves_after(swap: &V2Swap, (reserve0, reserve1): (U256, U256)) -> Option<(U256, U256)> {
+    Some((
+        reserve0
+            .checked_add(swap.amount0_in)?
+            .checked_sub(swap.amount0_out)?,
+        reserve1
+            .checked_add(swap.amount1_in)?
+            .checked_sub(swap.amount1_out)?,
+    ))
+}
+
+/// A pair to arbitrage, with its reserves ordered as `(weth, token)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArbPair {
+    /// The pair
+    pub address: Address,
+    /// The WETH reserve
+    pub weth_reserve: U256,
+    /// The token reserve
+    pub token_reserve: U256,
+}
+
+/// An arbitrage backrunning a shared swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackrunArb {
+    /// The leg buying the token with WETH
+    pub buy: SwapLeg,
+    /// The leg selling the token back for WETH
+    pub sell: SwapLeg,
+    /// The WETH gained before gas
+    pub revenue: U256,
+}
+
+/// Plans the most profitable arbitrage of `token` between two pairs, spending at most
+/// `max_weth_in` of WETH
+///
+/// Returns `None` if no amount is profitable in either direction.
+pub fn plan_arb(
+    weth: Address,
+    token: Address,
+    pairs: [ArbPair; 2],
+    max_weth_in: U256,
+) -> Option<BackrunArb> {
+    let fee = uniswap::UNIV2_FEE_BPS;
+    [(pairs[0], pairs[1]), (pairs[1], pairs[0])]
+        .into_iter()
+        .filter_map(|(buy, sell)| {
+            let bought = |weth_in| {
+                uniswap::get_amount_out(weth_in, buy.weth_reserve, buy.token_reserve, fee)
+            };
+            let sold = |token_in| {
+                uniswap::get_amount_out(token_in, sell.token_reserve, sell.weth_reserve, fee)
+            };
+            let revenue = |weth_in| sold(bought(weth_in)).saturating_sub(weth_in);
+            let weth_in = ternary_search(U256::zero(), max_weth_in, &revenue);
+            let token_out = bought(weth_in);
+            (!revenue(weth_in).is_zero()).then(|| BackrunArb {
+                buy: SwapLeg {
+                    token_in: weth,
+                    token_out: token,
+                    pair: buy.address,
+                    amount_in: weth_in,
+                    amount_out: token_out,
+                },
+                sell: SwapLeg {
+                    token_in: token,
+                    token_out: weth,
+                    pair: sell.address,
+                    amount_in: token_out,
+                    amount_out: sold(token_out),
+                },
+                revenue: revenue(weth_in),
+            })
+        })
+        .max_by_key(|arb| arb.revenue)
+}
+
+/// The priority fee per gas the selling leg bids, a `bribe_bps` share of the revenue left after
+/// both legs' base fee
+///
+/// Returns `None` if the revenue can't cover the gas and the bribe.
+pub fn backrun_bribe(revenue: U256, base_fee: U256, bribe_bps: u64) -> Option<U256> {
+    let leg_gas = U256::from(BACKRUN_LEG_GAS);
+    let base_cost = leg_gas * 2 * base_fee;
+    let bribe = revenue.checked_sub(base_cost)? * U256::from(bribe_bps) / 10_000;
+    let priority_fee = bribe / leg_gas;
+    (revenue > base_cost + leg_gas * priority_fee).then_some(priority_fee)
+}
+
+/// Splits a bribe into the rebate refunded to the user and what the builder keeps
+pub fn split_rebate(bribe: U256, refund_percent: u8) -> (U256, U256) {
+    let rebate = bribe * U256::from(refund_percent.min(100)) / 100;
+    (rebate, bribe - rebate)
+}
+
+/// The bundle block range
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Inclusion {
+    /// The first block the bundle is valid in
+    pub block: U64,
+    /// The last block the bundle is valid in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_block: Option<U64>,
+}
+
+/// A bundle body element
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum BundleItem {
+    /// A shared transaction, referenced by hash
+    Hash {
+        /// The transaction hash
+        hash: TxHash,
+    },
+    /// A signed transaction
+    #[serde(rename_all = "camelCase")]
+    Tx {
+        /// The signed transaction
+        tx: Bytes,
+        /// Whether the bundle is still valid if this transaction reverts
+        can_revert: bool,
+    },
+}
+
+/// A refund of part of the bundle's payment to the signer of a body element
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Refund {
+    /// The index of the body element whose signer is refunded
+    pub body_idx: u64,
+    /// The refunded share, in percent
+    pub percent: u8,
+}
+
+/// The bundle validity requirements
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Validity {
+    /// Refunds to body element signers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub refund: Vec<Refund>,
+}
+
+/// A `mev_sendBundle` bundle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MevShareBundle {
+    /// The bundle format version
+    pub version: String,
+    /// The block range
+    pub inclusion: Inclusion,
+    /// The bundle transactions
+    pub body: Vec<BundleItem>,
+    /// The refund requirements
+    pub validity: Validity,
+}
+
+impl MevShareBundle {
+    /// Builds a backrun of a shared transaction, refunding `refund_percent` of the payment to its
+    /// sender
+    pub fn backrun(
+        shared_tx: TxHash,
+        signed_backruns: Vec<Bytes>,
+        block: U64,
+        refund_percent: u8,
+    ) -> Result<Self> {
+        if refund_percent > 100 {
+            eyre::bail!("Refund of {}% is more than the payment", refund_percent);
+        }
+        let mut body = vec![BundleItem::Hash { hash: shared_tx }];
+        body.extend(signed_backruns.into_iter().map(|tx| BundleItem::Tx {
+            tx,
+            can_revert: false,
+        }));
+        Ok(Self {
+            version: "v0.1".to_string(),
+            inclusion: Inclusion {
+                block,
+                max_block: None,
+            },
+            body,
+            validity: Validity {
+                refund: vec![Refund {
+                    body_idx: 0,
+                    percent: refund_percent,
+                }],
+            },
+        })
+    }
+
+    /// The total share of the payment refunded, in percent
+    pub fn refund_percent(&self) -> u8 {
+        self.validity
+            .refund
+            .iter()
+            .map(|r| r.percent)
+            .fold(0u8, u8::saturating_add)
+            .min(100)
+    }
+}
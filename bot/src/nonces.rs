@@ -0,0 +1,196 @@
+//! Victim Nonce Gaps
+//!
+//! A pending swap signed ahead of its sender's earlier transactions can't execute until they land.
+//! Bundling it only wastes a submission: the relay simulation reverts, or worse, the bundle lands
+//! around a victim that never executes. Before evaluation, a victim's nonce is checked against its
+//! sender's mined nonce, and a victim behind a gap is held in the [NonceGaps] buffer instead.
+//!
+//! On every new block, [release_nonce_gaps] rereads the nonces of the senders held, and queues the
+//! victims whose gap closed for evaluation again. Victims whose nonce was used by another
+//! transaction are dropped, and any still held after [MAX_NONCE_GAP_BLOCKS] blocks expire.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use tokio::sync::watch;
+
+use crate::{
+    metrics,
+    pipeline::{self, Candidate, EvalQueue},
+};
+
+/// How many victims may be held behind nonce gaps at once
+pub const NONCE_GAP_CAPACITY: usize = 1_024;
+
+/// How many blocks a victim is held waiting for its gap to close
+pub const MAX_NONCE_GAP_BLOCKS: u64 = 5;
+
+/// How a victim's nonce compares to its sender's mined nonce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceCheck {
+    /// The victim is the sender's next transaction
+    Ready,
+    /// The sender has earlier transactions to land first
+    Gap {
+        /// How many nonces are missing
+        missing: U256,
+    },
+    /// Another transaction already used the nonce
+    Used,
+}
+
+/// Checks a victim's nonce against `sender_nonce`, the sender's transaction count
+pub fn check_nonce(nonce: U256, sender_nonce: U256) -> NonceCheck {
+    if nonce < sender_nonce {
+        NonceCheck::Used
+    } else if nonce > sender_nonce {
+        NonceCheck::Gap {
+            missing: nonce - sender_nonce,
+        }
+    } else {
+        NonceCheck::Ready
+    }
+}
+
+/// Victims held behind nonce gaps, by sender and nonce
+#[derive(Debug)]
+pub struct NonceGaps {
+    held: BTreeMap<Address, BTreeMap<U256, (Candidate, U64)>>,
+    len: usize,
+    capacity: usize,
+}
+
+impl NonceGaps {
+    /// Creates a buffer holding up to `capacity` victims
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            held: BTreeMap::new(),
+            len: 0,
+            capacity,
+        }
+    }
+
+    /// Holds a victim seen at `block`, handing it back if the buffer is full
+    ///
+    /// A victim with the same sender and nonce, like a gas bump, replaces the one held.
+    pub fn hold(&mut self, candidate: Candidate, block: U64) -> Option<Candidate> {
+        let sender = self.held.entry(candidate.tx.from).or_default();
+        let nonce = candidate.tx.nonce;
+        if !sender.contains_key(&nonce) {
+            if self.len >= self.capacity {
+                if sender.is_empty() {
+                    self.held.remove(&candidate.tx.from);
+                }
+                return Some(candidate);
+            }
+            self.len += 1;
+        }
+        sender.insert(nonce, (candidate, block));
+        None
+    }
+
+    /// The senders with victims held
+    pub fn senders(&self) -> Vec<Address> {
+        self.held.keys().copied().collect()
+    }
+
+    /// Releases `sender`'s victims now that its transaction count is `sender_nonce`
+    ///
+    /// The victim at `sender_nonce` is returned, those whose nonce was used are dropped, and later
+    /// ones stay held.
+    pub fn release(&mut self, sender: Address, sender_nonce: U256) -> Option<Candidate> {
+        let held = self.held.get_mut(&sender)?;
+        let later = held.split_off(&sender_nonce);
+        let used = std::mem::replace(held, later);
+        self.len -= used.len();
+        if !used.is_empty() {
+            metrics::global().add("nonce_gap_used", used.len() as u64);
+        }
+        let ready = held.remove(&sender_nonce).map(|(candidate, _)| candidate);
+        if ready.is_some() {
+            self.len -= 1;
+        }
+        if held.is_empty() {
+            self.held.remove(&sender);
+        }
+        ready
+    }
+
+    /// Drops victims held since more than [MAX_NONCE_GAP_BLOCKS] before `block`, returning how
+    /// many expired
+    pub fn expire(&mut self, block: U64) -> usize {
+        let mut expired = 0;
+        self.held.retain(|_, held| {
+            held.retain(|_, (_, since)| {
+                let keep = block.saturating_sub(*since).as_u64() <= MAX_NONCE_GAP_BLOCKS;
+                if !keep {
+                    expired += 1;
+                }
+                keep
+            });
+            !held.is_empty()
+        });
+        self.len -= expired;
+        expired
+    }
+
+    /// The number of victims held
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no victims are held
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Requeues victims held in `gaps` whose gap closed into `queue` on every new block
+///
+/// Released victims are counted in the `nonce_gap_released` metric and expired ones in
+/// `nonce_gap_expired`.
+pub async fn release_nonce_gaps(
+    client: Arc<Provider<Ws>>,
+    mut head: watch::Receiver<U64>,
+    queue: Arc<EvalQueue>,
+    gaps: Arc<Mutex<NonceGaps>>,
+) -> Result<()> {
+    loop {
+        head.changed()
+            .await
+            .map_err(|_| eyre::eyre!("Block clock stopped"))?;
+        let latest = *head.borrow();
+        let senders = {
+            let mut gaps = gaps.lock().unwrap_or_else(|e| e.into_inner());
+            let expired = gaps.expire(latest);
+            if expired > 0 {
+                metrics::global().add("nonce_gap_expired", expired as u64);
+            }
+            gaps.senders()
+        };
+        for sender in senders {
+            let sender_nonce = match client.get_transaction_count(sender, None).await {
+                Ok(nonce) => nonce,
+                Err(e) => {
+                    tracing::debug!("[NONCE] Failed to read the nonce of {:?}: {:?}", sender, e);
+                    continue;
+                }
+            };
+            let released = gaps
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .release(sender, sender_nonce);
+            if let Some(candidate) = released {
+                tracing::debug!("[NONCE] Gap before {:?} closed", candidate.tx.hash);
+                metrics::increment("nonce_gap_released");
+                // Restamped, the time spent held doesn't count against the staleness budget
+                let score = pipeline::score_v2_swap(&candidate.tx).unwrap_or_default();
+                queue.requeue(Candidate::new(candidate.tx), score);
+            }
+        }
+    }
+}
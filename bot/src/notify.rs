@@ -0,0 +1,66 @@
+//! Notifications
+//!
+//! Alerts about state changes an operator should know about, like submission pausing. Alerts are
+//! always logged, and also posted to a webhook if `NOTIFY_WEBHOOK_URL` is set. The payload carries
+//! the message as both `text` and `content`, so Slack and Discord webhooks accept it as is.
+
+use eyre::Result;
+
+use crate::instances;
+
+/// Returns the optional alert webhook url
+pub fn get_notify_webhook_url() -> Option<String> {
+    instances::var("NOTIFY_WEBHOOK_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+}
+
+/// Returns the webhook payload for a message
+pub fn webhook_payload(message: &str) -> serde_json::Value {
+    serde_json::json!({
+        "text": message,
+        "content": message,
+    })
+}
+
+/// Sends alerts
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    /// The http client
+    pub http: reqwest::Client,
+    /// The webhook alerts are posted to
+    pub webhook: Option<String>,
+}
+
+impl Notifier {
+    /// Creates a notifier, reading the webhook from the environment
+    pub fn from_env() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            webhook: get_notify_webhook_url(),
+        }
+    }
+
+    /// Posts a message to the webhook
+    async fn post(&self, url: &str, message: &str) -> Result<()> {
+        self.http
+            .post(url)
+            .json(&webhook_payload(message))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Logs an alert and posts it to the webhook, if any
+    ///
+    /// Webhook failures are logged rather than returned, alerts are best effort.
+    pub async fn notify(&self, message: &str) {
+        tracing::warn!("[NOTIFY] {}", message);
+        if let Some(url) = &self.webhook {
+            if let Err(e) = self.post(url, message).await {
+                tracing::error!("[NOTIFY] Failed to post alert: {:?}", e);
+            }
+        }
+    }
+}
@@ -1,16 +1,40 @@
 //! Numerical Module for Calculations
 
+use std::str::FromStr;
+
 use ethers::{prelude::*, utils::parse_ether};
 use serde::{Deserialize, Serialize};
 
 use crate::uniswap;
 
+#[cfg(not(feature = "fast-math"))]
+use self::reference_sandwich_optimal_in as optimal_in_backend;
+#[cfg(feature = "fast-math")]
+use crate::fast_math::calculate_sandwich_optimal_in as optimal_in_backend;
+
 /// Calculate the max sandwich amount
+///
+/// Runs on ruint integers in `fast_math` when the `fast-math` feature is enabled.
 pub fn calculate_sandwich_optimal_in(
     user_amount_in: &U256,
     user_min_recv_token: &U256,
     weth_reserves: &U256,
     token_reserves: &U256,
+) -> U256 {
+    optimal_in_backend(
+        user_amount_in,
+        user_min_recv_token,
+        weth_reserves,
+        token_reserves,
+    )
+}
+
+/// Calculate the max sandwich amount using ethers `U256` math
+pub fn reference_sandwich_optimal_in(
+    user_amount_in: &U256,
+    user_min_recv_token: &U256,
+    weth_reserves: &U256,
+    token_reserves: &U256,
 ) -> U256 {
     // Set our search parameters
     let lower_bound = U256::zero();
@@ -39,28 +63,21 @@ pub struct ReserveState {
     pub b_reserves: U256,
 }
 
-/// The Frontrun State
+/// A swap through the sandwiched pair
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-pub struct PoolState {
-    /// Variable
-    pub variable: U256,
-    /// New token a reserves
-    pub new_a_reserves: U256,
-    /// New token b reserves
-    pub new_b_reserves: U256,
-}
-
-impl From<(U256, U256, U256)> for PoolState {
-    fn from((variable, new_a_reserves, new_b_reserves): (U256, U256, U256)) -> Self {
-        Self {
-            variable,
-            new_a_reserves,
-            new_b_reserves,
-        }
-    }
+pub struct SwapOutcome {
+    /// The amount swapped in
+    pub amount_in: U256,
+    /// The amount swapped out
+    pub amount_out: U256,
+    /// The pair's reserves after the swap, with WETH as token a
+    pub reserves_after: ReserveState,
 }
 
 /// A Sandwich Context
+///
+/// Every amount and reserve is explicit, so the context serializes as is into storage and the
+/// admin API.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SandwichContext {
     /// The Revenue
@@ -71,14 +88,14 @@ pub struct SandwichContext {
     pub user_amount_in: U256,
     /// The minimum amount the user wants to receive
     pub user_min_recv: U256,
-    /// The reserve state
+    /// The reserves before the sandwich, with WETH as token a
     pub reserve_state: ReserveState,
-    /// The frontrun state
-    pub frontrun_state: PoolState,
-    /// The target state
-    pub target_state: PoolState,
-    /// The backrun state
-    pub backrun_state: PoolState,
+    /// The frontrun, WETH in and token out
+    pub frontrun: SwapOutcome,
+    /// The victim's swap behind the frontrun, WETH in and token out
+    pub victim: SwapOutcome,
+    /// The backrun, token in and WETH out
+    pub backrun: SwapOutcome,
 }
 
 /// Calculates the sandwich states to achieve a sandwich
@@ -90,27 +107,22 @@ pub fn calculate_sandwich_context(
     token_reserves: &U256,
 ) -> eyre::Result<SandwichContext> {
     // Calculate the frontrun state
-    let frontrun_state: PoolState =
-        uniswap::get_univ2_data_given_in(optimal_weth_in, weth_reserves, token_reserves).into();
+    let (frontrun_out, weth_after_frontrun, token_after_frontrun) =
+        uniswap::get_univ2_data_given_in(optimal_weth_in, weth_reserves, token_reserves);
 
     // Calculate the target state
-    let target_state: PoolState = uniswap::get_univ2_data_given_in(
+    let (victim_out, weth_after_victim, token_after_victim) = uniswap::get_univ2_data_given_in(
         user_amount_in,
-        &frontrun_state.new_a_reserves,
-        &frontrun_state.new_b_reserves,
-    )
-    .into();
+        &weth_after_frontrun,
+        &token_after_frontrun,
+    );
 
     // Calculate the backrun state
-    let backrun_state: PoolState = uniswap::get_univ2_data_given_in(
-        &frontrun_state.variable,
-        &target_state.new_b_reserves,
-        &target_state.new_a_reserves,
-    )
-    .into();
+    let (backrun_out, token_after_backrun, weth_after_backrun) =
+        uniswap::get_univ2_data_given_in(&frontrun_out, &token_after_victim, &weth_after_victim);
 
     // Sanity check the minimum acceptable output
-    if target_state.variable < *user_min_recv {
+    if victim_out < *user_min_recv {
         return Err(eyre::eyre!(
             "The minimum acceptable output is less than the user minimum acceptable output"
         ));
@@ -118,8 +130,7 @@ pub fn calculate_sandwich_context(
 
     // Return a constructed SandwichContext
     Ok(SandwichContext {
-        revenue: backrun_state
-            .variable
+        revenue: backrun_out
             .checked_sub(*optimal_weth_in)
             .unwrap_or(U256::zero()),
         optimal_weth_in: *optimal_weth_in,
@@ -129,12 +140,196 @@ pub fn calculate_sandwich_context(
             a_reserves: *weth_reserves,
             b_reserves: *token_reserves,
         },
-        frontrun_state,
-        target_state,
-        backrun_state,
+        frontrun: SwapOutcome {
+            amount_in: *optimal_weth_in,
+            amount_out: frontrun_out,
+            reserves_after: ReserveState {
+                a_reserves: weth_after_frontrun,
+                b_reserves: token_after_frontrun,
+            },
+        },
+        victim: SwapOutcome {
+            amount_in: *user_amount_in,
+            amount_out: victim_out,
+            reserves_after: ReserveState {
+                a_reserves: weth_after_victim,
+                b_reserves: token_after_victim,
+            },
+        },
+        backrun: SwapOutcome {
+            amount_in: frontrun_out,
+            amount_out: backrun_out,
+            reserves_after: ReserveState {
+                a_reserves: weth_after_backrun,
+                b_reserves: token_after_backrun,
+            },
+        },
     })
 }
 
+/// Calculates the most profitable sandwich achievable with at most `max_weth_in`
+///
+/// When the unconstrained optimal input exceeds the available inventory, the revenue curve is
+/// searched below the constraint so capital-constrained operators still capture partial value.
+pub fn calculate_constrained_sandwich(
+    max_weth_in: &U256,
+    user_amount_in: &U256,
+    user_min_recv: &U256,
+    weth_reserves: &U256,
+    token_reserves: &U256,
+) -> eyre::Result<SandwichContext> {
+    let optimal_weth_in =
+        calculate_sandwich_optimal_in(user_amount_in, user_min_recv, weth_reserves, token_reserves);
+    let upper_bound = optimal_weth_in.min(*max_weth_in);
+
+    let revenue = move |amount_in: U256| -> U256 {
+        calculate_sandwich_context(
+            &amount_in,
+            user_amount_in,
+            user_min_recv,
+            weth_reserves,
+            token_reserves,
+        )
+        .map(|ctx| ctx.revenue)
+        .unwrap_or_default()
+    };
+
+    // Revenue usually peaks right at the constraint, so prefer the bound on ties
+    let best = ternary_search(U256::zero(), upper_bound, revenue);
+    let best = if revenue(upper_bound) >= revenue(best) {
+        upper_bound
+    } else {
+        best
+    };
+
+    calculate_sandwich_context(
+        &best,
+        user_amount_in,
+        user_min_recv,
+        weth_reserves,
+        token_reserves,
+    )
+}
+
+/// What the frontrun size is optimized for
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Optimizer {
+    /// The most revenue
+    #[default]
+    MaxProfit,
+    /// The most revenue per WETH committed, above a minimum revenue
+    ReturnOnCapital,
+}
+
+impl FromStr for Optimizer {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "profit" | "max-profit" | "max_profit" => Ok(Optimizer::MaxProfit),
+            "roc" | "return-on-capital" | "return_on_capital" => Ok(Optimizer::ReturnOnCapital),
+            _ => Err(eyre::eyre!("Unknown optimizer \"{}\"", s)),
+        }
+    }
+}
+
+/// Returns the revenue per WETH of frontrun input, scaled by 1e18
+pub fn return_on_capital(revenue: &U256, weth_in: &U256) -> U256 {
+    if weth_in.is_zero() {
+        return U256::zero();
+    }
+    revenue.saturating_mul(U256::exp10(18)) / weth_in
+}
+
+/// Calculates the sandwich with the best return on capital making at least `min_revenue`
+///
+/// When inventory is shared across many concurrent opportunities, a smaller frontrun that earns
+/// more per WETH can beat the most profitable one. Revenue grows up to the optimal input, so the
+/// smallest input making `min_revenue` is found first and the return is searched above it.
+pub fn calculate_capital_efficient_sandwich(
+    min_revenue: &U256,
+    user_amount_in: &U256,
+    user_min_recv: &U256,
+    weth_reserves: &U256,
+    token_reserves: &U256,
+) -> eyre::Result<SandwichContext> {
+    let optimal_weth_in =
+        calculate_sandwich_optimal_in(user_amount_in, user_min_recv, weth_reserves, token_reserves);
+
+    let revenue = move |amount_in: U256| -> U256 {
+        calculate_sandwich_context(
+            &amount_in,
+            user_amount_in,
+            user_min_recv,
+            weth_reserves,
+            token_reserves,
+        )
+        .map(|ctx| ctx.revenue)
+        .unwrap_or_default()
+    };
+    let min_revenue = (*min_revenue).max(U256::one());
+    if revenue(optimal_weth_in) < min_revenue {
+        return Err(eyre::eyre!(
+            "The optimal sandwich revenue is less than the minimum revenue"
+        ));
+    }
+
+    // Find the smallest input making the minimum revenue
+    let (mut lower, mut upper) = (U256::zero(), optimal_weth_in);
+    while upper - lower > U256::one() {
+        let mid = (lower + upper) / 2;
+        if revenue(mid) >= min_revenue {
+            upper = mid;
+        } else {
+            lower = mid;
+        }
+    }
+
+    // The return usually peaks at the smallest input, so prefer it on ties
+    let roc = move |amount_in: U256| -> U256 { return_on_capital(&revenue(amount_in), &amount_in) };
+    let best = ternary_search(upper, optimal_weth_in, roc);
+    let best = if roc(upper) >= roc(best) { upper } else { best };
+
+    calculate_sandwich_context(
+        &best,
+        user_amount_in,
+        user_min_recv,
+        weth_reserves,
+        token_reserves,
+    )
+}
+
+/// Ternary Search for the input maximizing a unimodal objective within the bounds
+pub fn ternary_search(
+    lower_bound: U256,
+    upper_bound: U256,
+    objective: impl Fn(U256) -> U256,
+) -> U256 {
+    let (mut lower, mut upper) = (lower_bound, upper_bound);
+
+    // Narrow the bounds by thirds
+    while upper > lower && upper - lower > U256::from(2u64) {
+        let third = (upper - lower) / 3;
+        let (left, right) = (lower + third, upper - third);
+        if objective(left) < objective(right) {
+            lower = left;
+        } else {
+            upper = right;
+        }
+    }
+
+    // Scan the few remaining candidates
+    let mut best = lower;
+    let mut candidate = lower;
+    while candidate <= upper {
+        if objective(candidate) > objective(best) {
+            best = candidate;
+        }
+        candidate += U256::one();
+    }
+    best
+}
+
 /// Binary Search to find the optimal sandwichable amount
 /// Using binary search here as the profit function isn't normally distributed
 /// NOTE: Can likely speed this up by using quadratic search. (possibly even furth by using boundries as heuristics)
@@ -0,0 +1,173 @@
+//! Observer Mode
+//!
+//! When the relay stops answering or legs can't be signed, submitting is pointless but the
+//! mempool warm state is still worth keeping. Instead of exiting, the bot switches to observer
+//! mode: candidates are still evaluated and recorded as opportunities, but nothing is submitted.
+//! Every so often one candidate is let through as a probe, and the first successful relay round
+//! trip switches back.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{metrics, notify::Notifier};
+
+/// The default number of consecutive relay failures before switching to observer mode
+pub const DEFAULT_RELAY_FAILURE_THRESHOLD: u32 = 5;
+
+/// The default interval between submission probes in observer mode, in seconds
+pub const DEFAULT_OBSERVER_PROBE_SECS: u64 = 30;
+
+/// Why the bot stopped submitting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degradation {
+    /// The relay failed too many requests in a row
+    RelayUnreachable,
+    /// A sandwich leg couldn't be signed
+    SignerUnavailable,
+}
+
+impl Degradation {
+    /// A short description for alerts
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Degradation::RelayUnreachable => "relay unreachable",
+            Degradation::SignerUnavailable => "signer unavailable",
+        }
+    }
+}
+
+/// A change in whether the bot is observing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObserverTransition {
+    /// Submission stopped
+    Entered(Degradation),
+    /// Submission resumed
+    Recovered(Degradation),
+}
+
+#[derive(Debug)]
+struct ObserverState {
+    reason: Option<Degradation>,
+    relay_failures: u32,
+    last_probe: Option<Instant>,
+}
+
+/// A shared switch between submitting and observing
+#[derive(Debug, Clone)]
+pub struct ObserverMode {
+    state: Arc<Mutex<ObserverState>>,
+    failure_threshold: u32,
+    probe_interval: Duration,
+}
+
+impl Default for ObserverMode {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_RELAY_FAILURE_THRESHOLD,
+            Duration::from_secs(DEFAULT_OBSERVER_PROBE_SECS),
+        )
+    }
+}
+
+impl ObserverMode {
+    /// Creates a switch that observes after `failure_threshold` relay failures in a row, probing
+    /// every `probe_interval`
+    pub fn new(failure_threshold: u32, probe_interval: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ObserverState {
+                reason: None,
+                relay_failures: 0,
+                last_probe: None,
+            })),
+            failure_threshold: failure_threshold.max(1),
+            probe_interval,
+        }
+    }
+
+    /// Why the bot is observing, if it is
+    pub fn reason(&self) -> Option<Degradation> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).reason
+    }
+
+    /// Whether the bot is observing
+    pub fn is_observing(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// Whether a candidate may be submitted at `now`
+    ///
+    /// While observing, one candidate per probe interval is let through to test for recovery.
+    pub fn should_submit(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.reason.is_none() {
+            return true;
+        }
+        match state.last_probe {
+            Some(last) if now.saturating_duration_since(last) < self.probe_interval => false,
+            _ => {
+                state.last_probe = Some(now);
+                true
+            }
+        }
+    }
+
+    fn enter(state: &mut ObserverState, reason: Degradation) -> Option<ObserverTransition> {
+        if state.reason.is_some() {
+            return None;
+        }
+        state.reason = Some(reason);
+        state.last_probe = Some(Instant::now());
+        Some(ObserverTransition::Entered(reason))
+    }
+
+    /// Records a failed relay request, observing once they reach the threshold
+    pub fn relay_failed(&self) -> Option<ObserverTransition> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.relay_failures = state.relay_failures.saturating_add(1);
+        if state.relay_failures < self.failure_threshold {
+            return None;
+        }
+        Self::enter(&mut state, Degradation::RelayUnreachable)
+    }
+
+    /// Records a signing failure, observing immediately
+    pub fn signer_failed(&self) -> Option<ObserverTransition> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        Self::enter(&mut state, Degradation::SignerUnavailable)
+    }
+
+    /// Records a signed bundle the relay accepted, resuming submission
+    pub fn succeeded(&self) -> Option<ObserverTransition> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.relay_failures = 0;
+        state.last_probe = None;
+        state.reason.take().map(ObserverTransition::Recovered)
+    }
+}
+
+/// Counts and alerts on a transition, if any
+pub async fn announce(notifier: &Notifier, transition: Option<ObserverTransition>) {
+    match transition {
+        Some(ObserverTransition::Entered(reason)) => {
+            metrics::increment("observer_entered");
+            notifier
+                .notify(&format!(
+                    "Switching to observer mode ({}), opportunities are still recorded",
+                    reason.describe()
+                ))
+                .await;
+        }
+        Some(ObserverTransition::Recovered(reason)) => {
+            metrics::increment("observer_recovered");
+            notifier
+                .notify(&format!(
+                    "Leaving observer mode ({} resolved), resuming submission",
+                    reason.describe()
+                ))
+                .await;
+        }
+        None => {}
+    }
+}
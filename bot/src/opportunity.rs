@@ -0,0 +1,224 @@
+//! Opportunities
+//!
+//! An [Opportunity] collects everything learned about a sandwichable transaction as it moves
+//! through evaluation: the victim, its decoded swap, the pair and the reserves it was priced
+//! against, the sandwich context and the bundle planned for it. Shipped opportunities are appended
+//! to an [OpportunityStore] as json lines, which can be loaded back to replay or backtest them.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{abi::SwapExactETHForTokensCall, numeric::SandwichContext, simulation::StateSnapshot};
+
+/// The milliseconds since the unix epoch
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// A decoded `swapExactETHForTokens` call
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DecodedSwap {
+    /// The minimum output of the last token in the path
+    pub amount_out_min: U256,
+    /// The swap path, starting with WETH
+    pub path: Vec<Address>,
+    /// The output recipient
+    pub to: Address,
+    /// The unix timestamp after which the swap reverts
+    pub deadline: U256,
+}
+
+impl From<&SwapExactETHForTokensCall> for DecodedSwap {
+    fn from(call: &SwapExactETHForTokensCall) -> Self {
+        Self {
+            amount_out_min: call.amount_out_min,
+            path: call.path.clone(),
+            to: call.to,
+            deadline: call.deadline,
+        }
+    }
+}
+
+/// The bundle planned for an opportunity
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BundlePlan {
+    /// The block the bundle targets
+    pub target_block: U64,
+    /// The searcher wallet sending the legs
+    pub wallet: Address,
+    /// The frontrun nonce, the backrun uses the next one
+    pub nonce: U256,
+    /// The target block's base fee
+    pub base_fee: U256,
+    /// The share of the revenue bribed to the builder, in bips
+    pub bribe_bps: u64,
+    /// The signed frontrun, victim and backrun
+    pub transactions: Vec<Bytes>,
+}
+
+/// When an opportunity reached each stage, in milliseconds since the unix epoch
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Timestamps {
+    /// When the victim was first seen
+    pub seen: u64,
+    /// When the sandwich was priced
+    pub priced: Option<u64>,
+    /// When the bundle was planned
+    pub planned: Option<u64>,
+}
+
+/// A sandwich opportunity threaded through evaluation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Opportunity {
+    /// The victim transaction
+    pub victim: Transaction,
+    /// The victim's decoded swap
+    pub swap: DecodedSwap,
+    /// The sandwiched pair
+    pub pair: Address,
+    /// The token bought from the pair
+    pub token: Address,
+    /// The pair's reserves, as `(weth, token)`, and the block they were read at
+    pub reserves: StateSnapshot,
+    /// The minimum amount of `token` the victim receives from the pair
+    pub user_min_recv: U256,
+    /// The sandwich context, once priced
+    pub context: Option<SandwichContext>,
+    /// The bundle, once planned
+    pub plan: Option<BundlePlan>,
+    /// When each stage was reached
+    pub timestamps: Timestamps,
+}
+
+impl Opportunity {
+    /// Starts an opportunity for a victim first seen at `received_at`
+    pub fn new(
+        victim: Transaction,
+        swap: DecodedSwap,
+        pair: Address,
+        reserves: StateSnapshot,
+        user_min_recv: U256,
+        received_at: Instant,
+    ) -> Self {
+        let seen = unix_millis().saturating_sub(received_at.elapsed().as_millis() as u64);
+        Self {
+            token: swap.path.get(1).copied().unwrap_or_default(),
+            victim,
+            swap,
+            pair,
+            reserves,
+            user_min_recv,
+            context: None,
+            plan: None,
+            timestamps: Timestamps {
+                seen,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The WETH reserve of the pair
+    pub fn weth_reserves(&self) -> U256 {
+        self.reserves.reserves.0
+    }
+
+    /// The token reserve of the pair
+    pub fn token_reserves(&self) -> U256 {
+        self.reserves.reserves.1
+    }
+
+    /// The victim's WETH input
+    pub fn user_amount_in(&self) -> U256 {
+        self.victim.value
+    }
+
+    /// Records the priced sandwich
+    pub fn price(&mut self, context: SandwichContext) {
+        self.context = Some(context);
+        self.timestamps.priced = Some(unix_millis());
+    }
+
+    /// Records the planned bundle
+    pub fn plan(&mut self, plan: BundlePlan) {
+        self.plan = Some(plan);
+        self.timestamps.planned = Some(unix_millis());
+    }
+}
+
+/// An append-only json lines file of opportunities
+#[derive(Debug)]
+pub struct OpportunityStore {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl OpportunityStore {
+    /// Opens a store, creating the file if it doesn't exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| eyre::eyre!("Failed to open opportunity store {:?}: {:?}", path, e))?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// The path of the store
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends an opportunity
+    pub fn append(&self, opportunity: &Opportunity) -> Result<()> {
+        let mut line = serde_json::to_string(opportunity)?;
+        line.push('\n');
+        self.file
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Loads every opportunity in a store
+    ///
+    /// Malformed lines, like one torn by a crash mid-append, are skipped and counted in a warning.
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<Opportunity>> {
+        let file = File::open(path.as_ref())?;
+        let mut opportunities = Vec::new();
+        let mut malformed = 0;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(opportunity) => opportunities.push(opportunity),
+                Err(_) => malformed += 1,
+            }
+        }
+        if malformed > 0 {
+            tracing::warn!(
+                "Skipped {} malformed lines in opportunity store {:?}",
+                malformed,
+                path.as_ref()
+            );
+        }
+        Ok(opportunities)
+    }
+}
@@ -0,0 +1,396 @@
+//! Off-chain Order Flow
+//!
+//! Watches the UniswapX order API and evaluates whether an open order can be filled against a
+//! Uniswap V2 pair.
+//!
+//! Dutch orders decay from their start to their end amounts, so an order is priced at the amounts
+//! the reactor resolves it to in the target block. Fills go through the executor contract, which
+//! pays the swapper from its inventory of the output token and swaps the input it receives back
+//! into the output token through the pair in the same transaction, keeping the surplus.
+
+use std::{collections::HashSet, str::FromStr, time::Duration};
+
+use ethers::{
+    abi::{self, Token},
+    prelude::*,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::{executor::SwapLeg, uniswap};
+
+/// The UniswapX open orders endpoint
+pub const UNISWAPX_ORDERS_URL: &str =
+    "https://api.uniswap.org/v2/orders?orderStatus=open&chainId=1";
+
+/// The UniswapX exclusive dutch order reactor
+pub const UNISWAPX_REACTOR: &str = "0x6000da47483062A0D734Ba3dc7576Ce6A0B645C4";
+
+/// An off-chain order source
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum OrderSource {
+    /// UniswapX dutch orders, fillable by anyone through the reactor
+    UniswapX,
+}
+
+impl FromStr for OrderSource {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "uniswapx" => Ok(OrderSource::UniswapX),
+            _ => Err(eyre::eyre!("Unknown order source \"{}\"", s)),
+        }
+    }
+}
+
+/// A comma-separated list of order sources
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OrderSources(pub Vec<OrderSource>);
+
+impl FromStr for OrderSources {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(OrderSource::from_str)
+            .collect::<Result<Vec<_>>>()
+            .map(OrderSources)
+    }
+}
+
+/// An open order normalized across sources
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Order {
+    /// Where the order came from
+    pub source: OrderSource,
+    /// The order hash or uid
+    pub id: String,
+    /// The order owner
+    pub owner: Address,
+    /// The token the owner sells
+    pub sell_token: Address,
+    /// The token the owner buys
+    pub buy_token: Address,
+    /// The amount of `sell_token` sold, once any decay ended
+    pub sell_amount: U256,
+    /// The minimum amount of `buy_token` the owner accepts, once any decay ended
+    pub min_buy_amount: U256,
+    /// The unix timestamp after which the order is invalid
+    pub deadline: u64,
+    /// The abi-encoded order, for sources that can be filled permissionlessly
+    pub encoded_order: Option<Bytes>,
+    /// The owner's signature over the encoded order
+    pub signature: Option<Bytes>,
+    /// How a dutch order's amounts decay to the amounts above
+    pub decay: Option<DutchDecay>,
+}
+
+/// The linear decay of a dutch order's amounts from their start amounts
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DutchDecay {
+    /// The unix timestamp the decay starts at
+    pub start_time: u64,
+    /// The unix timestamp the decay ends at
+    pub end_time: u64,
+    /// The amount of `sell_token` sold before the decay starts
+    pub sell_start_amount: U256,
+    /// The minimum amount of `buy_token` accepted before the decay starts
+    pub buy_start_amount: U256,
+}
+
+impl Order {
+    /// The `(sell, min buy)` amounts the order resolves to in a block at `timestamp`
+    ///
+    /// Like the reactor, decreasing amounts round down and increasing ones round up.
+    pub fn amounts_at(&self, timestamp: u64) -> (U256, U256) {
+        let decay = match &self.decay {
+            Some(d) => d,
+            None => return (self.sell_amount, self.min_buy_amount),
+        };
+        let resolve = |start: U256, end: U256| {
+            if timestamp >= decay.end_time {
+                return end;
+            }
+            if timestamp <= decay.start_time {
+                return start;
+            }
+            let elapsed = U256::from(timestamp - decay.start_time);
+            let duration = U256::from(decay.end_time - decay.start_time);
+            if end < start {
+                start - (start - end) * elapsed / duration
+            } else {
+                start + ((end - start) * elapsed + duration - 1) / duration
+            }
+        };
+        (
+            resolve(decay.sell_start_amount, self.sell_amount),
+            resolve(decay.buy_start_amount, self.min_buy_amount),
+        )
+    }
+}
+
+/// A UniswapX fill executed by the executor contract
+///
+/// The executor approves the reactor to pull the output from its inventory, executes the order,
+/// and swaps the input it received back into the output token through the pair.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OrderFill {
+    /// The reactor settling the order
+    pub reactor: Address,
+    /// The reactor `execute` calldata
+    pub execute: Bytes,
+    /// The token paid to the swapper
+    pub output_token: Address,
+    /// The amount paid to the swapper in the target block
+    pub output_amount: U256,
+    /// The swap of the order's input back into the output token
+    pub rebalance: SwapLeg,
+}
+
+/// A priced plan for an open order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OrderPlan {
+    /// The evaluated order
+    pub order: Order,
+    /// The pair the order is priced against
+    pub pair: Address,
+    /// The `sell_token` the order resolves to selling in the target block
+    pub sell_amount: U256,
+    /// The `buy_token` the order resolves to accepting in the target block
+    pub buy_amount: U256,
+    /// The `buy_token` output the pair gives for `sell_amount`
+    pub pool_amount_out: U256,
+    /// The output above `buy_amount`
+    pub surplus: U256,
+    /// The fill
+    pub fill: OrderFill,
+}
+
+/// Parses a decimal or hex quantity string
+fn parse_amount(value: &str) -> Result<U256> {
+    if let Some(hex) = value.strip_prefix("0x") {
+        return U256::from_str_radix(hex, 16).map_err(|e| eyre::eyre!("Invalid amount: {:?}", e));
+    }
+    U256::from_dec_str(value).map_err(|e| eyre::eyre!("Invalid amount \"{}\": {:?}", value, e))
+}
+
+/// Parses an address string
+fn parse_address(value: &str) -> Result<Address> {
+    Address::from_str(value).map_err(|_| eyre::eyre!("Invalid address \"{}\"", value))
+}
+
+/// A UniswapX order amount
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UniswapXAmount {
+    token: String,
+    #[serde(default)]
+    start_amount: Option<String>,
+    end_amount: String,
+}
+
+/// A UniswapX API order
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UniswapXOrder {
+    order_hash: String,
+    encoded_order: String,
+    signature: String,
+    swapper: String,
+    #[serde(default)]
+    deadline: u64,
+    #[serde(default)]
+    decay_start_time: Option<u64>,
+    #[serde(default)]
+    decay_end_time: Option<u64>,
+    input: UniswapXAmount,
+    outputs: Vec<UniswapXAmount>,
+}
+
+/// The UniswapX API response
+#[derive(Debug, Clone, Deserialize)]
+struct UniswapXResponse {
+    orders: Vec<UniswapXOrder>,
+}
+
+impl TryFrom<UniswapXOrder> for Order {
+    type Error = eyre::Report;
+
+    fn try_from(order: UniswapXOrder) -> Result<Self> {
+        // Fee outputs pay the same token, and the filler pays every output
+        let token = order
+            .outputs
+            .first()
+            .ok_or_else(|| eyre::eyre!("UniswapX order has no outputs"))?
+            .token
+            .clone();
+        let (mut buy_start_amount, mut min_buy_amount) = (U256::zero(), U256::zero());
+        for output in order.outputs.iter().filter(|o| o.token == token) {
+            let end = parse_amount(&output.end_amount)?;
+            buy_start_amount += match &output.start_amount {
+                Some(start) => parse_amount(start)?,
+                None => end,
+            };
+            min_buy_amount += end;
+        }
+        let sell_amount = parse_amount(&order.input.end_amount)?;
+        let decay = match (order.decay_start_time, order.decay_end_time) {
+            (Some(start_time), Some(end_time)) if start_time < end_time => Some(DutchDecay {
+                start_time,
+                end_time,
+                sell_start_amount: match &order.input.start_amount {
+                    Some(start) => parse_amount(start)?,
+                    None => sell_amount,
+                },
+                buy_start_amount,
+            }),
+            _ => None,
+        };
+        Ok(Order {
+            source: OrderSource::UniswapX,
+            id: order.order_hash,
+            owner: parse_address(&order.swapper)?,
+            sell_token: parse_address(&order.input.token)?,
+            buy_token: parse_address(&token)?,
+            sell_amount,
+            min_buy_amount,
+            deadline: order.deadline,
+            encoded_order: Some(Bytes::from(hex::decode(
+                order.encoded_order.trim_start_matches("0x"),
+            )?)),
+            signature: Some(Bytes::from(hex::decode(
+                order.signature.trim_start_matches("0x"),
+            )?)),
+            decay,
+        })
+    }
+}
+
+/// Fetches the open orders from a source
+///
+/// Orders that fail to parse are dropped rather than failing the whole batch.
+pub async fn fetch_orders(client: &reqwest::Client, source: OrderSource) -> Result<Vec<Order>> {
+    let orders = match source {
+        OrderSource::UniswapX => client
+            .get(UNISWAPX_ORDERS_URL)
+            .send()
+            .await?
+            .json::<UniswapXResponse>()
+            .await?
+            .orders
+            .into_iter()
+            .filter_map(|o| Order::try_from(o).ok())
+            .collect(),
+    };
+    Ok(orders)
+}
+
+/// Polls a source, sending each newly seen order down the channel
+///
+/// Returns once the receiving end of the channel is dropped.
+pub async fn watch_orders(
+    source: OrderSource,
+    interval: Duration,
+    sender: mpsc::Sender<Order>,
+) -> Result<()> {
+    let client = reqwest::Client::new();
+    let mut seen = HashSet::new();
+    loop {
+        match fetch_orders(&client, source).await {
+            Ok(orders) => {
+                for order in orders {
+                    if seen.insert(order.id.clone()) && sender.send(order).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            Err(e) => tracing::debug!("Failed to fetch {:?} orders: {:?}", source, e),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Encodes a UniswapX reactor `execute((bytes order, bytes sig))` fill
+pub fn encode_uniswapx_fill(order: &Order) -> Result<Bytes> {
+    let (encoded, signature) = match (&order.encoded_order, &order.signature) {
+        (Some(e), Some(s)) => (e, s),
+        _ => eyre::bail!(
+            "Order {} is missing its encoded order or signature",
+            order.id
+        ),
+    };
+    let mut calldata = ethers::utils::id("execute((bytes,bytes))").to_vec();
+    calldata.extend(abi::encode(&[Token::Tuple(vec![
+        Token::Bytes(encoded.as_ref().to_vec()),
+        Token::Bytes(signature.as_ref().to_vec()),
+    ])]));
+    Ok(Bytes::from(calldata))
+}
+
+/// Evaluates an order against the reserves of its Uniswap V2 pair in a block at `timestamp`
+///
+/// `sell_reserves` and `buy_reserves` are the pair reserves of the order's sell and buy tokens.
+/// Returns a plan if routing the order's resolved input through the pair beats its resolved
+/// output.
+pub fn evaluate_order(
+    order: &Order,
+    sell_reserves: &U256,
+    buy_reserves: &U256,
+    timestamp: u64,
+) -> Result<Option<OrderPlan>> {
+    evaluate_order_in(
+        order,
+        &uniswap::V2Deployment::default(),
+        sell_reserves,
+        buy_reserves,
+        timestamp,
+    )
+}
+
+/// [evaluate_order] against the order's pair in `deployment`
+pub fn evaluate_order_in(
+    order: &Order,
+    deployment: &uniswap::V2Deployment,
+    sell_reserves: &U256,
+    buy_reserves: &U256,
+    timestamp: u64,
+) -> Result<Option<OrderPlan>> {
+    if order.deadline != 0 && order.deadline < timestamp {
+        return Ok(None);
+    }
+
+    let (sell_amount, buy_amount) = order.amounts_at(timestamp);
+    let pair = deployment.pair_address(order.sell_token, order.buy_token);
+    let (pool_amount_out, _, _) =
+        uniswap::get_univ2_data_given_in(&sell_amount, sell_reserves, buy_reserves);
+    if pool_amount_out <= buy_amount {
+        return Ok(None);
+    }
+
+    let fill = OrderFill {
+        reactor: parse_address(UNISWAPX_REACTOR)?,
+        execute: encode_uniswapx_fill(order)?,
+        output_token: order.buy_token,
+        output_amount: buy_amount,
+        rebalance: SwapLeg {
+            token_in: order.sell_token,
+            token_out: order.buy_token,
+            pair,
+            amount_in: sell_amount,
+            amount_out: pool_amount_out,
+        },
+    };
+    Ok(Some(OrderPlan {
+        order: order.clone(),
+        pair,
+        sell_amount,
+        buy_amount,
+        pool_amount_out,
+        surplus: pool_amount_out - buy_amount,
+        fill,
+    }))
+}
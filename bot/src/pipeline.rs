@@ -0,0 +1,399 @@
+//! Candidate Pipeline
+//!
+//! Every pending transaction enters the pipeline as a [Candidate] stamped with the monotonic time
+//! it was first seen. A sandwich priced against reserves from an older block, or one that took too
+//! long to evaluate, is dropped by the [StalenessGuard] instead of being submitted stale.
+//!
+//! Candidates are routed by their target address through a [RoutingTable]. Sandwichable targets
+//! have a scorer decoding the call, other strategies get their candidates diverted onto their own
+//! channel, and candidates to any other target are dropped with a single map lookup.
+//!
+//! Ingestion never waits on evaluation. Candidates passing the pre-filter are scored and held in a
+//! bounded [SheddingQueue], which sheds the lowest scored candidates once evaluation falls behind
+//! and hands out the highest scored first. Swaps from senders with a history of profitable
+//! sandwiches are boosted by their [crate::profiles] record, and swaps into recently launched
+//! tokens by [crate::launches]. Scores are then weighted by how often bundles around swaps of the
+//! same size land, from the [crate::ev] inclusion model.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use tokio::sync::{mpsc, watch, Notify};
+
+use crate::{
+    abi::SwapExactEthForTokensView, allowances, ev, instances, launches, metrics, profiles,
+    supervisor,
+};
+
+/// The default number of candidates waiting for evaluation
+pub const DEFAULT_EVAL_QUEUE_SIZE: usize = 256;
+
+/// The default ingestion channel size
+pub const DEFAULT_INGEST_QUEUE_SIZE: usize = 4_096;
+
+/// The default evaluation budget, in milliseconds
+pub const DEFAULT_CANDIDATE_BUDGET_MS: u64 = 800;
+
+/// The default time without a new block before the block subscription is restarted, in seconds
+pub const DEFAULT_BLOCK_STALL_SECS: u64 = 60;
+
+/// A pending transaction moving through the pipeline
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// The pending transaction
+    pub tx: Transaction,
+    /// When the transaction was first seen
+    pub received_at: Instant,
+    /// How often the candidate was requeued to be rebuilt
+    pub rebuilds: u32,
+}
+
+impl Candidate {
+    /// Stamps a transaction received now
+    pub fn new(tx: Transaction) -> Self {
+        Self {
+            tx,
+            received_at: Instant::now(),
+            rebuilds: 0,
+        }
+    }
+
+    /// Restamps the candidate to be evaluated again, counting the rebuild
+    pub fn rebuild(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            received_at: Instant::now(),
+            rebuilds: self.rebuilds + 1,
+        }
+    }
+}
+
+/// Why a candidate went stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Staleness {
+    /// Evaluation took longer than the budget
+    BudgetExceeded(Duration),
+    /// A block arrived after the reserves were read
+    NewBlock {
+        /// The block the reserves were read at
+        snapshot: U64,
+        /// The latest block
+        current: U64,
+    },
+}
+
+impl Staleness {
+    /// The metric counting candidates dropped for this reason
+    pub fn metric(&self) -> &'static str {
+        match self {
+            Staleness::BudgetExceeded(_) => "stale_budget_exceeded",
+            Staleness::NewBlock { .. } => "stale_new_block",
+        }
+    }
+}
+
+/// Drops candidates that are too old or priced against an outdated block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StalenessGuard {
+    /// The total time a candidate may spend in the pipeline
+    pub budget: Duration,
+}
+
+impl StalenessGuard {
+    /// Creates a guard with a budget in milliseconds
+    pub fn new(budget_ms: u64) -> Self {
+        Self {
+            budget: Duration::from_millis(budget_ms),
+        }
+    }
+
+    /// Checks a candidate whose reserves were read at `snapshot` against the latest block
+    pub fn check(
+        &self,
+        candidate: &Candidate,
+        snapshot: U64,
+        current: U64,
+        now: Instant,
+    ) -> Result<(), Staleness> {
+        let elapsed = now.saturating_duration_since(candidate.received_at);
+        if elapsed > self.budget {
+            return Err(Staleness::BudgetExceeded(elapsed));
+        }
+        if current != snapshot {
+            return Err(Staleness::NewBlock { snapshot, current });
+        }
+        Ok(())
+    }
+}
+
+/// Follows the chain head in the background, starting from `start`
+///
+/// The receiver always holds the latest block number, so checking it costs no requests. The
+/// subscription is supervised as `block_clock`, and resubscribed if it ends or no block arrives for
+/// `stall_after`.
+pub fn watch_blocks(
+    client: Arc<Provider<Ws>>,
+    start: U64,
+    stall_after: Duration,
+) -> watch::Receiver<U64> {
+    let (sender, receiver) = watch::channel(start);
+    let sender = Arc::new(sender);
+    supervisor::supervise_task("block_clock", Some(stall_after), move |heartbeat| {
+        let client = client.clone();
+        let sender = sender.clone();
+        async move {
+            let mut blocks = client
+                .subscribe_blocks()
+                .await
+                .map_err(|e| eyre::eyre!("Failed to subscribe to blocks: {:?}", e))?;
+            while let Some(block) = blocks.next().await {
+                heartbeat.beat();
+                if let Some(number) = block.number {
+                    if sender.send(number).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            eyre::bail!("Block subscription ended")
+        }
+    });
+    receiver
+}
+
+/// Scores a pending transaction for evaluation order, `None` if it can't be sandwiched
+///
+/// Only swaps through `router` that decode are kept. Larger swaps move the pool further and leave
+/// more to sandwich, so the swap value is the score.
+pub fn prefilter_score(tx: &Transaction, router: Address) -> Option<U256> {
+    if tx.to != Some(router) {
+        return None;
+    }
+    score_v2_swap(tx)
+}
+
+/// Scores a Uniswap V2 router swap by its value, `None` if it doesn't decode
+pub fn score_v2_swap(tx: &Transaction) -> Option<U256> {
+    SwapExactEthForTokensView::parse(tx.input.as_ref()).ok()?;
+    Some(tx.value)
+}
+
+/// Decodes and scores a candidate for the sandwich queue, `None` to drop it
+pub type Scorer = fn(&Transaction) -> Option<U256>;
+
+/// Where candidates to a target address go
+#[derive(Debug, Clone)]
+pub enum Route {
+    /// Scored for the sandwich queue
+    Sandwich(Scorer),
+    /// Handed to another strategy's channel
+    Divert(mpsc::Sender<Candidate>),
+}
+
+/// Routes candidates by their target address
+///
+/// Candidates to a target without a route, including contract creations, are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct RoutingTable {
+    routes: HashMap<Address, Route>,
+}
+
+impl RoutingTable {
+    /// Creates a table without routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes candidates to `target` through `scorer` into the sandwich queue
+    pub fn sandwich(&mut self, target: Address, scorer: Scorer) -> &mut Self {
+        self.routes.insert(target, Route::Sandwich(scorer));
+        self
+    }
+
+    /// Diverts candidates to any of `targets` onto a channel of `capacity` for another strategy
+    ///
+    /// A later route for the same target replaces an earlier one.
+    pub fn divert(&mut self, targets: &[Address], capacity: usize) -> mpsc::Receiver<Candidate> {
+        let (sender, receiver) = mpsc::channel(capacity);
+        for target in targets {
+            self.routes.insert(*target, Route::Divert(sender.clone()));
+        }
+        receiver
+    }
+
+    /// The route of a transaction, if its target has one
+    pub fn route(&self, tx: &Transaction) -> Option<&Route> {
+        self.routes.get(&tx.to?)
+    }
+
+    /// The number of routed targets
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Whether no target is routed
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+/// The token a pending swap buys from its first pair, if it decodes
+pub fn swap_token(tx: &Transaction) -> Option<Address> {
+    SwapExactEthForTokensView::parse(tx.input.as_ref())
+        .ok()?
+        .path_at(1)
+}
+
+/// A bounded queue handing out the highest scored candidate first
+#[derive(Debug)]
+pub struct SheddingQueue {
+    capacity: usize,
+    sequence: u64,
+    // Ties on score are broken by arrival, newest first
+    candidates: BTreeMap<(U256, u64), Candidate>,
+}
+
+impl SheddingQueue {
+    /// Creates a queue holding up to `capacity` candidates
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            sequence: 0,
+            candidates: BTreeMap::new(),
+        }
+    }
+
+    /// Queues a candidate, returning the candidate shed to make room, if any
+    ///
+    /// When full, the lowest scored of the queued candidates and the new one is shed.
+    pub fn push(&mut self, candidate: Candidate, score: U256) -> Option<Candidate> {
+        self.sequence += 1;
+        if self.candidates.len() >= self.capacity {
+            let lowest = *self.candidates.keys().next()?;
+            if score <= lowest.0 {
+                return Some(candidate);
+            }
+            self.candidates.insert((score, self.sequence), candidate);
+            return self.candidates.remove(&lowest);
+        }
+        self.candidates.insert((score, self.sequence), candidate);
+        None
+    }
+
+    /// Takes the highest scored candidate
+    pub fn pop(&mut self) -> Option<Candidate> {
+        let highest = *self.candidates.keys().next_back()?;
+        self.candidates.remove(&highest)
+    }
+
+    /// The number of queued candidates
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// Whether no candidates are queued
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
+
+/// The shared end of a [SheddingQueue] the evaluation loop waits on
+#[derive(Debug)]
+pub struct EvalQueue {
+    queue: Mutex<SheddingQueue>,
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+impl EvalQueue {
+    /// Waits for the highest scored candidate, or `None` once ingestion ended and it's drained
+    pub async fn next(&self) -> Option<Candidate> {
+        loop {
+            let notified = self.notify.notified();
+            if let Some(candidate) = self.queue.lock().unwrap_or_else(|e| e.into_inner()).pop() {
+                return Some(candidate);
+            }
+            if self.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            notified.await;
+        }
+    }
+
+    /// Queues a candidate for evaluation again, returning whether it wasn't shed
+    pub fn requeue(&self, candidate: Candidate, score: U256) -> bool {
+        let hash = candidate.tx.hash;
+        let shed = self
+            .queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(candidate, score);
+        self.notify.notify_one();
+        shed.map_or(true, |shed| shed.tx.hash != hash)
+    }
+}
+
+/// Routes ingested candidates, scoring sandwichable ones into a shedding queue for evaluation
+///
+/// Scores are boosted for senders whose swaps were profitably sandwiched before, and for swaps
+/// into recently launched tokens, then weighted by the inclusion rate of swaps of the same size.
+/// Diverted candidates are dropped rather than waited on when their strategy falls behind, counted
+/// in the `pipeline_divert_dropped` metric.
+///
+/// Shed candidates are counted in the `pipeline_shed` metric, and `pipeline_queued` counts those
+/// that were queued.
+pub fn shed_into_queue(
+    mut ingest: mpsc::Receiver<Candidate>,
+    routes: RoutingTable,
+    capacity: usize,
+) -> Arc<EvalQueue> {
+    let queue = Arc::new(EvalQueue {
+        queue: Mutex::new(SheddingQueue::new(capacity)),
+        notify: Notify::new(),
+        closed: AtomicBool::new(false),
+    });
+    let producer = queue.clone();
+    instances::spawn(async move {
+        while let Some(candidate) = ingest.recv().await {
+            // Approvals go to any token, so they're tracked before routing drops them
+            allowances::observe_approval(&candidate.tx);
+            let score = match routes.route(&candidate.tx) {
+                Some(Route::Sandwich(scorer)) => match scorer(&candidate.tx) {
+                    Some(s) => profiles::profiles().boost(&candidate.tx.from, s),
+                    None => continue,
+                },
+                Some(Route::Divert(strategy)) => {
+                    if strategy.try_send(candidate).is_err() {
+                        metrics::increment("pipeline_divert_dropped");
+                    }
+                    continue;
+                }
+                None => continue,
+            };
+            let score =
+                launches::launches().boost(swap_token(&candidate.tx), score, Instant::now());
+            let score = ev::inclusion_model().weigh_score(candidate.tx.value, score);
+            let shed = producer
+                .queue
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(candidate, score);
+            metrics::increment("pipeline_queued");
+            if let Some(shed) = shed {
+                metrics::increment("pipeline_shed");
+                tracing::debug!("[PIPELINE] Shed {:?} under load", shed.tx.hash);
+            }
+            producer.notify.notify_one();
+        }
+        tracing::error!("[PIPELINE] Ingestion ended");
+        producer.closed.store(true, Ordering::Release);
+        producer.notify.notify_waiters();
+    });
+    queue
+}
@@ -0,0 +1,386 @@
+//! Signed Sandwich Plans
+//!
+//! In a split deployment the detector, running next to the node, decides what to sandwich, and a
+//! remote executor process holding the hot wallet signs the legs. A [RemotePlan] wraps the priced
+//! [SandwichPlan] with what the executor needs to build the legs: their fees, the raw victim and
+//! the block the bundle targets, but no calldata, which the executor encodes itself.
+//!
+//! The detector signs each plan with its own key into a [SignedPlan], an EIP-191 signature of the
+//! plan's exact json, and never signs or streams the legs itself. Before the hot wallet signs
+//! anything, a [PlanVerifier] checks the plan was signed by a trusted detector, for this chain and
+//! executor, that it hasn't expired or been seen before, that its legs are a consistent sandwich
+//! returning at least the WETH they spend, and that it stays under the executor's own frontrun, fee
+//! and bribe limits. A leaked plan stream or a compromised link can't get the executor to sign
+//! anything else, and a faulty detector can't get it to sign a losing sandwich or drain it in fees.
+//!
+//! Seen plan ids are only held in memory, so a restarted executor would accept a plan it already
+//! executed until the plan expires, at most [PLAN_TTL_MS] later. The `execute-plans` consumer only
+//! reads plans streamed after it connects, which a restart doesn't replay.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    evaluate::SandwichPlan,
+    opportunity::unix_millis,
+    transactions::{LegFees, NonceSlot, TxFactory},
+};
+
+/// The version of the plan format
+pub const PLAN_VERSION: u8 = 2;
+
+/// How long a plan stays valid after it's signed, in milliseconds
+pub const PLAN_TTL_MS: u64 = 12_000;
+
+/// A priced sandwich for a remote executor to sign
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RemotePlan {
+    /// The plan format version
+    pub version: u8,
+    /// A random id, so each plan is only executed once
+    pub id: H256,
+    /// The chain the legs are signed for
+    pub chain_id: u64,
+    /// The executor contract the legs call
+    pub executor: Address,
+    /// The block the bundle targets, the legs are built for the one after it
+    pub target_block: U64,
+    /// When the plan expires, in milliseconds since the unix epoch
+    pub expires_at: u64,
+    /// The priced sandwich and its legs
+    pub sandwich: SandwichPlan,
+    /// The signed victim transaction
+    pub victim: Bytes,
+    /// The frontrun's fees
+    pub frontrun_fees: LegFees,
+    /// The backrun's fees, carrying the bribe
+    pub backrun_fees: LegFees,
+    /// The executor's balance of the backrun's input token before the frontrun
+    pub held: U256,
+}
+
+impl RemotePlan {
+    /// Plans a sandwich for `executor`, valid for [PLAN_TTL_MS] from now
+    pub fn new(
+        chain_id: u64,
+        executor: Address,
+        target_block: U64,
+        sandwich: SandwichPlan,
+        victim: Bytes,
+        fees: (LegFees, LegFees),
+    ) -> Self {
+        Self {
+            version: PLAN_VERSION,
+            id: H256::random(),
+            chain_id,
+            executor,
+            target_block,
+            expires_at: unix_millis().saturating_add(PLAN_TTL_MS),
+            sandwich,
+            victim,
+            frontrun_fees: fees.0,
+            backrun_fees: fees.1,
+            held: U256::zero(),
+        }
+    }
+
+    /// Guards the backrun against a short frontrun, `held` being the executor's prior balance
+    pub fn with_held(mut self, held: U256) -> Self {
+        self.held = held;
+        self
+    }
+
+    /// Signs the plan with the detector key
+    pub async fn sign(&self, detector: &LocalWallet) -> Result<SignedPlan> {
+        let plan = serde_json::to_string(self)?;
+        let signature = detector
+            .sign_message(plan.as_bytes())
+            .await
+            .map_err(|e| eyre::eyre!("Failed to sign sandwich plan: {:?}", e))?;
+        Ok(SignedPlan { plan, signature })
+    }
+
+    /// The bribe the backrun pays at its gas limit
+    pub fn bribe(&self) -> U256 {
+        self.backrun_fees
+            .max_priority_fee_per_gas
+            .saturating_mul(self.backrun_fees.gas_limit)
+    }
+
+    /// The WETH the legs spend, the frontrun's input
+    pub fn weth_spent(&self) -> U256 {
+        self.sandwich.frontrun.amount_in
+    }
+
+    /// Whether the plan is a sandwich of its victim
+    ///
+    /// The legs must buy then sell the sandwiched token through the sandwiched pair, and the raw
+    /// victim must hash to the planned victim.
+    pub fn is_consistent(&self) -> bool {
+        let SandwichPlan {
+            victim,
+            pair,
+            token,
+            frontrun,
+            backrun,
+            ..
+        } = &self.sandwich;
+        frontrun.pair == *pair
+            && backrun.pair == *pair
+            && frontrun.token_out == *token
+            && backrun.token_in == *token
+            && backrun.token_out == frontrun.token_in
+            && backrun.amount_in <= frontrun.amount_out
+            && H256::from(ethers::utils::keccak256(&self.victim)) == *victim
+    }
+}
+
+/// A plan with the detector's signature
+///
+/// The plan is kept as the exact json that was signed, and only parsed once verified.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedPlan {
+    /// The plan's json
+    pub plan: String,
+    /// The detector's EIP-191 signature of the json
+    pub signature: Signature,
+}
+
+impl SignedPlan {
+    /// The address that signed the plan
+    pub fn signer(&self) -> Result<Address> {
+        self.signature
+            .recover(self.plan.as_bytes())
+            .map_err(|e| eyre::eyre!("Failed to recover plan signer: {:?}", e))
+    }
+}
+
+/// Why a plan was refused
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum PlanRejection {
+    /// The signature doesn't recover to an address
+    #[error("plan signature is invalid")]
+    BadSignature,
+    /// The signer isn't a trusted detector
+    #[error("plan is signed by untrusted {0:?}")]
+    Untrusted(Address),
+    /// The signed json isn't a plan
+    #[error("plan is malformed: {0}")]
+    Malformed(String),
+    /// The plan is in another format version
+    #[error("plan version {0} isn't {PLAN_VERSION}")]
+    Version(u8),
+    /// The plan is for another chain or executor
+    #[error("plan is for executor {executor:?} on chain {chain_id}")]
+    WrongTarget {
+        /// The plan's chain
+        chain_id: u64,
+        /// The plan's executor
+        executor: Address,
+    },
+    /// The plan expired
+    #[error("plan expired at {0}")]
+    Expired(u64),
+    /// The plan was already verified once
+    #[error("plan {0:?} was already seen")]
+    Replayed(H256),
+    /// The legs don't form a sandwich of the victim
+    #[error("plan legs are inconsistent")]
+    Inconsistent,
+    /// The frontrun is larger than the executor allows
+    #[error("plan frontruns {weth_in}, over the limit of {max}")]
+    OverLimit {
+        /// The frontrun input
+        weth_in: U256,
+        /// The executor's limit
+        max: U256,
+    },
+    /// The backrun returns less WETH than the legs spend
+    #[error("plan spends {weth_spent} to get back {weth_out}")]
+    Unprofitable {
+        /// The frontrun input
+        weth_spent: U256,
+        /// The backrun output
+        weth_out: U256,
+    },
+    /// A leg pays more per gas than the executor allows
+    #[error("plan pays {max_fee_per_gas} per gas, over the limit of {max}")]
+    FeeOverLimit {
+        /// The leg's max fee per gas
+        max_fee_per_gas: U256,
+        /// The executor's limit
+        max: U256,
+    },
+    /// The backrun bribes more than the executor allows
+    #[error("plan bribes {bribe}, over the limit of {max}")]
+    BribeOverLimit {
+        /// The bribe at the backrun's gas limit
+        bribe: U256,
+        /// The executor's limit
+        max: U256,
+    },
+}
+
+impl PlanRejection {
+    /// The metric counting refusals for this reason
+    pub fn metric(&self) -> &'static str {
+        match self {
+            PlanRejection::BadSignature | PlanRejection::Untrusted(_) => "plan_untrusted",
+            PlanRejection::Malformed(_) | PlanRejection::Version(_) => "plan_malformed",
+            PlanRejection::WrongTarget { .. } => "plan_wrong_target",
+            PlanRejection::Expired(_) => "plan_expired",
+            PlanRejection::Replayed(_) => "plan_replayed",
+            PlanRejection::Inconsistent
+            | PlanRejection::OverLimit { .. }
+            | PlanRejection::Unprofitable { .. }
+            | PlanRejection::FeeOverLimit { .. }
+            | PlanRejection::BribeOverLimit { .. } => "plan_refused",
+        }
+    }
+}
+
+/// Checks plans on the executor side before the hot wallet signs them
+#[derive(Debug)]
+pub struct PlanVerifier {
+    trusted: Vec<Address>,
+    chain_id: u64,
+    executor: Address,
+    max_weth_in: Option<U256>,
+    max_fee_per_gas: Option<U256>,
+    max_bribe: Option<U256>,
+    /// The ids of verified plans, with when they expire
+    seen: Mutex<HashMap<H256, u64>>,
+}
+
+impl PlanVerifier {
+    /// Trusts plans signed by any of `trusted` for `executor` on `chain_id`
+    pub fn new(trusted: Vec<Address>, chain_id: u64, executor: Address) -> Self {
+        Self {
+            trusted,
+            chain_id,
+            executor,
+            max_weth_in: None,
+            max_fee_per_gas: None,
+            max_bribe: None,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Refuses plans frontrunning with more than `max_weth_in`
+    pub fn with_max_weth_in(mut self, max_weth_in: Option<U256>) -> Self {
+        self.max_weth_in = max_weth_in;
+        self
+    }
+
+    /// Refuses plans with a leg paying more than `max_fee_per_gas`
+    pub fn with_max_fee_per_gas(mut self, max_fee_per_gas: Option<U256>) -> Self {
+        self.max_fee_per_gas = max_fee_per_gas;
+        self
+    }
+
+    /// Refuses plans whose backrun bribes more than `max_bribe` at its gas limit
+    pub fn with_max_bribe(mut self, max_bribe: Option<U256>) -> Self {
+        self.max_bribe = max_bribe;
+        self
+    }
+
+    /// Verifies a plan at `now`, in milliseconds since the unix epoch, returning it if trusted
+    ///
+    /// Each plan is only returned once.
+    pub fn verify(&self, signed: &SignedPlan, now: u64) -> Result<RemotePlan, PlanRejection> {
+        let signer = signed.signer().map_err(|_| PlanRejection::BadSignature)?;
+        if !self.trusted.contains(&signer) {
+            return Err(PlanRejection::Untrusted(signer));
+        }
+        let plan: RemotePlan = serde_json::from_str(&signed.plan)
+            .map_err(|e| PlanRejection::Malformed(e.to_string()))?;
+        if plan.version != PLAN_VERSION {
+            return Err(PlanRejection::Version(plan.version));
+        }
+        if plan.chain_id != self.chain_id || plan.executor != self.executor {
+            return Err(PlanRejection::WrongTarget {
+                chain_id: plan.chain_id,
+                executor: plan.executor,
+            });
+        }
+        if now > plan.expires_at {
+            return Err(PlanRejection::Expired(plan.expires_at));
+        }
+        if !plan.is_consistent() {
+            return Err(PlanRejection::Inconsistent);
+        }
+        if plan.sandwich.backrun.amount_out < plan.weth_spent() {
+            return Err(PlanRejection::Unprofitable {
+                weth_spent: plan.weth_spent(),
+                weth_out: plan.sandwich.backrun.amount_out,
+            });
+        }
+        if let Some(max) = self.max_weth_in {
+            if plan.sandwich.frontrun.amount_in > max {
+                return Err(PlanRejection::OverLimit {
+                    weth_in: plan.sandwich.frontrun.amount_in,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = self.max_fee_per_gas {
+            let max_fee_per_gas = plan
+                .frontrun_fees
+                .max_fee_per_gas
+                .max(plan.backrun_fees.max_fee_per_gas);
+            if max_fee_per_gas > max {
+                return Err(PlanRejection::FeeOverLimit {
+                    max_fee_per_gas,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = self.max_bribe {
+            if plan.bribe() > max {
+                return Err(PlanRejection::BribeOverLimit {
+                    bribe: plan.bribe(),
+                    max,
+                });
+            }
+        }
+
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, expires_at| *expires_at >= now);
+        if seen.insert(plan.id, plan.expires_at).is_some() {
+            return Err(PlanRejection::Replayed(plan.id));
+        }
+        Ok(plan)
+    }
+}
+
+/// Builds and signs a verified plan's legs, returning the frontrun, victim and backrun
+///
+/// The backrun uses the nonce after the frontrun's, in the same wallet.
+pub async fn sign_plan(
+    tx_factory: &TxFactory,
+    plan: &RemotePlan,
+    nonce: NonceSlot,
+) -> Result<Vec<Bytes>> {
+    let block = plan.target_block + 1;
+    let legs = &plan.sandwich;
+    let frontrun = tx_factory.frontrun(&legs.frontrun, nonce, &plan.frontrun_fees, block)?;
+    let backrun = tx_factory.backrun_checked(
+        &legs.backrun,
+        plan.held,
+        NonceSlot {
+            wallet: nonce.wallet,
+            nonce: nonce.nonce + 1,
+        },
+        &plan.backrun_fees,
+        block,
+    )?;
+    Ok(vec![
+        tx_factory.sign(&frontrun).await?,
+        plan.victim.clone(),
+        tx_factory.sign(&backrun).await?,
+    ])
+}
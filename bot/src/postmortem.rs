@@ -0,0 +1,285 @@
+//! Competition Postmortems
+//!
+//! When a bundle doesn't land but its victim was mined anyway, another searcher usually got there
+//! first. The victim's block is searched for the sandwich around it: the nearest earlier swap on
+//! the same pair in the victim's direction and the nearest later one in the other direction, both
+//! sent to the same contract. The competitor's contract, sender and priority fees are recorded in
+//! the [CompetitionBook]. Our backrun's priority fee is raised to the median fee that beat us, up
+//! to [MAX_CALIBRATED_BRIBE_BPS] of the revenue, see [CompetitionBook::calibrate].
+//!
+//! Only priority fees are visible from the block. Competitors paying the builder with a coinbase
+//! transfer from their contract bribe more than is recorded.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Mutex, OnceLock},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::events::{V2Swap, SWAP_TOPIC},
+    metrics,
+};
+
+/// How many sightings the [CompetitionBook] keeps
+pub const MAX_SIGHTINGS: usize = 1_024;
+
+/// The percentile of the priority fees that beat us bribes are raised to
+pub const COMPETITIVE_FEE_PERCENTILE: u64 = 50;
+
+/// The most of the revenue after the frontrun's gas a calibrated bribe may pay, in bips
+pub const MAX_CALIBRATED_BRIBE_BPS: u64 = 9_000;
+
+/// A sandwich found in a mined block
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandwichSighting {
+    /// The block the sandwich was mined in
+    pub block: U64,
+    /// The sandwiched pair
+    pub pair: Address,
+    /// The victim transaction
+    pub victim: H256,
+    /// The competitor's frontrun
+    pub frontrun: H256,
+    /// The competitor's backrun
+    pub backrun: H256,
+    /// The competitor's searcher wallet, the backrun's sender
+    pub searcher: Address,
+    /// The competitor's contract both legs called
+    pub contract: Address,
+    /// The backrun's priority fee per gas
+    pub priority_fee_per_gas: U256,
+    /// The priority fees both legs paid, zero until their receipts are read
+    pub bribe: U256,
+    /// Our backrun's priority fee per gas, if we lost the victim to this sandwich
+    pub our_priority_fee_per_gas: Option<U256>,
+}
+
+/// The priority fee per gas a transaction paid on top of `base_fee`
+pub fn priority_fee_per_gas(tx: &Transaction, base_fee: U256) -> U256 {
+    match tx.max_fee_per_gas {
+        Some(max_fee) => tx
+            .max_priority_fee_per_gas
+            .unwrap_or_default()
+            .min(max_fee.saturating_sub(base_fee)),
+        None => tx.gas_price.unwrap_or_default().saturating_sub(base_fee),
+    }
+}
+
+/// Finds the sandwiches in a block from its transactions, in block order, and its V2 `Swap` logs
+///
+/// A victim may be counted in more than one sandwich when several competitors surround it.
+pub fn find_sandwiches(
+    block: U64,
+    txs: &[Transaction],
+    logs: &[Log],
+    base_fee: U256,
+) -> Vec<SandwichSighting> {
+    let mut by_pair = BTreeMap::<Address, Vec<(usize, V2Swap)>>::new();
+    for log in logs {
+        let index = match log.transaction_index {
+            Some(i) => i.as_usize(),
+            None => continue,
+        };
+        if let Ok(swap) = V2Swap::decode(log) {
+            by_pair.entry(swap.pair).or_default().push((index, swap));
+        }
+    }
+
+    let mut sightings = Vec::new();
+    for (pair, mut swaps) in by_pair {
+        swaps.sort_by_key(|(index, _)| *index);
+        for (position, (victim_index, victim_swap)) in swaps.iter().enumerate() {
+            let victim = match txs.get(*victim_index) {
+                Some(tx) => tx,
+                None => continue,
+            };
+            let direction = victim_swap.zero_for_one();
+
+            // The nearest earlier swap the same way, from a contract the victim didn't call
+            let frontrun = swaps[..position].iter().rev().find_map(|(index, swap)| {
+                let tx = txs.get(*index)?;
+                (*index < *victim_index
+                    && swap.zero_for_one() == direction
+                    && tx.to.is_some()
+                    && tx.to != victim.to)
+                    .then_some(tx)
+            });
+            let frontrun = match frontrun {
+                Some(tx) => tx,
+                None => continue,
+            };
+
+            // The nearest later swap the other way through the same contract
+            let backrun = swaps[position + 1..].iter().find_map(|(index, swap)| {
+                let tx = txs.get(*index)?;
+                (*index > *victim_index && swap.zero_for_one() != direction && tx.to == frontrun.to)
+                    .then_some(tx)
+            });
+            let backrun = match backrun {
+                Some(tx) => tx,
+                None => continue,
+            };
+
+            sightings.push(SandwichSighting {
+                block,
+                pair,
+                victim: victim.hash,
+                frontrun: frontrun.hash,
+                backrun: backrun.hash,
+                searcher: backrun.from,
+                contract: backrun.to.unwrap_or_default(),
+                priority_fee_per_gas: priority_fee_per_gas(backrun, base_fee),
+                ..Default::default()
+            });
+        }
+    }
+    sightings
+}
+
+/// Finds the sandwiches in a mined block, with the priority fees their legs paid
+pub async fn analyze_block<M: Middleware>(client: &M, block: U64) -> Result<Vec<SandwichSighting>> {
+    let mined = client
+        .get_block_with_txs(block)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch block {}: {:?}", block, e))?
+        .ok_or_else(|| eyre::eyre!("Block {} not found", block))?;
+    let filter = Filter::new()
+        .from_block(block)
+        .to_block(block)
+        .topic0(H256::from(SWAP_TOPIC));
+    let logs = client
+        .get_logs(&filter)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch swaps of block {}: {:?}", block, e))?;
+    let base_fee = mined.base_fee_per_gas.unwrap_or_default();
+
+    let mut sightings = find_sandwiches(block, &mined.transactions, &logs, base_fee);
+    for sighting in &mut sightings {
+        for hash in [sighting.frontrun, sighting.backrun] {
+            let tx = mined.transactions.iter().find(|tx| tx.hash == hash);
+            let receipt = client
+                .get_transaction_receipt(hash)
+                .await
+                .map_err(|e| eyre::eyre!("Failed to fetch receipt {:?}: {:?}", hash, e))?;
+            if let (Some(tx), Some(gas_used)) = (tx, receipt.and_then(|r| r.gas_used)) {
+                sighting.bribe = sighting
+                    .bribe
+                    .saturating_add(gas_used.saturating_mul(priority_fee_per_gas(tx, base_fee)));
+            }
+        }
+    }
+    Ok(sightings)
+}
+
+/// Looks for whoever sandwiched a victim we lost, recording them in [competition]
+///
+/// Returns `None` if the victim wasn't mined, or was mined without being sandwiched. Sightings are
+/// counted in the `postmortem_outbid` metric.
+pub async fn investigate_loss<M: Middleware>(
+    client: &M,
+    victim: H256,
+    our_priority_fee_per_gas: U256,
+) -> Result<Option<SandwichSighting>> {
+    let receipt = client
+        .get_transaction_receipt(victim)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch receipt {:?}: {:?}", victim, e))?;
+    let block = match receipt.and_then(|r| r.block_number) {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+    let sighting = analyze_block(client, block)
+        .await?
+        .into_iter()
+        .find(|s| s.victim == victim)
+        .map(|s| SandwichSighting {
+            our_priority_fee_per_gas: Some(our_priority_fee_per_gas),
+            ..s
+        });
+    if let Some(sighting) = &sighting {
+        metrics::increment("postmortem_outbid");
+        competition().record(sighting.clone());
+    }
+    Ok(sighting)
+}
+
+/// The competitor sandwiches seen, most recent last
+#[derive(Debug, Default)]
+pub struct CompetitionBook {
+    sightings: Mutex<VecDeque<SandwichSighting>>,
+}
+
+impl CompetitionBook {
+    /// Creates an empty book
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a sighting, forgetting the oldest past [MAX_SIGHTINGS]
+    pub fn record(&self, sighting: SandwichSighting) {
+        let mut sightings = self.sightings.lock().unwrap_or_else(|e| e.into_inner());
+        sightings.push_back(sighting);
+        while sightings.len() > MAX_SIGHTINGS {
+            sightings.pop_front();
+        }
+    }
+
+    /// The recorded sightings, oldest first
+    pub fn sightings(&self) -> Vec<SandwichSighting> {
+        self.sightings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// How many victims each competitor contract took from us
+    pub fn losses_by_contract(&self) -> BTreeMap<Address, u64> {
+        let mut losses = BTreeMap::new();
+        for sighting in self
+            .sightings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            *losses.entry(sighting.contract).or_default() += 1;
+        }
+        losses
+    }
+
+    /// The winning backrun priority fee per gas at a percentile, `None` before any sightings
+    pub fn priority_fee_percentile(&self, percentile: u64) -> Option<U256> {
+        let mut fees = self
+            .sightings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|s| s.priority_fee_per_gas)
+            .collect::<Vec<_>>();
+        fees.sort_unstable();
+        let last = fees.len().checked_sub(1)?;
+        fees.get(last * percentile.min(100) as usize / 100).copied()
+    }
+
+    /// Raises a priority fee per gas to the [COMPETITIVE_FEE_PERCENTILE] of those that beat us,
+    /// without exceeding `cap`
+    ///
+    /// Fees over the cap, or above what beat us, are left as they are.
+    pub fn calibrate(&self, priority_fee_per_gas: U256, cap: U256) -> U256 {
+        match self.priority_fee_percentile(COMPETITIVE_FEE_PERCENTILE) {
+            Some(winning) => priority_fee_per_gas.max(winning.min(cap)),
+            None => priority_fee_per_gas,
+        }
+    }
+}
+
+/// Returns the process-wide competition book
+pub fn competition() -> &'static CompetitionBook {
+    static BOOK: OnceLock<CompetitionBook> = OnceLock::new();
+    BOOK.get_or_init(CompetitionBook::new)
+}
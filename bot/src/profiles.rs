@@ -0,0 +1,175 @@
+//! Victim Profiles
+//!
+//! Some senders are sandwiched again and again: traders with loose slippage settings and bots
+//! that never tighten them. Each sender's sandwiches and realized profit are recorded in a
+//! [ProfileBook], and senders with landed sandwiches get their swaps boosted in the evaluation
+//! queue. Profiles can be saved to and loaded from a json file so they survive restarts.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::opportunity::unix_millis;
+
+/// The score boost per profitable sandwich of a sender, in bips
+pub const BOOST_PER_LANDED_BPS: u64 = 2_500;
+
+/// The largest score boost a sender can earn, in bips
+pub const MAX_BOOST_BPS: u64 = 20_000;
+
+/// How many senders are profiled, the least recently sandwiched being evicted first
+pub const MAX_PROFILES: usize = 65_536;
+
+/// What's known about a victim sender
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VictimProfile {
+    /// How many of the sender's swaps were sandwiched
+    pub shipped: u32,
+    /// How many of those landed with a profit
+    pub landed: u32,
+    /// The total realized profit from the sender's swaps
+    pub profit: U256,
+    /// When the sender was last sandwiched, in milliseconds since the unix epoch
+    pub last_seen: u64,
+}
+
+impl VictimProfile {
+    /// The score boost the sender has earned, in bips
+    pub fn boost_bps(&self) -> u64 {
+        (self.landed as u64 * BOOST_PER_LANDED_BPS).min(MAX_BOOST_BPS)
+    }
+}
+
+/// Makes room for a new sender by evicting the least recently sandwiched eighth of the profiles
+///
+/// Evicting in bulk keeps the sort amortized across many inserts.
+fn make_room(profiles: &mut HashMap<Address, VictimProfile>, sender: &Address) {
+    if profiles.len() < MAX_PROFILES || profiles.contains_key(sender) {
+        return;
+    }
+    let mut by_age = profiles
+        .iter()
+        .map(|(sender, profile)| (profile.last_seen, *sender))
+        .collect::<Vec<_>>();
+    by_age.sort_unstable();
+    for (_, sender) in by_age.into_iter().take(MAX_PROFILES / 8) {
+        profiles.remove(&sender);
+    }
+}
+
+/// Victim profiles by sender, up to [MAX_PROFILES]
+#[derive(Debug, Default)]
+pub struct ProfileBook {
+    profiles: Mutex<HashMap<Address, VictimProfile>>,
+    // Held across a save, so concurrent saves can't interleave or land out of order
+    saving: Mutex<()>,
+}
+
+impl ProfileBook {
+    /// Records a shipped sandwich of a sender's swap
+    pub fn record_shipped(&self, sender: Address) {
+        let mut profiles = self.profiles.lock().unwrap_or_else(|e| e.into_inner());
+        make_room(&mut profiles, &sender);
+        let profile = profiles.entry(sender).or_default();
+        profile.shipped = profile.shipped.saturating_add(1);
+        profile.last_seen = unix_millis();
+    }
+
+    /// Records the realized profit of a landed sandwich of a sender's swap
+    pub fn record_profit(&self, sender: Address, profit: U256) {
+        if profit.is_zero() {
+            return;
+        }
+        let mut profiles = self.profiles.lock().unwrap_or_else(|e| e.into_inner());
+        make_room(&mut profiles, &sender);
+        let profile = profiles.entry(sender).or_default();
+        profile.landed = profile.landed.saturating_add(1);
+        profile.profit = profile.profit.saturating_add(profit);
+    }
+
+    /// Returns a sender's profile
+    pub fn get(&self, sender: &Address) -> Option<VictimProfile> {
+        self.profiles
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(sender)
+            .copied()
+    }
+
+    /// The number of profiled senders
+    pub fn len(&self) -> usize {
+        self.profiles
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// Whether no sender is profiled
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Boosts a sender's evaluation score by the boost they've earned
+    pub fn boost(&self, sender: &Address, score: U256) -> U256 {
+        match self.get(sender) {
+            Some(profile) => {
+                let boost = U256::from(10_000 + profile.boost_bps());
+                score.saturating_mul(boost) / 10_000
+            }
+            None => score,
+        }
+    }
+
+    /// Replaces the profiles with those in a json file, if it exists
+    ///
+    /// A file that doesn't parse, like one torn by a crash, is warned about and ignored.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(0);
+        }
+        let loaded: HashMap<Address, VictimProfile> = match serde_json::from_slice(&fs::read(path)?)
+        {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                tracing::warn!(
+                    "[PROFILES] Ignoring invalid victim profiles in {:?}: {:?}",
+                    path,
+                    e
+                );
+                HashMap::new()
+            }
+        };
+        let count = loaded.len();
+        *self.profiles.lock().unwrap_or_else(|e| e.into_inner()) = loaded;
+        Ok(count)
+    }
+
+    /// Writes the profiles to a json file, atomically replacing it
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let _saving = self.saving.lock().unwrap_or_else(|e| e.into_inner());
+        let json = {
+            let profiles = self.profiles.lock().unwrap_or_else(|e| e.into_inner());
+            serde_json::to_vec(&*profiles)?
+        };
+        let path = path.as_ref();
+        let mut tmp = PathBuf::from(path);
+        tmp.set_extension("tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Returns the global victim profiles
+pub fn profiles() -> &'static ProfileBook {
+    static PROFILES: OnceLock<ProfileBook> = OnceLock::new();
+    PROFILES.get_or_init(ProfileBook::default)
+}
@@ -1,7 +1,17 @@
 //! A relayer module
 
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
 use ethers::prelude::*;
 use ethers_flashbots::{BundleRequest, BundleTransaction, SimulatedBundle};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{metrics, mev_share::MevShareBundle};
 
 /// Helper function to help catch the various ways errors can be thrown from simulation
 /// This helper function is needed as simulation response has many ways where the
@@ -42,3 +52,407 @@ pub fn construct_bundle<T: Into<BundleTransaction>>(
     // Return the constructed bundle request
     Ok(bundle_request)
 }
+
+/// A bundle a relay accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentBundle {
+    /// The bundle hash, if the relay returned one
+    pub bundle_hash: Option<H256>,
+    /// The block the bundle targets
+    pub block: U64,
+}
+
+/// Simulates and sends bundles through a Flashbots-compatible relay, signed with the searcher
+/// identity
+///
+/// Requests go through the shared relay http client from [crate::transport::relay_http_client],
+/// where the `ethers-flashbots` middleware would open a client of its own.
+#[derive(Debug, Clone)]
+pub struct FlashbotsRelay {
+    /// The http client
+    pub http: reqwest::Client,
+    /// The relay url
+    pub url: Url,
+    /// The searcher identity requests are signed with
+    pub signer: LocalWallet,
+}
+
+impl FlashbotsRelay {
+    /// Creates a relay reached through `http`
+    pub fn new(http: reqwest::Client, url: Url, signer: LocalWallet) -> Self {
+        Self { http, url, signer }
+    }
+
+    /// Posts a signed json-rpc request, returning the result
+    async fn request<P: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        params: P,
+    ) -> eyre::Result<R> {
+        let body = serde_json::to_string(&serde_json::json!({
+            "id": 1,
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        }))?;
+        let signature = self
+            .signer
+            .sign_message(format!("0x{}", hex::encode(keccak256(body.as_bytes()))))
+            .await?;
+        let response = self
+            .http
+            .post(self.url.clone())
+            .header("Content-Type", "application/json")
+            .header(
+                "X-Flashbots-Signature",
+                format!("{:?}:0x{}", self.signer.address(), signature),
+            )
+            .body(body)
+            .send()
+            .await?;
+        // Keep the status as reqwest formats it, so [RelayError::classify] can match it
+        if let Err(e) = response.error_for_status_ref() {
+            let text = response.text().await.unwrap_or_default();
+            eyre::bail!("{} failed: {:?}: {}", method, e, text);
+        }
+        let mut response: serde_json::Value = response.json().await?;
+        if let Some(error) = response.get("error") {
+            eyre::bail!("{} error: {}", method, error);
+        }
+        Ok(serde_json::from_value(response["result"].take())?)
+    }
+
+    /// Simulates a bundle with `eth_callBundle`
+    pub async fn simulate_bundle(&self, bundle: &BundleRequest) -> eyre::Result<SimulatedBundle> {
+        if bundle.block().is_none() || bundle.simulation_block().is_none() {
+            eyre::bail!("Bundle is missing its target or simulation block");
+        }
+        self.request("eth_callBundle", [bundle]).await
+    }
+
+    /// Sends a bundle with `eth_sendBundle`
+    pub async fn send_bundle(&self, bundle: &BundleRequest) -> eyre::Result<SentBundle> {
+        let block = bundle
+            .block()
+            .ok_or_else(|| eyre::eyre!("Bundle is missing its target block"))?;
+        let response: Option<serde_json::Value> = self.request("eth_sendBundle", [bundle]).await?;
+        let bundle_hash = response
+            .as_ref()
+            .and_then(|r| r.get("bundleHash"))
+            .and_then(|h| serde_json::from_value(h.clone()).ok());
+        Ok(SentBundle { bundle_hash, block })
+    }
+
+    /// Sends a backrun of a shared transaction with `mev_sendBundle`
+    pub async fn send_mev_share_bundle(&self, bundle: &MevShareBundle) -> eyre::Result<SentBundle> {
+        let response: Option<serde_json::Value> = self.request("mev_sendBundle", [bundle]).await?;
+        let bundle_hash = response
+            .as_ref()
+            .and_then(|r| r.get("bundleHash"))
+            .and_then(|h| serde_json::from_value(h.clone()).ok());
+        Ok(SentBundle {
+            bundle_hash,
+            block: bundle.inclusion.block,
+        })
+    }
+}
+
+/// The default pause after a relay rate limits a request without saying for how long, in
+/// milliseconds
+pub const DEFAULT_RELAY_BACKOFF_MS: u64 = 1_000;
+
+/// The longest a relay is paused for after rate limiting, in milliseconds
+pub const MAX_RELAY_BACKOFF_MS: u64 = 30_000;
+
+/// How often a victim is rebuilt after its bundle's nonces went stale
+pub const MAX_REBUILDS: u32 = 1;
+
+/// Why a relay rejected a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayError {
+    /// The relay is throttling requests, optionally saying for how long
+    RateLimited {
+        /// How long to wait before the next request
+        retry_after: Option<Duration>,
+    },
+    /// A leg's nonce was already used on chain
+    NonceTooLow,
+    /// The relay already has the bundle or one of its transactions
+    AlreadyKnown,
+    /// The bundle exceeds the relay's size or gas limits
+    BundleTooLarge,
+    /// The relay simulated the bundle and it failed
+    SimulationFailed(String),
+    /// The relay couldn't be reached or answered with a server error
+    Unreachable(String),
+    /// Any other error
+    Other(String),
+}
+
+/// How to react to a [RelayError]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayReaction {
+    /// Stop sending to the relay for a while
+    Backoff(Duration),
+    /// Drop the bundle and rebuild it on fresh nonces
+    Rebuild,
+    /// Drop the bundle, the relay itself is fine
+    Drop,
+    /// Count the failure towards observer mode
+    Degrade,
+}
+
+impl RelayError {
+    /// Classifies a relay error message
+    ///
+    /// Status codes are only matched as reqwest formats them, so hashes in the message can't be
+    /// mistaken for one.
+    pub fn classify(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+        if has(&[
+            "status(429)",
+            "too many requests",
+            "rate limit",
+            "rate-limit",
+        ]) {
+            RelayError::RateLimited {
+                retry_after: retry_after(&lower),
+            }
+        } else if has(&["nonce too low", "nonce is too low", "invalid nonce"]) {
+            RelayError::NonceTooLow
+        } else if has(&["already known", "known transaction", "already imported"]) {
+            RelayError::AlreadyKnown
+        } else if has(&[
+            "status(413)",
+            "too large",
+            "oversized",
+            "exceeds block gas limit",
+        ]) {
+            RelayError::BundleTooLarge
+        } else if has(&["revert", "simulation", "out of gas", "insufficient funds"]) {
+            RelayError::SimulationFailed(message.to_string())
+        } else if has(&[
+            "connect",
+            "timed out",
+            "timeout",
+            "status(502)",
+            "status(503)",
+        ]) {
+            RelayError::Unreachable(message.to_string())
+        } else {
+            RelayError::Other(message.to_string())
+        }
+    }
+
+    /// Classifies any error by its debug representation
+    pub fn from_error<E: std::fmt::Debug>(error: &E) -> Self {
+        Self::classify(&format!("{:?}", error))
+    }
+
+    /// How the bot reacts to the error
+    pub fn reaction(&self) -> RelayReaction {
+        match self {
+            RelayError::RateLimited { retry_after } => RelayReaction::Backoff(
+                retry_after
+                    .unwrap_or(Duration::from_millis(DEFAULT_RELAY_BACKOFF_MS))
+                    .min(Duration::from_millis(MAX_RELAY_BACKOFF_MS)),
+            ),
+            RelayError::NonceTooLow => RelayReaction::Rebuild,
+            RelayError::AlreadyKnown
+            | RelayError::BundleTooLarge
+            | RelayError::SimulationFailed(_) => RelayReaction::Drop,
+            RelayError::Unreachable(_) | RelayError::Other(_) => RelayReaction::Degrade,
+        }
+    }
+
+    /// The metric counting the error
+    pub fn metric(&self) -> &'static str {
+        match self {
+            RelayError::RateLimited { .. } => "relay_error_rate_limited",
+            RelayError::NonceTooLow => "relay_error_nonce_too_low",
+            RelayError::AlreadyKnown => "relay_error_already_known",
+            RelayError::BundleTooLarge => "relay_error_bundle_too_large",
+            RelayError::SimulationFailed(_) => "relay_error_simulation_failed",
+            RelayError::Unreachable(_) => "relay_error_unreachable",
+            RelayError::Other(_) => "relay_error_other",
+        }
+    }
+
+    /// A short description for logs and decision reports
+    pub fn describe(&self) -> &'static str {
+        match self {
+            RelayError::RateLimited { .. } => "rate limited",
+            RelayError::NonceTooLow => "nonce too low",
+            RelayError::AlreadyKnown => "already known",
+            RelayError::BundleTooLarge => "bundle too large",
+            RelayError::SimulationFailed(_) => "simulation failed",
+            RelayError::Unreachable(_) => "unreachable",
+            RelayError::Other(_) => "unclassified error",
+        }
+    }
+}
+
+/// Parses the seconds from a `retry after` hint in an error message
+fn retry_after(message: &str) -> Option<Duration> {
+    let start = message
+        .find("retry-after")
+        .or_else(|| message.find("retry after"))?;
+    let secs = message[start + "retry after".len()..]
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(secs))
+}
+
+/// Relays paused after rate limiting, by name
+#[derive(Debug, Default)]
+pub struct RelayBackoffs {
+    until: HashMap<String, Instant>,
+}
+
+impl RelayBackoffs {
+    /// Creates a set with no paused relays
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pauses a relay for `duration` from `now`, extending any longer pause already in place
+    pub fn back_off(&mut self, relay: &str, now: Instant, duration: Duration) {
+        let until = now + duration;
+        let entry = self.until.entry(relay.to_string()).or_insert(until);
+        *entry = (*entry).max(until);
+    }
+
+    /// Whether a relay is paused at `now`
+    pub fn is_backing_off(&self, relay: &str, now: Instant) -> bool {
+        self.until.get(relay).map_or(false, |until| now < *until)
+    }
+}
+
+/// Returns the process-wide relay backoffs
+pub fn relay_backoffs() -> &'static Mutex<RelayBackoffs> {
+    static BACKOFFS: OnceLock<Mutex<RelayBackoffs>> = OnceLock::new();
+    BACKOFFS.get_or_init(|| Mutex::new(RelayBackoffs::new()))
+}
+
+/// Whether a relay is paused after rate limiting
+pub fn relay_paused(relay: &str) -> bool {
+    relay_backoffs()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_backing_off(relay, Instant::now())
+}
+
+/// Counts a relay error in its `relay_error_*` metric and pauses the relay if it's rate limiting
+///
+/// Returns the reaction left to the caller: dropping, rebuilding, or counting towards observer
+/// mode.
+pub fn react(relay: &str, error: &RelayError) -> RelayReaction {
+    metrics::increment(error.metric());
+    let reaction = error.reaction();
+    if let RelayReaction::Backoff(duration) = reaction {
+        relay_backoffs()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .back_off(relay, Instant::now(), duration);
+        tracing::info!("[RELAY] Backing off {} for {:?}", relay, duration);
+    }
+    reaction
+}
+
+/// How many target blocks of submissions are remembered
+pub const SUBMISSION_HISTORY_BLOCKS: u64 = 64;
+
+/// Bundles already submitted, by target block and relay
+///
+/// A claim stays after a failed submission, since relays penalizing duplicates count the attempt
+/// either way.
+#[derive(Debug, Default)]
+pub struct SubmittedBundles {
+    blocks: BTreeMap<U64, HashSet<(String, H256)>>,
+}
+
+impl SubmittedBundles {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claims a bundle's submission to a relay for a block, returning whether it wasn't claimed
+    /// before
+    ///
+    /// Blocks more than [SUBMISSION_HISTORY_BLOCKS] behind the latest are forgotten.
+    pub fn claim(&mut self, relay: &str, block: U64, bundle_hash: H256) -> bool {
+        let claimed = self
+            .blocks
+            .entry(block)
+            .or_default()
+            .insert((relay.to_string(), bundle_hash));
+        if let Some(latest) = self.blocks.keys().next_back() {
+            let oldest = latest.saturating_sub(U64::from(SUBMISSION_HISTORY_BLOCKS));
+            self.blocks = self.blocks.split_off(&oldest);
+        }
+        claimed
+    }
+
+    /// Whether a bundle was submitted to a relay for a block
+    pub fn contains(&self, relay: &str, block: U64, bundle_hash: H256) -> bool {
+        self.blocks
+            .get(&block)
+            .map_or(false, |b| b.contains(&(relay.to_string(), bundle_hash)))
+    }
+
+    /// The number of remembered submissions
+    pub fn len(&self) -> usize {
+        self.blocks.values().map(HashSet::len).sum()
+    }
+
+    /// Whether no submissions are remembered
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+/// Returns the process-wide submitted bundles
+pub fn submitted_bundles() -> &'static Mutex<SubmittedBundles> {
+    static SUBMITTED: OnceLock<Mutex<SubmittedBundles>> = OnceLock::new();
+    SUBMITTED.get_or_init(|| Mutex::new(SubmittedBundles::new()))
+}
+
+/// Claims a submission in [submitted_bundles], counting duplicates in the
+/// `duplicate_submissions` metric
+///
+/// The bundle hash is [crate::simulation::bundle_hash] of its signed transactions.
+pub fn claim_submission(relay: &str, block: U64, bundle_hash: H256) -> bool {
+    let claimed = submitted_bundles()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .claim(relay, block, bundle_hash);
+    if !claimed {
+        metrics::increment("duplicate_submissions");
+        tracing::info!(
+            "[RELAY] Bundle {:?} was already sent to {} for block {}, skipping...",
+            bundle_hash,
+            relay,
+            block
+        );
+    }
+    claimed
+}
+
+/// Claims a bundle request's submission through [claim_submission]
+pub fn claim_request(relay: &str, bundle: &BundleRequest) -> bool {
+    let hashes = bundle
+        .transaction_hashes()
+        .iter()
+        .flat_map(|hash| hash.to_fixed_bytes())
+        .collect::<Vec<u8>>();
+    claim_submission(
+        relay,
+        bundle.block().unwrap_or_default(),
+        H256::from(ethers::utils::keccak256(hashes)),
+    )
+}
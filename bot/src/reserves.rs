@@ -0,0 +1,286 @@
+//! Reserve Sanity
+//!
+//! The optimal frontrun is sized against the pair's reserves at the chain tip. A pair whose price
+//! was pushed far in the last block, like by a one-block pump or a donation, is a classic trap for
+//! sandwich bots: the frontrun is sized against reserves that snap back once the manipulator
+//! unwinds, and the backrun sells into a pool that no longer matches the simulation.
+//!
+//! [ReserveHistory] keeps the reserves of each pair seen over the last [RESERVE_HISTORY_BLOCKS]
+//! blocks. Before pricing, [check_reserves] refuses reserves whose price moved more than
+//! `max_move_bps` since the last block seen, or sits more than `max_average_deviation_bps` off the
+//! price of the history's average reserves. A pair without a sample from the block before is
+//! read at that block first by [sane_reserves], so the last block's move is always checked.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, OnceLock},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    state::StateReader,
+    uniswap::{SortedPair, V2Deployment},
+};
+
+/// How many blocks of reserves are kept per pair
+pub const RESERVE_HISTORY_BLOCKS: u64 = 20;
+
+/// How many pairs reserves are kept for
+pub const RESERVE_HISTORY_PAIRS: usize = 4_096;
+
+/// The default price move since the last block reserves are refused after, in bips
+pub const DEFAULT_MAX_RESERVE_MOVE_BPS: u64 = 2_000;
+
+/// The default distance from the average price reserves are refused after, in bips
+pub const DEFAULT_MAX_RESERVE_AVERAGE_DEVIATION_BPS: u64 = 3_000;
+
+/// The reserve sanity settings
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReserveSanity {
+    /// The largest price move since the last block seen, in bips, or 0 to allow any
+    pub max_move_bps: u64,
+    /// The largest distance from the history's average price, in bips, or 0 to allow any
+    pub max_average_deviation_bps: u64,
+}
+
+impl ReserveSanity {
+    /// Whether any check is enabled
+    pub fn enabled(&self) -> bool {
+        self.max_move_bps > 0 || self.max_average_deviation_bps > 0
+    }
+}
+
+impl Default for ReserveSanity {
+    fn default() -> Self {
+        Self {
+            max_move_bps: DEFAULT_MAX_RESERVE_MOVE_BPS,
+            max_average_deviation_bps: DEFAULT_MAX_RESERVE_AVERAGE_DEVIATION_BPS,
+        }
+    }
+}
+
+/// A pair's reserves at a block, WETH first
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReserveSample {
+    /// The block the reserves were read at
+    pub block: U64,
+    /// The WETH reserve
+    pub weth: U256,
+    /// The token reserve
+    pub token: U256,
+}
+
+/// Why reserves aren't trusted
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum ReserveAnomaly {
+    /// The price moved too far since the last block seen
+    #[error("price moved {bps} bips since block {since}")]
+    Moved {
+        /// The block the move is measured from
+        since: U64,
+        /// The move, in bips
+        bps: u64,
+    },
+    /// The price is too far from the average of the history
+    #[error("price is {bps} bips off the average over {samples} blocks")]
+    OffAverage {
+        /// The samples averaged
+        samples: usize,
+        /// The distance, in bips
+        bps: u64,
+    },
+}
+
+impl ReserveAnomaly {
+    /// The metric counting skips for this anomaly
+    pub fn metric(&self) -> &'static str {
+        match self {
+            ReserveAnomaly::Moved { .. } => "reserve_moved",
+            ReserveAnomaly::OffAverage { .. } => "reserve_off_average",
+        }
+    }
+}
+
+/// How far the WETH price of `to` is from that of `from`, in bips of `from`
+///
+/// Reserves without a price are infinitely far apart.
+pub fn price_move_bps(from: (U256, U256), to: (U256, U256)) -> u64 {
+    // weth_to / token_to against weth_from / token_from, cross-multiplied
+    let moved = to.0.saturating_mul(from.1);
+    let base = from.0.saturating_mul(to.1);
+    if base.is_zero() {
+        return if moved.is_zero() { 0 } else { u64::MAX };
+    }
+    let diff = if moved > base {
+        moved - base
+    } else {
+        base - moved
+    };
+    let bps = diff.saturating_mul(U256::from(10_000u64)) / base;
+    if bps > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        bps.as_u64()
+    }
+}
+
+/// Checks `current` reserves against the samples of earlier blocks
+pub fn check_reserves(
+    config: &ReserveSanity,
+    history: &[ReserveSample],
+    current: &ReserveSample,
+) -> Result<(), ReserveAnomaly> {
+    let prior = history
+        .iter()
+        .filter(|s| s.block < current.block)
+        .collect::<Vec<_>>();
+    let now = (current.weth, current.token);
+    if let Some(last) = prior.last() {
+        let bps = price_move_bps((last.weth, last.token), now);
+        if config.max_move_bps > 0 && bps > config.max_move_bps {
+            return Err(ReserveAnomaly::Moved {
+                since: last.block,
+                bps,
+            });
+        }
+    }
+    if config.max_average_deviation_bps > 0 && !prior.is_empty() {
+        let average = prior.iter().fold((U256::zero(), U256::zero()), |sum, s| {
+            (sum.0.saturating_add(s.weth), sum.1.saturating_add(s.token))
+        });
+        let bps = price_move_bps(average, now);
+        if bps > config.max_average_deviation_bps {
+            return Err(ReserveAnomaly::OffAverage {
+                samples: prior.len(),
+                bps,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The reserves seen for each pair over the last [RESERVE_HISTORY_BLOCKS] blocks
+#[derive(Debug)]
+pub struct ReserveHistory {
+    pairs: HashMap<Address, VecDeque<ReserveSample>>,
+    capacity: usize,
+}
+
+impl ReserveHistory {
+    /// Creates a history keeping up to `capacity` pairs
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            pairs: HashMap::new(),
+            capacity,
+        }
+    }
+
+    /// Records a pair's reserves, replacing any sample of the same block
+    ///
+    /// When full, the pair seen least recently is forgotten.
+    pub fn record(&mut self, pair: Address, sample: ReserveSample) {
+        if !self.pairs.contains_key(&pair) && self.pairs.len() >= self.capacity {
+            let stalest = self
+                .pairs
+                .iter()
+                .min_by_key(|(_, samples)| samples.back().map(|s| s.block))
+                .map(|(pair, _)| *pair);
+            if let Some(stalest) = stalest {
+                self.pairs.remove(&stalest);
+            }
+        }
+        let samples = self.pairs.entry(pair).or_default();
+        let at = samples.partition_point(|s| s.block < sample.block);
+        match samples.get(at) {
+            Some(s) if s.block == sample.block => samples[at] = sample,
+            _ => samples.insert(at, sample),
+        }
+        let newest = samples.back().map(|s| s.block).unwrap_or_default();
+        while samples.front().map_or(false, |s| {
+            newest.saturating_sub(s.block).as_u64() >= RESERVE_HISTORY_BLOCKS
+        }) {
+            samples.pop_front();
+        }
+    }
+
+    /// The samples kept for a pair, oldest first
+    pub fn samples(&self, pair: &Address) -> Vec<ReserveSample> {
+        self.pairs
+            .get(pair)
+            .map(|samples| samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a sample of `block` is kept for a pair
+    pub fn contains(&self, pair: &Address, block: U64) -> bool {
+        self.pairs
+            .get(pair)
+            .map_or(false, |samples| samples.iter().any(|s| s.block == block))
+    }
+
+    /// The number of pairs kept
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Whether no pairs are kept
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+/// Returns the global reserve history
+pub fn reserve_history() -> &'static Mutex<ReserveHistory> {
+    static RESERVE_HISTORY: OnceLock<Mutex<ReserveHistory>> = OnceLock::new();
+    RESERVE_HISTORY.get_or_init(|| Mutex::new(ReserveHistory::new(RESERVE_HISTORY_PAIRS)))
+}
+
+/// Records the `(weth, token)` reserves of `pair` in `deployment` read at `block` and checks them
+/// against its history
+///
+/// Reserves of the block before are read first when the history doesn't have them.
+pub async fn sane_reserves<S: StateReader + ?Sized>(
+    state: &S,
+    config: &ReserveSanity,
+    deployment: &V2Deployment,
+    pair: &SortedPair,
+    weth: Address,
+    block: U64,
+    reserves: (U256, U256),
+) -> Result<Result<(), ReserveAnomaly>> {
+    let address = pair.address_in(deployment);
+    let previous = block.saturating_sub(U64::one());
+    let known = reserve_history()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(&address, previous);
+    let earlier = if known || block.is_zero() {
+        None
+    } else {
+        let (weth_reserve, token_reserve) = pair
+            .reserves_in(state, deployment, weth, Some(previous))
+            .await?;
+        Some(ReserveSample {
+            block: previous,
+            weth: weth_reserve,
+            token: token_reserve,
+        })
+    };
+
+    let current = ReserveSample {
+        block,
+        weth: reserves.0,
+        token: reserves.1,
+    };
+    let mut history = reserve_history().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(earlier) = earlier {
+        history.record(address, earlier);
+    }
+    let checked = check_reserves(config, &history.samples(&address), &current);
+    history.record(address, current);
+    Ok(checked)
+}
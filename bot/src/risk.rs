@@ -0,0 +1,243 @@
+//! Risk Checks
+//!
+//! Guards evaluated before a sandwich is signed and submitted.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{abi::ERC20, executor::ExecutionMode};
+
+/// The capital available to fund a sandwich
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Inventory {
+    /// WETH held by the executor contract
+    pub executor_weth: U256,
+    /// ETH held by the searcher wallet
+    pub searcher_eth: U256,
+}
+
+impl Inventory {
+    /// Fetches the executor's WETH balance and the searcher's ETH balance
+    pub async fn fetch<M: Middleware + 'static>(
+        client: Arc<M>,
+        weth: Address,
+        executor: Address,
+        searcher: Address,
+    ) -> Result<Self> {
+        let executor_weth = ERC20::new(weth, client.clone())
+            .balance_of(executor)
+            .call()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch executor WETH balance: {:?}", e))?;
+        let searcher_eth = client
+            .get_balance(searcher, None)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch searcher ETH balance: {:?}", e))?;
+        Ok(Self {
+            executor_weth,
+            searcher_eth,
+        })
+    }
+
+    /// The most WETH that can be swapped in the frontrun, after reserving `gas_cost` for gas
+    pub fn available_weth_in(&self, mode: ExecutionMode, gas_cost: U256) -> U256 {
+        match mode {
+            ExecutionMode::Inventory => self.executor_weth,
+            ExecutionMode::Value => self.searcher_eth.saturating_sub(gas_cost),
+        }
+    }
+}
+
+/// Returns the worst-case gas cost of both sandwich legs
+pub fn worst_case_gas_cost(gas_limit: U256, max_fee_per_gas: U256) -> U256 {
+    gas_limit
+        .saturating_mul(max_fee_per_gas)
+        .saturating_mul(U256::from(2))
+}
+
+/// The outcome of a balance preflight check
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Preflight {
+    /// The full amount can be funded
+    Proceed,
+    /// Only the contained amount can be funded
+    Downsize(U256),
+    /// The sandwich cannot be funded
+    Skip(PreflightFailure),
+}
+
+/// Why a balance preflight check failed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PreflightFailure {
+    /// The searcher can't pay for gas on both legs
+    InsufficientGas,
+    /// There isn't enough WETH to fund the frontrun
+    InsufficientWeth,
+}
+
+impl PreflightFailure {
+    /// The metric counter name for this failure
+    pub fn metric(&self) -> &'static str {
+        match self {
+            PreflightFailure::InsufficientGas => "preflight_insufficient_gas",
+            PreflightFailure::InsufficientWeth => "preflight_insufficient_weth",
+        }
+    }
+}
+
+/// Checks that the inventory can fund `amount_in` plus the worst-case `gas_cost` of both legs
+///
+/// If `downsize` is set, an underfunded frontrun is shrunk to the available inventory instead of skipped.
+pub fn preflight(
+    amount_in: U256,
+    gas_cost: U256,
+    inventory: &Inventory,
+    mode: ExecutionMode,
+    downsize: bool,
+) -> Preflight {
+    if inventory.searcher_eth < gas_cost {
+        return Preflight::Skip(PreflightFailure::InsufficientGas);
+    }
+
+    let available = inventory.available_weth_in(mode, gas_cost);
+    if amount_in <= available {
+        return Preflight::Proceed;
+    }
+    if downsize && !available.is_zero() {
+        return Preflight::Downsize(available);
+    }
+    Preflight::Skip(PreflightFailure::InsufficientWeth)
+}
+
+/// The default share of revenue bribed to the builder, in bips
+pub const DEFAULT_BRIBE_BPS: u64 = 1337;
+
+/// The largest share of revenue that can be bribed, in bips
+pub const MAX_BRIBE_BPS: u64 = 9_999;
+
+/// Guards against sandwiches profitable enough to make uncle-bandit or reorg attacks worthwhile
+///
+/// A large enough bundle is worth more to a builder or validator reorging the block and replaying
+/// it than to include as-is, so big revenue either pays a growing share to the builder or is refused.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReorgGuard {
+    /// Revenue above which the bribe share grows
+    pub bribe_threshold: Option<U256>,
+    /// Extra bips bribed for each multiple of `bribe_threshold` in revenue
+    pub bribe_step_bps: u64,
+    /// Revenue above which the sandwich is refused outright
+    pub max_revenue: Option<U256>,
+}
+
+impl Default for ReorgGuard {
+    fn default() -> Self {
+        Self {
+            bribe_threshold: None,
+            bribe_step_bps: 1_000,
+            max_revenue: None,
+        }
+    }
+}
+
+impl ReorgGuard {
+    /// Returns the share of `revenue` to bribe, in bips, or `None` if the sandwich should be refused
+    pub fn bribe_bps(&self, revenue: U256, base_bps: u64) -> Option<u64> {
+        if matches!(self.max_revenue, Some(max) if revenue > max) {
+            return None;
+        }
+        let bps = match self.bribe_threshold {
+            Some(threshold) if !threshold.is_zero() && revenue > threshold => {
+                let multiples = (revenue / threshold)
+                    .min(U256::from(MAX_BRIBE_BPS))
+                    .as_u64();
+                base_bps.saturating_add(self.bribe_step_bps.saturating_mul(multiples))
+            }
+            _ => base_bps,
+        };
+        Some(bps.min(MAX_BRIBE_BPS))
+    }
+}
+
+/// The default rolling window token exposure is capped over, one day
+pub const DEFAULT_EXPOSURE_WINDOW_SECS: u64 = 86_400;
+
+/// Caps on the WETH committed to a single token
+///
+/// A token that rugs takes every sandwich on it down together, so the frontrun WETH committed to
+/// any one token within the window is capped.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExposureLimits {
+    /// The most WETH committed to one token per window, unlimited if unset
+    pub max_token_exposure: Option<U256>,
+    /// The rolling window, in seconds
+    pub window_secs: u64,
+}
+
+impl Default for ExposureLimits {
+    fn default() -> Self {
+        Self {
+            max_token_exposure: None,
+            window_secs: DEFAULT_EXPOSURE_WINDOW_SECS,
+        }
+    }
+}
+
+/// Tracks the WETH committed to each token over a rolling window
+#[derive(Debug, Clone)]
+pub struct ExposureTracker {
+    limits: ExposureLimits,
+    commitments: HashMap<Address, VecDeque<(Instant, U256)>>,
+}
+
+impl ExposureTracker {
+    /// Creates a tracker enforcing `limits`
+    pub fn new(limits: ExposureLimits) -> Self {
+        Self {
+            limits,
+            commitments: HashMap::new(),
+        }
+    }
+
+    /// The WETH committed to `token` within the window ending at `now`
+    pub fn exposure(&mut self, token: Address, now: Instant) -> U256 {
+        let window = Duration::from_secs(self.limits.window_secs);
+        let commitments = match self.commitments.get_mut(&token) {
+            Some(c) => c,
+            None => return U256::zero(),
+        };
+        while matches!(commitments.front(), Some((at, _)) if now.saturating_duration_since(*at) >= window)
+        {
+            commitments.pop_front();
+        }
+        if commitments.is_empty() {
+            self.commitments.remove(&token);
+            return U256::zero();
+        }
+        commitments
+            .iter()
+            .fold(U256::zero(), |acc, (_, amount)| acc.saturating_add(*amount))
+    }
+
+    /// Whether committing `amount` more to `token` stays within the cap
+    pub fn allows(&mut self, token: Address, amount: U256, now: Instant) -> bool {
+        match self.limits.max_token_exposure {
+            Some(max) => self.exposure(token, now).saturating_add(amount) <= max,
+            None => true,
+        }
+    }
+
+    /// Records `amount` committed to `token` at `now`
+    pub fn commit(&mut self, token: Address, amount: U256, now: Instant) {
+        self.commitments
+            .entry(token)
+            .or_default()
+            .push_back((now, amount));
+    }
+}
@@ -0,0 +1,450 @@
+//! Victim and Token Safety
+//!
+//! Some victims are bait: swaps built to lure sandwich bots into tokens that trap or drain them.
+//! Each candidate is checked for the known bait patterns, and every pattern found adds to a risk
+//! score. Sandwiches scoring above the configured maximum are refused.
+//!
+//! Token bytecode is also scanned for functions that let the deployer trap or tax the tokens we
+//! buy, see [scan_bytecode].
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::{V2Sync, SYNC_TOPIC},
+    state::StateReader,
+};
+
+/// The default number of blocks checked for code changes and reserve manipulation
+pub const DEFAULT_LOOKBACK_BLOCKS: u64 = 50;
+
+/// The default largest reserve swing between two syncs that isn't suspicious, in bips
+pub const DEFAULT_MAX_RESERVE_SWING_BPS: u64 = 5_000;
+
+/// The default highest risk score a sandwich may have
+pub const DEFAULT_MAX_RISK_SCORE: u32 = 99;
+
+/// A bait pattern
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum RiskFlag {
+    /// The token's code was deployed or changed within the lookback
+    CodeChangedRecently,
+    /// The pair's reserves swung further than the maximum within the lookback
+    ReserveManipulation,
+    /// The victim's sender has baited us before or is a known baiter
+    KnownBaiter,
+    /// The token is an upgradeable proxy, so its logic can change under us
+    UpgradeableProxy,
+    /// The token can blacklist holders
+    Blacklist,
+    /// The token's transfers can be paused
+    PausableTransfers,
+    /// The token's transfer fees can be changed
+    FeeSetter,
+    /// A honeypot API found the token can't be sold
+    Honeypot,
+    /// A honeypot API found the token's buy or sell tax above the maximum
+    HighTax,
+}
+
+impl RiskFlag {
+    /// How much the flag adds to the risk score
+    pub fn weight(&self) -> u32 {
+        match self {
+            RiskFlag::CodeChangedRecently => 100,
+            RiskFlag::ReserveManipulation => 50,
+            RiskFlag::KnownBaiter => 100,
+            RiskFlag::UpgradeableProxy => 40,
+            RiskFlag::Blacklist => 100,
+            RiskFlag::PausableTransfers => 60,
+            RiskFlag::FeeSetter => 60,
+            RiskFlag::Honeypot => 100,
+            RiskFlag::HighTax => 100,
+        }
+    }
+
+    /// The metric counting candidates with this flag
+    pub fn metric(&self) -> &'static str {
+        match self {
+            RiskFlag::CodeChangedRecently => "safety_code_changed",
+            RiskFlag::ReserveManipulation => "safety_reserve_manipulation",
+            RiskFlag::KnownBaiter => "safety_known_baiter",
+            RiskFlag::UpgradeableProxy => "safety_upgradeable_proxy",
+            RiskFlag::Blacklist => "safety_blacklist",
+            RiskFlag::PausableTransfers => "safety_pausable",
+            RiskFlag::FeeSetter => "safety_fee_setter",
+            RiskFlag::Honeypot => "safety_honeypot",
+            RiskFlag::HighTax => "safety_high_tax",
+        }
+    }
+}
+
+/// The risk flags found for a candidate
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SafetyReport {
+    /// The flags found, without duplicates
+    pub flags: Vec<RiskFlag>,
+}
+
+impl SafetyReport {
+    /// Adds a flag if it isn't already present
+    pub fn flag(&mut self, flag: RiskFlag) {
+        if !self.flags.contains(&flag) {
+            self.flags.push(flag);
+        }
+    }
+
+    /// Adds every flag of another report
+    pub fn merge(&mut self, other: &SafetyReport) {
+        for flag in &other.flags {
+            self.flag(*flag);
+        }
+    }
+
+    /// The summed weight of every flag
+    pub fn score(&self) -> u32 {
+        self.flags
+            .iter()
+            .map(RiskFlag::weight)
+            .fold(0, u32::saturating_add)
+    }
+
+    /// Whether the score is within `max_score`
+    pub fn is_safe(&self, max_score: u32) -> bool {
+        self.score() <= max_score
+    }
+}
+
+/// A comma-separated list of addresses
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AddressList(pub Vec<Address>);
+
+impl FromStr for AddressList {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|a| Address::from_str(a).map_err(|_| eyre::eyre!("Invalid address \"{}\"", a)))
+            .collect::<Result<Vec<_>>>()
+            .map(AddressList)
+    }
+}
+
+/// The safety check settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SafetyConfig {
+    /// Blocks checked for code changes and reserve manipulation
+    pub lookback_blocks: u64,
+    /// The largest reserve swing between two syncs that isn't suspicious, in bips
+    pub max_reserve_swing_bps: u64,
+    /// The highest risk score a sandwich may have
+    pub max_risk_score: u32,
+    /// Senders known to bait sandwich bots
+    pub known_baiters: AddressList,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            lookback_blocks: DEFAULT_LOOKBACK_BLOCKS,
+            max_reserve_swing_bps: DEFAULT_MAX_RESERVE_SWING_BPS,
+            max_risk_score: DEFAULT_MAX_RISK_SCORE,
+            known_baiters: AddressList::default(),
+        }
+    }
+}
+
+/// Returns the largest swing of either reserve between consecutive syncs, in bips
+pub fn max_reserve_swing_bps(syncs: &[V2Sync]) -> u64 {
+    let swing = |before: U256, after: U256| {
+        if before.is_zero() {
+            return 0;
+        }
+        let difference = if after > before {
+            after - before
+        } else {
+            before - after
+        };
+        let bps = difference.saturating_mul(U256::from(10_000)) / before;
+        bps.min(U256::from(u64::MAX)).as_u64()
+    };
+    syncs
+        .windows(2)
+        .map(|w| swing(w[0].reserve0, w[1].reserve0).max(swing(w[0].reserve1, w[1].reserve1)))
+        .max()
+        .unwrap_or_default()
+}
+
+/// The EIP-1967 implementation slot, `keccak256("eip1967.proxy.implementation") - 1`
+pub const EIP1967_IMPLEMENTATION_SLOT: [u8; 32] = [
+    0x36, 0x08, 0x94, 0xa1, 0x3b, 0xa1, 0xa3, 0x21, 0x06, 0x67, 0xc8, 0x28, 0x49, 0x2d, 0xb9, 0x8d,
+    0xca, 0x3e, 0x20, 0x76, 0xcc, 0x37, 0x35, 0xa9, 0x20, 0xa3, 0xca, 0x50, 0x5d, 0x38, 0x2b, 0xbc,
+];
+
+/// Functions that upgrade or expose a proxy's implementation
+pub const PROXY_SIGNATURES: &[&str] = &[
+    "upgradeTo(address)",
+    "upgradeToAndCall(address,bytes)",
+    "implementation()",
+];
+
+/// Functions that blacklist holders
+pub const BLACKLIST_SIGNATURES: &[&str] = &[
+    "blacklist(address)",
+    "addToBlacklist(address)",
+    "isBlacklisted(address)",
+    "setBlacklist(address,bool)",
+    "setBots(address[])",
+    "isBot(address)",
+];
+
+/// Functions that pause transfers
+pub const PAUSE_SIGNATURES: &[&str] = &["pause()", "paused()"];
+
+/// Functions that change transfer fees or limits
+pub const FEE_SETTER_SIGNATURES: &[&str] = &[
+    "setFee(uint256)",
+    "setFees(uint256,uint256)",
+    "setTaxFee(uint256)",
+    "setBuyFee(uint256)",
+    "setSellFee(uint256)",
+    "setMaxTxAmount(uint256)",
+];
+
+/// The `DELEGATECALL` opcode
+const DELEGATECALL: u8 = 0xf4;
+
+/// The `PUSH1` opcode, `PUSH1..=PUSH32` are contiguous
+const PUSH1: u8 = 0x60;
+
+/// The `PUSH32` opcode
+const PUSH32: u8 = 0x7f;
+
+/// The instructions of some bytecode, as `(opcode, immediate)` pairs
+///
+/// Walking instructions instead of searching the raw bytes keeps push data from being mistaken for
+/// opcodes. Trailing push data cut off by the end of the code is returned short.
+pub fn instructions(code: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut pc = 0;
+    std::iter::from_fn(move || {
+        let opcode = *code.get(pc)?;
+        let size = if (PUSH1..=PUSH32).contains(&opcode) {
+            (opcode - PUSH1 + 1) as usize
+        } else {
+            0
+        };
+        let start = (pc + 1).min(code.len());
+        let end = (start + size).min(code.len());
+        pc += 1 + size;
+        Some((opcode, &code[start..end]))
+    })
+}
+
+/// Scans token bytecode for dangerous functions
+///
+/// Selectors are matched against the `PUSH4` immediates of the dispatcher, so renamed or
+/// obfuscated functions slip through. A clean scan lowers the odds of a trap, it doesn't rule one
+/// out.
+pub fn scan_bytecode(code: &[u8]) -> SafetyReport {
+    let mut selectors = HashSet::new();
+    let mut delegatecall = false;
+    let mut eip1967 = false;
+    for (opcode, immediate) in instructions(code) {
+        match opcode {
+            0x63 if immediate.len() == 4 => {
+                selectors.insert([immediate[0], immediate[1], immediate[2], immediate[3]]);
+            }
+            PUSH32 if immediate == EIP1967_IMPLEMENTATION_SLOT => eip1967 = true,
+            DELEGATECALL => delegatecall = true,
+            _ => {}
+        }
+    }
+    let has_any = |signatures: &[&str]| {
+        signatures
+            .iter()
+            .any(|sig| selectors.contains(&ethers::utils::id(sig)))
+    };
+
+    let mut report = SafetyReport::default();
+    if eip1967 || delegatecall || has_any(PROXY_SIGNATURES) {
+        report.flag(RiskFlag::UpgradeableProxy);
+    }
+    if has_any(BLACKLIST_SIGNATURES) {
+        report.flag(RiskFlag::Blacklist);
+    }
+    if has_any(PAUSE_SIGNATURES) {
+        report.flag(RiskFlag::PausableTransfers);
+    }
+    if has_any(FEE_SETTER_SIGNATURES) {
+        report.flag(RiskFlag::FeeSetter);
+    }
+    report
+}
+
+/// Fetches a token's bytecode and scans it for dangerous functions
+pub async fn scan_token<S: StateReader + ?Sized>(
+    state: &S,
+    token: Address,
+) -> Result<SafetyReport> {
+    let code = state.code(token, None).await?;
+    if code.is_empty() {
+        eyre::bail!("Token {:?} has no code", token);
+    }
+    Ok(scan_bytecode(code.as_ref()))
+}
+
+/// How many landed sandwiches that made nothing mark a sender as a baiter
+pub const BAIT_STRIKES: u32 = 2;
+
+/// How long a strike, and so a baiter mark, lasts after the sender's latest one, in seconds
+pub const BAITER_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// A sender's landed sandwiches that made nothing
+#[derive(Debug, Clone, Copy)]
+struct Strikes {
+    count: u32,
+    last: Instant,
+}
+
+impl Strikes {
+    fn expired(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last) >= Duration::from_secs(BAITER_TTL_SECS)
+    }
+}
+
+/// Senders that have baited us, shared across the bot
+///
+/// One sandwich that made nothing can be bad luck, like a competing bundle landing first, so a
+/// sender is only marked after [BAIT_STRIKES] of them, and the mark expires [BAITER_TTL_SECS] after
+/// the latest.
+#[derive(Debug, Default)]
+pub struct BaiterRegistry {
+    baiters: Mutex<HashMap<Address, Strikes>>,
+}
+
+impl BaiterRegistry {
+    /// Records a landed sandwich of a sender's swap that made nothing, returning whether the
+    /// sender is now marked as a baiter
+    pub fn strike(&self, sender: Address, now: Instant) -> bool {
+        let mut baiters = self.baiters.lock().unwrap_or_else(|e| e.into_inner());
+        baiters.retain(|_, strikes| !strikes.expired(now));
+        let strikes = baiters.entry(sender).or_insert(Strikes {
+            count: 0,
+            last: now,
+        });
+        strikes.count = strikes.count.saturating_add(1);
+        strikes.last = now;
+        strikes.count >= BAIT_STRIKES
+    }
+
+    /// Clears a sender's strikes, like after a sandwich of their swap made a profit
+    pub fn forgive(&self, sender: &Address) {
+        self.baiters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(sender);
+    }
+
+    /// Records a sender as a baiter outright, like one restored from a snapshot
+    pub fn mark(&self, sender: Address) {
+        self.baiters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                sender,
+                Strikes {
+                    count: BAIT_STRIKES,
+                    last: Instant::now(),
+                },
+            );
+    }
+
+    /// Whether a sender is marked as a baiter
+    pub fn contains(&self, sender: &Address) -> bool {
+        self.contains_at(sender, Instant::now())
+    }
+
+    /// Whether a sender is marked as a baiter at `now`
+    pub fn contains_at(&self, sender: &Address, now: Instant) -> bool {
+        self.baiters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(sender)
+            .map_or(false, |s| s.count >= BAIT_STRIKES && !s.expired(now))
+    }
+
+    /// Every sender marked as a baiter, sorted
+    pub fn list(&self) -> Vec<Address> {
+        let now = Instant::now();
+        let mut baiters = self
+            .baiters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(_, s)| s.count >= BAIT_STRIKES && !s.expired(now))
+            .map(|(sender, _)| *sender)
+            .collect::<Vec<_>>();
+        baiters.sort_unstable();
+        baiters
+    }
+}
+
+/// Returns the global baiter registry
+pub fn baiters() -> &'static BaiterRegistry {
+    static BAITERS: OnceLock<BaiterRegistry> = OnceLock::new();
+    BAITERS.get_or_init(BaiterRegistry::default)
+}
+
+/// Checks a candidate victim for bait patterns and scans its token's bytecode
+///
+/// Needs a node that serves code and logs `lookback_blocks` into the past.
+pub async fn assess_victim<M: Middleware + 'static, S: StateReader + ?Sized>(
+    client: Arc<M>,
+    state: &S,
+    config: &SafetyConfig,
+    sender: Address,
+    token: Address,
+    pair: Address,
+    block: U64,
+) -> Result<SafetyReport> {
+    let mut report = SafetyReport::default();
+    if config.known_baiters.0.contains(&sender) || baiters().contains(&sender) {
+        report.flag(RiskFlag::KnownBaiter);
+    }
+
+    let past = block.saturating_sub(config.lookback_blocks.into());
+    let code_now = state.code(token, Some(block)).await?;
+    let code_then = state.code(token, Some(past)).await?;
+    if code_now != code_then {
+        report.flag(RiskFlag::CodeChangedRecently);
+    }
+    report.merge(&scan_bytecode(code_now.as_ref()));
+
+    let filter = Filter::new()
+        .address(pair)
+        .topic0(H256::from(SYNC_TOPIC))
+        .from_block(BlockNumber::Number(past))
+        .to_block(BlockNumber::Number(block));
+    let syncs = client
+        .get_logs(&filter)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch pair syncs: {:?}", e))?
+        .iter()
+        .filter_map(|log| V2Sync::decode(log).ok())
+        .collect::<Vec<_>>();
+    if max_reserve_swing_bps(&syncs) > config.max_reserve_swing_bps {
+        report.flag(RiskFlag::ReserveManipulation);
+    }
+
+    Ok(report)
+}
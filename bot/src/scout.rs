@@ -0,0 +1,330 @@
+//! Mempool Flow Analytics
+//!
+//! The `scout` binary streams the configured mempool sources without a wallet, relay or executor,
+//! to research swap flow or evaluate a new chain before running the bot on it. Every
+//! `swapExactETHForTokens` call to the [ScoutChain]'s Uniswap V2 router, the only swap the bot
+//! sandwiches, is decoded and priced against its pair like the bot would, with [analyze_swap], and
+//! aggregated in [FlowStats]: the WETH volume through each pair, the distribution of the slippage
+//! victims allow, and the fraction of swaps a sandwich could profit from before gas and the bribe.
+
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi,
+    evaluate::{self, StateProvider},
+    numeric, uniswap, utils,
+};
+
+/// The default interval between flow reports, in seconds
+pub const DEFAULT_SCOUT_REPORT_SECS: u64 = 60;
+
+/// The upper bounds of the slippage histogram's buckets, in bips, the last bucket being above them
+pub const SLIPPAGE_BUCKETS_BPS: [u64; 6] = [10, 50, 100, 300, 500, 1_000];
+
+/// How many pairs a report lists, by WETH volume
+pub const REPORT_TOP_PAIRS: usize = 10;
+
+/// The default number of swaps priced at once
+pub const DEFAULT_SCOUT_MAX_INFLIGHT: usize = 64;
+
+/// The contracts a chain's swaps go through, mainnet's by default
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScoutChain {
+    /// The Uniswap V2 router swaps are sent to
+    pub router: Address,
+    /// The wrapped native token
+    pub weth: Address,
+    /// The Uniswap V2 deployment the router swaps through
+    pub v2: uniswap::V2Deployment,
+}
+
+impl Default for ScoutChain {
+    fn default() -> Self {
+        Self {
+            router: uniswap::get_univ2_router_address(),
+            weth: utils::get_weth_address(),
+            v2: uniswap::V2Deployment::default(),
+        }
+    }
+}
+
+/// What a decoded swap offers a sandwich
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SwapClass {
+    /// The optimal sandwich makes more than the minimum revenue, before gas and the bribe
+    Sandwichable,
+    /// The victim's slippage leaves too little to sandwich
+    Unprofitable,
+    /// The swap's deadline has passed
+    Expired,
+    /// A pair on the path couldn't be read
+    Unpriced,
+}
+
+/// A decoded and priced router swap
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SwapFlow {
+    /// The swap transaction
+    pub hash: TxHash,
+    /// The WETH pair the swap buys from
+    pub pair: Address,
+    /// The token bought from the pair
+    pub token: Address,
+    /// The WETH swapped in
+    pub weth_in: U256,
+    /// The slippage the swap allows on the WETH pair, in bips, when priced
+    pub slippage_bps: Option<u64>,
+    /// The optimal sandwich's revenue, before gas and the bribe
+    pub revenue: U256,
+    /// What the swap offers a sandwich
+    pub class: SwapClass,
+}
+
+/// The slippage a swap of `weth_in` allows by accepting `min_recv` tokens, in bips
+pub fn slippage_bps(
+    weth_in: U256,
+    min_recv: U256,
+    weth_reserves: U256,
+    token_reserves: U256,
+) -> u64 {
+    let (expected, _, _) =
+        uniswap::get_univ2_data_given_in(&weth_in, &weth_reserves, &token_reserves);
+    if expected.is_zero() {
+        return 0;
+    }
+    (expected.saturating_sub(min_recv) * 10_000 / expected).as_u64()
+}
+
+/// The revenue of the optimal sandwich of a swap of `weth_in` accepting `min_recv` tokens
+pub fn sandwich_revenue(
+    weth_in: U256,
+    min_recv: U256,
+    weth_reserves: U256,
+    token_reserves: U256,
+) -> U256 {
+    let optimal_weth_in = numeric::calculate_sandwich_optimal_in(
+        &weth_in,
+        &min_recv,
+        &weth_reserves,
+        &token_reserves,
+    );
+    if optimal_weth_in.is_zero() {
+        return U256::zero();
+    }
+    numeric::calculate_sandwich_context(
+        &optimal_weth_in,
+        &weth_in,
+        &min_recv,
+        &weth_reserves,
+        &token_reserves,
+    )
+    .map(|c| c.revenue)
+    .unwrap_or_default()
+}
+
+/// Decodes and prices a pending `swapExactETHForTokens` through the `chain`'s router at `now`
+///
+/// Returns `None` when the transaction isn't a WETH swap through the router. Swaps whose pairs
+/// can't be read from `state` are classified as [SwapClass::Unpriced] rather than failing.
+pub async fn analyze_swap<S: StateProvider + ?Sized>(
+    tx: &Transaction,
+    chain: &ScoutChain,
+    now: u64,
+    state: &S,
+    min_revenue: U256,
+) -> Option<SwapFlow> {
+    if tx.to != Some(chain.router) {
+        return None;
+    }
+    let weth = chain.weth;
+    let decoded = abi::decode_uniswap_router_calldata(&tx.input).ok()?;
+    if decoded.path.len() < 2 || decoded.path[0] != weth {
+        return None;
+    }
+    let token = decoded.path[1];
+    let sorted_pair = uniswap::SortedPair::new(weth, token);
+    let mut flow = SwapFlow {
+        hash: tx.hash,
+        pair: sorted_pair.address_in(&chain.v2),
+        token,
+        weth_in: tx.value,
+        slippage_bps: None,
+        revenue: U256::zero(),
+        class: SwapClass::Unpriced,
+    };
+    if U256::from(now) > decoded.deadline {
+        flow.class = SwapClass::Expired;
+        return Some(flow);
+    }
+
+    let priced = async {
+        let min_recv = evaluate::min_recv_through_path_in(
+            state,
+            &chain.v2,
+            decoded.amount_out_min,
+            &decoded.path,
+        )
+        .await?;
+        let reserves = sorted_pair.orient(weth, state.reserves(flow.pair).await?);
+        Ok::<_, eyre::Report>((min_recv, reserves))
+    };
+    let (min_recv, (weth_reserves, token_reserves)) = match priced.await {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::debug!("[SCOUT] Failed to price {:?}: {:?}", tx.hash, e);
+            return Some(flow);
+        }
+    };
+    flow.slippage_bps = Some(slippage_bps(
+        tx.value,
+        min_recv,
+        weth_reserves,
+        token_reserves,
+    ));
+    flow.revenue = sandwich_revenue(tx.value, min_recv, weth_reserves, token_reserves);
+    flow.class = if flow.revenue > min_revenue {
+        SwapClass::Sandwichable
+    } else {
+        SwapClass::Unprofitable
+    };
+    Some(flow)
+}
+
+/// The histogram bucket a slippage falls in, an index into [SLIPPAGE_BUCKETS_BPS] or one past it
+pub fn slippage_bucket(slippage_bps: u64) -> usize {
+    SLIPPAGE_BUCKETS_BPS
+        .iter()
+        .position(|bound| slippage_bps <= *bound)
+        .unwrap_or(SLIPPAGE_BUCKETS_BPS.len())
+}
+
+/// The swap flow through a single pair
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PairFlow {
+    /// The token the pair trades against WETH
+    pub token: Address,
+    /// The swaps decoded
+    pub swaps: u64,
+    /// The WETH swapped in
+    pub weth_volume: U256,
+    /// The swaps a sandwich could profit from
+    pub sandwichable: u64,
+    /// The summed revenue of their optimal sandwiches, before gas and the bribe
+    pub revenue: U256,
+}
+
+/// Swap flow aggregated since the scout started
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FlowStats {
+    /// Pending transactions seen
+    pub seen: u64,
+    /// Transactions to the router, decoded or not
+    pub router_calls: u64,
+    /// WETH-in swaps decoded
+    pub swaps: u64,
+    /// Swaps a sandwich could profit from
+    pub sandwichable: u64,
+    /// Swaps that allow too little slippage
+    pub unprofitable: u64,
+    /// Swaps past their deadline
+    pub expired: u64,
+    /// Swaps whose pairs couldn't be read
+    pub unpriced: u64,
+    /// The WETH swapped in
+    pub weth_volume: U256,
+    /// Priced swaps counted by [slippage_bucket]
+    pub slippage: [u64; SLIPPAGE_BUCKETS_BPS.len() + 1],
+    /// The flow through each pair
+    pub pairs: HashMap<Address, PairFlow>,
+}
+
+impl FlowStats {
+    /// Creates empty stats
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a pending transaction, and whether it calls `router`
+    pub fn record_seen(&mut self, tx: &Transaction, router: Address) {
+        self.seen += 1;
+        if tx.to == Some(router) {
+            self.router_calls += 1;
+        }
+    }
+
+    /// Counts a decoded swap
+    pub fn record(&mut self, flow: &SwapFlow) {
+        self.swaps += 1;
+        self.weth_volume = self.weth_volume.saturating_add(flow.weth_in);
+        match flow.class {
+            SwapClass::Sandwichable => self.sandwichable += 1,
+            SwapClass::Unprofitable => self.unprofitable += 1,
+            SwapClass::Expired => self.expired += 1,
+            SwapClass::Unpriced => self.unpriced += 1,
+        }
+        if let Some(slippage) = flow.slippage_bps {
+            self.slippage[slippage_bucket(slippage)] += 1;
+        }
+
+        let pair = self.pairs.entry(flow.pair).or_default();
+        pair.token = flow.token;
+        pair.swaps += 1;
+        pair.weth_volume = pair.weth_volume.saturating_add(flow.weth_in);
+        if flow.class == SwapClass::Sandwichable {
+            pair.sandwichable += 1;
+            pair.revenue = pair.revenue.saturating_add(flow.revenue);
+        }
+    }
+
+    /// The fraction of decoded swaps a sandwich could profit from
+    pub fn sandwichable_fraction(&self) -> f64 {
+        if self.swaps == 0 {
+            return 0.0;
+        }
+        self.sandwichable as f64 / self.swaps as f64
+    }
+
+    /// The `n` pairs with the most WETH volume, largest first
+    pub fn top_pairs(&self, n: usize) -> Vec<(Address, PairFlow)> {
+        let mut pairs = self
+            .pairs
+            .iter()
+            .map(|(pair, flow)| (*pair, *flow))
+            .collect::<Vec<_>>();
+        pairs.sort_by(|a, b| b.1.weth_volume.cmp(&a.1.weth_volume));
+        pairs.truncate(n);
+        pairs
+    }
+
+    /// Emits the stats as a `[SCOUT] Flow` event, followed by a `[SCOUT] Pair` event per top pair
+    pub fn log(&self) {
+        tracing::info!(
+            seen = self.seen,
+            router_calls = self.router_calls,
+            swaps = self.swaps,
+            sandwichable = self.sandwichable,
+            unprofitable = self.unprofitable,
+            expired = self.expired,
+            unpriced = self.unpriced,
+            sandwichable_fraction = self.sandwichable_fraction(),
+            weth_volume = %self.weth_volume,
+            slippage = ?self.slippage,
+            slippage_buckets_bps = ?SLIPPAGE_BUCKETS_BPS,
+            "[SCOUT] Flow"
+        );
+        for (pair, flow) in self.top_pairs(REPORT_TOP_PAIRS) {
+            tracing::info!(
+                pair = ?pair,
+                token = ?flow.token,
+                swaps = flow.swaps,
+                weth_volume = %flow.weth_volume,
+                sandwichable = flow.sandwichable,
+                revenue = %flow.revenue,
+                "[SCOUT] Pair"
+            );
+        }
+    }
+}
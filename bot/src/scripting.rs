@@ -0,0 +1,215 @@
+//! Scripted Filters
+//!
+//! Operators can express go/no-go rules in a [Rhai](https://rhai.rs) script instead of code, like
+//! skipping tokens whose symbol contains ELON unless the pair holds more than 500 WETH:
+//!
+//! ```rhai
+//! !symbol.contains("ELON") || liquidity > 500.0
+//! ```
+//!
+//! The script is compiled once at startup and run against each candidate once its reserves are
+//! known, before the victim is simulated. It sees the candidate through the constants of
+//! [ScriptCandidate] and must evaluate to a bool, `false` skipping the candidate.
+//!
+//! Scripts run on the evaluation path, so each run is held to a strict budget of operations, and
+//! to small limits on call depth, strings, arrays and maps. A script that runs out of budget,
+//! fails, or doesn't return a bool skips the candidate rather than letting it through.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use thiserror::Error;
+
+use crate::abi::ERC20;
+
+/// How deep script functions may call each other
+const MAX_CALL_LEVELS: usize = 16;
+
+/// How deeply script expressions may nest
+const MAX_EXPR_DEPTH: usize = 32;
+
+/// The longest string a script may build, in bytes
+const MAX_STRING_SIZE: usize = 1_024;
+
+/// The most elements of a script array or map
+const MAX_COLLECTION_SIZE: usize = 256;
+
+/// What a script knows about a candidate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptCandidate {
+    /// The token bought with WETH, seen as `token`
+    pub token: Address,
+    /// The token's symbol, seen as `symbol`, empty if it couldn't be read
+    pub symbol: String,
+    /// The pair swapped through, seen as `pair`
+    pub pair: Address,
+    /// The victim's sender, seen as `sender`
+    pub sender: Address,
+    /// The victim's WETH input in wei, seen as `weth_in` in ether
+    pub weth_in: U256,
+    /// The pair's WETH reserve in wei, seen as `liquidity` in ether
+    pub liquidity: U256,
+    /// The pair's token reserve, seen as `token_reserve` in the token's smallest unit
+    pub token_reserve: U256,
+    /// The block the reserves were read at, seen as `block`
+    pub block: U64,
+}
+
+impl ScriptCandidate {
+    /// The script scope holding the candidate's constants
+    fn scope(&self) -> Scope<'static> {
+        let mut scope = Scope::new();
+        scope.push_constant("token", format!("{:?}", self.token));
+        scope.push_constant("symbol", self.symbol.clone());
+        scope.push_constant("pair", format!("{:?}", self.pair));
+        scope.push_constant("sender", format!("{:?}", self.sender));
+        scope.push_constant("weth_in", ether(self.weth_in));
+        scope.push_constant("liquidity", ether(self.liquidity));
+        scope.push_constant("token_reserve", units(self.token_reserve));
+        scope.push_constant("block", self.block.as_u64() as i64);
+        scope
+    }
+}
+
+/// A wei amount in ether, losing precision past an f64's
+fn ether(wei: U256) -> f64 {
+    units(wei) / 1e18
+}
+
+/// An integer amount as an f64, losing precision past an f64's
+fn units(amount: U256) -> f64 {
+    amount.to_string().parse().unwrap_or(f64::MAX)
+}
+
+/// Why a script couldn't decide on a candidate
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum ScriptError {
+    /// The script ran out of operations
+    #[error("script ran past its budget of {operations} operations")]
+    Budget {
+        /// The budget
+        operations: u64,
+    },
+    /// The script failed
+    #[error("script failed: {0}")]
+    Failed(String),
+    /// The script evaluated to something other than a bool
+    #[error("script returned a {0}, not a bool")]
+    NotBool(String),
+}
+
+impl ScriptError {
+    /// The metric counting skips for this error
+    pub fn metric(&self) -> &'static str {
+        match self {
+            ScriptError::Budget { .. } => "script_budget_exceeded",
+            ScriptError::Failed(_) | ScriptError::NotBool(_) => "script_errors",
+        }
+    }
+}
+
+/// A compiled go/no-go script
+#[derive(Debug)]
+pub struct ScriptFilter {
+    engine: Engine,
+    ast: AST,
+    max_operations: u64,
+    reads_symbol: bool,
+}
+
+impl ScriptFilter {
+    /// Compiles a script, each run of which may take up to `max_operations` operations
+    pub fn compile(source: &str, max_operations: u64) -> Result<Self> {
+        if max_operations == 0 {
+            eyre::bail!("Scripts need a budget of at least one operation");
+        }
+        let mut engine = Engine::new();
+        engine.set_max_operations(max_operations);
+        engine.set_max_call_levels(MAX_CALL_LEVELS);
+        engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+        engine.set_max_string_size(MAX_STRING_SIZE);
+        engine.set_max_array_size(MAX_COLLECTION_SIZE);
+        engine.set_max_map_size(MAX_COLLECTION_SIZE);
+        // Keep scripts off stdout, and away from compiling more code at runtime
+        engine.on_print(|text| tracing::debug!("[SCRIPT] {}", text));
+        engine.on_debug(|text, _, _| tracing::debug!("[SCRIPT] {}", text));
+        engine.disable_symbol("eval");
+
+        let ast = engine
+            .compile(source)
+            .map_err(|e| eyre::eyre!("Failed to compile the filter script: {}", e))?;
+        Ok(Self {
+            engine,
+            ast,
+            max_operations,
+            reads_symbol: source.contains("symbol"),
+        })
+    }
+
+    /// Compiles the script at `path`
+    pub fn from_file(path: &Path, max_operations: u64) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| eyre::eyre!("Failed to read the filter script {:?}: {:?}", path, e))?;
+        Self::compile(&source, max_operations)
+    }
+
+    /// Whether the script may read `symbol`, which costs a request the first time a token is seen
+    pub fn reads_symbol(&self) -> bool {
+        self.reads_symbol
+    }
+
+    /// Runs the script against a candidate, `true` letting it through
+    pub fn evaluate(&self, candidate: &ScriptCandidate) -> Result<bool, ScriptError> {
+        let mut scope = candidate.scope();
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast)
+            .map_err(|e| match *e {
+                EvalAltResult::ErrorTooManyOperations(_) => ScriptError::Budget {
+                    operations: self.max_operations,
+                },
+                e => ScriptError::Failed(e.to_string()),
+            })?;
+        result
+            .as_bool()
+            .map_err(|type_name| ScriptError::NotBool(type_name.to_string()))
+    }
+}
+
+/// Returns the global token symbol cache
+pub fn token_symbols() -> &'static Mutex<HashMap<Address, String>> {
+    static TOKEN_SYMBOLS: OnceLock<Mutex<HashMap<Address, String>>> = OnceLock::new();
+    TOKEN_SYMBOLS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Reads a token's symbol, caching it for the session
+///
+/// A symbol that can't be read, like a `bytes32` one, is empty and read again next time.
+pub async fn token_symbol<M: Middleware + 'static>(client: Arc<M>, token: Address) -> String {
+    if let Some(symbol) = token_symbols()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&token)
+    {
+        return symbol.clone();
+    }
+    match ERC20::new(token, client).symbol().call().await {
+        Ok(symbol) => {
+            token_symbols()
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(token, symbol.clone());
+            symbol
+        }
+        Err(e) => {
+            tracing::debug!("[SCRIPT] Failed to read the symbol of {:?}: {:?}", token, e);
+            String::new()
+        }
+    }
+}
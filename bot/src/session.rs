@@ -0,0 +1,265 @@
+//! Session Reports
+//!
+//! When the bot exits, on a signal or a failure, a [SessionReport] recaps the session from what's
+//! already in memory: how long it ran, where candidates were dropped in the pipeline, the profit
+//! realized, the tokens most bundles were shipped for, and the failures counted along the way. It's
+//! logged as a single `[SESSION] Report` event, and posted to the notification webhook with
+//! `NOTIFY_SHUTDOWN_REPORT`, so operators get a recap without querying storage.
+//!
+//! Stages come from the `candidates_skipped_<stage>` counters recorded with every
+//! [crate::explain::DecisionReport], so they cover the whole session rather than only the
+//! explanations still kept.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounting::{self, Ledger},
+    metrics,
+    notify::Notifier,
+};
+
+/// How many tokens the report lists
+pub const TOP_TOKENS: usize = 5;
+
+/// The counter prefix of candidates skipped in each stage
+pub const SKIPPED_STAGE_PREFIX: &str = "candidates_skipped_";
+
+/// Bundles shipped for a token
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenActivity {
+    /// The token bought from the sandwiched pair
+    pub token: Address,
+    /// Bundles shipped
+    pub bundles: u64,
+    /// The summed expected value of those bundles, in wei
+    pub expected_value: U256,
+}
+
+/// The bundles shipped per token this session
+#[derive(Debug, Default)]
+pub struct SessionTokens {
+    tokens: Mutex<HashMap<Address, (u64, U256)>>,
+}
+
+impl SessionTokens {
+    /// Records a bundle shipped for `token`
+    pub fn record_shipped(&self, token: Address, expected_value: U256) {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let (bundles, value) = tokens.entry(token).or_default();
+        *bundles += 1;
+        *value = value.saturating_add(expected_value);
+    }
+
+    /// The `limit` tokens with the most bundles shipped, ties broken by expected value
+    pub fn top(&self, limit: usize) -> Vec<TokenActivity> {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let mut top = tokens
+            .iter()
+            .map(|(token, (bundles, expected_value))| TokenActivity {
+                token: *token,
+                bundles: *bundles,
+                expected_value: *expected_value,
+            })
+            .collect::<Vec<_>>();
+        top.sort_by(|a, b| {
+            (b.bundles, b.expected_value)
+                .cmp(&(a.bundles, a.expected_value))
+                .then(a.token.cmp(&b.token))
+        });
+        top.truncate(limit);
+        top
+    }
+}
+
+/// Returns the global per-token session activity
+pub fn session_tokens() -> &'static SessionTokens {
+    static SESSION_TOKENS: OnceLock<SessionTokens> = OnceLock::new();
+    SESSION_TOKENS.get_or_init(SessionTokens::default)
+}
+
+/// Whether a counter counts failures
+///
+/// Task restarts and stalls, and any counter naming a failure.
+pub fn is_error_metric(name: &str) -> bool {
+    let name = name.rsplit_once('.').map_or(name, |(_, name)| name);
+    name.contains("fail")
+        || name.starts_with("task_restarts_")
+        || name.starts_with("task_stalls_")
+        || name.starts_with("instance_restarts_")
+}
+
+/// A recap of the session
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionReport {
+    /// How long the bot ran, in seconds
+    pub uptime_secs: u64,
+    /// Candidates skipped in each stage, by stage
+    pub skipped: BTreeMap<String, u64>,
+    /// Candidates handed to submission
+    pub shipped: u64,
+    /// Candidates recorded instead of submitted, in observer mode
+    pub observed: u64,
+    /// The profit realized this session
+    pub profit: Ledger,
+    /// The profit of landed sandwiches still waiting for confirmations
+    pub pending_profit: Ledger,
+    /// The tokens most bundles were shipped for
+    pub top_tokens: Vec<TokenActivity>,
+    /// Failure counters, by name
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl SessionReport {
+    /// Builds a report from the session's counters
+    ///
+    /// Counters of named instances keep their `<instance>.` prefix.
+    pub fn from_counters(
+        uptime: Duration,
+        counters: &BTreeMap<String, u64>,
+        profit: Ledger,
+        pending_profit: Ledger,
+        top_tokens: Vec<TokenActivity>,
+    ) -> Self {
+        let mut report = Self {
+            uptime_secs: uptime.as_secs(),
+            profit,
+            pending_profit,
+            top_tokens,
+            ..Default::default()
+        };
+        for (name, value) in counters {
+            let (prefix, base) = match name.rsplit_once('.') {
+                Some((instance, base)) => (format!("{instance}."), base),
+                None => (String::new(), name.as_str()),
+            };
+            if let Some(stage) = base.strip_prefix(SKIPPED_STAGE_PREFIX) {
+                report.skipped.insert(format!("{prefix}{stage}"), *value);
+            } else if base == "candidates_shipped" {
+                report.shipped += value;
+            } else if base == "candidates_observed" {
+                report.observed += value;
+            } else if is_error_metric(name) && *value > 0 {
+                report.errors.insert(name.clone(), *value);
+            }
+        }
+        report
+    }
+
+    /// Captures the report from the global metrics, ledger and token activity
+    pub fn capture(uptime: Duration) -> Self {
+        Self::from_counters(
+            uptime,
+            &metrics::global().snapshot(),
+            accounting::ledger().snapshot(),
+            accounting::ledger().pending_snapshot(),
+            session_tokens().top(TOP_TOKENS),
+        )
+    }
+
+    /// Candidates evaluated, whatever became of them
+    pub fn evaluated(&self) -> u64 {
+        self.skipped.values().sum::<u64>() + self.shipped + self.observed
+    }
+
+    /// Logs the report as a single structured event
+    pub fn log(&self) {
+        tracing::info!(
+            uptime_secs = self.uptime_secs,
+            evaluated = self.evaluated(),
+            shipped = self.shipped,
+            observed = self.observed,
+            net_profit = %self.profit.net_profit(),
+            pending_profit = %self.pending_profit.net_profit(),
+            skipped = %serde_json::to_string(&self.skipped).unwrap_or_default(),
+            top_tokens = %serde_json::to_string(&self.top_tokens).unwrap_or_default(),
+            errors = %serde_json::to_string(&self.errors).unwrap_or_default(),
+            "[SESSION] Report"
+        );
+    }
+
+    /// A readable recap for the notification webhook
+    pub fn message(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "Session ended after {}h{:02}m",
+                self.uptime_secs / 3_600,
+                self.uptime_secs % 3_600 / 60
+            ),
+            format!(
+                "Candidates: {} evaluated, {} shipped, {} observed",
+                self.evaluated(),
+                self.shipped,
+                self.observed
+            ),
+            format!(
+                "Net profit: {} ETH realized, {} ETH pending",
+                format_ether(self.profit.net_profit()),
+                format_ether(self.pending_profit.net_profit())
+            ),
+        ];
+        let mut skipped = self.skipped.iter().collect::<Vec<_>>();
+        skipped.sort_by(|a, b| b.1.cmp(a.1));
+        if !skipped.is_empty() {
+            let stages = skipped
+                .iter()
+                .take(TOP_TOKENS)
+                .map(|(stage, count)| format!("{stage} {count}"))
+                .collect::<Vec<_>>();
+            lines.push(format!("Most skipped at: {}", stages.join(", ")));
+        }
+        if !self.top_tokens.is_empty() {
+            let tokens = self
+                .top_tokens
+                .iter()
+                .map(|t| format!("{:?} ({})", t.token, t.bundles))
+                .collect::<Vec<_>>();
+            lines.push(format!("Top tokens: {}", tokens.join(", ")));
+        }
+        if !self.errors.is_empty() {
+            let errors = self
+                .errors
+                .iter()
+                .map(|(name, count)| format!("{name} {count}"))
+                .collect::<Vec<_>>();
+            lines.push(format!("Errors: {}", errors.join(", ")));
+        }
+        lines.join("\n")
+    }
+}
+
+fn format_ether(wei: U256) -> String {
+    ethers::utils::format_units(wei, "ether").unwrap_or_else(|_| wei.to_string())
+}
+
+/// Waits for Ctrl-C, or `SIGTERM` on unix
+pub async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+/// Logs the session report, posting it to `notifier` if given
+pub async fn report_shutdown(uptime: Duration, notifier: Option<&Notifier>) {
+    let report = SessionReport::capture(uptime);
+    report.log();
+    if let Some(notifier) = notifier {
+        notifier.notify(&report.message()).await;
+    }
+}
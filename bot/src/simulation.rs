@@ -0,0 +1,496 @@
+//! Victim Simulation
+//!
+//! A sandwich is only valid if the victim's swap succeeds between our legs. Victims with expired
+//! permits, insufficient balances or fee-on-transfer paths revert, taking the bundle down with
+//! them. The victim is simulated alone against pending state before any legs are signed, and its
+//! output is checked against the swap we decoded.
+//!
+//! Bundle simulations are cached by the sandwich, its victim, pair and leg amounts, and the state
+//! they ran against. A sandwich rebuilt in the same block on the same reserves, like on fresh
+//! nonces, reuses its simulation.
+//!
+//! Other pending swaps through the sandwiched pair may be ordered ahead of the bundle. The
+//! [CompetingSwaps] seen recently can be applied to the reserves before pricing, so profit isn't
+//! overestimated when the pair is busy.
+//!
+//! Once the bribe is priced, the bribed bundle is simulated again and the builder payment reported
+//! by the relay is checked against [BuilderPaymentLimits] before submission. Right before each
+//! relay submission, the [VictimRecheck] confirms the victim still clears its minimum behind our
+//! frontrun at the pair's current reserves.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ethers::{
+    abi::{ParamType, Token},
+    prelude::*,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::state::StateReader;
+
+/// The default tolerance between the decoded and simulated victim output, in bips
+pub const DEFAULT_VICTIM_TOLERANCE_BPS: u64 = 100;
+
+/// The default number of cached bundle simulations
+pub const DEFAULT_SIMULATION_CACHE_SIZE: usize = 1_024;
+
+/// The default tolerance between the simulated and expected builder payment, in bips
+pub const DEFAULT_PAYMENT_TOLERANCE_BPS: u64 = 100;
+
+/// How long a pending swap is considered competition, one slot
+pub const COMPETING_SWAP_TTL_SECS: u64 = 12;
+
+/// Why a simulated victim doesn't match its decoded swap
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum VictimMismatch {
+    /// The router returned no amounts for the path
+    #[error("simulated swap returned {0} amounts")]
+    MissingAmounts(usize),
+    /// The simulated input isn't the transaction value
+    #[error("simulated input {simulated} differs from the decoded {decoded}")]
+    AmountIn {
+        /// The decoded input
+        decoded: U256,
+        /// The simulated input
+        simulated: U256,
+    },
+    /// The simulated final output is below the victim's minimum
+    #[error("simulated output {simulated} is below the minimum {min}")]
+    BelowMinimum {
+        /// The victim's minimum output
+        min: U256,
+        /// The simulated output
+        simulated: U256,
+    },
+    /// The sandwiched pool moved differently than the reserves predict
+    #[error("simulated pool output {simulated} diverges from the expected {expected}")]
+    Diverged {
+        /// The output predicted from the reserves
+        expected: U256,
+        /// The simulated output
+        simulated: U256,
+    },
+}
+
+impl VictimMismatch {
+    /// The metric counting skips for this mismatch
+    pub fn metric(&self) -> &'static str {
+        match self {
+            VictimMismatch::MissingAmounts(_) => "victim_sim_missing_amounts",
+            VictimMismatch::AmountIn { .. } => "victim_sim_amount_in",
+            VictimMismatch::BelowMinimum { .. } => "victim_sim_below_minimum",
+            VictimMismatch::Diverged { .. } => "victim_sim_diverged",
+        }
+    }
+}
+
+/// Simulates a pending router swap on its own against pending state, returning the path amounts
+///
+/// Errors if the call reverts.
+pub async fn simulate_victim<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx: &Transaction,
+) -> Result<Vec<U256>> {
+    let mut request = TransactionRequest::new()
+        .from(tx.from)
+        .value(tx.value)
+        .data(tx.input.clone())
+        .gas(tx.gas);
+    if let Some(to) = tx.to {
+        request = request.to(to);
+    }
+    let output = client
+        .call(&request.into(), Some(BlockNumber::Pending.into()))
+        .await
+        .map_err(|e| eyre::eyre!("Victim simulation reverted: {:?}", e))?;
+    decode_amounts(&output)
+}
+
+/// Decodes the `uint256[] amounts` returned by the router's swap functions
+pub fn decode_amounts(output: &[u8]) -> Result<Vec<U256>> {
+    let tokens = ethers::abi::decode(&[ParamType::Array(Box::new(ParamType::Uint(256)))], output)
+        .map_err(|e| eyre::eyre!("Failed to decode swap amounts: {:?}", e))?;
+    match tokens.into_iter().next() {
+        Some(Token::Array(amounts)) => {
+            Ok(amounts.into_iter().filter_map(Token::into_uint).collect())
+        }
+        _ => eyre::bail!("Swap returned no amounts"),
+    }
+}
+
+/// Checks simulated path amounts against the decoded swap
+///
+/// `expected_out` is the first hop's output predicted from the sandwiched pair's reserves. The
+/// simulated output may differ from it by `tolerance_bps`, since pending state can include other
+/// swaps through the pair.
+pub fn verify_victim_amounts(
+    amounts: &[U256],
+    amount_in: U256,
+    amount_out_min: U256,
+    expected_out: U256,
+    tolerance_bps: u64,
+) -> Result<(), VictimMismatch> {
+    let (simulated_in, simulated_out, final_out) = match amounts {
+        [first, second, rest @ ..] => (*first, *second, *rest.last().unwrap_or(second)),
+        _ => return Err(VictimMismatch::MissingAmounts(amounts.len())),
+    };
+    if simulated_in != amount_in {
+        return Err(VictimMismatch::AmountIn {
+            decoded: amount_in,
+            simulated: simulated_in,
+        });
+    }
+    if final_out < amount_out_min {
+        return Err(VictimMismatch::BelowMinimum {
+            min: amount_out_min,
+            simulated: final_out,
+        });
+    }
+    let difference = if simulated_out > expected_out {
+        simulated_out - expected_out
+    } else {
+        expected_out - simulated_out
+    };
+    if difference.saturating_mul(U256::from(10_000)) > expected_out * tolerance_bps {
+        return Err(VictimMismatch::Diverged {
+            expected: expected_out,
+            simulated: simulated_out,
+        });
+    }
+    Ok(())
+}
+
+/// The victim would receive less than its minimum behind our frontrun
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+#[error("victim would receive {expected} behind the frontrun, below its minimum {min}")]
+pub struct VictimMinViolation {
+    /// The victim's minimum output from the sandwiched pair
+    pub min: U256,
+    /// The output the victim would receive
+    pub expected: U256,
+}
+
+/// Rechecks a sandwich's victim against the pair's reserves at submission time
+///
+/// The victim is priced against reserves read when the candidate was evaluated. If the pair moves
+/// while the bundle is held for a relay's cutoff, the victim may no longer clear its minimum behind
+/// our frontrun, reverting and taking the bundle with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VictimRecheck {
+    /// The sandwiched pair
+    pub pair: Address,
+    /// The WETH address
+    pub weth: Address,
+    /// The token the victim buys
+    pub token: Address,
+    /// Our frontrun's WETH input
+    pub frontrun_weth_in: U256,
+    /// The victim's WETH input
+    pub user_amount_in: U256,
+    /// The victim's minimum output from the sandwiched pair
+    pub user_min_recv: U256,
+}
+
+impl VictimRecheck {
+    /// Checks the victim behind our frontrun at the given reserves, returning its output
+    pub fn check(
+        &self,
+        weth_reserves: U256,
+        token_reserves: U256,
+    ) -> Result<U256, VictimMinViolation> {
+        let (_, weth_reserves, token_reserves) = crate::uniswap::get_univ2_data_given_in(
+            &self.frontrun_weth_in,
+            &weth_reserves,
+            &token_reserves,
+        );
+        let (expected, _, _) = crate::uniswap::get_univ2_data_given_in(
+            &self.user_amount_in,
+            &weth_reserves,
+            &token_reserves,
+        );
+        if expected < self.user_min_recv {
+            return Err(VictimMinViolation {
+                min: self.user_min_recv,
+                expected,
+            });
+        }
+        Ok(expected)
+    }
+
+    /// Checks the victim against the pair's current reserves
+    pub async fn check_current<S: StateReader + ?Sized>(
+        &self,
+        state: &S,
+    ) -> Result<Result<U256, VictimMinViolation>> {
+        let reserves = state.reserves(self.pair, None).await?;
+        let (weth_reserves, token_reserves) =
+            crate::uniswap::SortedPair::new(self.weth, self.token).orient(self.weth, reserves);
+        Ok(self.check(weth_reserves, token_reserves))
+    }
+}
+
+/// Hashes a bundle's signed transactions in order
+pub fn bundle_hash(transactions: &[Bytes]) -> H256 {
+    let hashes = transactions
+        .iter()
+        .flat_map(|tx| ethers::utils::keccak256(tx))
+        .collect::<Vec<u8>>();
+    H256::from(ethers::utils::keccak256(hashes))
+}
+
+/// The state a bundle was simulated against
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StateSnapshot {
+    /// The block the state was read at
+    pub block: U64,
+    /// The sandwiched pair's reserves at that block
+    pub reserves: (U256, U256),
+}
+
+/// What a sandwich simulation depends on besides the state it ran against
+///
+/// Legs are re-signed whenever their nonce or fees change, so the signed bundle's hash would miss
+/// the cache for the same sandwich.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimulationKey {
+    /// The victim transaction
+    pub victim: TxHash,
+    /// The pair sandwiched
+    pub pair: Address,
+    /// The frontrun's input
+    pub frontrun_in: U256,
+    /// The backrun's input
+    pub backrun_in: U256,
+}
+
+/// Caches sandwich simulations by [SimulationKey] and state snapshot
+#[derive(Debug, Clone)]
+pub struct SimulationCache<T> {
+    capacity: usize,
+    entries: HashMap<SimulationKey, (StateSnapshot, T)>,
+}
+
+impl<T: Clone> SimulationCache<T> {
+    /// Creates a cache holding up to `capacity` simulations
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached simulation of a sandwich if it ran against `snapshot`
+    ///
+    /// Other transactions in a later block can change the outcome even when the pair's reserves
+    /// haven't moved, so both the block and the reserves must match.
+    pub fn get(&self, key: &SimulationKey, snapshot: &StateSnapshot) -> Option<T> {
+        let (cached, result) = self.entries.get(key)?;
+        if cached.block != snapshot.block || cached.reserves != snapshot.reserves {
+            return None;
+        }
+        Some(result.clone())
+    }
+
+    /// Caches the simulation of a sandwich against `snapshot`
+    ///
+    /// When full, entries from the oldest block are evicted first.
+    pub fn insert(&mut self, key: SimulationKey, snapshot: StateSnapshot, result: T) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (s, _))| s.block)
+                .map(|(hash, _)| *hash);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key, (snapshot, result));
+    }
+
+    /// The number of cached simulations
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no simulations are cached
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A pending WETH to token swap through a pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingSwap {
+    /// The pending transaction
+    pub hash: TxHash,
+    /// The pair swapped through
+    pub pair: Address,
+    /// The WETH swapped in
+    pub weth_in: U256,
+    /// The priority fee offered, or the gas price of legacy transactions
+    pub priority_fee: U256,
+    /// When the swap was seen
+    pub seen_at: Instant,
+}
+
+impl PendingSwap {
+    /// The priority fee a transaction offers
+    pub fn priority_fee_of(tx: &Transaction) -> U256 {
+        tx.max_priority_fee_per_gas
+            .or(tx.gas_price)
+            .unwrap_or_default()
+    }
+}
+
+/// Recently seen pending swaps, by pair
+#[derive(Debug, Clone, Default)]
+pub struct CompetingSwaps {
+    swaps: HashMap<Address, Vec<PendingSwap>>,
+}
+
+impl CompetingSwaps {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a pending swap, forgetting swaps older than a slot
+    pub fn observe(&mut self, swap: PendingSwap) {
+        let ttl = Duration::from_secs(COMPETING_SWAP_TTL_SECS);
+        self.swaps.retain(|_, swaps| {
+            swaps.retain(|s| swap.seen_at.saturating_duration_since(s.seen_at) < ttl);
+            !swaps.is_empty()
+        });
+        let swaps = self.swaps.entry(swap.pair).or_default();
+        if !swaps.iter().any(|s| s.hash == swap.hash) {
+            swaps.push(swap);
+        }
+    }
+
+    /// The other swaps through `pair` paying at least `min_priority_fee`, highest paying first
+    pub fn competitors(
+        &self,
+        pair: Address,
+        victim: TxHash,
+        min_priority_fee: U256,
+    ) -> Vec<PendingSwap> {
+        let mut competitors = self
+            .swaps
+            .get(&pair)
+            .map(|swaps| {
+                swaps
+                    .iter()
+                    .filter(|s| s.hash != victim && s.priority_fee >= min_priority_fee)
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        competitors.sort_by(|a, b| b.priority_fee.cmp(&a.priority_fee));
+        competitors
+    }
+}
+
+/// Applies competing WETH to token swaps to a pair's `(weth, token)` reserves
+pub fn apply_competing_swaps(
+    weth_reserves: U256,
+    token_reserves: U256,
+    swaps: &[PendingSwap],
+) -> (U256, U256) {
+    swaps
+        .iter()
+        .fold((weth_reserves, token_reserves), |(weth, token), swap| {
+            let (_, weth, token) =
+                crate::uniswap::get_univ2_data_given_in(&swap.weth_in, &weth, &token);
+            (weth, token)
+        })
+}
+
+/// Why a simulated builder payment doesn't match the bribe
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum BuilderPaymentError {
+    /// The bundle pays the builder less than the minimum
+    #[error("builder payment {payment} is below the minimum {min}")]
+    BelowMinimum {
+        /// The bundle's simulated coinbase difference
+        payment: U256,
+        /// The minimum
+        min: U256,
+    },
+    /// Our legs pay the builder differently than the bribe math predicts
+    #[error("legs paid the builder {simulated}, expected {expected}")]
+    Mismatch {
+        /// The payment predicted from the priority fees
+        expected: U256,
+        /// Our legs' simulated coinbase difference
+        simulated: U256,
+    },
+}
+
+impl BuilderPaymentError {
+    /// The metric counting skips for this reason
+    pub fn metric(&self) -> &'static str {
+        match self {
+            BuilderPaymentError::BelowMinimum { .. } => "builder_payment_below_minimum",
+            BuilderPaymentError::Mismatch { .. } => "builder_payment_mismatch",
+        }
+    }
+}
+
+/// The builder payment checks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BuilderPaymentLimits {
+    /// The least a bundle must pay the builder, in wei
+    pub min_payment: U256,
+    /// How far our legs' simulated payment may drift from the bribe math, in bips
+    pub tolerance_bps: u64,
+}
+
+impl Default for BuilderPaymentLimits {
+    fn default() -> Self {
+        Self {
+            min_payment: U256::zero(),
+            tolerance_bps: DEFAULT_PAYMENT_TOLERANCE_BPS,
+        }
+    }
+}
+
+impl BuilderPaymentLimits {
+    /// Checks a simulated bundle's builder payment
+    ///
+    /// `payment` is the whole bundle's coinbase difference, priority fees and coinbase transfers
+    /// included. `simulated` is our legs' share of it and `expected` what the bribe math predicts.
+    pub fn check(
+        &self,
+        payment: U256,
+        simulated: U256,
+        expected: U256,
+    ) -> Result<(), BuilderPaymentError> {
+        if payment < self.min_payment {
+            return Err(BuilderPaymentError::BelowMinimum {
+                payment,
+                min: self.min_payment,
+            });
+        }
+        let difference = if simulated > expected {
+            simulated - expected
+        } else {
+            expected - simulated
+        };
+        if difference.saturating_mul(U256::from(10_000))
+            > expected.saturating_mul(self.tolerance_bps.into())
+        {
+            return Err(BuilderPaymentError::Mismatch {
+                expected,
+                simulated,
+            });
+        }
+        Ok(())
+    }
+}
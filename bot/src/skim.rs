@@ -0,0 +1,259 @@
+//! Profit Skimming
+//!
+//! Backrun proceeds accumulate as WETH on the executor contract, and the searcher wallet is a hot
+//! key. With `SKIM_TO` set, a periodic task moves the executor's WETH above the working capital,
+//! and optionally the searcher's ETH above a float, to that cold address. Every skim is alerted.
+//!
+//! In manual mode the task only alerts about skimmable profit, and `subway skim` sends it.
+//!
+//! In-flight bundles trade the executor's WETH and use the searcher wallets' nonces, so the task
+//! waits until none are pending, and takes its nonces from the factory's nonce manager.
+
+use std::{sync::Arc, time::Duration};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::ERC20,
+    control::send_executor_call,
+    metrics,
+    notify::Notifier,
+    transactions::{LegFees, TxFactory},
+};
+
+/// How often skimmable profit is checked, in seconds
+pub const DEFAULT_SKIM_SECS: u64 = 3_600;
+
+/// The gas limit for an executor withdrawal
+pub const WITHDRAWAL_GAS_LIMIT: u64 = 80_000;
+
+/// The gas limit for a plain ETH transfer
+pub const TRANSFER_GAS_LIMIT: u64 = 21_000;
+
+/// The profit skimming settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SkimConfig {
+    /// The cold address profit is sent to, skimming is disabled without one
+    pub to: Option<Address>,
+    /// The WETH left on the executor for trading, in wei
+    pub working_capital: U256,
+    /// The ETH left in the searcher wallet, in wei, or `None` to never skim the wallet
+    pub wallet_float: Option<U256>,
+    /// The smallest amount worth a transaction, in wei
+    pub min_skim: U256,
+    /// How often skimmable profit is checked, in seconds
+    pub interval_secs: u64,
+    /// Whether the periodic task only alerts, leaving transfers to `subway skim`
+    pub manual: bool,
+}
+
+impl Default for SkimConfig {
+    fn default() -> Self {
+        Self {
+            to: None,
+            working_capital: U256::zero(),
+            wallet_float: None,
+            min_skim: U256::zero(),
+            interval_secs: DEFAULT_SKIM_SECS,
+            manual: false,
+        }
+    }
+}
+
+/// What a skim would move to the cold address
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SkimPlan {
+    /// WETH withdrawn from the executor
+    pub executor_weth: Option<U256>,
+    /// ETH sent from the searcher wallet
+    pub wallet_eth: Option<U256>,
+}
+
+impl SkimPlan {
+    /// Whether there's nothing to skim
+    pub fn is_empty(&self) -> bool {
+        self.executor_weth.is_none() && self.wallet_eth.is_none()
+    }
+}
+
+/// Returns what `balance` holds above `keep`, if that's at least `min_skim`
+pub fn skimmable(balance: U256, keep: U256, min_skim: U256) -> Option<U256> {
+    balance
+        .checked_sub(keep)
+        .filter(|amount| !amount.is_zero() && *amount >= min_skim)
+}
+
+/// Plans a skim from the executor's WETH and the searcher's ETH balances
+pub fn plan_skim(executor_weth: U256, wallet_eth: U256, config: &SkimConfig) -> SkimPlan {
+    SkimPlan {
+        executor_weth: skimmable(executor_weth, config.working_capital, config.min_skim),
+        wallet_eth: config
+            .wallet_float
+            .and_then(|float| skimmable(wallet_eth, float, config.min_skim)),
+    }
+}
+
+/// Reads the balances and plans a skim
+pub async fn check_skim<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: &TxFactory,
+    weth: Address,
+    config: &SkimConfig,
+) -> Result<SkimPlan> {
+    let executor_weth = ERC20::new(weth, client.clone())
+        .balance_of(tx_factory.executor.address())
+        .call()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch executor balance: {:?}", e))?;
+    let wallet_eth = client
+        .get_balance(tx_factory.searcher(), None)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch searcher balance: {:?}", e))?;
+    Ok(plan_skim(executor_weth, wallet_eth, config))
+}
+
+/// Sends a planned skim to `to`, returning the transaction hashes
+///
+/// Both transactions take their nonce from the nonce manager, so the wallet transfer queues behind
+/// the withdrawal.
+pub async fn send_skim<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: &TxFactory,
+    weth: Address,
+    to: Address,
+    plan: &SkimPlan,
+) -> Result<Vec<TxHash>> {
+    let mut hashes = Vec::new();
+    if let Some(amount) = plan.executor_weth {
+        let data = tx_factory.executor.encode_withdrawal(weth, to, amount)?;
+        hashes.push(
+            send_executor_call(client.clone(), tx_factory, data, WITHDRAWAL_GAS_LIMIT).await?,
+        );
+    }
+    if let Some(amount) = plan.wallet_eth {
+        let (nonce, _nonce_guard) = tx_factory
+            .nonces
+            .direct(&*client, tx_factory.searcher())
+            .await?;
+        let base_fee = client
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch latest block: {:?}", e))?
+            .and_then(|b| b.base_fee_per_gas)
+            .ok_or_else(|| eyre::eyre!("Latest block is missing a base fee"))?;
+        let fees = LegFees {
+            max_fee_per_gas: base_fee * 2,
+            max_priority_fee_per_gas: U256::from(1_000_000_000u64),
+            gas_limit: U256::from(TRANSFER_GAS_LIMIT),
+        };
+        let tx = tx_factory.call(to, Bytes::default(), amount, nonce, &fees);
+        let signed = tx_factory.sign(&tx).await?;
+        let pending = client
+            .send_raw_transaction(signed)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to send skim transaction: {:?}", e))?;
+        hashes.push(*pending);
+    }
+    Ok(hashes)
+}
+
+/// Whether any searcher wallet has bundles in flight for blocks that aren't mined yet
+pub async fn has_pending_bundles<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: &TxFactory,
+) -> Result<bool> {
+    let head = client
+        .get_block_number()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch block number: {:?}", e))?;
+    Ok(tx_factory
+        .searchers()
+        .into_iter()
+        .any(|wallet| tx_factory.nonces.is_pending(wallet, head + 1)))
+}
+
+/// Describes a skim for alerts
+pub fn describe_skim(plan: &SkimPlan, to: Address) -> String {
+    let mut parts = Vec::new();
+    if let Some(amount) = plan.executor_weth {
+        parts.push(format!("{} wei of WETH from the executor", amount));
+    }
+    if let Some(amount) = plan.wallet_eth {
+        parts.push(format!("{} wei of ETH from the searcher", amount));
+    }
+    format!("{} to {:?}", parts.join(" and "), to)
+}
+
+/// Checks skimmable profit and skims it, or only alerts in manual mode, returning the sent hashes
+pub async fn skim_profit<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: &TxFactory,
+    weth: Address,
+    config: &SkimConfig,
+    notifier: &Notifier,
+    manual: bool,
+) -> Result<Vec<TxHash>> {
+    let to = config
+        .to
+        .ok_or_else(|| eyre::eyre!("\"SKIM_TO\" is not set"))?;
+    if !manual && has_pending_bundles(client.clone(), tx_factory).await? {
+        metrics::increment("profit_skims_deferred");
+        return Ok(Vec::new());
+    }
+    let plan = check_skim(client.clone(), tx_factory, weth, config).await?;
+    if plan.is_empty() {
+        return Ok(Vec::new());
+    }
+    if manual {
+        notifier
+            .notify(&format!(
+                "Profit to skim: {}, run `subway skim`",
+                describe_skim(&plan, to)
+            ))
+            .await;
+        return Ok(Vec::new());
+    }
+    let hashes = send_skim(client, tx_factory, weth, to, &plan).await?;
+    metrics::increment("profit_skims");
+    notifier
+        .notify(&format!(
+            "Skimming {}: {:?}",
+            describe_skim(&plan, to),
+            hashes
+        ))
+        .await;
+    Ok(hashes)
+}
+
+/// Skims profit every `config.interval_secs`
+///
+/// A failed skim is alerted and retried on the next tick, rather than restarting the task.
+pub async fn watch_profit_skim<M: Middleware + 'static>(
+    client: Arc<M>,
+    tx_factory: Arc<TxFactory>,
+    weth: Address,
+    config: SkimConfig,
+    notifier: Notifier,
+) -> Result<()> {
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    loop {
+        ticker.tick().await;
+        if let Err(e) = skim_profit(
+            client.clone(),
+            &tx_factory,
+            weth,
+            &config,
+            &notifier,
+            config.manual,
+        )
+        .await
+        {
+            metrics::increment("profit_skim_failures");
+            notifier
+                .notify(&format!("Failed to skim profit: {:?}", e))
+                .await;
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Chain Data Snapshots
+//!
+//! A fresh instance starts with empty caches, and its first candidates each wait on requests for
+//! pair tokens and decimals that a running instance already knows. A [ChainSnapshot] exports the
+//! immutable data the [CachedReader] has read, along with the senders marked as baiters, so a
+//! restarted or newly deployed instance can load it at startup and warm up in seconds.
+//!
+//! Snapshots are tied to a chain id, and a snapshot of another chain or format version is refused
+//! rather than primed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    opportunity::unix_millis,
+    safety,
+    state::{CachedData, CachedReader, StateReader},
+};
+
+/// The snapshot format version
+pub const CHAIN_SNAPSHOT_VERSION: u32 = 1;
+
+/// The default interval between chain data snapshots, in seconds
+pub const DEFAULT_CHAIN_SNAPSHOT_SECS: u64 = 300;
+
+/// The chain data a warmed up instance has learned
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainSnapshot {
+    /// The snapshot format version
+    pub version: u32,
+    /// The chain the data was read from
+    pub chain_id: u64,
+    /// When the snapshot was taken, in milliseconds since the unix epoch
+    pub timestamp: u64,
+    /// The pair tokens and token decimals
+    pub cache: CachedData,
+    /// Senders known to bait sandwich bots
+    pub baiters: Vec<Address>,
+}
+
+impl ChainSnapshot {
+    /// Captures the data cached by `reader` and the global baiter registry
+    pub fn capture<R: StateReader>(reader: &CachedReader<R>, chain_id: u64) -> Self {
+        Self {
+            version: CHAIN_SNAPSHOT_VERSION,
+            chain_id,
+            timestamp: unix_millis(),
+            cache: reader.cached_data(),
+            baiters: safety::baiters().list(),
+        }
+    }
+
+    /// Primes `reader` and the global baiter registry, returning how many cache entries were new
+    ///
+    /// Fails without priming anything if the snapshot is of another chain or format version.
+    pub fn prime<R: StateReader>(self, reader: &CachedReader<R>, chain_id: u64) -> Result<usize> {
+        if self.version != CHAIN_SNAPSHOT_VERSION {
+            eyre::bail!(
+                "Chain snapshot version {} isn't supported, expected {}",
+                self.version,
+                CHAIN_SNAPSHOT_VERSION
+            );
+        }
+        if self.chain_id != chain_id {
+            eyre::bail!(
+                "Chain snapshot is of chain {}, not {}",
+                self.chain_id,
+                chain_id
+            );
+        }
+        for baiter in self.baiters {
+            safety::baiters().mark(baiter);
+        }
+        Ok(reader.prime(self.cache))
+    }
+
+    /// Saves the snapshot, replacing the file atomically
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = PathBuf::from(path);
+        tmp.set_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Loads a snapshot, or `None` if the file doesn't exist yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes).map_err(|e| {
+                eyre::eyre!("Invalid chain snapshot in {:?}: {:?}", path, e)
+            })?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Saves a snapshot of `reader`'s cached data to `path` every `interval`
+pub async fn persist_chain_snapshots<R: StateReader>(
+    reader: Arc<CachedReader<R>>,
+    chain_id: u64,
+    path: PathBuf,
+    interval: Duration,
+) -> Result<()> {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick is immediate, and there's nothing new to save yet
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        if let Err(e) = ChainSnapshot::capture(&reader, chain_id).save(&path) {
+            tracing::warn!(
+                "[SNAPSHOT] Failed to save chain data to {:?}: {:?}",
+                path,
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Spam Protection
+//!
+//! Flooding the mempool with decodable but worthless swaps is a cheap way to stall a sandwich bot,
+//! since every candidate costs several RPC round trips to evaluate. The [SpamGuard] runs right
+//! after decoding and drops dust swaps and senders or tokens that exceed their rate limit.
+//!
+//! Other searchers' swaps set slippage so tight there's nothing to sandwich. The [BotRegistry]
+//! skips configured bot senders and recipient contracts, and learns senders whose swaps keep
+//! leaving no room.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::safety::AddressList;
+
+/// The default swaps a sender may submit per window
+pub const DEFAULT_SENDER_LIMIT: u32 = 5;
+
+/// The default swaps into a token evaluated per window
+pub const DEFAULT_TOKEN_LIMIT: u32 = 20;
+
+/// The default rate limit window, one slot
+pub const DEFAULT_SPAM_WINDOW_SECS: u64 = 12;
+
+/// The default minimum victim swap value, 0.01 ether, below which a sandwich can't cover its gas
+pub const DEFAULT_MIN_VICTIM_VALUE: u128 = 10_000_000_000_000_000;
+
+/// The default swaps without slippage room after which a sender is treated as a bot
+pub const DEFAULT_BOT_STRIKES: u32 = 3;
+
+/// How many keys a limiter or the bot registry tracks per generation
+///
+/// Keys are kept in a current and a previous generation. Rotating drops the previous one whole,
+/// so memory stays bounded under a flood of fresh senders without scanning for stale keys.
+pub const GENERATION_CAPACITY: usize = 16_384;
+
+/// How many learned bots are remembered, the earliest learned being forgotten first
+pub const MAX_LEARNED_BOTS: usize = 16_384;
+
+/// The spam protection limits
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SpamLimits {
+    /// Swaps a sender may submit per window
+    pub sender_limit: u32,
+    /// Swaps into a token evaluated per window
+    pub token_limit: u32,
+    /// The rate limit window, in seconds
+    pub window_secs: u64,
+    /// The minimum victim swap value, in wei
+    pub min_value: U256,
+}
+
+impl Default for SpamLimits {
+    fn default() -> Self {
+        Self {
+            sender_limit: DEFAULT_SENDER_LIMIT,
+            token_limit: DEFAULT_TOKEN_LIMIT,
+            window_secs: DEFAULT_SPAM_WINDOW_SECS,
+            min_value: U256::from(DEFAULT_MIN_VICTIM_VALUE),
+        }
+    }
+}
+
+/// Why a swap was dropped as spam
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpamRejection {
+    /// The swap is too small to sandwich profitably
+    Dust,
+    /// The sender exceeded its rate limit
+    SenderLimited,
+    /// The output token exceeded its rate limit
+    TokenLimited,
+    /// The sender or recipient is a known searcher
+    KnownBot,
+}
+
+impl SpamRejection {
+    /// The metric counting drops for this reason
+    pub fn metric(&self) -> &'static str {
+        match self {
+            SpamRejection::Dust => "spam_dust",
+            SpamRejection::SenderLimited => "spam_sender_limited",
+            SpamRejection::TokenLimited => "spam_token_limited",
+            SpamRejection::KnownBot => "spam_known_bot",
+        }
+    }
+}
+
+/// A fixed-window rate limiter keyed by address
+///
+/// Generations rotate every window, or early once [GENERATION_CAPACITY] keys are current, which
+/// can forget a key's count under a flood of fresh senders.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    current: HashMap<Address, (Instant, u32)>,
+    previous: HashMap<Address, (Instant, u32)>,
+    rotated_at: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `limit` hits per `window`
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            current: HashMap::new(),
+            previous: HashMap::new(),
+            rotated_at: None,
+        }
+    }
+
+    /// Counts a hit for `key`, returning whether it's within the limit
+    pub fn check(&mut self, key: Address, now: Instant) -> bool {
+        let rotated_at = *self.rotated_at.get_or_insert(now);
+        if now.saturating_duration_since(rotated_at) >= self.window
+            || self.current.len() >= GENERATION_CAPACITY
+        {
+            self.previous = std::mem::take(&mut self.current);
+            self.rotated_at = Some(now);
+        }
+        if let Some(window) = self.previous.remove(&key) {
+            self.current.insert(key, window);
+        }
+        let (start, count) = self.current.entry(key).or_insert((now, 0));
+        if now.saturating_duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        *count = count.saturating_add(1);
+        *count <= self.limit
+    }
+
+    /// The number of keys being tracked
+    pub fn len(&self) -> usize {
+        self.current.len() + self.previous.len()
+    }
+
+    /// Whether no keys are being tracked
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty() && self.previous.is_empty()
+    }
+}
+
+/// Drops dust and rate-limited swaps before they're evaluated
+#[derive(Debug, Clone)]
+pub struct SpamGuard {
+    min_value: U256,
+    senders: RateLimiter,
+    tokens: RateLimiter,
+}
+
+impl SpamGuard {
+    /// Creates a guard enforcing `limits`
+    pub fn new(limits: SpamLimits) -> Self {
+        let window = Duration::from_secs(limits.window_secs);
+        Self {
+            min_value: limits.min_value,
+            senders: RateLimiter::new(limits.sender_limit, window),
+            tokens: RateLimiter::new(limits.token_limit, window),
+        }
+    }
+
+    /// Checks a swap of `value` from `sender` into `token`
+    ///
+    /// Swaps dropped for their sender don't count against the token, so one spammer can't exhaust
+    /// a token's limit for everyone else.
+    pub fn check(
+        &mut self,
+        sender: Address,
+        token: Address,
+        value: U256,
+        now: Instant,
+    ) -> Result<(), SpamRejection> {
+        if value < self.min_value {
+            return Err(SpamRejection::Dust);
+        }
+        if !self.senders.check(sender, now) {
+            return Err(SpamRejection::SenderLimited);
+        }
+        if !self.tokens.check(token, now) {
+            return Err(SpamRejection::TokenLimited);
+        }
+        Ok(())
+    }
+}
+
+/// Known and learned searcher bots
+///
+/// Strikes of senders not yet learned are kept in generations of [GENERATION_CAPACITY], and at
+/// most [MAX_LEARNED_BOTS] learned bots are remembered.
+#[derive(Debug, Clone)]
+pub struct BotRegistry {
+    known: HashSet<Address>,
+    strikes: HashMap<Address, u32>,
+    previous_strikes: HashMap<Address, u32>,
+    learned: HashSet<Address>,
+    learned_order: VecDeque<Address>,
+    strike_limit: u32,
+}
+
+impl BotRegistry {
+    /// Creates a registry of `known` bots, learning senders after `strike_limit` strikes
+    ///
+    /// A `strike_limit` of 0 turns learning off.
+    pub fn new(known: &AddressList, strike_limit: u32) -> Self {
+        Self {
+            known: known.0.iter().copied().collect(),
+            strikes: HashMap::new(),
+            previous_strikes: HashMap::new(),
+            learned: HashSet::new(),
+            learned_order: VecDeque::new(),
+            strike_limit,
+        }
+    }
+
+    /// Checks a swap from `sender` paying out to `recipient`
+    pub fn check(&self, sender: &Address, recipient: &Address) -> Result<(), SpamRejection> {
+        if self.learned.contains(sender)
+            || self.known.contains(sender)
+            || self.known.contains(recipient)
+        {
+            return Err(SpamRejection::KnownBot);
+        }
+        Ok(())
+    }
+
+    /// Records a swap from `sender` that left no slippage to sandwich, returning whether the
+    /// sender was just learned as a bot
+    pub fn strike(&mut self, sender: Address) -> bool {
+        if self.strike_limit == 0 || self.learned.contains(&sender) {
+            return false;
+        }
+        if self.strikes.len() >= GENERATION_CAPACITY {
+            self.previous_strikes = std::mem::take(&mut self.strikes);
+        }
+        let previous = self.previous_strikes.remove(&sender).unwrap_or(0);
+        let strikes = self.strikes.entry(sender).or_insert(previous);
+        *strikes = strikes.saturating_add(1);
+        if *strikes < self.strike_limit {
+            return false;
+        }
+        self.strikes.remove(&sender);
+        if self.learned_order.len() >= MAX_LEARNED_BOTS {
+            if let Some(earliest) = self.learned_order.pop_front() {
+                self.learned.remove(&earliest);
+            }
+        }
+        self.learned.insert(sender);
+        self.learned_order.push_back(sender);
+        true
+    }
+
+    /// Forgets the strikes of a sender whose swap could be sandwiched
+    pub fn clear(&mut self, sender: &Address) {
+        self.strikes.remove(sender);
+        self.previous_strikes.remove(sender);
+    }
+
+    /// The number of senders learned as bots
+    pub fn learned(&self) -> usize {
+        self.learned.len()
+    }
+}
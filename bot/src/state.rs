@@ -0,0 +1,437 @@
+//! State Reader
+//!
+//! Every candidate reads the same handful of contracts: the sandwiched pair's reserves, its
+//! token's bytecode, and the pairs along the victim's path. [StateReader] is the one interface the
+//! uniswap, safety and simulation modules read chain state through, so a [CachedReader] in front
+//! of the node can answer repeated reads without a request.
+//!
+//! Data that can't change is cached for the life of the process: a pair's tokens, a token's
+//! decimals, and deployed bytecode, which can't be replaced since selfdestruct stopped clearing
+//! code. An address without code at a block had none at any earlier block either, so the latest
+//! block it's known to be empty at is kept too, bounding a young pair's age checks to a read per
+//! block. Reserves move every block, so they're cached for the chain tip only and dropped as soon
+//! as a later block is read. Reads of the latest state without a block number always go to the node.
+//!
+//! The cached pair tokens and decimals can be exported as [CachedData] and primed into a new
+//! [CachedReader], see [crate::snapshot].
+//!
+//! A [ConsensusReader] cross-checks reserve reads against a second provider, so a compromised or
+//! lagging node can't feed the sandwich math bad state unnoticed.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    sync::Arc,
+    sync::Mutex,
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::{UniswapV2Pair, ERC20},
+    metrics,
+};
+
+/// Reads contract state, at a block number or the latest block when `None`
+pub trait StateReader: Send + Sync {
+    /// Returns the bytecode at `address`
+    fn code(&self, address: Address, block: Option<U64>) -> BoxFuture<'_, Result<Bytes>>;
+
+    /// Returns an ERC20 token's decimals
+    fn decimals(&self, token: Address) -> BoxFuture<'_, Result<u8>>;
+
+    /// Returns a pair's `(token0, token1)`
+    fn pair_tokens(&self, pair: Address) -> BoxFuture<'_, Result<(Address, Address)>>;
+
+    /// Returns a pair's reserves, ordered as `(reserve0, reserve1)` like `getReserves`
+    fn reserves(&self, pair: Address, block: Option<U64>) -> BoxFuture<'_, Result<(U256, U256)>>;
+}
+
+impl<R: StateReader + ?Sized> StateReader for Arc<R> {
+    fn code(&self, address: Address, block: Option<U64>) -> BoxFuture<'_, Result<Bytes>> {
+        (**self).code(address, block)
+    }
+
+    fn decimals(&self, token: Address) -> BoxFuture<'_, Result<u8>> {
+        (**self).decimals(token)
+    }
+
+    fn pair_tokens(&self, pair: Address) -> BoxFuture<'_, Result<(Address, Address)>> {
+        (**self).pair_tokens(pair)
+    }
+
+    fn reserves(&self, pair: Address, block: Option<U64>) -> BoxFuture<'_, Result<(U256, U256)>> {
+        (**self).reserves(pair, block)
+    }
+}
+
+/// Reads state from a node on every call
+#[derive(Debug, Clone)]
+pub struct ProviderReader<M> {
+    client: Arc<M>,
+}
+
+impl<M: Middleware + 'static> ProviderReader<M> {
+    /// Reads through `client`
+    pub fn new(client: Arc<M>) -> Self {
+        Self { client }
+    }
+}
+
+impl<M: Middleware + 'static> StateReader for ProviderReader<M> {
+    fn code(&self, address: Address, block: Option<U64>) -> BoxFuture<'_, Result<Bytes>> {
+        Box::pin(async move {
+            self.client
+                .get_code(address, block.map(|b| BlockNumber::Number(b).into()))
+                .await
+                .map_err(|e| eyre::eyre!("Failed to fetch code of {:?}: {:?}", address, e))
+        })
+    }
+
+    fn decimals(&self, token: Address) -> BoxFuture<'_, Result<u8>> {
+        Box::pin(async move {
+            ERC20::new(token, self.client.clone())
+                .decimals()
+                .call()
+                .await
+                .map_err(|e| eyre::eyre!("Failed to get decimals of {:?}: {:?}", token, e))
+        })
+    }
+
+    fn pair_tokens(&self, pair: Address) -> BoxFuture<'_, Result<(Address, Address)>> {
+        Box::pin(async move {
+            let contract = UniswapV2Pair::new(pair, self.client.clone());
+            let token0 = contract
+                .token_0()
+                .call()
+                .await
+                .map_err(|e| eyre::eyre!("Failed to get token0 of {:?}: {:?}", pair, e))?;
+            let token1 = contract
+                .token_1()
+                .call()
+                .await
+                .map_err(|e| eyre::eyre!("Failed to get token1 of {:?}: {:?}", pair, e))?;
+            Ok((token0, token1))
+        })
+    }
+
+    fn reserves(&self, pair: Address, block: Option<U64>) -> BoxFuture<'_, Result<(U256, U256)>> {
+        Box::pin(async move {
+            let contract = UniswapV2Pair::new(pair, self.client.clone());
+            let mut call = contract.get_reserves();
+            if let Some(block) = block {
+                call = call.block(BlockNumber::Number(block));
+            }
+            let (reserve0, reserve1, _) = call
+                .call()
+                .await
+                .map_err(|e| eyre::eyre!("Failed to get reserves of {:?}: {:?}", pair, e))?;
+            Ok((U256::from(reserve0), U256::from(reserve1)))
+        })
+    }
+}
+
+/// Deployed bytecode, with the earliest block it's known to exist at
+#[derive(Debug, Clone)]
+struct CachedCode {
+    code: Bytes,
+    since: Option<U64>,
+}
+
+#[derive(Debug, Default)]
+struct Caches {
+    code: HashMap<Address, CachedCode>,
+    absent: HashMap<Address, U64>,
+    decimals: HashMap<Address, u8>,
+    pair_tokens: HashMap<Address, (Address, Address)>,
+    tip: U64,
+    reserves: HashMap<Address, (U256, U256)>,
+}
+
+/// The immutable data a [CachedReader] has read, which can prime another
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedData {
+    /// Each pair's `(token0, token1)`
+    pub pair_tokens: BTreeMap<Address, (Address, Address)>,
+    /// Each token's decimals
+    pub decimals: BTreeMap<Address, u8>,
+}
+
+impl CachedData {
+    /// How many entries there are
+    pub fn len(&self) -> usize {
+        self.pair_tokens.len() + self.decimals.len()
+    }
+
+    /// Whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Caches the reads of another [StateReader]
+///
+/// Hits and misses are counted in the `state_cache_hit` and `state_cache_miss` metrics.
+#[derive(Debug)]
+pub struct CachedReader<R> {
+    inner: R,
+    caches: Mutex<Caches>,
+}
+
+impl<R: StateReader> CachedReader<R> {
+    /// Caches the reads of `inner`
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            caches: Mutex::new(Caches::default()),
+        }
+    }
+
+    /// The block whose reserves are cached
+    pub fn tip(&self) -> U64 {
+        self.caches.lock().unwrap_or_else(|e| e.into_inner()).tip
+    }
+
+    /// A copy of the cached pair tokens and decimals
+    ///
+    /// Bytecode isn't included, it's large and only read for the few tokens that get sandwiched.
+    pub fn cached_data(&self) -> CachedData {
+        let caches = self.caches.lock().unwrap_or_else(|e| e.into_inner());
+        CachedData {
+            pair_tokens: caches.pair_tokens.iter().map(|(k, v)| (*k, *v)).collect(),
+            decimals: caches.decimals.iter().map(|(k, v)| (*k, *v)).collect(),
+        }
+    }
+
+    /// Caches previously read pair tokens and decimals, returning how many entries were new
+    pub fn prime(&self, data: CachedData) -> usize {
+        let mut caches = self.caches.lock().unwrap_or_else(|e| e.into_inner());
+        let before = caches.pair_tokens.len() + caches.decimals.len();
+        for (pair, tokens) in data.pair_tokens {
+            caches.pair_tokens.entry(pair).or_insert(tokens);
+        }
+        for (token, decimals) in data.decimals {
+            caches.decimals.entry(token).or_insert(decimals);
+        }
+        caches.pair_tokens.len() + caches.decimals.len() - before
+    }
+
+    fn lookup<T>(&self, read: impl FnOnce(&mut Caches) -> Option<T>) -> Option<T> {
+        let hit = read(&mut self.caches.lock().unwrap_or_else(|e| e.into_inner()));
+        metrics::increment(if hit.is_some() {
+            "state_cache_hit"
+        } else {
+            "state_cache_miss"
+        });
+        hit
+    }
+
+    fn store(&self, write: impl FnOnce(&mut Caches)) {
+        write(&mut self.caches.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+impl<R: StateReader> StateReader for CachedReader<R> {
+    fn code(&self, address: Address, block: Option<U64>) -> BoxFuture<'_, Result<Bytes>> {
+        Box::pin(async move {
+            // Code is the same at any block after one it's known to exist at
+            let cached = self.lookup(|c| {
+                if let (Some(block), Some(empty_at)) = (block, c.absent.get(&address)) {
+                    if block <= *empty_at {
+                        return Some(Bytes::default());
+                    }
+                }
+                let cached = c.code.get(&address)?;
+                match (block, cached.since) {
+                    (None, _) => Some(cached.code.clone()),
+                    (Some(block), Some(since)) if block >= since => Some(cached.code.clone()),
+                    _ => None,
+                }
+            });
+            if let Some(code) = cached {
+                return Ok(code);
+            }
+
+            // An address without code may still be deployed to, but not before that block
+            let code = self.inner.code(address, block).await?;
+            if code.as_ref().is_empty() {
+                if let Some(block) = block {
+                    self.store(|c| {
+                        let empty_at = c.absent.entry(address).or_insert(block);
+                        *empty_at = (*empty_at).max(block);
+                    });
+                }
+            } else {
+                self.store(|c| {
+                    let cached = c.code.entry(address).or_insert_with(|| CachedCode {
+                        code: code.clone(),
+                        since: block,
+                    });
+                    cached.since = match (cached.since, block) {
+                        (Some(since), Some(block)) => Some(since.min(block)),
+                        (since, block) => since.or(block),
+                    };
+                });
+            }
+            Ok(code)
+        })
+    }
+
+    fn decimals(&self, token: Address) -> BoxFuture<'_, Result<u8>> {
+        Box::pin(async move {
+            if let Some(decimals) = self.lookup(|c| c.decimals.get(&token).copied()) {
+                return Ok(decimals);
+            }
+            let decimals = self.inner.decimals(token).await?;
+            self.store(|c| {
+                c.decimals.insert(token, decimals);
+            });
+            Ok(decimals)
+        })
+    }
+
+    fn pair_tokens(&self, pair: Address) -> BoxFuture<'_, Result<(Address, Address)>> {
+        Box::pin(async move {
+            if let Some(tokens) = self.lookup(|c| c.pair_tokens.get(&pair).copied()) {
+                return Ok(tokens);
+            }
+            let tokens = self.inner.pair_tokens(pair).await?;
+            self.store(|c| {
+                c.pair_tokens.insert(pair, tokens);
+            });
+            Ok(tokens)
+        })
+    }
+
+    fn reserves(&self, pair: Address, block: Option<U64>) -> BoxFuture<'_, Result<(U256, U256)>> {
+        Box::pin(async move {
+            let block = match block {
+                Some(b) => b,
+                None => return self.inner.reserves(pair, None).await,
+            };
+            let cached = self.lookup(|c| {
+                if block == c.tip {
+                    c.reserves.get(&pair).copied()
+                } else {
+                    None
+                }
+            });
+            if let Some(reserves) = cached {
+                return Ok(reserves);
+            }
+
+            let reserves = self.inner.reserves(pair, Some(block)).await?;
+            // Only the tip is cached, reads of earlier blocks pass through
+            self.store(|c| {
+                if block > c.tip {
+                    c.tip = block;
+                    c.reserves.clear();
+                }
+                if block == c.tip {
+                    c.reserves.insert(pair, reserves);
+                }
+            });
+            Ok(reserves)
+        })
+    }
+}
+
+/// What to do when two providers disagree on reserves
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReserveConsensus {
+    /// Don't cross-check, the default since each check costs a second read
+    #[default]
+    Off,
+    /// Log and count disagreements, trusting the primary provider
+    Warn,
+    /// Fail reads the providers disagree on
+    Enforce,
+}
+
+impl FromStr for ReserveConsensus {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "off" | "false" => Ok(ReserveConsensus::Off),
+            "warn" | "true" => Ok(ReserveConsensus::Warn),
+            "enforce" => Ok(ReserveConsensus::Enforce),
+            _ => Err(eyre::eyre!("Unknown reserve consensus \"{}\"", s)),
+        }
+    }
+}
+
+/// Cross-checks the reserve reads of a primary [StateReader] against a secondary one
+///
+/// Only reserves read at a block number are compared, since the providers' latest blocks may
+/// differ. Other reads go to the primary alone. Disagreements are counted in the
+/// `reserve_consensus_mismatch` metric, and failed secondary reads, like from a provider lagging
+/// behind the block, in `reserve_consensus_unavailable`.
+#[derive(Debug)]
+pub struct ConsensusReader<P, S> {
+    primary: P,
+    secondary: S,
+    mode: ReserveConsensus,
+}
+
+impl<P: StateReader, S: StateReader> ConsensusReader<P, S> {
+    /// Checks the reserves read by `primary` against `secondary`
+    pub fn new(primary: P, secondary: S, mode: ReserveConsensus) -> Self {
+        Self {
+            primary,
+            secondary,
+            mode,
+        }
+    }
+}
+
+impl<P: StateReader, S: StateReader> StateReader for ConsensusReader<P, S> {
+    fn code(&self, address: Address, block: Option<U64>) -> BoxFuture<'_, Result<Bytes>> {
+        self.primary.code(address, block)
+    }
+
+    fn decimals(&self, token: Address) -> BoxFuture<'_, Result<u8>> {
+        self.primary.decimals(token)
+    }
+
+    fn pair_tokens(&self, pair: Address) -> BoxFuture<'_, Result<(Address, Address)>> {
+        self.primary.pair_tokens(pair)
+    }
+
+    fn reserves(&self, pair: Address, block: Option<U64>) -> BoxFuture<'_, Result<(U256, U256)>> {
+        Box::pin(async move {
+            if self.mode == ReserveConsensus::Off || block.is_none() {
+                return self.primary.reserves(pair, block).await;
+            }
+            let (primary, secondary) = futures::join!(
+                self.primary.reserves(pair, block),
+                self.secondary.reserves(pair, block)
+            );
+            let primary = primary?;
+            match secondary {
+                Ok(secondary) if secondary != primary => {
+                    metrics::increment("reserve_consensus_mismatch");
+                    tracing::warn!(
+                        "[STATE] Providers disagree on the reserves of {:?} at {:?}: {:?} and {:?}",
+                        pair,
+                        block,
+                        primary,
+                        secondary
+                    );
+                    if self.mode == ReserveConsensus::Enforce {
+                        eyre::bail!("Providers disagree on the reserves of {:?}", pair);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    metrics::increment("reserve_consensus_unavailable");
+                    tracing::debug!("[STATE] Failed to cross-check reserves: {:?}", e);
+                }
+            }
+            Ok(primary)
+        })
+    }
+}
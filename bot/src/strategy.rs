@@ -0,0 +1,142 @@
+//! Strategy Rules
+//!
+//! Opportunities are classified by what they trade, and each class can have its own profit floor.
+//! Floors are priced in USDC so they hold their value as ETH moves, and converted to WETH through
+//! the Uniswap V2 WETH/USDC pair when checked.
+
+use std::str::FromStr;
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{safety::AddressList, state::StateReader, uniswap};
+
+/// The decimals of USDC
+pub const USDC_DECIMALS: u32 = 6;
+
+/// What an opportunity trades
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum OpportunityClass {
+    /// A V2 pair with a blue-chip token
+    BlueChip,
+    /// A V2 pair with any other token
+    LongTail,
+}
+
+impl FromStr for OpportunityClass {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "bluechip" | "blue-chip" => Ok(OpportunityClass::BlueChip),
+            "longtail" | "long-tail" => Ok(OpportunityClass::LongTail),
+            _ => Err(eyre::eyre!("Unknown opportunity class \"{}\"", s)),
+        }
+    }
+}
+
+/// The least profit a class of opportunity must make
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProfitFloor {
+    /// The class the floor applies to
+    pub class: OpportunityClass,
+    /// The floor, in USDC base units
+    pub usdc: U256,
+}
+
+impl FromStr for ProfitFloor {
+    type Err = eyre::Report;
+
+    /// Parses `class=dollars`, like `longtail=12.5`
+    fn from_str(s: &str) -> Result<Self> {
+        let (class, dollars) = s
+            .split_once('=')
+            .ok_or_else(|| eyre::eyre!("Expected class=dollars, got \"{}\"", s))?;
+        let usdc = ethers::utils::parse_units(dollars.trim(), USDC_DECIMALS)
+            .map_err(|_| eyre::eyre!("Invalid dollar amount \"{}\"", dollars))?;
+        Ok(Self {
+            class: OpportunityClass::from_str(class.trim())?,
+            usdc,
+        })
+    }
+}
+
+/// A comma-separated list of profit floors
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProfitFloors(pub Vec<ProfitFloor>);
+
+impl FromStr for ProfitFloors {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(ProfitFloor::from_str)
+            .collect::<Result<Vec<_>>>()
+            .map(ProfitFloors)
+    }
+}
+
+/// The profit rules
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProfitRules {
+    /// The profit floor of each class, none by default
+    pub floors: ProfitFloors,
+    /// The tokens whose pairs are blue-chip
+    pub blue_chips: AddressList,
+}
+
+impl ProfitRules {
+    /// Whether any class has a floor
+    pub fn is_empty(&self) -> bool {
+        self.floors.0.is_empty()
+    }
+
+    /// Classifies an opportunity on `token`
+    pub fn classify(&self, token: Address) -> OpportunityClass {
+        if self.blue_chips.0.contains(&token) {
+            OpportunityClass::BlueChip
+        } else {
+            OpportunityClass::LongTail
+        }
+    }
+
+    /// Returns a class's floor in USDC base units, the last one configured winning
+    pub fn floor_usdc(&self, class: OpportunityClass) -> Option<U256> {
+        self.floors
+            .0
+            .iter()
+            .rev()
+            .find(|floor| floor.class == class)
+            .map(|floor| floor.usdc)
+    }
+
+    /// Returns a class's floor in WETH at the given WETH/USDC reserves
+    pub fn floor_weth(
+        &self,
+        class: OpportunityClass,
+        weth_reserves: U256,
+        usdc_reserves: U256,
+    ) -> Option<U256> {
+        let usdc = self.floor_usdc(class)?;
+        if usdc_reserves.is_zero() {
+            return Some(U256::MAX);
+        }
+        Some(usdc.saturating_mul(weth_reserves) / usdc_reserves)
+    }
+}
+
+/// Reads the WETH/USDC reserves of the pair in `deployment` at `block`, ordered as `(weth, usdc)`
+pub async fn weth_usdc_reserves<S: StateReader + ?Sized>(
+    state: &S,
+    deployment: &uniswap::V2Deployment,
+    weth: Address,
+    usdc: Address,
+    block: Option<U64>,
+) -> Result<(U256, U256)> {
+    uniswap::SortedPair::new(weth, usdc)
+        .reserves_in(state, deployment, weth, block)
+        .await
+}
@@ -0,0 +1,170 @@
+//! Block Summaries
+//!
+//! Rather than aggregating thousands of per-transaction log lines, dashboards can follow a single
+//! `[BLOCK]` event per block: how many candidates were seen and evaluated while it was the chain
+//! head, and how many bundles targeting it were submitted and included, with their expected value
+//! and the profit they realized.
+//!
+//! Inclusion and settlement are only known once the target block is mined, so a block is summarized
+//! [SUMMARY_DELAY_BLOCKS] after it. Activity recorded for a block already summarized is counted in
+//! the `summary_late_events` metric instead.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, OnceLock},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::metrics;
+
+/// How many blocks after a block its summary is emitted
+pub const SUMMARY_DELAY_BLOCKS: u64 = 2;
+
+/// The most blocks summarized at once, when the head jumps ahead
+pub const MAX_SUMMARY_GAP: u64 = 64;
+
+/// What happened around a single block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSummary {
+    /// The block
+    pub block: U64,
+    /// Candidates taken for evaluation while the block was the head
+    pub seen: u64,
+    /// Candidates that got as far as being priced
+    pub evaluated: u64,
+    /// Bundles targeting the block that a relay accepted
+    pub submitted: u64,
+    /// Bundles that landed in the block
+    pub included: u64,
+    /// The summed expected value of the submitted bundles
+    pub expected_value: U256,
+    /// The profit realized by the bundles that landed
+    pub realized_profit: U256,
+}
+
+impl BlockSummary {
+    /// Emits the summary as a single structured `[BLOCK]` event
+    pub fn log(&self) {
+        tracing::info!(
+            block = self.block.as_u64(),
+            seen = self.seen,
+            evaluated = self.evaluated,
+            submitted = self.submitted,
+            included = self.included,
+            expected_value = %self.expected_value,
+            realized_profit = %self.realized_profit,
+            "[BLOCK] Summary"
+        );
+    }
+}
+
+/// The summaries of blocks not yet emitted
+#[derive(Debug, Default)]
+pub struct BlockSummaries {
+    open: Mutex<(Option<U64>, BTreeMap<U64, BlockSummary>)>,
+}
+
+impl BlockSummaries {
+    /// Creates an empty set of summaries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a candidate taken for evaluation while `block` was the head
+    pub fn seen(&self, block: U64) {
+        self.update(block, |s| s.seen += 1);
+    }
+
+    /// Counts a candidate priced while `block` was the head
+    pub fn evaluated(&self, block: U64) {
+        self.update(block, |s| s.evaluated += 1);
+    }
+
+    /// Counts a bundle targeting `block` that a relay accepted
+    pub fn submitted(&self, block: U64, expected_value: U256) {
+        self.update(block, |s| {
+            s.submitted += 1;
+            s.expected_value = s.expected_value.saturating_add(expected_value);
+        });
+    }
+
+    /// Counts a bundle that landed in `block`
+    pub fn included(&self, block: U64) {
+        self.update(block, |s| s.included += 1);
+    }
+
+    /// Adds the profit a bundle that landed in `block` realized
+    pub fn realized(&self, block: U64, profit: U256) {
+        self.update(block, |s| {
+            s.realized_profit = s.realized_profit.saturating_add(profit)
+        });
+    }
+
+    /// Closes every block up to `block`, returning their summaries in order
+    ///
+    /// Blocks without activity are summarized too, so dashboards see every block, but at most the
+    /// last [MAX_SUMMARY_GAP] are.
+    pub fn close(&self, block: U64) -> Vec<BlockSummary> {
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        let (closed, summaries) = &mut *open;
+        let first = match *closed {
+            Some(closed) if closed >= block => return Vec::new(),
+            Some(closed) => closed + 1,
+            None => summaries.keys().next().copied().unwrap_or(block).min(block),
+        };
+        let first = first.max(block.saturating_sub(U64::from(MAX_SUMMARY_GAP - 1)));
+        *closed = Some(block);
+
+        let remaining = summaries.split_off(&(block + 1));
+        let mut ended = std::mem::replace(summaries, remaining);
+        (first.as_u64()..=block.as_u64())
+            .map(U64::from)
+            .map(|b| {
+                ended.remove(&b).unwrap_or(BlockSummary {
+                    block: b,
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    fn update(&self, block: U64, apply: impl FnOnce(&mut BlockSummary)) {
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        let (closed, summaries) = &mut *open;
+        if matches!(*closed, Some(closed) if block <= closed) {
+            metrics::increment("summary_late_events");
+            return;
+        }
+        let summary = summaries.entry(block).or_insert(BlockSummary {
+            block,
+            ..Default::default()
+        });
+        apply(summary);
+    }
+}
+
+/// Returns the process-wide block summaries
+pub fn block_summaries() -> &'static BlockSummaries {
+    static SUMMARIES: OnceLock<BlockSummaries> = OnceLock::new();
+    SUMMARIES.get_or_init(BlockSummaries::new)
+}
+
+/// Emits each block's summary [SUMMARY_DELAY_BLOCKS] after it, following the chain head
+pub async fn emit_summaries(mut head: watch::Receiver<U64>) -> Result<()> {
+    loop {
+        head.changed()
+            .await
+            .map_err(|_| eyre::eyre!("Block clock stopped"))?;
+        let latest = *head.borrow();
+        if latest.as_u64() < SUMMARY_DELAY_BLOCKS {
+            continue;
+        }
+        for summary in block_summaries().close(latest - SUMMARY_DELAY_BLOCKS) {
+            summary.log();
+        }
+    }
+}
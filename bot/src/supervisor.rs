@@ -0,0 +1,247 @@
+//! Task Supervision
+//!
+//! The bot leans on a few long-running tasks: the mempool sources, the block clock and the admin
+//! API. A dropped websocket or a panic used to end one of them silently, leaving the bot running
+//! blind. [supervise_task] runs a task and restarts it with a backoff when it fails, panics, or
+//! stalls. A task given a stall timeout beats its [Heartbeat] on every event it handles, and is
+//! aborted and restarted once it goes that long without one.
+//!
+//! Every supervised task is registered with its [TaskHealth], served by the admin API's
+//! `GET /health`. Restarts and stalls are counted in `task_restarts_<name>` and
+//! `task_stalls_<name>`.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::{instances, metrics, opportunity::unix_millis};
+
+/// The delay before a task's first restart, doubled on each consecutive failure
+pub const DEFAULT_TASK_BACKOFF_SECS: u64 = 1;
+
+/// The longest delay between restarts of a task
+pub const MAX_TASK_BACKOFF_SECS: u64 = 60;
+
+/// When a task is considered stalled, checked at least this often, in milliseconds
+pub const STALL_CHECK_MS: u64 = 1_000;
+
+/// Marks a supervised task as alive
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat(Arc<AtomicU64>);
+
+impl Heartbeat {
+    /// Records an event now
+    pub fn beat(&self) {
+        self.0.store(unix_millis(), Ordering::Relaxed);
+    }
+
+    /// When the last event was recorded, in milliseconds since the unix epoch
+    pub fn last(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Whether there's been no event for `stall_after` by `now_ms`
+    pub fn is_stalled(&self, stall_after: Duration, now_ms: u64) -> bool {
+        now_ms.saturating_sub(self.last()) > stall_after.as_millis() as u64
+    }
+}
+
+/// What a supervised task is doing
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// Running
+    Running,
+    /// Waiting to be restarted
+    Restarting,
+    /// Finished and won't be restarted
+    Stopped,
+}
+
+/// The health of a supervised task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TaskHealth {
+    /// What the task is doing
+    pub status: TaskStatus,
+    /// How many times it was restarted
+    pub restarts: u64,
+    /// The last event it handled, in milliseconds since the unix epoch
+    pub last_beat: u64,
+    /// How long it may go without an event before it's restarted, in seconds
+    pub stall_secs: Option<u64>,
+    /// Why it was last restarted
+    pub last_error: Option<String>,
+}
+
+impl TaskHealth {
+    /// Whether the task is running and not stalled at `now_ms`
+    pub fn is_healthy(&self, now_ms: u64) -> bool {
+        self.status == TaskStatus::Running
+            && self.stall_secs.map_or(true, |secs| {
+                now_ms.saturating_sub(self.last_beat) <= secs.saturating_mul(1000)
+            })
+    }
+}
+
+#[derive(Debug)]
+struct SupervisedTask {
+    heartbeat: Heartbeat,
+    stall_after: Option<Duration>,
+    health: Mutex<TaskHealth>,
+}
+
+impl SupervisedTask {
+    fn update(&self, update: impl FnOnce(&mut TaskHealth)) {
+        update(&mut self.health.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+/// Every supervised task, by name
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+    tasks: Mutex<BTreeMap<String, Arc<SupervisedTask>>>,
+}
+
+impl TaskRegistry {
+    fn register(&self, name: String, stall_after: Option<Duration>) -> Arc<SupervisedTask> {
+        let heartbeat = Heartbeat::default();
+        heartbeat.beat();
+        let task = Arc::new(SupervisedTask {
+            stall_after,
+            health: Mutex::new(TaskHealth {
+                status: TaskStatus::Running,
+                restarts: 0,
+                last_beat: heartbeat.last(),
+                stall_secs: stall_after.map(|d| d.as_secs()),
+                last_error: None,
+            }),
+            heartbeat,
+        });
+        self.tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name, task.clone());
+        task
+    }
+
+    /// The health of every supervised task, by name
+    pub fn health(&self) -> BTreeMap<String, TaskHealth> {
+        self.tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(name, task)| {
+                let mut health = task
+                    .health
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .clone();
+                health.last_beat = task.heartbeat.last();
+                (name.clone(), health)
+            })
+            .collect()
+    }
+}
+
+/// Returns the global task registry
+pub fn tasks() -> &'static TaskRegistry {
+    static TASKS: OnceLock<TaskRegistry> = OnceLock::new();
+    TASKS.get_or_init(TaskRegistry::default)
+}
+
+/// How a run of a supervised task ended
+enum Exit {
+    Finished,
+    Failed(String),
+    Stalled,
+}
+
+/// Waits for a run to end, aborting it if it stalls
+async fn watch_run(task: &SupervisedTask, mut handle: JoinHandle<Result<()>>) -> Exit {
+    let check = task
+        .stall_after
+        .map(|d| d.min(Duration::from_millis(STALL_CHECK_MS)));
+    loop {
+        tokio::select! {
+            result = &mut handle => {
+                return match result {
+                    Ok(Ok(())) => Exit::Finished,
+                    Ok(Err(e)) => Exit::Failed(format!("{:?}", e)),
+                    Err(e) if e.is_panic() => Exit::Failed(format!("Panicked: {:?}", e)),
+                    Err(e) => Exit::Failed(format!("{:?}", e)),
+                };
+            }
+            _ = tokio::time::sleep(check.unwrap_or_default()), if check.is_some() => {
+                let stall_after = task.stall_after.unwrap_or_default();
+                if task.heartbeat.is_stalled(stall_after, unix_millis()) {
+                    handle.abort();
+                    return Exit::Stalled;
+                }
+            }
+        }
+    }
+}
+
+/// Runs a task under supervision, restarting it until a run finishes cleanly
+///
+/// `task` is called for every run with the task's [Heartbeat]. With a `stall_after`, a run that
+/// doesn't beat for that long is aborted and restarted. Inside a named instance, the task is
+/// registered under the instance's namespace and stops with it.
+pub fn supervise_task<F, Fut>(name: &str, stall_after: Option<Duration>, task: F) -> JoinHandle<()>
+where
+    F: Fn(Heartbeat) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let name = name.to_string();
+    let supervised = tasks().register(instances::namespaced(&name), stall_after);
+    instances::spawn(async move {
+        let mut backoff = Duration::from_secs(DEFAULT_TASK_BACKOFF_SECS);
+        loop {
+            supervised.heartbeat.beat();
+            supervised.update(|h| h.status = TaskStatus::Running);
+            let started = Instant::now();
+            let handle = instances::spawn(task(supervised.heartbeat.clone()));
+            let error = match watch_run(&supervised, handle).await {
+                Exit::Finished => {
+                    supervised.update(|h| h.status = TaskStatus::Stopped);
+                    return;
+                }
+                Exit::Failed(error) => error,
+                Exit::Stalled => {
+                    metrics::increment(&format!("task_stalls_{name}"));
+                    format!("No events for {:?}", stall_after.unwrap_or_default())
+                }
+            };
+
+            // A run that stayed up longer than the longest backoff starts the backoff over
+            let max_backoff = Duration::from_secs(MAX_TASK_BACKOFF_SECS);
+            if started.elapsed() > max_backoff {
+                backoff = Duration::from_secs(DEFAULT_TASK_BACKOFF_SECS);
+            }
+            tracing::warn!(
+                "[SUPERVISOR] {} stopped: {}, restarting in {:?}",
+                name,
+                error,
+                backoff
+            );
+            metrics::increment(&format!("task_restarts_{name}"));
+            supervised.update(|h| {
+                h.status = TaskStatus::Restarting;
+                h.restarts += 1;
+                h.last_error = Some(error);
+            });
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(max_backoff);
+        }
+    })
+}
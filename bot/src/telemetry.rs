@@ -2,7 +2,9 @@ use tracing::subscriber::set_global_default;
 use tracing::{Level, Subscriber};
 use tracing_log::LogTracer;
 use tracing_subscriber::Layer;
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
+use tracing_subscriber::{layer::SubscriberExt, reload, EnvFilter, Registry};
+
+use crate::admin::LogFilterControl;
 
 use ansi_term::Colour::{Blue, Cyan, Purple, Red, Yellow};
 
@@ -105,6 +107,35 @@ pub fn get_subscriber(env_filter: String) -> impl Subscriber + Sync + Send {
     Registry::default().with(env_filter).with(formatting_layer)
 }
 
+/// A handle reloading a subscriber's filter at runtime
+#[derive(Debug, Clone)]
+pub struct LogFilter(reload::Handle<EnvFilter, Registry>);
+
+impl LogFilterControl for LogFilter {
+    fn current(&self) -> String {
+        self.0.with_current(|f| f.to_string()).unwrap_or_default()
+    }
+
+    fn set(&self, directives: &str) -> eyre::Result<()> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|e| eyre::eyre!("Invalid log filter \"{}\": {}", directives, e))?;
+        self.0
+            .reload(filter)
+            .map_err(|e| eyre::eyre!("Failed to reload log filter: {}", e))
+    }
+}
+
+/// Subscriber Composer with a reloadable filter
+///
+/// Like [get_subscriber], but the filter can be replaced through the returned [LogFilter].
+pub fn get_reloadable_subscriber(env_filter: String) -> (impl Subscriber + Sync + Send, LogFilter) {
+    let env_filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
+    let (filter_layer, handle) = reload::Layer::new(env_filter);
+    let subscriber = Registry::default().with(filter_layer).with(AsniTermLayer);
+    (subscriber, LogFilter(handle))
+}
+
 /// Globally registers a subscriber.
 ///
 /// ### Panics
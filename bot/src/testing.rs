@@ -0,0 +1,258 @@
+//! Test Doubles
+//!
+//! Deterministic stand-ins for the network, so strategies can be unit tested without a node or
+//! relay. [MockTxSource] replays a fixed set of pending transactions, [MockStateReader] serves
+//! canned chain state, [MockRelay] records the bundles submitted to it, and [FrozenClock] only
+//! moves when told to, for the guards that take the current time as an argument.
+//!
+//! Only compiled with the `testing` feature.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use futures::future::BoxFuture;
+use tokio::sync::mpsc;
+
+use crate::{mempool::TxSource, state::StateReader, timing::BundleRelay};
+
+/// Replays a fixed set of pending transactions, then stays connected without delivering more
+#[derive(Debug, Clone, Default)]
+pub struct MockTxSource {
+    /// The transactions delivered, in order
+    pub txs: Vec<Transaction>,
+}
+
+impl MockTxSource {
+    /// Delivers `txs` in order
+    pub fn new(txs: Vec<Transaction>) -> Self {
+        Self { txs }
+    }
+}
+
+impl TxSource for MockTxSource {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn run(&self, sender: mpsc::Sender<Transaction>) -> BoxFuture<'static, Result<()>> {
+        let txs = self.txs.clone();
+        Box::pin(async move {
+            for tx in txs {
+                if sender.send(tx).await.is_err() {
+                    return Ok(());
+                }
+            }
+            // Ending the stream would reconnect and replay it
+            sender.closed().await;
+            Ok(())
+        })
+    }
+}
+
+/// Serves canned chain state, failing reads of anything it wasn't given
+#[derive(Debug, Clone, Default)]
+pub struct MockStateReader {
+    code: HashMap<Address, Bytes>,
+    decimals: HashMap<Address, u8>,
+    pairs: HashMap<Address, (Address, Address)>,
+    reserves: HashMap<(Address, Option<U64>), (U256, U256)>,
+    reads: Arc<Mutex<usize>>,
+}
+
+impl MockStateReader {
+    /// Creates a reader with no state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deploys `code` at `address` for every block
+    pub fn with_code(mut self, address: Address, code: Bytes) -> Self {
+        self.code.insert(address, code);
+        self
+    }
+
+    /// Sets an ERC20 token's decimals
+    pub fn with_decimals(mut self, token: Address, decimals: u8) -> Self {
+        self.decimals.insert(token, decimals);
+        self
+    }
+
+    /// Sets a pair's `(token0, token1)`
+    pub fn with_pair(mut self, pair: Address, tokens: (Address, Address)) -> Self {
+        self.pairs.insert(pair, tokens);
+        self
+    }
+
+    /// Sets a pair's `(reserve0, reserve1)` at `block`, or at every block without its own
+    pub fn with_reserves(
+        mut self,
+        pair: Address,
+        block: Option<U64>,
+        reserves: (U256, U256),
+    ) -> Self {
+        self.reserves.insert((pair, block), reserves);
+        self
+    }
+
+    /// How many reads were made
+    pub fn reads(&self) -> usize {
+        *self.reads.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn read<T: Clone>(&self, value: Option<&T>, what: String) -> Result<T> {
+        *self.reads.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+        value
+            .cloned()
+            .ok_or_else(|| eyre::eyre!("No mock state for {}", what))
+    }
+}
+
+impl StateReader for MockStateReader {
+    fn code(&self, address: Address, _block: Option<U64>) -> BoxFuture<'_, Result<Bytes>> {
+        let code = self.read(self.code.get(&address), format!("code of {:?}", address));
+        Box::pin(async move { code })
+    }
+
+    fn decimals(&self, token: Address) -> BoxFuture<'_, Result<u8>> {
+        let decimals = self.read(
+            self.decimals.get(&token),
+            format!("decimals of {:?}", token),
+        );
+        Box::pin(async move { decimals })
+    }
+
+    fn pair_tokens(&self, pair: Address) -> BoxFuture<'_, Result<(Address, Address)>> {
+        let tokens = self.read(self.pairs.get(&pair), format!("tokens of {:?}", pair));
+        Box::pin(async move { tokens })
+    }
+
+    fn reserves(&self, pair: Address, block: Option<U64>) -> BoxFuture<'_, Result<(U256, U256)>> {
+        let reserves = self.read(
+            self.reserves
+                .get(&(pair, block))
+                .or_else(|| self.reserves.get(&(pair, None))),
+            format!("reserves of {:?} at {:?}", pair, block),
+        );
+        Box::pin(async move { reserves })
+    }
+}
+
+/// A bundle submitted to a [MockRelay]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmittedBundle {
+    /// The signed transactions
+    pub signed_txs: Vec<Bytes>,
+    /// The block the bundle targets
+    pub block_number: U64,
+}
+
+/// Records the bundles submitted to it, accepting or rejecting all of them
+#[derive(Debug, Clone, Default)]
+pub struct MockRelay {
+    rejection: Option<String>,
+    submitted: Arc<Mutex<Vec<SubmittedBundle>>>,
+}
+
+impl MockRelay {
+    /// Creates a relay accepting every bundle
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects every bundle with `error`, still recording it
+    pub fn rejecting(error: impl Into<String>) -> Self {
+        Self {
+            rejection: Some(error.into()),
+            ..Self::default()
+        }
+    }
+
+    /// The bundles submitted so far, in order
+    pub fn submitted(&self) -> Vec<SubmittedBundle> {
+        self.submitted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl BundleRelay for MockRelay {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn submit_bundle<'a>(
+        &'a self,
+        signed_txs: &'a [Bytes],
+        block_number: U64,
+    ) -> BoxFuture<'a, Result<serde_json::Value>> {
+        let mut submitted = self.submitted.lock().unwrap_or_else(|e| e.into_inner());
+        submitted.push(SubmittedBundle {
+            signed_txs: signed_txs.to_vec(),
+            block_number,
+        });
+        let response = match &self.rejection {
+            Some(error) => Err(eyre::eyre!("{}", error)),
+            None => Ok(serde_json::json!({ "bundleHash": format!("{:#x}", submitted.len()) })),
+        };
+        Box::pin(async move { response })
+    }
+}
+
+/// A clock that only moves when advanced
+///
+/// Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct FrozenClock {
+    start: Instant,
+    start_unix_millis: u64,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl Default for FrozenClock {
+    fn default() -> Self {
+        Self::at(0)
+    }
+}
+
+impl FrozenClock {
+    /// Freezes the clock at `unix_millis` milliseconds since the unix epoch
+    pub fn at(unix_millis: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            start_unix_millis: unix_millis,
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Moves the clock forward by `by`
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    /// The time advanced since the clock was frozen
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The current instant
+    pub fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    /// The current milliseconds since the unix epoch
+    pub fn unix_millis(&self) -> u64 {
+        self.start_unix_millis
+            .saturating_add(self.elapsed().as_millis() as u64)
+    }
+
+    /// The current seconds since the unix epoch, like a block timestamp or swap deadline
+    pub fn unix_secs(&self) -> u64 {
+        self.unix_millis() / 1_000
+    }
+}
@@ -0,0 +1,215 @@
+//! Submission Timing
+//!
+//! Builders stop accepting bundles for a block some time into the slot. Submitting right before
+//! that cutoff keeps a bundle private for as long as possible, while submitting after it wastes
+//! the opportunity. The [SubmissionScheduler] learns each relay's cutoff from inclusion outcomes
+//! and holds bundles until just before it.
+//!
+//! Relays taking bundles of signed transactions implement [BundleRelay], so submission can be
+//! scheduled and exercised the same way whichever relay it goes to.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethers::prelude::*;
+use eyre::Result;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+
+/// The slot duration, in milliseconds
+pub const SLOT_DURATION_MS: u64 = 12_000;
+
+/// The default safety margin before the cutoff, in milliseconds
+pub const DEFAULT_MARGIN_MS: u64 = 500;
+
+/// A relay bundles of signed transactions are submitted to
+pub trait BundleRelay: Debug + Send + Sync {
+    /// The relay's name in submission timings and audit records
+    fn name(&self) -> &'static str;
+
+    /// Submits `signed_txs` for inclusion in `block_number`, returning the relay's response
+    fn submit_bundle<'a>(
+        &'a self,
+        signed_txs: &'a [Bytes],
+        block_number: U64,
+    ) -> BoxFuture<'a, Result<serde_json::Value>>;
+}
+
+/// The submission timing for a relay
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelayTiming {
+    /// The estimated builder cutoff, in milliseconds into the slot
+    pub cutoff_ms: u64,
+    /// How long before the cutoff to submit, in milliseconds
+    pub margin_ms: u64,
+}
+
+impl FromStr for RelayTiming {
+    type Err = eyre::Report;
+
+    /// Parses `cutoff_ms` or `cutoff_ms:margin_ms`
+    fn from_str(s: &str) -> Result<Self> {
+        let (cutoff, margin) = match s.split_once(':') {
+            Some((cutoff, margin)) => (cutoff, Some(margin)),
+            None => (s, None),
+        };
+        let cutoff_ms = cutoff
+            .trim()
+            .parse()
+            .map_err(|_| eyre::eyre!("Invalid cutoff \"{}\"", cutoff))?;
+        let margin_ms = match margin {
+            Some(m) => m
+                .trim()
+                .parse()
+                .map_err(|_| eyre::eyre!("Invalid margin \"{}\"", m))?,
+            None => DEFAULT_MARGIN_MS,
+        };
+        if cutoff_ms > SLOT_DURATION_MS {
+            eyre::bail!("Cutoff {}ms is longer than a slot", cutoff_ms);
+        }
+        Ok(Self {
+            cutoff_ms,
+            margin_ms,
+        })
+    }
+}
+
+/// Submission timings keyed by relay name, as a comma-separated `relay=cutoff_ms[:margin_ms]` list
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelayTimings(pub BTreeMap<String, RelayTiming>);
+
+impl FromStr for RelayTimings {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|entry| {
+                let (relay, timing) = entry
+                    .split_once('=')
+                    .ok_or_else(|| eyre::eyre!("Expected relay=cutoff_ms, got \"{}\"", entry))?;
+                Ok((relay.trim().to_lowercase(), timing.parse()?))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()
+            .map(RelayTimings)
+    }
+}
+
+/// Returns how far into the slot starting at `slot_start` (unix seconds) `now` is, in milliseconds
+pub fn slot_offset_ms(slot_start: u64, now: SystemTime) -> u64 {
+    let now_ms = now
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    now_ms.saturating_sub(slot_start.saturating_mul(1000))
+}
+
+/// Schedules bundle submissions against each relay's builder cutoff
+#[derive(Debug, Clone, Default)]
+pub struct SubmissionScheduler {
+    timings: BTreeMap<String, RelayTiming>,
+}
+
+impl SubmissionScheduler {
+    /// Creates a scheduler seeded with the configured timings
+    pub fn new(timings: RelayTimings) -> Self {
+        Self { timings: timings.0 }
+    }
+
+    /// Returns the current timing for a relay, if it's scheduled
+    pub fn timing(&self, relay: &str) -> Option<RelayTiming> {
+        self.timings.get(relay).copied()
+    }
+
+    /// Returns how long to hold a bundle for `relay` before submitting it
+    ///
+    /// Relays without a timing, and bundles already past the target, are submitted immediately.
+    pub fn delay(&self, relay: &str, slot_start: u64, now: SystemTime) -> Duration {
+        let timing = match self.timing(relay) {
+            Some(t) => t,
+            None => return Duration::ZERO,
+        };
+        let target_ms = timing.cutoff_ms.saturating_sub(timing.margin_ms);
+        let offset_ms = slot_offset_ms(slot_start, now);
+        Duration::from_millis(target_ms.saturating_sub(offset_ms))
+    }
+
+    /// Updates a relay's cutoff estimate from a submission `offset_ms` into the slot
+    ///
+    /// An inclusion proves the cutoff is at least the offset. A miss before the estimated cutoff
+    /// pulls the estimate a quarter of the way towards the offset, since misses are also caused
+    /// by losing the auction.
+    pub fn record(&mut self, relay: &str, offset_ms: u64, included: bool) {
+        let timing = match self.timings.get_mut(relay) {
+            Some(t) => t,
+            None => return,
+        };
+        if included {
+            timing.cutoff_ms = timing.cutoff_ms.max(offset_ms).min(SLOT_DURATION_MS);
+        } else if offset_ms < timing.cutoff_ms {
+            timing.cutoff_ms -= (timing.cutoff_ms - offset_ms) / 4;
+        }
+    }
+}
+
+/// Holds a submission for `relay` until its scheduled time, returning the slot offset it's released at
+pub async fn wait_for_slot(
+    scheduler: &Mutex<SubmissionScheduler>,
+    relay: &str,
+    slot_start: u64,
+) -> u64 {
+    let delay =
+        scheduler
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .delay(relay, slot_start, clock::now());
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    slot_offset_ms(slot_start, clock::now())
+}
+
+/// Waits for a bundle's target block, then records whether its frontrun landed for every relay it
+/// was sent to
+///
+/// Returns whether it landed, or `None` if the target block wasn't seen in time.
+pub async fn track_inclusion<M: Middleware + 'static>(
+    client: Arc<M>,
+    scheduler: Arc<Mutex<SubmissionScheduler>>,
+    submissions: Vec<(String, u64)>,
+    frontrun_hash: TxHash,
+    target_block: U64,
+) -> Result<Option<bool>> {
+    // Give up after a few slots past the target
+    for _ in 0..6 {
+        let block = client
+            .get_block_number()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch block number: {:?}", e))?;
+        if block >= target_block {
+            let receipt = client
+                .get_transaction_receipt(frontrun_hash)
+                .await
+                .map_err(|e| eyre::eyre!("Failed to fetch receipt: {:?}", e))?;
+            let included = receipt
+                .and_then(|r| r.block_number)
+                .map(|b| b == target_block)
+                .unwrap_or_default();
+            let mut scheduler = scheduler.lock().unwrap_or_else(|e| e.into_inner());
+            for (relay, offset_ms) in &submissions {
+                scheduler.record(relay, *offset_ms, included);
+            }
+            return Ok(Some(included));
+        }
+        tokio::time::sleep(Duration::from_millis(SLOT_DURATION_MS / 2)).await;
+    }
+    Ok(None)
+}
@@ -0,0 +1,440 @@
+//! Sandwich Transaction Construction
+//!
+//! Legs can be signed by any of several searcher wallets. Each wallet has its own nonce sequence,
+//! so concurrent bundles allocated to different wallets don't invalidate each other.
+//!
+//! Every task sending from the searcher wallets, sandwiches, other strategies and admin calls alike,
+//! takes its nonces from the factory's [NonceManager], so they don't reuse each other's nonces.
+//!
+//! With a replay guard, every leg is built for the block its bundle targets and reverts in any
+//! other, so a leg unbundled from its victim can't be replayed against us later.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use ethers::{
+    prelude::*,
+    types::transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conflicts::{BundleClaim, ClaimOutcome, ConflictTracker},
+    executor::{self, ExecutionMode, Executor, LiquidityPosition, PayloadCapability, SwapLeg},
+    liquidations::Liquidation,
+    orders::OrderFill,
+};
+
+/// The default gas limit for each sandwich leg
+pub const DEFAULT_LEG_GAS_LIMIT: u64 = 250_000;
+
+/// The fee parameters shared by both sandwich legs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LegFees {
+    /// The max fee per gas
+    pub max_fee_per_gas: U256,
+    /// The max priority fee per gas
+    pub max_priority_fee_per_gas: U256,
+    /// The gas limit
+    pub gas_limit: U256,
+}
+
+impl LegFees {
+    /// Fee parameters for a leg paying only the base fee
+    pub fn base_fee_only(base_fee: U256) -> Self {
+        Self {
+            max_fee_per_gas: base_fee,
+            max_priority_fee_per_gas: U256::zero(),
+            gas_limit: U256::from(DEFAULT_LEG_GAS_LIMIT),
+        }
+    }
+}
+
+/// A nonce in one searcher wallet's nonce sequence
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NonceSlot {
+    /// The index of the wallet in the factory
+    pub wallet: usize,
+    /// The nonce
+    pub nonce: U256,
+}
+
+impl From<U256> for NonceSlot {
+    /// A nonce of the primary wallet
+    fn from(nonce: U256) -> Self {
+        Self { wallet: 0, nonce }
+    }
+}
+
+/// Coordinates the searcher wallets' nonces between every task sending from them
+///
+/// Nonces are read at the pending block, so transactions sent directly count once the node has
+/// them. Bundles claim their nonces before they're shipped, keeping the higher value of any two
+/// that collide, and a transaction sent directly supersedes the bundles reusing its nonce.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    /// The claims of in-flight bundles
+    conflicts: Mutex<ConflictTracker>,
+    /// Held while a transaction is sent directly
+    direct: tokio::sync::Mutex<()>,
+}
+
+impl NonceManager {
+    /// Reads the next nonce of `wallet`
+    pub async fn next<M: Middleware>(&self, client: &M, wallet: Address) -> Result<U256> {
+        client
+            .get_transaction_count(wallet, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch the nonce of {:?}: {:?}", wallet, e))
+    }
+
+    /// Claims a bundle's block, pair and nonces, see [ConflictTracker::claim]
+    pub fn claim(&self, claim: BundleClaim) -> ClaimOutcome {
+        self.conflicts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .claim(claim)
+    }
+
+    /// Whether `wallet` has bundles in flight targeting `block` or later
+    pub fn is_pending(&self, wallet: Address, block: U64) -> bool {
+        self.conflicts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_pending(wallet, block)
+    }
+
+    /// Allocates the next nonce of `wallet` for a transaction sent outside a bundle
+    ///
+    /// In-flight bundles using the nonce are superseded. Hold the guard until the transaction is
+    /// sent, so no other direct transaction reads the same nonce.
+    pub async fn direct<M: Middleware>(
+        &self,
+        client: &M,
+        wallet: Address,
+    ) -> Result<(U256, tokio::sync::MutexGuard<'_, ()>)> {
+        let guard = self.direct.lock().await;
+        let nonce = self.next(client, wallet).await?;
+        let superseded = self
+            .conflicts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .release_nonce(wallet, nonce);
+        if superseded > 0 {
+            tracing::info!(
+                "[NONCE] Nonce {} of {:?} superseded {} in-flight bundle(s)",
+                nonce,
+                wallet,
+                superseded
+            );
+        }
+        Ok((nonce, guard))
+    }
+}
+
+/// Builds and signs the frontrun and backrun legs of a sandwich
+///
+/// The factory owns the executor and funding mode, so the payload encoding and transaction value
+/// always agree with each other.
+#[derive(Debug)]
+pub struct TxFactory {
+    /// The executor contract adapter
+    pub executor: Box<dyn Executor>,
+    /// How the frontrun leg is funded
+    pub mode: ExecutionMode,
+    /// The primary searcher wallet
+    pub wallet: LocalWallet,
+    /// Every searcher wallet, starting with the primary one
+    pub wallets: Vec<LocalWallet>,
+    /// The next wallet to allocate
+    cursor: AtomicUsize,
+    /// The chain id to sign for
+    pub chain_id: u64,
+    /// Whether legs only execute in the block they're built for
+    pub replay_guard: bool,
+    /// Hands out the searcher wallets' nonces
+    pub nonces: NonceManager,
+    /// How far, in bips, the frontrun output may fall short before the backrun reverts
+    pub abort_deviation_bps: Option<u64>,
+}
+
+impl TxFactory {
+    /// Creates a new transaction factory
+    ///
+    /// Errors if the executor cannot be funded with the requested mode.
+    pub fn new(
+        executor: Box<dyn Executor>,
+        mode: ExecutionMode,
+        wallet: LocalWallet,
+        chain_id: u64,
+    ) -> Result<Self> {
+        executor::ensure_supported(executor.as_ref(), mode)?;
+        let wallet = wallet.with_chain_id(chain_id);
+        Ok(Self {
+            executor,
+            mode,
+            wallets: vec![wallet.clone()],
+            wallet,
+            cursor: AtomicUsize::new(0),
+            chain_id,
+            replay_guard: false,
+            nonces: NonceManager::default(),
+            abort_deviation_bps: None,
+        })
+    }
+
+    /// Adds searcher wallets to allocate bundles to alongside the primary one
+    pub fn with_wallets(mut self, wallets: Vec<LocalWallet>) -> Self {
+        let chain_id = self.chain_id;
+        self.wallets
+            .extend(wallets.into_iter().map(|w| w.with_chain_id(chain_id)));
+        self
+    }
+
+    /// Restricts every leg to the block it's built for
+    ///
+    /// Errors if the executor can't check the block.
+    pub fn with_replay_guard(mut self, enabled: bool) -> Result<Self> {
+        if enabled && !self.executor.supports_replay_guard() {
+            eyre::bail!(
+                "Executor \"{}\" does not support replay guards",
+                self.executor.name()
+            );
+        }
+        self.replay_guard = enabled;
+        Ok(self)
+    }
+
+    /// Reverts backruns built with [TxFactory::backrun_checked] when the frontrun delivered more
+    /// than `deviation_bps` less than expected
+    ///
+    /// Errors if the executor can't check its balance, or legs aren't replay guarded, since the
+    /// minimum balance is packed after the target block.
+    pub fn with_abort_guard(mut self, deviation_bps: Option<u64>) -> Result<Self> {
+        if let Some(bps) = deviation_bps {
+            if !self.executor.supports_balance_guard() {
+                eyre::bail!(
+                    "Executor \"{}\" does not support balance guards",
+                    self.executor.name()
+                );
+            }
+            if !self.replay_guard {
+                eyre::bail!("Abort guards require replay guards");
+            }
+            if bps > 10_000 {
+                eyre::bail!("Abort deviation must be at most 10000 bips, got {}", bps);
+            }
+        }
+        self.abort_deviation_bps = deviation_bps;
+        Ok(self)
+    }
+
+    /// The payload capabilities the executor contract needs for the legs built
+    pub fn capabilities(&self) -> Vec<PayloadCapability> {
+        let mut capabilities = vec![PayloadCapability::Legs];
+        if self.replay_guard {
+            capabilities.push(PayloadCapability::ReplayGuard);
+        }
+        if self.abort_deviation_bps.is_some() {
+            capabilities.push(PayloadCapability::BalanceGuard);
+        }
+        capabilities
+    }
+
+    /// The primary searcher wallet address
+    pub fn searcher(&self) -> Address {
+        self.wallet.address()
+    }
+
+    /// The address of the wallet at `index`, falling back to the primary wallet
+    pub fn searcher_at(&self, index: usize) -> Address {
+        self.wallets
+            .get(index)
+            .map(|w| w.address())
+            .unwrap_or_else(|| self.searcher())
+    }
+
+    /// Every searcher wallet address
+    pub fn searchers(&self) -> Vec<Address> {
+        self.wallets.iter().map(|w| w.address()).collect()
+    }
+
+    /// Allocates the next wallet round-robin, skipping wallets `available` rejects
+    ///
+    /// Returns `None` if no wallet is available.
+    pub fn allocate_wallet(&self, available: impl Fn(usize) -> bool) -> Option<usize> {
+        let count = self.wallets.len();
+        (0..count)
+            .map(|_| self.cursor.fetch_add(1, Ordering::Relaxed) % count)
+            .find(|index| available(*index))
+    }
+
+    /// Builds an unsigned leg transaction to the executor, guarded to `block` if enabled
+    fn leg_request(
+        &self,
+        data: Bytes,
+        value: U256,
+        nonce: NonceSlot,
+        fees: &LegFees,
+        block: U64,
+    ) -> Result<TypedTransaction> {
+        let data = if self.replay_guard {
+            self.executor.guard_block(data, block)?
+        } else {
+            data
+        };
+        Ok(self.call(self.executor.address(), data, value, nonce, fees))
+    }
+
+    /// Builds an unsigned transaction from the searcher to an arbitrary contract
+    ///
+    /// Used for calls that don't go through the executor's legs, like admin calls. The
+    /// transaction is sent from the wallet owning the nonce.
+    pub fn call(
+        &self,
+        to: Address,
+        data: Bytes,
+        value: U256,
+        nonce: impl Into<NonceSlot>,
+        fees: &LegFees,
+    ) -> TypedTransaction {
+        let NonceSlot { wallet, nonce } = nonce.into();
+        TypedTransaction::Eip1559(Eip1559TransactionRequest {
+            to: Some(NameOrAddress::Address(to)),
+            from: Some(self.searcher_at(wallet)),
+            data: Some(data),
+            chain_id: Some(U64::from(self.chain_id)),
+            max_priority_fee_per_gas: Some(fees.max_priority_fee_per_gas),
+            max_fee_per_gas: Some(fees.max_fee_per_gas),
+            gas: Some(fees.gas_limit),
+            nonce: Some(nonce),
+            value: if value.is_zero() { None } else { Some(value) },
+            access_list: AccessList::default(),
+        })
+    }
+
+    /// Builds the unsigned frontrun transaction for a bundle targeting `block`
+    pub fn frontrun(
+        &self,
+        leg: &SwapLeg,
+        nonce: impl Into<NonceSlot>,
+        fees: &LegFees,
+        block: U64,
+    ) -> Result<TypedTransaction> {
+        let data = self.executor.encode_frontrun(leg, self.mode)?;
+        let value = self.executor.frontrun_value(leg, self.mode);
+        self.leg_request(data, value, nonce.into(), fees, block)
+    }
+
+    /// Builds the unsigned backrun transaction for a bundle targeting `block`
+    pub fn backrun(
+        &self,
+        leg: &SwapLeg,
+        nonce: impl Into<NonceSlot>,
+        fees: &LegFees,
+        block: U64,
+    ) -> Result<TypedTransaction> {
+        let data = self.executor.encode_backrun(leg)?;
+        self.leg_request(data, U256::zero(), nonce.into(), fees, block)
+    }
+
+    /// Builds the unsigned backrun transaction, aborting it if the frontrun output fell short
+    ///
+    /// `held` is the executor's balance of the leg's input token before the frontrun, so inventory
+    /// already held can't cover a shortfall. Without an abort guard this is [TxFactory::backrun].
+    pub fn backrun_checked(
+        &self,
+        leg: &SwapLeg,
+        held: U256,
+        nonce: impl Into<NonceSlot>,
+        fees: &LegFees,
+        block: U64,
+    ) -> Result<TypedTransaction> {
+        let bps = match self.abort_deviation_bps {
+            Some(bps) => bps,
+            None => return self.backrun(leg, nonce, fees, block),
+        };
+        let data = self.executor.encode_backrun(leg)?;
+        let data = self.executor.guard_block(data, block)?;
+        let data = self
+            .executor
+            .guard_balance(data, min_backrun_balance(held, leg.amount_in, bps))?;
+        Ok(self.call(self.executor.address(), data, U256::zero(), nonce, fees))
+    }
+
+    /// Builds the unsigned transaction minting a JIT liquidity position ahead of a swap
+    pub fn jit_mint(
+        &self,
+        position: &LiquidityPosition,
+        nonce: impl Into<NonceSlot>,
+        fees: &LegFees,
+        block: U64,
+    ) -> Result<TypedTransaction> {
+        let data = self.executor.encode_mint(position)?;
+        self.leg_request(data, U256::zero(), nonce.into(), fees, block)
+    }
+
+    /// Builds the unsigned transaction burning a JIT liquidity position after the swap
+    pub fn jit_burn(
+        &self,
+        position: &LiquidityPosition,
+        nonce: impl Into<NonceSlot>,
+        fees: &LegFees,
+        block: U64,
+    ) -> Result<TypedTransaction> {
+        let data = self.executor.encode_burn(position)?;
+        self.leg_request(data, U256::zero(), nonce.into(), fees, block)
+    }
+
+    /// Builds the unsigned transaction filling an order through the executor
+    pub fn fill_order(
+        &self,
+        fill: &OrderFill,
+        nonce: impl Into<NonceSlot>,
+        fees: &LegFees,
+        block: U64,
+    ) -> Result<TypedTransaction> {
+        let data = self.executor.encode_order_fill(fill)?;
+        self.leg_request(data, U256::zero(), nonce.into(), fees, block)
+    }
+
+    /// Builds the unsigned transaction liquidating a lending position
+    pub fn liquidate(
+        &self,
+        liquidation: &Liquidation,
+        nonce: impl Into<NonceSlot>,
+        fees: &LegFees,
+        block: U64,
+    ) -> Result<TypedTransaction> {
+        let data = self.executor.encode_liquidation(liquidation)?;
+        self.leg_request(data, U256::zero(), nonce.into(), fees, block)
+    }
+
+    /// Signs a transaction with the wallet it's from, returning the rlp-encoded signed transaction
+    pub async fn sign(&self, tx: &TypedTransaction) -> Result<Bytes> {
+        let wallet = match tx.from() {
+            Some(from) => self
+                .wallets
+                .iter()
+                .find(|w| w.address() == *from)
+                .ok_or_else(|| eyre::eyre!("No searcher wallet for {:?}", from))?,
+            None => &self.wallet,
+        };
+        let signature = wallet
+            .sign_transaction(tx)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to sign transaction: {:?}", e))?;
+        Ok(tx.rlp_signed(&signature))
+    }
+}
+
+/// The least balance of a backrun's input token the executor must hold for the leg to execute
+///
+/// `held` before the frontrun, plus the `expected` frontrun output less `deviation_bps`.
+pub fn min_backrun_balance(held: U256, expected: U256, deviation_bps: u64) -> U256 {
+    let tolerated = expected * U256::from(10_000u64.saturating_sub(deviation_bps)) / 10_000;
+    held.saturating_add(tolerated)
+}
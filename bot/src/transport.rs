@@ -0,0 +1,84 @@
+//! Relay Transport
+//!
+//! A bundle is only as early as its slowest hop, and a fresh connection per submission pays for
+//! DNS, the TCP handshake and TLS before the request even leaves. Relay submissions, Flashbots
+//! simulations included, share one [reqwest::Client] built by [relay_http_client]: connections are
+//! pooled and kept alive between blocks, Nagle's algorithm is off so small requests aren't held
+//! back, and HTTP/2 can be enabled for relays that multiplex.
+//!
+//! Every submission's round trip is timed with [record_round_trip], so a relay whose latency
+//! drifts shows up in the metrics rather than only as missed blocks.
+
+use std::time::Duration;
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::metrics;
+
+/// The default time an idle relay connection is kept open, in seconds
+pub const DEFAULT_RELAY_POOL_IDLE_SECS: u64 = 90;
+
+/// The default interval between TCP keep-alive probes on relay connections, in seconds
+pub const DEFAULT_RELAY_KEEPALIVE_SECS: u64 = 15;
+
+/// The default time a relay request may take before it's abandoned, in milliseconds
+pub const DEFAULT_RELAY_TIMEOUT_MS: u64 = 5_000;
+
+/// How many idle connections are kept per relay host
+pub const RELAY_POOL_MAX_IDLE_PER_HOST: usize = 8;
+
+/// The relay http client settings
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelayHttpConfig {
+    /// Whether to negotiate HTTP/2 with relays that support it, HTTP/1.1 only otherwise
+    pub http2: bool,
+    /// How long an idle connection is kept open, in seconds
+    pub pool_idle_secs: u64,
+    /// The interval between TCP keep-alive probes, in seconds
+    pub keepalive_secs: u64,
+    /// How long a request may take, in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for RelayHttpConfig {
+    fn default() -> Self {
+        Self {
+            http2: false,
+            pool_idle_secs: DEFAULT_RELAY_POOL_IDLE_SECS,
+            keepalive_secs: DEFAULT_RELAY_KEEPALIVE_SECS,
+            timeout_ms: DEFAULT_RELAY_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Builds the http client shared by relay submissions
+///
+/// Clones of the client share its connection pool.
+pub fn relay_http_client(config: &RelayHttpConfig) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .tcp_nodelay(true)
+        .tcp_keepalive(Duration::from_secs(config.keepalive_secs.max(1)))
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_secs))
+        .pool_max_idle_per_host(RELAY_POOL_MAX_IDLE_PER_HOST)
+        .timeout(Duration::from_millis(config.timeout_ms.max(1)));
+    builder = if config.http2 {
+        builder.http2_adaptive_window(true)
+    } else {
+        builder.http1_only()
+    };
+    builder
+        .build()
+        .map_err(|e| eyre::eyre!("Failed to build the relay http client: {:?}", e))
+}
+
+/// Records a submission round trip to `relay`
+///
+/// `relay_rtt_ms_<relay>` over `relay_requests_<relay>` gives the relay's average round trip, and
+/// the `relay_last_rtt_ms_<relay>` gauge holds the latest one.
+pub fn record_round_trip(relay: &str, elapsed: Duration) {
+    let millis = elapsed.as_millis().min(u64::MAX as u128) as u64;
+    metrics::global().add(&format!("relay_rtt_ms_{relay}"), millis);
+    metrics::increment(&format!("relay_requests_{relay}"));
+    metrics::global().set(&format!("relay_last_rtt_ms_{relay}"), millis);
+}
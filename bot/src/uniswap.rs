@@ -2,11 +2,177 @@
 
 use ethers::prelude::*;
 use eyre::Result;
-use hex::FromHex;
-use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::Arc};
+use thiserror::Error;
 
 use crate::utils::*;
-use crate::{abi::UniswapV2Pair, prelude::UniswapV2Factory};
+use crate::{
+    abi::{UniswapV2Pair, UniswapV2Router02},
+    prelude::UniswapV2Factory,
+    state::StateReader,
+};
+
+/// The Uniswap V2 swap fee, in bips
+pub const UNIV2_FEE_BPS: u32 = 30;
+
+/// The PancakeSwap V2 swap fee, in bips
+pub const PANCAKESWAP_V2_FEE_BPS: u32 = 25;
+
+/// How far, in wei, each hop of a computed quote may drift from the router's for rounding
+pub const QUOTE_ROUNDING_WEI: u64 = 1;
+
+/// The Uniswap V2 pair init code hash
+pub const UNIV2_PAIR_INIT_CODE_HASH: &str =
+    "96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f";
+
+/// A Uniswap V2 deployment, whose pair addresses derive from its factory and pair init code
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct V2Deployment {
+    /// The factory deploying the pairs
+    pub factory: Address,
+    /// The keccak256 of the pair init code
+    pub init_code_hash: H256,
+}
+
+impl Default for V2Deployment {
+    /// The mainnet Uniswap V2 deployment
+    fn default() -> Self {
+        Self {
+            factory: get_univ2_factory_address(),
+            init_code_hash: H256::from_str(UNIV2_PAIR_INIT_CODE_HASH).unwrap(),
+        }
+    }
+}
+
+impl V2Deployment {
+    /// The address of the pair of `a` and `b`, in either order
+    pub fn pair_address(&self, a: Address, b: Address) -> Address {
+        let (token0, token1) = if a < b { (a, b) } else { (b, a) };
+        let mut data = [0u8; 40];
+        data[0..20].copy_from_slice(token0.as_bytes());
+        data[20..].copy_from_slice(token1.as_bytes());
+        let salt = ethers::utils::keccak256(data);
+        ethers::utils::get_create2_address_from_hash(self.factory, salt, self.init_code_hash)
+    }
+}
+
+/// A pair's tokens in canonical order, with its swap fee
+///
+/// Pairs store their reserves ordered by token address. [SortedPair::orient] puts them in swap
+/// order, so callers don't compare addresses themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SortedPair {
+    /// The lower token address
+    pub token0: Address,
+    /// The higher token address
+    pub token1: Address,
+    /// The swap fee, in bips
+    pub fee_bps: u32,
+}
+
+impl SortedPair {
+    /// Sorts two tokens into a pair with the Uniswap V2 fee
+    pub fn new(a: Address, b: Address) -> Self {
+        let (token0, token1) = if a < b { (a, b) } else { (b, a) };
+        Self {
+            token0,
+            token1,
+            fee_bps: UNIV2_FEE_BPS,
+        }
+    }
+
+    /// Sets the swap fee, for fork DEXes
+    pub fn with_fee_bps(mut self, fee_bps: u32) -> Self {
+        self.fee_bps = fee_bps;
+        self
+    }
+
+    /// The other token of the pair, if `token` is in it
+    pub fn other(&self, token: Address) -> Option<Address> {
+        if token == self.token0 {
+            Some(self.token1)
+        } else if token == self.token1 {
+            Some(self.token0)
+        } else {
+            None
+        }
+    }
+
+    /// The Uniswap V2 pair address
+    pub fn address(&self) -> Result<Address> {
+        calculate_uniswap_v2_pair_address(&self.token0, &self.token1)
+    }
+
+    /// The pair's address in `deployment`
+    pub fn address_in(&self, deployment: &V2Deployment) -> Address {
+        deployment.pair_address(self.token0, self.token1)
+    }
+
+    /// Orders `(reserve0, reserve1)` as `(reserve_in, reserve_out)` for a swap from `token_in`
+    pub fn orient(&self, token_in: Address, (reserve0, reserve1): (U256, U256)) -> (U256, U256) {
+        if token_in == self.token0 {
+            (reserve0, reserve1)
+        } else {
+            (reserve1, reserve0)
+        }
+    }
+
+    /// Reads the Uniswap V2 reserves at `block`, or the latest block when `None`, ordered as
+    /// `(reserve_in, reserve_out)` for a swap from `token_in`
+    pub async fn reserves_from<S: StateReader + ?Sized>(
+        &self,
+        state: &S,
+        token_in: Address,
+        block: Option<U64>,
+    ) -> Result<(U256, U256)> {
+        let reserves = state.reserves(self.address()?, block).await?;
+        Ok(self.orient(token_in, reserves))
+    }
+
+    /// [SortedPair::reserves_from] for the pair in `deployment`
+    pub async fn reserves_in<S: StateReader + ?Sized>(
+        &self,
+        state: &S,
+        deployment: &V2Deployment,
+        token_in: Address,
+        block: Option<U64>,
+    ) -> Result<(U256, U256)> {
+        let reserves = state.reserves(self.address_in(deployment), block).await?;
+        Ok(self.orient(token_in, reserves))
+    }
+
+    /// The output of swapping `amount_in` at the pair's fee
+    pub fn amount_out(&self, amount_in: U256, reserve_in: U256, reserve_out: U256) -> U256 {
+        get_amount_out(amount_in, reserve_in, reserve_out, self.fee_bps)
+    }
+}
+
+/// The output of swapping `amount_in` through a constant product pair charging `fee_bps`
+pub fn get_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee_bps: u32) -> U256 {
+    let amount_in_with_fee =
+        amount_in.saturating_mul(U256::from(10_000u32.saturating_sub(fee_bps)));
+    let numerator = amount_in_with_fee.saturating_mul(reserve_out);
+    let denominator = reserve_in
+        .saturating_mul(U256::from(10_000u32))
+        .saturating_add(amount_in_with_fee);
+    numerator.checked_div(denominator).unwrap_or(U256::zero())
+}
+
+/// The input needed to swap out `amount_out` through a constant product pair charging `fee_bps`
+///
+/// Returns `U256::MAX` when the pair can't pay out that much.
+pub fn get_amount_in(amount_out: U256, reserve_in: U256, reserve_out: U256, fee_bps: u32) -> U256 {
+    let remaining = reserve_out.saturating_sub(amount_out);
+    let numerator = reserve_in
+        .saturating_mul(amount_out)
+        .saturating_mul(U256::from(10_000u32));
+    let denominator = remaining.saturating_mul(U256::from(10_000u32.saturating_sub(fee_bps)));
+    numerator
+        .checked_div(denominator)
+        .unwrap_or(U256::MAX - 1)
+        .saturating_add(U256::one())
+}
 
 /// Returns the Uniswap V2 Pair Contract Address
 ///
@@ -51,30 +217,7 @@ pub fn get_univ2_factory_contract(
 
 /// Gets the Uniswap V2 Pair Contract Address given two token addresses
 pub fn calculate_uniswap_v2_pair_address(a: &Address, b: &Address) -> Result<Address> {
-    // Sort the tokens
-    let mut tokens = vec![a, b];
-    tokens.sort();
-
-    // Copy the token addresses into a byte array
-    let mut data = [0u8; 40];
-    data[0..20].copy_from_slice(tokens[0].as_bytes());
-    data[20..].copy_from_slice(tokens[1].as_bytes());
-
-    // Hash the concatenated token address bytes
-    let salt = ethers::utils::keccak256(data);
-
-    // Get the init code
-    let init_code =
-        <[u8; 32]>::from_hex("96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f")
-            .map_err(|_| eyre::eyre!("Invalid init code hex"))?;
-
-    // Get the uniswap factory address
-    let factory = get_univ2_factory_address();
-
-    // Compute the address with create2
-    Ok(ethers::utils::get_create2_address_from_hash(
-        factory, salt, init_code,
-    ))
+    Ok(V2Deployment::default().pair_address(*a, *b))
 }
 
 /// Gets the Uniswap V2 Pair Contract Address given two token addresses
@@ -107,10 +250,7 @@ pub fn get_univ2_data_given_in(
     b_reserves: &U256,
 ) -> (U256, U256, U256) {
     // Calculate the output
-    let a_in_with_fee: U256 = a_in * 997;
-    let numerator: U256 = a_in_with_fee * b_reserves;
-    let denominator: U256 = a_reserves * 1000 + a_in_with_fee;
-    let b_out: U256 = numerator.checked_div(denominator).unwrap_or(U256::zero());
+    let b_out = get_amount_out(*a_in, *a_reserves, *b_reserves, UNIV2_FEE_BPS);
 
     // Calculate the new b reserves, accounting for underflow
     let new_b_reserves = b_reserves.checked_sub(b_out).unwrap_or(U256::one());
@@ -134,9 +274,7 @@ pub fn get_univ2_data_given_out(
     let new_b_reserves = b_reserves.checked_sub(*b_out).unwrap_or(U256::zero());
 
     // Calculate the amount in
-    let numerator: U256 = a_reserves * b_out * 1000;
-    let denominator: U256 = new_b_reserves * 997;
-    let a_in = numerator.checked_div(denominator).unwrap_or(U256::MAX - 1) + 1;
+    let a_in = get_amount_in(*b_out, *a_reserves, *b_reserves, UNIV2_FEE_BPS);
 
     // Calculate the new a reserves, accounting for overflow
     let new_a_reserves = a_reserves.checked_add(a_in).unwrap_or(U256::MAX);
@@ -146,9 +284,29 @@ pub fn get_univ2_data_given_out(
 }
 
 /// Compute how much the user is willing to accept as a minimum output
-pub async fn get_univ2_exact_weth_token_min_recv(
+pub async fn get_univ2_exact_weth_token_min_recv<S: StateReader + ?Sized>(
+    state: &S,
     final_min_recv: &U256,
     path: &Vec<Address>,
+    block: Option<U64>,
+) -> Result<U256> {
+    get_univ2_exact_weth_token_min_recv_in(
+        state,
+        &V2Deployment::default(),
+        final_min_recv,
+        path,
+        block,
+    )
+    .await
+}
+
+/// [get_univ2_exact_weth_token_min_recv] through the pairs of `deployment`
+pub async fn get_univ2_exact_weth_token_min_recv_in<S: StateReader + ?Sized>(
+    state: &S,
+    deployment: &V2Deployment,
+    final_min_recv: &U256,
+    path: &Vec<Address>,
+    block: Option<U64>,
 ) -> Result<U256> {
     let mut user_min_recv = *final_min_recv;
 
@@ -159,11 +317,10 @@ pub async fn get_univ2_exact_weth_token_min_recv(
         let from_token = path[i - 1];
         let to_token = path[i];
 
-        // Calculate the pair address using create2
-        let pair = calculate_uniswap_v2_pair_address(&from_token, &to_token)?;
-
-        // Get the token pair reserves
-        let (from_reserves, to_reserves) = get_uniswap_v2_reserves(&pair).await?;
+        // Get the token pair reserves in swap order
+        let (from_reserves, to_reserves) = SortedPair::new(from_token, to_token)
+            .reserves_in(state, deployment, from_token, block)
+            .await?;
 
         // Get the new reserve data
         (user_min_recv, _, _) =
@@ -176,3 +333,121 @@ pub async fn get_univ2_exact_weth_token_min_recv(
     // Return the final amount
     Ok(user_min_recv)
 }
+
+/// Whether computed amounts are cross-checked against the router's `getAmountsOut`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum QuoteCheck {
+    /// Don't check, the default since each check costs an `eth_call` per hop
+    #[default]
+    Off,
+    /// Log and count mismatches
+    Warn,
+    /// Panic on a mismatch, for catching math regressions in testing
+    Assert,
+}
+
+impl FromStr for QuoteCheck {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "off" | "false" => Ok(QuoteCheck::Off),
+            "warn" | "true" => Ok(QuoteCheck::Warn),
+            "assert" => Ok(QuoteCheck::Assert),
+            _ => Err(eyre::eyre!("Unknown quote check \"{}\"", s)),
+        }
+    }
+}
+
+/// A computed path amount that disagrees with the router
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+pub enum QuoteMismatch {
+    /// The router quoted a different number of hops
+    #[error("computed {computed} amounts, router quoted {quoted}")]
+    Length {
+        /// The number of computed amounts
+        computed: usize,
+        /// The number of quoted amounts
+        quoted: usize,
+    },
+    /// An amount differs by more than rounding
+    #[error("amount {index} computed as {computed}, router quoted {quoted}")]
+    Amount {
+        /// The index of the amount in the path
+        index: usize,
+        /// The computed amount
+        computed: U256,
+        /// The router's amount
+        quoted: U256,
+    },
+}
+
+/// Compares computed path amounts with the router's
+///
+/// Each hop may add [QUOTE_ROUNDING_WEI] of drift, since rounding compounds along the path.
+pub fn verify_quote(computed: &[U256], quoted: &[U256]) -> std::result::Result<(), QuoteMismatch> {
+    if computed.len() != quoted.len() {
+        return Err(QuoteMismatch::Length {
+            computed: computed.len(),
+            quoted: quoted.len(),
+        });
+    }
+    for (index, (computed, quoted)) in computed.iter().zip(quoted).enumerate() {
+        let difference = if computed > quoted {
+            *computed - *quoted
+        } else {
+            *quoted - *computed
+        };
+        if difference > U256::from(QUOTE_ROUNDING_WEI * index as u64) {
+            return Err(QuoteMismatch::Amount {
+                index,
+                computed: *computed,
+                quoted: *quoted,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Computes the amounts of a swap along `path` through the pairs of `deployment`, from their
+/// reserves at `block`
+pub async fn compute_amounts_out<S: StateReader + ?Sized>(
+    state: &S,
+    deployment: &V2Deployment,
+    amount_in: U256,
+    path: &[Address],
+    block: U64,
+) -> Result<Vec<U256>> {
+    let mut amounts = vec![amount_in];
+    for hop in path.windows(2) {
+        let pair = SortedPair::new(hop[0], hop[1]);
+        let (reserve_in, reserve_out) = pair
+            .reserves_in(state, deployment, hop[0], Some(block))
+            .await?;
+        let amount_in = *amounts.last().unwrap_or(&amount_in);
+        amounts.push(pair.amount_out(amount_in, reserve_in, reserve_out));
+    }
+    Ok(amounts)
+}
+
+/// Checks computed amounts along `path` against the router's `getAmountsOut` at `block`
+///
+/// Errors if either quote can't be read, and returns the mismatch if they disagree.
+pub async fn check_amounts_out<M: Middleware + 'static, S: StateReader + ?Sized>(
+    client: Arc<M>,
+    state: &S,
+    deployment: &V2Deployment,
+    router: Address,
+    amount_in: U256,
+    path: &[Address],
+    block: U64,
+) -> Result<std::result::Result<(), QuoteMismatch>> {
+    let computed = compute_amounts_out(state, deployment, amount_in, path, block).await?;
+    let quoted = UniswapV2Router02::new(router, client)
+        .get_amounts_out(amount_in, path.to_vec())
+        .block(BlockNumber::Number(block))
+        .call()
+        .await
+        .map_err(|e| eyre::eyre!("Failed to quote getAmountsOut: {:?}", e))?;
+    Ok(verify_quote(&computed, &quoted))
+}
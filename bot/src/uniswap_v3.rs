@@ -0,0 +1,46 @@
+//! Uniswap V3 Pools
+//!
+//! A V3 token pair has a pool per fee tier. Pool addresses are derived from the factory, the
+//! sorted tokens and the fee with [get_pool], without a request. A V3 candidate names the fee of
+//! the pool it swaps through in its calldata, so its pool is known without reading the others.
+
+use std::str::FromStr;
+
+use ethers::{
+    abi::{self, Token},
+    prelude::*,
+};
+use hex::FromHex;
+
+/// The Uniswap V3 factory
+pub const UNISWAP_V3_FACTORY: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
+
+/// The init code hash of Uniswap V3 pools
+pub const V3_POOL_INIT_CODE_HASH: &str =
+    "e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54";
+
+/// Returns the Uniswap V3 factory address
+pub fn get_factory_address() -> Address {
+    Address::from_str(UNISWAP_V3_FACTORY).unwrap()
+}
+
+/// Derives the address of the V3 pool of two tokens at a fee tier
+pub fn get_pool(token_a: Address, token_b: Address, fee: u32) -> Address {
+    get_pool_in(get_factory_address(), token_a, token_b, fee)
+}
+
+/// [get_pool] for the pools deployed by `factory`
+pub fn get_pool_in(factory: Address, token_a: Address, token_b: Address, fee: u32) -> Address {
+    let (token0, token1) = if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    };
+    let salt = ethers::utils::keccak256(abi::encode(&[
+        Token::Address(token0),
+        Token::Address(token1),
+        Token::Uint(U256::from(fee)),
+    ]));
+    let init_code = <[u8; 32]>::from_hex(V3_POOL_INIT_CODE_HASH).unwrap();
+    ethers::utils::get_create2_address_from_hash(factory, salt, init_code)
+}
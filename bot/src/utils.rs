@@ -6,6 +6,8 @@ use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
 use eyre::Result;
 use rand::Rng;
 
+use crate::instances;
+
 /// Sorts two tokens
 pub fn sort_tokens(a: &mut Address, b: &mut Address) {
     if a > b {
@@ -13,6 +15,14 @@ pub fn sort_tokens(a: &mut Address, b: &mut Address) {
     }
 }
 
+/// Compares two secrets in time independent of where they first differ
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
 /// Returns the WETH Contract Address
 ///
 /// Although this function unwraps the address conversion, it is safe as the string is checked.
@@ -79,7 +89,7 @@ pub fn read_env_vars() -> Result<Vec<(String, String)>> {
     ];
     for key in keys {
         // Read environment variable
-        let value = std::env::var(key)
+        let value = instances::var(key)
             .map_err(|_| eyre::eyre!("Required environment variable \"{}\" not set", key))?;
         env_vars.push((key.to_string(), value));
     }
@@ -88,23 +98,39 @@ pub fn read_env_vars() -> Result<Vec<(String, String)>> {
 
 /// Returns the configured Sandwich Contract Address
 pub fn get_sandwich_contract_address() -> Result<Address> {
-    let addr = std::env::var("SANDWICH_CONTRACT")
+    let addr = instances::var("SANDWICH_CONTRACT")
         .map_err(|_| eyre::eyre!("Required environment variable \"SANDWICH_CONTRACT\" not set"))?;
     Address::from_str(&addr).map_err(|_| eyre::eyre!("Invalid address \"{}\"", addr))
 }
 
 /// Return a Provider for the given URL
 pub fn get_http_provider() -> Result<Provider<Http>> {
-    let url = std::env::var("RPC_URL")
+    let url = instances::var("RPC_URL")
         .map_err(|_| eyre::eyre!("Required environment variable \"RPC_URL\" not set"))?;
     Provider::<Http>::try_from(url).map_err(|_| eyre::eyre!("Invalid RPC URL"))
 }
 
+/// Return a Provider reserve reads are cross-checked against
+///
+/// Reads `RESERVE_CONSENSUS_RPC_URL`, without falling back to a provider the bot already reads
+/// from, as a check against the same node proves nothing.
+pub fn get_consensus_provider() -> Result<Provider<Http>> {
+    let url = instances::var("RESERVE_CONSENSUS_RPC_URL").map_err(|_| {
+        eyre::eyre!("Required environment variable \"RESERVE_CONSENSUS_RPC_URL\" not set")
+    })?;
+    Provider::<Http>::try_from(url).map_err(|_| eyre::eyre!("Invalid reserve consensus RPC URL"))
+}
+
 /// Return a Provider for the given Websocket URL
 pub async fn get_ws_provider() -> Result<Provider<Ws>> {
-    let url = std::env::var("RPC_URL_WSS")
+    let url = instances::var("RPC_URL_WSS")
         .map_err(|_| eyre::eyre!("Required environment variable \"RPC_URL_WSS\" not set"))?;
-    Provider::<Ws>::connect(&url)
+    connect_ws_provider(&url).await
+}
+
+/// Return a Provider connected to `url`
+pub async fn connect_ws_provider(url: &str) -> Result<Provider<Ws>> {
+    Provider::<Ws>::connect(url)
         .await
         .map_err(|e| eyre::eyre!("RPC Connection Error: {:?}", e))
 }
@@ -115,25 +141,131 @@ pub async fn create_websocket_client() -> Result<Arc<Provider<Ws>>> {
     Ok(Arc::new(client))
 }
 
+/// Returns the node's IPC socket path, if one is configured in `RPC_IPC_PATH`
+pub fn get_ipc_path() -> Option<String> {
+    instances::var("RPC_IPC_PATH")
+        .ok()
+        .filter(|p| !p.is_empty())
+}
+
+/// Return a Provider for the node's IPC socket at `path`
+pub async fn get_ipc_provider(path: &str) -> Result<Provider<Ipc>> {
+    let ipc = Ipc::connect(path)
+        .await
+        .map_err(|e| eyre::eyre!("IPC Connection Error on {}: {:?}", path, e))?;
+    Ok(Provider::new(ipc))
+}
+
+/// Create an IPC Client, if `RPC_IPC_PATH` is set
+///
+/// A node on the same host answers over its unix socket without the websocket framing and TCP
+/// round trips, so reads and subscriptions go there when it's available.
+pub async fn create_ipc_client() -> Result<Option<Arc<Provider<Ipc>>>> {
+    match get_ipc_path() {
+        Some(path) => Ok(Some(Arc::new(get_ipc_provider(&path).await?))),
+        None => Ok(None),
+    }
+}
+
 /// Construct the searcher wallet
 pub fn get_searcher_wallet() -> Result<LocalWallet> {
-    let private_key = std::env::var("PRIVATE_KEY")
+    let private_key = instances::var("PRIVATE_KEY")
         .map_err(|_| eyre::eyre!("Required environment variable \"PRIVATE_KEY\" not set"))?;
     private_key
         .parse::<LocalWallet>()
         .map_err(|e| eyre::eyre!("Failed to parse private key: {:?}", e))
 }
 
+/// Returns the optional extra searcher wallets bundles are spread across
+///
+/// Read from `EXTRA_PRIVATE_KEYS` as comma-separated private keys.
+pub fn get_extra_searcher_wallets() -> Result<Vec<LocalWallet>> {
+    let keys = match instances::var("EXTRA_PRIVATE_KEYS") {
+        Ok(k) => k,
+        Err(_) => return Ok(Vec::new()),
+    };
+    keys.split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(|k| {
+            k.parse::<LocalWallet>()
+                .map_err(|e| eyre::eyre!("Failed to parse extra private key: {:?}", e))
+        })
+        .collect()
+}
+
+/// Reads the key sandwich plans are signed with from `PLAN_SIGNER_KEY`, if set
+///
+/// It identifies the detector to remote executors, and should not be a searcher wallet key.
+pub fn get_plan_signer() -> Result<Option<LocalWallet>> {
+    match instances::var("PLAN_SIGNER_KEY") {
+        Ok(key) if !key.is_empty() => key
+            .parse::<LocalWallet>()
+            .map(Some)
+            .map_err(|e| eyre::eyre!("Failed to parse plan signer: {:?}", e)),
+        _ => Ok(None),
+    }
+}
+
 /// Construct the bundle signer
 /// This is your flashbots searcher identity
 pub fn get_bundle_signer() -> Result<LocalWallet> {
-    let private_key = std::env::var("FLASHBOTS_AUTH_KEY")
+    let private_key = instances::var("FLASHBOTS_AUTH_KEY")
         .map_err(|_| eyre::eyre!("Required environment variable \"FLASHBOTS_AUTH_KEY\" not set"))?;
     private_key
         .parse::<LocalWallet>()
         .map_err(|e| eyre::eyre!("Failed to parse flashbots signer: {:?}", e))
 }
 
+/// The keys an instance signs with
+#[derive(Debug, Clone)]
+pub struct Wallets {
+    /// The searcher wallet sandwiches are sent from
+    pub searcher: LocalWallet,
+    /// Extra searcher wallets bundles are spread across
+    pub extra: Vec<LocalWallet>,
+    /// The Flashbots searcher identity bundles are signed with
+    pub bundle_signer: LocalWallet,
+    /// The key sandwich plans are signed with, if any
+    pub plan_signer: Option<LocalWallet>,
+}
+
+impl Wallets {
+    /// Reads the keys from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            searcher: get_searcher_wallet()?,
+            extra: get_extra_searcher_wallets()?,
+            bundle_signer: get_bundle_signer()?,
+            plan_signer: get_plan_signer()?,
+        })
+    }
+}
+
+/// The node endpoints an instance connects to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    /// The websocket RPC url
+    pub rpc_url_wss: String,
+    /// The node's IPC socket path, if it runs on the same host
+    pub ipc_path: Option<String>,
+    /// The provider reserve reads are cross-checked against, if any
+    pub consensus_rpc_url: Option<String>,
+}
+
+impl Endpoints {
+    /// Reads the endpoints from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            rpc_url_wss: instances::var("RPC_URL_WSS").map_err(|_| {
+                eyre::eyre!("Required environment variable \"RPC_URL_WSS\" not set")
+            })?,
+            ipc_path: get_ipc_path(),
+            consensus_rpc_url: instances::var("RESERVE_CONSENSUS_RPC_URL").ok(),
+        })
+    }
+}
+
 /// Creates a client from a provider
 pub fn create_http_client(
     p: Provider<Http>,
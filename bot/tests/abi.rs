@@ -0,0 +1,214 @@
+use ethers::{abi::AbiEncode, prelude::*};
+
+use subway_rs::abi::*;
+
+fn swap_call() -> SwapExactETHForTokensCall {
+    SwapExactETHForTokensCall {
+        amount_out_min: U256::from(1_000_000u64),
+        path: vec![Address::random(), Address::random(), Address::random()],
+        to: Address::random(),
+        deadline: U256::from(u64::MAX),
+    }
+}
+
+#[test]
+fn test_swap_selector() {
+    assert_eq!(
+        SWAP_EXACT_ETH_FOR_TOKENS_SELECTOR,
+        ethers::utils::id("swapExactETHForTokens(uint256,address[],address,uint256)")
+    );
+}
+
+#[test]
+fn test_decode_round_trip() {
+    let call = swap_call();
+    let calldata = Bytes::from(call.clone().encode());
+
+    let view = SwapExactEthForTokensView::parse(calldata.as_ref()).unwrap();
+    assert_eq!(view.amount_out_min(), call.amount_out_min);
+    assert_eq!(view.to(), call.to);
+    assert_eq!(view.deadline(), call.deadline);
+    assert_eq!(view.path_len(), 3);
+    assert_eq!(view.path_at(2), Some(call.path[2]));
+    assert_eq!(view.path_at(3), None);
+    assert_eq!(view.path().collect::<Vec<_>>(), call.path);
+
+    assert_eq!(decode_uniswap_router_calldata(&calldata).unwrap(), call);
+}
+
+#[test]
+fn test_decode_rejects_invalid_calldata() {
+    let mut calldata = swap_call().encode();
+
+    assert_eq!(
+        SwapExactEthForTokensView::parse(&calldata[..2]),
+        Err(DecodeError::MissingSelector)
+    );
+    assert_eq!(
+        SwapExactEthForTokensView::parse(&calldata[..100]),
+        Err(DecodeError::TooShort)
+    );
+    // Drop the last path element
+    assert_eq!(
+        SwapExactEthForTokensView::parse(&calldata[..calldata.len() - 32]),
+        Err(DecodeError::TooShort)
+    );
+
+    // Set the high bytes of the path offset
+    calldata[4 + 32] = 0xff;
+    assert_eq!(
+        SwapExactEthForTokensView::parse(&calldata),
+        Err(DecodeError::InvalidOffset)
+    );
+
+    calldata[0] = 0;
+    assert_eq!(
+        SwapExactEthForTokensView::parse(&calldata),
+        Err(DecodeError::InvalidSelector([0x00, 0xf3, 0x6a, 0xb5]))
+    );
+}
+
+fn log(address: Address, topics: Vec<H256>, words: &[U256]) -> Log {
+    let mut data = Vec::new();
+    for word in words {
+        let mut bytes = [0u8; 32];
+        word.to_big_endian(&mut bytes);
+        data.extend_from_slice(&bytes);
+    }
+    Log {
+        address,
+        topics,
+        data: Bytes::from(data),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_event_topics() {
+    assert_eq!(
+        TRANSFER_TOPIC,
+        ethers::utils::keccak256("Transfer(address,address,uint256)")
+    );
+    assert_eq!(
+        SYNC_TOPIC,
+        ethers::utils::keccak256("Sync(uint112,uint112)")
+    );
+    assert_eq!(
+        SWAP_TOPIC,
+        ethers::utils::keccak256("Swap(address,uint256,uint256,uint256,uint256,address)")
+    );
+    assert_eq!(
+        PAIR_CREATED_TOPIC,
+        ethers::utils::keccak256("PairCreated(address,address,address,uint256)")
+    );
+}
+
+#[test]
+fn test_decode_pair_created() {
+    let factory = Address::random();
+    let (token0, token1, pair) = (Address::random(), Address::random(), Address::random());
+    let created = log(
+        factory,
+        vec![PAIR_CREATED_TOPIC.into(), token0.into(), token1.into()],
+        &[U256::from(H256::from(pair).as_bytes()), 42.into()],
+    );
+    let decoded = V2PairCreated::decode(&created).unwrap();
+    assert_eq!(
+        decoded,
+        V2PairCreated {
+            factory,
+            token0,
+            token1,
+            pair,
+            index: 42.into(),
+        }
+    );
+    assert_eq!(decoded.other(token0), Some(token1));
+    assert_eq!(decoded.other(token1), Some(token0));
+    assert_eq!(decoded.other(pair), None);
+    assert_eq!(
+        DecodedLog::decode(&created),
+        Some(DecodedLog::PairCreated(decoded))
+    );
+}
+
+#[test]
+fn test_decode_events() {
+    let pair = Address::random();
+    let router = Address::random();
+    let to = Address::random();
+
+    let swap = log(
+        pair,
+        vec![SWAP_TOPIC.into(), router.into(), to.into()],
+        &[100.into(), 0.into(), 0.into(), 250.into()],
+    );
+    let decoded = V2Swap::decode(&swap).unwrap();
+    assert_eq!(decoded.sender, router);
+    assert_eq!(decoded.to, to);
+    assert_eq!(decoded.amount0_in, U256::from(100));
+    assert_eq!(decoded.amount1_out, U256::from(250));
+    assert!(decoded.zero_for_one());
+    assert_eq!(DecodedLog::decode(&swap), Some(DecodedLog::Swap(decoded)));
+
+    let sync = log(pair, vec![SYNC_TOPIC.into()], &[1.into(), 2.into()]);
+    assert_eq!(
+        V2Sync::decode(&sync).unwrap(),
+        V2Sync {
+            pair,
+            reserve0: 1.into(),
+            reserve1: 2.into()
+        }
+    );
+
+    // Mismatched signatures and layouts are rejected
+    assert_eq!(
+        Erc20Transfer::decode(&sync),
+        Err(EventDecodeError::UnknownTopic(SYNC_TOPIC.into()))
+    );
+    let short = log(pair, vec![SYNC_TOPIC.into()], &[1.into()]);
+    assert_eq!(
+        V2Sync::decode(&short),
+        Err(EventDecodeError::InvalidData {
+            expected: 64,
+            actual: 32
+        })
+    );
+    assert_eq!(DecodedLog::decode(&short), None);
+    assert_eq!(
+        V2Sync::decode(&log(pair, vec![], &[])),
+        Err(EventDecodeError::MissingTopic)
+    );
+}
+
+#[test]
+fn test_token_flows_and_reserves() {
+    let weth = Address::random();
+    let executor = Address::random();
+    let pair = Address::random();
+    let transfer = |from: Address, to: Address, value: u64| {
+        log(
+            weth,
+            vec![TRANSFER_TOPIC.into(), from.into(), to.into()],
+            &[value.into()],
+        )
+    };
+    let logs = vec![
+        transfer(executor, pair, 100),
+        log(pair, vec![SYNC_TOPIC.into()], &[1.into(), 2.into()]),
+        transfer(pair, executor, 130),
+        log(pair, vec![SYNC_TOPIC.into()], &[3.into(), 4.into()]),
+    ];
+    assert_eq!(
+        token_flows(&logs, weth, executor),
+        (U256::from(100), U256::from(130))
+    );
+    assert_eq!(
+        latest_reserves(&logs),
+        vec![V2Sync {
+            pair,
+            reserve0: 3.into(),
+            reserve1: 4.into()
+        }]
+    );
+}
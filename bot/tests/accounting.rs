@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+
+use ethers::prelude::*;
+use subway_rs::accounting::*;
+
+fn transfer(token: Address, from: Address, to: Address, amount: u64) -> Log {
+    let mut data = [0u8; 32];
+    U256::from(amount).to_big_endian(&mut data);
+    Log {
+        address: token,
+        topics: vec![
+            H256::from(ethers::utils::keccak256(
+                "Transfer(address,address,uint256)",
+            )),
+            H256::from(from),
+            H256::from(to),
+        ],
+        data: Bytes::from(data.to_vec()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_weth_flows() {
+    let weth = Address::from_low_u64_be(1);
+    let executor = Address::from_low_u64_be(2);
+    let pair = Address::from_low_u64_be(3);
+    let logs = vec![
+        transfer(weth, executor, pair, 100),
+        transfer(pair, pair, executor, 5_000),
+        transfer(weth, pair, executor, 130),
+        transfer(weth, pair, Address::from_low_u64_be(4), 999),
+    ];
+    assert_eq!(
+        weth_flows(&logs, weth, executor),
+        (U256::from(100), U256::from(130))
+    );
+}
+
+#[test]
+fn test_settlement_profit() {
+    let settlement = Settlement {
+        both_legs: true,
+        weth_spent: 100.into(),
+        weth_received: 130.into(),
+        gas: 5.into(),
+        ..Default::default()
+    };
+    assert_eq!(settlement.gross_profit(), U256::from(30));
+    assert!(!settlement.is_loss());
+
+    let loss = Settlement {
+        weth_received: 90.into(),
+        ..settlement
+    };
+    assert_eq!(loss.gross_profit(), U256::zero());
+    assert!(loss.is_loss());
+}
+
+#[test]
+fn test_deviation_bps() {
+    assert_eq!(deviation_bps(100.into(), 100.into()), 0);
+    assert_eq!(deviation_bps(100.into(), 90.into()), 1_000);
+    assert_eq!(deviation_bps(100.into(), 150.into()), 5_000);
+    assert_eq!(deviation_bps(0.into(), 0.into()), 0);
+    assert_eq!(deviation_bps(0.into(), 1.into()), 10_000);
+}
+
+#[test]
+fn test_reconcile() {
+    let accounting = Accounting::new();
+    let wallet = Address::random();
+    let settlement = Settlement {
+        both_legs: true,
+        weth_spent: 100.into(),
+        weth_received: 130.into(),
+        gas: 5.into(),
+        ..Default::default()
+    };
+    let fees = FeeEstimate {
+        frontrun_gas: 1.into(),
+        backrun_gas: 1.into(),
+        base_fee: 2.into(),
+        bribe_per_gas: 2.into(),
+        refund_percent: 0,
+    };
+    let reconciliation = accounting.reconcile(wallet, 40.into(), &fees, &settlement);
+    assert_eq!(reconciliation.realized_profit, U256::from(30));
+    assert_eq!(reconciliation.deviation_bps, 2_500);
+    assert_eq!(reconciliation.estimated_gas, U256::from(6));
+    assert_eq!(reconciliation.realized_gas, U256::from(5));
+
+    // Profit stays pending until the block has enough confirmations
+    assert_eq!(accounting.snapshot(), Ledger::default());
+    assert_eq!(accounting.pending_snapshot().gross_profit, U256::from(30));
+    let canonical = BTreeMap::from([(U64::zero(), H256::zero())]);
+    let outcome = accounting.confirm(10.into(), 12, &canonical);
+    assert_eq!(outcome, Confirmations::default());
+    let outcome = accounting.confirm(11.into(), 12, &canonical);
+    assert_eq!(outcome.realized, 1);
+    assert_eq!(accounting.pending_snapshot(), Ledger::default());
+
+    let ledger = accounting.snapshot();
+    assert_eq!(ledger.gross_profit, U256::from(30));
+    assert_eq!(ledger.estimated_profit, U256::from(40));
+    assert_eq!(ledger.estimated_gas, U256::from(6));
+    assert_eq!(ledger.estimated_bribes, U256::from(2));
+    assert_eq!(ledger.net_profit(), U256::from(25));
+    assert_eq!(accounting.wallet_snapshots()[&wallet], ledger);
+}
+
+#[test]
+fn test_aggregate_across_wallets() {
+    let accounting = Accounting::new();
+    let (a, b) = (Address::random(), Address::random());
+    accounting.record_for(a, 10.into(), 1.into(), 2.into());
+    accounting.record_for(b, 20.into(), 0.into(), 3.into());
+    accounting.record_for(a, 5.into(), 0.into(), 1.into());
+
+    let wallets = accounting.wallet_snapshots();
+    assert_eq!(wallets[&a].gross_profit, U256::from(15));
+    assert_eq!(wallets[&b].gross_profit, U256::from(20));
+
+    let total = accounting.snapshot();
+    assert_eq!(total.gross_profit, U256::from(35));
+    assert_eq!(total.net_profit(), U256::from(28));
+}
+
+#[test]
+fn test_lifetime_ledger() {
+    let accounting = Accounting::new();
+    accounting.record(10.into(), 1.into(), 2.into());
+    accounting.restore(Ledger {
+        gross_profit: 100.into(),
+        rebates: 0.into(),
+        gas: 20.into(),
+        estimated_profit: 110.into(),
+        ..Default::default()
+    });
+
+    assert_eq!(accounting.snapshot().gross_profit, U256::from(10));
+    let lifetime = accounting.lifetime();
+    assert_eq!(lifetime.gross_profit, U256::from(110));
+    assert_eq!(lifetime.gas, U256::from(22));
+    assert_eq!(lifetime.net_profit(), U256::from(87));
+}
+
+#[test]
+fn test_leg_gas_from_receipts() {
+    // The backrun was signed with a 10 gwei bribe, but the base fee rose and capped its tip
+    let receipt = TransactionReceipt {
+        gas_used: Some(100_000.into()),
+        effective_gas_price: Some(U256::exp10(9) * 35),
+        ..Default::default()
+    };
+    let backrun = leg_gas(&receipt, U256::exp10(9) * 30);
+    assert_eq!(backrun.cost(), U256::exp10(14) * 35);
+    assert_eq!(backrun.priority_fee_per_gas(), U256::exp10(9) * 5);
+    assert_eq!(backrun.priority_fee(), U256::exp10(14) * 5);
+
+    let frontrun = leg_gas(&receipt, U256::exp10(9) * 35);
+    assert_eq!(frontrun.priority_fee(), U256::zero());
+    let settlement = Settlement {
+        gas: frontrun.cost() + backrun.cost(),
+        frontrun_gas: Some(frontrun),
+        backrun_gas: Some(backrun),
+        ..Default::default()
+    };
+    assert_eq!(settlement.bribe(), U256::exp10(14) * 5);
+
+    let fees = FeeEstimate {
+        frontrun_gas: 100_000.into(),
+        backrun_gas: 100_000.into(),
+        base_fee: U256::exp10(9) * 30,
+        bribe_per_gas: U256::exp10(9) * 10,
+        refund_percent: 0,
+    };
+    assert_eq!(fees.bribe(), U256::exp10(15));
+    assert_eq!(fees.cost(), U256::exp10(15) * 7);
+
+    // Realized bribes are kept next to the bribes they were priced with
+    let accounting = Accounting::new();
+    let reconciliation = accounting.reconcile(Address::random(), 0.into(), &fees, &settlement);
+    assert_eq!(reconciliation.realized_gas, U256::exp10(15) * 7);
+    assert_eq!(reconciliation.gas_deviation_bps, 0);
+    accounting.confirm(0.into(), 1, &BTreeMap::from([(U64::zero(), H256::zero())]));
+    let ledger = accounting.snapshot();
+    assert_eq!(ledger.bribes, U256::exp10(14) * 5);
+    assert_eq!(ledger.estimated_bribes, U256::exp10(15));
+    assert_eq!(accounting.lifetime().bribes, ledger.bribes);
+}
+
+#[test]
+fn test_reorged_settlement_rolled_back() {
+    let accounting = Accounting::new();
+    let wallet = Address::random();
+    let settlement = |block: u64, hash: H256| Settlement {
+        both_legs: true,
+        weth_spent: 100.into(),
+        weth_received: 130.into(),
+        block_number: block.into(),
+        block_hash: hash,
+        ..Default::default()
+    };
+    let (kept, orphaned) = (H256::random(), H256::random());
+    let fees = FeeEstimate::default();
+    for (block, hash) in [(5, kept), (6, orphaned), (7, kept)] {
+        accounting.reconcile(wallet, 30.into(), &fees, &settlement(block, hash));
+    }
+    let pending: Vec<U64> = vec![5.into(), 6.into(), 7.into()];
+    assert_eq!(accounting.pending_blocks(), pending);
+    assert_eq!(accounting.pending_snapshot().gross_profit, U256::from(90));
+
+    // Block 6 was replaced, and block 7 hasn't been fetched yet
+    let canonical = BTreeMap::from([(5.into(), kept), (6.into(), H256::random())]);
+    let outcome = accounting.confirm(8.into(), 3, &canonical);
+    assert_eq!(
+        outcome,
+        Confirmations {
+            realized: 1,
+            reorged: 1
+        }
+    );
+    assert_eq!(accounting.snapshot().gross_profit, U256::from(30));
+    assert_eq!(
+        accounting.wallet_snapshots()[&wallet].gross_profit,
+        U256::from(30)
+    );
+    assert_eq!(accounting.pending_blocks(), vec![7.into()]);
+    assert_eq!(accounting.pending_snapshot().gross_profit, U256::from(30));
+}
+
+#[test]
+fn test_pending_settlements_restored() {
+    let accounting = Accounting::new();
+    let settlement = Settlement {
+        both_legs: true,
+        weth_spent: 100.into(),
+        weth_received: 130.into(),
+        block_number: 5.into(),
+        block_hash: H256::random(),
+        ..Default::default()
+    };
+    accounting.reconcile(
+        Address::random(),
+        30.into(),
+        &FeeEstimate::default(),
+        &settlement,
+    );
+
+    // A restarted session confirms what the previous one left pending
+    let restarted = Accounting::new();
+    restarted.restore_pending(accounting.pending());
+    assert_eq!(restarted.pending_blocks(), vec![5.into()]);
+    let canonical = BTreeMap::from([(5.into(), settlement.block_hash)]);
+    assert_eq!(restarted.confirm(20.into(), 12, &canonical).realized, 1);
+    assert_eq!(restarted.snapshot().gross_profit, U256::from(30));
+    assert!(restarted.pending().is_empty());
+}
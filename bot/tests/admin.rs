@@ -0,0 +1,181 @@
+use std::sync::{Arc, Mutex};
+
+use subway_rs::{admin::*, metrics};
+
+/// A log filter that accepts anything but `invalid`
+#[derive(Default)]
+struct TestFilter(Mutex<String>);
+
+impl LogFilterControl for TestFilter {
+    fn current(&self) -> String {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn set(&self, directives: &str) -> eyre::Result<()> {
+        if directives == "invalid" {
+            eyre::bail!("invalid filter");
+        }
+        *self.0.lock().unwrap() = directives.to_string();
+        Ok(())
+    }
+}
+
+fn request(raw: &str) -> Request {
+    Request::parse(raw.as_bytes()).unwrap()
+}
+
+/// An authorized request to `target`
+fn authorized(method: &str, target: &str, body: &str) -> Request {
+    request(&format!(
+        "{} {} HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n{}",
+        method, target, body
+    ))
+}
+
+#[test]
+fn test_parse_request() {
+    let parsed = request("put /log-filter?secs=1 HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\nContent-Length: 29\r\n\r\ninfo,subway_rs::uniswap=trace");
+    assert_eq!(parsed.method, "PUT");
+    assert_eq!(parsed.path, "/log-filter");
+    assert_eq!(parsed.query.get("secs").map(String::as_str), Some("1"));
+    assert_eq!(
+        parsed.headers.get("host").map(String::as_str),
+        Some("localhost")
+    );
+    assert_eq!(parsed.bearer_token(), Some("secret"));
+    assert_eq!(parsed.body, "info,subway_rs::uniswap=trace");
+
+    assert!(Request::parse(b"GET /metrics HTTP/1.1\r\n").is_err());
+    assert!(Request::parse(b"\r\n\r\n").is_err());
+}
+
+#[test]
+fn test_response_bytes() {
+    let bytes = Response::new(200, "ok").to_bytes();
+    let text = String::from_utf8(bytes).unwrap();
+    assert!(text.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(text.contains("Content-Length: 2\r\n"));
+    assert!(text.ends_with("\r\n\r\nok"));
+}
+
+#[test]
+fn test_admin_metrics() {
+    metrics::increment("admin_test_counter");
+    let admin = Admin::new();
+    let response = admin.handle(&request("GET /metrics HTTP/1.1\r\n\r\n"));
+    assert_eq!(response.status, 200);
+    assert!(response.body.contains("\"admin_test_counter\""));
+
+    assert_eq!(
+        admin
+            .handle(&request("DELETE /metrics HTTP/1.1\r\n\r\n"))
+            .status,
+        405
+    );
+    assert_eq!(
+        admin.handle(&request("GET /nope HTTP/1.1\r\n\r\n")).status,
+        404
+    );
+    let response = admin.handle(&request("GET /health HTTP/1.1\r\n\r\n"));
+    assert_eq!(response.status, 200);
+    assert!(response.body.starts_with('{'));
+    let response = admin.handle(&request("GET /competition HTTP/1.1\r\n\r\n"));
+    assert_eq!(response.status, 200);
+    assert!(response.body.starts_with('{'));
+
+    // Without a log filter there's nothing to reconfigure
+    assert_eq!(
+        admin
+            .handle(&request("GET /log-filter HTTP/1.1\r\n\r\n"))
+            .status,
+        404
+    );
+}
+
+#[tokio::test]
+async fn test_admin_log_filter() {
+    let filter = Arc::new(TestFilter::default());
+    filter.set("info").unwrap();
+    let admin = Admin::new()
+        .with_log_filter(filter.clone())
+        .with_token("secret".into());
+
+    let response = admin.handle(&request("GET /log-filter HTTP/1.1\r\n\r\n"));
+    assert_eq!(response, Response::new(200, "info"));
+
+    let response = admin.handle(&authorized(
+        "PUT",
+        "/log-filter",
+        "info,subway_rs::uniswap=trace",
+    ));
+    assert_eq!(response.status, 200);
+    assert_eq!(filter.current(), "info,subway_rs::uniswap=trace");
+
+    // Invalid filters and durations are refused and leave the filter alone
+    let response = admin.handle(&authorized("PUT", "/log-filter", "invalid"));
+    assert_eq!(response.status, 400);
+    let response = admin.handle(&authorized("PUT", "/log-filter?secs=soon", "debug"));
+    assert_eq!(response.status, 400);
+    assert_eq!(filter.current(), "info,subway_rs::uniswap=trace");
+}
+
+#[tokio::test]
+async fn test_admin_log_filter_restores() {
+    let filter = Arc::new(TestFilter::default());
+    filter.set("info").unwrap();
+    let admin = Admin::new()
+        .with_log_filter(filter.clone())
+        .with_token("secret".into());
+
+    admin.handle(&authorized("PUT", "/log-filter?secs=1", "trace"));
+    assert_eq!(filter.current(), "trace");
+    tokio::time::sleep(std::time::Duration::from_millis(1_500)).await;
+    assert_eq!(filter.current(), "info");
+
+    // A later change cancels the pending restore
+    admin.handle(&authorized("PUT", "/log-filter?secs=1", "trace"));
+    admin.handle(&authorized("PUT", "/log-filter", "debug"));
+    tokio::time::sleep(std::time::Duration::from_millis(1_500)).await;
+    assert_eq!(filter.current(), "debug");
+}
+
+#[test]
+fn test_admin_requires_token() {
+    let filter = Arc::new(TestFilter::default());
+    filter.set("info").unwrap();
+
+    // Without a configured token, mutating routes are always refused
+    let admin = Admin::new().with_log_filter(filter.clone());
+    assert_eq!(
+        admin
+            .handle(&authorized("PUT", "/log-filter", "trace"))
+            .status,
+        401
+    );
+    assert_eq!(admin.handle(&authorized("POST", "/resume", "")).status, 401);
+
+    let admin = admin.with_token("secret".into());
+    let response = admin.handle(&request("PUT /log-filter HTTP/1.1\r\n\r\ntrace"));
+    assert_eq!(response.status, 401);
+    let response = admin.handle(&request(
+        "PUT /log-filter HTTP/1.1\r\nAuthorization: Bearer wrong\r\n\r\ntrace",
+    ));
+    assert_eq!(response.status, 401);
+    assert_eq!(filter.current(), "info");
+    let response = admin.handle(&request("POST /approve?id=1 HTTP/1.1\r\n\r\n"));
+    assert_eq!(response.status, 401);
+    let response = admin.handle(&request("POST /reject?id=1 HTTP/1.1\r\n\r\n"));
+    assert_eq!(response.status, 401);
+    let response = admin.handle(&authorized("POST", "/approve?id=18446744073709551615", ""));
+    assert_eq!(response.status, 404);
+
+    // Reads stay open
+    let response = admin.handle(&request("GET /log-filter HTTP/1.1\r\n\r\n"));
+    assert_eq!(response, Response::new(200, "info"));
+    assert_eq!(
+        admin
+            .handle(&authorized("PUT", "/log-filter", "trace"))
+            .status,
+        200
+    );
+}
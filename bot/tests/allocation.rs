@@ -0,0 +1,73 @@
+use ethers::prelude::*;
+use subway_rs::{allocation::*, conflicts::ClaimHandle};
+
+/// Revenue growing linearly with input at `per_weth` up to `cap`
+fn linear(per_weth: u64, cap: u64) -> impl Fn(U256) -> U256 {
+    move |weth_in: U256| weth_in.min(U256::from(cap)) * per_weth
+}
+
+fn commit(
+    allocator: &mut InventoryAllocator,
+    block: u64,
+    weth_in: u64,
+    revenue: u64,
+) -> ClaimHandle {
+    let handle = ClaimHandle::default();
+    let allocation = Allocation {
+        weth_in: U256::from(weth_in),
+        revenue: U256::from(revenue),
+        displaced: vec![],
+    };
+    allocator.commit(U64::from(block), &allocation, handle.clone());
+    handle
+}
+
+#[test]
+fn test_allocate_shares_remaining_inventory() {
+    let mut allocator = InventoryAllocator::new();
+    let block = U64::from(10);
+
+    let first = allocator
+        .allocate(block, U256::from(100), U256::from(60), linear(1, 60))
+        .unwrap();
+    assert_eq!(first.weth_in, U256::from(60));
+    allocator.commit(block, &first, ClaimHandle::default());
+    assert_eq!(allocator.reserved(block), U256::from(60));
+
+    // The second sandwich only gets what's left rather than the whole balance
+    let second = allocator
+        .allocate(block, U256::from(100), U256::from(60), linear(1, 60))
+        .unwrap();
+    assert_eq!(second.weth_in, U256::from(40));
+    assert!(second.displaced.is_empty());
+
+    // A new block starts with the whole inventory
+    assert_eq!(allocator.reserved(U64::from(11)), U256::zero());
+}
+
+#[test]
+fn test_allocate_displaces_lower_return_bundles() {
+    let mut allocator = InventoryAllocator::new();
+    let block = U64::from(10);
+    let low = commit(&mut allocator, 10, 50, 50);
+    let high = commit(&mut allocator, 10, 50, 500);
+
+    // Worth more with the low return bundle's inventory than it makes
+    let allocation = allocator
+        .allocate(block, U256::from(100), U256::from(50), linear(5, 50))
+        .unwrap();
+    assert_eq!(allocation.weth_in, U256::from(50));
+    assert_eq!(allocation.displaced.len(), 1);
+    assert_eq!(
+        allocator.commit(block, &allocation, ClaimHandle::default()),
+        1
+    );
+    assert!(low.is_superseded());
+    assert!(!high.is_superseded());
+    assert_eq!(allocator.reserved(block), U256::from(100));
+
+    // Not worth displacing anything for
+    assert!(allocator
+        .allocate(block, U256::from(100), U256::from(50), linear(1, 50))
+        .is_none());
+}
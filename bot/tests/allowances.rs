@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+use ethers::{
+    abi::{self, Token},
+    prelude::*,
+};
+
+use subway_rs::allowances::*;
+
+fn approve(
+    from: Address,
+    token: Address,
+    spender: Address,
+    amount: U256,
+    nonce: u64,
+) -> Transaction {
+    let mut input = APPROVE_SELECTOR.to_vec();
+    input.extend(abi::encode(&[Token::Address(spender), Token::Uint(amount)]));
+    Transaction {
+        hash: H256::random(),
+        from,
+        to: Some(token),
+        nonce: nonce.into(),
+        input: input.into(),
+        max_fee_per_gas: Some(U256::from(30_000_000_000u64)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_approve_selector() {
+    assert_eq!(
+        APPROVE_SELECTOR,
+        ethers::utils::id("approve(address,uint256)")
+    );
+}
+
+#[test]
+fn test_pending_approvals() {
+    let (owner, token, spender) = (Address::random(), Address::random(), Address::random());
+    let now = Instant::now();
+    let mut approvals = PendingApprovals::new();
+
+    assert!(!approvals.observe(&Transaction::default(), now));
+    let first = approve(owner, token, spender, U256::one(), 0);
+    assert!(approvals.observe(&first, now));
+    let pending = approvals.pending(owner, token, spender).unwrap();
+    assert_eq!(pending.amount, U256::one());
+    assert_eq!(pending.spender, spender);
+
+    // A replacement overwrites the earlier approval
+    let bumped = approve(owner, token, spender, U256::MAX, 0);
+    assert!(approvals.observe(&bumped, now));
+    assert_eq!(approvals.len(), 1);
+    assert_eq!(
+        approvals.pending(owner, token, spender).unwrap().tx.hash,
+        bumped.hash
+    );
+    assert!(approvals.pending(owner, token, Address::random()).is_none());
+
+    // Old approvals are forgotten on the next observation
+    let later = now + Duration::from_secs(PENDING_APPROVAL_TTL_SECS);
+    let other = approve(Address::random(), token, spender, U256::one(), 0);
+    assert!(approvals.observe(&other, later));
+    assert!(approvals.pending(owner, token, spender).is_none());
+    assert_eq!(approvals.len(), 1);
+}
+
+#[test]
+fn test_check_approval() {
+    let (owner, token, spender) = (Address::random(), Address::random(), Address::random());
+    let base_fee = U256::from(20_000_000_000u64);
+    let amount = U256::exp10(18);
+    let mut approvals = PendingApprovals::new();
+    let victim = Transaction {
+        from: owner,
+        nonce: 5.into(),
+        ..Default::default()
+    };
+    let check = |approvals: &PendingApprovals, base_fee: U256| {
+        check_approval(approvals, &victim, token, spender, amount, base_fee)
+    };
+
+    assert_eq!(check(&approvals, base_fee), ApprovalDependency::None);
+
+    let approval = approve(owner, token, spender, U256::MAX, 4);
+    approvals.observe(&approval, Instant::now());
+    assert_eq!(
+        check(&approvals, base_fee),
+        ApprovalDependency::Include(approval.clone())
+    );
+    assert!(matches!(
+        check(&approvals, U256::from(40_000_000_000u64)),
+        ApprovalDependency::Skip(_)
+    ));
+
+    // Too small, or not right before the victim
+    approvals.observe(
+        &approve(owner, token, spender, U256::one(), 4),
+        Instant::now(),
+    );
+    assert!(matches!(
+        check(&approvals, base_fee),
+        ApprovalDependency::Skip(_)
+    ));
+    approvals.observe(
+        &approve(owner, token, spender, U256::MAX, 3),
+        Instant::now(),
+    );
+    assert!(matches!(
+        check(&approvals, base_fee),
+        ApprovalDependency::Skip(_)
+    ));
+
+    // An approval after the victim doesn't gate it
+    approvals.observe(
+        &approve(owner, token, spender, U256::one(), 6),
+        Instant::now(),
+    );
+    assert_eq!(check(&approvals, base_fee), ApprovalDependency::None);
+}
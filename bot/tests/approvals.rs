@@ -0,0 +1,60 @@
+use ethers::prelude::*;
+use std::str::FromStr;
+
+use subway_rs::{approvals::*, executor::*};
+
+fn weth() -> Address {
+    Address::from_str("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap()
+}
+
+#[test]
+fn test_approvals_from_str() {
+    let approvals = Approvals::from_str(&format!(
+        "{:?}:{:?}, {:?}:{}",
+        weth(),
+        Address::zero(),
+        weth(),
+        AAVE_V3_POOL
+    ))
+    .unwrap();
+    assert_eq!(approvals.0.len(), 2);
+    assert_eq!(approvals.0[0].spender, Address::zero());
+    assert!(Approvals::from_str("0x01").is_err());
+    assert!(Approvals::from_str("").unwrap().0.is_empty());
+}
+
+#[test]
+fn test_required_approvals() {
+    assert!(required_approvals(&Approvals::default()).is_empty());
+
+    // Duplicates are collapsed
+    let aave = Approval {
+        token: weth(),
+        spender: Address::from_str(AAVE_V3_POOL).unwrap(),
+    };
+    assert_eq!(required_approvals(&Approvals(vec![aave, aave])), vec![aave]);
+}
+
+#[test]
+fn test_encode_approvals() {
+    let approval = Approval {
+        token: weth(),
+        spender: Address::from_str(AAVE_V3_POOL).unwrap(),
+    };
+    assert!(ExecutorKind::Subway
+        .build(Address::random())
+        .encode_approvals(&[approval])
+        .is_err());
+
+    let calldata = ExecutorKind::Multicall
+        .build(Address::random())
+        .encode_approvals(&[approval])
+        .unwrap();
+    assert_eq!(
+        calldata[..4],
+        ethers::utils::id(MulticallExecutor::MULTICALL_SIGNATURE)
+    );
+    // The approve selector is embedded in the batched call
+    let approve = ethers::utils::id("approve(address,uint256)");
+    assert!(calldata.windows(4).any(|w| w == approve));
+}
@@ -0,0 +1,69 @@
+use ethers::prelude::*;
+use subway_rs::audit::*;
+
+fn record(accepted: bool) -> SubmissionRecord {
+    let response: Result<&str, &str> = if accepted {
+        Ok("0xbundle")
+    } else {
+        Err("rate limited")
+    };
+    SubmissionRecord::new(
+        "flashbots",
+        U64::from(100),
+        &[Bytes::from(vec![1, 2, 3]), Bytes::from(vec![4, 5, 6])],
+        &response,
+    )
+}
+
+fn temp_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("audit-{:x}.jsonl", H64::random()))
+}
+
+#[test]
+fn test_submission_record() {
+    let accepted = record(true);
+    assert!(accepted.accepted);
+    assert_eq!(accepted.response, "\"0xbundle\"");
+    assert_eq!(accepted.transactions.len(), 2);
+    assert!(accepted.timestamp > 0);
+
+    let refused = record(false);
+    assert!(!refused.accepted);
+    assert_eq!(refused.response, "\"rate limited\"");
+}
+
+#[tokio::test]
+async fn test_audit_log_roundtrip() {
+    let path = temp_path();
+    let log = AuditLog::open(&path, None).unwrap();
+    let first = record(true);
+    let second = record(false);
+    log.append(first.clone()).await.unwrap();
+    log.append(second.clone()).await.unwrap();
+
+    let loaded = AuditLog::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].record, first);
+    assert_eq!(loaded[1].record, second);
+    assert!(loaded.iter().all(|entry| entry.signature.is_none()));
+}
+
+#[tokio::test]
+async fn test_signed_audit_log() {
+    let path = temp_path();
+    let signer = LocalWallet::new(&mut rand::thread_rng());
+    let log = AuditLog::open(&path, Some(signer.clone())).unwrap();
+    log.append(record(true)).await.unwrap();
+
+    let mut loaded = AuditLog::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let entry = loaded.pop().unwrap();
+    assert!(entry.verify(signer.address()));
+    assert!(!entry.verify(Address::random()));
+
+    // Tampered records no longer verify
+    let mut tampered = entry;
+    tampered.record.accepted = false;
+    assert!(!tampered.verify(signer.address()));
+}
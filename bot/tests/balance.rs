@@ -0,0 +1,26 @@
+use ethers::prelude::*;
+use subway_rs::balance::*;
+
+#[test]
+fn test_pause_transitions() {
+    let reserve = U256::from(100);
+    assert_eq!(
+        transition(99.into(), reserve, false),
+        Some(PauseTransition::Paused)
+    );
+    assert_eq!(transition(99.into(), reserve, true), None);
+    assert_eq!(
+        transition(100.into(), reserve, true),
+        Some(PauseTransition::Resumed)
+    );
+    assert_eq!(transition(100.into(), reserve, false), None);
+}
+
+#[test]
+fn test_pause_switch_is_shared() {
+    let switch = PauseSwitch::default();
+    let watcher = switch.clone();
+    assert!(!switch.is_paused());
+    watcher.set(true);
+    assert!(switch.is_paused());
+}
@@ -0,0 +1,51 @@
+use subway_rs::bloxroute::*;
+
+#[test]
+fn test_subscribe_request() {
+    let request: serde_json::Value = serde_json::from_str(&subscribe_request()).unwrap();
+    assert_eq!(request["method"], "subscribe");
+    assert_eq!(request["params"][0], "newTxs");
+}
+
+#[test]
+fn test_parse_notification() {
+    // The subscription acknowledgement carries no transaction
+    let ack = r#"{"jsonrpc":"2.0","id":1,"result":"a1b2c3"}"#;
+    assert!(parse_notification(ack).unwrap().is_none());
+
+    let notification = r#"{
+        "jsonrpc": "2.0",
+        "method": "subscribe",
+        "params": {
+            "subscription": "a1b2c3",
+            "result": {
+                "txHash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+                "txContents": {
+                    "hash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+                    "nonce": "0x1",
+                    "from": "0x2222222222222222222222222222222222222222",
+                    "to": "0x7a250d5630b4cf539739df2c5dacb4c659f2488d",
+                    "value": "0xde0b6b3a7640000",
+                    "gas": "0x30d40",
+                    "gasPrice": "0x3b9aca00",
+                    "input": "0x7ff36ab5",
+                    "v": "0x25",
+                    "r": "0x1",
+                    "s": "0x1"
+                }
+            }
+        }
+    }"#;
+    let tx = parse_notification(notification).unwrap().unwrap();
+    assert_eq!(tx.nonce, 1.into());
+    assert_eq!(
+        tx.to,
+        Some(
+            "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+                .parse()
+                .unwrap()
+        )
+    );
+
+    assert!(parse_notification("not json").is_err());
+}
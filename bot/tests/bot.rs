@@ -0,0 +1,65 @@
+#![cfg(feature = "relay")]
+
+use ethers::prelude::*;
+
+use subway_rs::{
+    bot::*,
+    config::Config,
+    jit::JitConfig,
+    numeric::Optimizer,
+    risk::{ExposureLimits, ReorgGuard},
+};
+
+#[test]
+fn test_builder_defaults_to_config() {
+    let builder = BotBuilder::new();
+    assert_eq!(builder.config(), &Config::default());
+    assert_eq!(
+        RiskLimits::from_config(builder.config()).max_bundle_gas_bps,
+        Config::default().max_bundle_gas_bps
+    );
+}
+
+#[test]
+fn test_builder_overrides_strategy_and_risk() {
+    let config = Config::default();
+    let limits = RiskLimits {
+        reorg_guard: ReorgGuard {
+            max_revenue: Some(U256::exp10(18)),
+            ..Default::default()
+        },
+        exposure: ExposureLimits {
+            max_token_exposure: Some(U256::exp10(17)),
+            window_secs: 60,
+        },
+        downsize_to_inventory: false,
+        ..RiskLimits::from_config(&config)
+    };
+    let builder = BotBuilder::new()
+        .with_config(config)
+        .with_strategy(Strategy::Jit(JitConfig {
+            enabled: false,
+            ..Default::default()
+        }))
+        .with_strategy(Strategy::Sandwich {
+            optimizer: Optimizer::ReturnOnCapital,
+            min_roc_revenue: 1_000.into(),
+            profit_rules: Default::default(),
+        })
+        .with_risk(limits);
+
+    // Adding a strategy enables it
+    let config = builder.config();
+    assert!(config.jit.enabled);
+    assert!(!config.liquidations.enabled);
+    assert_eq!(config.optimizer, Optimizer::ReturnOnCapital);
+    assert_eq!(config.min_roc_revenue, U256::from(1_000));
+    assert_eq!(RiskLimits::from_config(config), limits);
+}
+
+#[test]
+fn test_flashbots_relays() {
+    let relays = Relays::flashbots().unwrap();
+    assert_eq!(relays.flashbots.as_str(), "https://relay.flashbots.net/");
+    assert!(relays.others.is_empty());
+}
@@ -0,0 +1,64 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use subway_rs::clock::*;
+
+#[test]
+fn test_ntp_timestamp_millis() {
+    // 2_208_988_800 seconds after 1900 is the unix epoch, and half the fraction is 500ms
+    let mut bytes = [0u8; 8];
+    bytes[..4].copy_from_slice(&2_208_988_800u32.to_be_bytes());
+    bytes[4..].copy_from_slice(&0x8000_0000u32.to_be_bytes());
+    assert_eq!(ntp_timestamp_millis(bytes), 500);
+
+    bytes[..4].copy_from_slice(&(2_208_988_800u32 + 1_700_000_000).to_be_bytes());
+    bytes[4..].copy_from_slice(&[0; 4]);
+    assert_eq!(ntp_timestamp_millis(bytes), 1_700_000_000_000);
+}
+
+#[test]
+fn test_ntp_offset() {
+    // The server is 1s ahead, with 20ms each way on the wire
+    assert_eq!(ntp_offset_ms(0, 1_020, 1_030, 50), 1_000);
+    // The server is 300ms behind
+    assert_eq!(ntp_offset_ms(1_000, 710, 720, 1_040), -300);
+    // The round trip itself doesn't count as drift
+    assert_eq!(ntp_offset_ms(0, 100, 100, 200), 0);
+}
+
+#[test]
+fn test_block_drift() {
+    let mut lags = BlockLags::new(3);
+    assert_eq!(lags.drift_ms(), None);
+
+    // Blocks arriving a second or two after their timestamps
+    lags.record(100, 101_200);
+    lags.record(112, 113_900);
+    assert_eq!(lags.drift_ms(), Some(0));
+
+    // A block arriving before its timestamp puts the clock behind
+    lags.record(124, 123_400);
+    assert_eq!(lags.drift_ms(), Some(-600));
+
+    // Once it's out of the window, blocks arriving late put the clock ahead
+    lags.record(136, 146_000);
+    lags.record(148, 158_000);
+    lags.record(160, 170_000);
+    assert_eq!(lags.drift_ms(), Some(10_000 - BLOCK_ARRIVAL_ALLOWANCE_MS));
+}
+
+#[test]
+fn test_corrected_now() {
+    let unix_millis = |time: SystemTime| time.duration_since(UNIX_EPOCH).unwrap().as_millis();
+    set_offset_ms(60_000);
+    let ahead = unix_millis(now()) - unix_millis(SystemTime::now());
+    assert!((59_000..=60_000).contains(&ahead));
+    let system_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    assert!(unix_secs() >= system_secs + 59);
+
+    set_offset_ms(-60_000);
+    assert!(now() + Duration::from_secs(59) < SystemTime::now());
+    set_offset_ms(0);
+}
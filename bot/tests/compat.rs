@@ -0,0 +1,24 @@
+#![cfg(feature = "alloy")]
+
+use ethers::prelude::*;
+
+use subway_rs::compat::*;
+
+#[test]
+fn test_round_trip_conversions() {
+    let address = Address::random();
+    assert_eq!(address.to_alloy().to_ethers(), address);
+
+    let hash = H256::random();
+    assert_eq!(hash.to_alloy().to_ethers(), hash);
+
+    let value = U256::MAX - U256::from(12345);
+    assert_eq!(value.to_alloy().to_ethers(), value);
+    assert_eq!(
+        U256::from(1_000_000u64).to_alloy(),
+        alloy_primitives::U256::from(1_000_000u64)
+    );
+
+    let bytes = Bytes::from(vec![1u8, 2, 3]);
+    assert_eq!(bytes.to_alloy().to_ethers(), bytes);
+}
@@ -0,0 +1,25 @@
+use subway_rs::{config::*, executor::*};
+
+#[test]
+fn test_config_from_env() {
+    std::env::remove_var("CHAIN_ID");
+    std::env::set_var("SANDWICH_EXECUTOR", "multicall");
+    std::env::set_var("EXECUTION_MODE", "value");
+    let config = Config::from_env().unwrap();
+    assert_eq!(config.chain_id, 1);
+    assert_eq!(config.executor, ExecutorKind::Multicall);
+    assert_eq!(config.execution_mode, ExecutionMode::Value);
+
+    // Invalid values are reported instead of silently defaulting
+    std::env::set_var("CHAIN_ID", "mainnet");
+    assert!(Config::from_env().is_err());
+    std::env::remove_var("CHAIN_ID");
+
+    // Simulate-only mode refuses strategies that send on their own
+    std::env::set_var("SIMULATE_ONLY", "true");
+    assert!(Config::from_env().unwrap().simulate_only);
+    std::env::set_var("JIT_LIQUIDITY", "true");
+    assert!(Config::from_env().is_err());
+    std::env::remove_var("JIT_LIQUIDITY");
+    std::env::remove_var("SIMULATE_ONLY");
+}
@@ -0,0 +1,97 @@
+use ethers::prelude::*;
+use subway_rs::conflicts::*;
+
+fn claim(block: u64, pair: Address, wallet: Address, nonce: u64, value: u64) -> BundleClaim {
+    BundleClaim {
+        target_block: U64::from(block),
+        pair,
+        wallet,
+        first_nonce: U256::from(nonce),
+        last_nonce: U256::from(nonce + 1),
+        value: U256::from(value),
+    }
+}
+
+#[test]
+fn test_conflicts_with() {
+    let (pair, other_pair) = (Address::random(), Address::random());
+    let (wallet, other_wallet) = (Address::random(), Address::random());
+    let base = claim(10, pair, wallet, 5, 100);
+
+    // Same pair, same block
+    assert!(base.conflicts_with(&claim(10, pair, other_wallet, 0, 1)));
+    // Overlapping nonces from the same wallet
+    assert!(base.conflicts_with(&claim(10, other_pair, wallet, 6, 1)));
+    // Adjacent nonces don't overlap
+    assert!(!base.conflicts_with(&claim(10, other_pair, wallet, 7, 1)));
+    // Different wallets never share nonces
+    assert!(!base.conflicts_with(&claim(10, other_pair, other_wallet, 5, 1)));
+    // Different blocks never conflict
+    assert!(!base.conflicts_with(&claim(11, pair, wallet, 5, 1)));
+}
+
+#[test]
+fn test_tracker_keeps_higher_value() {
+    let mut tracker = ConflictTracker::new();
+    let (pair, wallet) = (Address::random(), Address::random());
+
+    let first = match tracker.claim(claim(10, pair, wallet, 0, 100)) {
+        ClaimOutcome::Accepted { handle, superseded } => {
+            assert_eq!(superseded, 0);
+            handle
+        }
+        other => panic!("expected accepted, got {:?}", other),
+    };
+
+    // A lower or equal value conflict is rejected
+    match tracker.claim(claim(10, pair, Address::random(), 0, 100)) {
+        ClaimOutcome::Rejected { existing_value } => assert_eq!(existing_value, U256::from(100)),
+        other => panic!("expected rejected, got {:?}", other),
+    }
+    assert!(!first.is_superseded());
+
+    // A higher value conflict supersedes it
+    match tracker.claim(claim(10, pair, Address::random(), 0, 150)) {
+        ClaimOutcome::Accepted { superseded, .. } => assert_eq!(superseded, 1),
+        other => panic!("expected accepted, got {:?}", other),
+    }
+    assert!(first.is_superseded());
+    assert_eq!(tracker.len(), 1);
+}
+
+#[test]
+fn test_tracker_forgets_past_blocks() {
+    let mut tracker = ConflictTracker::new();
+    let (pair, wallet) = (Address::random(), Address::random());
+    assert!(matches!(
+        tracker.claim(claim(10, pair, wallet, 0, 100)),
+        ClaimOutcome::Accepted { .. }
+    ));
+    assert!(matches!(
+        tracker.claim(claim(11, pair, wallet, 2, 1)),
+        ClaimOutcome::Accepted { superseded: 0, .. }
+    ));
+    assert_eq!(tracker.len(), 1);
+}
+
+#[test]
+fn test_tracker_releases_nonces() {
+    let mut tracker = ConflictTracker::new();
+    let wallet = Address::random();
+    let handle = match tracker.claim(claim(10, Address::random(), wallet, 5, 100)) {
+        ClaimOutcome::Accepted { handle, .. } => handle,
+        other => panic!("expected accepted, got {:?}", other),
+    };
+    assert!(tracker.is_pending(wallet, U64::from(10)));
+    assert!(!tracker.is_pending(wallet, U64::from(11)));
+    assert!(!tracker.is_pending(Address::random(), U64::from(10)));
+
+    // A nonce the bundle doesn't use leaves it alone
+    assert_eq!(tracker.release_nonce(wallet, U256::from(7)), 0);
+    assert_eq!(tracker.release_nonce(Address::random(), U256::from(5)), 0);
+
+    // Taking one of its nonces supersedes it
+    assert_eq!(tracker.release_nonce(wallet, U256::from(6)), 1);
+    assert!(handle.is_superseded());
+    assert!(!tracker.is_pending(wallet, U64::from(10)));
+}
@@ -0,0 +1,168 @@
+use ethers::prelude::*;
+
+use subway_rs::{admin::*, control::*, executor::*, transactions::*};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+fn factory_of(kind: ExecutorKind) -> TxFactory {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    TxFactory::new(
+        kind.build(Address::random()),
+        ExecutionMode::Inventory,
+        wallet,
+        1,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_control_command_parsing() {
+    let owner = Address::random();
+    assert_eq!(
+        ControlCommand::from_args(&args(&["pause"])).unwrap(),
+        Some(ControlCommand::Pause)
+    );
+    assert_eq!(
+        ControlCommand::from_args(&args(&["unpause"])).unwrap(),
+        Some(ControlCommand::Unpause)
+    );
+    assert_eq!(
+        ControlCommand::from_args(&args(&["executor-status"])).unwrap(),
+        Some(ControlCommand::Status)
+    );
+    assert_eq!(
+        ControlCommand::from_args(&args(&["transfer-ownership", &format!("{:?}", owner)])).unwrap(),
+        Some(ControlCommand::TransferOwnership(owner))
+    );
+    assert!(ControlCommand::from_args(&args(&["transfer-ownership"])).is_err());
+    assert!(ControlCommand::from_args(&args(&["transfer-ownership", "nope"])).is_err());
+    assert_eq!(
+        ControlCommand::from_args(&args(&["approve"])).unwrap(),
+        None
+    );
+    assert_eq!(ControlCommand::from_args(&[]).unwrap(), None);
+}
+
+#[test]
+fn test_control_calldata() {
+    let factory = factory_of(ExecutorKind::Subway);
+    let pause = ControlCommand::Pause.calldata(&factory).unwrap().unwrap();
+    assert_eq!(pause.to_vec(), ethers::utils::id("pause()").to_vec());
+    let unpause = ControlCommand::Unpause.calldata(&factory).unwrap().unwrap();
+    assert_eq!(unpause.to_vec(), ethers::utils::id("unpause()").to_vec());
+    assert_eq!(ControlCommand::Status.calldata(&factory).unwrap(), None);
+
+    let owner = Address::random();
+    let transfer = ControlCommand::TransferOwnership(owner)
+        .calldata(&factory)
+        .unwrap()
+        .unwrap();
+    assert_eq!(transfer.len(), 36);
+    assert_eq!(
+        &transfer[..4],
+        &ethers::utils::id("transferOwnership(address)")
+    );
+    assert_eq!(&transfer[16..], owner.as_bytes());
+    assert!(ControlCommand::TransferOwnership(Address::zero())
+        .calldata(&factory)
+        .is_err());
+
+    // Only the subway executor has owner controls
+    let multicall = factory_of(ExecutorKind::Multicall);
+    assert!(!multicall.executor.supports_pause());
+    assert!(ControlCommand::Pause.calldata(&multicall).is_err());
+}
+
+#[test]
+fn test_decode_status() {
+    let owner = Address::random();
+    let mut owner_word = [0u8; 32];
+    owner_word[12..].copy_from_slice(owner.as_bytes());
+    let mut paused_word = [0u8; 32];
+    paused_word[31] = 1;
+    assert_eq!(
+        decode_status(&owner_word, &paused_word).unwrap(),
+        ExecutorStatus {
+            owner,
+            paused: true
+        }
+    );
+    assert!(!decode_status(&owner_word, &[0u8; 32]).unwrap().paused);
+    assert!(decode_status(&owner_word, &[]).is_err());
+}
+
+#[test]
+fn test_decode_payload_version() {
+    let mut version = [0u8; 32];
+    version[31] = 3;
+    assert_eq!(decode_payload_version(&version).unwrap(), 3);
+    version[0] = 1;
+    assert!(decode_payload_version(&version).is_err());
+    assert!(decode_payload_version(&[]).is_err());
+}
+
+#[test]
+fn test_is_revert() {
+    assert!(is_revert(
+        "(code: 3, message: execution reverted, data: Some(String(\"0x\")))"
+    ));
+    assert!(is_revert("Reverted"));
+    assert!(!is_revert(
+        "(code: -32000, message: header not found, data: None)"
+    ));
+    assert!(!is_revert("Connection reset by peer"));
+}
+
+#[test]
+fn test_dispatches() {
+    let selector = ethers::utils::id("burnLiquidity(address,int24,int24,uint128)");
+    // `PUSH4 selector EQ` in a dispatcher
+    let mut code = vec![0x60, 0xe0, 0x1c, 0x63];
+    code.extend(selector);
+    code.push(0x14);
+    assert!(dispatches(&code, selector));
+    assert!(!dispatches(&code, ethers::utils::id("pause()")));
+    // The selector bytes pushed as anything but a `PUSH4` don't count
+    code[3] = 0x64;
+    assert!(!dispatches(&code, selector));
+    assert!(!dispatches(&[], selector));
+}
+
+#[tokio::test]
+async fn test_kill_switch() {
+    let switch = KillSwitch::default();
+    let mut halts = switch.subscribe();
+    assert!(!switch.is_halted());
+    assert!(switch.halt());
+    assert!(switch.is_halted());
+    assert!(halts.has_changed().unwrap());
+    assert!(*halts.borrow_and_update());
+
+    // Halting twice doesn't wake subscribers
+    assert!(!switch.halt());
+    assert!(!halts.has_changed().unwrap());
+
+    assert!(switch.resume());
+    assert!(!switch.resume());
+    assert!(!switch.is_halted());
+    assert!(!*halts.borrow_and_update());
+}
+
+#[test]
+fn test_admin_kill_switch() {
+    let admin = Admin::new();
+    let handle = |raw: &str| admin.handle(&Request::parse(raw.as_bytes()).unwrap());
+
+    assert_eq!(handle("GET /halt HTTP/1.1\r\n\r\n").status, 405);
+    let response = handle("POST /halt HTTP/1.1\r\n\r\n");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, "Halted");
+    assert!(kill_switch().is_halted());
+    assert_eq!(handle("POST /halt HTTP/1.1\r\n\r\n").body, "Already halted");
+
+    assert_eq!(handle("POST /resume HTTP/1.1\r\n\r\n").body, "Resumed");
+    assert!(!kill_switch().is_halted());
+    assert_eq!(handle("POST /resume HTTP/1.1\r\n\r\n").body, "Not halted");
+}
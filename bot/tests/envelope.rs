@@ -0,0 +1,159 @@
+use ethers::{prelude::*, utils::keccak256};
+use rlp::RlpStream;
+
+use subway_rs::envelope::*;
+
+fn wallet() -> LocalWallet {
+    "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+        .parse()
+        .unwrap()
+}
+
+fn router() -> Address {
+    "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+        .parse()
+        .unwrap()
+}
+
+/// Signs a type 3 or type 4 transaction to the router, returning its raw envelope
+fn signed_envelope(tx_type: u64) -> Bytes {
+    let fields = if tx_type == BLOB_TX_TYPE { 11 } else { 10 };
+    let mut unsigned = RlpStream::new_list(fields);
+    unsigned.append(&U256::one());
+    unsigned.append(&U256::from(7));
+    unsigned.append(&U256::exp10(9));
+    unsigned.append(&(U256::exp10(9) * 30));
+    unsigned.append(&U256::from(200_000));
+    unsigned.append(&router());
+    unsigned.append(&U256::exp10(17));
+    unsigned.append(&vec![0x7f, 0xf3, 0x6a, 0xb5]);
+    unsigned.append(&AccessList::default());
+    if tx_type == BLOB_TX_TYPE {
+        unsigned.append(&U256::exp10(9));
+        unsigned.append_list::<H256, H256>(&[H256::repeat_byte(1)]);
+    } else {
+        unsigned.begin_list(1);
+        unsigned.begin_list(6);
+        unsigned.append(&U256::one());
+        unsigned.append(&Address::repeat_byte(2));
+        unsigned.append(&U64::from(8));
+        unsigned.append(&U64::one());
+        unsigned.append(&U256::from(3));
+        unsigned.append(&U256::from(4));
+    }
+    let unsigned = unsigned.out();
+
+    let mut sighash = vec![tx_type as u8];
+    sighash.extend_from_slice(&unsigned);
+    let signature = wallet().sign_hash(H256::from(keccak256(&sighash)));
+
+    let mut signed = RlpStream::new_list(fields + 3);
+    let items = rlp::Rlp::new(&unsigned);
+    for item in items.iter() {
+        signed.append_raw(item.as_raw(), 1);
+    }
+    signed.append(&U64::from(signature.v - 27));
+    signed.append(&signature.r);
+    signed.append(&signature.s);
+    let mut raw = vec![tx_type as u8];
+    raw.extend_from_slice(&signed.out());
+    Bytes::from(raw)
+}
+
+#[test]
+fn test_decode_blob_transaction() {
+    let raw = signed_envelope(BLOB_TX_TYPE);
+    let tx = decode_raw(&raw).unwrap();
+    assert_eq!(TxType::of(&tx), TxType::Blob);
+    assert_eq!(tx.hash, H256::from(keccak256(&raw)));
+    assert_eq!(tx.from, wallet().address());
+    assert_eq!(tx.to, Some(router()));
+    assert_eq!(tx.value, U256::exp10(17));
+    assert_eq!(tx.input.as_ref(), &[0x7f, 0xf3, 0x6a, 0xb5]);
+    assert_eq!(tx.nonce, U256::from(7));
+    assert_eq!(
+        blob_versioned_hashes(&tx).unwrap(),
+        vec![H256::repeat_byte(1)]
+    );
+    assert_eq!(max_fee_per_blob_gas(&tx).unwrap(), U256::exp10(9));
+    assert_eq!(encode_raw(&tx).unwrap(), raw);
+}
+
+#[test]
+fn test_decode_set_code_transaction() {
+    let raw = signed_envelope(SET_CODE_TX_TYPE);
+    let tx = decode_raw(&raw).unwrap();
+    assert_eq!(TxType::of(&tx), TxType::SetCode);
+    assert_eq!(tx.from, wallet().address());
+    assert_eq!(tx.to, Some(router()));
+    assert_eq!(
+        authorization_list(&tx).unwrap(),
+        vec![Authorization {
+            chain_id: U256::one(),
+            address: Address::repeat_byte(2),
+            nonce: U64::from(8),
+            y_parity: U64::one(),
+            r: U256::from(3),
+            s: U256::from(4),
+        }]
+    );
+    assert_eq!(encode_raw(&tx).unwrap(), raw);
+}
+
+#[test]
+fn test_decode_blob_network_form() {
+    // The sidecar is dropped, leaving the hash of the transaction itself
+    let raw = signed_envelope(BLOB_TX_TYPE);
+    let mut wrapper = RlpStream::new_list(4);
+    wrapper.append_raw(&raw[1..], 1);
+    for _ in 0..3 {
+        wrapper.append_list::<Vec<u8>, Vec<u8>>(&[vec![0; 48]]);
+    }
+    let mut network = vec![BLOB_TX_TYPE as u8];
+    network.extend_from_slice(&wrapper.out());
+
+    let tx = decode_raw(&network).unwrap();
+    assert_eq!(tx.hash, H256::from(keccak256(&raw)));
+    assert_eq!(encode_raw(&tx).unwrap(), raw);
+}
+
+#[test]
+fn test_json_round_trip() {
+    // Pending transactions arrive as json, with the fields ethers doesn't know kept aside
+    for tx_type in [BLOB_TX_TYPE, SET_CODE_TX_TYPE] {
+        let raw = signed_envelope(tx_type);
+        let mut json = serde_json::to_value(decode_raw(&raw).unwrap()).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        let v = fields.remove("v").unwrap();
+        fields.insert("yParity".to_string(), v);
+
+        let tx = from_json(json).unwrap();
+        assert_eq!(tx.to, Some(router()));
+        assert_eq!(encode_raw(&tx).unwrap(), raw);
+    }
+}
+
+#[test]
+fn test_bundleable() {
+    let blob = decode_raw(&signed_envelope(BLOB_TX_TYPE)).unwrap();
+    assert!(!bundleable(&blob));
+    let set_code = decode_raw(&signed_envelope(SET_CODE_TX_TYPE)).unwrap();
+    assert!(bundleable(&set_code));
+    assert!(bundleable(&Transaction::default()));
+}
+
+#[test]
+fn test_encode_rejects_mismatched_hash() {
+    let mut tx = decode_raw(&signed_envelope(SET_CODE_TX_TYPE)).unwrap();
+    tx.value += U256::one();
+    assert!(encode_raw(&tx).is_err());
+
+    tx.transaction_type = Some(U64::from(0x7e));
+    assert!(encode_raw(&tx).is_err());
+}
+
+#[test]
+fn test_decode_rejects_other_types() {
+    assert!(decode_raw(&[]).is_err());
+    assert!(decode_raw(&[0x02, 0xc0]).is_err());
+}
@@ -0,0 +1,75 @@
+use ethers::prelude::*;
+
+use subway_rs::ev::*;
+
+fn eth(amount: u64) -> U256 {
+    U256::exp10(18) * amount
+}
+
+#[test]
+fn test_buckets() {
+    assert_eq!(bribe_bucket(0), 0);
+    assert_eq!(bribe_bucket(1_337), 1);
+    assert_eq!(bribe_bucket(10_000), 9);
+    assert_eq!(size_bucket(U256::zero()), 0);
+    assert_eq!(size_bucket(U256::from(SIZE_BUCKET_UNIT)), 1);
+    assert_eq!(size_bucket(eth(1)), 7);
+    assert_eq!(size_bucket(eth(1_000_000)), MAX_SIZE_BUCKET);
+}
+
+#[test]
+fn test_prior_before_outcomes() {
+    let model = InclusionModel::default();
+    assert_eq!(
+        model.probability_bps(1_337, eth(1)),
+        DEFAULT_INCLUSION_PRIOR_BPS
+    );
+    assert_eq!(
+        model.expected_value(1_337, eth(1), eth(2), eth(1)),
+        eth(1) / 2
+    );
+
+    // Costs above the revenue leave nothing
+    assert_eq!(
+        model.expected_value(1_337, eth(1), eth(1), eth(2)),
+        U256::zero()
+    );
+}
+
+#[test]
+fn test_inclusion_rates_by_bucket() {
+    let model = InclusionModel::new(5_000);
+    for _ in 0..16 {
+        model.record(9_000, eth(1), true);
+        model.record(1_000, eth(1), false);
+    }
+
+    // Higher bribes land more often
+    let high = model.probability_bps(9_000, eth(1));
+    let low = model.probability_bps(1_000, eth(1));
+    assert!(high > 8_000, "{}", high);
+    assert!(low < 2_000, "{}", low);
+    assert_eq!(model.size_probability_bps(eth(1)), 5_000);
+
+    // An untried bribe for the size leans on the size's rate
+    assert_eq!(model.probability_bps(5_000, eth(1)), 5_000);
+
+    // Other sizes are untouched
+    assert_eq!(model.size_probability_bps(eth(100)), 5_000);
+    assert_eq!(model.snapshot().len(), 2);
+}
+
+#[test]
+fn test_expected_value_ranks_over_raw_profit() {
+    let model = InclusionModel::default();
+    for _ in 0..20 {
+        model.record(1_000, eth(1), false);
+        model.record(1_000, eth(50), true);
+    }
+
+    // A smaller estimate that usually lands is worth more than a larger one that rarely does
+    let rarely = model.expected_value(1_000, eth(1), eth(3), U256::zero());
+    let usually = model.expected_value(1_000, eth(50), eth(2), U256::zero());
+    assert!(usually > rarely);
+    assert!(model.weigh_score(eth(50), eth(1)) > model.weigh_score(eth(1), eth(1)));
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use subway_rs::{abi::SwapExactETHForTokensCall, evaluate::*, uniswap, utils};
+
+fn ether(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(18)
+}
+
+fn victim(
+    router: Address,
+    path: Vec<Address>,
+    amount_out_min: U256,
+    deadline: U256,
+) -> Transaction {
+    let call = SwapExactETHForTokensCall {
+        amount_out_min,
+        path,
+        to: Address::random(),
+        deadline,
+    };
+    Transaction {
+        hash: H256::random(),
+        to: Some(router),
+        value: ether(10),
+        input: ethers::abi::AbiEncode::encode(call).into(),
+        ..Default::default()
+    }
+}
+
+/// A WETH pair with 1,000 ether and 1,000,000 tokens
+fn state(weth: Address, token: Address) -> (Address, HashMap<Address, (U256, U256)>) {
+    let pair = uniswap::calculate_uniswap_v2_pair_address(&weth, &token).unwrap();
+    let reserves = if weth < token {
+        (ether(1_000), ether(1_000_000))
+    } else {
+        (ether(1_000_000), ether(1_000))
+    };
+    (pair, HashMap::from([(pair, reserves)]))
+}
+
+#[tokio::test]
+async fn test_evaluate_sandwich() {
+    let router = uniswap::get_univ2_router_address();
+    let weth = utils::get_weth_address();
+    let token = Address::random();
+    let (pair, state) = state(weth, token);
+
+    let tx = victim(router, vec![weth, token], U256::zero(), U256::MAX);
+    let plan = evaluate_sandwich(&tx, router, weth, 0, &state)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(plan.victim, tx.hash);
+    assert_eq!(plan.pair, pair);
+    assert_eq!(plan.token, token);
+    assert!(!plan.context.revenue.is_zero());
+    assert_eq!(plan.frontrun.amount_in, plan.context.optimal_weth_in);
+    assert_eq!(plan.frontrun.amount_out, plan.backrun.amount_in);
+    assert_eq!(
+        plan.backrun.amount_out,
+        plan.context.optimal_weth_in + plan.context.revenue
+    );
+
+    // The bot's loop prices from the same reserves to the same plan
+    let reserves = (ether(1_000), ether(1_000_000));
+    let priced = price_sandwich(tx.hash, weth, pair, token, tx.value, U256::zero(), reserves);
+    assert_eq!(priced.unwrap(), Some(plan));
+}
+
+#[tokio::test]
+async fn test_evaluate_sandwich_skips() {
+    let router = uniswap::get_univ2_router_address();
+    let weth = utils::get_weth_address();
+    let token = Address::random();
+    let (_, state) = state(weth, token);
+
+    // Not through the router
+    let tx = victim(
+        Address::random(),
+        vec![weth, token],
+        U256::zero(),
+        U256::MAX,
+    );
+    assert!(evaluate_sandwich(&tx, router, weth, 0, &state)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Expired deadline
+    let tx = victim(router, vec![weth, token], U256::zero(), U256::from(100));
+    assert!(evaluate_sandwich(&tx, router, weth, 101, &state)
+        .await
+        .unwrap()
+        .is_none());
+
+    // No slippage left to take
+    let (exact_out, _, _) =
+        uniswap::get_univ2_data_given_in(&ether(10), &ether(1_000), &ether(1_000_000));
+    let tx = victim(router, vec![weth, token], exact_out, U256::MAX);
+    assert!(evaluate_sandwich(&tx, router, weth, 0, &state)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Missing state errors
+    let tx = victim(
+        router,
+        vec![weth, Address::random()],
+        U256::zero(),
+        U256::MAX,
+    );
+    assert!(evaluate_sandwich(&tx, router, weth, 0, &state)
+        .await
+        .is_err());
+}
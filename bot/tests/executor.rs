@@ -0,0 +1,309 @@
+use ethers::prelude::*;
+use std::str::FromStr;
+
+use subway_rs::executor::*;
+
+fn weth() -> Address {
+    Address::from_str("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap()
+}
+
+fn usdc() -> Address {
+    Address::from_str("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap()
+}
+
+fn pair() -> Address {
+    Address::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap()
+}
+
+#[test]
+fn test_token_out_no() {
+    // USDC sorts before WETH, so buying USDC with WETH outputs token0
+    let leg = SwapLeg {
+        token_in: weth(),
+        token_out: usdc(),
+        pair: pair(),
+        amount_in: U256::from(100),
+        amount_out: U256::from(50),
+    };
+    assert_eq!(leg.token_out_no(), 0);
+    assert_eq!(leg.amounts_out(), (U256::from(50), U256::zero()));
+
+    // Selling USDC for WETH outputs token1
+    let leg = SwapLeg {
+        token_in: usdc(),
+        token_out: weth(),
+        ..leg
+    };
+    assert_eq!(leg.token_out_no(), 1);
+    assert_eq!(leg.amounts_out(), (U256::zero(), U256::from(50)));
+}
+
+#[test]
+fn test_subway_executor_packed_payload() {
+    let leg = SwapLeg {
+        token_in: weth(),
+        token_out: usdc(),
+        pair: pair(),
+        amount_in: U256::from(100),
+        amount_out: U256::from(50),
+    };
+    let payload = SubwayExecutor::encode_leg(&leg).unwrap();
+    assert_eq!(payload.as_ref().len(), SubwayExecutor::PAYLOAD_LENGTH);
+    assert_eq!(payload.as_ref()[0], SubwayExecutor::SANDWICH_TAG);
+    assert_eq!(&payload.as_ref()[1..21], weth().as_bytes());
+    assert_eq!(&payload.as_ref()[21..41], pair().as_bytes());
+    assert_eq!(&payload.as_ref()[41..57], &100u128.to_be_bytes());
+    assert_eq!(&payload.as_ref()[57..73], &50u128.to_be_bytes());
+    assert_eq!(payload.as_ref()[73], 0);
+
+    // No admin or view selector can be mistaken for a payload
+    for signature in [
+        SubwayExecutor::WITHDRAW_SIGNATURE,
+        SubwayExecutor::PAUSE_SIGNATURE,
+        SubwayExecutor::UNPAUSE_SIGNATURE,
+        SubwayExecutor::TRANSFER_OWNERSHIP_SIGNATURE,
+        SubwayExecutor::OWNER_SIGNATURE,
+        SubwayExecutor::PAUSED_SIGNATURE,
+        SubwayExecutor::PAYLOAD_VERSION_SIGNATURE,
+        "recoverERC20(address)",
+        "recoverETH()",
+    ] {
+        assert_ne!(
+            ethers::utils::id(signature)[0],
+            SubwayExecutor::SANDWICH_TAG
+        );
+    }
+}
+
+#[test]
+fn test_subway_executor_rejects_overflow() {
+    let leg = SwapLeg {
+        token_in: weth(),
+        token_out: usdc(),
+        pair: pair(),
+        amount_in: U256::from(u128::MAX) + 1,
+        amount_out: U256::from(50),
+    };
+    assert!(SubwayExecutor::encode_leg(&leg).is_err());
+}
+
+#[test]
+fn test_abi_executors_selectors() {
+    let leg = SwapLeg {
+        token_in: weth(),
+        token_out: usdc(),
+        pair: pair(),
+        amount_in: U256::from(100),
+        amount_out: U256::from(50),
+    };
+
+    let v3 = V3Executor::new(Address::zero());
+    let data = v3.encode_frontrun(&leg, ExecutionMode::Value).unwrap();
+    assert_eq!(
+        &data.as_ref()[0..4],
+        &ethers::utils::id(V3Executor::SWAP_SIGNATURE)
+    );
+    assert_eq!(data.as_ref().len(), 4 + 5 * 32);
+
+    let multicall = MulticallExecutor::new(Address::zero());
+    let data = multicall.encode_backrun(&leg).unwrap();
+    assert_eq!(
+        &data.as_ref()[0..4],
+        &ethers::utils::id(MulticallExecutor::MULTICALL_SIGNATURE)
+    );
+}
+
+#[test]
+fn test_withdrawal_encoding() {
+    let to = Address::random();
+    let amount = U256::exp10(18);
+
+    let subway = SubwayExecutor::new(Address::zero());
+    let data = subway.encode_withdrawal(weth(), to, amount).unwrap();
+    assert_eq!(
+        &data.as_ref()[0..4],
+        &ethers::utils::id(SubwayExecutor::WITHDRAW_SIGNATURE)
+    );
+    assert_eq!(data.as_ref().len(), 4 + 3 * 32);
+    assert_eq!(&data.as_ref()[48..68], to.as_bytes());
+
+    let multicall = MulticallExecutor::new(Address::zero());
+    let data = multicall.encode_withdrawal(weth(), to, amount).unwrap();
+    assert_eq!(
+        &data.as_ref()[0..4],
+        &ethers::utils::id(MulticallExecutor::MULTICALL_SIGNATURE)
+    );
+
+    let v3 = V3Executor::new(Address::zero());
+    assert!(v3.encode_withdrawal(weth(), to, amount).is_err());
+}
+
+#[test]
+fn test_liquidity_encoding() {
+    let position = LiquidityPosition {
+        pool: pair(),
+        tick_lower: -120,
+        tick_upper: 60,
+        liquidity: 42,
+    };
+
+    let v3 = V3Executor::new(Address::zero());
+    assert!(v3.supports_jit());
+    let mint = v3.encode_mint(&position).unwrap();
+    assert_eq!(
+        &mint.as_ref()[0..4],
+        &ethers::utils::id(V3Executor::MINT_SIGNATURE)
+    );
+    assert_eq!(mint.as_ref().len(), 4 + 4 * 32);
+    assert_eq!(&mint.as_ref()[16..36], pair().as_bytes());
+    // Negative ticks are sign-extended
+    assert_eq!(&mint.as_ref()[36..66], &[0xff; 30]);
+    assert_eq!(&mint.as_ref()[66..68], &(-120i16).to_be_bytes());
+    assert_eq!(
+        U256::from_big_endian(&mint.as_ref()[68..100]),
+        U256::from(60)
+    );
+    assert_eq!(
+        U256::from_big_endian(&mint.as_ref()[100..132]),
+        U256::from(42)
+    );
+
+    let burn = v3.encode_burn(&position).unwrap();
+    assert_eq!(
+        &burn.as_ref()[0..4],
+        &ethers::utils::id(V3Executor::BURN_SIGNATURE)
+    );
+    assert_eq!(&burn.as_ref()[4..], &mint.as_ref()[4..]);
+
+    let subway = SubwayExecutor::new(Address::zero());
+    assert!(!subway.supports_jit());
+    assert!(subway.encode_mint(&position).is_err());
+    assert!(subway.encode_burn(&position).is_err());
+}
+
+#[test]
+fn test_liquidation_encoding() {
+    let liquidation = subway_rs::liquidations::Liquidation {
+        protocol: subway_rs::liquidations::LendingProtocol::AaveV3,
+        borrower: Address::random(),
+        market: pair(),
+        debt_asset: usdc(),
+        collateral: weth(),
+        repay_amount: U256::exp10(6),
+    };
+
+    let multicall = MulticallExecutor::new(Address::zero());
+    assert!(multicall.supports_liquidations());
+    let data = multicall.encode_liquidation(&liquidation).unwrap();
+    assert_eq!(
+        &data.as_ref()[0..4],
+        &ethers::utils::id(MulticallExecutor::MULTICALL_SIGNATURE)
+    );
+    let calls = ethers::abi::decode(
+        &[ethers::abi::ParamType::Array(Box::new(
+            ethers::abi::ParamType::Tuple(vec![
+                ethers::abi::ParamType::Address,
+                ethers::abi::ParamType::Uint(256),
+                ethers::abi::ParamType::Bytes,
+            ]),
+        ))],
+        &data.as_ref()[4..],
+    )
+    .unwrap()
+    .pop()
+    .unwrap()
+    .into_array()
+    .unwrap();
+    let targets = calls
+        .into_iter()
+        .map(|call| {
+            call.into_tuple().unwrap()[0]
+                .clone()
+                .into_address()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(targets, vec![usdc(), pair()]);
+
+    let subway = SubwayExecutor::new(Address::zero());
+    assert!(!subway.supports_liquidations());
+    assert!(subway.encode_liquidation(&liquidation).is_err());
+}
+
+#[test]
+fn test_execution_modes() {
+    let leg = SwapLeg {
+        token_in: weth(),
+        token_out: usdc(),
+        pair: pair(),
+        amount_in: U256::from(100),
+        amount_out: U256::from(50),
+    };
+
+    // The classic contract can only swap its own inventory
+    let subway = SubwayExecutor::new(Address::zero());
+    assert!(subway.supports(ExecutionMode::Inventory));
+    assert!(!subway.supports(ExecutionMode::Value));
+    assert!(subway.encode_frontrun(&leg, ExecutionMode::Value).is_err());
+    assert_eq!(
+        subway.frontrun_value(&leg, ExecutionMode::Inventory),
+        U256::zero()
+    );
+
+    // The multicall executor prepends a weth deposit in value mode
+    let multicall = MulticallExecutor::new(Address::zero());
+    let inventory = multicall
+        .encode_frontrun(&leg, ExecutionMode::Inventory)
+        .unwrap();
+    let value = multicall
+        .encode_frontrun(&leg, ExecutionMode::Value)
+        .unwrap();
+    assert!(value.as_ref().len() > inventory.as_ref().len());
+    assert_eq!(
+        multicall.frontrun_value(&leg, ExecutionMode::Value),
+        U256::from(100)
+    );
+    assert_eq!(
+        ExecutionMode::from_str("value").unwrap(),
+        ExecutionMode::Value
+    );
+}
+
+#[test]
+fn test_executor_kind_from_str() {
+    assert_eq!(
+        ExecutorKind::from_str("subway").unwrap(),
+        ExecutorKind::Subway
+    );
+    assert_eq!(ExecutorKind::from_str("V3").unwrap(), ExecutorKind::V3);
+    assert_eq!(
+        ExecutorKind::from_str("multicall").unwrap(),
+        ExecutorKind::Multicall
+    );
+    assert!(ExecutorKind::from_str("unknown").is_err());
+    assert_eq!(ExecutorKind::Multicall.build(pair()).address(), pair());
+}
+
+#[test]
+fn test_payload_version_check() {
+    let subway = SubwayExecutor::new(Address::random());
+    let all = [
+        PayloadCapability::Legs,
+        PayloadCapability::ReplayGuard,
+        PayloadCapability::BalanceGuard,
+    ];
+    assert!(check_payload_version(&subway, SubwayExecutor::PAYLOAD_VERSION, &all).is_ok());
+
+    // Older contracts don't expect the sandwich tag
+    let unversioned = SubwayExecutor::UNVERSIONED_PAYLOAD_VERSION;
+    assert!(check_payload_version(&subway, unversioned, &[PayloadCapability::Legs]).is_err());
+    assert!(check_payload_version(&subway, 3, &[PayloadCapability::Legs]).is_err());
+    assert!(check_payload_version(&subway, 3, &all).is_err());
+
+    // Newer contracts may have changed the format
+    assert!(check_payload_version(&subway, SubwayExecutor::PAYLOAD_VERSION + 1, &[]).is_err());
+
+    // Executors without a version are never checked
+    let multicall = MulticallExecutor::new(Address::random());
+    assert!(check_payload_version(&multicall, 0, &all).is_ok());
+}
@@ -0,0 +1,113 @@
+use std::time::Instant;
+
+use ethers::prelude::*;
+
+use subway_rs::{admin::*, explain::*};
+
+#[test]
+fn test_report_records_the_stage_it_ends_in() {
+    let tx = TxHash::random();
+    {
+        let mut report = DecisionReport::new(tx, Instant::now());
+        report.stage("decode");
+        report.stage("sizing");
+        report.value("optimal_weth_in", U256::from(42));
+        report.stage("profit_floor");
+        report.value("profit_floor", U256::from(100));
+        report.skip("Revenue is under the floor");
+    }
+    let explanation = explanations().get(&tx).unwrap();
+    assert_eq!(explanation.verdict, Verdict::Skipped);
+    assert_eq!(explanation.stage, "profit_floor");
+    assert_eq!(
+        explanation.reason.as_deref(),
+        Some("Revenue is under the floor")
+    );
+    assert_eq!(explanation.passed, vec!["ingest", "decode", "sizing"]);
+    assert_eq!(explanation.values["optimal_weth_in"], "42");
+    assert_eq!(explanation.values["profit_floor"], "100");
+
+    // Dropping the report without a reason still records where it stopped
+    let unexplained = TxHash::random();
+    {
+        let mut report = DecisionReport::new(unexplained, Instant::now());
+        report.stage("reserves");
+    }
+    let explanation = explanations().get(&unexplained).unwrap();
+    assert_eq!(explanation.verdict, Verdict::Skipped);
+    assert_eq!(explanation.stage, "reserves");
+    assert_eq!(explanation.reason, None);
+
+    // Shipped bundles collect notes, and discarded reports aren't recorded
+    let shipped = TxHash::random();
+    let mut report = DecisionReport::new(shipped, Instant::now());
+    report.conclude(Verdict::Shipped);
+    drop(report);
+    assert!(explanations().note(shipped, "Superseded"));
+    assert_eq!(
+        explanations().get(&shipped).unwrap().notes,
+        vec!["Superseded"]
+    );
+
+    let discarded = TxHash::random();
+    DecisionReport::new(discarded, Instant::now()).discard();
+    assert!(explanations().get(&discarded).is_none());
+    assert!(!explanations().note(discarded, "Missing"));
+}
+
+#[test]
+fn test_explanation_log_keeps_the_latest() {
+    let log = ExplanationLog::new(2);
+    let explanation = |tx: TxHash| Explanation {
+        tx,
+        received_at: 0,
+        decided_at: 0,
+        verdict: Verdict::Skipped,
+        stage: "decode".to_string(),
+        reason: None,
+        passed: vec![],
+        values: Default::default(),
+        notes: vec![],
+    };
+    let txs = (0..3).map(|_| TxHash::random()).collect::<Vec<_>>();
+    for tx in &txs {
+        log.record(explanation(*tx));
+    }
+    assert_eq!(log.len(), 2);
+    assert!(log.get(&txs[0]).is_none());
+    assert!(log.get(&txs[1]).is_some());
+
+    // Re-recording a transaction replaces its explanation
+    let mut replaced = explanation(txs[2]);
+    replaced.verdict = Verdict::Shipped;
+    log.record(replaced);
+    assert_eq!(log.len(), 2);
+    assert_eq!(log.get(&txs[2]).unwrap().verdict, Verdict::Shipped);
+}
+
+#[test]
+fn test_admin_explain() {
+    let tx = TxHash::random();
+    {
+        let mut report = DecisionReport::new(tx, Instant::now());
+        report.skip("Spam");
+    }
+    let admin = Admin::new();
+    let handle = |raw: String| admin.handle(&Request::parse(raw.as_bytes()).unwrap());
+
+    let response = handle(format!("GET /explain?tx={:?} HTTP/1.1\r\n\r\n", tx));
+    assert_eq!(response.status, 200);
+    assert!(response.body.contains("\"reason\":\"Spam\""));
+    assert_eq!(
+        handle("GET /explain?tx=nope HTTP/1.1\r\n\r\n".to_string()).status,
+        400
+    );
+    assert_eq!(
+        handle(format!(
+            "GET /explain?tx={:?} HTTP/1.1\r\n\r\n",
+            TxHash::random()
+        ))
+        .status,
+        404
+    );
+}
@@ -0,0 +1,166 @@
+use std::time::Instant;
+
+use ethers::prelude::*;
+use subway_rs::{
+    export::*,
+    numeric,
+    opportunity::{BundlePlan, DecodedSwap, Opportunity, OpportunityStore},
+    simulation::StateSnapshot,
+};
+
+/// The columns of schema version 1, which later versions may only append to
+const V1_HEADER: &str = "schema_version,victim,sender,pair,token,path,user_amount_in,\
+amount_out_min,user_min_recv,deadline,reserves_block,weth_reserves,token_reserves,\
+optimal_weth_in,revenue,target_block,wallet,base_fee,bribe_bps,flash_loan_amount,seen_at_ms,\
+priced_at_ms,planned_at_ms";
+
+fn opportunity() -> Opportunity {
+    let token = Address::repeat_byte(0x22);
+    Opportunity::new(
+        Transaction {
+            hash: H256::repeat_byte(0x11),
+            from: Address::repeat_byte(0x33),
+            value: U256::from(1_000),
+            ..Default::default()
+        },
+        DecodedSwap {
+            amount_out_min: U256::from(900),
+            path: vec![Address::repeat_byte(0x44), token],
+            to: Address::random(),
+            deadline: U256::from(1_700_000_000u64),
+        },
+        Address::repeat_byte(0x55),
+        StateSnapshot {
+            block: U64::from(10),
+            reserves: (U256::from(100_000), U256::from(200_000)),
+        },
+        U256::from(900),
+        Instant::now(),
+    )
+}
+
+fn planned() -> Opportunity {
+    let mut opportunity = opportunity();
+    opportunity.price(
+        numeric::calculate_sandwich_context(
+            &U256::from(50),
+            &U256::from(1_000),
+            &U256::zero(),
+            &U256::from(100_000),
+            &U256::from(200_000),
+        )
+        .unwrap(),
+    );
+    opportunity.plan(BundlePlan {
+        target_block: U64::from(11),
+        bribe_bps: 9_000,
+        ..Default::default()
+    });
+    opportunity
+}
+
+#[test]
+fn test_schema_is_stable() {
+    let names = COLUMNS.iter().map(|c| c.name).collect::<Vec<_>>();
+    assert_eq!(names.join(","), V1_HEADER);
+    assert_eq!(EXPORT_SCHEMA_VERSION, 1);
+    assert!(parquet_message_type().contains("OPTIONAL BYTE_ARRAY revenue (UTF8);"));
+    assert!(parquet_message_type().contains("REQUIRED INT64 reserves_block (INTEGER(64,false));"));
+
+    // Every row fills every column, with a value of the column's type
+    for opportunity in [opportunity(), planned()] {
+        let row = export_row(&opportunity);
+        assert_eq!(row.len(), COLUMNS.len());
+        for (column, cell) in COLUMNS.iter().zip(&row) {
+            match cell {
+                None => assert!(column.nullable, "{} is null", column.name),
+                Some(Cell::Text(_)) => assert_eq!(column.kind, ColumnType::Utf8),
+                Some(Cell::UInt(_)) => assert_eq!(column.kind, ColumnType::UInt64),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_write_csv() {
+    let opportunity = opportunity();
+    let mut csv = Vec::new();
+    assert_eq!(
+        write_csv(&[opportunity.clone(), planned()], &mut csv).unwrap(),
+        2
+    );
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], V1_HEADER);
+
+    // The path holds a comma so it's quoted, and unreached stages are empty
+    assert_eq!(
+        lines[1],
+        format!(
+            "1,{:?},{:?},{:?},{:?},\"{:?},{:?}\",1000,900,900,1700000000,10,100000,200000,,,,,,,,{},,",
+            H256::repeat_byte(0x11),
+            Address::repeat_byte(0x33),
+            Address::repeat_byte(0x55),
+            Address::repeat_byte(0x22),
+            Address::repeat_byte(0x44),
+            Address::repeat_byte(0x22),
+            opportunity.timestamps.seen,
+        )
+    );
+    let planned = lines[2].rsplit(',').collect::<Vec<_>>();
+    assert!(!planned[0].is_empty() && !planned[1].is_empty());
+    assert!(lines[2].contains(",11,0x0000000000000000000000000000000000000000,0,9000,,"));
+}
+
+#[test]
+fn test_export_file() {
+    assert_eq!(
+        ExportFormat::from_path("out/opportunities.CSV").unwrap(),
+        ExportFormat::Csv
+    );
+    assert_eq!(
+        ExportFormat::from_path("opportunities.parquet").unwrap(),
+        ExportFormat::Parquet
+    );
+    assert!(ExportFormat::from_path("opportunities").is_err());
+    assert!(ExportFormat::from_path("opportunities.xlsx").is_err());
+
+    // Stored opportunities export as they were appended
+    let dir = std::env::temp_dir().join(format!("subway-export-{}", H256::random()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = OpportunityStore::open(dir.join("opportunities.jsonl")).unwrap();
+    store.append(&opportunity()).unwrap();
+    store.append(&planned()).unwrap();
+    let opportunities = OpportunityStore::load(store.path()).unwrap();
+    let out = dir.join("opportunities.csv");
+    assert_eq!(export_file(&opportunities, &out).unwrap(), 2);
+    assert_eq!(std::fs::read_to_string(&out).unwrap().lines().count(), 3);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_write_parquet() {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let dir = std::env::temp_dir().join(format!("subway-export-{}", H256::random()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let out = dir.join("opportunities.parquet");
+    assert_eq!(
+        export_file(&[opportunity(), planned(), opportunity()], &out).unwrap(),
+        3
+    );
+
+    let reader = SerializedFileReader::new(std::fs::File::open(&out).unwrap()).unwrap();
+    let metadata = reader.metadata().file_metadata();
+    assert_eq!(metadata.num_rows(), 3);
+    let names = metadata
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|c| c.name().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names.join(","), V1_HEADER);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,224 @@
+use ethers::prelude::*;
+
+use subway_rs::gas::*;
+
+#[test]
+fn test_blob_base_fee() {
+    assert_eq!(blob_base_fee(0), U256::from(MIN_BLOB_BASE_FEE));
+    assert_eq!(blob_base_fee(BLOB_BASE_FEE_UPDATE_FRACTION), U256::from(2));
+    // e^10 ~= 22026
+    assert_eq!(
+        blob_base_fee(10 * BLOB_BASE_FEE_UPDATE_FRACTION),
+        U256::from(22_026)
+    );
+
+    assert_eq!(next_excess_blob_gas(0, TARGET_BLOB_GAS_PER_BLOCK), 0);
+    assert_eq!(
+        next_excess_blob_gas(1_000, TARGET_BLOB_GAS_PER_BLOCK + GAS_PER_BLOB),
+        1_000 + GAS_PER_BLOB
+    );
+    assert_eq!(next_excess_blob_gas(1_000, 0), 0);
+}
+
+#[test]
+fn test_charged_gas() {
+    let data = [0u8, 0, 1, 2];
+    assert_eq!(
+        calldata_floor_gas(&data),
+        TX_BASE_GAS + 10 * (1 + 1 + 4 + 4)
+    );
+
+    // Execution heavy legs pay their regular gas
+    assert_eq!(
+        charged_gas(&data, U256::from(120_000u64)),
+        U256::from(120_000u64)
+    );
+    // Calldata heavy legs pay the floor
+    let calldata = vec![1u8; 1_000];
+    assert_eq!(
+        charged_gas(&calldata, U256::from(40_000u64)),
+        U256::from(calldata_floor_gas(&calldata))
+    );
+}
+
+#[test]
+fn test_block_fees() {
+    let block: BlockFees = serde_json::from_value(serde_json::json!({
+        "number": "0x10",
+        "gasUsed": "0x1c9c380",
+        "gasLimit": "0x1c9c380",
+        "baseFeePerGas": "0x3b9aca00",
+        "blobGasUsed": "0x60000",
+        "excessBlobGas": "0x0",
+        "extraData": "0x546974616e2028746974616e6275696c6465722e78797a29",
+        "hash": "0x1111111111111111111111111111111111111111111111111111111111111111"
+    }))
+    .unwrap();
+    assert_eq!(block.blob_count(), 3);
+    assert!(block.is_full());
+    assert_eq!(block.builder(), "titantitanbuilderxyz");
+    assert_eq!(block.next_blob_base_fee(), U256::from(MIN_BLOB_BASE_FEE));
+
+    // Pre-4844 blocks have no blob fields
+    let block: BlockFees = serde_json::from_value(serde_json::json!({
+        "number": "0x1",
+        "gasUsed": "0x0",
+        "gasLimit": "0x1c9c380"
+    }))
+    .unwrap();
+    assert_eq!(block.blob_count(), 0);
+    assert!(!block.is_full());
+    assert_eq!(block.builder(), "unknown");
+}
+
+#[test]
+fn test_bundle_gas_budget_check() {
+    let budget = BundleGasBudget::new(U256::from(30_000_000u64), 2_500);
+    assert_eq!(budget.builder_limit(), U256::from(7_500_000u64));
+    assert!(budget.check(U256::from(400_000u64)).is_ok());
+    assert_eq!(
+        budget.check(U256::from(8_000_000u64)),
+        Err(BundleGasError::ExceedsBuilderLimit {
+            gas: U256::from(8_000_000u64),
+            limit: U256::from(7_500_000u64),
+        })
+    );
+
+    let partial = BundleGasBudget {
+        gas_used: U256::from(29_800_000u64),
+        ..budget
+    };
+    assert_eq!(
+        partial.check(U256::from(400_000u64)),
+        Err(BundleGasError::ExceedsRemaining {
+            gas: U256::from(400_000u64),
+            remaining: U256::from(200_000u64),
+        })
+    );
+}
+
+#[test]
+fn test_bundle_gas_budget_fit() {
+    let budget = BundleGasBudget::new(U256::from(3_000_000u64), 2_500);
+    let sandwiches = vec![
+        (
+            vec!["frontrun 1", "victim 1", "backrun 1"],
+            U256::from(400_000u64),
+        ),
+        (
+            vec!["frontrun 2", "victim 2", "backrun 2"],
+            U256::from(400_000u64),
+        ),
+    ];
+
+    // Two victims over the builder limit are rejected unless splitting
+    assert_eq!(
+        budget.fit(&sandwiches, false),
+        Err(BundleGasError::ExceedsBuilderLimit {
+            gas: U256::from(800_000u64),
+            limit: U256::from(750_000u64),
+        })
+    );
+    assert_eq!(
+        budget.fit(&sandwiches, true).unwrap(),
+        vec![
+            vec!["frontrun 1", "victim 1", "backrun 1"],
+            vec!["frontrun 2", "victim 2", "backrun 2"],
+        ]
+    );
+
+    // Fitting groups stay in one bundle
+    assert_eq!(budget.fit(&sandwiches[..1], true).unwrap().len(), 1);
+
+    // A single oversized sandwich can't be split
+    let oversized = vec![(
+        vec!["frontrun", "victim", "backrun"],
+        U256::from(800_000u64),
+    )];
+    assert!(budget.fit(&oversized, true).is_err());
+}
+
+#[test]
+fn test_base_fee_guard() {
+    let gwei = |n: u64| U256::from(n) * U256::exp10(9);
+    let mut guard = BaseFeeGuard::new(BaseFeeLimits {
+        max_base_fee: Some(gwei(100)),
+        max_volatility_bps: Some(2_000),
+        volatility_blocks: 3,
+    });
+    assert_eq!(guard.volatility_bps(), 0);
+
+    guard.observe(U64::from(1), gwei(20));
+    guard.observe(U64::from(2), gwei(22));
+    assert_eq!(guard.volatility_bps(), 1_000);
+    assert!(guard.check(gwei(22)).is_ok());
+    assert_eq!(
+        guard.check(gwei(101)),
+        Err(BaseFeeRejection::TooHigh {
+            base_fee: gwei(101),
+            max: gwei(100),
+        })
+    );
+
+    // Repeated blocks are only recorded once
+    guard.observe(U64::from(2), gwei(50));
+    assert_eq!(guard.volatility_bps(), 1_000);
+
+    guard.observe(U64::from(3), gwei(25));
+    assert_eq!(
+        guard.check(gwei(25)),
+        Err(BaseFeeRejection::Volatile {
+            volatility_bps: 2_500,
+            max_bps: 2_000,
+        })
+    );
+
+    // The spike rolls out of the window
+    guard.observe(U64::from(4), gwei(24));
+    guard.observe(U64::from(5), gwei(24));
+    assert_eq!(guard.volatility_bps(), 416);
+    assert!(guard.check(gwei(24)).is_ok());
+}
+
+#[test]
+fn test_gas_model() {
+    let mut model = GasModel::new(2_000);
+    let pair = Address::random();
+    let token = Address::random();
+    assert!(model.estimate(&pair, &token).is_none());
+
+    // The first sample is taken as is, later ones are averaged in
+    model.observe(pair, token, U256::from(100_000), U256::from(120_000));
+    model.observe(pair, token, U256::from(200_000), U256::from(220_000));
+    let estimate = model.estimate(&pair, &token).unwrap();
+    assert_eq!(estimate.frontrun, U256::from(120_000));
+    assert_eq!(estimate.backrun, U256::from(140_000));
+    assert_eq!(estimate.samples, 2);
+
+    // Other pairs of the same token fall back to the token's average
+    assert_eq!(model.estimate(&Address::random(), &token), Some(estimate));
+    assert!(model
+        .estimate(&Address::random(), &Address::random())
+        .is_none());
+}
+
+#[test]
+fn test_estimate_bribe() {
+    let gwei = |n: u64| U256::from(n) * U256::exp10(9);
+    let gas = LegGas {
+        frontrun: U256::from(100_000),
+        backrun: U256::from(100_000),
+        samples: 1,
+    };
+
+    let estimate = estimate_bribe(U256::exp10(18), &gas, gwei(10), 1337).unwrap();
+    assert_eq!(
+        estimate.max_priority_fee_per_gas,
+        U256::from(1_335_663_000_000u64)
+    );
+    assert_eq!(estimate.profit, U256::from(864_433_700_000_000_000u64));
+
+    // Too little revenue to bribe above the base fee, or to pay for the frontrun at all
+    assert!(estimate_bribe(gwei(2_000_000), &gas, gwei(10), 1337).is_none());
+    assert!(estimate_bribe(gwei(500_000), &gas, gwei(10), 1337).is_none());
+}
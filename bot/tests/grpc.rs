@@ -0,0 +1,126 @@
+#![cfg(feature = "grpc")]
+
+use std::time::{Duration, Instant};
+
+use ethers::prelude::*;
+use subway_rs::{
+    grpc::*,
+    manual_approval::ApprovalOutcome,
+    opportunity::{BundlePlan, DecodedSwap, Opportunity},
+    simulation::StateSnapshot,
+};
+use tonic::Request;
+
+fn opportunity() -> Opportunity {
+    let token = Address::random();
+    let mut opportunity = Opportunity::new(
+        Transaction {
+            hash: H256::random(),
+            value: U256::from(1_000),
+            ..Default::default()
+        },
+        DecodedSwap {
+            amount_out_min: U256::from(900),
+            path: vec![Address::random(), token],
+            to: Address::random(),
+            deadline: U256::from(1_700_000_000u64),
+        },
+        Address::random(),
+        StateSnapshot {
+            block: U64::from(10),
+            reserves: (U256::from(100_000), U256::from(200_000)),
+        },
+        U256::from(900),
+        Instant::now(),
+    );
+    opportunity.plan(BundlePlan {
+        target_block: U64::from(11),
+        transactions: vec![Bytes::from(vec![0x02, 0xaa])],
+        ..Default::default()
+    });
+    opportunity
+}
+
+/// Receives the message streamed for `opportunity`, skipping those of other tests
+async fn received(
+    feed: &mut tokio::sync::broadcast::Receiver<proto::Opportunity>,
+    opportunity: &Opportunity,
+) -> proto::Opportunity {
+    let victim = format!("{:?}", opportunity.victim.hash);
+    loop {
+        let message = feed.recv().await.unwrap();
+        if message.victim == victim {
+            return message;
+        }
+    }
+}
+
+#[test]
+fn test_opportunity_message() {
+    let opportunity = opportunity();
+    let message = opportunity_message(&opportunity, U256::from(500), None, None).unwrap();
+    assert_eq!(message.id, 0);
+    assert!(!message.decision_required);
+    assert_eq!(message.victim, format!("{:?}", opportunity.victim.hash));
+    assert_eq!(message.token, format!("{:?}", opportunity.token));
+    assert_eq!(message.weth_in, "500");
+    assert_eq!(message.revenue, "0");
+    assert_eq!(message.target_block, 11);
+    assert!(message.signed_plan.is_empty());
+
+    // The json carries the whole opportunity, signed bundle included
+    let decoded: Opportunity = serde_json::from_str(&message.json).unwrap();
+    assert_eq!(decoded, opportunity);
+}
+
+#[tokio::test]
+async fn test_publish_and_decide() {
+    let mut feed = opportunity_feed().subscribe();
+
+    // Streamed only
+    let streamed = opportunity();
+    assert!(publish(&streamed, U256::from(500), None, None).is_none());
+    let message = received(&mut feed, &streamed).await;
+    assert!(!message.decision_required);
+
+    // Held for a decision
+    let window = Duration::from_secs(5);
+    let held = opportunity();
+    let (request, decision) = publish(&held, U256::from(500), None, Some(window)).unwrap();
+    let message = received(&mut feed, &held).await;
+    assert_eq!(message.id, request.id);
+    assert!(message.decision_required);
+    assert_eq!(message.expires_at, request.expires_at);
+
+    assert!(remote_decisions().decide(request.id, true).is_some());
+    assert!(remote_decisions().decide(request.id, true).is_none());
+    let outcome = remote_decisions().wait(request.id, decision, window).await;
+    assert_eq!(outcome, ApprovalOutcome::Approved);
+    assert_eq!(decision_metric(outcome), "remote_executed");
+}
+
+#[tokio::test]
+async fn test_undecided_opportunity_expires() {
+    let window = Duration::from_millis(10);
+    let (request, decision) = publish(&opportunity(), U256::from(500), None, Some(window)).unwrap();
+    let outcome = remote_decisions().wait(request.id, decision, window).await;
+    assert_eq!(outcome, ApprovalOutcome::Expired);
+    assert_eq!(decision_metric(outcome), "remote_expired");
+    assert!(remote_decisions().decide(request.id, true).is_none());
+}
+
+#[test]
+fn test_authorize() {
+    let mut request = Request::new(());
+    assert!(authorize(&request, "secret").is_err());
+
+    request
+        .metadata_mut()
+        .insert("authorization", "Bearer wrong".parse().unwrap());
+    assert!(authorize(&request, "secret").is_err());
+
+    request
+        .metadata_mut()
+        .insert("authorization", "Bearer secret".parse().unwrap());
+    assert!(authorize(&request, "secret").is_ok());
+}
@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+use ethers::prelude::*;
+use subway_rs::{honeypot::*, safety::RiskFlag};
+
+#[test]
+fn test_parse_apis() {
+    assert_eq!(
+        HoneypotApis::from_str("goplus, honeypot.is").unwrap().0,
+        vec![HoneypotApi::GoPlus, HoneypotApi::HoneypotIs]
+    );
+    assert!(HoneypotApis::from_str("").unwrap().0.is_empty());
+    assert!(HoneypotApis::from_str("tokensniffer").is_err());
+}
+
+#[test]
+fn test_parse_goplus() {
+    let token = Address::from_low_u64_be(0xabc);
+    let response = serde_json::json!({
+        "code": 1,
+        "message": "OK",
+        "result": {
+            "0x0000000000000000000000000000000000000abc": {
+                "is_honeypot": "0",
+                "cannot_sell_all": "0",
+                "buy_tax": "0.05",
+                "sell_tax": "0.125"
+            }
+        }
+    });
+    assert_eq!(
+        parse_goplus(&response, token).unwrap(),
+        HoneypotVerdict {
+            is_honeypot: false,
+            buy_tax_bps: 500,
+            sell_tax_bps: 1_250,
+        }
+    );
+    assert!(parse_goplus(&response, Address::zero()).is_err());
+}
+
+#[test]
+fn test_parse_honeypot_is() {
+    let response = serde_json::json!({
+        "honeypotResult": { "isHoneypot": true },
+        "simulationResult": { "buyTax": 1.5, "sellTax": 99 }
+    });
+    assert_eq!(
+        parse_honeypot_is(&response).unwrap(),
+        HoneypotVerdict {
+            is_honeypot: true,
+            buy_tax_bps: 150,
+            sell_tax_bps: 9_900,
+        }
+    );
+    assert!(parse_honeypot_is(&serde_json::json!({})).is_err());
+}
+
+#[test]
+fn test_verdict_report() {
+    let clean = HoneypotVerdict {
+        is_honeypot: false,
+        buy_tax_bps: 100,
+        sell_tax_bps: 1_000,
+    };
+    assert!(clean.report(DEFAULT_MAX_TOKEN_TAX_BPS).flags.is_empty());
+
+    let taxed = HoneypotVerdict {
+        sell_tax_bps: 1_001,
+        ..clean
+    };
+    assert_eq!(
+        taxed.report(DEFAULT_MAX_TOKEN_TAX_BPS).flags,
+        vec![RiskFlag::HighTax]
+    );
+
+    let honeypot = HoneypotVerdict {
+        is_honeypot: true,
+        ..clean
+    };
+    assert_eq!(
+        honeypot.report(DEFAULT_MAX_TOKEN_TAX_BPS).flags,
+        vec![RiskFlag::Honeypot]
+    );
+}
+
+#[tokio::test]
+async fn test_cached_reports_skip_apis() {
+    let checker = HoneypotChecker::new(HoneypotConfig::default(), 1);
+    let token = Address::random();
+    assert!(checker.cached(&token).is_none());
+
+    // With no APIs configured every answer is complete, so the empty report is cached
+    assert!(checker.check(token).await.unwrap().flags.is_empty());
+    assert!(checker.cached(&token).is_some());
+}
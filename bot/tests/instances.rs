@@ -0,0 +1,147 @@
+use std::{str::FromStr, sync::Arc};
+
+use subway_rs::{instances::*, metrics};
+
+#[test]
+fn test_instance_names_from_str() {
+    assert_eq!(
+        InstanceNames::from_str(" Base ").unwrap().0,
+        vec!["base".to_string()]
+    );
+    assert!(InstanceNames::from_str("").is_err());
+    assert_eq!(
+        InstanceNames::from_str("Mainnet, base").unwrap().0,
+        vec!["mainnet".to_string(), "base".to_string()]
+    );
+    assert!(InstanceNames::from_str("base,BASE").is_err());
+    assert!(InstanceNames::from_str("main-net").is_err());
+    assert_eq!(env_prefix("base"), "BASE_");
+}
+
+#[tokio::test]
+async fn test_instances_isolate_env_and_metrics() {
+    std::env::set_var("INSTANCES_TEST_SHARED", "shared");
+    std::env::set_var("INSTANCES_TEST_ONLY", "default");
+    std::env::set_var("ISOLATED_INSTANCES_TEST_ONLY", "isolated");
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(supervise("isolated".to_string(), || async {
+            assert_eq!(current().as_deref(), Some("isolated"));
+            assert_eq!(var("INSTANCES_TEST_ONLY").unwrap(), "isolated");
+            assert_eq!(var("INSTANCES_TEST_SHARED").unwrap(), "shared");
+            metrics::increment("instances_test_counter");
+
+            // Spawned tasks keep the instance
+            spawn(async { metrics::increment("instances_test_counter") })
+                .await
+                .unwrap();
+            Ok::<_, eyre::Report>(())
+        }))
+        .await;
+
+    assert_eq!(current(), None);
+    assert_eq!(var("INSTANCES_TEST_ONLY").unwrap(), "default");
+    assert_eq!(metrics::global().get("isolated.instances_test_counter"), 2);
+    assert_eq!(metrics::global().get("instances_test_counter"), 0);
+}
+
+#[tokio::test]
+async fn test_instances_on_different_chains() {
+    for (prefix, key) in [("MAINNET", 1), ("BASE", 2)] {
+        std::env::set_var(format!("{prefix}_PRIVATE_KEY"), format!("{key:064x}"));
+        std::env::set_var(
+            format!("{prefix}_FLASHBOTS_AUTH_KEY"),
+            format!("{:064x}", key + 10),
+        );
+        std::env::set_var(
+            format!("{prefix}_RPC_URL_WSS"),
+            format!("ws://{prefix}.node:8546"),
+        );
+    }
+    std::env::set_var("BASE_CHAIN_ID", "8453");
+    std::env::set_var(
+        "BASE_WETH_ADDRESS",
+        "0x4200000000000000000000000000000000000006",
+    );
+    std::env::set_var(
+        "BASE_UNIV2_FACTORY",
+        "0x8909Dc15e40173Ff4699343b6eB8132c65e18eC6",
+    );
+    std::env::set_var(
+        "BASE_UNIV2_ROUTER",
+        "0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24",
+    );
+
+    let mainnet = Instance::from_env("mainnet").unwrap();
+    let base = Instance::from_env("base").unwrap();
+    assert_eq!((mainnet.config.chain_id, base.config.chain_id), (1, 8453));
+    assert!(mainnet.config.addresses.is_mainnet());
+    assert_ne!(mainnet.config.addresses.weth, base.config.addresses.weth);
+    assert_ne!(
+        mainnet.wallets.searcher.address(),
+        base.wallets.searcher.address()
+    );
+    assert_ne!(mainnet.endpoints.rpc_url_wss, base.endpoints.rpc_url_wss);
+
+    // Pairs are derived from each chain's own deployment
+    let token = "0x00000000000000000000000000000000000000aa"
+        .parse()
+        .unwrap();
+    let pair = |i: &Instance| {
+        i.config
+            .addresses
+            .v2
+            .pair_address(i.config.addresses.weth, token)
+    };
+    assert_ne!(pair(&mainnet), pair(&base));
+
+    // Another chain can't fall back to the mainnet contracts
+    std::env::set_var("ORPHAN_CHAIN_ID", "10");
+    assert!(Instance::from_env("orphan").is_err());
+
+    // Both run side by side, each in its own namespace
+    let barrier = Arc::new(tokio::sync::Barrier::new(2));
+    let runs = [mainnet, base].map(|instance| {
+        let barrier = barrier.clone();
+        supervise(instance.name.clone(), move || {
+            let (name, barrier) = (instance.name.clone(), barrier.clone());
+            async move {
+                assert_eq!(current(), Some(name.clone()));
+                metrics::increment("chains_test_runs");
+                barrier.wait().await;
+                assert_eq!(current(), Some(name));
+                Ok::<_, eyre::Report>(())
+            }
+        })
+    });
+    let local = tokio::task::LocalSet::new();
+    local.run_until(futures::future::join_all(runs)).await;
+
+    assert_eq!(metrics::global().get("mainnet.chains_test_runs"), 1);
+    assert_eq!(metrics::global().get("base.chains_test_runs"), 1);
+}
+
+#[tokio::test]
+async fn test_supervise_aborts_instance_tasks() {
+    let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+    let sender = std::sync::Mutex::new(Some(sender));
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(supervise("aborting".to_string(), || {
+            let sender = sender.lock().unwrap().take();
+            async move {
+                // Holds the sender until it's aborted
+                spawn(async move {
+                    let _sender = sender;
+                    std::future::pending::<()>().await
+                });
+                Ok::<_, eyre::Report>(())
+            }
+        }))
+        .await;
+
+    // The sender is dropped without sending once the task is aborted
+    assert!(receiver.await.is_err());
+}
@@ -0,0 +1,215 @@
+use ethers::{
+    abi::{self, Token},
+    prelude::*,
+};
+use std::str::FromStr;
+
+use subway_rs::jit::*;
+
+fn weth() -> Address {
+    Address::from_str("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap()
+}
+
+fn usdc() -> Address {
+    Address::from_str("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap()
+}
+
+fn swap(amount_in: U256) -> V3Swap {
+    V3Swap {
+        token_in: weth(),
+        token_out: usdc(),
+        fee: 3_000,
+        recipient: Address::random(),
+        amount_in,
+        amount_out_minimum: U256::one(),
+        sqrt_price_limit_x96: U256::zero(),
+    }
+}
+
+fn pool(tick: i32) -> PoolState {
+    PoolState {
+        address: Address::random(),
+        token0: usdc(),
+        sqrt_price_x96: sqrt_ratio_at_tick(tick).unwrap(),
+        tick,
+        liquidity: 10u128.pow(18),
+        tick_spacing: 60,
+        fee: 3_000,
+    }
+}
+
+#[test]
+fn test_v3_pool_address() {
+    assert_eq!(
+        calculate_uniswap_v3_pool_address(weth(), usdc(), 3_000).unwrap(),
+        Address::from_str("0x8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8").unwrap()
+    );
+    assert_eq!(
+        calculate_uniswap_v3_pool_address(usdc(), weth(), 500).unwrap(),
+        Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap()
+    );
+}
+
+#[test]
+fn test_sqrt_ratio_at_tick() {
+    assert_eq!(sqrt_ratio_at_tick(0).unwrap(), q96());
+    assert_eq!(
+        sqrt_ratio_at_tick(MIN_TICK).unwrap(),
+        U256::from(4_295_128_739u64)
+    );
+    assert_eq!(
+        sqrt_ratio_at_tick(MAX_TICK).unwrap(),
+        U256::from_dec_str("1461446703485210103287273052203988822378723970342").unwrap()
+    );
+    assert!(sqrt_ratio_at_tick(-60).unwrap() < q96());
+    assert!(sqrt_ratio_at_tick(60).unwrap() > q96());
+    assert!(sqrt_ratio_at_tick(MAX_TICK + 1).is_err());
+    assert!(sqrt_ratio_at_tick(MIN_TICK - 1).is_err());
+}
+
+#[test]
+fn test_liquidity_math() {
+    let lower = sqrt_ratio_at_tick(-60).unwrap();
+    let upper = sqrt_ratio_at_tick(60).unwrap();
+    let amount = U256::exp10(18);
+
+    // In range, the scarcer side bounds the liquidity and the amounts round down
+    let liquidity = liquidity_for_amounts(q96(), lower, upper, amount, amount).unwrap();
+    let (amount0, amount1) = amounts_for_liquidity(q96(), lower, upper, liquidity).unwrap();
+    assert!(amount0 <= amount && amount1 <= amount);
+    assert!(amount - amount0.min(amount1) <= U256::from(2));
+
+    // Below the range only token0 is paid, above it only token1
+    let (amount0, amount1) = amounts_for_liquidity(lower, lower, upper, liquidity).unwrap();
+    assert!(!amount0.is_zero() && amount1.is_zero());
+    let (amount0, amount1) = amounts_for_liquidity(upper, lower, upper, liquidity).unwrap();
+    assert!(amount0.is_zero() && !amount1.is_zero());
+
+    assert_eq!(
+        mul_div(U256::MAX, U256::from(2), U256::from(4)),
+        Some(U256::MAX / 2)
+    );
+    assert_eq!(mul_div(U256::one(), U256::one(), U256::zero()), None);
+}
+
+#[test]
+fn test_position_range() {
+    assert_eq!(position_range(30, 60, 1), Some((0, 60)));
+    assert_eq!(position_range(-30, 60, 1), Some((-60, 0)));
+    assert_eq!(position_range(30, 60, 2), Some((-60, 120)));
+    assert_eq!(position_range(30, 60, 0), None);
+    assert_eq!(position_range(MAX_TICK - 1, 60, 1), None);
+}
+
+#[test]
+fn test_decode_exact_input_single() {
+    let swap = swap(U256::exp10(19));
+    let mut data = ethers::utils::id(EXACT_INPUT_SINGLE_SIGNATURE).to_vec();
+    data.extend(abi::encode(&[Token::Tuple(vec![
+        Token::Address(swap.token_in),
+        Token::Address(swap.token_out),
+        Token::Uint(U256::from(swap.fee)),
+        Token::Address(swap.recipient),
+        Token::Uint(U256::MAX),
+        Token::Uint(swap.amount_in),
+        Token::Uint(swap.amount_out_minimum),
+        Token::Uint(swap.sqrt_price_limit_x96),
+    ])]));
+    assert_eq!(V3Swap::decode(&data), Some(swap));
+    assert_eq!(V3Swap::decode(&data[..40]), None);
+    assert_eq!(V3Swap::decode(&[0u8; 4]), None);
+}
+
+#[test]
+fn test_plan_jit() {
+    let config = JitConfig::default();
+    let pool = pool(30);
+    let balance = U256::exp10(18);
+    let swap = swap(U256::exp10(20));
+
+    let plan = plan_jit(&swap, &pool, weth(), balance, balance, &config).unwrap();
+    assert_eq!(plan.position.pool, pool.address);
+    assert_eq!(
+        (plan.position.tick_lower, plan.position.tick_upper),
+        (0, 60)
+    );
+    assert!(plan.amount0 < balance && plan.amount1 < balance);
+
+    // The fee is split pro rata with the liquidity already in range
+    let liquidity = U256::from(plan.position.liquidity);
+    let total = liquidity + U256::from(pool.liquidity);
+    let fee = swap.amount_in * 3_000 / 1_000_000;
+    assert_eq!(plan.expected_fees, fee * liquidity / total);
+    assert_eq!(plan.share_bps, (liquidity * 10_000 / total).as_u64());
+
+    // The swap pushes the price to the top of the range, selling the position's token0
+    assert!(!plan.inventory_loss.is_zero());
+    assert!(plan.inventory_loss < plan.expected_fees);
+    assert_eq!(plan.net_fees(), plan.expected_fees - plan.inventory_loss);
+
+    // Small swaps, swaps selling the other token and empty inventory are skipped
+    let small = V3Swap {
+        amount_in: config.min_swap_weth - 1,
+        ..swap
+    };
+    assert!(plan_jit(&small, &pool, weth(), balance, balance, &config).is_none());
+    let selling = V3Swap {
+        token_in: usdc(),
+        token_out: weth(),
+        ..swap
+    };
+    assert!(plan_jit(&selling, &pool, weth(), balance, balance, &config).is_none());
+    assert!(plan_jit(&swap, &pool, weth(), U256::zero(), U256::zero(), &config).is_none());
+}
+
+#[test]
+fn test_inventory_loss() {
+    let (sqrt_a, sqrt_b) = (
+        sqrt_ratio_at_tick(0).unwrap(),
+        sqrt_ratio_at_tick(60).unwrap(),
+    );
+    let sqrt_price = sqrt_ratio_at_tick(30).unwrap();
+    let liquidity = U256::exp10(21);
+
+    // Selling token0 lowers the price and selling token1 raises it
+    let down = next_sqrt_price(sqrt_price, liquidity, U256::exp10(15), true).unwrap();
+    let up = next_sqrt_price(sqrt_price, liquidity, U256::exp10(15), false).unwrap();
+    assert!(down < sqrt_price && up > sqrt_price);
+    assert_eq!(
+        next_sqrt_price(sqrt_price, liquidity, U256::zero(), true),
+        Some(sqrt_price)
+    );
+    assert_eq!(
+        next_sqrt_price(sqrt_price, U256::zero(), U256::one(), true),
+        None
+    );
+
+    // A price that doesn't move loses nothing, one that moves further loses more
+    let loss = |next| inventory_loss(sqrt_a, sqrt_b, liquidity, sqrt_price, next, false).unwrap();
+    assert_eq!(loss(sqrt_price), U256::zero());
+    assert!(!loss(up).is_zero());
+    assert!(loss(sqrt_b) > loss(up));
+    assert!(
+        !inventory_loss(sqrt_a, sqrt_b, liquidity, sqrt_price, down, true)
+            .unwrap()
+            .is_zero()
+    );
+}
+
+#[test]
+fn test_jit_bribe() {
+    let base_fee = U256::from(10_000_000_000u64);
+    let gas = U256::from(JIT_MINT_GAS_LIMIT + JIT_BURN_GAS_LIMIT);
+
+    // Fees below gas at the base fee can't pay a bribe
+    assert_eq!(jit_bribe(gas * base_fee, base_fee, 1_337), None);
+
+    let fees = U256::exp10(17);
+    let priority_fee = jit_bribe(fees, base_fee, 1_337).unwrap();
+    let mint_cost = U256::from(JIT_MINT_GAS_LIMIT) * base_fee;
+    assert_eq!(
+        priority_fee,
+        (fees - mint_cost) * 1_337 / 10_000 / U256::from(JIT_BURN_GAS_LIMIT)
+    );
+    assert_eq!(jit_bribe(fees, base_fee, 10_000), None);
+}
@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+use ethers::prelude::*;
+use subway_rs::launches::*;
+
+#[test]
+fn test_launched_tokens() {
+    let launched = LaunchedTokens::default();
+    let token = Address::random();
+    let now = Instant::now();
+    let score = U256::from(1_000);
+
+    assert!(!launched.contains(&token, now));
+    assert_eq!(launched.boost(Some(token), score, now), score);
+
+    launched.insert(token, now + Duration::from_secs(60));
+    assert!(launched.contains(&token, now));
+    assert_eq!(launched.boost(Some(token), score, now), U256::from(2_000));
+    assert_eq!(launched.boost(None, score, now), score);
+    assert_eq!(launched.boost(Some(Address::random()), score, now), score);
+
+    // Tokens stop being tracked once their launch window ends
+    let later = now + Duration::from_secs(60);
+    assert!(!launched.contains(&token, later));
+    assert_eq!(launched.boost(Some(token), score, later), score);
+}
+
+#[test]
+fn test_trusted_launches() {
+    let pair = Address::random();
+    let token = Address::random();
+    let mut config = LaunchConfig {
+        min_pair_age_blocks: 10,
+        ..Default::default()
+    };
+    assert!(!config.is_trusted(&pair, &token));
+
+    config.trusted_launches = format!("{:?}", token).parse().unwrap();
+    assert!(config.is_trusted(&pair, &token));
+    assert!(config.is_trusted(&Address::random(), &token));
+    assert!(!config.is_trusted(&pair, &Address::random()));
+}
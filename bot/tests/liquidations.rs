@@ -0,0 +1,185 @@
+use ethers::prelude::*;
+
+use subway_rs::liquidations::*;
+
+fn word(address: Address) -> Vec<u8> {
+    let mut word = vec![0u8; 12];
+    word.extend(address.as_bytes());
+    word
+}
+
+fn aave_borrow(reserve: Address, borrower: Address) -> Log {
+    Log {
+        address: get_aave_pool_address(),
+        topics: vec![
+            event_topic(AAVE_BORROW_EVENT),
+            H256::from(reserve),
+            H256::from(borrower),
+            H256::zero(),
+        ],
+        data: vec![0u8; 4 * 32].into(),
+        ..Default::default()
+    }
+}
+
+fn aave_collateral(reserve: Address, user: Address) -> Log {
+    Log {
+        address: get_aave_pool_address(),
+        topics: vec![
+            event_topic(AAVE_COLLATERAL_EVENT),
+            H256::from(reserve),
+            H256::from(user),
+        ],
+        ..Default::default()
+    }
+}
+
+fn aave_health(health_factor: u128) -> Health {
+    Health::Aave {
+        health_factor: U256::from(health_factor),
+    }
+}
+
+#[test]
+fn test_lending_protocols_parsing() {
+    assert_eq!(
+        "aave, Compound".parse::<LendingProtocols>().unwrap(),
+        LendingProtocols(vec![LendingProtocol::AaveV3, LendingProtocol::CompoundV2])
+    );
+    assert!("maker".parse::<LendingProtocols>().is_err());
+    assert_eq!(LendingProtocols::default().0.len(), 2);
+}
+
+#[test]
+fn test_health() {
+    let config = LiquidationConfig::default();
+    let one = 10u128.pow(18);
+
+    assert!(aave_health(one - 1).is_liquidatable());
+    assert!(!aave_health(one).is_liquidatable());
+    assert!(aave_health(one).is_near(&config));
+    assert!(!aave_health(one * 105 / 100).is_near(&config));
+
+    let compound = |liquidity: u128, shortfall: u128| Health::Compound {
+        liquidity: U256::from(liquidity),
+        shortfall: U256::from(shortfall),
+    };
+    assert!(compound(0, 1).is_liquidatable());
+    assert!(compound(0, 1).is_near(&config));
+    assert!(!compound(1, 0).is_liquidatable());
+    assert!(compound(1, 0).is_near(&config));
+    assert!(!compound(1_000 * one, 0).is_near(&config));
+}
+
+#[test]
+fn test_position_book_events() {
+    let mut book = PositionBook::new();
+    let borrower = Address::random();
+    let (debt, collateral) = (Address::random(), Address::random());
+
+    assert!(book.apply_log(&aave_borrow(debt, borrower)));
+    assert!(book.apply_log(&aave_collateral(collateral, borrower)));
+    let position = book.get(LendingProtocol::AaveV3, borrower).unwrap();
+    assert!(position.debts.contains(&debt));
+    assert!(position.collaterals.contains(&collateral));
+
+    // Compound borrows are only trusted from known markets
+    let market = Address::random();
+    let mut data = word(borrower);
+    data.extend(vec![0u8; 3 * 32]);
+    let compound_borrow = Log {
+        address: market,
+        topics: vec![event_topic(COMPOUND_BORROW_EVENT)],
+        data: data.into(),
+        ..Default::default()
+    };
+    assert!(!book.apply_log(&compound_borrow));
+    book.set_compound_markets(vec![market]);
+    assert!(book.apply_log(&compound_borrow));
+
+    let mut entered = word(market);
+    entered.extend(word(borrower));
+    assert!(book.apply_log(&Log {
+        address: get_comptroller_address(),
+        topics: vec![event_topic(COMPOUND_MARKET_ENTERED_EVENT)],
+        data: entered.into(),
+        ..Default::default()
+    }));
+    let position = book.get(LendingProtocol::CompoundV2, borrower).unwrap();
+    assert!(position.debts.contains(&market));
+    assert!(position.collaterals.contains(&market));
+    assert_eq!(book.len(), 2);
+
+    assert!(!book.apply_log(&Log::default()));
+}
+
+#[test]
+fn test_near_liquidation() {
+    let config = LiquidationConfig::default();
+    let one = 10u128.pow(18);
+    let mut book = PositionBook::new();
+    let borrowers = [Address::random(), Address::random(), Address::random()];
+    for borrower in borrowers {
+        book.apply_log(&aave_borrow(Address::random(), borrower));
+        book.apply_log(&aave_collateral(Address::random(), borrower));
+    }
+    book.set_health(
+        LendingProtocol::AaveV3,
+        borrowers[0],
+        aave_health(one * 102 / 100),
+    );
+    book.set_health(
+        LendingProtocol::AaveV3,
+        borrowers[1],
+        aave_health(one * 99 / 100),
+    );
+    book.set_health(LendingProtocol::AaveV3, borrowers[2], aave_health(2 * one));
+
+    let near = book
+        .near_liquidation(&config)
+        .into_iter()
+        .map(|p| p.borrower)
+        .collect::<Vec<_>>();
+    assert_eq!(near, vec![borrowers[1], borrowers[0]]);
+
+    // Positions without collateral can't be liquidated
+    let uncollateralized = Address::random();
+    book.apply_log(&aave_borrow(Address::random(), uncollateralized));
+    book.set_health(LendingProtocol::AaveV3, uncollateralized, aave_health(0));
+    assert_eq!(book.near_liquidation(&config).len(), 2);
+}
+
+#[test]
+fn test_liquidation_calls() {
+    let liquidation = Liquidation {
+        protocol: LendingProtocol::AaveV3,
+        borrower: Address::random(),
+        market: get_aave_pool_address(),
+        debt_asset: Address::random(),
+        collateral: Address::random(),
+        repay_amount: U256::exp10(18),
+    };
+    let (target, data) = liquidation.call();
+    assert_eq!(target, get_aave_pool_address());
+    assert_eq!(&data[..4], &ethers::utils::id(AAVE_LIQUIDATION_SIGNATURE));
+    assert_eq!(data.len(), 4 + 5 * 32);
+    assert_eq!(&data[16..36], liquidation.collateral.as_bytes());
+    assert_eq!(&data[48..68], liquidation.debt_asset.as_bytes());
+    assert_eq!(&data[80..100], liquidation.borrower.as_bytes());
+
+    let market = Address::random();
+    let compound = Liquidation {
+        protocol: LendingProtocol::CompoundV2,
+        market,
+        ..liquidation
+    };
+    let (target, data) = compound.call();
+    assert_eq!(target, market);
+    assert_eq!(
+        &data[..4],
+        &ethers::utils::id(COMPOUND_LIQUIDATION_SIGNATURE)
+    );
+    assert_eq!(data.len(), 4 + 3 * 32);
+    assert_eq!(&data[16..36], compound.borrower.as_bytes());
+    assert_eq!(&data[80..100], compound.collateral.as_bytes());
+}
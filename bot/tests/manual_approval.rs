@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use ethers::prelude::*;
+
+use subway_rs::{admin::*, manual_approval::*};
+
+fn open(
+    gate: &ApprovalGate,
+    window: Duration,
+) -> (ApprovalRequest, tokio::sync::oneshot::Receiver<bool>) {
+    gate.open(
+        TxHash::random(),
+        Address::random(),
+        U256::exp10(18),
+        U256::exp10(16),
+        U64::from(100),
+        window,
+    )
+}
+
+#[tokio::test]
+async fn test_approval_gate_outcomes() {
+    let gate = ApprovalGate::new();
+    let window = Duration::from_secs(5);
+
+    let (approved, decision) = open(&gate, window);
+    assert_eq!(gate.pending(), vec![approved.clone()]);
+    assert_eq!(gate.decide(approved.id, true), Some(approved.clone()));
+    assert_eq!(
+        gate.wait(approved.id, decision, window).await,
+        ApprovalOutcome::Approved
+    );
+
+    let (rejected, decision) = open(&gate, window);
+    assert_ne!(rejected.id, approved.id);
+    assert!(gate.decide(rejected.id, false).is_some());
+    assert_eq!(
+        gate.wait(rejected.id, decision, window).await,
+        ApprovalOutcome::Rejected
+    );
+    assert!(gate.pending().is_empty());
+
+    // Unanswered requests expire and can't be decided on afterwards
+    let window = Duration::from_millis(10);
+    let (expired, decision) = open(&gate, window);
+    assert_eq!(
+        gate.wait(expired.id, decision, window).await,
+        ApprovalOutcome::Expired
+    );
+    assert!(gate.pending().is_empty());
+    assert_eq!(gate.decide(expired.id, true), None);
+}
+
+#[test]
+fn test_telegram_callbacks() {
+    let keyboard = approval_keyboard(7);
+    let buttons = keyboard["inline_keyboard"][0].as_array().unwrap();
+    let data = buttons
+        .iter()
+        .map(|b| b["callback_data"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(data, vec!["approve:7", "reject:7"]);
+    assert_eq!(parse_callback("approve:7"), Some((7, true)));
+    assert_eq!(parse_callback("reject:7"), Some((7, false)));
+    assert_eq!(parse_callback("ignore:7"), None);
+    assert_eq!(parse_callback("approve:x"), None);
+
+    let updates = serde_json::json!({
+        "ok": true,
+        "result": [
+            {
+                "update_id": 10,
+                "callback_query": {
+                    "id": "a",
+                    "data": "approve:7",
+                    "message": { "chat": { "id": 42 } }
+                }
+            },
+            {
+                "update_id": 11,
+                "callback_query": {
+                    "id": "b",
+                    "data": "approve:8",
+                    "message": { "chat": { "id": 13 } }
+                }
+            },
+            { "update_id": 12, "message": { "chat": { "id": 42 }, "text": "hi" } }
+        ]
+    });
+    let (decisions, offset) = parse_updates(&updates, 42, 0);
+    assert_eq!(
+        decisions,
+        vec![TelegramDecision {
+            callback_id: "a".to_string(),
+            id: 7,
+            approve: true,
+        }]
+    );
+    assert_eq!(offset, 13);
+}
+
+#[tokio::test]
+async fn test_admin_approvals() {
+    let window = Duration::from_secs(5);
+    let (request, decision) = open(approval_gate(), window);
+    let admin = Admin::new();
+    let handle = |raw: &str| admin.handle(&Request::parse(raw.as_bytes()).unwrap());
+
+    let response = handle("GET /approvals HTTP/1.1\r\n\r\n");
+    assert_eq!(response.status, 200);
+    assert!(response.body.contains(&format!("\"id\":{}", request.id)));
+
+    assert_eq!(handle("POST /approve HTTP/1.1\r\n\r\n").status, 400);
+    assert_eq!(handle("GET /approve?id=1 HTTP/1.1\r\n\r\n").status, 405);
+    let approve = format!("POST /approve?id={} HTTP/1.1\r\n\r\n", request.id);
+    assert_eq!(handle(&approve).status, 200);
+    assert_eq!(handle(&approve).status, 404);
+    assert_eq!(
+        approval_gate().wait(request.id, decision, window).await,
+        ApprovalOutcome::Approved
+    );
+}
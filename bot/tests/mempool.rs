@@ -0,0 +1,160 @@
+use ethers::prelude::*;
+use std::str::FromStr;
+
+use subway_rs::mempool::*;
+
+#[test]
+fn test_tx_sources_from_str() {
+    assert_eq!(TxSources::default().0, vec![TxSourceKind::Node]);
+    assert_eq!(
+        TxSources::from_str("node, eden").unwrap().0,
+        vec![TxSourceKind::Node, TxSourceKind::Eden]
+    );
+    assert_eq!(
+        TxSources::from_str("ipc,node").unwrap().0,
+        vec![TxSourceKind::Ipc, TxSourceKind::Node]
+    );
+    assert_eq!(
+        TxSources::from_str("node,TXPOOL").unwrap().0,
+        vec![TxSourceKind::Node, TxSourceKind::Txpool]
+    );
+    assert!(TxSources::from_str("").is_err());
+    assert!(TxSources::from_str("node,mystery").is_err());
+}
+
+#[test]
+fn test_deduplicator() {
+    let mut dedup = Deduplicator::new(2);
+    let (a, b, c) = (TxHash::random(), TxHash::random(), TxHash::random());
+    assert!(dedup.insert(a));
+    assert!(!dedup.insert(a));
+    assert!(dedup.insert(b));
+
+    // The oldest hash is forgotten past the capacity
+    assert!(dedup.insert(c));
+    assert!(!dedup.insert(b));
+    assert!(dedup.insert(a));
+}
+
+#[test]
+fn test_deduplicator_first_seen() {
+    let mut dedup = Deduplicator::new(4);
+    let hash = TxHash::random();
+    assert!(dedup.first_seen(&hash).is_none());
+    dedup.insert(hash);
+    let first_seen = dedup.first_seen(&hash).unwrap();
+
+    // Duplicates keep the original timestamp
+    assert!(!dedup.insert(hash));
+    assert_eq!(dedup.first_seen(&hash), Some(first_seen));
+    assert_eq!(
+        TxSourceKind::from_str("fiber").unwrap(),
+        TxSourceKind::Fiber
+    );
+}
+
+#[test]
+fn test_inclusion_tracker() {
+    let mut tracker = InclusionTracker::new(4);
+    let (a, b, c) = (TxHash::random(), TxHash::random(), TxHash::random());
+    tracker.record_seen("eden", a, 1_000);
+    tracker.record_seen("node", a, 1_500);
+    // Only the first delivery from a source counts
+    tracker.record_seen("node", a, 1_800);
+    tracker.record_seen("node", b, 3_500);
+
+    // Eden saw it first, both ahead of the block
+    assert_eq!(tracker.record_mined(a, 3_000), Some(2_000));
+    // Only seen after the block's timestamp
+    assert_eq!(tracker.record_mined(b, 3_000), None);
+    // Never seen
+    assert_eq!(tracker.record_mined(c, 3_000), None);
+
+    let report = tracker.report();
+    assert_eq!(report.mined, 3);
+    assert_eq!(report.seen, 1);
+    assert!((report.fraction_seen() - 1.0 / 3.0).abs() < 1e-9);
+    let eden = &report.sources["eden"];
+    assert_eq!((eden.seen, eden.first, eden.late), (1, 1, 0));
+    assert_eq!(eden.average_lead_ms(), Some(2_000));
+    let node = &report.sources["node"];
+    assert_eq!((node.seen, node.first, node.late), (1, 0, 1));
+    assert_eq!(node.average_lead_ms(), Some(1_500));
+    assert_eq!(report.source_fraction_seen("fiber"), 0.0);
+}
+
+#[test]
+fn test_inclusion_tracker_capacity() {
+    let mut tracker = InclusionTracker::new(1);
+    let (a, b) = (TxHash::random(), TxHash::random());
+    tracker.record_seen("node", a, 1_000);
+    tracker.record_seen("node", b, 1_000);
+
+    // The oldest sighting is forgotten past the capacity
+    assert_eq!(tracker.record_mined(a, 2_000), None);
+    assert_eq!(tracker.record_mined(b, 2_000), Some(1_000));
+}
+
+#[test]
+fn test_is_unsupported() {
+    assert!(is_unsupported(
+        "(code: -32601, message: the method eth_newPendingTransactionFilter does not exist/is not available, data: None)"
+    ));
+    assert!(is_unsupported("notifications not supported"));
+    assert!(!is_unsupported("connection reset by peer"));
+}
+
+#[test]
+fn test_txpool_diff() {
+    let tx = |n: u64| Transaction {
+        hash: H256::from_low_u64_be(n),
+        ..Default::default()
+    };
+    let mut pool = TxpoolDiff::default();
+    let hashes = |txs: Vec<Transaction>| txs.iter().map(|tx| tx.hash).collect::<Vec<_>>();
+
+    // The first poll only seeds the pool
+    assert_eq!(hashes(pool.diff(vec![tx(1), tx(2)])), vec![]);
+    // Only transactions new to the pool are delivered
+    assert_eq!(
+        hashes(pool.diff(vec![tx(2), tx(3), tx(3)])),
+        vec![tx(3).hash]
+    );
+    // One that was mined and reappears, after a reorg, is delivered again
+    assert_eq!(hashes(pool.diff(vec![tx(3)])), vec![]);
+    assert_eq!(hashes(pool.diff(vec![tx(1), tx(3)])), vec![tx(1).hash]);
+}
+
+#[test]
+fn test_mined_txs() {
+    let mut mined = MinedTxs::new(2);
+    let (a, b, c) = (H256::random(), H256::random(), H256::random());
+    mined.record(U64::from(10), vec![a]);
+    mined.record(U64::from(11), vec![b]);
+    assert_eq!(mined.contains(&a), Some(U64::from(10)));
+    assert_eq!(mined.contains(&c), None);
+
+    // The oldest block is forgotten
+    mined.record(U64::from(12), vec![c]);
+    assert_eq!(mined.len(), 2);
+    assert_eq!(mined.contains(&a), None);
+    assert_eq!(mined.contains(&c), Some(U64::from(12)));
+}
+
+#[test]
+fn test_mined_txs_reorg() {
+    let mut mined = MinedTxs::new(4);
+    let (a, b) = (H256::random(), H256::random());
+    mined.record(U64::from(10), vec![a, b]);
+
+    // The replacement block only kept one of the transactions
+    mined.record(U64::from(10), vec![b]);
+    assert_eq!(mined.len(), 1);
+    assert_eq!(mined.contains(&a), None);
+    assert_eq!(mined.contains(&b), Some(U64::from(10)));
+
+    // A transaction reorged into the next block moves with it
+    mined.record(U64::from(11), vec![b]);
+    mined.record(U64::from(10), vec![]);
+    assert_eq!(mined.contains(&b), Some(U64::from(11)));
+}
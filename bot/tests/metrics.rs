@@ -0,0 +1,51 @@
+use subway_rs::metrics::*;
+
+#[test]
+fn test_metrics_counters() {
+    let metrics = Metrics::new();
+    assert_eq!(metrics.get("bundles_sent"), 0);
+    metrics.increment("bundles_sent");
+    metrics.add("bundles_sent", 2);
+    assert_eq!(metrics.get("bundles_sent"), 3);
+    assert_eq!(metrics.snapshot().len(), 1);
+
+    // The global registry is shared
+    increment("test_counter");
+    assert_eq!(global().get("test_counter"), 1);
+}
+
+#[test]
+fn test_metrics_lifetime_and_gauges() {
+    let metrics = Metrics::new();
+    metrics.add("bundles_sent", 2);
+    metrics.set("head_block", 10);
+    metrics.set("head_block", 11);
+
+    // Totals of earlier sessions add to this one's, gauges set this session win
+    metrics.restore(
+        [
+            ("bundles_sent".to_string(), 5),
+            ("launches_seen".to_string(), 1),
+        ]
+        .into(),
+        [("head_block".to_string(), 3), ("observing".to_string(), 1)].into(),
+    );
+    assert_eq!(metrics.get("bundles_sent"), 2);
+    assert_eq!(metrics.lifetime().get("bundles_sent"), Some(&7));
+    assert_eq!(metrics.lifetime().get("launches_seen"), Some(&1));
+    assert_eq!(metrics.gauges().get("head_block"), Some(&11));
+    assert_eq!(metrics.gauges().get("observing"), Some(&1));
+}
+
+#[test]
+fn test_metrics_snapshot_persistence() {
+    let path = std::env::temp_dir().join(format!("metrics-{}.json", rand::random::<u64>()));
+    assert_eq!(MetricsSnapshot::load(&path).unwrap(), None);
+
+    increment("persisted_counter");
+    let snapshot = MetricsSnapshot::capture();
+    assert_eq!(snapshot.session.get("persisted_counter"), Some(&1));
+    snapshot.save(&path).unwrap();
+    assert_eq!(MetricsSnapshot::load(&path).unwrap(), Some(snapshot));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,136 @@
+use ethers::prelude::*;
+
+use subway_rs::{abi::SWAP_TOPIC, accounting::*, mev_share::*};
+
+#[test]
+fn test_backrun_bundle() {
+    let shared_tx = TxHash::random();
+    let bundle = MevShareBundle::backrun(
+        shared_tx,
+        vec![Bytes::from(vec![1u8, 2, 3]), Bytes::from(vec![4u8])],
+        U64::from(100),
+        90,
+    )
+    .unwrap();
+    assert_eq!(bundle.refund_percent(), 90);
+
+    let json = serde_json::to_value(&bundle).unwrap();
+    assert_eq!(json["version"], "v0.1");
+    assert_eq!(json["inclusion"]["block"], "0x64");
+    assert!(json["inclusion"].get("maxBlock").is_none());
+    assert_eq!(json["body"][0]["hash"], format!("{shared_tx:?}"));
+    assert_eq!(json["body"][1]["tx"], "0x010203");
+    assert_eq!(json["body"][1]["canRevert"], false);
+    assert_eq!(json["body"][2]["tx"], "0x04");
+    assert_eq!(json["validity"]["refund"][0]["bodyIdx"], 0);
+    assert_eq!(json["validity"]["refund"][0]["percent"], 90);
+
+    assert!(MevShareBundle::backrun(shared_tx, Vec::new(), U64::one(), 101).is_err());
+}
+
+#[test]
+fn test_event_stream() {
+    let pair = Address::random();
+    let mut data = [0u8; 128];
+    data[31] = 7; // amount0In
+    data[127] = 5; // amount1Out
+    let event = serde_json::json!({
+        "hash": H256::repeat_byte(1),
+        "logs": [{
+            "address": pair,
+            "topics": [H256::from(SWAP_TOPIC), H256::zero(), H256::zero()],
+            "data": Bytes::from(data.to_vec()),
+        }],
+    });
+    let stream = format!(
+        ":ping\n\ndata: {event}\n\ndata: {{\"hash\":\"{:?}\"}}\n",
+        H256::zero()
+    );
+
+    // Events split across chunks are only returned once complete
+    let mut events = EventStream::default();
+    let (head, tail) = stream.as_bytes().split_at(40);
+    assert!(events.push(head).is_empty());
+    let parsed = events.push(tail);
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed[0].hash, H256::repeat_byte(1));
+
+    let swaps = parsed[0].swaps();
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].pair, pair);
+    assert_eq!(
+        reserves_after(&swaps[0], (100.into(), 100.into())),
+        Some((107.into(), 95.into()))
+    );
+
+    // Hints without data or swaps have nothing to backrun
+    assert!(parsed[1].swaps().is_empty());
+}
+
+#[test]
+fn test_plan_arb() {
+    let (weth, token) = (Address::random(), Address::random());
+    let ether = U256::exp10(18);
+    let hinted = ArbPair {
+        address: Address::random(),
+        weth_reserve: ether * 100,
+        token_reserve: ether * 110,
+    };
+    let twin = ArbPair {
+        address: Address::random(),
+        weth_reserve: ether * 100,
+        token_reserve: ether * 100,
+    };
+
+    // The token is cheaper in the hinted pair after the swap, so it's bought there
+    let arb = plan_arb(weth, token, [hinted, twin], ether * 10).unwrap();
+    assert_eq!(arb.buy.pair, hinted.address);
+    assert_eq!(arb.sell.pair, twin.address);
+    assert_eq!(arb.buy.amount_out, arb.sell.amount_in);
+    assert_eq!(arb.revenue, arb.sell.amount_out - arb.buy.amount_in);
+    assert!(arb.buy.amount_in <= ether * 10);
+    assert!(!arb.revenue.is_zero());
+
+    // Inventory caps the size, and pairs at the same price have nothing to take
+    let capped = plan_arb(weth, token, [hinted, twin], ether).unwrap();
+    assert!(capped.buy.amount_in <= ether);
+    assert!(ether - capped.buy.amount_in < U256::from(10));
+    assert!(plan_arb(weth, token, [twin, twin], ether * 10).is_none());
+
+    // The bribe must leave the revenue above the gas
+    let base_fee = U256::exp10(9) * 30;
+    let priority_fee = backrun_bribe(arb.revenue, base_fee, 5_000).unwrap();
+    assert!(!priority_fee.is_zero());
+    assert!(backrun_bribe(U256::exp10(9), base_fee, 5_000).is_none());
+}
+
+#[test]
+fn test_rebate_accounting() {
+    let (rebate, kept) = split_rebate(U256::from(1_000), 90);
+    assert_eq!(rebate, U256::from(900));
+    assert_eq!(kept, U256::from(100));
+
+    // The refunded part of the bribe moves from the gas to the rebates
+    let accounting = Accounting::new();
+    let settlement = Settlement {
+        both_legs: true,
+        weth_spent: 100.into(),
+        weth_received: 1_200.into(),
+        gas: 1_100.into(),
+        backrun_gas: Some(PaidGas {
+            gas_used: 10.into(),
+            effective_gas_price: 100.into(),
+            base_fee: 0.into(),
+        }),
+        ..Default::default()
+    };
+    let fees = FeeEstimate {
+        refund_percent: 90,
+        ..Default::default()
+    };
+    accounting.reconcile(Address::random(), 1_100.into(), &fees, &settlement);
+    let pending = accounting.pending_snapshot();
+    assert_eq!(pending.rebates, U256::from(900));
+    assert_eq!(pending.gas, U256::from(200));
+    assert_eq!(pending.net_profit(), U256::zero());
+}
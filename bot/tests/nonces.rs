@@ -0,0 +1,86 @@
+use ethers::prelude::*;
+
+use subway_rs::{nonces::*, pipeline::Candidate};
+
+fn victim(from: Address, nonce: u64) -> Candidate {
+    Candidate::new(Transaction {
+        hash: H256::random(),
+        from,
+        nonce: U256::from(nonce),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_check_nonce() {
+    assert_eq!(check_nonce(U256::from(5), U256::from(5)), NonceCheck::Ready);
+    assert_eq!(check_nonce(U256::from(4), U256::from(5)), NonceCheck::Used);
+    assert_eq!(
+        check_nonce(U256::from(8), U256::from(5)),
+        NonceCheck::Gap {
+            missing: U256::from(3)
+        }
+    );
+}
+
+#[test]
+fn test_release_closed_gaps() {
+    let sender = Address::random();
+    let mut gaps = NonceGaps::new(8);
+    let (first, second) = (victim(sender, 6), victim(sender, 7));
+    assert!(gaps.hold(first.clone(), U64::from(100)).is_none());
+    assert!(gaps.hold(second.clone(), U64::from(100)).is_none());
+    assert_eq!(gaps.senders(), vec![sender]);
+
+    // The gap is still open
+    assert!(gaps.release(sender, U256::from(5)).is_none());
+    assert_eq!(gaps.len(), 2);
+
+    // The earlier transaction landed, so the first victim is next
+    let released = gaps.release(sender, U256::from(6)).unwrap();
+    assert_eq!(released.tx.hash, first.tx.hash);
+    assert_eq!(gaps.len(), 1);
+
+    // The second victim's nonce was used by a replacement
+    assert!(gaps.release(sender, U256::from(8)).is_none());
+    assert!(gaps.is_empty());
+    assert!(gaps.senders().is_empty());
+}
+
+#[test]
+fn test_replace_held_victim() {
+    let sender = Address::random();
+    let mut gaps = NonceGaps::new(8);
+    gaps.hold(victim(sender, 3), U64::from(1));
+    let bumped = victim(sender, 3);
+    gaps.hold(bumped.clone(), U64::from(1));
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(
+        gaps.release(sender, U256::from(3)).unwrap().tx.hash,
+        bumped.tx.hash
+    );
+}
+
+#[test]
+fn test_hold_capacity() {
+    let mut gaps = NonceGaps::new(1);
+    assert!(gaps
+        .hold(victim(Address::random(), 1), U64::from(1))
+        .is_none());
+    assert!(gaps
+        .hold(victim(Address::random(), 1), U64::from(1))
+        .is_some());
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(gaps.senders().len(), 1);
+}
+
+#[test]
+fn test_expire_held_victims() {
+    let mut gaps = NonceGaps::new(8);
+    gaps.hold(victim(Address::random(), 1), U64::from(10));
+    gaps.hold(victim(Address::random(), 1), U64::from(12));
+    assert_eq!(gaps.expire(U64::from(10 + MAX_NONCE_GAP_BLOCKS)), 0);
+    assert_eq!(gaps.expire(U64::from(11 + MAX_NONCE_GAP_BLOCKS)), 1);
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(gaps.senders().len(), 1);
+}
@@ -0,0 +1,8 @@
+use subway_rs::notify::*;
+
+#[test]
+fn test_webhook_payload() {
+    let payload = webhook_payload("paused");
+    assert_eq!(payload["text"], "paused");
+    assert_eq!(payload["content"], "paused");
+}
@@ -111,20 +111,29 @@ fn test_calculate_sandwich_context() {
                 a_reserves: weth_reserves,
                 b_reserves: token_reserves,
             },
-            frontrun_state: numeric::PoolState {
-                variable: U256::from(285),
-                new_a_reserves: U256::from(1400),
-                new_b_reserves: U256::from(715),
+            frontrun: numeric::SwapOutcome {
+                amount_in: optimal_weth_in,
+                amount_out: U256::from(285),
+                reserves_after: numeric::ReserveState {
+                    a_reserves: U256::from(1400),
+                    b_reserves: U256::from(715),
+                },
             },
-            target_state: numeric::PoolState {
-                variable: U256::from(10),
-                new_a_reserves: U256::from(1420),
-                new_b_reserves: U256::from(705),
+            victim: numeric::SwapOutcome {
+                amount_in: user_amount_in,
+                amount_out: U256::from(10),
+                reserves_after: numeric::ReserveState {
+                    a_reserves: U256::from(1420),
+                    b_reserves: U256::from(705),
+                },
             },
-            backrun_state: numeric::PoolState {
-                variable: U256::from(407),
-                new_a_reserves: U256::from(990),
-                new_b_reserves: U256::from(1013),
+            backrun: numeric::SwapOutcome {
+                amount_in: U256::from(285),
+                amount_out: U256::from(407),
+                reserves_after: numeric::ReserveState {
+                    a_reserves: U256::from(1013),
+                    b_reserves: U256::from(990),
+                },
             },
         }
     );
@@ -168,21 +177,186 @@ fn test_calculate_sandwich_context_optimal() {
                 a_reserves: weth_reserves,
                 b_reserves: token_reserves,
             },
-            frontrun_state: numeric::PoolState {
-                variable: U256::from(286),
-                new_a_reserves: U256::from(1402),
-                new_b_reserves: U256::from(714),
+            frontrun: numeric::SwapOutcome {
+                amount_in: optimal_weth_in,
+                amount_out: U256::from(286),
+                reserves_after: numeric::ReserveState {
+                    a_reserves: U256::from(1402),
+                    b_reserves: U256::from(714),
+                },
             },
-            target_state: numeric::PoolState {
-                variable: U256::from(10),
-                new_a_reserves: U256::from(1422),
-                new_b_reserves: U256::from(704),
+            victim: numeric::SwapOutcome {
+                amount_in: user_amount_in,
+                amount_out: U256::from(10),
+                reserves_after: numeric::ReserveState {
+                    a_reserves: U256::from(1422),
+                    b_reserves: U256::from(704),
+                },
             },
-            backrun_state: numeric::PoolState {
-                variable: U256::from(409),
-                new_a_reserves: U256::from(990),
-                new_b_reserves: U256::from(1013),
+            backrun: numeric::SwapOutcome {
+                amount_in: U256::from(286),
+                amount_out: U256::from(409),
+                reserves_after: numeric::ReserveState {
+                    a_reserves: U256::from(1013),
+                    b_reserves: U256::from(990),
+                },
             },
         }
     );
 }
+
+#[test]
+fn test_calculate_constrained_sandwich() {
+    let user_amount_in = U256::from(20);
+    let user_min_recv = U256::from(10);
+    let weth_reserves = U256::from(1000);
+    let token_reserves = U256::from(1000);
+
+    // A loose constraint matches the unconstrained optimum
+    let ctx = numeric::calculate_constrained_sandwich(
+        &U256::from(10_000),
+        &user_amount_in,
+        &user_min_recv,
+        &weth_reserves,
+        &token_reserves,
+    )
+    .unwrap();
+    assert_eq!(ctx.optimal_weth_in, U256::from(402));
+    assert_eq!(ctx.revenue, U256::from(7));
+
+    // A binding constraint still captures partial revenue
+    let ctx = numeric::calculate_constrained_sandwich(
+        &U256::from(200),
+        &user_amount_in,
+        &user_min_recv,
+        &weth_reserves,
+        &token_reserves,
+    )
+    .unwrap();
+    assert_eq!(ctx.optimal_weth_in, U256::from(200));
+    assert_eq!(ctx.revenue, U256::from(4));
+}
+
+#[test]
+fn test_ternary_search() {
+    // A concave objective peaking at 42
+    let objective = |x: U256| -> U256 {
+        let x = x.as_u64() as i64;
+        U256::from((10_000 - (x - 42) * (x - 42)).max(0) as u64)
+    };
+    assert_eq!(
+        numeric::ternary_search(U256::zero(), U256::from(100), objective),
+        U256::from(42)
+    );
+    assert_eq!(
+        numeric::ternary_search(U256::zero(), U256::from(10), objective),
+        U256::from(10)
+    );
+}
+
+#[cfg(feature = "fast-math")]
+#[test]
+fn test_fast_math_matches_reference() {
+    use subway_rs::fast_math;
+
+    let weth_reserves = ethers::utils::parse_ether("1000").unwrap();
+    let token_reserves = ethers::utils::parse_ether("2000000").unwrap();
+    for victim in ["1", "10", "50"] {
+        let user_amount_in = ethers::utils::parse_ether(victim).unwrap();
+        let (expected_out, _, _) = subway_rs::uniswap::get_univ2_data_given_in(
+            &user_amount_in,
+            &weth_reserves,
+            &token_reserves,
+        );
+        let user_min_recv = expected_out * 95 / 100;
+        assert_eq!(
+            fast_math::calculate_sandwich_optimal_in(
+                &user_amount_in,
+                &user_min_recv,
+                &weth_reserves,
+                &token_reserves,
+            ),
+            numeric::reference_sandwich_optimal_in(
+                &user_amount_in,
+                &user_min_recv,
+                &weth_reserves,
+                &token_reserves,
+            )
+        );
+    }
+
+    let value = U256::MAX - 1;
+    assert_eq!(fast_math::to_ethers(&fast_math::from_ethers(&value)), value);
+}
+
+#[test]
+fn test_calculate_capital_efficient_sandwich() {
+    let user_amount_in = ethers::utils::parse_ether("20").unwrap();
+    let user_min_recv = ethers::utils::parse_ether("10").unwrap();
+    let weth_reserves = ethers::utils::parse_ether("1000").unwrap();
+    let token_reserves = ethers::utils::parse_ether("1000").unwrap();
+    let optimal_weth_in = numeric::calculate_sandwich_optimal_in(
+        &user_amount_in,
+        &user_min_recv,
+        &weth_reserves,
+        &token_reserves,
+    );
+    let max_profit = numeric::calculate_sandwich_context(
+        &optimal_weth_in,
+        &user_amount_in,
+        &user_min_recv,
+        &weth_reserves,
+        &token_reserves,
+    )
+    .unwrap();
+
+    // Half the most revenue for a better return on a smaller frontrun
+    let min_revenue = max_profit.revenue / 2;
+    let ctx = numeric::calculate_capital_efficient_sandwich(
+        &min_revenue,
+        &user_amount_in,
+        &user_min_recv,
+        &weth_reserves,
+        &token_reserves,
+    )
+    .unwrap();
+    assert!(ctx.revenue >= min_revenue);
+    assert!(ctx.optimal_weth_in < max_profit.optimal_weth_in);
+    assert!(
+        numeric::return_on_capital(&ctx.revenue, &ctx.optimal_weth_in)
+            >= numeric::return_on_capital(&max_profit.revenue, &max_profit.optimal_weth_in)
+    );
+
+    // No sandwich makes more than the most revenue
+    assert!(numeric::calculate_capital_efficient_sandwich(
+        &(max_profit.revenue + 1),
+        &user_amount_in,
+        &user_min_recv,
+        &weth_reserves,
+        &token_reserves,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_sandwich_context_serde() {
+    let ctx = numeric::calculate_sandwich_context(
+        &U256::from(400),
+        &U256::from(20),
+        &U256::from(10),
+        &U256::from(1000),
+        &U256::from(1000),
+    )
+    .unwrap();
+
+    let json = serde_json::to_value(ctx).unwrap();
+    assert_eq!(json["victim"]["amount_out"], serde_json::json!("0xa"));
+    assert_eq!(
+        json["backrun"]["reserves_after"]["a_reserves"],
+        serde_json::json!("0x3f5")
+    );
+    assert_eq!(
+        serde_json::from_value::<numeric::SandwichContext>(json).unwrap(),
+        ctx
+    );
+}
@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+
+use subway_rs::observer::*;
+
+#[test]
+fn test_relay_failures() {
+    let observer = ObserverMode::new(3, Duration::from_secs(30));
+    assert!(!observer.is_observing());
+    assert!(observer.should_submit(Instant::now()));
+
+    assert_eq!(observer.relay_failed(), None);
+    assert_eq!(observer.relay_failed(), None);
+    assert_eq!(
+        observer.relay_failed(),
+        Some(ObserverTransition::Entered(Degradation::RelayUnreachable))
+    );
+    assert_eq!(observer.reason(), Some(Degradation::RelayUnreachable));
+
+    // Already observing
+    assert_eq!(observer.relay_failed(), None);
+    assert_eq!(observer.signer_failed(), None);
+
+    assert_eq!(
+        observer.succeeded(),
+        Some(ObserverTransition::Recovered(Degradation::RelayUnreachable))
+    );
+    assert!(!observer.is_observing());
+    assert_eq!(observer.succeeded(), None);
+
+    // A success resets the failure count
+    observer.relay_failed();
+    observer.relay_failed();
+    observer.succeeded();
+    assert_eq!(observer.relay_failed(), None);
+}
+
+#[test]
+fn test_observer_probes() {
+    let observer = ObserverMode::new(1, Duration::from_secs(30));
+    assert_eq!(
+        observer.signer_failed(),
+        Some(ObserverTransition::Entered(Degradation::SignerUnavailable))
+    );
+
+    // No probe until the interval passes, then only one per interval
+    let now = Instant::now();
+    assert!(!observer.should_submit(now));
+    let later = now + Duration::from_secs(31);
+    assert!(observer.should_submit(later));
+    assert!(!observer.should_submit(later + Duration::from_secs(1)));
+    assert!(observer.should_submit(later + Duration::from_secs(31)));
+}
@@ -0,0 +1,81 @@
+use std::time::Instant;
+
+use ethers::prelude::*;
+use subway_rs::{opportunity::*, simulation::StateSnapshot};
+
+fn opportunity() -> Opportunity {
+    let weth = Address::random();
+    let token = Address::random();
+    let victim = Transaction {
+        value: U256::from(1_000),
+        ..Default::default()
+    };
+    Opportunity::new(
+        victim,
+        DecodedSwap {
+            amount_out_min: U256::from(900),
+            path: vec![weth, token],
+            to: Address::random(),
+            deadline: U256::from(1_700_000_000u64),
+        },
+        Address::random(),
+        StateSnapshot {
+            block: U64::from(10),
+            reserves: (U256::from(100_000), U256::from(200_000)),
+        },
+        U256::from(900),
+        Instant::now(),
+    )
+}
+
+#[test]
+fn test_opportunity_stages() {
+    let mut opportunity = opportunity();
+    assert_eq!(opportunity.token, opportunity.swap.path[1]);
+    assert_eq!(opportunity.weth_reserves(), U256::from(100_000));
+    assert_eq!(opportunity.token_reserves(), U256::from(200_000));
+    assert_eq!(opportunity.user_amount_in(), U256::from(1_000));
+    assert!(opportunity.timestamps.seen > 0);
+    assert!(opportunity.timestamps.planned.is_none());
+
+    opportunity.plan(BundlePlan {
+        target_block: U64::from(11),
+        nonce: U256::from(7),
+        ..Default::default()
+    });
+    assert!(opportunity.timestamps.planned.is_some());
+    assert_eq!(opportunity.plan.unwrap().nonce, U256::from(7));
+}
+
+#[test]
+fn test_opportunity_store_roundtrip() {
+    let path = std::env::temp_dir().join(format!("opportunities-{:x}.jsonl", H64::random()));
+    let store = OpportunityStore::open(&path).unwrap();
+    let first = opportunity();
+    let second = opportunity();
+    store.append(&first).unwrap();
+    store.append(&second).unwrap();
+
+    let loaded = OpportunityStore::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, vec![first, second]);
+}
+
+#[test]
+fn test_opportunity_store_skips_malformed() {
+    let path = std::env::temp_dir().join(format!("opportunities-{:x}.jsonl", H64::random()));
+    let store = OpportunityStore::open(&path).unwrap();
+    let first = opportunity();
+    store.append(&first).unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    std::io::Write::write_all(&mut file, b"{\"victim\":\n").unwrap();
+    let second = opportunity();
+    store.append(&second).unwrap();
+
+    let loaded = OpportunityStore::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, vec![first, second]);
+}
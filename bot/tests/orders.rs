@@ -0,0 +1,134 @@
+use ethers::prelude::*;
+use std::str::FromStr;
+
+use subway_rs::{
+    executor::{Executor, MulticallExecutor},
+    orders::*,
+};
+
+fn order() -> Order {
+    Order {
+        source: OrderSource::UniswapX,
+        id: String::from("0x01"),
+        owner: Address::random(),
+        // WETH -> USDC
+        sell_token: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+        buy_token: Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+        sell_amount: U256::from(100),
+        min_buy_amount: U256::from(80),
+        deadline: 1_000,
+        encoded_order: Some(Bytes::from(vec![1u8, 2, 3])),
+        signature: Some(Bytes::from(vec![4u8, 5, 6])),
+        decay: None,
+    }
+}
+
+#[test]
+fn test_order_sources_from_str() {
+    let sources = OrderSources::from_str("uniswapx, UniswapX").unwrap();
+    assert_eq!(
+        sources.0,
+        vec![OrderSource::UniswapX, OrderSource::UniswapX]
+    );
+    assert!(OrderSources::from_str("").unwrap().0.is_empty());
+    assert!(OrderSources::from_str("uniswapx,cow").is_err());
+}
+
+#[test]
+fn test_evaluate_uniswapx_order() {
+    let order = order();
+
+    // Deep reserves give ~99 out, beating the minimum of 80
+    let plan = evaluate_order(&order, &U256::from(1_000_000), &U256::from(1_000_000), 0)
+        .unwrap()
+        .unwrap();
+    assert_eq!(plan.pool_amount_out, U256::from(99));
+    assert_eq!(plan.surplus, U256::from(19));
+    assert_eq!(
+        plan.pair,
+        Address::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap()
+    );
+    assert_eq!(
+        plan.fill.reactor,
+        Address::from_str(UNISWAPX_REACTOR).unwrap()
+    );
+    assert_eq!(
+        &plan.fill.execute.as_ref()[0..4],
+        &ethers::utils::id("execute((bytes,bytes))")
+    );
+
+    // The executor pays the minimum and swaps the whole input back into the output token
+    assert_eq!(plan.fill.output_token, order.buy_token);
+    assert_eq!(plan.fill.output_amount, U256::from(80));
+    assert_eq!(plan.fill.rebalance.pair, plan.pair);
+    assert_eq!(plan.fill.rebalance.amount_in, U256::from(100));
+    assert_eq!(plan.fill.rebalance.amount_out, U256::from(99));
+    let executor = MulticallExecutor::new(Address::random());
+    let calldata = executor.encode_order_fill(&plan.fill).unwrap();
+    assert_eq!(
+        &calldata.as_ref()[0..4],
+        &ethers::utils::id(MulticallExecutor::MULTICALL_SIGNATURE)
+    );
+
+    // Shallow reserves can't beat the minimum
+    assert!(
+        evaluate_order(&order, &U256::from(200), &U256::from(200), 0)
+            .unwrap()
+            .is_none()
+    );
+
+    // Expired orders are skipped
+    assert!(evaluate_order(
+        &order,
+        &U256::from(1_000_000),
+        &U256::from(1_000_000),
+        1_001
+    )
+    .unwrap()
+    .is_none());
+}
+
+#[test]
+fn test_dutch_decay() {
+    let order = Order {
+        decay: Some(DutchDecay {
+            start_time: 100,
+            end_time: 200,
+            sell_start_amount: U256::from(100),
+            buy_start_amount: U256::from(120),
+        }),
+        ..order()
+    };
+
+    // The output decays from its start to its end amount, rounding down
+    assert_eq!(order.amounts_at(50), (U256::from(100), U256::from(120)));
+    assert_eq!(order.amounts_at(133), (U256::from(100), U256::from(107)));
+    assert_eq!(order.amounts_at(200), (U256::from(100), U256::from(80)));
+    assert_eq!(order.amounts_at(300), (U256::from(100), U256::from(80)));
+
+    // Increasing amounts round up
+    let exact_output = Order {
+        decay: Some(DutchDecay {
+            sell_start_amount: U256::from(90),
+            buy_start_amount: U256::from(80),
+            ..order.decay.unwrap()
+        }),
+        ..order.clone()
+    };
+    assert_eq!(
+        exact_output.amounts_at(133),
+        (U256::from(94), U256::from(80))
+    );
+
+    // Early in the decay the order asks more than the pair gives, later it doesn't
+    let reserves = U256::from(1_000_000);
+    assert!(evaluate_order(&order, &reserves, &reserves, 110)
+        .unwrap()
+        .is_none());
+    let plan = evaluate_order(&order, &reserves, &reserves, 160)
+        .unwrap()
+        .unwrap();
+    assert_eq!(plan.buy_amount, U256::from(96));
+    assert_eq!(plan.fill.output_amount, U256::from(96));
+    assert_eq!(plan.surplus, U256::from(3));
+}
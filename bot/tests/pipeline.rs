@@ -0,0 +1,131 @@
+use std::time::{Duration, Instant};
+
+use ethers::prelude::*;
+use subway_rs::pipeline::*;
+
+#[test]
+fn test_staleness_guard() {
+    let guard = StalenessGuard::new(DEFAULT_CANDIDATE_BUDGET_MS);
+    let candidate = Candidate::new(Transaction::default());
+    let start = candidate.received_at;
+
+    assert!(guard
+        .check(
+            &candidate,
+            10.into(),
+            10.into(),
+            start + Duration::from_millis(800)
+        )
+        .is_ok());
+    assert_eq!(
+        guard.check(
+            &candidate,
+            10.into(),
+            10.into(),
+            start + Duration::from_millis(801)
+        ),
+        Err(Staleness::BudgetExceeded(Duration::from_millis(801)))
+    );
+
+    let stale = guard.check(&candidate, 10.into(), 11.into(), Instant::now());
+    assert_eq!(
+        stale,
+        Err(Staleness::NewBlock {
+            snapshot: 10.into(),
+            current: 11.into()
+        })
+    );
+    assert_eq!(stale.unwrap_err().metric(), "stale_new_block");
+}
+
+fn candidate(value: u64) -> Candidate {
+    Candidate::new(Transaction {
+        value: value.into(),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_shedding_queue() {
+    let mut queue = SheddingQueue::new(2);
+    assert!(queue.push(candidate(5), 5.into()).is_none());
+    assert!(queue.push(candidate(1), 1.into()).is_none());
+
+    // Full: a lower score than everything queued is shed itself
+    let shed = queue.push(candidate(0), 0.into()).unwrap();
+    assert_eq!(shed.tx.value, U256::zero());
+
+    // Full: a higher score evicts the lowest
+    let shed = queue.push(candidate(9), 9.into()).unwrap();
+    assert_eq!(shed.tx.value, U256::from(1));
+    assert_eq!(queue.len(), 2);
+
+    assert_eq!(queue.pop().unwrap().tx.value, U256::from(9));
+    assert_eq!(queue.pop().unwrap().tx.value, U256::from(5));
+    assert!(queue.pop().is_none());
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_prefilter_score() {
+    let router = Address::random();
+    let path = vec![Address::random(), Address::random()];
+    let call = subway_rs::abi::SwapExactETHForTokensCall {
+        amount_out_min: 1.into(),
+        path: path.clone(),
+        to: Address::random(),
+        deadline: U256::MAX,
+    };
+    let tx = Transaction {
+        to: Some(router),
+        value: 42.into(),
+        input: ethers::abi::AbiEncode::encode(call).into(),
+        ..Default::default()
+    };
+    assert_eq!(prefilter_score(&tx, router), Some(U256::from(42)));
+    assert_eq!(swap_token(&tx), Some(path[1]));
+    assert_eq!(prefilter_score(&tx, Address::random()), None);
+
+    let undecodable = Transaction {
+        input: Bytes::from(vec![0u8; 4]),
+        ..tx
+    };
+    assert_eq!(prefilter_score(&undecodable, router), None);
+    assert_eq!(swap_token(&undecodable), None);
+}
+
+#[tokio::test]
+async fn test_routing_table() {
+    let (router, strategy) = (Address::random(), Address::random());
+    let mut routes = RoutingTable::new();
+    routes.sandwich(router, score_v2_swap);
+    let mut diverted = routes.divert(&[strategy], 1);
+    assert_eq!(routes.len(), 2);
+
+    let to = |target: Option<Address>| Transaction {
+        to: target,
+        ..Default::default()
+    };
+    assert!(matches!(
+        routes.route(&to(Some(router))),
+        Some(Route::Sandwich(_))
+    ));
+    assert!(routes.route(&to(Some(Address::random()))).is_none());
+    assert!(routes.route(&to(None)).is_none());
+
+    match routes.route(&to(Some(strategy))) {
+        Some(Route::Divert(sender)) => sender.try_send(candidate(1)).unwrap(),
+        _ => panic!("expected a diverted route"),
+    }
+    assert_eq!(diverted.recv().await.unwrap().tx.value, U256::one());
+}
+
+#[test]
+fn test_candidate_rebuild() {
+    let first = candidate(5);
+    let rebuilt = first.rebuild();
+    assert_eq!(rebuilt.tx.value, U256::from(5));
+    assert_eq!(rebuilt.rebuilds, 1);
+    assert!(rebuilt.received_at >= first.received_at);
+    assert_eq!(rebuilt.rebuild().rebuilds, 2);
+}
@@ -0,0 +1,206 @@
+use ethers::prelude::*;
+
+use subway_rs::{
+    evaluate::SandwichPlan, executor::*, numeric, opportunity::unix_millis, plans::*,
+    transactions::*,
+};
+
+fn plan(executor: Address) -> RemotePlan {
+    let weth = Address::random();
+    let token = Address::random();
+    let pair = Address::random();
+    let victim = Bytes::from(vec![0x02, 0xde, 0xad]);
+    let context = numeric::calculate_sandwich_context(
+        &U256::exp10(18),
+        &U256::exp10(19),
+        &U256::zero(),
+        &U256::exp10(21),
+        &U256::exp10(24),
+    )
+    .unwrap();
+    let sandwich = SandwichPlan {
+        victim: H256::from(ethers::utils::keccak256(&victim)),
+        pair,
+        token,
+        user_min_recv: U256::zero(),
+        context,
+        frontrun: SwapLeg {
+            token_in: weth,
+            token_out: token,
+            pair,
+            amount_in: context.optimal_weth_in,
+            amount_out: context.frontrun.amount_out,
+        },
+        backrun: SwapLeg {
+            token_in: token,
+            token_out: weth,
+            pair,
+            amount_in: context.frontrun.amount_out,
+            amount_out: context.backrun.amount_out,
+        },
+    };
+    let fees = LegFees::base_fee_only(U256::from(10));
+    RemotePlan::new(1, executor, U64::from(100), sandwich, victim, (fees, fees))
+}
+
+#[tokio::test]
+async fn test_verify_signed_plan() {
+    let detector = LocalWallet::new(&mut rand::thread_rng());
+    let executor = Address::random();
+    let plan = plan(executor);
+    assert!(plan.is_consistent());
+    let signed = plan.sign(&detector).await.unwrap();
+    assert_eq!(signed.signer().unwrap(), detector.address());
+
+    let verifier = PlanVerifier::new(vec![detector.address()], 1, executor);
+    assert_eq!(verifier.verify(&signed, unix_millis()).unwrap(), plan);
+
+    // Each plan is only executed once
+    let replayed = verifier.verify(&signed, unix_millis()).unwrap_err();
+    assert_eq!(replayed, PlanRejection::Replayed(plan.id));
+    assert_eq!(replayed.metric(), "plan_replayed");
+}
+
+#[tokio::test]
+async fn test_refuse_untrusted_or_tampered_plans() {
+    let detector = LocalWallet::new(&mut rand::thread_rng());
+    let impostor = LocalWallet::new(&mut rand::thread_rng());
+    let executor = Address::random();
+    let verifier = PlanVerifier::new(vec![detector.address()], 1, executor);
+
+    let signed = plan(executor).sign(&impostor).await.unwrap();
+    assert_eq!(
+        verifier.verify(&signed, unix_millis()),
+        Err(PlanRejection::Untrusted(impostor.address()))
+    );
+
+    // Changing the plan after signing recovers a different signer
+    let mut tampered = plan(executor).sign(&detector).await.unwrap();
+    tampered.plan = tampered
+        .plan
+        .replacen("\"chain_id\":1", "\"chain_id\":5", 1);
+    let rejection = verifier.verify(&tampered, unix_millis()).unwrap_err();
+    assert!(matches!(rejection, PlanRejection::Untrusted(_)));
+    assert_eq!(rejection.metric(), "plan_untrusted");
+}
+
+#[tokio::test]
+async fn test_refuse_plans_for_elsewhere_or_expired() {
+    let detector = LocalWallet::new(&mut rand::thread_rng());
+    let executor = Address::random();
+    let verifier = PlanVerifier::new(vec![detector.address()], 1, executor);
+
+    let other = plan(Address::random()).sign(&detector).await.unwrap();
+    assert!(matches!(
+        verifier.verify(&other, unix_millis()),
+        Err(PlanRejection::WrongTarget { chain_id: 1, .. })
+    ));
+
+    let plan = plan(executor);
+    let signed = plan.sign(&detector).await.unwrap();
+    assert_eq!(
+        verifier.verify(&signed, plan.expires_at + 1),
+        Err(PlanRejection::Expired(plan.expires_at))
+    );
+}
+
+#[tokio::test]
+async fn test_refuse_inconsistent_or_oversized_plans() {
+    let detector = LocalWallet::new(&mut rand::thread_rng());
+    let executor = Address::random();
+    let verifier = PlanVerifier::new(vec![detector.address()], 1, executor)
+        .with_max_weth_in(Some(U256::exp10(17)));
+
+    // The raw victim must be the planned one
+    let mut swapped = plan(executor);
+    swapped.victim = Bytes::from(vec![0x02, 0xbe, 0xef]);
+    assert!(!swapped.is_consistent());
+    let signed = swapped.sign(&detector).await.unwrap();
+    assert_eq!(
+        verifier.verify(&signed, unix_millis()),
+        Err(PlanRejection::Inconsistent)
+    );
+
+    // The backrun must sell what the frontrun bought
+    let mut redirected = plan(executor);
+    redirected.sandwich.backrun.token_out = Address::random();
+    assert!(!redirected.is_consistent());
+
+    let plan = plan(executor);
+    let signed = plan.sign(&detector).await.unwrap();
+    let rejection = verifier.verify(&signed, unix_millis()).unwrap_err();
+    assert_eq!(
+        rejection,
+        PlanRejection::OverLimit {
+            weth_in: plan.sandwich.frontrun.amount_in,
+            max: U256::exp10(17),
+        }
+    );
+    assert_eq!(rejection.metric(), "plan_refused");
+}
+
+#[tokio::test]
+async fn test_sign_plan_legs() {
+    let executor = Address::random();
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let factory = TxFactory::new(
+        ExecutorKind::Multicall.build(executor),
+        ExecutionMode::Inventory,
+        wallet,
+        1,
+    )
+    .unwrap();
+    let plan = plan(executor);
+    let signed = sign_plan(&factory, &plan, NonceSlot::from(U256::from(7)))
+        .await
+        .unwrap();
+    assert_eq!(signed.len(), 3);
+    assert_eq!(signed[1], plan.victim);
+
+    let frontrun = rlp::Rlp::new(&signed[0][1..]);
+    let backrun = rlp::Rlp::new(&signed[2][1..]);
+    assert_eq!(frontrun.val_at::<U256>(1).unwrap(), U256::from(7));
+    assert_eq!(backrun.val_at::<U256>(1).unwrap(), U256::from(8));
+}
+
+#[tokio::test]
+async fn test_refuse_losing_or_expensive_plans() {
+    let detector = LocalWallet::new(&mut rand::thread_rng());
+    let executor = Address::random();
+    let verifier = PlanVerifier::new(vec![detector.address()], 1, executor)
+        .with_max_fee_per_gas(Some(U256::from(100)))
+        .with_max_bribe(Some(U256::from(1_000)));
+
+    // The backrun must return what the frontrun spent
+    let mut losing = plan(executor);
+    losing.sandwich.backrun.amount_out = losing.sandwich.frontrun.amount_in - 1;
+    let signed = losing.sign(&detector).await.unwrap();
+    assert_eq!(
+        verifier.verify(&signed, unix_millis()),
+        Err(PlanRejection::Unprofitable {
+            weth_spent: losing.sandwich.frontrun.amount_in,
+            weth_out: losing.sandwich.frontrun.amount_in - 1,
+        })
+    );
+
+    let mut overpaying = plan(executor);
+    overpaying.frontrun_fees.max_fee_per_gas = U256::from(101);
+    let signed = overpaying.sign(&detector).await.unwrap();
+    assert!(matches!(
+        verifier.verify(&signed, unix_millis()),
+        Err(PlanRejection::FeeOverLimit { .. })
+    ));
+
+    let mut bribing = plan(executor);
+    bribing.backrun_fees.max_priority_fee_per_gas = U256::from(1_000);
+    let signed = bribing.sign(&detector).await.unwrap();
+    let rejection = verifier.verify(&signed, unix_millis()).unwrap_err();
+    assert_eq!(
+        rejection,
+        PlanRejection::BribeOverLimit {
+            bribe: bribing.bribe(),
+            max: U256::from(1_000),
+        }
+    );
+    assert_eq!(rejection.metric(), "plan_refused");
+}
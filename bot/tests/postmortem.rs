@@ -0,0 +1,174 @@
+use ethers::prelude::*;
+
+use subway_rs::{abi::events::SWAP_TOPIC, postmortem::*};
+
+fn pair() -> Address {
+    Address::repeat_byte(0x11)
+}
+
+fn router() -> Address {
+    "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+        .parse()
+        .unwrap()
+}
+
+fn competitor() -> Address {
+    Address::repeat_byte(0xcc)
+}
+
+fn tx(index: u8, from: Address, to: Address, priority_fee: u64) -> Transaction {
+    Transaction {
+        hash: H256::repeat_byte(index + 1),
+        from,
+        to: Some(to),
+        transaction_index: Some(U64::from(index)),
+        transaction_type: Some(U64::from(2)),
+        max_priority_fee_per_gas: Some(U256::from(priority_fee)),
+        max_fee_per_gas: Some(U256::exp10(11)),
+        ..Default::default()
+    }
+}
+
+/// A V2 `Swap` on the pair, selling token0 when `zero_for_one`
+fn swap(index: u8, zero_for_one: bool) -> Log {
+    let amounts = if zero_for_one {
+        [U256::exp10(18), U256::zero(), U256::zero(), U256::exp10(9)]
+    } else {
+        [U256::zero(), U256::exp10(9), U256::exp10(18), U256::zero()]
+    };
+    let mut data = Vec::new();
+    for amount in amounts {
+        let mut bytes = [0u8; 32];
+        amount.to_big_endian(&mut bytes);
+        data.extend_from_slice(&bytes);
+    }
+    Log {
+        address: pair(),
+        topics: vec![
+            H256::from(SWAP_TOPIC),
+            H256::from(router()),
+            H256::from(Address::repeat_byte(0x22)),
+        ],
+        data: Bytes::from(data),
+        transaction_index: Some(U64::from(index)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_find_sandwich() {
+    let searcher = Address::repeat_byte(0xee);
+    let txs = vec![
+        tx(0, Address::repeat_byte(1), router(), 1),
+        tx(1, searcher, competitor(), 30),
+        tx(2, Address::repeat_byte(2), router(), 2),
+        tx(3, searcher, competitor(), 40),
+    ];
+    let logs = vec![swap(0, false), swap(1, true), swap(2, true), swap(3, false)];
+
+    let sightings = find_sandwiches(U64::from(100), &txs, &logs, U256::from(10));
+    assert_eq!(sightings.len(), 1);
+    let sighting = &sightings[0];
+    assert_eq!(sighting.block, U64::from(100));
+    assert_eq!(sighting.pair, pair());
+    assert_eq!(sighting.victim, txs[2].hash);
+    assert_eq!(sighting.frontrun, txs[1].hash);
+    assert_eq!(sighting.backrun, txs[3].hash);
+    assert_eq!(sighting.searcher, searcher);
+    assert_eq!(sighting.contract, competitor());
+    assert_eq!(sighting.priority_fee_per_gas, U256::from(40));
+    assert_eq!(sighting.our_priority_fee_per_gas, None);
+}
+
+#[test]
+fn test_ignore_unsandwiched_swaps() {
+    // Swaps the same way around the victim, and legs through different contracts
+    let txs = vec![
+        tx(0, Address::repeat_byte(1), competitor(), 30),
+        tx(1, Address::repeat_byte(2), router(), 2),
+        tx(2, Address::repeat_byte(1), competitor(), 40),
+        tx(3, Address::repeat_byte(3), Address::repeat_byte(0xdd), 50),
+    ];
+    let same_way = vec![swap(0, true), swap(1, true), swap(2, true)];
+    assert!(find_sandwiches(U64::one(), &txs, &same_way, U256::zero()).is_empty());
+
+    let other_contract = vec![swap(0, true), swap(1, true), swap(3, false)];
+    assert!(find_sandwiches(U64::one(), &txs, &other_contract, U256::zero()).is_empty());
+
+    let no_frontrun = vec![swap(1, true), swap(2, false)];
+    assert!(find_sandwiches(U64::one(), &txs, &no_frontrun, U256::zero()).is_empty());
+}
+
+#[test]
+fn test_priority_fee_per_gas() {
+    let base_fee = U256::exp10(10);
+
+    // Dynamic fee transactions pay their tip, capped by what's left of the max fee
+    let mut dynamic = tx(0, Address::zero(), router(), 5);
+    assert_eq!(priority_fee_per_gas(&dynamic, base_fee), U256::from(5));
+    dynamic.max_fee_per_gas = Some(base_fee + 2);
+    assert_eq!(priority_fee_per_gas(&dynamic, base_fee), U256::from(2));
+
+    // Legacy transactions pay everything above the base fee
+    let legacy = Transaction {
+        gas_price: Some(base_fee + 7),
+        ..Default::default()
+    };
+    assert_eq!(priority_fee_per_gas(&legacy, base_fee), U256::from(7));
+    assert_eq!(priority_fee_per_gas(&legacy, base_fee * 2), U256::zero());
+}
+
+#[test]
+fn test_competition_book() {
+    let book = CompetitionBook::new();
+    assert_eq!(book.priority_fee_percentile(50), None);
+
+    for fee in 1..=5u64 {
+        book.record(SandwichSighting {
+            contract: if fee % 2 == 0 { router() } else { competitor() },
+            priority_fee_per_gas: U256::from(fee * 10),
+            ..Default::default()
+        });
+    }
+    assert_eq!(book.sightings().len(), 5);
+    assert_eq!(book.priority_fee_percentile(0), Some(U256::from(10)));
+    assert_eq!(book.priority_fee_percentile(50), Some(U256::from(30)));
+    assert_eq!(book.priority_fee_percentile(100), Some(U256::from(50)));
+    assert_eq!(book.priority_fee_percentile(500), Some(U256::from(50)));
+
+    let losses = book.losses_by_contract();
+    assert_eq!(losses[&competitor()], 3);
+    assert_eq!(losses[&router()], 2);
+
+    // Bribes are raised to the median that beat us, up to the cap
+    assert_eq!(
+        book.calibrate(U256::from(20), U256::from(100)),
+        U256::from(30)
+    );
+    assert_eq!(
+        book.calibrate(U256::from(20), U256::from(25)),
+        U256::from(25)
+    );
+    assert_eq!(
+        book.calibrate(U256::from(40), U256::from(100)),
+        U256::from(40)
+    );
+    assert_eq!(
+        CompetitionBook::new().calibrate(U256::from(20), U256::from(100)),
+        U256::from(20)
+    );
+}
+
+#[test]
+fn test_competition_book_forgets_oldest() {
+    let book = CompetitionBook::new();
+    for i in 0..MAX_SIGHTINGS + 10 {
+        book.record(SandwichSighting {
+            block: U64::from(i),
+            ..Default::default()
+        });
+    }
+    let sightings = book.sightings();
+    assert_eq!(sightings.len(), MAX_SIGHTINGS);
+    assert_eq!(sightings[0].block, U64::from(10));
+}
@@ -0,0 +1,79 @@
+use ethers::prelude::*;
+use subway_rs::profiles::*;
+
+#[test]
+fn test_profile_boost() {
+    let book = ProfileBook::default();
+    let sender = Address::random();
+    let score = U256::from(10_000);
+
+    // Unknown and unprofitable senders aren't boosted
+    assert_eq!(book.boost(&sender, score), score);
+    book.record_shipped(sender);
+    book.record_profit(sender, U256::zero());
+    assert_eq!(book.boost(&sender, score), score);
+
+    book.record_profit(sender, U256::from(5));
+    assert_eq!(book.boost(&sender, score), U256::from(12_500));
+
+    // The boost is capped
+    for _ in 0..20 {
+        book.record_profit(sender, U256::from(5));
+    }
+    assert_eq!(book.boost(&sender, score), U256::from(30_000));
+
+    let profile = book.get(&sender).unwrap();
+    assert_eq!(profile.shipped, 1);
+    assert_eq!(profile.landed, 21);
+    assert_eq!(profile.profit, U256::from(105));
+    assert!(profile.last_seen > 0);
+}
+
+#[test]
+fn test_profiles_roundtrip() {
+    let path = std::env::temp_dir().join(format!("profiles-{:x}.json", H64::random()));
+    let book = ProfileBook::default();
+    assert_eq!(book.load(&path).unwrap(), 0);
+
+    let sender = Address::random();
+    book.record_shipped(sender);
+    book.record_profit(sender, U256::from(7));
+    book.save(&path).unwrap();
+
+    let loaded = ProfileBook::default();
+    assert_eq!(loaded.load(&path).unwrap(), 1);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.get(&sender), book.get(&sender));
+}
+
+#[test]
+fn test_profiles_ignore_invalid_file() {
+    let path = std::env::temp_dir().join(format!("profiles-{:x}.json", H64::random()));
+    std::fs::write(&path, b"{\"0x").unwrap();
+
+    let book = ProfileBook::default();
+    assert_eq!(book.load(&path).unwrap(), 0);
+
+    // The next save replaces it
+    let sender = Address::random();
+    book.record_profit(sender, U256::from(7));
+    book.save(&path).unwrap();
+    let loaded = ProfileBook::default();
+    assert_eq!(loaded.load(&path).unwrap(), 1);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_profiles_bounded() {
+    let book = ProfileBook::default();
+    for _ in 0..MAX_PROFILES {
+        book.record_shipped(Address::random());
+    }
+    assert_eq!(book.len(), MAX_PROFILES);
+
+    // A new sender evicts the least recently sandwiched in bulk
+    let sender = Address::random();
+    book.record_shipped(sender);
+    assert_eq!(book.len(), MAX_PROFILES - MAX_PROFILES / 8 + 1);
+    assert!(book.get(&sender).is_some());
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "relay")]
+
 use ethers::prelude::*;
 use ethers_flashbots::{BundleTransaction, SimulatedBundle, SimulatedTransaction};
 use hex::FromHex;
@@ -151,3 +153,106 @@ fn test_construct_bundle_signed_transactions() {
         BundleTransaction::Signed(tx) => assert_eq!(tx.hash, tx_hash),
     }
 }
+
+#[test]
+fn test_classify_relay_errors() {
+    use relayer::{RelayError, RelayReaction};
+    use std::time::Duration;
+
+    let limited = RelayError::classify("RelayError(reqwest::Error { kind: Status(429) })");
+    assert_eq!(limited, RelayError::RateLimited { retry_after: None });
+    assert_eq!(
+        limited.reaction(),
+        RelayReaction::Backoff(Duration::from_millis(relayer::DEFAULT_RELAY_BACKOFF_MS))
+    );
+    assert_eq!(
+        RelayError::classify("Too Many Requests, retry after 3 seconds"),
+        RelayError::RateLimited {
+            retry_after: Some(Duration::from_secs(3))
+        }
+    );
+    // Hints are capped
+    assert_eq!(
+        RelayError::classify("rate limited, Retry-After: 3600").reaction(),
+        RelayReaction::Backoff(Duration::from_millis(relayer::MAX_RELAY_BACKOFF_MS))
+    );
+
+    let stale = RelayError::classify("JsonRpcError { code: -32000, message: \"nonce too low\" }");
+    assert_eq!(stale, RelayError::NonceTooLow);
+    assert_eq!(stale.reaction(), RelayReaction::Rebuild);
+    assert_eq!(stale.metric(), "relay_error_nonce_too_low");
+
+    assert_eq!(
+        RelayError::classify("already known").reaction(),
+        RelayReaction::Drop
+    );
+    assert_eq!(
+        RelayError::classify("bundle too large"),
+        RelayError::BundleTooLarge
+    );
+    assert!(matches!(
+        RelayError::classify("execution reverted"),
+        RelayError::SimulationFailed(_)
+    ));
+    assert_eq!(
+        RelayError::classify("error trying to connect: dns error").reaction(),
+        RelayReaction::Degrade
+    );
+
+    // Hashes in the message aren't mistaken for status codes
+    assert!(matches!(
+        RelayError::classify(
+            "bundle 0x4290000000000000000000000000000000000000000000000000000000000413"
+        ),
+        RelayError::Other(_)
+    ));
+}
+
+#[test]
+fn test_relay_backoffs() {
+    use std::time::{Duration, Instant};
+
+    let mut backoffs = relayer::RelayBackoffs::new();
+    let now = Instant::now();
+    assert!(!backoffs.is_backing_off("flashbots", now));
+
+    backoffs.back_off("flashbots", now, Duration::from_secs(2));
+    assert!(backoffs.is_backing_off("flashbots", now + Duration::from_secs(1)));
+    assert!(!backoffs.is_backing_off("echo", now));
+
+    // A shorter pause doesn't cut a longer one short
+    backoffs.back_off("flashbots", now, Duration::from_secs(1));
+    assert!(backoffs.is_backing_off("flashbots", now + Duration::from_millis(1_500)));
+    assert!(!backoffs.is_backing_off("flashbots", now + Duration::from_secs(2)));
+}
+
+#[test]
+fn test_submitted_bundles() {
+    let mut submitted = relayer::SubmittedBundles::new();
+    let hash = H256::repeat_byte(1);
+    assert!(submitted.claim("flashbots", U64::from(10), hash));
+    assert!(!submitted.claim("flashbots", U64::from(10), hash));
+
+    // The same bundle may go to another relay, or target another block
+    assert!(submitted.claim("echo", U64::from(10), hash));
+    assert!(submitted.claim("flashbots", U64::from(11), hash));
+    assert_eq!(submitted.len(), 3);
+
+    // Old blocks are forgotten
+    let latest = U64::from(11 + relayer::SUBMISSION_HISTORY_BLOCKS);
+    assert!(submitted.claim("flashbots", latest, hash));
+    assert!(!submitted.contains("flashbots", U64::from(10), hash));
+    assert!(submitted.contains("flashbots", U64::from(11), hash));
+}
+
+#[test]
+fn test_claim_request_matches_bundle_hash() {
+    let signed_transactions = vec![Bytes::from(vec![0xc0]), Bytes::from(vec![0xc1, 0x80])];
+    let bundle = relayer::construct_bundle(signed_transactions.clone(), U64::from(7)).unwrap();
+    assert!(relayer::claim_request("golden", &bundle));
+    assert!(!relayer::claim_submission(
+        "golden",
+        U64::from(8),
+        subway_rs::simulation::bundle_hash(&signed_transactions)
+    ));
+}
@@ -0,0 +1,121 @@
+use ethers::prelude::*;
+
+use subway_rs::reserves::*;
+
+fn sample(block: u64, weth: u64, token: u64) -> ReserveSample {
+    ReserveSample {
+        block: U64::from(block),
+        weth: U256::from(weth),
+        token: U256::from(token),
+    }
+}
+
+#[test]
+fn test_price_move_bps() {
+    let base = (U256::from(100), U256::from(1_000));
+    assert_eq!(price_move_bps(base, base), 0);
+
+    // Scaling both reserves keeps the price
+    assert_eq!(
+        price_move_bps(base, (U256::from(200), U256::from(2_000))),
+        0
+    );
+
+    // Doubling the WETH reserve doubles the price, halving it halves it
+    assert_eq!(
+        price_move_bps(base, (U256::from(200), U256::from(1_000))),
+        10_000
+    );
+    assert_eq!(
+        price_move_bps(base, (U256::from(50), U256::from(1_000))),
+        5_000
+    );
+
+    // Empty reserves have no price
+    assert_eq!(price_move_bps((U256::zero(), U256::zero()), base), u64::MAX);
+}
+
+#[test]
+fn test_check_reserves() {
+    let config = ReserveSanity {
+        max_move_bps: 2_000,
+        max_average_deviation_bps: 1_000,
+    };
+
+    // Pairs without history are trusted
+    assert!(check_reserves(&config, &[], &sample(10, 100, 1_000)).is_ok());
+
+    // A small move since the last block passes
+    let history = [sample(8, 100, 1_000), sample(9, 105, 1_000)];
+    assert!(check_reserves(&config, &history, &sample(10, 108, 1_000)).is_ok());
+
+    // A pump in the last block is refused
+    assert_eq!(
+        check_reserves(&config, &history, &sample(10, 150, 1_000)),
+        Err(ReserveAnomaly::Moved {
+            since: U64::from(9),
+            bps: 4_285,
+        })
+    );
+
+    // A slow climb passes each block, but not the average
+    let history = [
+        sample(6, 100, 1_000),
+        sample(7, 100, 1_000),
+        sample(8, 100, 1_000),
+        sample(9, 115, 1_000),
+    ];
+    let anomaly = check_reserves(&config, &history, &sample(10, 130, 1_000)).unwrap_err();
+    assert!(matches!(
+        anomaly,
+        ReserveAnomaly::OffAverage { samples: 4, .. }
+    ));
+    assert_eq!(anomaly.metric(), "reserve_off_average");
+
+    // Later samples and disabled checks are ignored
+    let history = [sample(11, 500, 1_000)];
+    assert!(check_reserves(&config, &history, &sample(10, 100, 1_000)).is_ok());
+    let disabled = ReserveSanity {
+        max_move_bps: 0,
+        max_average_deviation_bps: 0,
+    };
+    assert!(!disabled.enabled());
+    let history = [sample(9, 100, 1_000)];
+    assert!(check_reserves(&disabled, &history, &sample(10, 500, 1_000)).is_ok());
+}
+
+#[test]
+fn test_reserve_history() {
+    let pair = Address::random();
+    let mut history = ReserveHistory::new(2);
+
+    // Samples are kept in block order, one per block
+    history.record(pair, sample(10, 100, 1_000));
+    history.record(pair, sample(8, 90, 1_000));
+    history.record(pair, sample(10, 110, 1_000));
+    assert_eq!(
+        history.samples(&pair),
+        vec![sample(8, 90, 1_000), sample(10, 110, 1_000)]
+    );
+    assert!(history.contains(&pair, U64::from(8)));
+    assert!(!history.contains(&pair, U64::from(9)));
+
+    // Samples older than the window are dropped
+    history.record(pair, sample(8 + RESERVE_HISTORY_BLOCKS, 120, 1_000));
+    assert_eq!(
+        history.samples(&pair),
+        vec![
+            sample(10, 110, 1_000),
+            sample(8 + RESERVE_HISTORY_BLOCKS, 120, 1_000)
+        ]
+    );
+
+    // When full, the pair seen least recently is forgotten
+    let other = Address::random();
+    let newest = Address::random();
+    history.record(other, sample(5, 100, 1_000));
+    history.record(newest, sample(40, 100, 1_000));
+    assert_eq!(history.len(), 2);
+    assert!(history.samples(&other).is_empty());
+    assert_eq!(history.samples(&pair).len(), 2);
+}
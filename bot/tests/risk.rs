@@ -0,0 +1,118 @@
+use ethers::prelude::*;
+
+use subway_rs::{executor::ExecutionMode, risk::*};
+
+#[test]
+fn test_worst_case_gas_cost() {
+    let cost = worst_case_gas_cost(U256::from(250_000), U256::from(10));
+    assert_eq!(cost, U256::from(5_000_000));
+    assert_eq!(worst_case_gas_cost(U256::MAX, U256::from(2)), U256::MAX);
+}
+
+#[test]
+fn test_preflight_inventory_mode() {
+    let inventory = Inventory {
+        executor_weth: U256::from(1_000),
+        searcher_eth: U256::from(100),
+    };
+    let mode = ExecutionMode::Inventory;
+
+    // Fully funded
+    assert_eq!(
+        preflight(U256::from(1_000), U256::from(100), &inventory, mode, true),
+        Preflight::Proceed
+    );
+
+    // Underfunded frontrun is shrunk to the executor's WETH
+    assert_eq!(
+        preflight(U256::from(5_000), U256::from(100), &inventory, mode, true),
+        Preflight::Downsize(U256::from(1_000))
+    );
+    assert_eq!(
+        preflight(U256::from(5_000), U256::from(100), &inventory, mode, false),
+        Preflight::Skip(PreflightFailure::InsufficientWeth)
+    );
+
+    // Gas can't be paid for
+    assert_eq!(
+        preflight(U256::from(1), U256::from(101), &inventory, mode, true),
+        Preflight::Skip(PreflightFailure::InsufficientGas)
+    );
+}
+
+#[test]
+fn test_preflight_value_mode_reserves_gas() {
+    let inventory = Inventory {
+        executor_weth: U256::zero(),
+        searcher_eth: U256::from(1_000),
+    };
+    let mode = ExecutionMode::Value;
+    assert_eq!(
+        preflight(U256::from(900), U256::from(100), &inventory, mode, true),
+        Preflight::Proceed
+    );
+    assert_eq!(
+        preflight(U256::from(950), U256::from(100), &inventory, mode, true),
+        Preflight::Downsize(U256::from(900))
+    );
+    assert_eq!(
+        preflight(U256::from(950), U256::from(1_000), &inventory, mode, true),
+        Preflight::Skip(PreflightFailure::InsufficientWeth)
+    );
+}
+
+#[test]
+fn test_reorg_guard() {
+    // Unconfigured, the base bribe always applies
+    let guard = ReorgGuard::default();
+    assert_eq!(
+        guard.bribe_bps(U256::MAX, DEFAULT_BRIBE_BPS),
+        Some(DEFAULT_BRIBE_BPS)
+    );
+
+    let guard = ReorgGuard {
+        bribe_threshold: Some(U256::from(1_000)),
+        bribe_step_bps: 1_000,
+        max_revenue: Some(U256::from(5_000)),
+    };
+    assert_eq!(guard.bribe_bps(U256::from(1_000), 1_337), Some(1_337));
+    assert_eq!(guard.bribe_bps(U256::from(2_500), 1_337), Some(3_337));
+    assert_eq!(
+        guard.bribe_bps(U256::from(5_000), 9_000),
+        Some(MAX_BRIBE_BPS)
+    );
+    assert_eq!(guard.bribe_bps(U256::from(5_001), 1_337), None);
+}
+
+#[test]
+fn test_exposure_tracker() {
+    let mut tracker = ExposureTracker::new(ExposureLimits {
+        max_token_exposure: Some(U256::from(1_000)),
+        window_secs: 60,
+    });
+    let token = Address::random();
+    let start = std::time::Instant::now();
+
+    assert!(tracker.allows(token, U256::from(1_000), start));
+    tracker.commit(token, U256::from(600), start);
+    assert_eq!(tracker.exposure(token, start), U256::from(600));
+    assert!(tracker.allows(token, U256::from(400), start));
+    assert!(!tracker.allows(token, U256::from(401), start));
+
+    // Other tokens have their own cap
+    assert!(tracker.allows(Address::random(), U256::from(1_000), start));
+
+    // Commitments roll out of the window
+    let later = start + std::time::Duration::from_secs(60);
+    assert_eq!(tracker.exposure(token, later), U256::zero());
+    assert!(tracker.allows(token, U256::from(1_000), later));
+}
+
+#[test]
+fn test_exposure_unlimited() {
+    let mut tracker = ExposureTracker::new(ExposureLimits::default());
+    let token = Address::random();
+    let now = std::time::Instant::now();
+    tracker.commit(token, U256::MAX, now);
+    assert!(tracker.allows(token, U256::MAX, now));
+}
@@ -0,0 +1,143 @@
+use std::str::FromStr;
+
+use ethers::prelude::*;
+use subway_rs::{abi::V2Sync, safety::*};
+
+fn sync(reserve0: u64, reserve1: u64) -> V2Sync {
+    V2Sync {
+        pair: Address::zero(),
+        reserve0: reserve0.into(),
+        reserve1: reserve1.into(),
+    }
+}
+
+#[test]
+fn test_report_score() {
+    let mut report = SafetyReport::default();
+    assert_eq!(report.score(), 0);
+    assert!(report.is_safe(DEFAULT_MAX_RISK_SCORE));
+
+    report.flag(RiskFlag::ReserveManipulation);
+    report.flag(RiskFlag::ReserveManipulation);
+    assert_eq!(report.flags.len(), 1);
+    assert!(report.is_safe(DEFAULT_MAX_RISK_SCORE));
+
+    report.flag(RiskFlag::KnownBaiter);
+    assert_eq!(report.score(), 150);
+    assert!(!report.is_safe(DEFAULT_MAX_RISK_SCORE));
+}
+
+#[test]
+fn test_max_reserve_swing() {
+    assert_eq!(max_reserve_swing_bps(&[]), 0);
+    assert_eq!(max_reserve_swing_bps(&[sync(100, 100)]), 0);
+    assert_eq!(
+        max_reserve_swing_bps(&[sync(100, 100), sync(110, 91), sync(400, 25)]),
+        26_363
+    );
+}
+
+#[test]
+fn test_baiter_registry() {
+    let registry = BaiterRegistry::default();
+    let sender = Address::random();
+    assert!(!registry.contains(&sender));
+    registry.mark(sender);
+    assert!(registry.contains(&sender));
+    assert_eq!(registry.list(), vec![sender]);
+}
+
+#[test]
+fn test_baiter_strikes() {
+    let registry = BaiterRegistry::default();
+    let sender = Address::random();
+    let now = std::time::Instant::now();
+
+    // One zero-profit sandwich isn't enough
+    assert!(!registry.strike(sender, now));
+    assert!(!registry.contains_at(&sender, now));
+    assert!(registry.strike(sender, now));
+    assert!(registry.contains_at(&sender, now));
+
+    // The mark expires
+    let later = now + std::time::Duration::from_secs(BAITER_TTL_SECS);
+    assert!(!registry.contains_at(&sender, later));
+    assert!(!registry.strike(sender, later));
+
+    // A profitable sandwich clears the strikes
+    registry.forgive(&sender);
+    assert!(!registry.strike(sender, later));
+}
+
+#[test]
+fn test_parse_address_list() {
+    let list = AddressList::from_str(
+        "0x0000000000000000000000000000000000000001, 0x0000000000000000000000000000000000000002",
+    )
+    .unwrap();
+    assert_eq!(
+        list.0,
+        vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2)]
+    );
+    assert!(AddressList::from_str("").unwrap().0.is_empty());
+    assert!(AddressList::from_str("nope").is_err());
+}
+
+/// Builds a dispatcher-like snippet pushing each selector
+fn dispatcher(signatures: &[&str]) -> Vec<u8> {
+    let mut code = Vec::new();
+    for sig in signatures {
+        code.push(0x63);
+        code.extend_from_slice(&ethers::utils::id(sig));
+        code.push(0x14); // EQ
+    }
+    code
+}
+
+#[test]
+fn test_eip1967_slot() {
+    let slot = U256::from(ethers::utils::keccak256("eip1967.proxy.implementation")) - 1;
+    assert_eq!(H256::from_uint(&slot).0, EIP1967_IMPLEMENTATION_SLOT);
+}
+
+#[test]
+fn test_instructions_skip_push_data() {
+    // PUSH2 0xf4f4, STOP, truncated PUSH4
+    let code = [0x61, 0xf4, 0xf4, 0x00, 0x63, 0xaa];
+    let ops: Vec<_> = instructions(&code).collect();
+    assert_eq!(
+        ops,
+        vec![
+            (0x61, &[0xf4, 0xf4][..]),
+            (0x00, &[][..]),
+            (0x63, &[0xaa][..])
+        ]
+    );
+    assert!(scan_bytecode(&code).flags.is_empty());
+}
+
+#[test]
+fn test_scan_bytecode() {
+    assert!(scan_bytecode(&dispatcher(&["transfer(address,uint256)"]))
+        .flags
+        .is_empty());
+
+    let report = scan_bytecode(&dispatcher(&["isBlacklisted(address)", "setFee(uint256)"]));
+    assert_eq!(report.flags, vec![RiskFlag::Blacklist, RiskFlag::FeeSetter]);
+    assert_eq!(report.score(), 160);
+
+    let mut proxy = vec![0x7f];
+    proxy.extend_from_slice(&EIP1967_IMPLEMENTATION_SLOT);
+    proxy.extend(dispatcher(&["paused()"]));
+    let report = scan_bytecode(&proxy);
+    assert_eq!(
+        report.flags,
+        vec![RiskFlag::UpgradeableProxy, RiskFlag::PausableTransfers]
+    );
+    assert!(!report.is_safe(DEFAULT_MAX_RISK_SCORE));
+
+    assert_eq!(
+        scan_bytecode(&[0xf4]).flags,
+        vec![RiskFlag::UpgradeableProxy]
+    );
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use subway_rs::{abi::SwapExactETHForTokensCall, scout::*, uniswap};
+
+fn ether(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(18)
+}
+
+fn swap(router: Address, path: Vec<Address>, amount_out_min: U256, deadline: U256) -> Transaction {
+    let call = SwapExactETHForTokensCall {
+        amount_out_min,
+        path,
+        to: Address::random(),
+        deadline,
+    };
+    Transaction {
+        hash: H256::random(),
+        to: Some(router),
+        value: ether(10),
+        input: ethers::abi::AbiEncode::encode(call).into(),
+        ..Default::default()
+    }
+}
+
+/// A WETH pair with 1,000 ether and 1,000,000 tokens
+fn state(weth: Address, token: Address) -> HashMap<Address, (U256, U256)> {
+    let pair = uniswap::calculate_uniswap_v2_pair_address(&weth, &token).unwrap();
+    let reserves = if weth < token {
+        (ether(1_000), ether(1_000_000))
+    } else {
+        (ether(1_000_000), ether(1_000))
+    };
+    HashMap::from([(pair, reserves)])
+}
+
+#[test]
+fn test_slippage() {
+    let (expected, _, _) =
+        uniswap::get_univ2_data_given_in(&ether(10), &ether(1_000), &ether(1_000_000));
+    assert_eq!(
+        slippage_bps(ether(10), expected, ether(1_000), ether(1_000_000)),
+        0
+    );
+    assert_eq!(
+        slippage_bps(ether(10), expected / 2, ether(1_000), ether(1_000_000)),
+        5_000
+    );
+    assert_eq!(
+        slippage_bps(ether(10), U256::zero(), ether(1_000), ether(1_000_000)),
+        10_000
+    );
+
+    assert_eq!(slippage_bucket(0), 0);
+    assert_eq!(slippage_bucket(10), 0);
+    assert_eq!(slippage_bucket(11), 1);
+    assert_eq!(slippage_bucket(1_000), SLIPPAGE_BUCKETS_BPS.len() - 1);
+    assert_eq!(slippage_bucket(5_000), SLIPPAGE_BUCKETS_BPS.len());
+}
+
+#[tokio::test]
+async fn test_analyze_swap() {
+    let chain = ScoutChain::default();
+    let (router, weth) = (chain.router, chain.weth);
+    let token = Address::random();
+    let state = state(weth, token);
+
+    // Any slippage can be sandwiched
+    let loose = swap(router, vec![weth, token], U256::zero(), U256::MAX);
+    let flow = analyze_swap(&loose, &chain, 0, &state, U256::zero())
+        .await
+        .unwrap();
+    assert_eq!(flow.class, SwapClass::Sandwichable);
+    assert_eq!(flow.slippage_bps, Some(10_000));
+    assert!(!flow.revenue.is_zero());
+
+    // Unless the revenue is below the minimum
+    let flow = analyze_swap(&loose, &chain, 0, &state, U256::MAX)
+        .await
+        .unwrap();
+    assert_eq!(flow.class, SwapClass::Unprofitable);
+
+    // No slippage leaves nothing
+    let (expected, _, _) =
+        uniswap::get_univ2_data_given_in(&ether(10), &ether(1_000), &ether(1_000_000));
+    let tight = swap(router, vec![weth, token], expected, U256::MAX);
+    let flow = analyze_swap(&tight, &chain, 0, &state, U256::zero())
+        .await
+        .unwrap();
+    assert_eq!(flow.class, SwapClass::Unprofitable);
+    assert_eq!(flow.slippage_bps, Some(0));
+
+    // Expired, or through an unknown pair
+    let expired = swap(router, vec![weth, token], U256::zero(), U256::from(10));
+    let flow = analyze_swap(&expired, &chain, 11, &state, U256::zero())
+        .await
+        .unwrap();
+    assert_eq!(flow.class, SwapClass::Expired);
+    let unknown = swap(
+        router,
+        vec![weth, Address::random()],
+        U256::zero(),
+        U256::MAX,
+    );
+    let flow = analyze_swap(&unknown, &chain, 0, &state, U256::zero())
+        .await
+        .unwrap();
+    assert_eq!(flow.class, SwapClass::Unpriced);
+    assert_eq!(flow.slippage_bps, None);
+
+    // Another chain's pairs derive from its own factory
+    let other = ScoutChain {
+        v2: uniswap::V2Deployment {
+            factory: Address::random(),
+            ..Default::default()
+        },
+        ..chain
+    };
+    let flow = analyze_swap(&loose, &other, 0, &state, U256::zero())
+        .await
+        .unwrap();
+    assert_eq!(flow.class, SwapClass::Unpriced);
+    assert_eq!(flow.pair, other.v2.pair_address(weth, token));
+
+    // Not a WETH swap through the router
+    let elsewhere = swap(
+        Address::random(),
+        vec![weth, token],
+        U256::zero(),
+        U256::MAX,
+    );
+    assert!(analyze_swap(&elsewhere, &chain, 0, &state, U256::zero())
+        .await
+        .is_none());
+}
+
+#[test]
+fn test_flow_stats() {
+    let router = Address::random();
+    let (a, b) = (Address::random(), Address::random());
+    let flow = |pair: Address, weth: u64, class: SwapClass, slippage: Option<u64>| SwapFlow {
+        hash: H256::random(),
+        pair,
+        token: Address::random(),
+        weth_in: ether(weth),
+        slippage_bps: slippage,
+        revenue: U256::from(7),
+        class,
+    };
+
+    let mut stats = FlowStats::new();
+    assert_eq!(stats.sandwichable_fraction(), 0.0);
+    stats.record_seen(
+        &Transaction {
+            to: Some(router),
+            ..Default::default()
+        },
+        router,
+    );
+    stats.record_seen(&Transaction::default(), router);
+    assert_eq!((stats.seen, stats.router_calls), (2, 1));
+
+    stats.record(&flow(a, 1, SwapClass::Sandwichable, Some(200)));
+    stats.record(&flow(a, 2, SwapClass::Unprofitable, Some(5)));
+    stats.record(&flow(b, 5, SwapClass::Sandwichable, Some(200)));
+    stats.record(&flow(b, 1, SwapClass::Unpriced, None));
+    assert_eq!(stats.swaps, 4);
+    assert_eq!(stats.sandwichable_fraction(), 0.5);
+    assert_eq!(stats.weth_volume, ether(9));
+    assert_eq!(stats.slippage[0], 1);
+    assert_eq!(stats.slippage[slippage_bucket(200)], 2);
+    assert_eq!(stats.slippage.iter().sum::<u64>(), 3);
+
+    let top = stats.top_pairs(1);
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].0, b);
+    assert_eq!(top[0].1.weth_volume, ether(6));
+    assert_eq!(top[0].1.sandwichable, 1);
+    assert_eq!(top[0].1.revenue, U256::from(7));
+
+    // Reports round trip as json
+    let json = serde_json::to_string(&stats).unwrap();
+    assert_eq!(serde_json::from_str::<FlowStats>(&json).unwrap(), stats);
+}
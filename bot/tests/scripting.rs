@@ -0,0 +1,73 @@
+#![cfg(feature = "scripting")]
+
+use ethers::prelude::*;
+
+use subway_rs::scripting::*;
+
+const ELON_RULE: &str = r#"!symbol.contains("ELON") || liquidity > 500.0"#;
+
+fn candidate(symbol: &str, liquidity_weth: u64) -> ScriptCandidate {
+    ScriptCandidate {
+        token: Address::repeat_byte(0x11),
+        symbol: symbol.to_string(),
+        pair: Address::repeat_byte(0x22),
+        sender: Address::repeat_byte(0x33),
+        weth_in: ethers::utils::parse_ether(2).unwrap(),
+        liquidity: ethers::utils::parse_ether(liquidity_weth).unwrap(),
+        token_reserve: U256::from(1_000_000u64),
+        block: U64::from(17_000_000u64),
+    }
+}
+
+#[test]
+fn test_symbol_and_liquidity_rule() {
+    let filter = ScriptFilter::compile(ELON_RULE, 1_000).unwrap();
+    assert!(filter.reads_symbol());
+
+    assert!(filter.evaluate(&candidate("PEPE", 10)).unwrap());
+    assert!(!filter.evaluate(&candidate("BABYELON", 10)).unwrap());
+    assert!(filter.evaluate(&candidate("BABYELON", 501)).unwrap());
+}
+
+#[test]
+fn test_candidate_constants() {
+    let filter = ScriptFilter::compile(
+        r#"weth_in == 2.0
+            && token_reserve == 1000000.0
+            && block == 17000000
+            && pair == "0x2222222222222222222222222222222222222222"
+            && sender.starts_with("0x3333")"#,
+        1_000,
+    )
+    .unwrap();
+    assert!(!filter.reads_symbol());
+    assert!(filter.evaluate(&candidate("", 1)).unwrap());
+}
+
+#[test]
+fn test_budget_exceeded() {
+    let filter = ScriptFilter::compile("loop {}", 1_000).unwrap();
+    let err = filter.evaluate(&candidate("PEPE", 10)).unwrap_err();
+    assert_eq!(err, ScriptError::Budget { operations: 1_000 });
+    assert_eq!(err.metric(), "script_budget_exceeded");
+}
+
+#[test]
+fn test_script_errors() {
+    let filter = ScriptFilter::compile("weth_in + 1", 1_000).unwrap();
+    let err = filter.evaluate(&candidate("PEPE", 10)).unwrap_err();
+    assert!(matches!(err, ScriptError::NotBool(_)));
+    assert_eq!(err.metric(), "script_errors");
+
+    let filter = ScriptFilter::compile("missing > 1.0", 1_000).unwrap();
+    let err = filter.evaluate(&candidate("PEPE", 10)).unwrap_err();
+    assert!(matches!(err, ScriptError::Failed(_)));
+    assert_eq!(err.metric(), "script_errors");
+}
+
+#[test]
+fn test_rejects_bad_scripts() {
+    assert!(ScriptFilter::compile("liquidity >", 1_000).is_err());
+    assert!(ScriptFilter::compile(ELON_RULE, 0).is_err());
+    assert!(ScriptFilter::compile(r#"eval("true")"#, 1_000).is_err());
+}
@@ -0,0 +1,92 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use ethers::prelude::*;
+
+use subway_rs::{accounting::Ledger, session::*};
+
+#[test]
+fn test_report_from_counters() {
+    let counters = BTreeMap::from([
+        ("candidates_skipped_decode".to_string(), 40),
+        ("candidates_skipped_sizing".to_string(), 7),
+        ("candidates_shipped".to_string(), 3),
+        ("candidates_observed".to_string(), 1),
+        ("task_restarts_mempool_node".to_string(), 2),
+        ("ntp_failures".to_string(), 1),
+        ("profit_skim_failures".to_string(), 0),
+        ("blocks_seen".to_string(), 100),
+        ("alpha.candidates_skipped_decode".to_string(), 5),
+        ("alpha.candidates_shipped".to_string(), 2),
+    ]);
+    let profit = Ledger {
+        gross_profit: U256::from(100),
+        gas: U256::from(30),
+        ..Default::default()
+    };
+    let report = SessionReport::from_counters(
+        Duration::from_secs(3_725),
+        &counters,
+        profit,
+        Ledger::default(),
+        Vec::new(),
+    );
+
+    assert_eq!(report.uptime_secs, 3_725);
+    assert_eq!(
+        report.skipped,
+        BTreeMap::from([
+            ("alpha.decode".to_string(), 5),
+            ("decode".to_string(), 40),
+            ("sizing".to_string(), 7),
+        ])
+    );
+    assert_eq!(report.shipped, 5);
+    assert_eq!(report.observed, 1);
+    assert_eq!(report.evaluated(), 58);
+    assert_eq!(
+        report.errors,
+        BTreeMap::from([
+            ("ntp_failures".to_string(), 1),
+            ("task_restarts_mempool_node".to_string(), 2),
+        ])
+    );
+
+    let message = report.message();
+    assert!(message.contains("1h02m"));
+    assert!(message.contains("Most skipped at: decode 40"));
+    assert!(message.contains("0.000000000000000070 ETH realized"));
+}
+
+#[test]
+fn test_top_tokens() {
+    let tokens = SessionTokens::default();
+    let (a, b, c) = (
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        Address::from_low_u64_be(3),
+    );
+    tokens.record_shipped(a, U256::from(10));
+    tokens.record_shipped(b, U256::from(5));
+    tokens.record_shipped(b, U256::from(5));
+    tokens.record_shipped(c, U256::from(50));
+
+    let top = tokens.top(2);
+    assert_eq!(top.len(), 2);
+    assert_eq!(
+        top[0],
+        TokenActivity {
+            token: b,
+            bundles: 2,
+            expected_value: U256::from(10),
+        }
+    );
+    // Ties on bundles go to the higher expected value
+    assert_eq!(top[1].token, c);
+}
+
+#[test]
+fn test_is_error_metric() {
+    assert!(is_error_metric("honeypot_api_failed_goplus"));
+    assert!(is_error_metric("alpha.task_stalls_mempool_node"));
+    assert!(!is_error_metric("blocks_seen"));
+}
@@ -0,0 +1,239 @@
+use ethers::{abi::Token, prelude::*};
+use subway_rs::simulation::*;
+
+fn amounts(values: &[u64]) -> Vec<U256> {
+    values.iter().map(|v| U256::from(*v)).collect()
+}
+
+#[test]
+fn test_decode_amounts() {
+    let output = ethers::abi::encode(&[Token::Array(vec![
+        Token::Uint(U256::from(100)),
+        Token::Uint(U256::from(250)),
+    ])]);
+    assert_eq!(decode_amounts(&output).unwrap(), amounts(&[100, 250]));
+    assert!(decode_amounts(&[0u8; 4]).is_err());
+}
+
+#[test]
+fn test_verify_victim_amounts() {
+    // A direct swap matching the decoded input, minimum and expected output
+    let direct = amounts(&[1000, 2000]);
+    assert!(verify_victim_amounts(&direct, 1000.into(), 1900.into(), 2000.into(), 100).is_ok());
+
+    // Multi-hop paths check the first hop against the pair and the last against the minimum
+    let multi = amounts(&[1000, 2000, 50]);
+    assert!(verify_victim_amounts(&multi, 1000.into(), 50.into(), 2010.into(), 100).is_ok());
+    assert_eq!(
+        verify_victim_amounts(&multi, 1000.into(), 51.into(), 2000.into(), 100),
+        Err(VictimMismatch::BelowMinimum {
+            min: 51.into(),
+            simulated: 50.into()
+        })
+    );
+
+    assert_eq!(
+        verify_victim_amounts(&amounts(&[1000]), 1000.into(), 0.into(), 0.into(), 100),
+        Err(VictimMismatch::MissingAmounts(1))
+    );
+    assert_eq!(
+        verify_victim_amounts(&direct, 999.into(), 0.into(), 2000.into(), 100),
+        Err(VictimMismatch::AmountIn {
+            decoded: 999.into(),
+            simulated: 1000.into()
+        })
+    );
+
+    // Outputs more than the tolerance away from the reserves' prediction diverge
+    let diverged = verify_victim_amounts(&direct, 1000.into(), 0.into(), 2100.into(), 100);
+    assert_eq!(
+        diverged,
+        Err(VictimMismatch::Diverged {
+            expected: 2100.into(),
+            simulated: 2000.into()
+        })
+    );
+    assert_eq!(diverged.unwrap_err().metric(), "victim_sim_diverged");
+}
+
+#[test]
+fn test_bundle_hash_is_order_sensitive() {
+    let a = Bytes::from(vec![1u8, 2, 3]);
+    let b = Bytes::from(vec![4u8, 5, 6]);
+    assert_eq!(
+        bundle_hash(&[a.clone(), b.clone()]),
+        bundle_hash(&[a.clone(), b.clone()])
+    );
+    assert_ne!(bundle_hash(&[a.clone(), b.clone()]), bundle_hash(&[b, a]));
+}
+
+fn simulation_key() -> SimulationKey {
+    SimulationKey {
+        victim: H256::random(),
+        pair: Address::random(),
+        frontrun_in: U256::from(1_000),
+        backrun_in: U256::from(2_000),
+    }
+}
+
+#[test]
+fn test_simulation_cache() {
+    let mut cache = SimulationCache::new(2);
+    let key = simulation_key();
+    let snapshot = StateSnapshot {
+        block: U64::from(10),
+        reserves: (U256::from(100), U256::from(200)),
+    };
+    cache.insert(key, snapshot, 42u64);
+
+    // The same sandwich against the same state hits, however its legs were signed
+    assert_eq!(cache.get(&key, &snapshot), Some(42));
+    assert_eq!(cache.get(&simulation_key(), &snapshot), None);
+
+    // Other amounts miss
+    let resized = SimulationKey {
+        frontrun_in: U256::from(1_001),
+        ..key
+    };
+    assert_eq!(cache.get(&resized, &snapshot), None);
+
+    // A later block misses even with unchanged reserves
+    let later = StateSnapshot {
+        block: U64::from(11),
+        ..snapshot
+    };
+    assert_eq!(cache.get(&key, &later), None);
+
+    // Moved reserves miss in the same block
+    let moved = StateSnapshot {
+        reserves: (U256::from(101), U256::from(199)),
+        ..snapshot
+    };
+    assert_eq!(cache.get(&key, &moved), None);
+    assert_eq!(cache.get(&key, &snapshot), Some(42));
+}
+
+#[test]
+fn test_simulation_cache_evicts_oldest_block() {
+    let mut cache = SimulationCache::new(2);
+    let snapshot = |block: u64| StateSnapshot {
+        block: U64::from(block),
+        reserves: (U256::one(), U256::one()),
+    };
+    let (oldest, newer, newest) = (simulation_key(), simulation_key(), simulation_key());
+    cache.insert(oldest, snapshot(1), 1u64);
+    cache.insert(newer, snapshot(2), 2);
+    cache.insert(newest, snapshot(3), 3);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(&oldest, &snapshot(1)), None);
+    assert_eq!(cache.get(&newest, &snapshot(3)), Some(3));
+}
+
+#[test]
+fn test_competing_swaps() {
+    let pair = Address::random();
+    let victim = H256::random();
+    let now = std::time::Instant::now();
+    let swap = |hash: H256, priority_fee: u64, seen_at: std::time::Instant| PendingSwap {
+        hash,
+        pair,
+        weth_in: U256::from(1_000),
+        priority_fee: U256::from(priority_fee),
+        seen_at,
+    };
+
+    let mut swaps = CompetingSwaps::new();
+    let (low, high) = (H256::random(), H256::random());
+    swaps.observe(swap(victim, 5, now));
+    swaps.observe(swap(low, 1, now));
+    swaps.observe(swap(high, 9, now));
+    swaps.observe(swap(high, 9, now));
+
+    // The victim and swaps paying less than it are excluded, duplicates are recorded once
+    let competitors = swaps.competitors(pair, victim, U256::from(5));
+    assert_eq!(competitors.len(), 1);
+    assert_eq!(competitors[0].hash, high);
+    assert!(swaps
+        .competitors(Address::random(), victim, U256::zero())
+        .is_empty());
+
+    // Swaps older than a slot are forgotten
+    let later = now + std::time::Duration::from_secs(COMPETING_SWAP_TTL_SECS);
+    swaps.observe(swap(H256::random(), 1, later));
+    assert_eq!(swaps.competitors(pair, victim, U256::zero()).len(), 1);
+}
+
+#[test]
+fn test_apply_competing_swaps() {
+    let weth = U256::from(1_000_000);
+    let token = U256::from(2_000_000);
+    assert_eq!(apply_competing_swaps(weth, token, &[]), (weth, token));
+
+    let swap = PendingSwap {
+        hash: H256::random(),
+        pair: Address::random(),
+        weth_in: U256::from(10_000),
+        priority_fee: U256::zero(),
+        seen_at: std::time::Instant::now(),
+    };
+    let (new_weth, new_token) = apply_competing_swaps(weth, token, &[swap]);
+    assert_eq!(new_weth, U256::from(1_010_000));
+    assert_eq!(new_token, U256::from(1_980_257));
+}
+
+#[test]
+fn test_builder_payment_limits() {
+    let limits = BuilderPaymentLimits {
+        min_payment: U256::from(1_000),
+        tolerance_bps: 100,
+    };
+    assert!(limits
+        .check(U256::from(1_500), U256::from(1_000), U256::from(1_000))
+        .is_ok());
+    // Within 1% of the bribe math
+    assert!(limits
+        .check(U256::from(1_500), U256::from(990), U256::from(1_000))
+        .is_ok());
+
+    assert_eq!(
+        limits.check(U256::from(999), U256::from(999), U256::from(999)),
+        Err(BuilderPaymentError::BelowMinimum {
+            payment: U256::from(999),
+            min: U256::from(1_000),
+        })
+    );
+    assert_eq!(
+        limits.check(U256::from(1_500), U256::from(500), U256::from(1_000)),
+        Err(BuilderPaymentError::Mismatch {
+            expected: U256::from(1_000),
+            simulated: U256::from(500),
+        })
+    );
+}
+
+#[test]
+fn test_victim_recheck() {
+    let recheck = VictimRecheck {
+        pair: Address::random(),
+        weth: Address::random(),
+        token: Address::random(),
+        frontrun_weth_in: U256::from(400),
+        user_amount_in: U256::from(20),
+        user_min_recv: U256::from(10),
+    };
+
+    // The reserves the sandwich was priced against
+    assert_eq!(
+        recheck.check(U256::from(1_000), U256::from(1_000)),
+        Ok(U256::from(10))
+    );
+
+    // Another swap moved the pair while the bundle was held
+    assert_eq!(
+        recheck.check(U256::from(1_100), U256::from(910)),
+        Err(VictimMinViolation {
+            min: U256::from(10),
+            expected: U256::from(8),
+        })
+    );
+}
@@ -0,0 +1,42 @@
+use ethers::prelude::*;
+
+use subway_rs::skim::*;
+
+#[test]
+fn test_skimmable() {
+    assert_eq!(
+        skimmable(150.into(), 100.into(), 0.into()),
+        Some(U256::from(50))
+    );
+    assert_eq!(skimmable(100.into(), 100.into(), 0.into()), None);
+    assert_eq!(skimmable(90.into(), 100.into(), 0.into()), None);
+
+    // Amounts under the minimum are left for later
+    assert_eq!(skimmable(150.into(), 100.into(), 60.into()), None);
+}
+
+#[test]
+fn test_plan_skim() {
+    let mut config = SkimConfig {
+        to: Some(Address::random()),
+        working_capital: 100.into(),
+        ..Default::default()
+    };
+
+    // The wallet is only skimmed with a float
+    let plan = plan_skim(150.into(), 1_000.into(), &config);
+    assert_eq!(
+        plan,
+        SkimPlan {
+            executor_weth: Some(50.into()),
+            wallet_eth: None,
+        }
+    );
+
+    config.wallet_float = Some(400.into());
+    let plan = plan_skim(150.into(), 1_000.into(), &config);
+    assert_eq!(plan.wallet_eth, Some(600.into()));
+    assert!(describe_skim(&plan, Address::zero()).contains("600 wei of ETH from the searcher"));
+
+    assert!(plan_skim(100.into(), 400.into(), &config).is_empty());
+}
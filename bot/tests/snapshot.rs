@@ -0,0 +1,73 @@
+#![cfg(feature = "testing")]
+
+use ethers::prelude::*;
+
+use subway_rs::{safety, snapshot::*, state::*, testing::MockStateReader};
+
+fn pair() -> Address {
+    Address::from_low_u64_be(0xabc)
+}
+
+fn tokens() -> (Address, Address) {
+    (Address::from_low_u64_be(1), Address::from_low_u64_be(2))
+}
+
+/// Reads a pair's tokens and decimals through a cache, like a running instance would
+async fn warm_reader() -> CachedReader<MockStateReader> {
+    let reader = CachedReader::new(
+        MockStateReader::new()
+            .with_pair(pair(), tokens())
+            .with_decimals(tokens().1, 6),
+    );
+    reader.pair_tokens(pair()).await.unwrap();
+    reader.decimals(tokens().1).await.unwrap();
+    reader
+}
+
+#[tokio::test]
+async fn test_prime_from_snapshot() {
+    let baiter = Address::random();
+    safety::baiters().mark(baiter);
+    let snapshot = ChainSnapshot::capture(&warm_reader().await, 1);
+    assert_eq!(snapshot.cache.len(), 2);
+    assert!(snapshot.baiters.contains(&baiter));
+
+    // A fresh instance answers from the snapshot without reading the node
+    let node = MockStateReader::new();
+    let reader = CachedReader::new(node.clone());
+    assert_eq!(snapshot.clone().prime(&reader, 1).unwrap(), 2);
+    assert_eq!(reader.pair_tokens(pair()).await.unwrap(), tokens());
+    assert_eq!(reader.decimals(tokens().1).await.unwrap(), 6);
+    assert_eq!(node.reads(), 0);
+
+    // Priming again adds nothing new
+    assert_eq!(snapshot.prime(&reader, 1).unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_refuse_other_chains() {
+    let snapshot = ChainSnapshot::capture(&warm_reader().await, 1);
+    let reader = CachedReader::new(MockStateReader::new());
+    assert!(snapshot.clone().prime(&reader, 10).is_err());
+
+    let outdated = ChainSnapshot {
+        version: CHAIN_SNAPSHOT_VERSION + 1,
+        ..snapshot
+    };
+    assert!(outdated.prime(&reader, 1).is_err());
+    assert!(reader.cached_data().is_empty());
+}
+
+#[tokio::test]
+async fn test_save_and_load_snapshot() {
+    let path = std::env::temp_dir().join(format!("chain-{:x}.json", H64::random()));
+    assert_eq!(ChainSnapshot::load(&path).unwrap(), None);
+
+    let snapshot = ChainSnapshot::capture(&warm_reader().await, 1);
+    snapshot.save(&path).unwrap();
+    assert_eq!(ChainSnapshot::load(&path).unwrap(), Some(snapshot));
+
+    std::fs::write(&path, "not json").unwrap();
+    assert!(ChainSnapshot::load(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
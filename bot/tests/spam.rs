@@ -0,0 +1,134 @@
+use std::time::{Duration, Instant};
+
+use ethers::prelude::*;
+use subway_rs::{safety::AddressList, spam::*};
+
+fn limits() -> SpamLimits {
+    SpamLimits {
+        sender_limit: 2,
+        token_limit: 3,
+        window_secs: 12,
+        min_value: U256::from(100),
+    }
+}
+
+#[test]
+fn test_rate_limiter_window() {
+    let mut limiter = RateLimiter::new(2, Duration::from_secs(12));
+    let key = Address::random();
+    let now = Instant::now();
+    assert!(limiter.check(key, now));
+    assert!(limiter.check(key, now));
+    assert!(!limiter.check(key, now));
+    assert!(limiter.check(Address::random(), now));
+    assert_eq!(limiter.len(), 2);
+
+    // The window resets once it has elapsed
+    assert!(limiter.check(key, now + Duration::from_secs(12)));
+}
+
+#[test]
+fn test_rate_limiter_bounded() {
+    let mut limiter = RateLimiter::new(2, Duration::from_secs(12));
+    let now = Instant::now();
+    for _ in 0..GENERATION_CAPACITY * 3 {
+        limiter.check(Address::random(), now);
+    }
+    assert!(limiter.len() <= 2 * GENERATION_CAPACITY);
+
+    // Keys idle for two windows are dropped
+    limiter.check(Address::random(), now + Duration::from_secs(12));
+    limiter.check(Address::random(), now + Duration::from_secs(24));
+    assert_eq!(limiter.len(), 2);
+}
+
+#[test]
+fn test_spam_guard() {
+    let mut guard = SpamGuard::new(limits());
+    let now = Instant::now();
+    let token = Address::random();
+    let spammer = Address::random();
+
+    assert_eq!(
+        guard.check(spammer, token, 99.into(), now),
+        Err(SpamRejection::Dust)
+    );
+    assert!(guard.check(spammer, token, 100.into(), now).is_ok());
+    assert!(guard.check(spammer, token, 100.into(), now).is_ok());
+    assert_eq!(
+        guard.check(spammer, token, 100.into(), now),
+        Err(SpamRejection::SenderLimited)
+    );
+
+    // The spammer's dropped swaps don't use up the token's limit
+    assert!(guard
+        .check(Address::random(), token, 100.into(), now)
+        .is_ok());
+    assert_eq!(
+        guard.check(Address::random(), token, 100.into(), now),
+        Err(SpamRejection::TokenLimited)
+    );
+    assert_eq!(SpamRejection::TokenLimited.metric(), "spam_token_limited");
+}
+
+#[test]
+fn test_bot_registry() {
+    let known = Address::random();
+    let contract = Address::random();
+    let mut registry = BotRegistry::new(&AddressList(vec![known, contract]), 2);
+    let sender = Address::random();
+    let recipient = Address::random();
+
+    assert_eq!(
+        registry.check(&known, &recipient),
+        Err(SpamRejection::KnownBot)
+    );
+    assert_eq!(
+        registry.check(&sender, &contract),
+        Err(SpamRejection::KnownBot)
+    );
+    assert!(registry.check(&sender, &recipient).is_ok());
+
+    // A sandwichable swap resets the strikes
+    assert!(!registry.strike(sender));
+    registry.clear(&sender);
+    assert!(!registry.strike(sender));
+    assert!(registry.check(&sender, &recipient).is_ok());
+
+    assert!(registry.strike(sender));
+    assert_eq!(registry.learned(), 1);
+    assert_eq!(
+        registry.check(&sender, &recipient),
+        Err(SpamRejection::KnownBot)
+    );
+
+    // Learned bots stay learned
+    registry.clear(&sender);
+    assert!(!registry.strike(sender));
+    assert_eq!(registry.learned(), 1);
+}
+
+#[test]
+fn test_bot_registry_bounded() {
+    let mut registry = BotRegistry::new(&AddressList::default(), 1);
+    let first = Address::random();
+    assert!(registry.strike(first));
+    for _ in 0..MAX_LEARNED_BOTS {
+        assert!(registry.strike(Address::random()));
+    }
+    assert_eq!(registry.learned(), MAX_LEARNED_BOTS);
+
+    // The earliest learned bot is forgotten first
+    assert!(registry.check(&first, &Address::random()).is_ok());
+}
+
+#[test]
+fn test_bot_learning_disabled() {
+    let mut registry = BotRegistry::new(&AddressList::default(), 0);
+    let sender = Address::random();
+    for _ in 0..10 {
+        assert!(!registry.strike(sender));
+    }
+    assert!(registry.check(&sender, &Address::random()).is_ok());
+    assert_eq!(registry.learned(), 0);
+}
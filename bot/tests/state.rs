@@ -0,0 +1,231 @@
+use ethers::prelude::*;
+use eyre::Result;
+use futures::future::BoxFuture;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use subway_rs::state::*;
+
+/// Counts reads, with code deployed at block 10 and reserves equal to the block number
+#[derive(Debug, Default, Clone)]
+struct CountingReader {
+    reads: Arc<AtomicUsize>,
+}
+
+impl CountingReader {
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+}
+
+impl StateReader for CountingReader {
+    fn code(&self, _address: Address, block: Option<U64>) -> BoxFuture<'_, Result<Bytes>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        let code = match block {
+            Some(b) if b < U64::from(10) => Bytes::default(),
+            _ => Bytes::from(vec![0x60, 0x80]),
+        };
+        Box::pin(async move { Ok(code) })
+    }
+
+    fn decimals(&self, _token: Address) -> BoxFuture<'_, Result<u8>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move { Ok(18) })
+    }
+
+    fn pair_tokens(&self, _pair: Address) -> BoxFuture<'_, Result<(Address, Address)>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move { Ok((Address::from_low_u64_be(1), Address::from_low_u64_be(2))) })
+    }
+
+    fn reserves(&self, _pair: Address, block: Option<U64>) -> BoxFuture<'_, Result<(U256, U256)>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        let reserve = U256::from(block.unwrap_or_default().as_u64());
+        Box::pin(async move { Ok((reserve, reserve)) })
+    }
+}
+
+#[tokio::test]
+async fn test_cached_reader_caches_immutable_data() {
+    let inner = CountingReader::default();
+    let cached = CachedReader::new(inner.clone());
+    let (token, pair) = (Address::random(), Address::random());
+    for _ in 0..3 {
+        assert_eq!(cached.decimals(token).await.unwrap(), 18);
+        assert_eq!(
+            cached.pair_tokens(pair).await.unwrap(),
+            (Address::from_low_u64_be(1), Address::from_low_u64_be(2))
+        );
+    }
+    assert_eq!(inner.reads(), 2);
+
+    // Missing code is cached for its block and every earlier one
+    assert!(cached
+        .code(token, Some(U64::from(5)))
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(cached
+        .code(token, Some(U64::from(5)))
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(cached
+        .code(token, Some(U64::from(3)))
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(inner.reads(), 3);
+
+    // But not for later blocks, since it may still be deployed
+    assert!(cached
+        .code(token, Some(U64::from(6)))
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(inner.reads(), 4);
+
+    // Deployed code is served for its block and every later one
+    assert!(!cached
+        .code(token, Some(U64::from(12)))
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(!cached
+        .code(token, Some(U64::from(20)))
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(!cached.code(token, None).await.unwrap().is_empty());
+    assert_eq!(inner.reads(), 5);
+
+    // But not for earlier blocks
+    assert!(!cached
+        .code(token, Some(U64::from(11)))
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(inner.reads(), 6);
+    assert!(!cached
+        .code(token, Some(U64::from(11)))
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(inner.reads(), 6);
+}
+
+#[tokio::test]
+async fn test_cached_reader_scopes_reserves_to_the_tip() {
+    let inner = CountingReader::default();
+    let cached = CachedReader::new(inner.clone());
+    let pair = Address::random();
+    let at = |block: u64| Some(U64::from(block));
+
+    assert_eq!(cached.reserves(pair, at(5)).await.unwrap().0, U256::from(5));
+    assert_eq!(cached.reserves(pair, at(5)).await.unwrap().0, U256::from(5));
+    assert_eq!(inner.reads(), 1);
+    assert_eq!(cached.tip(), U64::from(5));
+
+    // A new block drops the previous tip's reserves
+    assert_eq!(cached.reserves(pair, at(6)).await.unwrap().0, U256::from(6));
+    assert_eq!(inner.reads(), 2);
+    assert_eq!(cached.tip(), U64::from(6));
+
+    // Earlier blocks and the latest block always read through
+    assert_eq!(cached.reserves(pair, at(5)).await.unwrap().0, U256::from(5));
+    cached.reserves(pair, None).await.unwrap();
+    assert_eq!(inner.reads(), 4);
+    assert_eq!(cached.reserves(pair, at(6)).await.unwrap().0, U256::from(6));
+    assert_eq!(inner.reads(), 4);
+}
+
+/// Serves fixed reserves, or fails reserve reads without any
+#[derive(Debug, Default, Clone)]
+struct FixedReserves(Option<(U256, U256)>);
+
+impl StateReader for FixedReserves {
+    fn code(&self, _address: Address, _block: Option<U64>) -> BoxFuture<'_, Result<Bytes>> {
+        Box::pin(async move { Ok(Bytes::default()) })
+    }
+
+    fn decimals(&self, _token: Address) -> BoxFuture<'_, Result<u8>> {
+        Box::pin(async move { Ok(6) })
+    }
+
+    fn pair_tokens(&self, _pair: Address) -> BoxFuture<'_, Result<(Address, Address)>> {
+        Box::pin(async move { Ok((Address::zero(), Address::zero())) })
+    }
+
+    fn reserves(&self, _pair: Address, _block: Option<U64>) -> BoxFuture<'_, Result<(U256, U256)>> {
+        let reserves = self.0.ok_or_else(|| eyre::eyre!("Header not found"));
+        Box::pin(async move { reserves })
+    }
+}
+
+#[test]
+fn test_reserve_consensus_from_str() {
+    assert_eq!(
+        "off".parse::<ReserveConsensus>().unwrap(),
+        ReserveConsensus::Off
+    );
+    assert_eq!(
+        "WARN".parse::<ReserveConsensus>().unwrap(),
+        ReserveConsensus::Warn
+    );
+    assert_eq!(
+        "enforce".parse::<ReserveConsensus>().unwrap(),
+        ReserveConsensus::Enforce
+    );
+    assert!("majority".parse::<ReserveConsensus>().is_err());
+}
+
+#[tokio::test]
+async fn test_consensus_reader() {
+    let pair = Address::random();
+    let block = Some(U64::from(7));
+    let agreeing = FixedReserves(Some((U256::from(7), U256::from(7))));
+    let disagreeing = FixedReserves(Some((U256::from(8), U256::from(7))));
+
+    // Agreeing providers serve the primary's reserves, other reads only go to the primary
+    let primary = CountingReader::default();
+    let reader = ConsensusReader::new(primary.clone(), agreeing, ReserveConsensus::Enforce);
+    assert_eq!(
+        reader.reserves(pair, block).await.unwrap(),
+        (U256::from(7), U256::from(7))
+    );
+    assert_eq!(reader.decimals(pair).await.unwrap(), 18);
+    assert_eq!(primary.reads(), 2);
+
+    // Disagreements fail enforced reads, and are only flagged otherwise
+    let reader = ConsensusReader::new(
+        CountingReader::default(),
+        disagreeing.clone(),
+        ReserveConsensus::Enforce,
+    );
+    assert!(reader.reserves(pair, block).await.is_err());
+    let reader = ConsensusReader::new(
+        CountingReader::default(),
+        disagreeing.clone(),
+        ReserveConsensus::Warn,
+    );
+    assert_eq!(
+        reader.reserves(pair, block).await.unwrap(),
+        (U256::from(7), U256::from(7))
+    );
+
+    // Latest reads can't be compared, and a lagging secondary doesn't block reads
+    let reader = ConsensusReader::new(
+        CountingReader::default(),
+        disagreeing,
+        ReserveConsensus::Enforce,
+    );
+    assert!(reader.reserves(pair, None).await.is_ok());
+    let reader = ConsensusReader::new(
+        CountingReader::default(),
+        FixedReserves(None),
+        ReserveConsensus::Enforce,
+    );
+    assert!(reader.reserves(pair, block).await.is_ok());
+}
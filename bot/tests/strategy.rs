@@ -0,0 +1,63 @@
+use std::str::FromStr;
+
+use ethers::prelude::*;
+use subway_rs::strategy::*;
+
+#[test]
+fn test_parse_profit_floors() {
+    let floors = ProfitFloors::from_str("bluechip=5, longtail=12.5").unwrap();
+    assert_eq!(
+        floors.0,
+        vec![
+            ProfitFloor {
+                class: OpportunityClass::BlueChip,
+                usdc: U256::from(5_000_000),
+            },
+            ProfitFloor {
+                class: OpportunityClass::LongTail,
+                usdc: U256::from(12_500_000),
+            },
+        ]
+    );
+    assert!(ProfitFloors::from_str("").unwrap().0.is_empty());
+    assert!(ProfitFloors::from_str("midcap=5").is_err());
+    assert!(ProfitFloors::from_str("v3=20").is_err());
+    assert!(ProfitFloors::from_str("longtail").is_err());
+    assert!(ProfitFloors::from_str("longtail=five").is_err());
+}
+
+#[test]
+fn test_profit_rules() {
+    let blue_chip = Address::random();
+    let rules = ProfitRules {
+        floors: "bluechip=5,longtail=50,longtail=100".parse().unwrap(),
+        blue_chips: format!("{:?}", blue_chip).parse().unwrap(),
+    };
+    assert_eq!(rules.classify(blue_chip), OpportunityClass::BlueChip);
+    assert_eq!(
+        rules.classify(Address::random()),
+        OpportunityClass::LongTail
+    );
+
+    // The last floor for a class wins, and classes without one have none
+    assert_eq!(
+        rules.floor_usdc(OpportunityClass::LongTail),
+        Some(U256::from(100_000_000))
+    );
+    assert_eq!(
+        ProfitRules::default().floor_usdc(OpportunityClass::BlueChip),
+        None
+    );
+
+    // At 2,000 USDC per ether, $5 is 0.0025 ether
+    let weth_reserves = U256::from(1_000) * U256::exp10(18);
+    let usdc_reserves = U256::from(2_000_000) * U256::exp10(6);
+    assert_eq!(
+        rules.floor_weth(OpportunityClass::BlueChip, weth_reserves, usdc_reserves),
+        Some(U256::from(2_500_000_000_000_000u64))
+    );
+    assert_eq!(
+        ProfitRules::default().floor_weth(OpportunityClass::LongTail, weth_reserves, usdc_reserves),
+        None
+    );
+}
@@ -0,0 +1,83 @@
+use ethers::prelude::*;
+
+use subway_rs::{metrics, summary::*};
+
+#[test]
+fn test_summarize_block() {
+    let summaries = BlockSummaries::new();
+    let block = U64::from(100);
+    summaries.seen(block);
+    summaries.seen(block);
+    summaries.evaluated(block);
+    summaries.submitted(block, U256::from(30));
+    summaries.submitted(block, U256::from(20));
+    summaries.included(block);
+    summaries.realized(block, U256::from(45));
+    summaries.seen(block + 1);
+
+    assert_eq!(
+        summaries.close(block),
+        vec![BlockSummary {
+            block,
+            seen: 2,
+            evaluated: 1,
+            submitted: 2,
+            included: 1,
+            expected_value: U256::from(50),
+            realized_profit: U256::from(45),
+        }]
+    );
+
+    // The next block stays open until it's closed
+    let next = summaries.close(block + 1);
+    assert_eq!(next.len(), 1);
+    assert_eq!(next[0].seen, 1);
+}
+
+#[test]
+fn test_summarize_quiet_blocks() {
+    let summaries = BlockSummaries::new();
+    summaries.seen(U64::from(10));
+    summaries.close(U64::from(10));
+
+    summaries.included(U64::from(13));
+    let closed = summaries.close(U64::from(13));
+    let blocks = closed.iter().map(|s| s.block.as_u64()).collect::<Vec<_>>();
+    assert_eq!(blocks, vec![11, 12, 13]);
+    assert_eq!(
+        closed[0],
+        BlockSummary {
+            block: U64::from(11),
+            ..Default::default()
+        }
+    );
+    assert_eq!(closed[2].included, 1);
+
+    // Closing again, or behind, summarizes nothing
+    assert!(summaries.close(U64::from(13)).is_empty());
+    assert!(summaries.close(U64::from(12)).is_empty());
+}
+
+#[test]
+fn test_cap_summary_gap() {
+    let summaries = BlockSummaries::new();
+    summaries.close(U64::from(1));
+    let closed = summaries.close(U64::from(1_000));
+    assert_eq!(closed.len() as u64, MAX_SUMMARY_GAP);
+    assert_eq!(closed.last().unwrap().block, U64::from(1_000));
+}
+
+#[test]
+fn test_count_late_events() {
+    let summaries = BlockSummaries::new();
+    summaries.close(U64::from(5));
+    let before = metrics::global().get("summary_late_events");
+    summaries.realized(U64::from(5), U256::one());
+    assert!(metrics::global().get("summary_late_events") > before);
+
+    // Nothing late is summarized
+    summaries.seen(U64::from(6));
+    let closed = summaries.close(U64::from(6));
+    assert_eq!(closed.len(), 1);
+    assert!(closed[0].realized_profit.is_zero());
+}
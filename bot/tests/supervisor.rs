@@ -0,0 +1,99 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use subway_rs::{metrics, supervisor::*};
+
+#[test]
+fn test_heartbeat_and_health() {
+    let heartbeat = Heartbeat::default();
+    heartbeat.beat();
+    let last = heartbeat.last();
+    assert!(!heartbeat.is_stalled(Duration::from_secs(1), last + 1_000));
+    assert!(heartbeat.is_stalled(Duration::from_secs(1), last + 1_001));
+
+    let health = TaskHealth {
+        status: TaskStatus::Running,
+        restarts: 0,
+        last_beat: 10_000,
+        stall_secs: Some(5),
+        last_error: None,
+    };
+    assert!(health.is_healthy(15_000));
+    assert!(!health.is_healthy(15_001));
+    let restarting = TaskHealth {
+        status: TaskStatus::Restarting,
+        ..health.clone()
+    };
+    assert!(!restarting.is_healthy(10_000));
+    let no_stall = TaskHealth {
+        stall_secs: None,
+        ..health
+    };
+    assert!(no_stall.is_healthy(u64::MAX));
+}
+
+#[tokio::test]
+async fn test_supervise_task_restarts_failures() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let counter = runs.clone();
+    supervise_task("supervisor_test_failing", None, move |_| {
+        let run = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            if run == 0 {
+                eyre::bail!("First run fails");
+            }
+            Ok(())
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    let health = &tasks().health()["supervisor_test_failing"];
+    assert_eq!(health.status, TaskStatus::Stopped);
+    assert_eq!(health.restarts, 1);
+    assert!(health
+        .last_error
+        .as_ref()
+        .unwrap()
+        .contains("First run fails"));
+    assert_eq!(
+        metrics::global().get("task_restarts_supervisor_test_failing"),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_supervise_task_restarts_stalls() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let counter = runs.clone();
+    supervise_task(
+        "supervisor_test_stalling",
+        Some(Duration::from_millis(100)),
+        move |heartbeat| {
+            let run = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                heartbeat.beat();
+                if run == 0 {
+                    // Never beats again
+                    std::future::pending::<()>().await;
+                }
+                Ok::<_, eyre::Report>(())
+            }
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    assert_eq!(tasks().health()["supervisor_test_stalling"].restarts, 1);
+    assert_eq!(
+        metrics::global().get("task_stalls_supervisor_test_stalling"),
+        1
+    );
+}
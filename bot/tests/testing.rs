@@ -0,0 +1,97 @@
+#![cfg(feature = "testing")]
+
+use ethers::prelude::*;
+use std::time::Duration;
+
+use subway_rs::{mempool, spam::RateLimiter, testing::*, timing::BundleRelay, uniswap::SortedPair};
+
+#[tokio::test]
+async fn test_mock_tx_source_replays_once() {
+    let txs = (1..=3)
+        .map(|i| Transaction {
+            hash: H256::from_low_u64_be(i),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    let source = MockTxSource::new([txs.clone(), txs.clone()].concat());
+    let mut candidates =
+        mempool::merge_sources(vec![Box::new(source)], 16, Duration::from_secs(30));
+    for tx in &txs {
+        assert_eq!(candidates.recv().await.unwrap().tx.hash, tx.hash);
+    }
+    let more = tokio::time::timeout(Duration::from_millis(50), candidates.recv()).await;
+    assert!(more.is_err());
+}
+
+#[tokio::test]
+async fn test_mock_state_reader() {
+    let (weth, token) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+    let pair = SortedPair::new(weth, token);
+    let state = MockStateReader::new()
+        .with_reserves(pair.address().unwrap(), None, (100.into(), 200.into()))
+        .with_reserves(
+            pair.address().unwrap(),
+            Some(7.into()),
+            (1.into(), 2.into()),
+        );
+
+    assert_eq!(
+        pair.reserves_from(&state, weth, None).await.unwrap(),
+        (100.into(), 200.into())
+    );
+    assert_eq!(
+        pair.reserves_from(&state, token, Some(7.into()))
+            .await
+            .unwrap(),
+        (2.into(), 1.into())
+    );
+    assert_eq!(
+        pair.reserves_from(&state, weth, Some(8.into()))
+            .await
+            .unwrap(),
+        (100.into(), 200.into())
+    );
+    assert!(SortedPair::new(weth, Address::from_low_u64_be(3))
+        .reserves_from(&state, weth, None)
+        .await
+        .is_err());
+    assert_eq!(state.reads(), 4);
+}
+
+#[tokio::test]
+async fn test_mock_relay_records_bundles() {
+    let bundle = vec![Bytes::from(vec![1]), Bytes::from(vec![2])];
+    let relay = MockRelay::new();
+    assert!(relay.submit_bundle(&bundle, 10.into()).await.is_ok());
+
+    let rejecting = MockRelay::rejecting("bundle underpriced");
+    let error = rejecting
+        .submit_bundle(&bundle, 11.into())
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), "bundle underpriced");
+    assert_eq!(
+        rejecting.submitted(),
+        vec![SubmittedBundle {
+            signed_txs: bundle,
+            block_number: 11.into(),
+        }]
+    );
+    assert_eq!(relay.submitted().len(), 1);
+}
+
+#[test]
+fn test_frozen_clock_drives_rate_limits() {
+    let clock = FrozenClock::at(1_700_000_000_000);
+    let sender = Address::random();
+    let mut limiter = RateLimiter::new(2, Duration::from_secs(12));
+    assert!(limiter.check(sender, clock.now()));
+    assert!(limiter.check(sender, clock.now()));
+    assert!(!limiter.check(sender, clock.now()));
+
+    // Only advancing the clock opens a new window
+    clock.clone().advance(Duration::from_secs(12));
+    assert!(limiter.check(sender, clock.now()));
+    assert_eq!(clock.unix_millis(), 1_700_000_012_000);
+    assert_eq!(clock.unix_secs(), 1_700_000_012);
+}
@@ -0,0 +1,61 @@
+use std::{
+    str::FromStr,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use subway_rs::timing::*;
+
+#[test]
+fn test_relay_timings_from_str() {
+    let timings = RelayTimings::from_str("Flashbots=9000:250, bloxroute=10000").unwrap();
+    assert_eq!(
+        timings.0["flashbots"],
+        RelayTiming {
+            cutoff_ms: 9000,
+            margin_ms: 250
+        }
+    );
+    assert_eq!(timings.0["bloxroute"].margin_ms, DEFAULT_MARGIN_MS);
+    assert!(RelayTimings::from_str("").unwrap().0.is_empty());
+    assert!(RelayTimings::from_str("flashbots").is_err());
+    assert!(RelayTimings::from_str("flashbots=13000").is_err());
+}
+
+#[test]
+fn test_scheduler_delay() {
+    let scheduler = SubmissionScheduler::new(RelayTimings::from_str("flashbots=9000:500").unwrap());
+    let slot_start = 1_000;
+    let at = |offset_ms: u64| UNIX_EPOCH + Duration::from_millis(slot_start * 1000 + offset_ms);
+
+    // Held until the margin before the cutoff
+    assert_eq!(
+        scheduler.delay("flashbots", slot_start, at(2_000)),
+        Duration::from_millis(6_500)
+    );
+    // Already past the target, so sent immediately
+    assert_eq!(
+        scheduler.delay("flashbots", slot_start, at(8_600)),
+        Duration::ZERO
+    );
+    // Unscheduled relays are never held
+    assert_eq!(scheduler.delay("echo", slot_start, at(0)), Duration::ZERO);
+    assert_eq!(slot_offset_ms(slot_start, at(1_234)), 1_234);
+}
+
+#[test]
+fn test_scheduler_learns_cutoff() {
+    let mut scheduler =
+        SubmissionScheduler::new(RelayTimings::from_str("flashbots=9000:500").unwrap());
+
+    // A late inclusion raises the cutoff
+    scheduler.record("flashbots", 9_400, true);
+    assert_eq!(scheduler.timing("flashbots").unwrap().cutoff_ms, 9_400);
+
+    // A miss pulls it a quarter of the way back
+    scheduler.record("flashbots", 8_600, false);
+    assert_eq!(scheduler.timing("flashbots").unwrap().cutoff_ms, 9_200);
+
+    // Unknown relays are ignored
+    scheduler.record("echo", 1_000, true);
+    assert!(scheduler.timing("echo").is_none());
+}
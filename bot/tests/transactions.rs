@@ -0,0 +1,224 @@
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+
+use subway_rs::{executor::*, transactions::*};
+
+fn leg() -> SwapLeg {
+    SwapLeg {
+        token_in: Address::random(),
+        token_out: Address::random(),
+        pair: Address::random(),
+        amount_in: U256::from(100),
+        amount_out: U256::from(50),
+    }
+}
+
+#[test]
+fn test_tx_factory_rejects_unsupported_mode() {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let executor = ExecutorKind::Subway.build(Address::random());
+    assert!(TxFactory::new(executor, ExecutionMode::Value, wallet, 1).is_err());
+}
+
+#[test]
+fn test_tx_factory_frontrun_value() {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let fees = LegFees::base_fee_only(U256::from(10));
+    let leg = leg();
+
+    // Inventory mode sends no value
+    let executor = ExecutorKind::Multicall.build(Address::random());
+    let factory = TxFactory::new(executor, ExecutionMode::Inventory, wallet.clone(), 1).unwrap();
+    let frontrun = factory
+        .frontrun(&leg, U256::zero(), &fees, U64::zero())
+        .unwrap();
+    assert_eq!(frontrun.value(), None);
+
+    // Value mode sends the frontrun amount in
+    let executor = ExecutorKind::Multicall.build(Address::random());
+    let factory = TxFactory::new(executor, ExecutionMode::Value, wallet, 1).unwrap();
+    let frontrun = factory
+        .frontrun(&leg, U256::zero(), &fees, U64::zero())
+        .unwrap();
+    assert_eq!(frontrun.value(), Some(&leg.amount_in));
+
+    // The backrun never carries value
+    let backrun = factory
+        .backrun(&leg, U256::one(), &fees, U64::zero())
+        .unwrap();
+    assert_eq!(backrun.value(), None);
+    assert_eq!(backrun.nonce(), Some(&U256::one()));
+    assert!(matches!(backrun, TypedTransaction::Eip1559(_)));
+}
+
+#[tokio::test]
+async fn test_tx_factory_sign() {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let executor = ExecutorKind::Subway.build(Address::random());
+    let factory = TxFactory::new(executor, ExecutionMode::Inventory, wallet, 1).unwrap();
+    let fees = LegFees::base_fee_only(U256::from(10));
+    let frontrun = factory
+        .frontrun(&leg(), U256::zero(), &fees, U64::zero())
+        .unwrap();
+    let signed = factory.sign(&frontrun).await.unwrap();
+    assert!(!signed.as_ref().is_empty());
+}
+
+#[tokio::test]
+async fn test_tx_factory_multiple_wallets() {
+    let primary = LocalWallet::new(&mut rand::thread_rng());
+    let extra = LocalWallet::new(&mut rand::thread_rng());
+    let executor = ExecutorKind::Multicall.build(Address::random());
+    let factory = TxFactory::new(executor, ExecutionMode::Inventory, primary.clone(), 1)
+        .unwrap()
+        .with_wallets(vec![extra.clone()]);
+    assert_eq!(
+        factory.searchers(),
+        vec![primary.address(), extra.address()]
+    );
+
+    // Wallets are allocated round-robin, skipping unavailable ones
+    assert_eq!(factory.allocate_wallet(|_| true), Some(0));
+    assert_eq!(factory.allocate_wallet(|_| true), Some(1));
+    assert_eq!(factory.allocate_wallet(|i| i == 1), Some(1));
+    assert_eq!(factory.allocate_wallet(|_| false), None);
+
+    // Legs are sent and signed from the wallet owning the nonce
+    let fees = LegFees::base_fee_only(U256::from(10));
+    let nonce = NonceSlot {
+        wallet: 1,
+        nonce: U256::from(7),
+    };
+    let backrun = factory.backrun(&leg(), nonce, &fees, U64::zero()).unwrap();
+    assert_eq!(backrun.from(), Some(&extra.address()));
+    assert_eq!(backrun.nonce(), Some(&U256::from(7)));
+    let signed = factory.sign(&backrun).await.unwrap();
+    let signature = extra
+        .with_chain_id(1u64)
+        .sign_transaction(&backrun)
+        .await
+        .unwrap();
+    assert_eq!(signed, backrun.rlp_signed(&signature));
+
+    // Plain nonces belong to the primary wallet
+    let frontrun = factory
+        .frontrun(&leg(), U256::zero(), &fees, U64::zero())
+        .unwrap();
+    assert_eq!(frontrun.from(), Some(&primary.address()));
+}
+
+#[test]
+fn test_tx_factory_replay_guard() {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let fees = LegFees::base_fee_only(U256::from(10));
+
+    // Executors that can't check the block refuse the guard
+    let executor = ExecutorKind::Multicall.build(Address::random());
+    let factory = TxFactory::new(executor, ExecutionMode::Inventory, wallet.clone(), 1).unwrap();
+    assert!(factory.with_replay_guard(true).is_err());
+
+    // Guarded subway legs carry the target block after the payload
+    let executor = ExecutorKind::Subway.build(Address::random());
+    let factory = TxFactory::new(executor, ExecutionMode::Inventory, wallet, 1)
+        .unwrap()
+        .with_replay_guard(true)
+        .unwrap();
+    let leg = leg();
+    let block = U64::from(17_000_000);
+    for tx in [
+        factory.frontrun(&leg, U256::zero(), &fees, block).unwrap(),
+        factory.backrun(&leg, U256::one(), &fees, block).unwrap(),
+    ] {
+        let data = tx.data().unwrap().as_ref();
+        assert_eq!(data.len(), SubwayExecutor::GUARDED_PAYLOAD_LENGTH);
+        assert_eq!(
+            &data[..SubwayExecutor::PAYLOAD_LENGTH],
+            SubwayExecutor::encode_leg(&leg).unwrap().as_ref()
+        );
+        assert_eq!(
+            &data[SubwayExecutor::PAYLOAD_LENGTH..],
+            &17_000_000u64.to_be_bytes()
+        );
+    }
+}
+
+#[test]
+fn test_tx_factory_abort_guard() {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let fees = LegFees::base_fee_only(U256::from(10));
+    let subway = || ExecutorKind::Subway.build(Address::random());
+
+    // The minimum balance is packed after the target block, so legs must be replay guarded
+    let factory = TxFactory::new(subway(), ExecutionMode::Inventory, wallet.clone(), 1).unwrap();
+    assert!(factory.with_abort_guard(Some(500)).is_err());
+    let executor = ExecutorKind::Multicall.build(Address::random());
+    let factory = TxFactory::new(executor, ExecutionMode::Inventory, wallet.clone(), 1).unwrap();
+    assert!(factory.with_abort_guard(Some(500)).is_err());
+
+    // Unchecked backruns are plain backruns
+    let leg = leg();
+    let block = U64::from(17_000_000);
+    let factory = TxFactory::new(subway(), ExecutionMode::Inventory, wallet, 1)
+        .unwrap()
+        .with_replay_guard(true)
+        .unwrap();
+    let backrun = factory
+        .backrun_checked(&leg, U256::from(7), U256::one(), &fees, block)
+        .unwrap();
+    assert_eq!(
+        backrun.data().unwrap().len(),
+        SubwayExecutor::GUARDED_PAYLOAD_LENGTH
+    );
+
+    // Checked backruns require the held balance plus the tolerated frontrun output
+    let factory = factory.with_abort_guard(Some(500)).unwrap();
+    let backrun = factory
+        .backrun_checked(&leg, U256::from(7), U256::one(), &fees, block)
+        .unwrap();
+    let data = backrun.data().unwrap().as_ref();
+    assert_eq!(data.len(), SubwayExecutor::CHECKED_PAYLOAD_LENGTH);
+    assert_eq!(
+        &data[SubwayExecutor::PAYLOAD_LENGTH..SubwayExecutor::GUARDED_PAYLOAD_LENGTH],
+        &17_000_000u64.to_be_bytes()
+    );
+    assert_eq!(
+        &data[SubwayExecutor::GUARDED_PAYLOAD_LENGTH..],
+        &(7u128 + 95).to_be_bytes()
+    );
+}
+
+#[test]
+fn test_min_backrun_balance() {
+    assert_eq!(
+        min_backrun_balance(U256::zero(), U256::from(1_000), 0),
+        U256::from(1_000)
+    );
+    assert_eq!(
+        min_backrun_balance(U256::from(10), U256::from(1_000), 250),
+        U256::from(985)
+    );
+    assert_eq!(
+        min_backrun_balance(U256::from(10), U256::from(1_000), 10_000),
+        U256::from(10)
+    );
+}
+
+#[test]
+fn test_tx_factory_capabilities() {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let executor = ExecutorKind::Subway.build(Address::random());
+    let factory = TxFactory::new(executor, ExecutionMode::Inventory, wallet, 1).unwrap();
+    assert_eq!(factory.capabilities(), vec![PayloadCapability::Legs]);
+    let factory = factory
+        .with_replay_guard(true)
+        .unwrap()
+        .with_abort_guard(Some(100))
+        .unwrap();
+    assert_eq!(
+        factory.capabilities(),
+        vec![
+            PayloadCapability::Legs,
+            PayloadCapability::ReplayGuard,
+            PayloadCapability::BalanceGuard
+        ]
+    );
+}
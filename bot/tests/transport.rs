@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use subway_rs::{metrics, transport::*};
+
+#[test]
+fn test_build_relay_http_client() {
+    assert!(relay_http_client(&RelayHttpConfig::default()).is_ok());
+    assert!(relay_http_client(&RelayHttpConfig {
+        http2: true,
+        pool_idle_secs: 0,
+        keepalive_secs: 0,
+        timeout_ms: 0,
+    })
+    .is_ok());
+}
+
+#[test]
+fn test_record_round_trip() {
+    let relay = "transport_test_relay";
+    record_round_trip(relay, Duration::from_millis(120));
+    record_round_trip(relay, Duration::from_millis(80));
+
+    assert_eq!(metrics::global().get(&format!("relay_rtt_ms_{relay}")), 200);
+    assert_eq!(metrics::global().get(&format!("relay_requests_{relay}")), 2);
+    assert_eq!(
+        metrics::global()
+            .gauges()
+            .get(&format!("relay_last_rtt_ms_{relay}"))
+            .copied(),
+        Some(80)
+    );
+}
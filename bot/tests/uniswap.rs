@@ -1,9 +1,9 @@
 #![allow(unused)]
 
 use ethers::prelude::*;
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
-use subway_rs::uniswap::*;
+use subway_rs::{state::ProviderReader, uniswap::*, utils::get_http_provider};
 
 #[test]
 fn test_get_univ2_router_address() {
@@ -151,7 +151,8 @@ async fn test_get_univ2_exact_weth_token_min_recv() {
     ];
 
     // Get the exact weth token min recv
-    let min_recv = get_univ2_exact_weth_token_min_recv(&final_min_recv, &path)
+    let state = ProviderReader::new(Arc::new(get_http_provider().unwrap()));
+    let min_recv = get_univ2_exact_weth_token_min_recv(&state, &final_min_recv, &path, None)
         .await
         .unwrap();
     assert_eq!(min_recv, U256::from(100_000u64));
@@ -182,8 +183,92 @@ async fn test_get_univ2_exact_weth_token_min_recv_multiple_tokens() {
     assert_eq!(a_in, U256::from_dec_str("469768901").unwrap());
 
     // Get the exact weth token min recv
-    let min_recv = get_univ2_exact_weth_token_min_recv(&final_min_recv, &path)
+    let state = ProviderReader::new(Arc::new(get_http_provider().unwrap()));
+    let min_recv = get_univ2_exact_weth_token_min_recv(&state, &final_min_recv, &path, None)
         .await
         .unwrap();
     assert!(min_recv > U256::from_dec_str("100000000").unwrap());
 }
+
+#[test]
+fn test_sorted_pair_orients_reserves() {
+    let (low, high) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+    let pair = SortedPair::new(high, low);
+    assert_eq!((pair.token0, pair.token1), (low, high));
+    assert_eq!(pair, SortedPair::new(low, high));
+    assert_eq!(pair.other(high), Some(low));
+    assert_eq!(pair.other(Address::zero()), None);
+    assert_eq!(
+        pair.address().unwrap(),
+        calculate_uniswap_v2_pair_address(&high, &low).unwrap()
+    );
+
+    let reserves = (U256::from(100), U256::from(200));
+    assert_eq!(
+        pair.orient(low, reserves),
+        (U256::from(100), U256::from(200))
+    );
+    assert_eq!(
+        pair.orient(high, reserves),
+        (U256::from(200), U256::from(100))
+    );
+}
+
+#[test]
+fn test_get_amount_out_fee_bps() {
+    let (amount_in, reserve_in, reserve_out) = (
+        U256::from(100000u64),
+        U256::from(10000000000u64),
+        U256::from(10000000000u64),
+    );
+
+    // The Uniswap V2 fee matches the classic 997 / 1000 math
+    let (b_out, _, _) = get_univ2_data_given_in(&amount_in, &reserve_in, &reserve_out);
+    assert_eq!(
+        get_amount_out(amount_in, reserve_in, reserve_out, UNIV2_FEE_BPS),
+        b_out
+    );
+
+    // A cheaper fork pays out more
+    let pancake = SortedPair::new(Address::from_low_u64_be(1), Address::from_low_u64_be(2))
+        .with_fee_bps(PANCAKESWAP_V2_FEE_BPS);
+    assert_eq!(
+        pancake.amount_out(amount_in, reserve_in, reserve_out),
+        U256::from(99749u64)
+    );
+
+    // The input needed for that output is no more than what was swapped
+    assert!(
+        get_amount_in(
+            U256::from(99749u64),
+            reserve_in,
+            reserve_out,
+            PANCAKESWAP_V2_FEE_BPS
+        ) <= amount_in
+    );
+}
+
+#[test]
+fn test_verify_quote() {
+    let amounts = |v: &[u64]| v.iter().map(|a| U256::from(*a)).collect::<Vec<_>>();
+
+    assert!(verify_quote(&amounts(&[100, 90, 80]), &amounts(&[100, 90, 80])).is_ok());
+    // Rounding drift compounds along the path
+    assert!(verify_quote(&amounts(&[100, 90, 80]), &amounts(&[100, 89, 82])).is_ok());
+
+    assert_eq!(
+        verify_quote(&amounts(&[100, 90, 80]), &amounts(&[100, 88, 80])),
+        Err(QuoteMismatch::Amount {
+            index: 1,
+            computed: U256::from(90),
+            quoted: U256::from(88),
+        })
+    );
+    assert_eq!(
+        verify_quote(&amounts(&[100, 90]), &amounts(&[100, 90, 80])),
+        Err(QuoteMismatch::Length {
+            computed: 2,
+            quoted: 3,
+        })
+    );
+}
@@ -0,0 +1,24 @@
+use ethers::prelude::*;
+use std::str::FromStr;
+
+use subway_rs::uniswap_v3::*;
+
+fn weth() -> Address {
+    Address::from_str("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap()
+}
+
+fn usdc() -> Address {
+    Address::from_str("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap()
+}
+
+#[test]
+fn test_get_pool() {
+    assert_eq!(
+        get_pool(weth(), usdc(), 3_000),
+        Address::from_str("0x8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8").unwrap()
+    );
+    assert_eq!(
+        get_pool(usdc(), weth(), 500),
+        Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap()
+    );
+}
@@ -85,3 +85,11 @@ fn test_calculate_next_block_base_fee_zero_gas_limit() {
     assert!(new_base_fee >= U256::from(1336));
     assert!(new_base_fee < U256::from(1346));
 }
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"secret", b"secret"));
+    assert!(!constant_time_eq(b"secret", b"secreT"));
+    assert!(!constant_time_eq(b"secret", b"secrets"));
+    assert!(!constant_time_eq(b"", b"secret"));
+}
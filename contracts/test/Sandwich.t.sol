@@ -15,6 +15,13 @@ import {
 interface ISandwich {
     function recoverERC20(address token) external;
     function recoverETH() external;
+    function withdrawERC20(address token, address to, uint256 amount) external;
+    function pause() external;
+    function unpause() external;
+    function transferOwnership(address newOwner) external;
+    function owner() external view returns (address);
+    function paused() external view returns (bool);
+    function payloadVersion() external view returns (uint256);
 }
 
 contract SandwichTest is Test {
@@ -32,6 +39,9 @@ contract SandwichTest is Test {
     // The Sandwich User
     address constant USER = address(0xBEEFBABE);
 
+    // The first byte of every sandwich payload
+    bytes1 constant SANDWICH_TAG = 0x00;
+
     /// @notice Set up the testing suite
     function setUp() public {
         // Deposit ether into WETH Contract
@@ -110,6 +120,27 @@ contract SandwichTest is Test {
         assertEq(weth.balanceOf(USER), 1e18 - 1);
     }
 
+    function testWithdrawERC20(address non_user) public {
+        vm.assume(non_user != USER);
+        address cold = address(0xC01D);
+
+        // non-users can't withdraw
+        vm.prank(non_user);
+        vm.expectRevert();
+        sandwich.withdrawERC20(address(weth), non_user, 1e17);
+
+        // The user can send part of the balance anywhere
+        vm.startPrank(USER);
+        sandwich.withdrawERC20(address(weth), cold, 3e17);
+        assertEq(weth.balanceOf(cold), 3e17);
+        assertEq(weth.balanceOf(address(sandwich)), 7e17);
+
+        // More than the balance reverts
+        vm.expectRevert();
+        sandwich.withdrawERC20(address(weth), cold, 1e18);
+        vm.stopPrank();
+    }
+
     function testOnlyUserCanCallFallback(address caller, bytes memory some) public {
         vm.assume(caller != USER);
         vm.assume(keccak256(some) != keccak256(bytes("")));
@@ -137,6 +168,125 @@ contract SandwichTest is Test {
         vm.stopPrank();
     }
 
+    function testGuardedSandwichOnlyExecutesInTargetBlock() public {
+        bytes memory payload = abi.encodePacked(getSandwichPayload(), uint64(block.number + 1));
+        vm.startPrank(USER);
+
+        // A leg replayed outside its target block reverts
+        (bool s, ) = address(sandwich).call(payload);
+        assertFalse(s);
+
+        vm.roll(block.number + 1);
+        (s, ) = address(sandwich).call(payload);
+        assertTrue(s);
+        vm.stopPrank();
+    }
+
+    function testCheckedSandwichRevertsBelowMinBalance() public {
+        // The setup function transfers 1e18 weth to the sandwich contract
+        bytes memory guarded = abi.encodePacked(getSandwichPayload(), uint64(block.number));
+        vm.startPrank(USER);
+
+        // A leg reverts when the contract holds less of the token than the minimum
+        (bool s, ) = address(sandwich).call(abi.encodePacked(guarded, uint128(1e18 + 1)));
+        assertFalse(s);
+
+        (s, ) = address(sandwich).call(abi.encodePacked(guarded, uint128(1e18)));
+        assertTrue(s);
+        vm.stopPrank();
+    }
+
+    function testPayloadVersion(address caller) public {
+        // Anyone can read the payload version
+        vm.prank(caller);
+        assertEq(sandwich.payloadVersion(), 4);
+    }
+
+    function testTokenPrefixCantHijackDispatch() public {
+        bytes4[9] memory selectors = [
+            ISandwich.recoverERC20.selector,
+            ISandwich.recoverETH.selector,
+            ISandwich.withdrawERC20.selector,
+            ISandwich.pause.selector,
+            ISandwich.unpause.selector,
+            ISandwich.transferOwnership.selector,
+            ISandwich.owner.selector,
+            ISandwich.paused.selector,
+            ISandwich.payloadVersion.selector
+        ];
+        address pair = address(0xFA1);
+        vm.startPrank(USER);
+        for (uint256 i = 0; i < selectors.length; i++) {
+            assertTrue(bytes1(selectors[i]) != SANDWICH_TAG);
+
+            // A token whose address starts with the selector is still swapped
+            address token = address(uint160(bytes20(selectors[i])) | 0xBEEF);
+            vm.expectCall(token, abi.encodeCall(IERC20.transfer, (pair, 100)));
+            vm.expectCall(
+                pair,
+                abi.encodeWithSelector(IUniswapV2Pair.swap.selector, 50, 0, address(sandwich))
+            );
+            (bool s, ) = address(sandwich).call(
+                abi.encodePacked(SANDWICH_TAG, token, pair, uint128(100), uint128(50), uint8(0))
+            );
+            assertTrue(s);
+        }
+        vm.stopPrank();
+        assertFalse(sandwich.paused());
+        assertEq(sandwich.owner(), USER);
+    }
+
+    function testPauseBlocksSandwiches(address non_user) public {
+        vm.assume(non_user != USER);
+        assertFalse(sandwich.paused());
+
+        // Only the owner can pause
+        vm.prank(non_user);
+        vm.expectRevert();
+        sandwich.pause();
+
+        vm.startPrank(USER);
+        sandwich.pause();
+        assertTrue(sandwich.paused());
+        assertEq(sandwich.owner(), USER);
+
+        // Sandwiches revert while paused, admin functions still work
+        (bool s, ) = address(sandwich).call(getSandwichPayload());
+        assertFalse(s);
+        sandwich.recoverERC20(address(weth));
+
+        sandwich.unpause();
+        assertFalse(sandwich.paused());
+        vm.stopPrank();
+    }
+
+    function testTransferOwnership(address newOwner) public {
+        vm.assume(newOwner != USER && newOwner != address(0));
+
+        // Only the owner can transfer, and never to the zero address
+        vm.prank(newOwner);
+        vm.expectRevert();
+        sandwich.transferOwnership(newOwner);
+        vm.startPrank(USER);
+        vm.expectRevert();
+        sandwich.transferOwnership(address(0));
+
+        // The pause flag survives the transfer
+        sandwich.pause();
+        sandwich.transferOwnership(newOwner);
+        vm.stopPrank();
+        assertEq(sandwich.owner(), newOwner);
+        assertTrue(sandwich.paused());
+
+        // The previous owner is locked out
+        vm.prank(USER);
+        vm.expectRevert();
+        sandwich.unpause();
+        vm.prank(newOwner);
+        sandwich.unpause();
+        assertFalse(sandwich.paused());
+    }
+
     // Helper methods
 
     /// @notice Constructs a sandwich payload
@@ -151,6 +301,7 @@ contract SandwichTest is Test {
         uint8 tokenOutNo = address(usdc) < address(weth) ? 0 : 1;
 
         payload = abi.encodePacked(
+            SANDWICH_TAG,
             address(weth), // token we're giving
             address(wethUsdcPair), // univ2 pair
             uint128(amountIn), // amountIn